@@ -1,11 +1,15 @@
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use zero_mysql::protocol::BinaryRowPayload;
 use zero_mysql::protocol::command::ColumnDefinition;
+use zero_mysql::protocol::command::prepared::write_execute;
 use zero_mysql::protocol::response::OkPayloadBytes;
 use zero_mysql::protocol::r#trait::BinaryResultSetHandler;
+use zero_mysql::protocol::r#trait::param::Params;
 use zero_mysql::raw::parse_value;
+use zero_mysql::statement_buffer::StatementBuffer;
 use zero_mysql::sync::Conn;
 use zero_mysql::value::Value;
+use zero_mysql::{FlushPolicy, Opts};
 
 pub struct User {
     pub id: i32,
@@ -27,6 +31,10 @@ pub struct Comment {
 }
 
 fn connection() -> zero_mysql::error::Result<Conn> {
+    connection_with(|_| {})
+}
+
+fn connection_with(configure: impl FnOnce(&mut Opts)) -> zero_mysql::error::Result<Conn> {
     let connection_url = std::env::var("MYSQL_DATABASE_URL")
         .or_else(|_| std::env::var("DATABASE_URL"))
         .map_err(|_unhelpful_err| {
@@ -34,7 +42,9 @@ fn connection() -> zero_mysql::error::Result<Conn> {
                 "DATABASE_URL must be set in order to run benchmarks".into(),
             )
         })?;
-    let mut conn = Conn::new(connection_url.as_str())?;
+    let mut opts = Opts::try_from(connection_url.as_str())?;
+    configure(&mut opts);
+    let mut conn = Conn::new(opts)?;
 
     conn.query_drop("SET FOREIGN_KEY_CHECKS = 0;")?;
     conn.query_drop("TRUNCATE TABLE comments")?;
@@ -306,10 +316,107 @@ fn bench_insert(c: &mut Criterion) {
     group.finish();
 }
 
+/// Point queries with and without `TCP_QUICKACK`
+fn bench_quickack_point_query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("quickack_point_query");
+
+    for quickack in [false, true] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(quickack),
+            &quickack,
+            |b, &quickack| {
+                let Ok(mut conn) = connection_with(|opts| opts.tcp_quickack = quickack) else {
+                    return;
+                };
+                let Ok(()) = insert_users(100, &mut conn, |_| None) else {
+                    return;
+                };
+                let Ok(mut stmt) =
+                    conn.prepare("SELECT id, name, hair_color FROM users WHERE id = ?")
+                else {
+                    return;
+                };
+
+                let mut handler = UsersHandler::new();
+                b.iter(|| {
+                    let _result = conn.exec(&mut stmt, (50,), &mut handler);
+                    std::mem::take(&mut handler.users)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Pipelined inserts with one write per command vs. one write per window
+fn bench_flush_policy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flush_policy");
+
+    for (name, policy) in [
+        ("per_command", FlushPolicy::PerCommand),
+        ("coalesce", FlushPolicy::Coalesce),
+    ] {
+        for size in [10, 100] {
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, &size| {
+                let Ok(mut conn) = connection_with(|opts| opts.flush_policy = policy) else {
+                    return;
+                };
+
+                b.iter(|| {
+                    let mut buffer = StatementBuffer::new();
+                    for x in 0..size {
+                        let _result = buffer.push(
+                            "INSERT INTO users (name, hair_color) VALUES (?, ?)",
+                            (format!("User {x}"), None::<&str>),
+                        );
+                    }
+                    conn.flush_statements(&mut buffer)
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// COM_STMT_EXECUTE encoding with a single reservation vs. growing the buffer per value.
+/// Does not need a server.
+fn bench_encode_execute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_execute");
+
+    for size in [10, 100, 1000].iter() {
+        let params: Vec<String> = (0..*size)
+            .map(|i| format!("user-{i}-{}", "x".repeat(i % 300)))
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("reserved", size), &params, |b, params| {
+            b.iter(|| {
+                let mut out = Vec::new();
+                let _result = write_execute(&mut out, 1, params);
+                out
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("growing", size), &params, |b, params| {
+            b.iter(|| {
+                let mut out = Vec::new();
+                out.extend_from_slice(&[0x17, 1, 0, 0, 0, 0, 1, 0, 0, 0]);
+                params.encode_null_bitmap(&mut out);
+                out.push(0x01);
+                params.encode_types(&mut out);
+                let _result = params.encode_values(&mut out);
+                out
+            })
+        });
+    }
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_trivial_query_by_id,
     bench_medium_complex_query_by_id,
-    bench_insert
+    bench_insert,
+    bench_quickack_point_query,
+    bench_flush_policy,
+    bench_encode_execute
 );
 criterion_main!(benches);
@@ -0,0 +1,176 @@
+//! Heap allocation accounting per command.
+//!
+//! With the `alloc-stats` feature enabled and [`CountingAllocator`] installed as the global
+//! allocator, every connection counts the heap allocations made while it executes a command,
+//! including the allocations of the result set handler.
+//! This makes it possible to check that a workload is really zero-copy and to catch
+//! allocation regressions in benchmarks.
+//!
+//! ```ignore
+//! use zero_mysql::alloc_stats::CountingAllocator;
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator::new(std::alloc::System);
+//!
+//! conn.query_drop("DO 1")?;
+//! println!("{:?}", conn.last_alloc_stats());
+//! ```
+//!
+//! Allocations are counted per thread and attributed to a command on every poll,
+//! so async commands are measured correctly even if the task moves between threads
+//! or other tasks run on the same thread in between.
+//!
+//! Without the feature or the allocator, all counts are zero.
+
+use std::future::Future;
+use std::ops::{Add, AddAssign, Sub};
+
+/// Heap allocations made by one or more commands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Number of `alloc` and `realloc` calls
+    pub allocations: u64,
+    /// Number of bytes requested by `alloc` and `realloc` calls
+    pub bytes: u64,
+}
+
+impl AllocStats {
+    /// Allocations made by the current thread so far.
+    #[inline]
+    pub fn current() -> Self {
+        #[cfg(feature = "alloc-stats")]
+        return counting::current();
+        #[cfg(not(feature = "alloc-stats"))]
+        Self::default()
+    }
+}
+
+impl Add for AllocStats {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            allocations: self.allocations.wrapping_add(rhs.allocations),
+            bytes: self.bytes.wrapping_add(rhs.bytes),
+        }
+    }
+}
+
+impl AddAssign for AllocStats {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for AllocStats {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            allocations: self.allocations.wrapping_sub(rhs.allocations),
+            bytes: self.bytes.wrapping_sub(rhs.bytes),
+        }
+    }
+}
+
+/// Run `f` and count the allocations it makes on the current thread.
+#[inline]
+pub fn count<T>(f: impl FnOnce() -> T) -> (T, AllocStats) {
+    let start = AllocStats::current();
+    let output = f();
+    (output, AllocStats::current() - start)
+}
+
+/// Await `fut` and count the allocations it makes while it is polled.
+pub async fn count_async<F: Future>(fut: F) -> (F::Output, AllocStats) {
+    let mut fut = std::pin::pin!(fut);
+    let mut stats = AllocStats::default();
+    let output = std::future::poll_fn(|cx| {
+        let start = AllocStats::current();
+        let poll = fut.as_mut().poll(cx);
+        stats += AllocStats::current() - start;
+        poll
+    })
+    .await;
+    (output, stats)
+}
+
+/// Allocation counters of a connection.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ConnAllocStats {
+    pub last: AllocStats,
+    pub total: AllocStats,
+}
+
+impl ConnAllocStats {
+    #[inline]
+    pub fn record(&mut self, stats: AllocStats) {
+        self.last = stats;
+        self.total += stats;
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+pub use counting::CountingAllocator;
+
+#[cfg(feature = "alloc-stats")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout};
+    use std::cell::Cell;
+
+    use super::AllocStats;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+        static BYTES: Cell<u64> = const { Cell::new(0) };
+    }
+
+    pub(super) fn current() -> AllocStats {
+        AllocStats {
+            allocations: ALLOCATIONS.try_with(Cell::get).unwrap_or(0),
+            bytes: BYTES.try_with(Cell::get).unwrap_or(0),
+        }
+    }
+
+    #[inline]
+    fn count(size: usize) {
+        let _ = ALLOCATIONS.try_with(|c| c.set(c.get().wrapping_add(1)));
+        let _ = BYTES.try_with(|c| c.set(c.get().wrapping_add(size as u64)));
+    }
+
+    /// A global allocator that counts the allocations of each thread and forwards them to `A`.
+    #[derive(Debug, Default)]
+    pub struct CountingAllocator<A>(A);
+
+    impl<A> CountingAllocator<A> {
+        pub const fn new(allocator: A) -> Self {
+            Self(allocator)
+        }
+    }
+
+    // SAFETY: every call is forwarded to `A` unchanged.
+    unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            // SAFETY: forwarded with the caller's guarantees.
+            unsafe { self.0.alloc(layout) }
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            // SAFETY: forwarded with the caller's guarantees.
+            unsafe { self.0.alloc_zeroed(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            // SAFETY: forwarded with the caller's guarantees.
+            unsafe { self.0.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count(new_size);
+            // SAFETY: forwarded with the caller's guarantees.
+            unsafe { self.0.realloc(ptr, layout, new_size) }
+        }
+    }
+}
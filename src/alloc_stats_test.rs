@@ -0,0 +1,51 @@
+use std::future::Future;
+use std::hint::black_box;
+use std::task::{Context, Poll, Waker};
+
+use crate::alloc_stats::{AllocStats, CountingAllocator, count, count_async};
+use crate::test_macros::{check, check_eq};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator<std::alloc::System> =
+    CountingAllocator::new(std::alloc::System);
+
+#[test]
+fn count_allocations() -> crate::error::Result<()> {
+    let (buf, stats) = count(|| black_box(vec![0_u8; 100]));
+    check_eq!(buf.len(), 100);
+    check_eq!(stats.allocations, 1);
+    check_eq!(stats.bytes, 100);
+
+    let ((), none) = count(|| {
+        black_box(1 + 1);
+    });
+    check_eq!(none, AllocStats::default());
+    Ok(())
+}
+
+#[test]
+fn count_across_polls() -> crate::error::Result<()> {
+    let mut yielded = false;
+    let fut = count_async(std::future::poll_fn(|cx| {
+        black_box(vec![0_u8; 10]);
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }));
+    let mut fut = std::pin::pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+    let stats = loop {
+        // Allocations between polls are not counted
+        black_box(vec![0_u8; 1000]);
+        if let Poll::Ready(((), stats)) = fut.as_mut().poll(&mut cx) {
+            break stats;
+        }
+    };
+    check_eq!(stats.allocations, 2);
+    check_eq!(stats.bytes, 20);
+    check!(AllocStats::current().bytes >= 2000);
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Per-connection scratch arena for short-lived owned values.
+//!
+//! Borrowing `&str` and `&[u8]` from the row is free, but a handler that needs to build an
+//! owned value (lowercase a string, join two columns, unescape JSON) allocates for every row.
+//! With [`Opts::scratch_arena`](crate::Opts::scratch_arena) enabled, the connection's `BufferSet`
+//! owns a bump arena and binary rows are passed to
+//! [`BinaryResultSetHandler::row_with_arena`](crate::protocol::r#trait::BinaryResultSetHandler::row_with_arena).
+//! [`ArenaString`] and [`ArenaVec`] allocate into it, and the whole arena is reset when the next
+//! result set starts, so the allocator is only hit when the arena grows.
+//!
+//! ```ignore
+//! fn row_with_arena(
+//!     &mut self,
+//!     cols: &[ColumnDefinition<'_>],
+//!     row: BinaryRowPayload<'_>,
+//!     arena: &ScratchArena,
+//! ) -> Result<()> {
+//!     let (name, _): (&str, _) = parse_value(&cols[0].tail, false, row.values())?;
+//!     let mut key = ArenaString::from_str_in(name, arena.bump());
+//!     key.make_ascii_lowercase();
+//!     self.seen.insert(hash(&key));
+//!     Ok(())
+//! }
+//! ```
+
+use bumpalo::Bump;
+use simdutf8::basic::from_utf8;
+
+use crate::error::{Error, Result};
+
+/// A `String` allocated in a [`ScratchArena`]
+pub type ArenaString<'arena> = bumpalo::collections::String<'arena>;
+
+/// A `Vec` allocated in a [`ScratchArena`]
+pub type ArenaVec<'arena, T> = bumpalo::collections::Vec<'arena, T>;
+
+/// A bump arena that is reset at the start of every result set.
+#[derive(Debug, Default)]
+pub struct ScratchArena {
+    bump: Bump,
+}
+
+impl ScratchArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The underlying allocator, for `ArenaString::new_in` and friends.
+    pub fn bump(&self) -> &Bump {
+        &self.bump
+    }
+
+    /// Copy UTF-8 bytes (e.g. a `STRING` value) into an [`ArenaString`].
+    pub fn alloc_str(&self, bytes: &[u8]) -> Result<ArenaString<'_>> {
+        let s = from_utf8(bytes).map_err(|e| {
+            Error::BadUsageError(format!(
+                "Cannot decode MySQL type STRING to ArenaString: {}",
+                e
+            ))
+        })?;
+        Ok(ArenaString::from_str_in(s, &self.bump))
+    }
+
+    /// Copy bytes into an [`ArenaVec`].
+    pub fn alloc_bytes(&self, bytes: &[u8]) -> ArenaVec<'_, u8> {
+        let mut vec = ArenaVec::with_capacity_in(bytes.len(), &self.bump);
+        vec.extend_from_slice(bytes);
+        vec
+    }
+
+    /// Bytes held by the arena, including unused capacity.
+    pub fn allocated_bytes(&self) -> usize {
+        self.bump.allocated_bytes()
+    }
+
+    /// Free all values and keep the largest chunk for reuse.
+    pub fn reset(&mut self) {
+        self.bump.reset();
+    }
+}
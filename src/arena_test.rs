@@ -0,0 +1,58 @@
+use crate::BufferSet;
+use crate::arena::{ArenaString, ArenaVec, ScratchArena};
+use crate::test_macros::{check, check_eq, check_err};
+
+#[test]
+fn alloc_str_and_bytes() -> crate::error::Result<()> {
+    let arena = ScratchArena::new();
+    let mut name = arena.alloc_str(b"Alice")?;
+    name.make_ascii_lowercase();
+    check_eq!(name.as_str(), "alice");
+
+    let bytes = arena.alloc_bytes(&[1, 2, 3]);
+    check_eq!(&bytes[..], &[1, 2, 3]);
+
+    let mut joined = ArenaString::new_in(arena.bump());
+    joined.push_str(&name);
+    joined.push('@');
+    check_eq!(joined.as_str(), "alice@");
+
+    let mut ids = ArenaVec::new_in(arena.bump());
+    ids.extend_from_slice(&[7_u32, 8]);
+    check_eq!(ids.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn alloc_str_rejects_invalid_utf8() -> crate::error::Result<()> {
+    let arena = ScratchArena::new();
+    check_err!(arena.alloc_str(&[0xff, 0xfe]));
+    Ok(())
+}
+
+#[test]
+fn reset_reuses_memory() -> crate::error::Result<()> {
+    let mut arena = ScratchArena::new();
+    for _ in 0..100 {
+        let _ = arena.alloc_bytes(&[0; 64]);
+    }
+    let allocated = arena.allocated_bytes();
+    arena.reset();
+    for _ in 0..100 {
+        let _ = arena.alloc_bytes(&[0; 64]);
+    }
+    check!(arena.allocated_bytes() <= allocated);
+    Ok(())
+}
+
+#[test]
+fn buffer_set_scratch_arena() -> crate::error::Result<()> {
+    let mut buffer_set = BufferSet::new();
+    check!(buffer_set.arena.is_none());
+    buffer_set.set_scratch_arena(true);
+    check!(buffer_set.arena.is_some());
+    buffer_set.reset_arena();
+    buffer_set.set_scratch_arena(false);
+    check!(buffer_set.arena.is_none());
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Audit hook for compliance logging.
+//!
+//! Set [`Opts::audit`](crate::Opts::audit) to receive an [`AuditEvent`] after every
+//! `query`, `prepare` and `exec` call on a connection. Unlike the
+//! [statement log](crate::statement_log), the event carries who ran the statement and
+//! what came of it, but never the SQL text.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::statement_log::{StatementKind, fingerprint};
+
+pub use crate::classify::StatementClass;
+
+/// An audited command.
+#[derive(Debug)]
+pub struct AuditEvent<'a> {
+    /// The user the connection is authenticated as.
+    pub user: &'a str,
+    pub connection_id: u64,
+    pub kind: StatementKind,
+    pub class: StatementClass,
+    /// See [`fingerprint`]. `None` if the SQL text is unknown.
+    pub fingerprint: Option<u64>,
+    /// The server-side id of the prepared statement.
+    pub statement_id: Option<u32>,
+    /// Summed over all result sets. Always 0 for failed commands and `prepare`.
+    pub rows_affected: u64,
+    /// The server error code, if the server rejected the command.
+    pub error_code: Option<u16>,
+    pub error: Option<&'a Error>,
+}
+
+/// Receives an event for every audited command.
+///
+/// Implemented for closures taking `&AuditEvent`.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &AuditEvent<'_>);
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(&AuditEvent<'_>) + Send + Sync,
+{
+    fn record(&self, event: &AuditEvent<'_>) {
+        self(event)
+    }
+}
+
+/// A shared [`AuditSink`].
+///
+/// ```
+/// use zero_mysql::Opts;
+/// use zero_mysql::audit::{AuditEvent, AuditLog};
+///
+/// let mut opts = Opts::default();
+/// opts.audit = Some(AuditLog::new(|event: &AuditEvent<'_>| {
+///     eprintln!(
+///         "{}@conn#{} {:?} rows={} error={:?}",
+///         event.user, event.connection_id, event.class, event.rows_affected, event.error_code
+///     );
+/// }));
+/// ```
+#[derive(Clone)]
+pub struct AuditLog {
+    sink: Arc<dyn AuditSink>,
+}
+
+impl AuditLog {
+    pub fn new(sink: impl AuditSink + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+        }
+    }
+
+    #[expect(clippy::too_many_arguments)]
+    pub(crate) fn record(
+        &self,
+        user: &str,
+        connection_id: u64,
+        kind: StatementKind,
+        class: StatementClass,
+        sql: Option<&str>,
+        statement_id: Option<u32>,
+        rows_affected: u64,
+        error: Option<&Error>,
+    ) {
+        let error_code = match error {
+            Some(Error::ServerError(err)) => Some(err.error_code),
+            _ => None,
+        };
+        self.sink.record(&AuditEvent {
+            user,
+            connection_id,
+            kind,
+            class,
+            fingerprint: sql.map(fingerprint),
+            statement_id,
+            rows_affected: if error.is_some() || kind == StatementKind::Prepare {
+                0
+            } else {
+                rows_affected
+            },
+            error_code,
+            error,
+        });
+    }
+}
+
+impl fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditLog").finish_non_exhaustive()
+    }
+}
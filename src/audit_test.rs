@@ -0,0 +1,97 @@
+use std::sync::{Arc, Mutex};
+
+use crate::audit::{AuditEvent, AuditLog, StatementClass};
+use crate::error::Error;
+use crate::protocol::response::ErrPayload;
+use crate::statement_log::{StatementKind, fingerprint};
+use crate::test_macros::check_eq;
+
+#[test]
+fn record_reports_outcome() -> crate::error::Result<()> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let audit = AuditLog::new(move |event: &AuditEvent<'_>| {
+        if let Ok(mut sink) = sink.lock() {
+            sink.push((
+                event.user.to_string(),
+                event.kind,
+                event.class,
+                event.fingerprint,
+                event.rows_affected,
+                event.error_code,
+            ));
+        }
+    });
+
+    let sql = "UPDATE t SET x = 1";
+    audit.record(
+        "app",
+        7,
+        StatementKind::Query,
+        StatementClass::Dml,
+        Some(sql),
+        None,
+        3,
+        None,
+    );
+    audit.record(
+        "app",
+        7,
+        StatementKind::Prepare,
+        StatementClass::Dml,
+        Some(sql),
+        Some(1),
+        3,
+        None,
+    );
+    let denied = Error::ServerError(ErrPayload {
+        error_code: 1142,
+        sql_state: "42000".to_string(),
+        message: "UPDATE command denied".to_string(),
+    });
+    audit.record(
+        "app",
+        7,
+        StatementKind::Exec,
+        StatementClass::Unknown,
+        None,
+        Some(1),
+        3,
+        Some(&denied),
+    );
+
+    let recorded = events.lock().map_err(|_poisoned| {
+        crate::error::Error::LibraryBug(color_eyre::eyre::eyre!("poisoned"))
+    })?;
+    let app = "app".to_string();
+    check_eq!(
+        *recorded,
+        vec![
+            (
+                app.clone(),
+                StatementKind::Query,
+                StatementClass::Dml,
+                Some(fingerprint(sql)),
+                3,
+                None
+            ),
+            (
+                app.clone(),
+                StatementKind::Prepare,
+                StatementClass::Dml,
+                Some(fingerprint(sql)),
+                0,
+                None
+            ),
+            (
+                app,
+                StatementKind::Exec,
+                StatementClass::Unknown,
+                None,
+                0,
+                Some(1142)
+            ),
+        ]
+    );
+    Ok(())
+}
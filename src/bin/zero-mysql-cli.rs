@@ -0,0 +1,511 @@
+//! `zero-mysql-cli`: run ad-hoc queries or a small benchmark against a server.
+//!
+//! ```text
+//! zero-mysql-cli [--url URL] query [--format table|csv|json] SQL
+//! zero-mysql-cli [--url URL] bench [--concurrency N] [--duration SECS] [--statement SQL]
+//! zero-mysql-cli [--url URL] dump-schema
+//! zero-mysql-cli verify --schema FILE SQL_FILE...
+//! ```
+//!
+//! The URL defaults to the `DATABASE_URL` environment variable.
+//! `dump-schema` writes the columns of the current database as a JSON schema snapshot, and
+//! `verify` checks the statements of SQL files against a snapshot without connecting.
+//! Rows are written as they arrive, except for `table` which needs the column widths.
+//! `json` writes one object per row (JSON Lines).
+
+use std::borrow::Cow;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use zero_mysql::Opts;
+use zero_mysql::error::{Error, Result, eyre};
+use zero_mysql::protocol::command::ColumnDefinition;
+use zero_mysql::protocol::primitive::read_string_lenenc;
+use zero_mysql::protocol::response::{OkPayload, OkPayloadBytes};
+use zero_mysql::protocol::r#trait::{BinaryResultSetHandler, TextResultSetHandler};
+use zero_mysql::protocol::{BinaryRowPayload, TextRowPayload};
+use zero_mysql::schema_check::{ColumnSchema, SCHEMA_DUMP_SQL, SchemaSnapshot, split_statements};
+use zero_mysql::sync::Conn;
+
+const USAGE: &str = "\
+usage: zero-mysql-cli [--url URL] query [--format table|csv|json] SQL
+       zero-mysql-cli [--url URL] bench [--concurrency N] [--duration SECS] [--statement SQL]
+       zero-mysql-cli [--url URL] dump-schema
+       zero-mysql-cli verify --schema FILE SQL_FILE...
+
+The URL defaults to $DATABASE_URL.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Debug)]
+enum Command {
+    Query {
+        format: Format,
+        sql: String,
+    },
+    Bench {
+        concurrency: usize,
+        duration: Duration,
+        statement: String,
+    },
+    DumpSchema,
+    Verify {
+        schema: String,
+        files: Vec<String>,
+    },
+}
+
+#[derive(Debug)]
+struct Args {
+    url: Option<String>,
+    command: Command,
+}
+
+fn usage_error(message: &str) -> Error {
+    Error::BadUsageError(format!("{message}\n\n{USAGE}"))
+}
+
+fn parse_number(flag: &str, value: Option<String>) -> Result<u64> {
+    value
+        .as_deref()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| usage_error(&format!("{flag} expects a number")))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args> {
+    let mut url = None;
+    let subcommand = loop {
+        match args.next().as_deref() {
+            Some("--url") => {
+                url = Some(
+                    args.next()
+                        .ok_or_else(|| usage_error("--url expects a value"))?,
+                )
+            }
+            Some("-h" | "--help") | None => return Err(usage_error("missing subcommand")),
+            Some(subcommand) => break subcommand.to_string(),
+        }
+    };
+
+    let command = match subcommand.as_str() {
+        "query" => {
+            let mut format = Format::Table;
+            let mut sql = Vec::new();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--format" => {
+                        format = match args.next().as_deref() {
+                            Some("table") => Format::Table,
+                            Some("csv") => Format::Csv,
+                            Some("json") => Format::Json,
+                            _ => return Err(usage_error("--format expects table, csv or json")),
+                        }
+                    }
+                    _ => sql.push(arg),
+                }
+            }
+            if sql.is_empty() {
+                return Err(usage_error("query expects SQL"));
+            }
+            Command::Query {
+                format,
+                sql: sql.join(" "),
+            }
+        }
+        "bench" => {
+            let mut concurrency = 4;
+            let mut duration = Duration::from_secs(10);
+            let mut statement = "SELECT 1".to_string();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--concurrency" => {
+                        concurrency = parse_number(&arg, args.next())?.max(1) as usize;
+                    }
+                    "--duration" => {
+                        duration = Duration::from_secs(parse_number(&arg, args.next())?);
+                    }
+                    "--statement" => {
+                        statement = args
+                            .next()
+                            .ok_or_else(|| usage_error("--statement expects SQL"))?;
+                    }
+                    _ => return Err(usage_error(&format!("unknown bench option {arg}"))),
+                }
+            }
+            Command::Bench {
+                concurrency,
+                duration,
+                statement,
+            }
+        }
+        "dump-schema" => Command::DumpSchema,
+        "verify" => {
+            let mut schema = None;
+            let mut files = Vec::new();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--schema" => {
+                        schema = Some(
+                            args.next()
+                                .ok_or_else(|| usage_error("--schema expects a file"))?,
+                        )
+                    }
+                    _ => files.push(arg),
+                }
+            }
+            let schema = schema.ok_or_else(|| usage_error("verify expects --schema FILE"))?;
+            if files.is_empty() {
+                return Err(usage_error("verify expects SQL files"));
+            }
+            Command::Verify { schema, files }
+        }
+        _ => return Err(usage_error(&format!("unknown subcommand {subcommand}"))),
+    };
+    Ok(Args { url, command })
+}
+
+/// Split a text protocol row into its values; `None` is NULL.
+fn text_values(row: TextRowPayload<'_>) -> Result<Vec<Option<Cow<'_, str>>>> {
+    let mut values = Vec::new();
+    let mut data = row.0;
+    while let Some((&first, after_null)) = data.split_first() {
+        if first == 0xFB {
+            values.push(None);
+            data = after_null;
+        } else {
+            let (value, rest) = read_string_lenenc(data)?;
+            values.push(Some(String::from_utf8_lossy(value)));
+            data = rest;
+        }
+    }
+    Ok(values)
+}
+
+fn write_csv_field(out: &mut impl Write, field: &str) -> io::Result<()> {
+    if field.contains([',', '"', '\n', '\r']) {
+        write!(out, "\"{}\"", field.replace('"', "\"\""))
+    } else {
+        out.write_all(field.as_bytes())
+    }
+}
+
+fn write_json_string(out: &mut impl Write, value: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            control if u32::from(control) < 0x20 => write!(out, "\\u{:04x}", u32::from(control))?,
+            _ => write!(out, "{c}")?,
+        }
+    }
+    out.write_all(b"\"")
+}
+
+/// Writes the result sets of a text query in the chosen [`Format`].
+struct OutputHandler<W: Write> {
+    out: W,
+    format: Format,
+    columns: Vec<String>,
+    /// Rows of the current result set, for [`Format::Table`]
+    table: Vec<Vec<String>>,
+    num_rows: u64,
+}
+
+impl<W: Write> OutputHandler<W> {
+    fn new(out: W, format: Format) -> Self {
+        Self {
+            out,
+            format,
+            columns: Vec::new(),
+            table: Vec::new(),
+            num_rows: 0,
+        }
+    }
+
+    fn write_table(&mut self) -> io::Result<()> {
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| c.chars().count()).collect();
+        for row in &self.table {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+        let separator: String = widths
+            .iter()
+            .map(|width| format!("+{}", "-".repeat(width + 2)))
+            .chain(["+".to_string()])
+            .collect();
+
+        writeln!(self.out, "{separator}")?;
+        write_table_row(&mut self.out, &self.columns, &widths)?;
+        writeln!(self.out, "{separator}")?;
+        for row in &self.table {
+            write_table_row(&mut self.out, row, &widths)?;
+        }
+        writeln!(self.out, "{separator}")?;
+        self.table.clear();
+        Ok(())
+    }
+}
+
+fn write_table_row(out: &mut impl Write, row: &[String], widths: &[usize]) -> io::Result<()> {
+    for (value, width) in row.iter().zip(widths) {
+        write!(out, "| {value:<width$} ")?;
+    }
+    writeln!(out, "|")
+}
+
+impl<W: Write> TextResultSetHandler for OutputHandler<W> {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        let ok = OkPayload::try_from(ok)?;
+        eprintln!(
+            "Query OK, {} rows affected, {} warnings",
+            ok.affected_rows, ok.warnings
+        );
+        Ok(())
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.columns = cols
+            .iter()
+            .map(|col| String::from_utf8_lossy(col.name_alias).into_owned())
+            .collect();
+        self.num_rows = 0;
+        if self.format == Format::Csv {
+            for (i, column) in self.columns.iter().enumerate() {
+                if i > 0 {
+                    self.out.write_all(b",")?;
+                }
+                write_csv_field(&mut self.out, column)?;
+            }
+            self.out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        let values = text_values(row)?;
+        self.num_rows += 1;
+        match self.format {
+            Format::Table => self.table.push(
+                values
+                    .into_iter()
+                    .map(|value| value.map_or_else(|| "NULL".to_string(), Cow::into_owned))
+                    .collect(),
+            ),
+            Format::Csv => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        self.out.write_all(b",")?;
+                    }
+                    write_csv_field(&mut self.out, value.as_deref().unwrap_or_default())?;
+                }
+                self.out.write_all(b"\n")?;
+            }
+            Format::Json => {
+                self.out.write_all(b"{")?;
+                for (i, (column, value)) in self.columns.iter().zip(&values).enumerate() {
+                    if i > 0 {
+                        self.out.write_all(b",")?;
+                    }
+                    write_json_string(&mut self.out, column)?;
+                    self.out.write_all(b":")?;
+                    match value {
+                        Some(value) => write_json_string(&mut self.out, value)?,
+                        None => self.out.write_all(b"null")?,
+                    }
+                }
+                self.out.write_all(b"}\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        if self.format == Format::Table {
+            self.write_table()?;
+        }
+        self.out.flush()?;
+        eprintln!("{} rows", self.num_rows);
+        Ok(())
+    }
+}
+
+/// Counts the rows of a benchmark statement without decoding them.
+#[derive(Default)]
+struct CountHandler {
+    rows: u64,
+}
+
+impl BinaryResultSetHandler for CountHandler {
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+
+    fn row(&mut self, _: &[ColumnDefinition<'_>], _: BinaryRowPayload<'_>) -> Result<()> {
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Execute `statement` on one connection until `deadline`, returning the latencies and row count.
+fn bench_worker(opts: Opts, statement: &str, deadline: Instant) -> Result<(Vec<Duration>, u64)> {
+    let mut conn = Conn::new(opts)?;
+    let mut stmt = conn.prepare(statement)?;
+    let mut latencies = Vec::new();
+    let mut handler = CountHandler::default();
+    while Instant::now() < deadline {
+        let started = Instant::now();
+        conn.exec(&mut stmt, (), &mut handler)?;
+        latencies.push(started.elapsed());
+    }
+    Ok((latencies, handler.rows))
+}
+
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    sorted
+        .get((sorted.len().saturating_sub(1)) * percent / 100)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn bench(opts: &Opts, concurrency: usize, duration: Duration, statement: &str) -> Result<()> {
+    let started = Instant::now();
+    let deadline = started + duration;
+    let workers: Vec<_> = std::iter::repeat_with(|| {
+        let opts = opts.clone();
+        let statement = statement.to_string();
+        thread::spawn(move || bench_worker(opts, &statement, deadline))
+    })
+    .take(concurrency)
+    .collect();
+
+    let mut latencies = Vec::new();
+    let mut rows = 0;
+    for worker in workers {
+        let (worker_latencies, worker_rows) = worker
+            .join()
+            .map_err(|_panic| Error::LibraryBug(eyre!("bench worker panicked")))??;
+        latencies.extend(worker_latencies);
+        rows += worker_rows;
+    }
+    let elapsed = started.elapsed();
+    latencies.sort_unstable();
+
+    println!("statement:   {statement}");
+    println!("concurrency: {concurrency}");
+    println!("duration:    {:.2?}", elapsed);
+    println!(
+        "executions:  {} ({:.0}/s)",
+        latencies.len(),
+        latencies.len() as f64 / elapsed.as_secs_f64()
+    );
+    println!("rows:        {rows}");
+    println!(
+        "latency:     p50 {:.2?}, p99 {:.2?}, max {:.2?}",
+        percentile(&latencies, 50),
+        percentile(&latencies, 99),
+        percentile(&latencies, 100)
+    );
+    Ok(())
+}
+
+fn dump_schema(opts: Opts) -> Result<()> {
+    let mut conn = Conn::new(opts)?;
+    let mut stmt = conn.prepare(SCHEMA_DUMP_SQL)?;
+    let rows: Vec<(String, String, String, String)> = conn.exec_collect(&mut stmt, ())?;
+    let mut snapshot = SchemaSnapshot::default();
+    for (table, name, column_type, nullable) in rows {
+        snapshot.push_column(
+            &table,
+            ColumnSchema {
+                name,
+                column_type,
+                nullable: nullable == "YES",
+            },
+        );
+    }
+    io::stdout()
+        .lock()
+        .write_all(snapshot.to_json().as_bytes())?;
+    Ok(())
+}
+
+/// Print every statement that does not match the snapshot, as `file:line: issue`.
+fn verify(schema: &str, files: &[String]) -> Result<()> {
+    let snapshot = SchemaSnapshot::from_json(&std::fs::read_to_string(schema)?)?;
+    let mut num_issues = 0;
+    for file in files {
+        let script = std::fs::read_to_string(file)?;
+        for (line, statement) in split_statements(&script) {
+            for issue in snapshot.check(statement) {
+                println!("{file}:{line}: {issue}");
+                num_issues += 1;
+            }
+        }
+    }
+    if num_issues > 0 {
+        return Err(Error::BadUsageError(format!(
+            "{num_issues} references do not match {schema}"
+        )));
+    }
+    Ok(())
+}
+
+fn run() -> Result<()> {
+    let args = parse_args(std::env::args().skip(1))?;
+    if let Command::Verify { schema, files } = &args.command {
+        return verify(schema, files);
+    }
+    let url = match args.url {
+        Some(url) => url,
+        None => std::env::var("DATABASE_URL")
+            .map_err(|_not_set| usage_error("pass --url or set DATABASE_URL"))?,
+    };
+    let opts = Opts::try_from(url.as_str())?;
+
+    match args.command {
+        Command::Query { format, sql } => {
+            let mut conn = Conn::new(opts)?;
+            let mut handler = OutputHandler::new(BufWriter::new(io::stdout().lock()), format);
+            conn.query(&sql, &mut handler)?;
+            handler.out.flush()?;
+        }
+        Command::Bench {
+            concurrency,
+            duration,
+            statement,
+        } => bench(&opts, concurrency, duration, &statement)?,
+        Command::DumpSchema => dump_schema(opts)?,
+        Command::Verify { .. } => {}
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,1402 @@
+//! Binary log replication client for change data capture.
+//!
+//! `Conn::binlog_dump` registers the connection as a replica (`COM_REGISTER_SLAVE`) and requests
+//! the binary log from a file and position (`COM_BINLOG_DUMP`). The returned `BinlogStream`
+//! yields one [`Event`] per packet, parsed in place from the read buffer.
+//!
+//! Row events are decoded with the [`TableMap`] of the preceding `TABLE_MAP_EVENT`,
+//! which the parser keeps per table id. Start the dump at a transaction boundary,
+//! e.g. a [`crate::snapshot::BinlogPosition`], so that every row event has its table map.
+//!
+//! With [`BinlogRequest::gtid_set`], the dump uses `COM_BINLOG_DUMP_GTID` and the server sends
+//! the transactions missing from the set (MySQL only). The stream keeps a [`BinlogPosition`]
+//! at the end of the last complete transaction, including the GTID set if one was requested,
+//! so a consumer that persists it after handling a transaction can resume from it after a restart.
+//!
+//! The server needs `binlog_format = ROW`, and the user needs the `REPLICATION SLAVE`
+//! and `REPLICATION CLIENT` privileges. Event checksums are stripped but not verified.
+//!
+//! ```no_run
+//! # fn run(conn: &mut zero_mysql::sync::Conn) -> zero_mysql::error::Result<()> {
+//! use zero_mysql::binlog::{BinlogRequest, EventData};
+//!
+//! let mut stream = conn.binlog_dump(&BinlogRequest::new(1001, "binlog.000042", 4))?;
+//! while let Some(event) = stream.next_event()? {
+//!     if let EventData::Rows(rows) = event.data {
+//!         for change in rows.rows() {
+//!             let change = change?;
+//!             println!("{:?} -> {:?}", change.before, change.after);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use zerocopy::byteorder::little_endian::{U16 as U16LE, U32 as U32LE};
+use zerocopy::{FromBytes, Immutable, KnownLayout};
+
+use crate::constant::ColumnType;
+use crate::error::{Error, Result, eyre};
+use crate::protocol::primitive::*;
+
+pub use crate::snapshot::BinlogPosition;
+
+/// Sent before `COM_BINLOG_DUMP` so that MySQL sends events with the checksums of the binlog
+pub(crate) const MYSQL_CHECKSUM_SQL: &str = "SET @source_binlog_checksum = @@global.binlog_checksum, \
+     @master_binlog_checksum = @@global.binlog_checksum";
+
+/// Sent before `COM_BINLOG_DUMP` so that MariaDB sends GTID events and checksums
+pub(crate) const MARIADB_CHECKSUM_SQL: &str = "SET @mariadb_slave_capability = 4, \
+     @master_binlog_checksum = @@global.binlog_checksum";
+
+/// The checksum algorithm of the binlog, `CRC32` or `NONE`
+pub(crate) const CHECKSUM_SQL: &str = "SELECT @@global.binlog_checksum";
+
+/// Returns true if the value of [`CHECKSUM_SQL`] means that events end with a CRC32.
+pub(crate) fn parse_checksum(rows: &[Vec<Option<String>>]) -> bool {
+    crate::snapshot::first_value(rows).is_some_and(|value| value.eq_ignore_ascii_case("CRC32"))
+}
+
+/// Size of [`EventHeader`]
+pub const EVENT_HEADER_LENGTH: usize = 19;
+
+/// `checksum_algorithm` of a `FORMAT_DESCRIPTION_EVENT`: events end with a CRC32
+const CHECKSUM_CRC32: u8 = 1;
+
+/// Binlog dump parameters for `Conn::binlog_dump`.
+#[derive(Debug, Clone)]
+pub struct BinlogRequest {
+    /// Server id of this replica, unique among the replicas of the source
+    pub server_id: u32,
+    /// Binary log file name, e.g. `binlog.000042`
+    pub file: String,
+    /// Byte offset in `file`; 4 is the first event
+    pub position: u64,
+    /// Executed GTID set in the text form of `@@gtid_executed`, e.g. `3e11fa47-...:1-23`.
+    /// If set, the server sends the transactions that are not in the set, starting from `file`
+    /// or, if `file` is empty, from the first binlog that has them.
+    ///
+    /// Default: `None`
+    pub gtid_set: Option<String>,
+    /// End the stream at the end of the binlog instead of waiting for new events
+    ///
+    /// Default: `false`
+    pub non_blocking: bool,
+    /// Host name reported in `SHOW REPLICAS`
+    ///
+    /// Default: `""`
+    pub hostname: String,
+    /// Port reported in `SHOW REPLICAS`
+    ///
+    /// Default: `0`
+    pub port: u16,
+}
+
+impl BinlogRequest {
+    pub fn new(server_id: u32, file: &str, position: u64) -> Self {
+        Self {
+            server_id,
+            file: file.to_string(),
+            position,
+            gtid_set: None,
+            non_blocking: false,
+            hostname: String::new(),
+            port: 0,
+        }
+    }
+
+    /// Dump the transactions that are not in `gtid_set`.
+    pub fn gtid(server_id: u32, gtid_set: &str) -> Self {
+        Self {
+            gtid_set: Some(gtid_set.to_string()),
+            ..Self::new(server_id, "", 4)
+        }
+    }
+
+    /// Resume from a position saved from [`crate::sync::BinlogStream::position`] or a snapshot.
+    pub fn from_position(server_id: u32, position: &BinlogPosition) -> Self {
+        Self {
+            gtid_set: position.gtid_set.clone(),
+            ..Self::new(server_id, &position.file, position.position)
+        }
+    }
+}
+
+/// Binlog event types
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[expect(non_camel_case_types)]
+pub enum EventType {
+    UNKNOWN_EVENT = 0,
+    START_EVENT_V3 = 1,
+    QUERY_EVENT = 2,
+    STOP_EVENT = 3,
+    ROTATE_EVENT = 4,
+    INTVAR_EVENT = 5,
+    RAND_EVENT = 13,
+    USER_VAR_EVENT = 14,
+    FORMAT_DESCRIPTION_EVENT = 15,
+    XID_EVENT = 16,
+    BEGIN_LOAD_QUERY_EVENT = 17,
+    EXECUTE_LOAD_QUERY_EVENT = 18,
+    TABLE_MAP_EVENT = 19,
+    WRITE_ROWS_EVENT_V1 = 23,
+    UPDATE_ROWS_EVENT_V1 = 24,
+    DELETE_ROWS_EVENT_V1 = 25,
+    INCIDENT_EVENT = 26,
+    HEARTBEAT_LOG_EVENT = 27,
+    IGNORABLE_LOG_EVENT = 28,
+    ROWS_QUERY_LOG_EVENT = 29,
+    WRITE_ROWS_EVENT = 30,
+    UPDATE_ROWS_EVENT = 31,
+    DELETE_ROWS_EVENT = 32,
+    GTID_LOG_EVENT = 33,
+    ANONYMOUS_GTID_LOG_EVENT = 34,
+    PREVIOUS_GTIDS_LOG_EVENT = 35,
+    TRANSACTION_CONTEXT_EVENT = 36,
+    VIEW_CHANGE_EVENT = 37,
+    XA_PREPARE_LOG_EVENT = 38,
+    PARTIAL_UPDATE_ROWS_EVENT = 39,
+    TRANSACTION_PAYLOAD_EVENT = 40,
+    HEARTBEAT_LOG_EVENT_V2 = 41,
+    /// MariaDB Extension
+    ANNOTATE_ROWS_EVENT = 160,
+    /// MariaDB Extension
+    BINLOG_CHECKPOINT_EVENT = 161,
+    /// MariaDB Extension
+    GTID_EVENT = 162,
+    /// MariaDB Extension
+    GTID_LIST_EVENT = 163,
+    /// MariaDB Extension
+    START_ENCRYPTION_EVENT = 164,
+}
+
+impl EventType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::UNKNOWN_EVENT),
+            1 => Some(Self::START_EVENT_V3),
+            2 => Some(Self::QUERY_EVENT),
+            3 => Some(Self::STOP_EVENT),
+            4 => Some(Self::ROTATE_EVENT),
+            5 => Some(Self::INTVAR_EVENT),
+            13 => Some(Self::RAND_EVENT),
+            14 => Some(Self::USER_VAR_EVENT),
+            15 => Some(Self::FORMAT_DESCRIPTION_EVENT),
+            16 => Some(Self::XID_EVENT),
+            17 => Some(Self::BEGIN_LOAD_QUERY_EVENT),
+            18 => Some(Self::EXECUTE_LOAD_QUERY_EVENT),
+            19 => Some(Self::TABLE_MAP_EVENT),
+            23 => Some(Self::WRITE_ROWS_EVENT_V1),
+            24 => Some(Self::UPDATE_ROWS_EVENT_V1),
+            25 => Some(Self::DELETE_ROWS_EVENT_V1),
+            26 => Some(Self::INCIDENT_EVENT),
+            27 => Some(Self::HEARTBEAT_LOG_EVENT),
+            28 => Some(Self::IGNORABLE_LOG_EVENT),
+            29 => Some(Self::ROWS_QUERY_LOG_EVENT),
+            30 => Some(Self::WRITE_ROWS_EVENT),
+            31 => Some(Self::UPDATE_ROWS_EVENT),
+            32 => Some(Self::DELETE_ROWS_EVENT),
+            33 => Some(Self::GTID_LOG_EVENT),
+            34 => Some(Self::ANONYMOUS_GTID_LOG_EVENT),
+            35 => Some(Self::PREVIOUS_GTIDS_LOG_EVENT),
+            36 => Some(Self::TRANSACTION_CONTEXT_EVENT),
+            37 => Some(Self::VIEW_CHANGE_EVENT),
+            38 => Some(Self::XA_PREPARE_LOG_EVENT),
+            39 => Some(Self::PARTIAL_UPDATE_ROWS_EVENT),
+            40 => Some(Self::TRANSACTION_PAYLOAD_EVENT),
+            41 => Some(Self::HEARTBEAT_LOG_EVENT_V2),
+            160 => Some(Self::ANNOTATE_ROWS_EVENT),
+            161 => Some(Self::BINLOG_CHECKPOINT_EVENT),
+            162 => Some(Self::GTID_EVENT),
+            163 => Some(Self::GTID_LIST_EVENT),
+            164 => Some(Self::START_ENCRYPTION_EVENT),
+            _ => None,
+        }
+    }
+}
+
+/// Common header of every binlog event (19 bytes)
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, FromBytes, KnownLayout, Immutable)]
+pub struct EventHeader {
+    timestamp: U32LE,
+    event_type: u8,
+    server_id: U32LE,
+    event_size: U32LE,
+    log_pos: U32LE,
+    flags: U16LE,
+}
+
+impl EventHeader {
+    /// Seconds since the epoch when the statement started
+    pub fn timestamp(&self) -> u32 {
+        self.timestamp.get()
+    }
+
+    /// Raw event type, see [`EventHeader::event_type`]
+    pub fn event_type_byte(&self) -> u8 {
+        self.event_type
+    }
+
+    /// `None` for event types unknown to this library
+    pub fn event_type(&self) -> Option<EventType> {
+        EventType::from_u8(self.event_type)
+    }
+
+    /// Server id of the server that originally wrote the event
+    pub fn server_id(&self) -> u32 {
+        self.server_id.get()
+    }
+
+    /// Size of the event including the header and the checksum
+    pub fn event_size(&self) -> u32 {
+        self.event_size.get()
+    }
+
+    /// Position of the next event in the binlog file, 0 for events not in the file
+    pub fn log_pos(&self) -> u32 {
+        self.log_pos.get()
+    }
+
+    pub fn flags(&self) -> u16 {
+        self.flags.get()
+    }
+}
+
+/// A binlog event, borrowed from the read buffer
+#[derive(Debug)]
+pub struct Event<'a> {
+    pub header: &'a EventHeader,
+    pub data: EventData<'a>,
+}
+
+#[derive(Debug)]
+pub enum EventData<'a> {
+    Query(QueryEvent<'a>),
+    Rotate(RotateEvent<'a>),
+    FormatDescription(FormatDescriptionEvent<'a>),
+    TableMap(TableMapEvent<'a>),
+    /// `WRITE_ROWS_EVENT`, `UPDATE_ROWS_EVENT` or `DELETE_ROWS_EVENT`, version 1 or 2
+    Rows(RowsEvent<'a>),
+    Gtid(GtidEvent),
+    /// `ANONYMOUS_GTID_LOG_EVENT`: the next transaction has no GTID (`gtid_mode = OFF`)
+    AnonymousGtid(GtidEvent),
+    /// `XID_EVENT`: commit of the transaction with this XA id
+    Xid(u64),
+    /// The body of any other event, without the checksum
+    Other(&'a [u8]),
+}
+
+/// `QUERY_EVENT`: a statement, including `BEGIN` and DDL
+#[derive(Debug, Clone, Copy)]
+pub struct QueryEvent<'a> {
+    pub thread_id: u32,
+    /// Execution time in seconds
+    pub exec_time: u32,
+    pub error_code: u16,
+    pub status_vars: &'a [u8],
+    /// Default schema
+    pub schema: &'a [u8],
+    pub query: &'a [u8],
+}
+
+/// `ROTATE_EVENT`: the stream continues in another binlog file
+#[derive(Debug, Clone, Copy)]
+pub struct RotateEvent<'a> {
+    pub position: u64,
+    pub next_file: &'a [u8],
+}
+
+/// `FORMAT_DESCRIPTION_EVENT`: first event of every binlog file
+#[derive(Debug, Clone, Copy)]
+pub struct FormatDescriptionEvent<'a> {
+    pub binlog_version: u16,
+    pub server_version: &'a [u8],
+    pub create_timestamp: u32,
+    pub header_length: u8,
+    /// 0: none, 1: CRC32
+    pub checksum_algorithm: u8,
+}
+
+/// `GTID_LOG_EVENT` or `ANONYMOUS_GTID_LOG_EVENT` (MySQL): the GTID of the next transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GtidEvent {
+    pub flags: u8,
+    /// Source UUID
+    pub sid: [u8; 16],
+    /// Transaction number
+    pub gno: u64,
+}
+
+impl GtidEvent {
+    /// `uuid:gno`
+    pub fn gtid(&self) -> String {
+        let mut out = String::with_capacity(57);
+        write_uuid(&mut out, &self.sid);
+        let _ = write!(out, ":{}", self.gno);
+        out
+    }
+}
+
+fn write_uuid(out: &mut String, sid: &[u8; 16]) {
+    for (i, byte) in sid.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            out.push('-');
+        }
+        let _ = write!(out, "{byte:02x}");
+    }
+}
+
+/// A MySQL GTID set: per source UUID, the ranges of executed transaction numbers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GtidSet {
+    /// Sorted, non-overlapping, non-adjacent `start..end` ranges
+    sids: BTreeMap<[u8; 16], Vec<(u64, u64)>>,
+}
+
+impl GtidSet {
+    /// Parse the text form, e.g. `3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7,\n...`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = || Error::BadUsageError(format!("Invalid GTID set: {text}"));
+        let mut set = Self::default();
+        for entry in text
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let mut parts = entry.split(':');
+            let uuid = parts.next().unwrap_or_default();
+            let hex: Vec<u8> = uuid.bytes().filter(|&byte| byte != b'-').collect();
+            if hex.len() != 32 {
+                return Err(invalid());
+            }
+            let mut sid = [0_u8; 16];
+            for (byte, pair) in sid.iter_mut().zip(hex.chunks_exact(2)) {
+                let pair = std::str::from_utf8(pair).map_err(|_utf8| invalid())?;
+                *byte = u8::from_str_radix(pair, 16).map_err(|_digit| invalid())?;
+            }
+            for interval in parts {
+                let (start, end) = interval.split_once('-').unwrap_or((interval, interval));
+                let start: u64 = start.trim().parse().map_err(|_number| invalid())?;
+                let end: u64 = end.trim().parse().map_err(|_number| invalid())?;
+                if start == 0 || end < start {
+                    return Err(invalid());
+                }
+                set.add_range(sid, start, end + 1);
+            }
+        }
+        Ok(set)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sids.is_empty()
+    }
+
+    pub fn contains(&self, sid: &[u8; 16], gno: u64) -> bool {
+        self.sids
+            .get(sid)
+            .is_some_and(|ranges| ranges.iter().any(|&(start, end)| start <= gno && gno < end))
+    }
+
+    /// Add the transaction of a `GTID_LOG_EVENT`.
+    pub fn add(&mut self, sid: [u8; 16], gno: u64) {
+        self.add_range(sid, gno, gno + 1);
+    }
+
+    fn add_range(&mut self, sid: [u8; 16], start: u64, end: u64) {
+        let ranges = self.sids.entry(sid).or_default();
+        ranges.push((start, end));
+        ranges.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for &range in ranges.iter() {
+            match merged.last_mut() {
+                Some(last) if range.0 <= last.1 => last.1 = last.1.max(range.1),
+                _ => merged.push(range),
+            }
+        }
+        *ranges = merged;
+    }
+
+    /// Write the binary form sent by `COM_BINLOG_DUMP_GTID`.
+    pub fn write_encoded(&self, out: &mut Vec<u8>) {
+        write_int_8(out, self.sids.len() as u64);
+        for (sid, ranges) in &self.sids {
+            out.extend_from_slice(sid);
+            write_int_8(out, ranges.len() as u64);
+            for &(start, end) in ranges {
+                write_int_8(out, start);
+                write_int_8(out, end);
+            }
+        }
+    }
+}
+
+/// The text form of `@@gtid_executed`, without line breaks
+impl std::fmt::Display for GtidSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        for (i, (sid, ranges)) in self.sids.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_uuid(&mut out, sid);
+            for &(start, end) in ranges {
+                if end - start == 1 {
+                    let _ = write!(out, ":{start}");
+                } else {
+                    let _ = write!(out, ":{start}-{}", end - 1);
+                }
+            }
+        }
+        f.write_str(&out)
+    }
+}
+
+/// Keeps the [`BinlogPosition`] after the last complete transaction of a binlog stream.
+#[derive(Debug)]
+pub(crate) struct PositionTracker {
+    position: BinlogPosition,
+    gtid_set: Option<GtidSet>,
+    /// The GTID of the current transaction
+    pending: Option<GtidEvent>,
+}
+
+impl PositionTracker {
+    pub(crate) fn new(request: &BinlogRequest) -> Result<Self> {
+        let gtid_set = request
+            .gtid_set
+            .as_deref()
+            .map(GtidSet::parse)
+            .transpose()?;
+        Ok(Self {
+            position: BinlogPosition {
+                file: request.file.clone(),
+                position: request.position,
+                gtid_set: gtid_set.as_ref().map(GtidSet::to_string),
+            },
+            gtid_set,
+            pending: None,
+        })
+    }
+
+    pub(crate) fn gtid_set(&self) -> Option<&GtidSet> {
+        self.gtid_set.as_ref()
+    }
+
+    pub(crate) fn position(&self) -> &BinlogPosition {
+        &self.position
+    }
+
+    pub(crate) fn update(&mut self, event: &Event<'_>) {
+        let committed = match &event.data {
+            EventData::Rotate(rotate) => {
+                self.position.file = String::from_utf8_lossy(rotate.next_file).into_owned();
+                self.position.position = rotate.position;
+                return;
+            }
+            EventData::Gtid(gtid) => {
+                self.pending = Some(*gtid);
+                return;
+            }
+            EventData::Xid(_) => true,
+            // DDL, or `COMMIT` of a non-transactional table
+            EventData::Query(query) => !query.query.eq_ignore_ascii_case(b"BEGIN"),
+            _ => false,
+        };
+        if !committed {
+            return;
+        }
+        // 0 in artificial events
+        if event.header.log_pos() != 0 {
+            self.position.position = u64::from(event.header.log_pos());
+        }
+        if let (Some(set), Some(gtid)) = (&mut self.gtid_set, self.pending.take()) {
+            set.add(gtid.sid, gtid.gno);
+            self.position.gtid_set = Some(set.to_string());
+        }
+    }
+}
+
+/// `TABLE_MAP_EVENT`: the table and column types of the following row events
+#[derive(Debug, Clone, Copy)]
+pub struct TableMapEvent<'a> {
+    pub table_id: u64,
+    pub flags: u16,
+    pub schema: &'a [u8],
+    pub table: &'a [u8],
+    /// A [`ColumnType`] byte per column
+    pub column_types: &'a [u8],
+    /// Type-specific metadata of the columns
+    pub metadata: &'a [u8],
+    /// Bit `i` is set if column `i` is nullable
+    pub null_bitmap: &'a [u8],
+    /// Optional metadata (`binlog_row_metadata`), a sequence of `[type: 1][length: lenenc][value]`
+    pub optional_metadata: &'a [u8],
+}
+
+/// Optional metadata type of the signedness of the numeric columns
+const SIGNEDNESS: u8 = 1;
+
+impl<'a> TableMapEvent<'a> {
+    fn parse(body: &'a [u8]) -> Result<Self> {
+        let (table_id, rest) = read_int_6(body)?;
+        let (flags, rest) = read_int_2(rest)?;
+        let (schema_len, rest) = read_int_1(rest)?;
+        let (schema, rest) = read_string_fix(rest, usize::from(schema_len))?;
+        let (_schema_nul, rest) = read_int_1(rest)?;
+        let (table_len, rest) = read_int_1(rest)?;
+        let (table, rest) = read_string_fix(rest, usize::from(table_len))?;
+        let (_table_nul, rest) = read_int_1(rest)?;
+        let (column_count, rest) = read_int_lenenc(rest)?;
+        let (column_types, rest) = read_string_fix(rest, column_count as usize)?;
+        let (metadata, rest) = read_string_lenenc(rest)?;
+        let (null_bitmap, optional_metadata) =
+            read_string_fix(rest, (column_count as usize).div_ceil(8))?;
+        Ok(Self {
+            table_id,
+            flags,
+            schema,
+            table,
+            column_types,
+            metadata,
+            null_bitmap,
+            optional_metadata,
+        })
+    }
+
+    /// Decode the columns into an owned [`TableMap`].
+    pub fn to_table_map(&self) -> Result<TableMap> {
+        let unsigned = self.signedness()?;
+        let mut numeric_index = 0;
+        let mut metadata = self.metadata;
+        let mut columns = Vec::with_capacity(self.column_types.len());
+        for (i, &type_byte) in self.column_types.iter().enumerate() {
+            let column_type = ColumnType::from_u8(type_byte).ok_or_else(|| {
+                Error::LibraryBug(eyre!("binlog: unknown column type 0x{type_byte:02X}"))
+            })?;
+            let (column_type, meta, rest) = read_column_metadata(column_type, metadata)?;
+            metadata = rest;
+            let is_unsigned = if is_numeric(column_type) {
+                let bit = numeric_index;
+                numeric_index += 1;
+                unsigned
+                    .and_then(|bitmap| bitmap.get(bit / 8))
+                    .is_some_and(|byte| byte & (0x80 >> (bit % 8)) != 0)
+            } else {
+                false
+            };
+            columns.push(BinlogColumn {
+                column_type,
+                metadata: meta,
+                nullable: self
+                    .null_bitmap
+                    .get(i / 8)
+                    .is_some_and(|byte| byte & (1 << (i % 8)) != 0),
+                unsigned: is_unsigned,
+            });
+        }
+        Ok(TableMap {
+            table_id: self.table_id,
+            schema: String::from_utf8_lossy(self.schema).into_owned(),
+            table: String::from_utf8_lossy(self.table).into_owned(),
+            columns,
+        })
+    }
+
+    /// The `SIGNEDNESS` optional metadata: a bit per numeric column, most significant bit first
+    fn signedness(&self) -> Result<Option<&'a [u8]>> {
+        let mut rest = self.optional_metadata;
+        while !rest.is_empty() {
+            let (kind, after_kind) = read_int_1(rest)?;
+            let (value, after_value) = read_string_lenenc(after_kind)?;
+            if kind == SIGNEDNESS {
+                return Ok(Some(value));
+            }
+            rest = after_value;
+        }
+        Ok(None)
+    }
+}
+
+/// Column types whose signedness is in the `SIGNEDNESS` optional metadata
+fn is_numeric(column_type: ColumnType) -> bool {
+    matches!(
+        column_type,
+        ColumnType::MYSQL_TYPE_TINY
+            | ColumnType::MYSQL_TYPE_SHORT
+            | ColumnType::MYSQL_TYPE_INT24
+            | ColumnType::MYSQL_TYPE_LONG
+            | ColumnType::MYSQL_TYPE_LONGLONG
+            | ColumnType::MYSQL_TYPE_NEWDECIMAL
+            | ColumnType::MYSQL_TYPE_FLOAT
+            | ColumnType::MYSQL_TYPE_DOUBLE
+    )
+}
+
+/// Read the metadata of a column of `column_type`, returning the real type and the metadata.
+///
+/// `MYSQL_TYPE_STRING` columns are `ENUM` and `SET` columns if their metadata says so,
+/// and their metadata becomes the maximum length.
+fn read_column_metadata(column_type: ColumnType, data: &[u8]) -> Result<(ColumnType, u16, &[u8])> {
+    match column_type {
+        ColumnType::MYSQL_TYPE_FLOAT
+        | ColumnType::MYSQL_TYPE_DOUBLE
+        | ColumnType::MYSQL_TYPE_BLOB
+        | ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_GEOMETRY
+        | ColumnType::MYSQL_TYPE_JSON
+        | ColumnType::MYSQL_TYPE_TIMESTAMP2
+        | ColumnType::MYSQL_TYPE_DATETIME2
+        | ColumnType::MYSQL_TYPE_TIME2 => {
+            let (meta, rest) = read_int_1(data)?;
+            Ok((column_type, u16::from(meta), rest))
+        }
+        ColumnType::MYSQL_TYPE_VARCHAR
+        | ColumnType::MYSQL_TYPE_VAR_STRING
+        | ColumnType::MYSQL_TYPE_BIT
+        | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+            let (meta, rest) = read_int_2(data)?;
+            Ok((column_type, meta, rest))
+        }
+        ColumnType::MYSQL_TYPE_STRING
+        | ColumnType::MYSQL_TYPE_ENUM
+        | ColumnType::MYSQL_TYPE_SET => {
+            let (real_type, rest) = read_int_1(data)?;
+            let (length, rest) = read_int_1(rest)?;
+            if real_type == ColumnType::MYSQL_TYPE_ENUM as u8 {
+                return Ok((ColumnType::MYSQL_TYPE_ENUM, u16::from(length), rest));
+            }
+            if real_type == ColumnType::MYSQL_TYPE_SET as u8 {
+                return Ok((ColumnType::MYSQL_TYPE_SET, u16::from(length), rest));
+            }
+            // The top bits of the maximum length are stored inverted in the type byte
+            let max_length = u16::from(length) | ((u16::from(real_type & 0x30) ^ 0x30) << 4);
+            Ok((ColumnType::MYSQL_TYPE_STRING, max_length, rest))
+        }
+        _ => Ok((column_type, 0, data)),
+    }
+}
+
+/// A column of a [`TableMap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinlogColumn {
+    pub column_type: ColumnType,
+    /// Type-specific metadata, e.g. the maximum length of a `VARCHAR`
+    pub metadata: u16,
+    pub nullable: bool,
+    /// Requires `binlog_row_metadata = FULL` on MySQL 8.0; `false` otherwise
+    pub unsigned: bool,
+}
+
+/// An owned copy of a [`TableMapEvent`], kept by the parser to decode row events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableMap {
+    pub table_id: u64,
+    pub schema: String,
+    pub table: String,
+    pub columns: Vec<BinlogColumn>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowsEventKind {
+    Write,
+    Update,
+    Delete,
+}
+
+/// `WRITE_ROWS_EVENT`, `UPDATE_ROWS_EVENT` or `DELETE_ROWS_EVENT`
+#[derive(Debug, Clone, Copy)]
+pub struct RowsEvent<'a> {
+    pub kind: RowsEventKind,
+    pub table_id: u64,
+    pub flags: u16,
+    /// Extra data of version 2 events
+    pub extra_data: &'a [u8],
+    pub column_count: usize,
+    /// Bit `i` is set if column `i` is in the before image, or the only image
+    pub columns_present: &'a [u8],
+    /// Bit `i` is set if column `i` is in the after image of an update
+    pub columns_present_after: &'a [u8],
+    /// Encoded rows
+    pub rows: &'a [u8],
+    /// The table map of `table_id`, if it was seen in this stream
+    pub table: Option<&'a TableMap>,
+}
+
+impl<'a> RowsEvent<'a> {
+    fn parse(
+        kind: RowsEventKind,
+        version_2: bool,
+        body: &'a [u8],
+        tables: &'a HashMap<u64, TableMap>,
+    ) -> Result<Self> {
+        let (table_id, rest) = read_int_6(body)?;
+        let (flags, rest) = read_int_2(rest)?;
+        let (extra_data, rest) = if version_2 {
+            // The length includes itself
+            let (extra_len, rest) = read_int_2(rest)?;
+            read_string_fix(rest, usize::from(extra_len.saturating_sub(2)))?
+        } else {
+            (&[][..], rest)
+        };
+        let (column_count, rest) = read_int_lenenc(rest)?;
+        let column_count = column_count as usize;
+        let (columns_present, rest) = read_string_fix(rest, column_count.div_ceil(8))?;
+        let (columns_present_after, rows) = if kind == RowsEventKind::Update {
+            read_string_fix(rest, column_count.div_ceil(8))?
+        } else {
+            (columns_present, rest)
+        };
+        Ok(Self {
+            kind,
+            table_id,
+            flags,
+            extra_data,
+            column_count,
+            columns_present,
+            columns_present_after,
+            rows,
+            table: tables.get(&table_id),
+        })
+    }
+
+    /// Decode the changed rows.
+    ///
+    /// Fails if the table map of the event was not seen.
+    pub fn rows(&self) -> Rows<'a> {
+        Rows {
+            event: *self,
+            rest: self.rows,
+        }
+    }
+}
+
+/// A changed row of a [`RowsEvent`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowChange<'a> {
+    /// The row before an update or delete
+    pub before: Option<Vec<BinlogValue<'a>>>,
+    /// The row after an insert or update
+    pub after: Option<Vec<BinlogValue<'a>>>,
+}
+
+/// Iterator over the rows of a [`RowsEvent`]
+pub struct Rows<'a> {
+    event: RowsEvent<'a>,
+    rest: &'a [u8],
+}
+
+impl<'a> Rows<'a> {
+    fn next_change(&mut self, table: &'a TableMap) -> Result<RowChange<'a>> {
+        let columns = table
+            .columns
+            .get(..self.event.column_count)
+            .ok_or_else(|| {
+                Error::LibraryBug(eyre!(
+                    "binlog: row event has {} columns, table map has {}",
+                    self.event.column_count,
+                    table.columns.len()
+                ))
+            })?;
+        let (first, rest) = decode_row(columns, self.event.columns_present, self.rest)?;
+        self.rest = rest;
+        Ok(match self.event.kind {
+            RowsEventKind::Write => RowChange {
+                before: None,
+                after: Some(first),
+            },
+            RowsEventKind::Delete => RowChange {
+                before: Some(first),
+                after: None,
+            },
+            RowsEventKind::Update => {
+                let (after, after_rest) =
+                    decode_row(columns, self.event.columns_present_after, self.rest)?;
+                self.rest = after_rest;
+                RowChange {
+                    before: Some(first),
+                    after: Some(after),
+                }
+            }
+        })
+    }
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = Result<RowChange<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let Some(table) = self.event.table else {
+            self.rest = &[];
+            return Some(Err(Error::BadUsageError(format!(
+                "binlog: no TABLE_MAP_EVENT for table id {}; start the dump at a transaction boundary",
+                self.event.table_id
+            ))));
+        };
+        let change = self.next_change(table);
+        if change.is_err() {
+            self.rest = &[];
+        }
+        Some(change)
+    }
+}
+
+/// A column value of a row event
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinlogValue<'a> {
+    /// The column is not in the row image (`binlog_row_image = MINIMAL` or `NOBLOB`)
+    Absent,
+    Null,
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    /// `DECIMAL` in its text form
+    Decimal(String),
+    /// String and binary types: `CHAR`, `VARCHAR`, `BINARY`, `BLOB`, `TEXT` and `GEOMETRY`
+    Bytes(&'a [u8]),
+    /// MySQL binary JSON
+    Json(&'a [u8]),
+    Bit(u64),
+    /// 1-based index of the `ENUM` value, 0 for the empty string
+    Enum(u16),
+    /// Bit `i` is set if the `i`-th `SET` member is included
+    Set(u64),
+    Year(u16),
+    Date {
+        year: u16,
+        month: u8,
+        day: u8,
+    },
+    DateTime {
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        micros: u32,
+    },
+    /// `TIMESTAMP` as seconds since the epoch
+    Timestamp {
+        seconds: u32,
+        micros: u32,
+    },
+    Time {
+        negative: bool,
+        hours: u32,
+        minutes: u8,
+        seconds: u8,
+        micros: u32,
+    },
+}
+
+fn is_set(bitmap: &[u8], i: usize) -> bool {
+    bitmap
+        .get(i / 8)
+        .is_some_and(|byte| byte & (1 << (i % 8)) != 0)
+}
+
+/// Decode a row image: a NULL bitmap of the present columns, then their non-NULL values
+fn decode_row<'a>(
+    columns: &[BinlogColumn],
+    present: &[u8],
+    data: &'a [u8],
+) -> Result<(Vec<BinlogValue<'a>>, &'a [u8])> {
+    let num_present = (0..columns.len()).filter(|&i| is_set(present, i)).count();
+    let (null_bitmap, mut rest) = read_string_fix(data, num_present.div_ceil(8))?;
+    let mut values = Vec::with_capacity(columns.len());
+    let mut present_index = 0;
+    for (i, column) in columns.iter().enumerate() {
+        if !is_set(present, i) {
+            values.push(BinlogValue::Absent);
+            continue;
+        }
+        let is_null = is_set(null_bitmap, present_index);
+        present_index += 1;
+        if is_null {
+            values.push(BinlogValue::Null);
+            continue;
+        }
+        let (value, after_value) = decode_value(column, rest)?;
+        values.push(value);
+        rest = after_value;
+    }
+    Ok((values, rest))
+}
+
+/// Big-endian unsigned integer of up to 8 bytes
+fn read_be(data: &[u8], len: usize) -> Result<(u64, &[u8])> {
+    let (bytes, rest) = read_string_fix(data, len)?;
+    let value = bytes
+        .iter()
+        .fold(0_u64, |acc, &byte| (acc << 8) | u64::from(byte));
+    Ok((value, rest))
+}
+
+/// Little-endian unsigned integer of up to 8 bytes
+fn read_le(data: &[u8], len: usize) -> Result<(u64, &[u8])> {
+    let (bytes, rest) = read_string_fix(data, len)?;
+    let value = bytes
+        .iter()
+        .rev()
+        .fold(0_u64, |acc, &byte| (acc << 8) | u64::from(byte));
+    Ok((value, rest))
+}
+
+/// Bytes prefixed by a little-endian length of `len_bytes` bytes
+fn read_length_prefixed(data: &[u8], len_bytes: usize) -> Result<(&[u8], &[u8])> {
+    let (len, rest) = read_le(data, len_bytes)?;
+    read_string_fix(rest, len as usize)
+}
+
+/// Fractional seconds of `TIMESTAMP2` and `DATETIME2` with `fsp` digits, in microseconds
+fn read_fraction(data: &[u8], fsp: u16) -> Result<(u32, &[u8])> {
+    let len = usize::from(fsp.div_ceil(2));
+    let (fraction, rest) = read_be(data, len)?;
+    Ok((fraction as u32 * 100_u32.pow(3 - len as u32), rest))
+}
+
+fn integer<'a>(value: u64, bits: u32, unsigned: bool) -> BinlogValue<'a> {
+    if unsigned {
+        BinlogValue::UInt(value)
+    } else {
+        // Sign-extend
+        let shift = 64 - bits;
+        BinlogValue::Int(((value << shift) as i64) >> shift)
+    }
+}
+
+fn decode_value<'a>(column: &BinlogColumn, data: &'a [u8]) -> Result<(BinlogValue<'a>, &'a [u8])> {
+    let meta = column.metadata;
+    match column.column_type {
+        ColumnType::MYSQL_TYPE_TINY => {
+            let (value, rest) = read_le(data, 1)?;
+            Ok((integer(value, 8, column.unsigned), rest))
+        }
+        ColumnType::MYSQL_TYPE_SHORT => {
+            let (value, rest) = read_le(data, 2)?;
+            Ok((integer(value, 16, column.unsigned), rest))
+        }
+        ColumnType::MYSQL_TYPE_INT24 => {
+            let (value, rest) = read_le(data, 3)?;
+            Ok((integer(value, 24, column.unsigned), rest))
+        }
+        ColumnType::MYSQL_TYPE_LONG => {
+            let (value, rest) = read_le(data, 4)?;
+            Ok((integer(value, 32, column.unsigned), rest))
+        }
+        ColumnType::MYSQL_TYPE_LONGLONG => {
+            let (value, rest) = read_le(data, 8)?;
+            Ok((integer(value, 64, column.unsigned), rest))
+        }
+        ColumnType::MYSQL_TYPE_YEAR => {
+            let (value, rest) = read_int_1(data)?;
+            let year = if value == 0 {
+                0
+            } else {
+                1900 + u16::from(value)
+            };
+            Ok((BinlogValue::Year(year), rest))
+        }
+        ColumnType::MYSQL_TYPE_FLOAT => {
+            let (bits, rest) = read_int_4(data)?;
+            Ok((BinlogValue::Float(f32::from_bits(bits)), rest))
+        }
+        ColumnType::MYSQL_TYPE_DOUBLE => {
+            let (bits, rest) = read_int_8(data)?;
+            Ok((BinlogValue::Double(f64::from_bits(bits)), rest))
+        }
+        ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+            let [precision, scale] = meta.to_le_bytes();
+            let (decimal, rest) = decode_decimal(data, precision, scale)?;
+            Ok((BinlogValue::Decimal(decimal), rest))
+        }
+        ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => {
+            let (value, rest) = read_int_3(data)?;
+            Ok((
+                BinlogValue::Date {
+                    year: (value >> 9) as u16,
+                    month: ((value >> 5) & 0x0F) as u8,
+                    day: (value & 0x1F) as u8,
+                },
+                rest,
+            ))
+        }
+        ColumnType::MYSQL_TYPE_TIMESTAMP => {
+            let (seconds, rest) = read_int_4(data)?;
+            Ok((BinlogValue::Timestamp { seconds, micros: 0 }, rest))
+        }
+        ColumnType::MYSQL_TYPE_TIMESTAMP2 => {
+            let (seconds, rest) = read_be(data, 4)?;
+            let (micros, rest) = read_fraction(rest, meta)?;
+            Ok((
+                BinlogValue::Timestamp {
+                    seconds: seconds as u32,
+                    micros,
+                },
+                rest,
+            ))
+        }
+        ColumnType::MYSQL_TYPE_DATETIME => {
+            // YYYYMMDDhhmmss as a decimal number
+            let (value, rest) = read_int_8(data)?;
+            let (date, time) = (value / 1_000_000, value % 1_000_000);
+            Ok((
+                BinlogValue::DateTime {
+                    year: (date / 10_000) as u16,
+                    month: (date / 100 % 100) as u8,
+                    day: (date % 100) as u8,
+                    hour: (time / 10_000) as u8,
+                    minute: (time / 100 % 100) as u8,
+                    second: (time % 100) as u8,
+                    micros: 0,
+                },
+                rest,
+            ))
+        }
+        ColumnType::MYSQL_TYPE_DATETIME2 => {
+            // [sign: 1][year * 13 + month: 17][day: 5][hour: 5][minute: 6][second: 6]
+            let (value, rest) = read_be(data, 5)?;
+            let (micros, rest) = read_fraction(rest, meta)?;
+            let value = value.wrapping_sub(0x80_0000_0000);
+            let (ymd, hms) = (value >> 17, value & 0x1FFFF);
+            let year_month = ymd >> 5;
+            Ok((
+                BinlogValue::DateTime {
+                    year: (year_month / 13) as u16,
+                    month: (year_month % 13) as u8,
+                    day: (ymd & 0x1F) as u8,
+                    hour: (hms >> 12) as u8,
+                    minute: ((hms >> 6) & 0x3F) as u8,
+                    second: (hms & 0x3F) as u8,
+                    micros,
+                },
+                rest,
+            ))
+        }
+        ColumnType::MYSQL_TYPE_TIME => {
+            // hhmmss as a signed decimal number
+            let (value, rest) = read_int_3(data)?;
+            let value = (i64::from(value) << 40) >> 40;
+            let abs = value.unsigned_abs();
+            Ok((
+                BinlogValue::Time {
+                    negative: value < 0,
+                    hours: (abs / 10_000) as u32,
+                    minutes: (abs / 100 % 100) as u8,
+                    seconds: (abs % 100) as u8,
+                    micros: 0,
+                },
+                rest,
+            ))
+        }
+        ColumnType::MYSQL_TYPE_TIME2 => decode_time2(data, meta),
+        ColumnType::MYSQL_TYPE_VARCHAR | ColumnType::MYSQL_TYPE_VAR_STRING => {
+            let len_bytes = if meta < 256 { 1 } else { 2 };
+            let (bytes, rest) = read_length_prefixed(data, len_bytes)?;
+            Ok((BinlogValue::Bytes(bytes), rest))
+        }
+        ColumnType::MYSQL_TYPE_STRING => {
+            let len_bytes = if meta < 256 { 1 } else { 2 };
+            let (bytes, rest) = read_length_prefixed(data, len_bytes)?;
+            Ok((BinlogValue::Bytes(bytes), rest))
+        }
+        ColumnType::MYSQL_TYPE_ENUM => {
+            let (value, rest) = read_le(data, usize::from(meta))?;
+            Ok((BinlogValue::Enum(value as u16), rest))
+        }
+        ColumnType::MYSQL_TYPE_SET => {
+            let (value, rest) = read_le(data, usize::from(meta))?;
+            Ok((BinlogValue::Set(value), rest))
+        }
+        ColumnType::MYSQL_TYPE_BIT => {
+            let [bits, bytes] = meta.to_le_bytes();
+            let len = usize::from(bytes) + usize::from(bits).div_ceil(8);
+            let (value, rest) = read_be(data, len)?;
+            Ok((BinlogValue::Bit(value), rest))
+        }
+        ColumnType::MYSQL_TYPE_BLOB
+        | ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_GEOMETRY => {
+            let (bytes, rest) = read_length_prefixed(data, usize::from(meta))?;
+            Ok((BinlogValue::Bytes(bytes), rest))
+        }
+        ColumnType::MYSQL_TYPE_JSON => {
+            let (bytes, rest) = read_length_prefixed(data, usize::from(meta))?;
+            Ok((BinlogValue::Json(bytes), rest))
+        }
+        column_type => Err(Error::Unsupported(format!(
+            "binlog: decoding {column_type:?} columns"
+        ))),
+    }
+}
+
+/// `TIME2` with `fsp` fractional digits.
+///
+/// The integer part and the fraction are stored as one signed big-endian number,
+/// except that the fraction of a negative time with 1 to 4 digits is stored separately.
+fn decode_time2(data: &[u8], fsp: u16) -> Result<(BinlogValue<'static>, &[u8])> {
+    let (int_part, rest) = read_be(data, 3)?;
+    let mut int_part = int_part as i64 - 0x80_0000;
+    let (packed, rest) = match fsp.div_ceil(2) {
+        0 => (int_part << 24, rest),
+        1 => {
+            let (fraction, rest) = read_int_1(rest)?;
+            let mut fraction = i64::from(fraction as i8);
+            if int_part < 0 && fraction != 0 {
+                int_part += 1;
+                fraction -= 0x100;
+            }
+            ((int_part << 24) + fraction * 10_000, rest)
+        }
+        2 => {
+            let (fraction, rest) = read_be(rest, 2)?;
+            let mut fraction = i64::from(fraction as u16 as i16);
+            if int_part < 0 && fraction != 0 {
+                int_part += 1;
+                fraction -= 0x10000;
+            }
+            ((int_part << 24) + fraction * 100, rest)
+        }
+        _ => {
+            let (fraction, rest) = read_be(rest, 3)?;
+            (
+                (((int_part + 0x80_0000) << 24) | fraction as i64) - 0x8000_0000_0000,
+                rest,
+            )
+        }
+    };
+    let abs = packed.unsigned_abs();
+    let hms = abs >> 24;
+    Ok((
+        BinlogValue::Time {
+            negative: packed < 0,
+            hours: ((hms >> 12) & 0x3FF) as u32,
+            minutes: ((hms >> 6) & 0x3F) as u8,
+            seconds: (hms & 0x3F) as u8,
+            micros: (abs & 0xFF_FFFF) as u32,
+        },
+        rest,
+    ))
+}
+
+/// Bytes used by a group of 0 to 9 decimal digits
+const DIG2BYTES: [usize; 10] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4];
+
+/// Decode a binary `DECIMAL(precision, scale)` to its text form.
+///
+/// The digits are stored big-endian in groups of 9 per 4 bytes, with the sign in the top bit
+/// and all bits inverted for negative numbers.
+fn decode_decimal(data: &[u8], precision: u8, scale: u8) -> Result<(String, &[u8])> {
+    let intg = usize::from(precision.saturating_sub(scale));
+    let scale = usize::from(scale);
+    let (intg0, intg0x) = (intg / 9, intg % 9);
+    let (frac0, frac0x) = (scale / 9, scale % 9);
+    let size = intg0 * 4 + DIG2BYTES[intg0x] + frac0 * 4 + DIG2BYTES[frac0x];
+    let (bytes, rest) = read_string_fix(data, size)?;
+
+    let mut buf = bytes.to_vec();
+    let Some(first) = buf.first_mut() else {
+        return Ok(("0".to_string(), rest));
+    };
+    let negative = *first & 0x80 == 0;
+    *first ^= 0x80;
+    if negative {
+        for byte in &mut buf {
+            *byte = !*byte;
+        }
+    }
+
+    let mut digits = buf.as_slice();
+    let mut group = |num_digits: usize, out: &mut String| -> Result<()> {
+        let (value, after) = read_be(digits, DIG2BYTES[num_digits])?;
+        digits = after;
+        let _ = write!(out, "{value:0num_digits$}");
+        Ok(())
+    };
+    let mut integer_part = String::new();
+    if intg0x > 0 {
+        group(intg0x, &mut integer_part)?;
+    }
+    for _ in 0..intg0 {
+        group(9, &mut integer_part)?;
+    }
+    let mut fraction_part = String::new();
+    for _ in 0..frac0 {
+        group(9, &mut fraction_part)?;
+    }
+    if frac0x > 0 {
+        group(frac0x, &mut fraction_part)?;
+    }
+
+    let integer_part = integer_part.trim_start_matches('0');
+    let mut out = String::with_capacity(integer_part.len() + fraction_part.len() + 3);
+    if negative {
+        out.push('-');
+    }
+    out.push_str(if integer_part.is_empty() {
+        "0"
+    } else {
+        integer_part
+    });
+    if !fraction_part.is_empty() {
+        out.push('.');
+        out.push_str(&fraction_part);
+    }
+    Ok((out, rest))
+}
+
+/// Parses binlog events, keeping the state that later events depend on:
+/// the checksum algorithm and the table maps.
+#[derive(Debug, Default)]
+pub struct BinlogParser {
+    checksum: bool,
+    tables: HashMap<u64, TableMap>,
+}
+
+impl BinlogParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A parser for a stream whose events end with a CRC32 before the first
+    /// `FORMAT_DESCRIPTION_EVENT`, as the rotate event at the start of a dump does.
+    pub fn with_checksum(checksum: bool) -> Self {
+        Self {
+            checksum,
+            ..Self::default()
+        }
+    }
+
+    /// The table map of `table_id`, if seen
+    pub fn table(&self, table_id: u64) -> Option<&TableMap> {
+        self.tables.get(&table_id)
+    }
+
+    /// Parse an event (the payload of a binlog packet without the leading 0x00).
+    pub fn parse<'a>(&'a mut self, event: &'a [u8]) -> Result<Event<'a>> {
+        let (header, body) = EventHeader::ref_from_prefix(event)
+            .map_err(|_cast| Error::LibraryBug(eyre!("binlog: event shorter than its header")))?;
+
+        let format_description = header.event_type == EventType::FORMAT_DESCRIPTION_EVENT as u8;
+        if format_description {
+            // The checksum algorithm precedes the checksum, which a format description
+            // event carries even if checksums are off
+            let algorithm = body
+                .len()
+                .checked_sub(5)
+                .and_then(|pos| body.get(pos))
+                .copied();
+            self.checksum = algorithm == Some(CHECKSUM_CRC32);
+        }
+        let body = if self.checksum || format_description {
+            body.get(..body.len().saturating_sub(4)).unwrap_or(body)
+        } else {
+            body
+        };
+
+        if header.event_type == EventType::TABLE_MAP_EVENT as u8 {
+            let table_map = TableMapEvent::parse(body)?.to_table_map()?;
+            self.tables.insert(table_map.table_id, table_map);
+        }
+        let this: &'a Self = self;
+        let data = this.parse_body(header, body)?;
+        Ok(Event { header, data })
+    }
+
+    fn parse_body<'a>(&'a self, header: &EventHeader, body: &'a [u8]) -> Result<EventData<'a>> {
+        let Some(event_type) = header.event_type() else {
+            return Ok(EventData::Other(body));
+        };
+        let rows = |kind, version_2| {
+            RowsEvent::parse(kind, version_2, body, &self.tables).map(EventData::Rows)
+        };
+        match event_type {
+            EventType::QUERY_EVENT => {
+                let (thread_id, rest) = read_int_4(body)?;
+                let (exec_time, rest) = read_int_4(rest)?;
+                let (schema_len, rest) = read_int_1(rest)?;
+                let (error_code, rest) = read_int_2(rest)?;
+                let (status_vars_len, rest) = read_int_2(rest)?;
+                let (status_vars, rest) = read_string_fix(rest, usize::from(status_vars_len))?;
+                let (schema, rest) = read_string_fix(rest, usize::from(schema_len))?;
+                let (_nul, query) = read_int_1(rest)?;
+                Ok(EventData::Query(QueryEvent {
+                    thread_id,
+                    exec_time,
+                    error_code,
+                    status_vars,
+                    schema,
+                    query,
+                }))
+            }
+            EventType::ROTATE_EVENT => {
+                let (position, next_file) = read_int_8(body)?;
+                Ok(EventData::Rotate(RotateEvent {
+                    position,
+                    next_file,
+                }))
+            }
+            EventType::FORMAT_DESCRIPTION_EVENT => {
+                let (binlog_version, rest) = read_int_2(body)?;
+                let (server_version, rest) = read_string_fix(rest, 50)?;
+                let (create_timestamp, rest) = read_int_4(rest)?;
+                let (header_length, rest) = read_int_1(rest)?;
+                // The checksum was already stripped
+                let checksum_algorithm = rest.last().copied().unwrap_or(0);
+                let version_len = server_version
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .unwrap_or(server_version.len());
+                Ok(EventData::FormatDescription(FormatDescriptionEvent {
+                    binlog_version,
+                    server_version: server_version.get(..version_len).unwrap_or_default(),
+                    create_timestamp,
+                    header_length,
+                    checksum_algorithm,
+                }))
+            }
+            EventType::TABLE_MAP_EVENT => Ok(EventData::TableMap(TableMapEvent::parse(body)?)),
+            EventType::WRITE_ROWS_EVENT_V1 => rows(RowsEventKind::Write, false),
+            EventType::UPDATE_ROWS_EVENT_V1 => rows(RowsEventKind::Update, false),
+            EventType::DELETE_ROWS_EVENT_V1 => rows(RowsEventKind::Delete, false),
+            EventType::WRITE_ROWS_EVENT => rows(RowsEventKind::Write, true),
+            EventType::UPDATE_ROWS_EVENT => rows(RowsEventKind::Update, true),
+            EventType::DELETE_ROWS_EVENT => rows(RowsEventKind::Delete, true),
+            EventType::GTID_LOG_EVENT | EventType::ANONYMOUS_GTID_LOG_EVENT => {
+                let (flags, rest) = read_int_1(body)?;
+                let (sid, rest) = read_string_fix(rest, 16)?;
+                let (gno, _) = read_int_8(rest)?;
+                let mut gtid = GtidEvent {
+                    flags,
+                    sid: [0; 16],
+                    gno,
+                };
+                gtid.sid.copy_from_slice(sid);
+                if event_type == EventType::GTID_LOG_EVENT {
+                    Ok(EventData::Gtid(gtid))
+                } else {
+                    Ok(EventData::AnonymousGtid(gtid))
+                }
+            }
+            EventType::XID_EVENT => {
+                let (xid, _) = read_int_8(body)?;
+                Ok(EventData::Xid(xid))
+            }
+            _ => Ok(EventData::Other(body)),
+        }
+    }
+}
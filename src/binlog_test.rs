@@ -0,0 +1,501 @@
+use crate::binlog::{
+    BinlogParser, BinlogPosition, BinlogRequest, BinlogValue, EVENT_HEADER_LENGTH, EventData,
+    EventType, GtidSet, PositionTracker, RowChange, RowsEvent, RowsEventKind,
+};
+use crate::constant::ColumnType;
+use crate::error::Error;
+use crate::protocol::command::binlog::{
+    BINLOG_DUMP_NON_BLOCK, BINLOG_THROUGH_GTID, write_binlog_dump, write_binlog_dump_gtid,
+    write_register_replica,
+};
+use crate::test_macros::{check, check_eq};
+
+const TABLE_ID: u64 = 42;
+
+fn event(event_type: EventType, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&1_700_000_000_u32.to_le_bytes());
+    out.push(event_type as u8);
+    out.extend_from_slice(&1_u32.to_le_bytes());
+    out.extend_from_slice(&((EVENT_HEADER_LENGTH + body.len()) as u32).to_le_bytes());
+    out.extend_from_slice(&1000_u32.to_le_bytes());
+    out.extend_from_slice(&0_u16.to_le_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+fn format_description(checksum_algorithm: u8) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&4_u16.to_le_bytes());
+    let mut server_version = [0_u8; 50];
+    server_version[..6].copy_from_slice(b"8.0.36");
+    body.extend_from_slice(&server_version);
+    body.extend_from_slice(&0_u32.to_le_bytes());
+    body.push(EVENT_HEADER_LENGTH as u8);
+    // post-header lengths
+    body.extend_from_slice(&[0, 13, 0, 8, 0, 0, 0, 0]);
+    body.push(checksum_algorithm);
+    body.extend_from_slice(&[0xAA; 4]);
+    event(EventType::FORMAT_DESCRIPTION_EVENT, &body)
+}
+
+/// `(id INT, name VARCHAR(50), amount DECIMAL(10,2), counter BIGINT UNSIGNED NULL)`
+fn table_map() -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&TABLE_ID.to_le_bytes()[..6]);
+    body.extend_from_slice(&1_u16.to_le_bytes());
+    body.extend_from_slice(b"\x04test\x00");
+    body.extend_from_slice(b"\x05items\x00");
+    body.push(4);
+    body.extend_from_slice(&[
+        ColumnType::MYSQL_TYPE_LONG as u8,
+        ColumnType::MYSQL_TYPE_VARCHAR as u8,
+        ColumnType::MYSQL_TYPE_NEWDECIMAL as u8,
+        ColumnType::MYSQL_TYPE_LONGLONG as u8,
+    ]);
+    // VARCHAR(50) in utf8mb4: 200 bytes; DECIMAL(10,2)
+    body.extend_from_slice(&[4, 200, 0, 10, 2]);
+    // only `counter` is nullable
+    body.push(0b1000);
+    // SIGNEDNESS: numeric columns are id, amount and counter; counter is unsigned
+    body.extend_from_slice(&[1, 1, 0b0010_0000]);
+    event(EventType::TABLE_MAP_EVENT, &body)
+}
+
+/// An image of all 4 columns
+fn row(id: i32, name: &str, amount: &[u8], counter: Option<u64>) -> Vec<u8> {
+    let mut out = vec![if counter.is_some() { 0 } else { 0b1000 }];
+    out.extend_from_slice(&id.to_le_bytes());
+    out.push(name.len() as u8);
+    out.extend_from_slice(name.as_bytes());
+    out.extend_from_slice(amount);
+    if let Some(counter) = counter {
+        out.extend_from_slice(&counter.to_le_bytes());
+    }
+    out
+}
+
+fn rows_event(event_type: EventType, rows: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&TABLE_ID.to_le_bytes()[..6]);
+    body.extend_from_slice(&0_u16.to_le_bytes());
+    // extra data length, including itself
+    body.extend_from_slice(&2_u16.to_le_bytes());
+    body.push(4);
+    body.push(0b1111);
+    if event_type == EventType::UPDATE_ROWS_EVENT {
+        body.push(0b1111);
+    }
+    body.extend_from_slice(rows);
+    event(event_type, &body)
+}
+
+/// 1234.56 and -1234.56 as DECIMAL(10,2)
+const POSITIVE: [u8; 5] = [0x80, 0x00, 0x04, 0xD2, 0x38];
+const NEGATIVE: [u8; 5] = [0x7F, 0xFF, 0xFB, 0x2D, 0xC7];
+
+fn rows(event: EventData<'_>) -> crate::error::Result<RowsEvent<'_>> {
+    match event {
+        EventData::Rows(rows) => Ok(rows),
+        _ => Err(Error::LibraryBug(crate::error::eyre!("not a rows event"))),
+    }
+}
+
+#[test]
+fn query_event() -> crate::error::Result<()> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&7_u32.to_le_bytes());
+    body.extend_from_slice(&0_u32.to_le_bytes());
+    body.push(4);
+    body.extend_from_slice(&0_u16.to_le_bytes());
+    body.extend_from_slice(&2_u16.to_le_bytes());
+    body.extend_from_slice(&[0xFF, 0xFF]);
+    body.extend_from_slice(b"test\x00BEGIN");
+
+    let mut parser = BinlogParser::new();
+    let bytes = event(EventType::QUERY_EVENT, &body);
+    let event = parser.parse(&bytes)?;
+    check_eq!(event.header.event_type(), Some(EventType::QUERY_EVENT));
+    check_eq!(event.header.timestamp(), 1_700_000_000);
+    check_eq!(event.header.log_pos(), 1000);
+    let EventData::Query(query) = event.data else {
+        return Err(Error::LibraryBug(crate::error::eyre!("not a query event")));
+    };
+    check_eq!(query.thread_id, 7);
+    check_eq!(query.status_vars, [0xFF, 0xFF]);
+    check_eq!(query.schema, b"test");
+    check_eq!(query.query, b"BEGIN");
+    Ok(())
+}
+
+#[test]
+fn rotate_event_with_checksum() -> crate::error::Result<()> {
+    let mut body = 4_u64.to_le_bytes().to_vec();
+    body.extend_from_slice(b"binlog.000002");
+    body.extend_from_slice(&[0xAA; 4]);
+
+    let mut parser = BinlogParser::with_checksum(true);
+    let bytes = event(EventType::ROTATE_EVENT, &body);
+    let EventData::Rotate(rotate) = parser.parse(&bytes)?.data else {
+        return Err(Error::LibraryBug(crate::error::eyre!("not a rotate event")));
+    };
+    check_eq!(rotate.position, 4);
+    check_eq!(rotate.next_file, b"binlog.000002");
+    Ok(())
+}
+
+#[test]
+fn format_description_sets_checksum() -> crate::error::Result<()> {
+    let mut parser = BinlogParser::new();
+    let fde = format_description(1);
+    let EventData::FormatDescription(description) = parser.parse(&fde)?.data else {
+        return Err(Error::LibraryBug(crate::error::eyre!(
+            "not a format description"
+        )));
+    };
+    check_eq!(description.binlog_version, 4);
+    check_eq!(description.server_version, b"8.0.36");
+    check_eq!(description.checksum_algorithm, 1);
+
+    let mut body = 99_u64.to_le_bytes().to_vec();
+    body.extend_from_slice(&[0xAA; 4]);
+    let xid = event(EventType::XID_EVENT, &body);
+    check!(matches!(parser.parse(&xid)?.data, EventData::Xid(99)));
+
+    // A binlog without checksums turns them off again
+    let fde_without_checksum = format_description(0);
+    parser.parse(&fde_without_checksum)?;
+    let xid_without_checksum = event(EventType::XID_EVENT, &100_u64.to_le_bytes());
+    check!(matches!(
+        parser.parse(&xid_without_checksum)?.data,
+        EventData::Xid(100)
+    ));
+    Ok(())
+}
+
+#[test]
+fn gtid_event() -> crate::error::Result<()> {
+    let mut body = vec![1];
+    body.extend_from_slice(&[
+        0x3E, 0x11, 0xFA, 0x47, 0x71, 0xCA, 0x11, 0xE1, 0x9E, 0x33, 0xC8, 0x0A, 0xA9, 0x42, 0x95,
+        0x62,
+    ]);
+    body.extend_from_slice(&23_u64.to_le_bytes());
+    let mut parser = BinlogParser::new();
+    let bytes = event(EventType::GTID_LOG_EVENT, &body);
+    let EventData::Gtid(gtid) = parser.parse(&bytes)?.data else {
+        return Err(Error::LibraryBug(crate::error::eyre!("not a GTID event")));
+    };
+    check_eq!(gtid.gtid(), "3e11fa47-71ca-11e1-9e33-c80aa9429562:23");
+    Ok(())
+}
+
+const SID: [u8; 16] = [
+    0x3E, 0x11, 0xFA, 0x47, 0x71, 0xCA, 0x11, 0xE1, 0x9E, 0x33, 0xC8, 0x0A, 0xA9, 0x42, 0x95, 0x62,
+];
+
+fn gtid_body(gno: u64) -> Vec<u8> {
+    let mut body = vec![1];
+    body.extend_from_slice(&SID);
+    body.extend_from_slice(&gno.to_le_bytes());
+    body
+}
+
+#[test]
+fn anonymous_gtid_event() -> crate::error::Result<()> {
+    let mut body = vec![1];
+    body.extend_from_slice(&[0; 16]);
+    body.extend_from_slice(&0_u64.to_le_bytes());
+    let mut parser = BinlogParser::new();
+    let bytes = event(EventType::ANONYMOUS_GTID_LOG_EVENT, &body);
+    check!(matches!(
+        parser.parse(&bytes)?.data,
+        EventData::AnonymousGtid(gtid) if gtid.sid == [0; 16]
+    ));
+    Ok(())
+}
+
+#[test]
+fn gtid_set_text_form() -> crate::error::Result<()> {
+    let text = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7,\n\
+                00000000-0000-0000-0000-000000000001:3";
+    let mut set = GtidSet::parse(text)?;
+    check_eq!(
+        set.to_string(),
+        "00000000-0000-0000-0000-000000000001:3,3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7"
+    );
+    check!(set.contains(&SID, 5));
+    check!(!set.contains(&SID, 6));
+
+    set.add(SID, 6);
+    set.add(SID, 9);
+    check_eq!(
+        set.to_string(),
+        "00000000-0000-0000-0000-000000000001:3,3e11fa47-71ca-11e1-9e33-c80aa9429562:1-7:9"
+    );
+
+    check!(GtidSet::parse("")?.is_empty());
+    check!(GtidSet::parse("3e11fa47:1").is_err());
+    check!(GtidSet::parse("3e11fa47-71ca-11e1-9e33-c80aa9429562:5-1").is_err());
+    check!(GtidSet::parse("3e11fa47-71ca-11e1-9e33-c80aa9429562:0").is_err());
+    Ok(())
+}
+
+#[test]
+fn gtid_set_encoding() -> crate::error::Result<()> {
+    let set = GtidSet::parse("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:7")?;
+    let mut out = Vec::new();
+    set.write_encoded(&mut out);
+    let mut expected = 1_u64.to_le_bytes().to_vec();
+    expected.extend_from_slice(&SID);
+    for value in [2_u64, 1, 6, 7, 8] {
+        expected.extend_from_slice(&value.to_le_bytes());
+    }
+    check_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn position_advances_per_transaction() -> crate::error::Result<()> {
+    let mut request = BinlogRequest::gtid(1001, "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-22");
+    request.file = "binlog.000001".to_string();
+    let mut tracker = PositionTracker::new(&request)?;
+    let mut parser = BinlogParser::new();
+
+    let mut rotate = 4_u64.to_le_bytes().to_vec();
+    rotate.extend_from_slice(b"binlog.000002");
+    let rotate_event = event(EventType::ROTATE_EVENT, &rotate);
+    tracker.update(&parser.parse(&rotate_event)?);
+    check_eq!(
+        *tracker.position(),
+        BinlogPosition {
+            file: "binlog.000002".to_string(),
+            position: 4,
+            gtid_set: Some("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-22".to_string()),
+        }
+    );
+
+    // The transaction is in progress until its XID event
+    let gtid = event(EventType::GTID_LOG_EVENT, &gtid_body(23));
+    tracker.update(&parser.parse(&gtid)?);
+    let table_map_event = table_map();
+    tracker.update(&parser.parse(&table_map_event)?);
+    check_eq!(tracker.position().position, 4);
+
+    let xid = event(EventType::XID_EVENT, &7_u64.to_le_bytes());
+    tracker.update(&parser.parse(&xid)?);
+    check_eq!(tracker.position().position, 1000);
+    check_eq!(
+        tracker.position().gtid_set.as_deref(),
+        Some("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-23")
+    );
+
+    let from = BinlogRequest::from_position(1001, tracker.position());
+    check_eq!(from.file, "binlog.000002");
+    check_eq!(from.position, 1000);
+    check_eq!(from.gtid_set, tracker.position().gtid_set);
+    Ok(())
+}
+
+#[test]
+fn table_map_event() -> crate::error::Result<()> {
+    let mut parser = BinlogParser::new();
+    let bytes = table_map();
+    let EventData::TableMap(event) = parser.parse(&bytes)?.data else {
+        return Err(Error::LibraryBug(crate::error::eyre!("not a table map")));
+    };
+    check_eq!(event.table_id, TABLE_ID);
+    check_eq!(event.schema, b"test");
+    check_eq!(event.table, b"items");
+
+    let table = parser
+        .table(TABLE_ID)
+        .ok_or_else(|| Error::LibraryBug(crate::error::eyre!("table map not kept")))?;
+    check_eq!(table.table, "items");
+    check_eq!(table.columns.len(), 4);
+    check_eq!(table.columns[1].metadata, 200);
+    check!(!table.columns[0].unsigned);
+    check!(!table.columns[2].unsigned);
+    check!(table.columns[3].unsigned);
+    check!(table.columns[3].nullable);
+    check!(!table.columns[0].nullable);
+    Ok(())
+}
+
+#[test]
+fn write_and_delete_rows() -> crate::error::Result<()> {
+    let mut parser = BinlogParser::new();
+    parser.parse(&table_map())?;
+
+    let mut images = row(-1, "ab", &POSITIVE, None);
+    images.extend(row(2, "", &NEGATIVE, Some(u64::MAX)));
+    let bytes = rows_event(EventType::WRITE_ROWS_EVENT, &images);
+    let event = rows(parser.parse(&bytes)?.data)?;
+    check_eq!(event.kind, RowsEventKind::Write);
+    let inserted = event.rows().collect::<crate::error::Result<Vec<_>>>()?;
+    check_eq!(
+        inserted,
+        vec![
+            RowChange {
+                before: None,
+                after: Some(vec![
+                    BinlogValue::Int(-1),
+                    BinlogValue::Bytes(b"ab"),
+                    BinlogValue::Decimal("1234.56".to_string()),
+                    BinlogValue::Null,
+                ]),
+            },
+            RowChange {
+                before: None,
+                after: Some(vec![
+                    BinlogValue::Int(2),
+                    BinlogValue::Bytes(b""),
+                    BinlogValue::Decimal("-1234.56".to_string()),
+                    BinlogValue::UInt(u64::MAX),
+                ]),
+            },
+        ]
+    );
+
+    let image = row(3, "x", &POSITIVE, Some(5));
+    let delete_bytes = rows_event(EventType::DELETE_ROWS_EVENT, &image);
+    let deleted = rows(parser.parse(&delete_bytes)?.data)?
+        .rows()
+        .collect::<crate::error::Result<Vec<_>>>()?;
+    check_eq!(deleted.len(), 1);
+    check!(deleted[0].after.is_none());
+    check_eq!(
+        deleted[0].before.as_ref().map(|values| values[0].clone()),
+        Some(BinlogValue::Int(3))
+    );
+    Ok(())
+}
+
+#[test]
+fn update_rows() -> crate::error::Result<()> {
+    let mut parser = BinlogParser::new();
+    parser.parse(&table_map())?;
+
+    let mut images = row(1, "old", &POSITIVE, Some(1));
+    images.extend(row(1, "new", &POSITIVE, Some(2)));
+    let bytes = rows_event(EventType::UPDATE_ROWS_EVENT, &images);
+    let event = rows(parser.parse(&bytes)?.data)?;
+    check_eq!(event.kind, RowsEventKind::Update);
+    let updated = event.rows().collect::<crate::error::Result<Vec<_>>>()?;
+    check_eq!(updated.len(), 1);
+    check_eq!(
+        updated[0].before.as_ref().map(|values| values[1].clone()),
+        Some(BinlogValue::Bytes(b"old"))
+    );
+    check_eq!(
+        updated[0].after.as_ref().map(|values| values[3].clone()),
+        Some(BinlogValue::UInt(2))
+    );
+    Ok(())
+}
+
+#[test]
+fn rows_without_table_map() -> crate::error::Result<()> {
+    let mut parser = BinlogParser::new();
+    let image = row(1, "a", &POSITIVE, None);
+    let bytes = rows_event(EventType::WRITE_ROWS_EVENT, &image);
+    let event = rows(parser.parse(&bytes)?.data)?;
+    check!(event.table.is_none());
+    let mut iter = event.rows();
+    check!(matches!(iter.next(), Some(Err(Error::BadUsageError(_)))));
+    check!(iter.next().is_none());
+    Ok(())
+}
+
+/// `(created DATETIME(0), at TIME(0))`
+#[test]
+fn temporal_columns() -> crate::error::Result<()> {
+    let mut map_body = Vec::new();
+    map_body.extend_from_slice(&7_u64.to_le_bytes()[..6]);
+    map_body.extend_from_slice(&0_u16.to_le_bytes());
+    map_body.extend_from_slice(b"\x01t\x00\x01t\x00");
+    map_body.push(2);
+    map_body.extend_from_slice(&[
+        ColumnType::MYSQL_TYPE_DATETIME2 as u8,
+        ColumnType::MYSQL_TYPE_TIME2 as u8,
+    ]);
+    map_body.extend_from_slice(&[2, 0, 0]);
+    map_body.push(0);
+    let mut parser = BinlogParser::new();
+    parser.parse(&event(EventType::TABLE_MAP_EVENT, &map_body))?;
+
+    // 2024-05-06 07:08:09
+    let year_month: u64 = 2024 * 13 + 5;
+    let ymd = (year_month << 5) | 6;
+    let hms: u64 = (7 << 12) | (8 << 6) | 9;
+    let datetime = ((ymd << 17) | hms) + 0x80_0000_0000;
+    // 12:34:56
+    let time: u64 = ((12 << 12) | (34 << 6) | 56) + 0x80_0000;
+
+    let mut rows_body = Vec::new();
+    rows_body.extend_from_slice(&7_u64.to_le_bytes()[..6]);
+    rows_body.extend_from_slice(&0_u16.to_le_bytes());
+    rows_body.push(2);
+    rows_body.push(0b11);
+    rows_body.push(0);
+    rows_body.extend_from_slice(&datetime.to_be_bytes()[3..]);
+    rows_body.extend_from_slice(&time.to_be_bytes()[5..]);
+    let bytes = event(EventType::WRITE_ROWS_EVENT_V1, &rows_body);
+    let event = rows(parser.parse(&bytes)?.data)?;
+    let mut iter = event.rows();
+    let change = iter
+        .next()
+        .ok_or_else(|| Error::LibraryBug(crate::error::eyre!("no row")))??;
+    check_eq!(
+        change.after,
+        Some(vec![
+            BinlogValue::DateTime {
+                year: 2024,
+                month: 5,
+                day: 6,
+                hour: 7,
+                minute: 8,
+                second: 9,
+                micros: 0,
+            },
+            BinlogValue::Time {
+                negative: false,
+                hours: 12,
+                minutes: 34,
+                seconds: 56,
+                micros: 0,
+            },
+        ])
+    );
+    check!(iter.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn dump_commands() -> crate::error::Result<()> {
+    let mut out = Vec::new();
+    write_binlog_dump(&mut out, 1001, BINLOG_DUMP_NON_BLOCK, "binlog.000001", 4);
+    let mut expected = vec![0x12, 4, 0, 0, 0, 1, 0, 0xE9, 0x03, 0, 0];
+    expected.extend_from_slice(b"binlog.000001");
+    check_eq!(out, expected);
+
+    out.clear();
+    write_binlog_dump_gtid(&mut out, 1001, BINLOG_THROUGH_GTID, "", 4, &[1, 2]);
+    check_eq!(
+        out,
+        [
+            0x1E, 4, 0, 0xE9, 0x03, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 2
+        ]
+    );
+
+    out.clear();
+    write_register_replica(&mut out, 1001, "host", "", "", 3306);
+    check_eq!(
+        out,
+        [
+            0x15, 0xE9, 0x03, 0, 0, 4, b'h', b'o', b's', b't', 0, 0, 0xEA, 0x0C, 0, 0, 0, 0, 0, 0,
+            0, 0
+        ]
+    );
+    Ok(())
+}
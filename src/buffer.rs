@@ -1,3 +1,7 @@
+use crate::arena::ScratchArena;
+use crate::column_names::ColumnNames;
+use crate::protocol::command::ColumnDefinitions;
+
 /// A set of reusable buffers for MySQL protocol communication
 ///
 /// `Conn` uses a single `BufferSet` for all its operations.
@@ -20,6 +24,17 @@ pub struct BufferSet {
     /// ColumnDefinition packets in one buffer
     /// Bytes are valid during an operation.
     pub column_definition_buffer: Vec<u8>,
+
+    /// Scratch arena for owned values decoded from binary rows, enabled by `Opts::scratch_arena`
+    /// Values are valid until the next result set starts.
+    pub arena: Option<ScratchArena>,
+
+    /// Cache of interned column names, enabled by `Opts::intern_column_names`
+    pub column_names: Option<ColumnNames>,
+
+    /// `CLIENT_DEPRECATE_EOF` was not negotiated, so the server sends an EOF packet
+    /// after column definitions and ends result sets with an EOF packet instead of OK
+    pub legacy_eof: bool,
 }
 
 impl BufferSet {
@@ -30,6 +45,9 @@ impl BufferSet {
             read_buffer: Vec::new(),
             write_buffer: vec![0; 4],
             column_definition_buffer: Vec::new(),
+            arena: None,
+            column_names: None,
+            legacy_eof: false,
         }
     }
 
@@ -40,6 +58,9 @@ impl BufferSet {
             read_buffer: Vec::new(),
             write_buffer: vec![0; 4],
             column_definition_buffer: Vec::new(),
+            arena: None,
+            column_names: None,
+            legacy_eof: false,
         }
     }
 
@@ -63,6 +84,40 @@ impl BufferSet {
         &self.write_buffer
     }
 
+    /// Create or drop the scratch arena.
+    pub fn set_scratch_arena(&mut self, enabled: bool) {
+        if enabled {
+            self.arena.get_or_insert_with(ScratchArena::new);
+        } else {
+            self.arena = None;
+        }
+    }
+
+    /// Create or drop the column name cache.
+    pub fn set_intern_column_names(&mut self, enabled: bool) {
+        if enabled {
+            self.column_names.get_or_insert_with(ColumnNames::new);
+        } else {
+            self.column_names = None;
+        }
+    }
+
+    /// Intern the column names of `column_defs` if the column name cache is enabled.
+    #[inline]
+    pub fn intern_columns(&mut self, column_defs: &mut ColumnDefinitions) {
+        if let Some(names) = &mut self.column_names {
+            column_defs.intern_names(names);
+        }
+    }
+
+    /// Free all values in the scratch arena. Called at the start of every result set.
+    #[inline]
+    pub fn reset_arena(&mut self) {
+        if let Some(arena) = &mut self.arena {
+            arena.reset();
+        }
+    }
+
     /// Get the payload length (total buffer length minus 4-byte header).
     #[inline]
     pub fn payload_len(&self) -> usize {
@@ -109,6 +109,7 @@ impl BufferPool {
         buffer_set.initial_handshake.clear();
         buffer_set.read_buffer.clear();
         buffer_set.column_definition_buffer.clear();
+        buffer_set.reset_arena();
         // write_buffer is handled by new_write_buffer()
 
         // Ignore if pool is full
@@ -0,0 +1,423 @@
+//! Character sets and collations.
+//!
+//! Column definitions carry a collation id, not a charset name. [`Charset::from_collation`]
+//! maps it to the character set, and [`Charset::decode`] turns text in that character set
+//! into a `str`. UTF-8 and ASCII text is borrowed as-is. Other character sets are transcoded
+//! with the `encoding_rs` feature; without it, they are decoded as UTF-8, which only works for
+//! ASCII text.
+//!
+//! [`Collation`] selects the connection collation with `Opts::charset` and `Opts::collation`.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use simdutf8::basic::from_utf8;
+
+use crate::error::{Error, Result};
+
+/// A MySQL character set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Charset {
+    Utf8mb4,
+    Utf8mb3,
+    Ascii,
+    Binary,
+    /// MySQL `latin1` is Windows-1252, not ISO 8859-1
+    Latin1,
+    Latin2,
+    Latin5,
+    Latin7,
+    Cp1250,
+    Cp1251,
+    Cp1256,
+    Cp1257,
+    Cp866,
+    Koi8r,
+    Koi8u,
+    Greek,
+    Hebrew,
+    Tis620,
+    Macroman,
+    Big5,
+    Gb2312,
+    Gbk,
+    Gb18030,
+    Sjis,
+    Cp932,
+    Ujis,
+    Eucjpms,
+    Euckr,
+    Ucs2,
+    Utf16,
+    Utf16le,
+    Utf32,
+    /// A collation id this crate does not know, e.g. a MariaDB-specific one. Decoded as UTF-8.
+    Unknown(u16),
+}
+
+impl Charset {
+    /// The character set of the collation `id` in a column definition
+    pub fn from_collation(id: u16) -> Self {
+        match id {
+            45 | 46 | 224..=247 | 255..=323 => Self::Utf8mb4,
+            33 | 76 | 83 | 192..=215 | 223 => Self::Utf8mb3,
+            11 | 65 => Self::Ascii,
+            63 => Self::Binary,
+            5 | 8 | 15 | 31 | 47 | 48 | 49 | 94 => Self::Latin1,
+            2 | 9 | 21 | 27 | 77 => Self::Latin2,
+            30 | 78 => Self::Latin5,
+            20 | 41 | 42 | 79 => Self::Latin7,
+            26 | 34 | 44 | 66 | 99 => Self::Cp1250,
+            14 | 23 | 50 | 51 | 52 => Self::Cp1251,
+            57 | 67 => Self::Cp1256,
+            29 | 58 | 59 => Self::Cp1257,
+            36 | 68 => Self::Cp866,
+            7 | 74 => Self::Koi8r,
+            22 | 75 => Self::Koi8u,
+            25 | 70 => Self::Greek,
+            16 | 71 => Self::Hebrew,
+            18 | 89 => Self::Tis620,
+            39 | 53 => Self::Macroman,
+            1 | 84 => Self::Big5,
+            24 | 86 => Self::Gb2312,
+            28 | 87 => Self::Gbk,
+            248..=250 => Self::Gb18030,
+            13 | 88 => Self::Sjis,
+            95 | 96 => Self::Cp932,
+            12 | 91 => Self::Ujis,
+            97 | 98 => Self::Eucjpms,
+            19 | 85 => Self::Euckr,
+            35 | 90 | 128..=151 | 159 => Self::Ucs2,
+            54 | 55 | 101..=124 => Self::Utf16,
+            56 | 62 => Self::Utf16le,
+            60 | 61 | 160..=183 => Self::Utf32,
+            _ => Self::Unknown(id),
+        }
+    }
+
+    /// The character set named `name`, e.g. `"latin1"`. `"utf8"` is `utf8mb3`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "utf8mb4" => Self::Utf8mb4,
+            "utf8mb3" | "utf8" => Self::Utf8mb3,
+            "ascii" => Self::Ascii,
+            "binary" => Self::Binary,
+            "latin1" => Self::Latin1,
+            "latin2" => Self::Latin2,
+            "latin5" => Self::Latin5,
+            "latin7" => Self::Latin7,
+            "cp1250" => Self::Cp1250,
+            "cp1251" => Self::Cp1251,
+            "cp1256" => Self::Cp1256,
+            "cp1257" => Self::Cp1257,
+            "cp866" => Self::Cp866,
+            "koi8r" => Self::Koi8r,
+            "koi8u" => Self::Koi8u,
+            "greek" => Self::Greek,
+            "hebrew" => Self::Hebrew,
+            "tis620" => Self::Tis620,
+            "macroman" => Self::Macroman,
+            "big5" => Self::Big5,
+            "gb2312" => Self::Gb2312,
+            "gbk" => Self::Gbk,
+            "gb18030" => Self::Gb18030,
+            "sjis" => Self::Sjis,
+            "cp932" => Self::Cp932,
+            "ujis" => Self::Ujis,
+            "eucjpms" => Self::Eucjpms,
+            "euckr" => Self::Euckr,
+            "ucs2" => Self::Ucs2,
+            "utf16" => Self::Utf16,
+            "utf16le" => Self::Utf16le,
+            "utf32" => Self::Utf32,
+            _ => return None,
+        })
+    }
+
+    /// The default collation of the character set, or `None` for `Unknown`.
+    ///
+    /// `utf8mb4` defaults to `utf8mb4_general_ci`, which MySQL and MariaDB both support.
+    pub fn default_collation(self) -> Option<Collation> {
+        Some(Collation(match self {
+            Self::Utf8mb4 => 45,
+            Self::Utf8mb3 => 33,
+            Self::Ascii => 11,
+            Self::Binary => 63,
+            Self::Latin1 => 8,
+            Self::Latin2 => 9,
+            Self::Latin5 => 30,
+            Self::Latin7 => 41,
+            Self::Cp1250 => 26,
+            Self::Cp1251 => 51,
+            Self::Cp1256 => 57,
+            Self::Cp1257 => 59,
+            Self::Cp866 => 36,
+            Self::Koi8r => 7,
+            Self::Koi8u => 22,
+            Self::Greek => 25,
+            Self::Hebrew => 16,
+            Self::Tis620 => 18,
+            Self::Macroman => 39,
+            Self::Big5 => 1,
+            Self::Gb2312 => 24,
+            Self::Gbk => 28,
+            Self::Gb18030 => 248,
+            Self::Sjis => 13,
+            Self::Cp932 => 95,
+            Self::Ujis => 12,
+            Self::Eucjpms => 97,
+            Self::Euckr => 19,
+            Self::Ucs2 => 35,
+            Self::Utf16 => 54,
+            Self::Utf16le => 56,
+            Self::Utf32 => 60,
+            Self::Unknown(_) => return None,
+        }))
+    }
+
+    /// The server rejects `ucs2`, `utf16`, `utf16le` and `utf32` as the connection character set
+    pub fn is_client_charset(self) -> bool {
+        !matches!(self, Self::Ucs2 | Self::Utf16 | Self::Utf16le | Self::Utf32)
+    }
+
+    /// A multibyte character can end in `0x5C` (`\`), so a backslash escape can be read as the
+    /// trail byte of the character before it and no longer escapes anything
+    pub fn escape_with_backslash_is_dangerous(self) -> bool {
+        matches!(
+            self,
+            Self::Big5 | Self::Gbk | Self::Gb18030 | Self::Sjis | Self::Cp932
+        )
+    }
+
+    /// The MySQL name of the character set, e.g. `"latin1"`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8mb4 => "utf8mb4",
+            Self::Utf8mb3 => "utf8mb3",
+            Self::Ascii => "ascii",
+            Self::Binary => "binary",
+            Self::Latin1 => "latin1",
+            Self::Latin2 => "latin2",
+            Self::Latin5 => "latin5",
+            Self::Latin7 => "latin7",
+            Self::Cp1250 => "cp1250",
+            Self::Cp1251 => "cp1251",
+            Self::Cp1256 => "cp1256",
+            Self::Cp1257 => "cp1257",
+            Self::Cp866 => "cp866",
+            Self::Koi8r => "koi8r",
+            Self::Koi8u => "koi8u",
+            Self::Greek => "greek",
+            Self::Hebrew => "hebrew",
+            Self::Tis620 => "tis620",
+            Self::Macroman => "macroman",
+            Self::Big5 => "big5",
+            Self::Gb2312 => "gb2312",
+            Self::Gbk => "gbk",
+            Self::Gb18030 => "gb18030",
+            Self::Sjis => "sjis",
+            Self::Cp932 => "cp932",
+            Self::Ujis => "ujis",
+            Self::Eucjpms => "eucjpms",
+            Self::Euckr => "euckr",
+            Self::Ucs2 => "ucs2",
+            Self::Utf16 => "utf16",
+            Self::Utf16le => "utf16le",
+            Self::Utf32 => "utf32",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+
+    /// Text in this character set is valid UTF-8 without transcoding
+    pub fn is_utf8(self) -> bool {
+        matches!(
+            self,
+            Self::Utf8mb4 | Self::Utf8mb3 | Self::Ascii | Self::Unknown(_)
+        )
+    }
+
+    /// The `encoding_rs` encoding of the character set, or `None` for `binary` and `utf32`
+    #[cfg(feature = "encoding_rs")]
+    pub fn encoding(self) -> Option<&'static encoding_rs::Encoding> {
+        use encoding_rs::*;
+
+        Some(match self {
+            Self::Utf8mb4 | Self::Utf8mb3 | Self::Unknown(_) => UTF_8,
+            Self::Ascii | Self::Latin1 => WINDOWS_1252,
+            Self::Latin2 => ISO_8859_2,
+            Self::Latin5 => WINDOWS_1254,
+            Self::Latin7 => ISO_8859_13,
+            Self::Cp1250 => WINDOWS_1250,
+            Self::Cp1251 => WINDOWS_1251,
+            Self::Cp1256 => WINDOWS_1256,
+            Self::Cp1257 => WINDOWS_1257,
+            Self::Cp866 => IBM866,
+            Self::Koi8r => KOI8_R,
+            Self::Koi8u => KOI8_U,
+            Self::Greek => ISO_8859_7,
+            Self::Hebrew => ISO_8859_8,
+            Self::Tis620 => WINDOWS_874,
+            Self::Macroman => MACINTOSH,
+            Self::Big5 => BIG5,
+            Self::Gb2312 | Self::Gbk => GBK,
+            Self::Gb18030 => GB18030,
+            Self::Sjis | Self::Cp932 => SHIFT_JIS,
+            Self::Ujis | Self::Eucjpms => EUC_JP,
+            Self::Euckr => EUC_KR,
+            Self::Ucs2 | Self::Utf16 => UTF_16BE,
+            Self::Utf16le => UTF_16LE,
+            Self::Binary | Self::Utf32 => return None,
+        })
+    }
+
+    /// Decode text in this character set, borrowing `bytes` when they are already UTF-8.
+    pub fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>> {
+        if self.is_utf8() {
+            return from_utf8(bytes).map(Cow::Borrowed).map_err(|e| {
+                Error::BadUsageError(format!("Cannot decode {} text: {}", self.name(), e))
+            });
+        }
+        self.transcode(bytes)
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    fn transcode(self, bytes: &[u8]) -> Result<Cow<'_, str>> {
+        let encoding = self
+            .encoding()
+            .ok_or_else(|| Error::BadUsageError(format!("Cannot decode {} text", self.name())))?;
+        encoding
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .ok_or_else(|| Error::BadUsageError(format!("Invalid {} text", self.name())))
+    }
+
+    #[cfg(not(feature = "encoding_rs"))]
+    fn transcode(self, bytes: &[u8]) -> Result<Cow<'_, str>> {
+        from_utf8(bytes).map(Cow::Borrowed).map_err(|e| {
+            Error::BadUsageError(format!(
+                "Cannot decode {} text as UTF-8 (enable the `encoding_rs` feature to transcode it): {}",
+                self.name(),
+                e
+            ))
+        })
+    }
+}
+
+impl FromStr for Charset {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::from_name(name)
+            .ok_or_else(|| Error::BadUsageError(format!("Unknown character set '{}'", name)))
+    }
+}
+
+/// Collations with a name, by id
+const COLLATIONS: &[(u16, &str)] = &[
+    (1, "big5_chinese_ci"),
+    (7, "koi8r_general_ci"),
+    (8, "latin1_swedish_ci"),
+    (9, "latin2_general_ci"),
+    (11, "ascii_general_ci"),
+    (12, "ujis_japanese_ci"),
+    (13, "sjis_japanese_ci"),
+    (16, "hebrew_general_ci"),
+    (18, "tis620_thai_ci"),
+    (19, "euckr_korean_ci"),
+    (22, "koi8u_general_ci"),
+    (24, "gb2312_chinese_ci"),
+    (25, "greek_general_ci"),
+    (26, "cp1250_general_ci"),
+    (28, "gbk_chinese_ci"),
+    (30, "latin5_turkish_ci"),
+    (33, "utf8mb3_general_ci"),
+    (35, "ucs2_general_ci"),
+    (36, "cp866_general_ci"),
+    (39, "macroman_general_ci"),
+    (41, "latin7_general_ci"),
+    (45, "utf8mb4_general_ci"),
+    (46, "utf8mb4_bin"),
+    (47, "latin1_bin"),
+    (48, "latin1_general_ci"),
+    (49, "latin1_general_cs"),
+    (51, "cp1251_general_ci"),
+    (54, "utf16_general_ci"),
+    (56, "utf16le_general_ci"),
+    (57, "cp1256_general_ci"),
+    (59, "cp1257_general_ci"),
+    (60, "utf32_general_ci"),
+    (63, "binary"),
+    (65, "ascii_bin"),
+    (83, "utf8mb3_bin"),
+    (95, "cp932_japanese_ci"),
+    (97, "eucjpms_japanese_ci"),
+    (192, "utf8mb3_unicode_ci"),
+    (224, "utf8mb4_unicode_ci"),
+    (246, "utf8mb4_unicode_520_ci"),
+    (248, "gb18030_chinese_ci"),
+    (255, "utf8mb4_0900_ai_ci"),
+    (278, "utf8mb4_0900_as_cs"),
+    (305, "utf8mb4_0900_as_ci"),
+    (309, "utf8mb4_0900_bin"),
+];
+
+/// A collation id, as in the handshake and in column definitions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Collation(pub u16);
+
+impl Collation {
+    /// The collation of a connection unless `Opts::charset` or `Opts::collation` is set
+    pub const UTF8MB4_GENERAL_CI: Self = Self(45);
+
+    /// The collation named `name`, e.g. `"utf8mb4_0900_ai_ci"`. `utf8_` is `utf8mb3_`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let name = match name.strip_prefix("utf8_") {
+            Some(rest) => format!("utf8mb3_{}", rest),
+            None => name,
+        };
+        COLLATIONS
+            .iter()
+            .find(|(_, known)| *known == name)
+            .map(|&(id, _)| Self(id))
+    }
+
+    pub fn id(self) -> u16 {
+        self.0
+    }
+
+    /// The name of the collation, or `None` if this crate does not know it
+    pub fn name(self) -> Option<&'static str> {
+        COLLATIONS
+            .iter()
+            .find(|&&(id, _)| id == self.0)
+            .map(|&(_, name)| name)
+    }
+
+    pub fn charset(self) -> Charset {
+        Charset::from_collation(self.0)
+    }
+}
+
+/// The name, or the id if the name is unknown
+impl fmt::Display for Collation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// A collation name or id
+impl FromStr for Collation {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        if let Ok(id) = name.parse::<u16>() {
+            return Ok(Self(id));
+        }
+        Self::from_name(name)
+            .ok_or_else(|| Error::BadUsageError(format!("Unknown collation '{}'", name)))
+    }
+}
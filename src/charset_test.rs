@@ -0,0 +1,134 @@
+use std::borrow::Cow;
+
+use zerocopy::FromBytes;
+
+use crate::charset::{Charset, Collation};
+use crate::constant::ColumnType;
+use crate::error::Error;
+use crate::protocol::command::ColumnDefinitionTail;
+use crate::test_macros::{check, check_eq};
+
+/// A VARCHAR column with collation `id`
+fn varchar(id: u16) -> [u8; 12] {
+    let mut tail = [0_u8; 12];
+    tail[..2].copy_from_slice(&id.to_le_bytes());
+    tail[6] = ColumnType::MYSQL_TYPE_VAR_STRING as u8;
+    tail
+}
+
+fn lenenc(text: &[u8]) -> Vec<u8> {
+    let mut data = vec![text.len() as u8];
+    data.extend_from_slice(text);
+    data
+}
+
+/// Decode `text` in a VARCHAR column with collation `id`
+fn parse<T: for<'a> crate::raw::FromRawValue<'a>>(id: u16, text: &[u8]) -> crate::error::Result<T> {
+    let tail = varchar(id);
+    let col = ColumnDefinitionTail::ref_from_bytes(&tail)?;
+    crate::raw::parse_value::<T>(col, false, &lenenc(text)).map(|(value, _)| value)
+}
+
+#[test]
+fn collation_ids() -> crate::error::Result<()> {
+    check_eq!(Charset::from_collation(255), Charset::Utf8mb4);
+    check_eq!(Charset::from_collation(45), Charset::Utf8mb4);
+    check_eq!(Charset::from_collation(33), Charset::Utf8mb3);
+    check_eq!(Charset::from_collation(63), Charset::Binary);
+    check_eq!(Charset::from_collation(8), Charset::Latin1);
+    check_eq!(Charset::from_collation(51), Charset::Cp1251);
+    check_eq!(Charset::from_collation(248), Charset::Gb18030);
+    check_eq!(Charset::from_collation(2048), Charset::Unknown(2048));
+    check_eq!(Charset::Cp1251.name(), "cp1251");
+    check!(Charset::Unknown(2048).is_utf8());
+    check!(!Charset::Latin1.is_utf8());
+    Ok(())
+}
+
+#[test]
+fn utf8_is_borrowed() -> crate::error::Result<()> {
+    let decoded = Charset::Utf8mb4.decode("h\u{e9}llo".as_bytes())?;
+    check!(matches!(decoded, Cow::Borrowed("h\u{e9}llo")));
+    check!(Charset::Utf8mb4.decode(b"\xE9").is_err());
+    Ok(())
+}
+
+#[test]
+fn ascii_in_legacy_charsets() -> crate::error::Result<()> {
+    check_eq!(parse::<String>(8, b"plain")?, "plain");
+    let tail = varchar(51);
+    let col = ColumnDefinitionTail::ref_from_bytes(&tail)?;
+    let data = lenenc(b"plain");
+    let (borrowed, _) = crate::raw::parse_value::<&str>(col, false, &data)?;
+    check_eq!(borrowed, "plain");
+    Ok(())
+}
+
+#[cfg(feature = "encoding_rs")]
+#[test]
+fn transcode_legacy_charsets() -> crate::error::Result<()> {
+    // latin1 is Windows-1252: 0x80 is the euro sign
+    check_eq!(parse::<String>(8, b"caf\xE9 \x80")?, "caf\u{e9} \u{20ac}");
+    check_eq!(
+        parse::<String>(51, b"\xCF\xF0\xE8\xE2\xE5\xF2")?,
+        "\u{41f}\u{440}\u{438}\u{432}\u{435}\u{442}"
+    );
+    check_eq!(
+        parse::<Option<String>>(28, b"\xC4\xE3\xBA\xC3")?,
+        Some("\u{4f60}\u{597d}".to_string())
+    );
+    let tail = varchar(8);
+    let col = ColumnDefinitionTail::ref_from_bytes(&tail)?;
+    let data = lenenc(b"caf\xE9");
+    let (cow, _) = crate::raw::parse_value::<Cow<'_, str>>(col, false, &data)?;
+    check!(matches!(cow, Cow::Owned(_)));
+    check_eq!(cow, "caf\u{e9}");
+    check!(Charset::Utf32.decode(b"\0\0\0a").is_err());
+    Ok(())
+}
+
+#[cfg(not(feature = "encoding_rs"))]
+#[test]
+fn legacy_charsets_need_encoding_rs() -> crate::error::Result<()> {
+    check!(parse::<String>(8, b"caf\xE9").is_err());
+    Ok(())
+}
+
+#[test]
+fn collation_names() -> crate::error::Result<()> {
+    check_eq!(
+        Collation::from_name("utf8mb4_0900_AS_CS"),
+        Some(Collation(278))
+    );
+    check_eq!(Collation::from_name("utf8_bin"), Some(Collation(83)));
+    check_eq!(Collation::from_name("nope"), None);
+    check_eq!("latin1_swedish_ci".parse::<Collation>()?, Collation(8));
+    check_eq!("224".parse::<Collation>()?, Collation(224));
+    check!(matches!(
+        "nope".parse::<Collation>(),
+        Err(Error::BadUsageError(_))
+    ));
+    check_eq!(Collation(224).to_string(), "utf8mb4_unicode_ci");
+    check_eq!(Collation(1000).to_string(), "1000");
+    check_eq!(Collation(309).charset(), Charset::Utf8mb4);
+    Ok(())
+}
+
+#[test]
+fn charset_names() -> crate::error::Result<()> {
+    check_eq!("UTF8".parse::<Charset>()?, Charset::Utf8mb3);
+    check_eq!("cp1251".parse::<Charset>()?, Charset::Cp1251);
+    check!("nope".parse::<Charset>().is_err());
+    for charset in [
+        Charset::Utf8mb4,
+        Charset::Latin1,
+        Charset::Gb18030,
+        Charset::Utf32,
+    ] {
+        let collation = charset.default_collation();
+        check_eq!(collation.map(Collation::charset), Some(charset));
+        check_eq!(Charset::from_name(charset.name()), Some(charset));
+    }
+    check!(!Charset::Utf16.is_client_charset());
+    Ok(())
+}
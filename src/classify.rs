@@ -0,0 +1,284 @@
+//! A lightweight SQL statement classifier.
+//!
+//! [`classify`] is what auditing, `Opts::enforce_read_only` and `tokio::routed::route_for` use
+//! to tell reads from writes. It looks at keywords only, so it never fails on SQL it cannot parse;
+//! unrecognized statements are [`StatementClass::Unknown`].
+
+use std::borrow::Cow;
+
+use crate::error::{Error, Result};
+use crate::sql_lexer::{Token, TokenKind, tokenize};
+
+/// Sent on connect for `Opts::enforce_read_only`, so that the server rejects what the classifier misses
+pub(crate) const READ_ONLY_SQL: &str = "SET SESSION TRANSACTION READ ONLY";
+
+/// What a statement does, judged by its leading keyword.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatementClass {
+    /// `SELECT`, `TABLE`, `VALUES`, `SHOW`, `DESCRIBE`, `EXPLAIN`
+    Select,
+    /// `INSERT`, `UPDATE`, `DELETE`, `REPLACE`, `LOAD`, `CALL`
+    Dml,
+    /// `CREATE`, `ALTER`, `DROP`, `RENAME`, `TRUNCATE`
+    Ddl,
+    /// `BEGIN`, `START`, `COMMIT`, `ROLLBACK`, `SAVEPOINT`, `RELEASE`, `XA`
+    Transaction,
+    /// `SET`, `GRANT`, `REVOKE`, `KILL`, `FLUSH`, `USE`, `LOCK`, and other session or server
+    /// administration
+    Admin,
+    /// Empty or unrecognized, or the SQL text of a prepared statement is unknown
+    #[default]
+    Unknown,
+}
+
+/// Classify `sql` by its first keyword, skipping comments and opening parentheses.
+///
+/// For `WITH`, the keyword after the common table expressions decides.
+/// Only the first statement of a multi-statement query is classified.
+///
+/// ```
+/// use zero_mysql::classify::{StatementClass, classify};
+///
+/// assert_eq!(classify("/* report */ (SELECT 1) UNION (SELECT 2)"), StatementClass::Select);
+/// assert_eq!(
+///     classify("WITH stale AS (SELECT id FROM t) DELETE FROM t WHERE id IN (SELECT id FROM stale)"),
+///     StatementClass::Dml
+/// );
+/// assert_eq!(classify("-- migration\nALTER TABLE t ADD c INT"), StatementClass::Ddl);
+/// ```
+pub fn classify(sql: &str) -> StatementClass {
+    classify_tokens(&tokenize(sql))
+}
+
+pub(crate) fn classify_tokens(tokens: &[Token<'_>]) -> StatementClass {
+    let mut depth = 0usize;
+    let mut in_cte = false;
+    for token in tokens {
+        match token.kind {
+            TokenKind::Symbol if token.text == "(" => depth += 1,
+            TokenKind::Symbol if token.text == ")" => depth = depth.saturating_sub(1),
+            TokenKind::Word if !in_cte && token.text.eq_ignore_ascii_case("WITH") => {
+                in_cte = true;
+            }
+            TokenKind::Word if !in_cte || depth == 0 => {
+                let class = keyword_class(token.text);
+                // Skip the CTE names, `RECURSIVE` and `AS` until the main statement
+                if !in_cte || class != StatementClass::Unknown {
+                    return class;
+                }
+            }
+            _ => {}
+        }
+    }
+    StatementClass::Unknown
+}
+
+fn keyword_class(word: &str) -> StatementClass {
+    const CLASSES: &[(&str, StatementClass)] = &[
+        ("SELECT", StatementClass::Select),
+        ("TABLE", StatementClass::Select),
+        ("VALUES", StatementClass::Select),
+        ("SHOW", StatementClass::Select),
+        ("DESCRIBE", StatementClass::Select),
+        ("DESC", StatementClass::Select),
+        ("EXPLAIN", StatementClass::Select),
+        ("INSERT", StatementClass::Dml),
+        ("UPDATE", StatementClass::Dml),
+        ("DELETE", StatementClass::Dml),
+        ("REPLACE", StatementClass::Dml),
+        ("LOAD", StatementClass::Dml),
+        ("CALL", StatementClass::Dml),
+        ("CREATE", StatementClass::Ddl),
+        ("ALTER", StatementClass::Ddl),
+        ("DROP", StatementClass::Ddl),
+        ("RENAME", StatementClass::Ddl),
+        ("TRUNCATE", StatementClass::Ddl),
+        ("BEGIN", StatementClass::Transaction),
+        ("START", StatementClass::Transaction),
+        ("COMMIT", StatementClass::Transaction),
+        ("ROLLBACK", StatementClass::Transaction),
+        ("SAVEPOINT", StatementClass::Transaction),
+        ("RELEASE", StatementClass::Transaction),
+        ("XA", StatementClass::Transaction),
+        ("SET", StatementClass::Admin),
+        ("GRANT", StatementClass::Admin),
+        ("REVOKE", StatementClass::Admin),
+        ("KILL", StatementClass::Admin),
+        ("FLUSH", StatementClass::Admin),
+        ("RESET", StatementClass::Admin),
+        ("PURGE", StatementClass::Admin),
+        ("USE", StatementClass::Admin),
+        ("LOCK", StatementClass::Admin),
+        ("UNLOCK", StatementClass::Admin),
+        ("ANALYZE", StatementClass::Admin),
+        ("OPTIMIZE", StatementClass::Admin),
+        ("CHECK", StatementClass::Admin),
+        ("REPAIR", StatementClass::Admin),
+        ("INSTALL", StatementClass::Admin),
+        ("UNINSTALL", StatementClass::Admin),
+        ("SHUTDOWN", StatementClass::Admin),
+        ("CHANGE", StatementClass::Admin),
+        ("DO", StatementClass::Admin),
+    ];
+    CLASSES
+        .iter()
+        .find(|(keyword, _)| word.eq_ignore_ascii_case(keyword))
+        .map_or(StatementClass::Unknown, |(_, class)| *class)
+}
+
+/// An upper bound on the rows a `SELECT` returns, from its outermost `LIMIT` clause.
+///
+/// Only `LIMIT n`, `LIMIT offset, n` and `LIMIT n OFFSET offset` with number literals count;
+/// `LIMIT ?` and multi-statement queries have no bound. Only `SELECT`, `TABLE` and `VALUES`
+/// statements have one, not e.g. `EXPLAIN SELECT ... LIMIT 1`, which returns a row per table.
+///
+/// ```
+/// use zero_mysql::classify::max_rows;
+///
+/// assert_eq!(max_rows("SELECT * FROM t ORDER BY id LIMIT 20, 10"), Some(10));
+/// assert_eq!(max_rows("SELECT * FROM t WHERE id IN (SELECT id FROM u LIMIT 5)"), None);
+/// assert_eq!(max_rows("SELECT * FROM t LIMIT ?"), None);
+/// assert_eq!(max_rows("EXPLAIN SELECT * FROM t LIMIT 1"), None);
+/// ```
+pub fn max_rows(sql: &str) -> Option<u64> {
+    let tokens: Vec<Token<'_>> = tokenize(sql)
+        .into_iter()
+        .filter(|token| {
+            !matches!(
+                token.kind,
+                TokenKind::Whitespace | TokenKind::Comment | TokenKind::Hint
+            )
+        })
+        .collect();
+    // `EXPLAIN`, `SHOW` and the like also classify as `Select`, but `LIMIT` does not bound them
+    let keyword = tokens
+        .iter()
+        .find(|token| !(token.kind == TokenKind::Symbol && token.text == "("))?;
+    let is_query = keyword.kind == TokenKind::Word
+        && ["SELECT", "TABLE", "VALUES"]
+            .iter()
+            .any(|query| keyword.text.eq_ignore_ascii_case(query));
+    if !is_query {
+        return None;
+    }
+    let mut depth = 0usize;
+    let mut bound = None;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Symbol if token.text == "(" => depth += 1,
+            TokenKind::Symbol if token.text == ")" => depth = depth.saturating_sub(1),
+            // A multi-statement query, unless nothing follows
+            TokenKind::Symbol if token.text == ";" && depth == 0 && tokens.len() > i + 1 => {
+                return None;
+            }
+            TokenKind::Word if depth == 0 && token.text.eq_ignore_ascii_case("LIMIT") => {
+                bound = limit_count(tokens.get(i + 1..).unwrap_or_default());
+            }
+            _ => {}
+        }
+    }
+    bound
+}
+
+/// The row count of the tokens after `LIMIT`
+fn limit_count(tokens: &[Token<'_>]) -> Option<u64> {
+    let number = |token: &Token<'_>| {
+        (token.kind == TokenKind::Number)
+            .then(|| token.text.parse::<u64>().ok())
+            .flatten()
+    };
+    match tokens {
+        [first, comma, second, ..] if comma.kind == TokenKind::Symbol && comma.text == "," => {
+            number(first)?;
+            number(second)
+        }
+        [first, ..] => number(first),
+        [] => None,
+    }
+}
+
+/// Returns an error if a statement of `sql` writes, for `Opts::enforce_read_only`.
+///
+/// Writes are DML and DDL, `GRANT`, `REVOKE`, `SELECT ... INTO OUTFILE` and `INTO DUMPFILE`,
+/// and `READ WRITE` transactions and assignments to `transaction_read_only` or `tx_read_only`,
+/// which would lift `SET SESSION TRANSACTION READ ONLY`.
+/// Every statement of a multi-statement query is checked, including the body of executable
+/// comments (`/*! ... */` and `/*M! ... */`), which the server runs as SQL.
+pub(crate) fn check_read_only(sql: &str) -> Result<()> {
+    let sql = expand_executable_comments(sql);
+    let tokens = tokenize(&sql);
+    for statement in tokens.split(|token| token.kind == TokenKind::Symbol && token.text == ";") {
+        let class = classify_tokens(statement);
+        if statement_writes(class, statement) {
+            return Err(Error::BadUsageError(format!(
+                "{class:?} statement rejected by enforce_read_only"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn statement_writes(class: StatementClass, statement: &[Token<'_>]) -> bool {
+    let words: Vec<&str> = statement
+        .iter()
+        .filter(|token| token.kind == TokenKind::Word)
+        .map(|token| token.text)
+        .collect();
+    let has = |first: &str, second: &[&str]| {
+        words.windows(2).any(|pair| {
+            pair[0].eq_ignore_ascii_case(first)
+                && second.iter().any(|word| pair[1].eq_ignore_ascii_case(word))
+        })
+    };
+    let writes = match class {
+        StatementClass::Dml | StatementClass::Ddl => true,
+        StatementClass::Select => has("INTO", &["OUTFILE", "DUMPFILE"]),
+        StatementClass::Admin => words.first().is_some_and(|word| {
+            ["GRANT", "REVOKE"]
+                .iter()
+                .any(|w| word.eq_ignore_ascii_case(w))
+        }),
+        StatementClass::Transaction | StatementClass::Unknown => false,
+    };
+    // `transaction_read_only`, `@@transaction_read_only` and `@@session.transaction_read_only`
+    let sets_read_only = words
+        .first()
+        .is_some_and(|word| word.eq_ignore_ascii_case("SET"))
+        && words.iter().any(|word| {
+            let name = word.strip_prefix("@@").unwrap_or(word);
+            name.eq_ignore_ascii_case("transaction_read_only")
+                || name.eq_ignore_ascii_case("tx_read_only")
+        });
+    writes || sets_read_only || has("READ", &["WRITE"])
+}
+
+/// Replace every executable comment with its body, e.g. `/*!50000 DELETE FROM t */` with
+/// ` DELETE FROM t `, regardless of the server version it is meant for.
+fn expand_executable_comments(sql: &str) -> Cow<'_, str> {
+    let tokens = tokenize(sql);
+    let is_executable = |token: &Token<'_>| {
+        matches!(token.kind, TokenKind::Hint | TokenKind::Comment)
+            && (token.text.starts_with("/*!") || token.text.starts_with("/*M!"))
+    };
+    if !tokens.iter().any(is_executable) {
+        return Cow::Borrowed(sql);
+    }
+    let mut expanded = String::with_capacity(sql.len());
+    for token in &tokens {
+        if is_executable(token) {
+            let body = token
+                .text
+                .trim_start_matches("/*")
+                .trim_start_matches('M')
+                .trim_start_matches('!')
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .trim_end_matches("*/");
+            expanded.push(' ');
+            expanded.push_str(body);
+            expanded.push(' ');
+        } else {
+            expanded.push_str(token.text);
+        }
+    }
+    Cow::Owned(expanded)
+}
@@ -0,0 +1,137 @@
+use crate::classify::{StatementClass, check_read_only, classify, max_rows};
+use crate::test_macros::{check, check_eq};
+
+#[test]
+fn classify_by_leading_keyword() -> crate::error::Result<()> {
+    check_eq!(classify("SELECT 1"), StatementClass::Select);
+    check_eq!(classify("show tables"), StatementClass::Select);
+    check_eq!(
+        classify("(SELECT 1) UNION (SELECT 2)"),
+        StatementClass::Select
+    );
+    check_eq!(classify("INSERT INTO t VALUES (1)"), StatementClass::Dml);
+    check_eq!(classify("replace into t values (1)"), StatementClass::Dml);
+    check_eq!(classify("CREATE TABLE t (id INT)"), StatementClass::Ddl);
+    check_eq!(classify("TRUNCATE t"), StatementClass::Ddl);
+    check_eq!(classify("START TRANSACTION"), StatementClass::Transaction);
+    check_eq!(classify("SET autocommit = 0"), StatementClass::Admin);
+    check_eq!(classify("GRANT SELECT ON db.* TO u"), StatementClass::Admin);
+    check_eq!(classify(""), StatementClass::Unknown);
+    check_eq!(classify("HELP 'contents'"), StatementClass::Unknown);
+    Ok(())
+}
+
+#[test]
+fn classify_skips_comments() -> crate::error::Result<()> {
+    check_eq!(
+        classify("/* SELECT */ -- SELECT\nDELETE FROM t"),
+        StatementClass::Dml
+    );
+    check_eq!(classify("# note\nDROP TABLE t"), StatementClass::Ddl);
+    check_eq!(
+        classify("SELECT /*+ MAX_EXECUTION_TIME(1) */ 1"),
+        StatementClass::Select
+    );
+    Ok(())
+}
+
+#[test]
+fn classify_looks_past_ctes() -> crate::error::Result<()> {
+    check_eq!(
+        classify("WITH a AS (SELECT 1) SELECT * FROM a"),
+        StatementClass::Select
+    );
+    check_eq!(
+        classify(
+            "WITH RECURSIVE a (n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM a WHERE n < 3), \
+             b AS (SELECT n FROM a) UPDATE t JOIN b ON t.id = b.n SET t.x = 1"
+        ),
+        StatementClass::Dml
+    );
+    check_eq!(
+        classify("WITH a AS (SELECT 1) DELETE FROM t"),
+        StatementClass::Dml
+    );
+    Ok(())
+}
+
+#[test]
+fn read_only_rejects_writes() -> crate::error::Result<()> {
+    for sql in [
+        "SELECT * FROM t FOR UPDATE",
+        "(SELECT 1)",
+        "WITH a AS (SELECT 1) SELECT * FROM a",
+        "SHOW TABLES",
+        "SET @x = 1",
+        "START TRANSACTION READ ONLY",
+        "SELECT 1 INTO @x",
+        "SELECT 'INSERT INTO t VALUES (1)'",
+        "/* DELETE FROM t */ SELECT 1",
+        "SELECT @@transaction_read_only",
+        "SET @tx_read_only = 1",
+        "/*!40101 SET NAMES utf8mb4 */",
+        "SELECT /*+ MAX_EXECUTION_TIME(100) */ 1",
+    ] {
+        check!(check_read_only(sql).is_ok(), "{sql}");
+    }
+    for sql in [
+        "INSERT INTO t VALUES (1)",
+        "-- comment\nUPDATE t SET x = 1",
+        "WITH a AS (SELECT 1) DELETE FROM t",
+        "DROP TABLE t",
+        "GRANT SELECT ON db.* TO u",
+        "SELECT * FROM t INTO OUTFILE '/tmp/t'",
+        "SET SESSION TRANSACTION READ WRITE",
+        "START TRANSACTION READ WRITE",
+        "SELECT 1; DELETE FROM t",
+        "SET SESSION transaction_read_only = 0",
+        "SET @@session.transaction_read_only = OFF",
+        "SET @@transaction_read_only = 0",
+        "SET tx_read_only = 0",
+        "SET SESSION TRANSACTION_READ_ONLY=0",
+        "SET GLOBAL tx_read_only = OFF",
+        "SET @x = 1, transaction_read_only = 0",
+        "/*!50000 DELETE FROM t */",
+        "/*! DELETE FROM t */",
+        "/*M!100500 DELETE FROM t */",
+        "SELECT 1; /*!50000 SET transaction_read_only = 0 */",
+    ] {
+        check!(check_read_only(sql).is_err(), "{sql}");
+    }
+    Ok(())
+}
+
+#[test]
+fn max_rows_from_outermost_limit() -> crate::error::Result<()> {
+    check_eq!(max_rows("SELECT * FROM t LIMIT 10"), Some(10));
+    check_eq!(max_rows("select * from t limit 5 offset 100"), Some(5));
+    check_eq!(max_rows("SELECT * FROM t LIMIT 100, 5"), Some(5));
+    check_eq!(max_rows("SELECT * FROM t LIMIT 3 FOR UPDATE;"), Some(3));
+    check_eq!(
+        max_rows("(SELECT a FROM t LIMIT 5) UNION ALL (SELECT a FROM u LIMIT 5) LIMIT 7"),
+        Some(7)
+    );
+    check_eq!(
+        max_rows("WITH c AS (SELECT * FROM t LIMIT 2) SELECT * FROM c /* LIMIT 1 */"),
+        None
+    );
+    check_eq!(
+        max_rows("(SELECT a FROM t LIMIT 5) UNION ALL (SELECT a FROM u LIMIT 5)"),
+        None
+    );
+    check_eq!(max_rows("SELECT * FROM t LIMIT ?"), None);
+    check_eq!(max_rows("SELECT * FROM t LIMIT ?, 10"), None);
+    check_eq!(max_rows("SELECT 'LIMIT 1' FROM t"), None);
+    check_eq!(max_rows("SELECT 1 LIMIT 1; SELECT 2"), None);
+    check_eq!(max_rows("DELETE FROM t LIMIT 10"), None);
+    check_eq!(max_rows("TABLE t ORDER BY id LIMIT 4"), Some(4));
+    check_eq!(max_rows("VALUES ROW(1), ROW(2) LIMIT 1"), Some(1));
+    check_eq!(
+        max_rows("EXPLAIN SELECT * FROM t JOIN u USING (id) LIMIT 1"),
+        None
+    );
+    check_eq!(max_rows("DESCRIBE SELECT * FROM t LIMIT 1"), None);
+    check_eq!(max_rows("desc select * from t limit 1"), None);
+    check_eq!(max_rows("SHOW TABLES LIMIT 1"), None);
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Interned column names.
+//!
+//! With `Opts::intern_column_names`, every column definition read by a connection carries a
+//! [`ColumnNameId`] in [`ColumnDefinition::name_id`]. Equal names get equal ids on every
+//! connection, so a `#[derive(FromRow)]` struct matches columns to fields by comparing integers
+//! instead of strings, and the name text is allocated once per process as an `Arc<str>`.
+//!
+//! Each connection looks names up in its own cache first; the process-wide registry behind it is
+//! only locked the first time a connection sees a name. The registry never forgets a name, so
+//! do not enable interning for queries that generate unbounded numbers of distinct column aliases.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+
+use crate::protocol::command::ColumnDefinition;
+
+/// The id of an interned column name, equal for equal names on every connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ColumnNameId(u32);
+
+#[derive(Default)]
+struct Registry {
+    ids: HashMap<Arc<str>, ColumnNameId>,
+    names: Vec<Arc<str>>,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Mutex::default);
+
+/// Intern `name` in the process-wide registry.
+pub fn intern(name: &str) -> ColumnNameId {
+    intern_shared(name).0
+}
+
+/// The text of an interned name.
+pub fn name(id: ColumnNameId) -> Option<Arc<str>> {
+    let registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    registry.names.get(id.0 as usize).cloned()
+}
+
+fn intern_shared(name: &str) -> (ColumnNameId, Arc<str>) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((text, id)) = registry.ids.get_key_value(name) {
+        return (*id, Arc::clone(text));
+    }
+    // More than u32::MAX distinct names would exhaust memory first
+    let id = ColumnNameId(registry.names.len() as u32);
+    let text = Arc::<str>::from(name);
+    registry.names.push(Arc::clone(&text));
+    registry.ids.insert(Arc::clone(&text), id);
+    (id, text)
+}
+
+/// A connection's cache of interned column names
+#[derive(Debug, Default)]
+pub struct ColumnNames {
+    cache: HashMap<Box<[u8]>, (ColumnNameId, Arc<str>)>,
+}
+
+impl ColumnNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Intern a column name as sent by the server. Names that are not UTF-8 are interned lossily.
+    pub fn intern(&mut self, name: &[u8]) -> ColumnNameId {
+        if let Some((id, _)) = self.cache.get(name) {
+            return *id;
+        }
+        let interned = intern_shared(&String::from_utf8_lossy(name));
+        let id = interned.0;
+        self.cache.insert(name.into(), interned);
+        id
+    }
+
+    /// The text of a name interned by this connection, without locking the registry.
+    pub fn get(&self, name: &[u8]) -> Option<&Arc<str>> {
+        self.cache.get(name).map(|(_, text)| text)
+    }
+
+    /// Set [`ColumnDefinition::name_id`] of every column.
+    pub fn intern_columns(&mut self, cols: &mut [ColumnDefinition<'_>]) {
+        for col in cols {
+            col.name_id = Some(self.intern(col.name_alias));
+        }
+    }
+
+    /// The number of distinct names seen by this connection.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
@@ -0,0 +1,52 @@
+use zerocopy::FromBytes;
+
+use crate::column_names::{ColumnNames, intern, name};
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail, ColumnDefinitions};
+use crate::test_macros::{check, check_eq};
+
+/// LONGLONG, NOT NULL
+const BIGINT: [u8; 12] = [0x3F, 0, 20, 0, 0, 0, 0x08, 0x01, 0, 0, 0, 0];
+
+#[test]
+fn ids_are_shared_across_connections() -> crate::error::Result<()> {
+    let mut conn1 = ColumnNames::new();
+    let mut conn2 = ColumnNames::new();
+    let id = conn1.intern(b"column_names_test_id");
+    check_eq!(conn1.intern(b"column_names_test_id"), id);
+    check_eq!(conn2.intern(b"column_names_test_id"), id);
+    check_eq!(intern("column_names_test_id"), id);
+    check!(conn1.intern(b"column_names_test_name") != id);
+    check_eq!(conn1.len(), 2);
+    check_eq!(name(id).as_deref(), Some("column_names_test_id"));
+
+    // Repeated lookups share one allocation
+    let text1 = conn1.get(b"column_names_test_id").cloned();
+    let text2 = conn2.get(b"column_names_test_id").cloned();
+    check!(matches!((text1, text2), (Some(a), Some(b)) if std::sync::Arc::ptr_eq(&a, &b)));
+    Ok(())
+}
+
+#[test]
+fn intern_column_definitions() -> crate::error::Result<()> {
+    let tail = ColumnDefinitionTail::ref_from_bytes(&BIGINT)?;
+    let col = ColumnDefinition {
+        schema: b"test",
+        table_alias: b"t",
+        table_original: b"t",
+        name_alias: b"column_names_test_n",
+        name_original: b"n",
+        tail,
+        name_id: None,
+    };
+    let mut defs = ColumnDefinitions::from_definitions(&[col])?;
+    check_eq!(defs.definitions()[0].name_id, None);
+
+    defs.intern_names(&mut ColumnNames::new());
+    let id = intern("column_names_test_n");
+    check_eq!(defs.definitions()[0].name_id, Some(id));
+
+    // Copies keep the ids
+    let copied = ColumnDefinitions::from_definitions(defs.definitions())?;
+    check_eq!(copied.definitions()[0].name_id, Some(id));
+    Ok(())
+}
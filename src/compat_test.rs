@@ -0,0 +1,34 @@
+use crate::compat::named_to_positional;
+use crate::test_macros::check_eq;
+
+#[test]
+fn named_placeholders() -> crate::error::Result<()> {
+    check_eq!(
+        named_to_positional("SELECT * FROM users WHERE id = :id AND name = :name"),
+        (
+            "SELECT * FROM users WHERE id = ? AND name = ?".to_string(),
+            vec!["id", "name"]
+        )
+    );
+    check_eq!(
+        named_to_positional("UPDATE t SET a = :v, b = :v"),
+        ("UPDATE t SET a = ?, b = ?".to_string(), vec!["v", "v"])
+    );
+    Ok(())
+}
+
+#[test]
+fn named_placeholders_skip_literals_and_assignments() -> crate::error::Result<()> {
+    check_eq!(
+        named_to_positional("SELECT ':id', `:id`, @x := 1 /* :id */ FROM t WHERE a = :a"),
+        (
+            "SELECT ':id', `:id`, @x := 1 /* :id */ FROM t WHERE a = ?".to_string(),
+            vec!["a"]
+        )
+    );
+    check_eq!(
+        named_to_positional("SELECT 1"),
+        ("SELECT 1".to_string(), vec![])
+    );
+    Ok(())
+}
@@ -1,27 +1,62 @@
 use compio::net::TcpStream;
 #[cfg(unix)]
 use compio::net::UnixStream;
+use std::time::{Duration, Instant};
 use zerocopy::{FromBytes, FromZeros, IntoBytes};
 
 use crate::PreparedStatement;
+use crate::alloc_stats::{self, AllocStats, ConnAllocStats};
+use crate::audit::AuditLog;
 use crate::buffer::BufferSet;
 use crate::buffer_pool::PooledBufferSet;
+use crate::charset::Collation;
+use crate::classify::{READ_ONLY_SQL, StatementClass, check_read_only, classify, max_rows};
 use crate::constant::CapabilityFlags;
+use crate::dialect::Dialect;
+use crate::emulate::{self, TextToBinary};
 use crate::error::{Error, Result};
+use crate::handler::{HandlerGuard, SessionStateHook};
+use crate::hint;
+use crate::local_infile::{LocalInfile, LocalInfilePackets};
+use crate::negotiation::NegotiationReport;
+use crate::opts::FlushPolicy;
 use crate::protocol::TextRowPayload;
 use crate::protocol::command::Action;
 use crate::protocol::command::ColumnDefinition;
+use crate::protocol::command::attributes::{self, NO_ATTRIBUTES};
 use crate::protocol::command::bulk_exec::{BulkExec, BulkFlags, BulkParamsSet, write_bulk_execute};
-use crate::protocol::command::prepared::{Exec, read_prepare_ok, write_execute, write_prepare};
-use crate::protocol::command::query::{Query, write_query};
+use crate::protocol::command::check_columns_eof;
+use crate::protocol::command::prepared::{
+    Exec, LONG_DATA_CHUNK_SIZE, read_prepare_ok, write_close_statement, write_execute,
+    write_execute_with_attributes, write_prepare, write_send_long_data,
+};
+use crate::protocol::command::query::{Query, write_query, write_query_with_attributes};
 use crate::protocol::command::utility::{
     DropHandler, FirstHandler, write_ping, write_reset_connection,
 };
+use crate::protocol::compression::{CompressionStats, PacketCompression};
 use crate::protocol::connection::{Handshake, HandshakeAction, InitialHandshake};
 use crate::protocol::packet::PacketHeader;
 use crate::protocol::primitive::read_string_lenenc;
-use crate::protocol::response::{ErrPayloadBytes, OkPayloadBytes};
-use crate::protocol::r#trait::{BinaryResultSetHandler, TextResultSetHandler, param::Params};
+use crate::protocol::response::{ErrPayloadBytes, OkPayload, OkPayloadBytes, SessionStateChange};
+use crate::protocol::r#trait::{
+    BinaryResultSetHandler, Command, TextResultSetHandler, param::Params, param::TypedParam,
+};
+use crate::quote::quote_identifier;
+use crate::schema_drift::{SchemaDrift, SchemaDriftHook};
+use crate::server_info::{SERVER_INFO_SQL, ServerInfo, ServerInfoHandler};
+use crate::session::{
+    DATABASE_SQL, MARIADB_USER_VARIABLES_SQL, MYSQL_USER_VARIABLES_SQL, SessionSnapshot,
+    TrackedVariables, parse_user_variables,
+};
+use crate::snapshot::first_value;
+use crate::socket_stats::SocketStats;
+use crate::statement_buffer::{PIPELINE_WINDOW, StatementBuffer, write_buffered_execute};
+use crate::statement_cache::{StatementCache, StatementCacheStats};
+use crate::statement_log::{StatementKind, StatementLog};
+use crate::tls_info::{TLS_STATUS_SQL, TlsInfo, TlsStatusHandler};
+use crate::topology::TextRowsHandler;
+use crate::warm_up::StatementRecorder;
 
 use super::stream::Stream;
 
@@ -31,8 +66,66 @@ pub struct Conn {
     initial_handshake: InitialHandshake,
     capability_flags: CapabilityFlags,
     mariadb_capabilities: crate::constant::MariadbCapabilityFlags,
-    in_transaction: bool,
+    server_status: crate::constant::ServerStatusFlags,
+    dialect: Dialect,
     is_broken: bool,
+    statement_log: Option<StatementLog>,
+    audit: Option<AuditLog>,
+    schema_drift: Option<SchemaDriftHook>,
+    user: String,
+    auth_plugin: String,
+    negotiation_report: NegotiationReport,
+    /// Sent again as a connection attribute by `change_user()`
+    application_name: Option<String>,
+    /// `Opts::session_setup_sql()`, run after connecting and after every reset
+    session_setup: Option<String>,
+    affected_rows: u64,
+    retain_statement_sql: bool,
+    emulate_prepared_statements: bool,
+    /// `Opts::connection_collation()`, kept across `change_user()`
+    collation: Collation,
+    server_info: Option<ServerInfo>,
+    /// Set when a command failed with `ER_SERVER_SHUTDOWN`
+    server_shutdown: bool,
+    statement_cache: StatementCache,
+    statement_recorder: Option<StatementRecorder>,
+    read_only: bool,
+    role_changed: bool,
+    alloc_stats: ConnAllocStats,
+    local_infile: Option<LocalInfile>,
+    session_state_hook: Option<SessionStateHook>,
+    flush_policy: FlushPolicy,
+    tracked_variables: TrackedVariables,
+}
+
+impl std::fmt::Debug for Conn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Conn")
+            .field("connection_id", &self.connection_id())
+            .field(
+                "server_version",
+                &String::from_utf8_lossy(self.server_version()),
+            )
+            .field("server_status", &self.server_status)
+            .field("is_broken", &self.is_broken)
+            .finish_non_exhaustive()
+    }
+}
+
+/// `conn#42 (11.4.8-MariaDB)`
+impl std::fmt::Display for Conn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "conn#{} ({})",
+            self.connection_id(),
+            String::from_utf8_lossy(self.server_version())
+        )?;
+        if let Some(info) = &self.server_info {
+            write!(f, " on {}", info)?;
+        }
+        Ok(())
+    }
 }
 
 impl Conn {
@@ -45,7 +138,7 @@ impl Conn {
         #[cfg(unix)]
         let stream = if let Some(socket_path) = &opts.socket {
             let stream = UnixStream::connect(socket_path).await?;
-            Stream::unix(stream)
+            Stream::unix_with_capacity(stream, opts.read_buffer_size)
         } else {
             if opts.host.is_empty() {
                 return Err(Error::BadUsageError(
@@ -53,9 +146,9 @@ impl Conn {
                 ));
             }
             let addr = format!("{}:{}", opts.host, opts.port);
-            let stream = TcpStream::connect(&addr).await?;
+            let stream = timed(opts.connect_timeout, TcpStream::connect(&addr)).await?;
             stream.set_nodelay(opts.tcp_nodelay)?;
-            Stream::tcp(stream)
+            Stream::tcp_with_capacity(stream, opts.read_buffer_size)
         };
 
         #[cfg(not(unix))]
@@ -71,17 +164,22 @@ impl Conn {
                 ));
             }
             let addr = format!("{}:{}", opts.host, opts.port);
-            let stream = TcpStream::connect(&addr).await?;
+            let stream = timed(opts.connect_timeout, TcpStream::connect(&addr)).await?;
             stream.set_nodelay(opts.tcp_nodelay)?;
-            Stream::tcp(stream)
+            Stream::tcp_with_capacity(stream, opts.read_buffer_size)
         };
 
         Self::new_with_stream(stream, &opts).await
     }
 
     pub async fn new_with_stream(stream: Stream, opts: &crate::opts::Opts) -> Result<Self> {
+        let opts = &opts.resolve_password()?;
+        let collation = opts.connection_collation()?;
         let mut conn_stream = stream;
+        conn_stream.set_timeouts(opts.read_timeout, opts.write_timeout);
         let mut buffer_set = opts.buffer_pool.get_buffer_set();
+        buffer_set.set_scratch_arena(opts.scratch_arena);
+        buffer_set.set_intern_column_names(opts.intern_column_names);
 
         #[cfg(feature = "compio-tls")]
         let host = opts.host.clone();
@@ -102,7 +200,8 @@ impl Conn {
                 #[cfg(feature = "compio-tls")]
                 HandshakeAction::UpgradeTls { sequence_id } => {
                     write_handshake_payload(&mut conn_stream, &mut buffer_set, sequence_id).await?;
-                    conn_stream = conn_stream.upgrade_to_tls(&host).await?;
+                    let timeout = conn_stream.read_timeout();
+                    conn_stream = timed(timeout, conn_stream.upgrade_to_tls(&host)).await?;
                 }
                 #[cfg(not(feature = "compio-tls"))]
                 HandshakeAction::UpgradeTls { .. } => {
@@ -114,7 +213,22 @@ impl Conn {
             }
         }
 
+        let auth_plugin = handshake.auth_plugin(&buffer_set);
+        let negotiation_report = handshake.negotiation_report();
         let (initial_handshake, capability_flags, mariadb_capabilities) = handshake.finish()?;
+        buffer_set.legacy_eof = !capability_flags.contains(CapabilityFlags::CLIENT_DEPRECATE_EOF);
+        if let Some(compression) = PacketCompression::negotiated(capability_flags, opts) {
+            conn_stream.enable_compression(compression);
+        }
+        conn_stream.set_quickack(opts.tcp_quickack);
+        conn_stream.register_read_buffers(opts.registered_read_buffers);
+        let server_status = initial_handshake.status_flags;
+        let dialect = opts.dialect.unwrap_or_else(|| {
+            Dialect::detect(
+                &buffer_set.initial_handshake[initial_handshake.server_version.clone()],
+                capability_flags,
+            )
+        });
 
         let conn = Self {
             stream: conn_stream,
@@ -122,8 +236,35 @@ impl Conn {
             initial_handshake,
             capability_flags,
             mariadb_capabilities,
-            in_transaction: false,
+            server_status,
+            dialect,
             is_broken: false,
+            statement_log: opts.statement_log.clone(),
+            audit: opts.audit.clone(),
+            schema_drift: opts.schema_drift.clone(),
+            user: opts.user.clone(),
+            auth_plugin,
+            negotiation_report,
+            application_name: opts.application_name.clone(),
+            session_setup: opts.session_setup_sql(),
+            affected_rows: 0,
+            retain_statement_sql: opts.retain_statement_sql,
+            emulate_prepared_statements: opts.emulate_prepared_statements,
+            collation,
+            server_info: None,
+            server_shutdown: false,
+            statement_cache: StatementCache::new(
+                opts.statement_cache_bytes,
+                opts.statement_cache_size,
+            ),
+            statement_recorder: opts.statement_recorder.clone(),
+            read_only: false,
+            role_changed: false,
+            alloc_stats: ConnAllocStats::default(),
+            local_infile: opts.local_infile.clone(),
+            session_state_hook: None,
+            flush_policy: opts.flush_policy,
+            tracked_variables: TrackedVariables::default(),
         };
 
         // Upgrade to Unix socket if connected via TCP to loopback
@@ -136,10 +277,25 @@ impl Conn {
         #[cfg(not(unix))]
         let mut conn = conn;
 
-        // Execute init command if specified
-        if let Some(init_command) = &opts.init_command {
+        for init_command in &opts.init_commands {
             conn.query_drop(init_command).await?;
         }
+        if let Some(session_setup) = conn.session_setup.clone() {
+            conn.query_drop(&session_setup).await?;
+        }
+        if opts.server_info {
+            let mut handler = ServerInfoHandler::default();
+            conn.query(SERVER_INFO_SQL, &mut handler).await?;
+            conn.server_info = Some(handler.into_info()?);
+        }
+        if opts.enforce_read_only {
+            conn.query_drop(READ_ONLY_SQL).await?;
+            conn.read_only = true;
+        }
+        if !opts.warm_up_statements.is_empty() {
+            conn.warm_up_statement_cache(opts.warm_up_statements.iter().map(String::as_str))
+                .await?;
+        }
 
         Ok(conn)
     }
@@ -152,6 +308,15 @@ impl Conn {
         self.capability_flags
     }
 
+    pub fn mariadb_capabilities(&self) -> crate::constant::MariadbCapabilityFlags {
+        self.mariadb_capabilities
+    }
+
+    fn bulk_operations(&self) -> bool {
+        self.mariadb_capabilities
+            .contains(crate::constant::MariadbCapabilityFlags::MARIADB_CLIENT_STMT_BULK_OPERATIONS)
+    }
+
     pub fn is_mysql(&self) -> bool {
         self.capability_flags.is_mysql()
     }
@@ -160,10 +325,57 @@ impl Conn {
         self.capability_flags.is_mariadb()
     }
 
+    /// The server dialect, from `Opts::dialect` or detected from the initial handshake
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
     pub fn connection_id(&self) -> u64 {
         self.initial_handshake.connection_id as u64
     }
 
+    /// The user the connection is authenticated as, updated by `change_user()`
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// The connection collation sent in the handshake, or set with `SET NAMES` when its id does
+    /// not fit the handshake byte or `Opts::set_names` is on
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    /// The server instance the connection landed on, if `Opts::server_info` is set
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
+
+    /// The authentication plugin the server accepted, e.g. `"caching_sha2_password"`,
+    /// updated by `change_user()`
+    pub fn auth_plugin(&self) -> &str {
+        &self.auth_plugin
+    }
+
+    /// Capabilities requested in `Opts` that the server or the dialect did not negotiate
+    pub fn negotiation_report(&self) -> &NegotiationReport {
+        &self.negotiation_report
+    }
+
+    /// TLS version, cipher and server certificate, or `None` if the connection is not encrypted.
+    ///
+    /// For encrypted connections, this reads the `Ssl_version` and `Ssl_cipher` session status
+    /// from the server, which takes a round trip.
+    /// The server certificate is not available on this backend.
+    ///
+    pub async fn tls_info(&mut self) -> Result<Option<TlsInfo>> {
+        if !self.capability_flags.contains(CapabilityFlags::CLIENT_SSL) {
+            return Ok(None);
+        }
+        let mut handler = TlsStatusHandler::default();
+        self.query(TLS_STATUS_SQL, &mut handler).await?;
+        Ok(handler.into_info(None))
+    }
+
     pub fn status_flags(&self) -> crate::constant::ServerStatusFlags {
         self.initial_handshake.status_flags
     }
@@ -172,22 +384,220 @@ impl Conn {
         self.is_broken
     }
 
+    /// Returns true if a command failed because the server is shutting down.
+    ///
+    /// The connection is also broken. Pools drop their idle connections to the same server when
+    /// such a connection is returned.
+    pub fn is_server_shutdown(&self) -> bool {
+        self.server_shutdown
+    }
+
+    /// Hits, misses and size of the statement cache used by [`exec_cached`](Self::exec_cached).
+    pub fn statement_cache_stats(&self) -> StatementCacheStats {
+        self.statement_cache.stats()
+    }
+
+    /// Heap allocations made by the last command.
+    ///
+    /// Always zero unless the `alloc-stats` feature is enabled and
+    /// [`CountingAllocator`](crate::alloc_stats) is the global allocator.
+    pub fn last_alloc_stats(&self) -> AllocStats {
+        self.alloc_stats.last
+    }
+
+    /// Heap allocations made by all commands since the connection was opened.
+    pub fn alloc_stats(&self) -> AllocStats {
+        self.alloc_stats.total
+    }
+
+    /// RTT, retransmits and send-queue depth from `TCP_INFO`.
+    ///
+    /// `None` for Unix sockets and on platforms other than Linux.
+    pub fn socket_stats(&self) -> Result<Option<SocketStats>> {
+        Ok(self.stream.socket_stats()?)
+    }
+
+    /// Compressed bytes and codec time per direction, or `None` if the connection is not compressed.
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.stream.compression_stats()
+    }
+
+    /// Whether reads go through a provided-buffer ring (`Opts::registered_read_buffers`).
+    /// `false` if the option is off, the connection uses TLS, or registration failed.
+    pub fn has_registered_read_buffers(&self) -> bool {
+        self.stream.has_registered_read_buffers()
+    }
+
+    /// Replace the handler for `LOAD DATA LOCAL INFILE` requests.
+    ///
+    /// The server only sends requests if `CLIENT_LOCAL_FILES` was negotiated,
+    /// i.e. `Opts::local_infile` was set or `Opts::capabilities` contains it.
+    pub fn set_local_infile(&mut self, local_infile: Option<LocalInfile>) {
+        self.local_infile = local_infile;
+    }
+
+    /// Call `hook` for every session state change the server reports
+    /// (`SERVER_SESSION_STATE_CHANGED`), e.g. a `SET SESSION` of a tracked variable or `USE db`.
+    ///
+    /// Which changes are reported depends on the server's `session_track_*` variables.
+    /// Replaces the previous hook.
+    pub fn on_session_state_change(
+        &mut self,
+        hook: impl FnMut(&SessionStateChange) + Send + Sync + 'static,
+    ) {
+        self.session_state_hook = Some(Box::new(hook));
+    }
+
+    fn notify_session_state(&mut self, changes: &[SessionStateChange]) {
+        self.tracked_variables.apply(changes);
+        if let Some(hook) = &mut self.session_state_hook {
+            changes.iter().for_each(hook);
+        }
+    }
+
+    /// Capture the default database, the tracked session system variables and the user variables,
+    /// to replay them on another connection with [`restore_session`](Self::restore_session),
+    /// e.g. after a failover. See [`crate::session`].
+    ///
+    /// # Errors
+    /// Returns the server error if the user variables cannot be read,
+    /// e.g. when `performance_schema` is disabled.
+    pub async fn snapshot_session(&mut self) -> Result<SessionSnapshot> {
+        let mut database = TextRowsHandler::default();
+        self.query(DATABASE_SQL, &mut database).await?;
+        let user_variables_sql = if self.is_mariadb() {
+            MARIADB_USER_VARIABLES_SQL
+        } else {
+            MYSQL_USER_VARIABLES_SQL
+        };
+        let mut user_variables = TextRowsHandler::default();
+        self.query(user_variables_sql, &mut user_variables).await?;
+        Ok(SessionSnapshot {
+            database: first_value(&database.rows),
+            system_variables: self.tracked_variables.to_vec(),
+            user_variables: parse_user_variables(user_variables.rows),
+        })
+    }
+
+    /// Replay a [`SessionSnapshot`] taken on another connection.
+    pub async fn restore_session(&mut self, snapshot: &SessionSnapshot) -> Result<()> {
+        for sql in snapshot.restore_statements() {
+            self.query_drop(&sql).await?;
+        }
+        Ok(())
+    }
+
+    fn log_start(&self) -> Option<Instant> {
+        self.statement_log.as_ref().map(|_| Instant::now())
+    }
+
+    fn log_statement<T>(
+        &self,
+        kind: StatementKind,
+        sql: Option<&str>,
+        statement_id: Option<u32>,
+        started: Option<Instant>,
+        result: &Result<T>,
+    ) {
+        if let (Some(log), Some(started)) = (&self.statement_log, started) {
+            log.log(kind, sql, statement_id, started, result.as_ref().err());
+        }
+        self.audit(
+            kind,
+            sql.map_or(StatementClass::Unknown, classify),
+            sql,
+            statement_id,
+            result,
+        );
+    }
+
+    fn log_exec<T>(
+        &self,
+        kind: StatementKind,
+        stmt: &mut PreparedStatement,
+        started: Option<Instant>,
+        result: &Result<T>,
+    ) {
+        if let (Some(log), Some(started)) = (&self.statement_log, started) {
+            log.log(
+                kind,
+                stmt.sql(),
+                Some(stmt.id()),
+                started,
+                result.as_ref().err(),
+            );
+        }
+        self.audit(kind, stmt.class(), stmt.sql(), Some(stmt.id()), result);
+        if let Some(hook) = &self.schema_drift
+            && result.is_ok()
+            && let Some(before) = stmt.track_columns()
+        {
+            hook.report(&SchemaDrift {
+                connection_id: self.connection_id(),
+                statement_id: stmt.id(),
+                sql: stmt.sql().map(str::to_string),
+                before,
+                after: stmt.columns_seen().to_vec(),
+            });
+        }
+    }
+
+    fn audit<T>(
+        &self,
+        kind: StatementKind,
+        class: StatementClass,
+        sql: Option<&str>,
+        statement_id: Option<u32>,
+        result: &Result<T>,
+    ) {
+        if let Some(audit) = &self.audit {
+            audit.record(
+                &self.user,
+                self.connection_id(),
+                kind,
+                class,
+                sql,
+                statement_id,
+                self.affected_rows,
+                result.as_ref().err(),
+            );
+        }
+    }
+
+    /// Reject writes if `Opts::enforce_read_only` is set
+    fn reject_writes(&self, sql: &str) -> Result<()> {
+        if self.read_only {
+            check_read_only(sql)
+        } else {
+            Ok(())
+        }
+    }
+
     #[inline]
     fn check_error<T>(&mut self, result: Result<T>) -> Result<T> {
-        if let Err(e) = &result
-            && e.is_conn_broken()
-        {
-            self.is_broken = true;
+        if let Err(e) = &result {
+            if e.is_conn_broken() {
+                self.is_broken = true;
+            }
+            if e.is_server_shutdown() {
+                self.server_shutdown = true;
+            }
         }
         result
     }
 
-    pub(crate) fn set_in_transaction(&mut self, value: bool) {
-        self.in_transaction = value;
+    pub fn in_transaction(&self) -> bool {
+        self.server_status
+            .contains(crate::constant::ServerStatusFlags::SERVER_STATUS_IN_TRANS)
     }
 
-    pub fn in_transaction(&self) -> bool {
-        self.in_transaction
+    pub fn autocommit(&self) -> bool {
+        self.server_status
+            .contains(crate::constant::ServerStatusFlags::SERVER_STATUS_AUTOCOMMIT)
+    }
+
+    pub fn server_status(&self) -> crate::constant::ServerStatusFlags {
+        self.server_status
     }
 
     #[cfg(unix)]
@@ -206,7 +616,7 @@ impl Conn {
             Ok(s) => s,
             Err(_) => return self,
         };
-        let stream = Stream::unix(unix_stream);
+        let stream = Stream::unix_with_capacity(unix_stream, opts.read_buffer_size);
 
         let mut opts_unix = opts.clone();
         opts_unix.upgrade_to_unix_socket = false;
@@ -217,7 +627,79 @@ impl Conn {
         }
     }
 
+    fn query_attributes(&self) -> bool {
+        self.capability_flags
+            .contains(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES)
+    }
+
+    /// Write COM_QUERY in the format negotiated in the handshake
+    fn write_query_command<A: TypedParam>(
+        &mut self,
+        sql: &str,
+        attributes: &[(&str, A)],
+    ) -> Result<()> {
+        if self.query_attributes() {
+            write_query_with_attributes(self.buffer_set.new_write_buffer(), sql, attributes)
+        } else if attributes.is_empty() {
+            write_query(self.buffer_set.new_write_buffer(), sql);
+            Ok(())
+        } else {
+            Err(attributes::unsupported())
+        }
+    }
+
+    /// Write COM_STMT_EXECUTE in the format negotiated in the handshake
+    fn write_execute_command<P: Params, A: TypedParam>(
+        &mut self,
+        statement_id: u32,
+        params: P,
+        attributes: &[(&str, A)],
+    ) -> Result<()> {
+        if self.query_attributes() {
+            write_execute_with_attributes(
+                self.buffer_set.new_write_buffer(),
+                statement_id,
+                params,
+                attributes,
+            )
+        } else if attributes.is_empty() {
+            write_execute(self.buffer_set.new_write_buffer(), statement_id, params)
+        } else {
+            Err(attributes::unsupported())
+        }
+    }
+
     async fn write_payload(&mut self) -> Result<()> {
+        let timeout = self.stream.write_timeout();
+        timed(timeout, async {
+            self.write_packets(false).await?;
+            self.stream.flush().await?;
+            Ok::<_, Error>(())
+        })
+        .await
+    }
+
+    /// Write a command of the pipelined batch: at once with `FlushPolicy::PerCommand`,
+    /// held back until [`Self::flush_pipelined`] with `FlushPolicy::Coalesce`.
+    async fn write_pipelined(&mut self) -> Result<()> {
+        match self.flush_policy {
+            FlushPolicy::PerCommand => self.write_payload().await,
+            FlushPolicy::Coalesce => self.write_packets(true).await,
+        }
+    }
+
+    /// Send the commands held back by [`Self::write_pipelined`].
+    async fn flush_pipelined(&mut self) -> Result<()> {
+        if self.flush_policy == FlushPolicy::Coalesce {
+            let timeout = self.stream.write_timeout();
+            timed(timeout, self.stream.flush()).await?;
+        }
+        Ok(())
+    }
+
+    /// Split the write buffer into packets and write them without flushing.
+    /// `deferred` keeps them in the stream until the next flush.
+    async fn write_packets(&mut self, deferred: bool) -> Result<()> {
         let mut sequence_id = 0_u8;
         let mut buffer = self.buffer_set.write_buffer_mut().as_mut_slice();
 
@@ -225,7 +707,11 @@ impl Conn {
             let chunk_size = buffer[4..].len().min(0xFFFFFF);
             PacketHeader::mut_from_bytes(&mut buffer[0..4])?
                 .encode_in_place(chunk_size, sequence_id);
-            self.stream.write_all(&buffer[..4 + chunk_size]).await?;
+            if deferred {
+                self.stream.write_deferred(&buffer[..4 + chunk_size]);
+            } else {
+                self.stream.write_all(&buffer[..4 + chunk_size]).await?;
+            }
 
             if chunk_size < 0xFFFFFF {
                 break;
@@ -234,18 +720,87 @@ impl Conn {
             sequence_id = sequence_id.wrapping_add(1);
             buffer = &mut buffer[0xFFFFFF..];
         }
-        self.stream.flush().await?;
         Ok(())
     }
 
     pub async fn prepare(&mut self, sql: &str) -> Result<PreparedStatement> {
-        let result = self.prepare_inner(sql).await;
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(self.prepare_inner(sql)).await;
+        self.log_statement(
+            StatementKind::Prepare,
+            Some(sql),
+            result.as_ref().ok().map(PreparedStatement::id),
+            started,
+            &result,
+        );
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    /// Deallocate a prepared statement on the server (`COM_STMT_CLOSE`).
+    pub async fn close_statement(&mut self, stmt: PreparedStatement) -> Result<()> {
+        if stmt.is_emulated() {
+            return Ok(());
+        }
+        write_close_statement(self.buffer_set.new_write_buffer(), stmt.id());
+        // the server does not respond to COM_STMT_CLOSE
+        let (result, allocs) = alloc_stats::count_async(self.write_payload()).await;
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    /// Send a parameter value in chunks (`COM_STMT_SEND_LONG_DATA`) without buffering it.
+    ///
+    /// Call this any number of times before `exec` and pass
+    /// [`LongData`](crate::protocol::r#trait::param::LongData) for the parameter.
+    /// The chunks are appended on the server; the total size is limited by `max_allowed_packet`.
+    pub async fn send_long_data(
+        &mut self,
+        stmt: &PreparedStatement,
+        param_index: u16,
+        data: &[u8],
+    ) -> Result<()> {
+        emulate::check_not_emulated(stmt, "send_long_data()")?;
+        let (result, allocs) =
+            alloc_stats::count_async(self.send_long_data_inner(stmt.id(), param_index, data)).await;
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
+    async fn send_long_data_inner(
+        &mut self,
+        statement_id: u32,
+        param_index: u16,
+        data: &[u8],
+    ) -> Result<()> {
+        let mut chunks = data.chunks(LONG_DATA_CHUNK_SIZE);
+        // An empty value is still sent once
+        let first = chunks.next().unwrap_or_default();
+        for chunk in std::iter::once(first).chain(chunks) {
+            write_send_long_data(
+                self.buffer_set.new_write_buffer(),
+                statement_id,
+                param_index,
+                chunk,
+            );
+            // the server does not respond to COM_STMT_SEND_LONG_DATA
+            self.write_payload().await?;
+        }
+        Ok(())
+    }
+
     async fn prepare_inner(&mut self, sql: &str) -> Result<PreparedStatement> {
         use crate::protocol::command::ColumnDefinitions;
 
+        self.reject_writes(sql)?;
+        if self.emulate_prepared_statements {
+            let mut stmt = PreparedStatement::emulated(sql);
+            if self.audit.is_some() {
+                stmt.set_class(classify(sql));
+            }
+            stmt.set_max_rows(max_rows(sql));
+            return Ok(stmt);
+        }
         self.buffer_set.read_buffer.clear();
 
         write_prepare(self.buffer_set.new_write_buffer(), sql);
@@ -267,15 +822,25 @@ impl Conn {
         for _ in 0..num_params {
             let _ = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
         }
+        if num_params > 0 && self.buffer_set.legacy_eof {
+            let _ = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
+            check_columns_eof(&self.buffer_set.read_buffer)?;
+        }
 
         // Read and cache column definitions for MARIADB_CLIENT_CACHE_METADATA support
         let column_definitions = if num_columns > 0 {
             self.read_column_definition_packets(num_columns as usize)
                 .await?;
-            Some(ColumnDefinitions::new(
+            if self.buffer_set.legacy_eof {
+                let _ = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
+                check_columns_eof(&self.buffer_set.read_buffer)?;
+            }
+            let mut col_defs = ColumnDefinitions::new(
                 num_columns as usize,
                 std::mem::take(&mut self.buffer_set.column_definition_buffer),
-            )?)
+            )?;
+            self.buffer_set.intern_columns(&mut col_defs);
+            Some(col_defs)
         } else {
             None
         };
@@ -284,6 +849,13 @@ impl Conn {
         if let Some(col_defs) = column_definitions {
             stmt.set_column_definitions(col_defs);
         }
+        if self.retain_statement_sql {
+            stmt.set_sql(sql);
+        }
+        if self.audit.is_some() {
+            stmt.set_class(classify(sql));
+        }
+        stmt.set_max_rows(max_rows(sql));
         Ok(stmt)
     }
 
@@ -292,19 +864,24 @@ impl Conn {
         let out = &mut self.buffer_set.column_definition_buffer;
         out.clear();
 
-        for _ in 0..num_columns {
-            self.stream.read_exact(header.as_mut_bytes()).await?;
-            let length = header.length();
-            out.extend((length as u32).to_ne_bytes());
-
-            out.reserve(length);
-            let spare = out.spare_capacity_mut();
-            self.stream.read_buf_exact(&mut spare[..length]).await?;
-            // SAFETY: read_buf_exact filled exactly `length` bytes
-            unsafe {
-                out.set_len(out.len() + length);
+        let timeout = self.stream.read_timeout();
+        timed(timeout, async {
+            for _ in 0..num_columns {
+                self.stream.read_exact(header.as_mut_bytes()).await?;
+                let length = header.length();
+                out.extend((length as u32).to_ne_bytes());
+
+                out.reserve(length);
+                let spare = out.spare_capacity_mut();
+                self.stream.read_buf_exact(&mut spare[..length]).await?;
+                // SAFETY: read_buf_exact filled exactly `length` bytes
+                unsafe {
+                    out.set_len(out.len() + length);
+                }
             }
-        }
+            Ok::<_, Error>(())
+        })
+        .await?;
 
         Ok(header.sequence_id)
     }
@@ -317,10 +894,20 @@ impl Conn {
         let cache_metadata = self
             .mariadb_capabilities
             .contains(crate::constant::MariadbCapabilityFlags::MARIADB_CLIENT_CACHE_METADATA);
-        let mut exec = Exec::new(handler, stmt, cache_metadata);
+        let mut guard = HandlerGuard::new(handler);
+        let mut exec = Exec::new(&mut guard, stmt, cache_metadata);
 
         loop {
-            match exec.step(&mut self.buffer_set)? {
+            let action = match exec.step(&mut self.buffer_set) {
+                Ok(action) => action,
+                Err(err) => {
+                    if guard.abandoned {
+                        self.is_broken = true;
+                    }
+                    return Err(err);
+                }
+            };
+            match action {
                 Action::NeedPacket(buffer) => {
                     buffer.clear();
                     let _ = read_payload(&mut self.stream, buffer).await?;
@@ -328,26 +915,80 @@ impl Conn {
                 Action::ReadColumnMetadata { num_columns } => {
                     self.read_column_definition_packets(num_columns).await?;
                 }
-                Action::Finished => return Ok(()),
+                Action::LocalInfile { .. } => {
+                    return Err(Error::LibraryBug(crate::error::eyre!(
+                        "unexpected LOCAL INFILE request"
+                    )));
+                }
+                Action::Finished => break,
             }
         }
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows = guard.affected_rows;
+        guard.finish()
+    }
+
+    /// Send the file requested by `LOAD DATA LOCAL INFILE`.
+    ///
+    /// Returns the error of opening or reading the file.
+    async fn send_local_infile(
+        &mut self,
+        mut packets: LocalInfilePackets,
+    ) -> Result<Option<Error>> {
+        let timeout = self.stream.write_timeout();
+        while let Some(packet) = packets.next_packet(self.buffer_set.write_buffer_mut())? {
+            timed(timeout, self.stream.write_all(packet)).await?;
+        }
+        timed(timeout, self.stream.flush()).await?;
+        Ok(packets.into_error())
     }
 
     async fn drive_query<H: TextResultSetHandler>(&mut self, handler: &mut H) -> Result<()> {
-        let mut query = Query::new(handler);
+        let mut guard = HandlerGuard::new(handler);
+        let mut query = Query::new(&mut guard);
+        let mut sequence_id = 0_u8;
+        let mut local_infile_error = None;
 
         loop {
-            match query.step(&mut self.buffer_set)? {
+            let action = match query.step(&mut self.buffer_set) {
+                Ok(action) => action,
+                Err(err) => {
+                    if guard.abandoned {
+                        self.is_broken = true;
+                    }
+                    return Err(local_infile_error.unwrap_or(err));
+                }
+            };
+            match action {
                 Action::NeedPacket(buffer) => {
                     buffer.clear();
-                    let _ = read_payload(&mut self.stream, buffer).await?;
+                    sequence_id = read_payload(&mut self.stream, buffer).await?;
                 }
                 Action::ReadColumnMetadata { num_columns } => {
                     self.read_column_definition_packets(num_columns).await?;
                 }
-                Action::Finished => return Ok(()),
+                Action::LocalInfile { filename } => {
+                    let packets =
+                        LocalInfilePackets::new(self.local_infile.as_ref(), filename, sequence_id);
+                    if let Some(err) = self.send_local_infile(packets).await? {
+                        local_infile_error.get_or_insert(err);
+                    }
+                }
+                Action::Finished => break,
             }
         }
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows = guard.affected_rows;
+        match local_infile_error {
+            Some(err) => Err(err),
+            None => guard.finish(),
+        }
     }
 
     pub async fn exec<P, H>(
@@ -360,10 +1001,122 @@ impl Conn {
         P: Params,
         H: BinaryResultSetHandler,
     {
-        let result = self.exec_inner(stmt, params, handler).await;
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.exec_inner(stmt, params, handler)).await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    /// Executes `sql` with a statement from the connection's statement cache,
+    /// preparing it on a miss.
+    ///
+    /// The cache is bounded by `Opts::statement_cache_bytes` and `Opts::statement_cache_size`.
+    /// Statements evicted to stay within the budget are closed on the server.
+    pub async fn exec_cached<P, H>(&mut self, sql: &str, params: P, handler: &mut H) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        let mut stmt = match self.statement_cache.take(sql) {
+            Some(stmt) => stmt,
+            None => {
+                if let Some(recorder) = &self.statement_recorder {
+                    recorder.record(sql);
+                }
+                self.prepare(sql).await?
+            }
+        };
+        let result = self.exec(&mut stmt, params, handler).await;
+        if self.is_broken {
+            return result;
+        }
+        for evicted in self.statement_cache.insert(sql, stmt) {
+            self.close_statement(evicted).await?;
+        }
+        result
+    }
+
+    /// Prepare `statements` into the statement cache of `exec_cached()`, e.g. from a warm-up file.
+    /// Returns the number of statements prepared.
+    ///
+    /// Statements that are already cached are skipped, as are statements the server rejects,
+    /// e.g. after a table was dropped. Does nothing if the cache is disabled.
+    pub async fn warm_up_statement_cache<'a>(
+        &mut self,
+        statements: impl IntoIterator<Item = &'a str>,
+    ) -> Result<usize> {
+        if !self.statement_cache.is_enabled() {
+            return Ok(0);
+        }
+        let mut prepared = 0;
+        for sql in statements {
+            if self.statement_cache.contains(sql) {
+                continue;
+            }
+            let stmt = match self.prepare(sql).await {
+                Ok(stmt) => stmt,
+                Err(err @ Error::ServerError(_)) => {
+                    tracing::warn!(error = %err, sql, "skipped a warm-up statement");
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            prepared += 1;
+            for evicted in self.statement_cache.insert(sql, stmt) {
+                self.close_statement(evicted).await?;
+            }
+        }
+        Ok(prepared)
+    }
+
+    /// Executes a prepared statement with a server-side execution time limit.
+    ///
+    /// The limit is applied with `SET SESSION max_execution_time` (MySQL) or
+    /// `SET SESSION max_statement_time` (MariaDB) and restored to `DEFAULT` afterwards.
+    /// On MySQL, the limit only applies to read-only `SELECT` statements.
+    pub async fn exec_with_timeout<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        timeout: Duration,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.exec_with_timeout_inner(stmt, params, timeout, handler))
+                .await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
+    async fn exec_with_timeout_inner<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        timeout: Duration,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        let is_mariadb = self.is_mariadb();
+        self.query_drop_inner(&hint::set_session_timeout(timeout, is_mariadb))
+            .await?;
+        let result = self.exec_inner(stmt, params, handler).await;
+        let reset = self
+            .query_drop_inner(hint::reset_session_timeout(is_mariadb))
+            .await;
+        result.and(reset)
+    }
+
     async fn exec_inner<P, H>(
         &mut self,
         stmt: &mut PreparedStatement,
@@ -374,11 +1127,80 @@ impl Conn {
         P: Params,
         H: BinaryResultSetHandler,
     {
-        write_execute(self.buffer_set.new_write_buffer(), stmt.id(), params)?;
+        self.exec_with_attrs_inner(stmt, params, NO_ATTRIBUTES, handler)
+            .await
+    }
+
+    /// Execute a prepared statement with query attributes (MySQL 8.0.23+).
+    ///
+    /// See [`Conn::query_with_attrs`].
+    pub async fn exec_with_attrs<P, A, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        A: TypedParam,
+        H: BinaryResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.exec_with_attrs_inner(stmt, params, attrs, handler))
+                .await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn exec_with_attrs_inner<P, A, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        A: TypedParam,
+        H: BinaryResultSetHandler,
+    {
+        if stmt.is_emulated() {
+            return self.exec_emulated(stmt, params, attrs, handler).await;
+        }
+        self.write_execute_command(stmt.id(), params, attrs)?;
         self.write_payload().await?;
         self.drive_exec(stmt, handler).await
     }
 
+    /// Execute an emulated statement as a text protocol query. See [`crate::emulate`].
+    async fn exec_emulated<P, A, H>(
+        &mut self,
+        stmt: &PreparedStatement,
+        params: P,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        A: TypedParam,
+        H: BinaryResultSetHandler,
+    {
+        let no_backslash_escapes = self
+            .server_status
+            .contains(crate::constant::ServerStatusFlags::SERVER_STATUS_NO_BACKSLASH_ESCAPES);
+        let sql = emulate::interpolate(
+            stmt.sql().unwrap_or_default(),
+            &params,
+            no_backslash_escapes,
+        )?;
+        self.write_query_command(&sql, attrs)?;
+        self.write_payload().await?;
+        self.drive_query(&mut TextToBinary::new(handler)).await
+    }
+
     async fn drive_bulk_exec<H: BinaryResultSetHandler>(
         &mut self,
         stmt: &mut crate::PreparedStatement,
@@ -387,10 +1209,20 @@ impl Conn {
         let cache_metadata = self
             .mariadb_capabilities
             .contains(crate::constant::MariadbCapabilityFlags::MARIADB_CLIENT_CACHE_METADATA);
-        let mut bulk_exec = BulkExec::new(handler, stmt, cache_metadata);
+        let mut guard = HandlerGuard::new(handler);
+        let mut bulk_exec = BulkExec::new(&mut guard, stmt, cache_metadata);
 
         loop {
-            match bulk_exec.step(&mut self.buffer_set)? {
+            let action = match bulk_exec.step(&mut self.buffer_set) {
+                Ok(action) => action,
+                Err(err) => {
+                    if guard.abandoned {
+                        self.is_broken = true;
+                    }
+                    return Err(err);
+                }
+            };
+            match action {
                 Action::NeedPacket(buffer) => {
                     buffer.clear();
                     let _ = read_payload(&mut self.stream, buffer).await?;
@@ -398,11 +1230,27 @@ impl Conn {
                 Action::ReadColumnMetadata { num_columns } => {
                     self.read_column_definition_packets(num_columns).await?;
                 }
-                Action::Finished => return Ok(()),
+                Action::LocalInfile { .. } => {
+                    return Err(Error::LibraryBug(crate::error::eyre!(
+                        "unexpected LOCAL INFILE request"
+                    )));
+                }
+                Action::Finished => break,
             }
         }
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows = guard.affected_rows;
+        guard.finish()
     }
 
+    /// Execute a bulk prepared statement with a result set handler
+    ///
+    /// MariaDB servers with `MARIADB_CLIENT_STMT_BULK_OPERATIONS` receive all parameter sets in one
+    /// `COM_STMT_BULK_EXECUTE`. MySQL has no bulk command: it executes `COM_STMT_EXECUTE` once
+    /// whatever its iteration count, so the statement is executed once per parameter set.
     pub async fn exec_bulk_insert_or_update<P, I, H>(
         &mut self,
         stmt: &mut PreparedStatement,
@@ -415,9 +1263,18 @@ impl Conn {
         I: Params,
         H: BinaryResultSetHandler,
     {
-        let result = self
-            .exec_bulk_insert_or_update_inner(stmt, params, flags, handler)
-            .await;
+        let kind = if self.bulk_operations() {
+            StatementKind::BulkExec
+        } else {
+            StatementKind::Exec
+        };
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(
+            self.exec_bulk_insert_or_update_inner(stmt, params, flags, handler),
+        )
+        .await;
+        self.log_exec(kind, stmt, started, &result);
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
@@ -433,7 +1290,7 @@ impl Conn {
         I: Params,
         H: BinaryResultSetHandler,
     {
-        if !self.is_mariadb() {
+        if !self.bulk_operations() || stmt.is_emulated() {
             for param in params {
                 self.exec_inner(stmt, param, &mut DropHandler::default())
                     .await?;
@@ -455,7 +1312,10 @@ impl Conn {
         Row: for<'buf> crate::raw::FromRow<'buf>,
         P: Params,
     {
-        let result = self.exec_first_inner(stmt, params).await;
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(self.exec_first_inner(stmt, params)).await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
@@ -468,10 +1328,9 @@ impl Conn {
         Row: for<'buf> crate::raw::FromRow<'buf>,
         P: Params,
     {
-        write_execute(self.buffer_set.new_write_buffer(), stmt.id(), params)?;
-        self.write_payload().await?;
         let mut handler = FirstHandler::<Row>::default();
-        self.drive_exec(stmt, &mut handler).await?;
+        self.exec_with_attrs_inner(stmt, params, NO_ATTRIBUTES, &mut handler)
+            .await?;
         Ok(handler.take())
     }
 
@@ -491,11 +1350,60 @@ impl Conn {
         Row: for<'buf> crate::raw::FromRow<'buf>,
         P: Params,
     {
-        let mut handler = crate::handler::CollectHandler::<Row>::default();
+        let mut handler = crate::handler::CollectHandler::<Row>::with_max_rows(stmt.max_rows());
         self.exec(stmt, params, &mut handler).await?;
         Ok(handler.into_rows())
     }
 
+    /// Execute a prepared statement and collect every result set, e.g. of a `CALL`.
+    ///
+    /// A `CALL` returns one [`ResultSet`](crate::multi_result::ResultSet) per `SELECT` in the procedure,
+    /// followed by one without columns for the status of the `CALL`.
+    pub async fn exec_multi<P: Params>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+    ) -> Result<Vec<crate::multi_result::ResultSet>> {
+        let mut handler = crate::multi_result::MultiResultHandler::default();
+        self.exec(stmt, params, &mut handler).await?;
+        Ok(handler.into_result_sets())
+    }
+
+    /// Execute a prepared statement and decode the rows into `Row`, or return the raw result set
+    /// with the decode error if any row does not decode, so that a consumer can keep working
+    /// across schema changes.
+    ///
+    /// The rows are buffered before decoding. For a `CALL`, the first result set is decoded.
+    pub async fn exec_decode_or_raw<Row, P>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+    ) -> Result<crate::multi_result::DecodeOrRaw<Row>>
+    where
+        Row: for<'buf> crate::raw::FromRow<'buf>,
+        P: Params,
+    {
+        let result_sets = self.exec_multi(stmt, params).await?;
+        Ok(crate::multi_result::DecodeOrRaw::from_result_sets(
+            result_sets,
+        ))
+    }
+
+    /// Execute a `CALL` with a statement from the statement cache and decode each result set
+    /// into its own `Vec`, e.g. `conn.call::<(Vec<A>, Vec<B>), _>("CALL proc(?)", (1,))`.
+    ///
+    /// Fails if the procedure returns a different number of result sets than `T` has `Vec`s,
+    /// or if the response does not end with the OK packet of the `CALL`.
+    pub async fn call<T, P>(&mut self, sql: &str, params: P) -> Result<T>
+    where
+        T: crate::multi_result::CallResults,
+        P: Params,
+    {
+        let mut handler = crate::multi_result::CallHandler::<T>::default();
+        self.exec_cached(sql, params, &mut handler).await?;
+        handler.finish()
+    }
+
     pub async fn exec_foreach<Row, P, F>(
         &mut self,
         stmt: &mut PreparedStatement,
@@ -530,7 +1438,10 @@ impl Conn {
     where
         H: TextResultSetHandler,
     {
-        let result = self.query_inner(sql, handler).await;
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(self.query_inner(sql, handler)).await;
+        self.log_statement(StatementKind::Query, Some(sql), None, started, &result);
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
@@ -538,24 +1449,168 @@ impl Conn {
     where
         H: TextResultSetHandler,
     {
-        write_query(self.buffer_set.new_write_buffer(), sql);
+        self.query_with_attrs_inner(sql, NO_ATTRIBUTES, handler)
+            .await
+    }
+
+    /// Execute a text protocol SQL query with query attributes (MySQL 8.0.23+).
+    ///
+    /// The server reads them with `mysql_query_attribute_string(name)`, e.g. for tracing or auditing.
+    /// Returns `Error::Unsupported` if the server did not negotiate `CLIENT_QUERY_ATTRIBUTES`.
+    pub async fn query_with_attrs<A, H>(
+        &mut self,
+        sql: &str,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        A: TypedParam,
+        H: TextResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.query_with_attrs_inner(sql, attrs, handler)).await;
+        self.log_statement(StatementKind::Query, Some(sql), None, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn query_with_attrs_inner<A, H>(
+        &mut self,
+        sql: &str,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        A: TypedParam,
+        H: TextResultSetHandler,
+    {
+        self.reject_writes(sql)?;
+        crate::paranoid::check_query(sql);
+        self.write_query_command(sql, attrs)?;
         self.write_payload().await?;
         self.drive_query(handler).await
     }
 
+    /// Execute a text protocol SQL query with a server-side execution time limit.
+    ///
+    /// On MySQL, a `/*+ MAX_EXECUTION_TIME(n) */` hint is added to `SELECT` statements
+    /// and other statements are sent unchanged.
+    /// On MariaDB, the query is wrapped in `SET STATEMENT max_statement_time=.. FOR`.
+    pub async fn query_with_timeout<H>(
+        &mut self,
+        sql: &str,
+        timeout: Duration,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        H: TextResultSetHandler,
+    {
+        let rewritten = hint::with_statement_timeout(sql, timeout, self.is_mariadb());
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(
+            self.query_inner(rewritten.as_deref().unwrap_or(sql), handler),
+        )
+        .await;
+        self.log_statement(
+            StatementKind::Query,
+            Some(rewritten.as_deref().unwrap_or(sql)),
+            None,
+            started,
+            &result,
+        );
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    /// Execute a text protocol SQL query and collect every result set,
+    /// e.g. of a multi-statement query or a `CALL`.
+    pub async fn query_multi(&mut self, sql: &str) -> Result<Vec<crate::multi_result::ResultSet>> {
+        let mut handler = crate::multi_result::MultiResultHandler::default();
+        self.query(sql, &mut handler).await?;
+        Ok(handler.into_result_sets())
+    }
     pub async fn query_drop(&mut self, sql: &str) -> Result<()> {
-        let result = self.query_drop_inner(sql).await;
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(self.query_drop_inner(sql)).await;
+        self.log_statement(StatementKind::Query, Some(sql), None, started, &result);
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
     async fn query_drop_inner(&mut self, sql: &str) -> Result<()> {
-        write_query(self.buffer_set.new_write_buffer(), sql);
+        self.reject_writes(sql)?;
+        crate::paranoid::check_query(sql);
+        self.write_query_command(sql, NO_ATTRIBUTES)?;
         self.write_payload().await?;
         self.drive_query(&mut DropHandler::default()).await
     }
 
+    /// Activate `role` for the session (`SET ROLE`), replacing the currently active roles.
+    ///
+    /// `role` is quoted as an identifier, so the host part defaults to `%`.
+    pub async fn set_role(&mut self, role: &str) -> Result<()> {
+        self.query_drop(&format!("SET ROLE {}", quote_identifier(role)))
+            .await?;
+        self.role_changed = true;
+        Ok(())
+    }
+
+    /// Restore the roles the session started with.
+    ///
+    /// Runs `SET ROLE DEFAULT` on MySQL and `SET ROLE NONE` on MariaDB.
+    pub async fn reset_role(&mut self) -> Result<()> {
+        let sql = if self.is_mariadb() {
+            "SET ROLE NONE"
+        } else {
+            "SET ROLE DEFAULT"
+        };
+        self.query_drop(sql).await?;
+        self.role_changed = false;
+        Ok(())
+    }
+
+    /// Returns true if `set_role()` was called since the connection was opened or reset.
+    pub fn role_changed(&self) -> bool {
+        self.role_changed
+    }
+
+    /// Run a protocol command that has no method on `Conn`.
+    ///
+    /// See [`Command`](crate::protocol::Command).
+    pub async fn run_command<C: Command>(&mut self, command: &mut C) -> Result<()> {
+        let (result, allocs) = alloc_stats::count_async(self.run_command_inner(command)).await;
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn run_command_inner<C: Command>(&mut self, command: &mut C) -> Result<()> {
+        command.encode(self.buffer_set.new_write_buffer())?;
+        self.write_payload().await?;
+        self.buffer_set.read_buffer.clear();
+        loop {
+            match command.step(&mut self.buffer_set)? {
+                Action::NeedPacket(buffer) => {
+                    buffer.clear();
+                    let _ = read_payload(&mut self.stream, buffer).await?;
+                }
+                Action::ReadColumnMetadata { num_columns } => {
+                    self.read_column_definition_packets(num_columns).await?;
+                }
+                Action::LocalInfile { .. } => {
+                    self.is_broken = true;
+                    return Err(Error::Unsupported(
+                        "LOCAL INFILE in a custom command".to_string(),
+                    ));
+                }
+                Action::Finished => return Ok(()),
+            }
+        }
+    }
+
     pub async fn ping(&mut self) -> Result<()> {
-        let result = self.ping_inner().await;
+        let (result, allocs) = alloc_stats::count_async(self.ping_inner()).await;
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
@@ -568,39 +1623,268 @@ impl Conn {
     }
 
     pub async fn reset(&mut self) -> Result<()> {
-        let result = self.reset_inner().await;
+        let (result, allocs) = alloc_stats::count_async(self.reset_inner()).await;
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
     async fn reset_inner(&mut self) -> Result<()> {
+        if !self
+            .dialect
+            .supports_reset_connection(self.server_version())
+        {
+            return Err(Error::Unsupported(format!(
+                "COM_RESET_CONNECTION is not used with {}",
+                self.dialect
+            )));
+        }
+        // The server closes every prepared statement
+        self.statement_cache.clear();
+        self.tracked_variables.clear();
         write_reset_connection(self.buffer_set.new_write_buffer());
         self.write_payload().await?;
         self.buffer_set.read_buffer.clear();
         let _ = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
-        self.in_transaction = false;
+        if self.buffer_set.read_buffer.first() == Some(&0xFF) {
+            Err(ErrPayloadBytes(&self.buffer_set.read_buffer))?
+        }
+        self.server_status = OkPayload::try_from(OkPayloadBytes(&self.buffer_set.read_buffer))
+            .map_or(
+                self.server_status - crate::constant::ServerStatusFlags::SERVER_STATUS_IN_TRANS,
+                |ok| ok.status_flags,
+            );
+        self.role_changed = false;
+        // The session was reset
+        if let Some(session_setup) = self.session_setup.clone() {
+            self.query_drop(&session_setup).await?;
+        }
+        if self.read_only {
+            self.query_drop(READ_ONLY_SQL).await?;
+        }
+        Ok(())
+    }
+
+    /// Re-authenticate as another user with `COM_CHANGE_USER`, without reconnecting.
+    ///
+    /// The server resets the session as with [`reset`](Self::reset): prepared statements are closed,
+    /// user variables and temporary tables are dropped, and an open transaction is rolled back.
+    /// `Opts::danger_zone` does not apply to the new authentication.
+    pub async fn change_user(
+        &mut self,
+        user: &str,
+        password: &str,
+        db: Option<&str>,
+    ) -> Result<()> {
+        let (result, allocs) =
+            alloc_stats::count_async(self.change_user_inner(user, password, db)).await;
+        self.alloc_stats.record(allocs);
+        if result.is_ok() {
+            self.user = user.to_string();
+        }
+        self.check_error(result)
+    }
+
+    async fn change_user_inner(
+        &mut self,
+        user: &str,
+        password: &str,
+        db: Option<&str>,
+    ) -> Result<()> {
+        self.statement_cache.clear();
+        self.tracked_variables.clear();
+        let opts = crate::opts::Opts {
+            user: user.to_string(),
+            password: password.to_string(),
+            db: db.map(str::to_string),
+            application_name: self.application_name.clone(),
+            collation: Some(self.collation),
+            ..Default::default()
+        };
+        let mut handshake = Handshake::change_user(
+            &opts,
+            self.initial_handshake.clone(),
+            self.capability_flags,
+            self.mariadb_capabilities,
+        );
+
+        loop {
+            match handshake.step(&mut self.buffer_set)? {
+                HandshakeAction::ReadPacket(buffer) => {
+                    buffer.clear();
+                    read_payload(&mut self.stream, buffer).await?;
+                }
+                HandshakeAction::WritePacket { sequence_id } => {
+                    write_handshake_payload(&mut self.stream, &mut self.buffer_set, sequence_id)
+                        .await?;
+                    self.buffer_set.read_buffer.clear();
+                    read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
+                }
+                HandshakeAction::UpgradeTls { .. } => {
+                    return Err(Error::LibraryBug(crate::error::eyre!(
+                        "TLS upgrade requested during COM_CHANGE_USER"
+                    )));
+                }
+                HandshakeAction::Finished => break,
+            }
+        }
+        self.auth_plugin = handshake.auth_plugin(&self.buffer_set);
+        handshake.finish()?;
+
+        self.server_status = OkPayload::try_from(OkPayloadBytes(&self.buffer_set.read_buffer))
+            .map_or(
+                self.server_status - crate::constant::ServerStatusFlags::SERVER_STATUS_IN_TRANS,
+                |ok| ok.status_flags,
+            );
+        self.role_changed = false;
+        // The session was reset
+        if let Some(session_setup) = self.session_setup.clone() {
+            self.query_drop(&session_setup).await?;
+        }
+        if self.read_only {
+            self.query_drop(READ_ONLY_SQL).await?;
+        }
         Ok(())
     }
 
+    /// Send the statements of `buffer` in one pipelined batch, then clear it.
+    ///
+    /// Each distinct SQL is prepared once and all executions are written before their responses
+    /// are read, [`PIPELINE_WINDOW`] at a time. Without an open transaction, the batch runs in its
+    /// own transaction that is committed on success and rolled back on error.
+    /// Inside a transaction, the statements after a failing one still run:
+    /// roll the transaction back on error.
+    ///
+    /// Returns the affected rows of each statement. On error, `buffer` is left as it was.
+    pub async fn flush_statements(&mut self, buffer: &mut StatementBuffer) -> Result<Vec<u64>> {
+        let (result, allocs) = alloc_stats::count_async(self.flush_statements_inner(buffer)).await;
+        self.alloc_stats.record(allocs);
+        let affected_rows = self.check_error(result)?;
+        buffer.clear();
+        Ok(affected_rows)
+    }
+
+    async fn flush_statements_inner(&mut self, buffer: &StatementBuffer) -> Result<Vec<u64>> {
+        if buffer.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.emulate_prepared_statements {
+            return Err(Error::BadUsageError(
+                "flush_statements() is not supported with Opts::emulate_prepared_statements"
+                    .to_string(),
+            ));
+        }
+        let mut stmts = Vec::with_capacity(buffer.sqls().len());
+        let mut result = Ok(Vec::new());
+        for sql in buffer.sqls() {
+            match self.prepare_inner(sql).await {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        let begin = !self.in_transaction();
+        if result.is_ok() {
+            result = self.execute_pipelined(buffer, &mut stmts, begin).await;
+            if begin {
+                if result.is_ok() {
+                    if let Err(err) = self.query_drop_inner("COMMIT").await {
+                        result = Err(err);
+                    }
+                } else {
+                    let _ = self.query_drop_inner("ROLLBACK").await;
+                }
+            }
+        }
+
+        // the server does not respond to COM_STMT_CLOSE
+        for stmt in stmts {
+            write_close_statement(self.buffer_set.new_write_buffer(), stmt.id());
+            if let Err(err) = self.write_pipelined().await {
+                return result.and(Err(err));
+            }
+        }
+        if let Err(err) = self.flush_pipelined().await {
+            return result.and(Err(err));
+        }
+        result
+    }
+
+    /// Write `BEGIN` if `begin`, then the executions of `buffer` in windows,
+    /// reading each window's responses after writing it.
+    async fn execute_pipelined(
+        &mut self,
+        buffer: &StatementBuffer,
+        stmts: &mut [PreparedStatement],
+        begin: bool,
+    ) -> Result<Vec<u64>> {
+        let statements: Vec<(usize, usize, &[u8])> = buffer.statements().collect();
+        let mut affected_rows = Vec::with_capacity(statements.len());
+        // A server error does not stop the pipeline; the remaining responses are read first
+        let mut first_error = None;
+
+        if begin {
+            self.write_query_command("BEGIN", NO_ATTRIBUTES)?;
+            self.write_pipelined().await?;
+        }
+        for (window_index, window) in statements.chunks(PIPELINE_WINDOW).enumerate() {
+            for (sql_index, num_params, payload) in window {
+                let statement_id = stmts[*sql_index].id();
+                let query_attributes = self.query_attributes();
+                write_buffered_execute(
+                    self.buffer_set.new_write_buffer(),
+                    payload,
+                    *num_params,
+                    statement_id,
+                    query_attributes,
+                );
+                self.write_pipelined().await?;
+            }
+            self.flush_pipelined().await?;
+            if begin && window_index == 0 {
+                match self.drive_query(&mut DropHandler::default()).await {
+                    Ok(()) => {}
+                    Err(err @ Error::ServerError(_)) => {
+                        first_error.get_or_insert(err);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            for (sql_index, _, _) in window {
+                let mut handler = DropHandler::default();
+                match self.drive_exec(&mut stmts[*sql_index], &mut handler).await {
+                    Ok(()) => affected_rows.push(handler.affected_rows()),
+                    Err(err @ Error::ServerError(_)) => {
+                        first_error.get_or_insert(err);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            if first_error.is_some() {
+                break;
+            }
+        }
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(affected_rows),
+        }
+    }
+
     pub async fn transaction<F, R>(&mut self, f: F) -> Result<R>
     where
-        F: std::ops::AsyncFnOnce(&mut Conn, super::transaction::Transaction) -> Result<R>,
+        F: std::ops::AsyncFnOnce(super::transaction::Transaction<'_>) -> Result<R>,
     {
-        if self.in_transaction {
+        if self.in_transaction() {
             return Err(Error::NestedTransaction);
         }
 
-        self.in_transaction = true;
+        self.query_drop("BEGIN").await?;
 
-        if let Err(err) = self.query_drop("BEGIN").await {
-            self.in_transaction = false;
-            return Err(err);
-        }
+        let result = f(super::transaction::Transaction::new(self)).await;
 
-        let tx = super::transaction::Transaction::new(self.connection_id());
-        let result = f(self, tx).await;
-
-        if self.in_transaction {
-            self.in_transaction = false;
+        if self.in_transaction() {
             match &result {
                 Ok(_) => self.query_drop("COMMIT").await?,
                 Err(_) => {
@@ -615,6 +1899,12 @@ impl Conn {
 
 /// Read a complete MySQL payload asynchronously, concatenating packets if they span multiple 16MB chunks.
 async fn read_payload(reader: &mut Stream, buffer: &mut Vec<u8>) -> Result<u8> {
+    let timeout = reader.read_timeout();
+    timed(timeout, read_packets(reader, buffer)).await
+}
+
+/// [`read_payload`] without the read timeout
+async fn read_packets(reader: &mut Stream, buffer: &mut Vec<u8>) -> Result<u8> {
     let mut packet_header = PacketHeader::new_zeroed();
 
     buffer.clear();
@@ -660,21 +1950,42 @@ async fn write_handshake_payload(
 ) -> Result<()> {
     let mut buffer = buffer_set.write_buffer_mut().as_mut_slice();
     let mut seq_id = sequence_id;
+    let timeout = stream.write_timeout();
+
+    timed(timeout, async {
+        loop {
+            let chunk_size = buffer[4..].len().min(0xFFFFFF);
+            PacketHeader::mut_from_bytes(&mut buffer[0..4])?.encode_in_place(chunk_size, seq_id);
+            stream.write_all(&buffer[..4 + chunk_size]).await?;
 
-    loop {
-        let chunk_size = buffer[4..].len().min(0xFFFFFF);
-        PacketHeader::mut_from_bytes(&mut buffer[0..4])?.encode_in_place(chunk_size, seq_id);
-        stream.write_all(&buffer[..4 + chunk_size]).await?;
+            if chunk_size < 0xFFFFFF {
+                break;
+            }
 
-        if chunk_size < 0xFFFFFF {
-            break;
+            seq_id = seq_id.wrapping_add(1);
+            buffer = &mut buffer[0xFFFFFF..];
         }
+        stream.flush().await?;
+        Ok::<_, Error>(())
+    })
+    .await
+}
 
-        seq_id = seq_id.wrapping_add(1);
-        buffer = &mut buffer[0xFFFFFF..];
-    }
-    stream.flush().await?;
-    Ok(())
+/// Fail with `Error::Timeout` if `io` does not finish within `timeout`
+async fn timed<T, E>(
+    timeout: Option<Duration>,
+    io: impl Future<Output = core::result::Result<T, E>>,
+) -> Result<T>
+where
+    E: Into<Error>,
+{
+    let result = match timeout {
+        Some(timeout) => compio::time::timeout(timeout, io)
+            .await
+            .map_err(|_elapsed| Error::Timeout)?,
+        None => io.await,
+    };
+    result.map_err(Into::into)
 }
 
 /// Handler to capture socket path from SELECT @@socket query
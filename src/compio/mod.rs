@@ -7,4 +7,4 @@ mod transaction;
 
 pub use conn::Conn;
 pub use pool::{Pool, PooledConn};
-pub use transaction::Transaction;
+pub use transaction::{Savepoint, Transaction};
@@ -1,66 +1,269 @@
 //! Asynchronous connection pool for compio (single-threaded, Rc-based).
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::fmt;
 use std::mem::ManuallyDrop;
+use std::ops::AsyncFnOnce;
 use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
+use std::time::Instant;
 
 use crate::error::Result;
 use crate::opts::Opts;
+use crate::pool_sizing::PoolSizer;
+use crate::protocol::r#trait::param::Params;
+use crate::raw::FromRow;
 
 use super::Conn;
+use super::transaction::Transaction;
 
 pub struct Pool {
-    opts: Opts,
-    conns: RefCell<Vec<Conn>>,
-    max_idle: usize,
+    opts: RefCell<Rc<Opts>>,
+    /// Incremented by `update_opts()`
+    generation: Cell<u64>,
+    /// Idle connections with the generation they were opened in
+    conns: RefCell<Vec<(Conn, u64)>>,
+    max_idle: Cell<usize>,
+    sizer: Option<PoolSizer>,
+    in_use: Cell<usize>,
 }
 
 impl Pool {
     pub fn new(opts: Opts) -> Rc<Self> {
         let max_idle = opts.pool_max_idle_conn;
+        let sizer = opts.pool_adaptive_sizing.clone().map(PoolSizer::new);
         Rc::new(Self {
-            opts,
+            opts: RefCell::new(Rc::new(opts)),
+            generation: Cell::new(0),
             conns: RefCell::new(Vec::new()),
-            max_idle,
+            max_idle: Cell::new(max_idle),
+            sizer,
+            in_use: Cell::new(0),
         })
     }
 
+    /// The options new connections are opened with.
+    pub fn opts(&self) -> Rc<Opts> {
+        Rc::clone(&self.opts.borrow())
+    }
+
+    /// Replace the options without recreating the pool.
+    ///
+    /// New connections are opened with `opts`, so a changed host, user, password or
+    /// `password_file` takes effect on the next connect, and `pool_max_idle_conn` applies
+    /// immediately. `pool_adaptive_sizing` keeps the settings the pool was created with.
+    ///
+    /// Existing connections are phased out: idle ones are closed when they would be checked out,
+    /// checked-out ones when they are returned.
+    pub fn update_opts(&self, opts: Opts) {
+        tracing::info!(host = %opts.host, "pool options updated");
+        self.max_idle.set(opts.pool_max_idle_conn);
+        *self.opts.borrow_mut() = Rc::new(opts);
+        self.generation.set(self.generation.get() + 1);
+    }
+
+    /// Close the idle connections and phase out the checked-out ones, which are closed when they
+    /// are returned. New connections are opened on demand.
+    ///
+    /// Called when a connection is returned after the server reported `ER_SERVER_SHUTDOWN`, so
+    /// that a restarted server does not see a storm of commands on dead connections.
+    pub fn drain_idle(&self) {
+        self.generation.set(self.generation.get() + 1);
+        self.conns.borrow_mut().clear();
+    }
+
+    /// The number of idle connections the pool currently keeps.
+    ///
+    /// This changes over time when `Opts::pool_adaptive_sizing` is set.
+    pub fn idle_capacity(&self) -> usize {
+        self.sizer
+            .as_ref()
+            .map_or(self.max_idle.get(), PoolSizer::target)
+    }
+
+    /// The number of idle connections in the pool.
+    pub fn idle_count(&self) -> usize {
+        self.conns.borrow().len()
+    }
+
+    /// The number of connections currently checked out of the pool.
+    pub fn in_use_count(&self) -> usize {
+        self.in_use.get()
+    }
+
     pub async fn get(self: &Rc<Self>) -> Result<PooledConn> {
-        let conn = loop {
+        let started = Instant::now();
+        let (conn, generation) = loop {
             let candidate = self.conns.borrow_mut().pop();
             match candidate {
-                Some(mut c) => {
+                // Opened before `update_opts()`
+                Some((_, generation)) if generation != self.generation.get() => {}
+                Some((mut c, generation)) => {
                     if c.ping().await.is_ok() {
-                        break c;
+                        break (c, generation);
                     }
                 }
-                None => break Conn::new(self.opts.clone()).await?,
+                None => {
+                    let generation = self.generation.get();
+                    let opts = (*self.opts()).clone();
+                    let conn = Conn::new(opts).await?;
+                    tracing::debug!(conn = %conn, "opened a pooled connection");
+                    break (conn, generation);
+                }
             }
         };
+        if let Some(sizer) = &self.sizer {
+            sizer.record_acquire(started.elapsed(), self.conns.borrow().len());
+        }
+        self.in_use.set(self.in_use.get() + 1);
         Ok(PooledConn {
             conn: ManuallyDrop::new(conn),
+            generation,
             pool: Rc::clone(self),
         })
     }
 
-    async fn check_in(&self, mut conn: Conn) {
-        if conn.is_broken() {
+    /// Acquire a connection with `role` activated (`SET ROLE`).
+    ///
+    /// The role is reset when the connection is returned to the pool.
+    pub async fn get_with_role(self: &Rc<Self>, role: &str) -> Result<PooledConn> {
+        let mut conn = self.get().await?;
+        conn.set_role(role).await?;
+        Ok(conn)
+    }
+
+    /// Acquire a connection, execute a text protocol query, and discard the result.
+    pub async fn query_drop(self: &Rc<Self>, sql: &str) -> Result<()> {
+        self.get().await?.query_drop(sql).await
+    }
+
+    /// Acquire a connection, execute `sql` without parameters, and collect all rows.
+    ///
+    /// The statement is prepared and closed for this call only.
+    /// For repeated executions, prepare it on a connection from `get()` instead.
+    pub async fn query_rows<Row>(self: &Rc<Self>, sql: &str) -> Result<Vec<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+    {
+        self.exec_collect(sql, ()).await
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and discard the result.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub async fn exec_drop<P: Params>(self: &Rc<Self>, sql: &str, params: P) -> Result<()> {
+        let mut conn = self.get().await?;
+        let mut stmt = conn.prepare(sql).await?;
+        let result = conn.exec_drop(&mut stmt, params).await;
+        let closed = conn.close_statement(stmt).await;
+        result?;
+        closed
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and return the first row.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub async fn exec_first<Row, P>(self: &Rc<Self>, sql: &str, params: P) -> Result<Option<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+        P: Params,
+    {
+        let mut conn = self.get().await?;
+        let mut stmt = conn.prepare(sql).await?;
+        let result = conn.exec_first(&mut stmt, params).await;
+        let closed = conn.close_statement(stmt).await;
+        let value = result?;
+        closed?;
+        Ok(value)
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and collect all rows.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub async fn exec_collect<Row, P>(self: &Rc<Self>, sql: &str, params: P) -> Result<Vec<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+        P: Params,
+    {
+        let mut conn = self.get().await?;
+        let mut stmt = conn.prepare(sql).await?;
+        let result = conn.exec_collect(&mut stmt, params).await;
+        let closed = conn.close_statement(stmt).await;
+        let value = result?;
+        closed?;
+        Ok(value)
+    }
+
+    /// Acquire a connection and run `f` in a transaction on it.
+    ///
+    /// See `Conn::transaction`.
+    pub async fn run_transaction<F, R>(self: &Rc<Self>, f: F) -> Result<R>
+    where
+        F: AsyncFnOnce(Transaction<'_>) -> Result<R>,
+    {
+        self.get().await?.transaction(f).await
+    }
+
+    async fn check_in(&self, mut conn: Conn, generation: u64) {
+        self.in_use.set(self.in_use.get().saturating_sub(1));
+        if let Some(sizer) = &self.sizer {
+            sizer.record_release();
+        }
+        if conn.is_server_shutdown() {
+            tracing::warn!(conn = %conn, "server is shutting down, draining the pool");
+            self.drain_idle();
             return;
         }
-        if conn.reset().await.is_err() {
+        if conn.is_broken() || generation != self.generation.get() {
+            return;
+        }
+        let reset = if conn
+            .dialect()
+            .supports_reset_connection(conn.server_version())
+        {
+            conn.reset().await
+        } else {
+            // Without COM_RESET_CONNECTION, only end the transaction
+            conn.query_drop("ROLLBACK").await
+        };
+        if reset.is_err() {
             return;
         }
         let mut conns = self.conns.borrow_mut();
-        if conns.len() < self.max_idle {
-            conns.push(conn);
+        if conns.len() < self.idle_capacity() {
+            conns.push((conn, generation));
         }
     }
 }
 
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("idle", &self.idle_count())
+            .field("in_use", &self.in_use_count())
+            .field("idle_capacity", &self.idle_capacity())
+            .field("max_concurrency", &self.opts().pool_max_concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+/// `pool (2 in use, 3 idle, idle capacity 100)`
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pool ({} in use, {} idle, idle capacity {})",
+            self.in_use_count(),
+            self.idle_count(),
+            self.idle_capacity()
+        )
+    }
+}
+
 pub struct PooledConn {
     pool: Rc<Pool>,
     conn: ManuallyDrop<Conn>,
+    generation: u64,
 }
 
 impl Deref for PooledConn {
@@ -81,8 +284,9 @@ impl Drop for PooledConn {
         // SAFETY: conn is never accessed after this
         let conn = unsafe { ManuallyDrop::take(&mut self.conn) };
         let pool = Rc::clone(&self.pool);
+        let generation = self.generation;
         compio::runtime::spawn(async move {
-            pool.check_in(conn).await;
+            pool.check_in(conn, generation).await;
         })
         .detach();
     }
@@ -5,24 +5,36 @@
 //! `read_exact(payload)` would each be a separate io_uring submission.
 //! With buffering, a single read fills the buffer and subsequent message
 //! parses are served from memory.
+//!
+//! With `Opts::registered_read_buffers`, TCP and Unix reads go through an io_uring
+//! provided-buffer ring set up once per connection. The kernel picks a free buffer for every read
+//! and the filled bytes are copied into the read buffer before the ring buffer is returned.
 
 use std::mem::MaybeUninit;
+use std::time::Duration;
 
-use compio::buf::BufResult;
-use compio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use compio::buf::{BufResult, IntoInner, IoBufMut};
+use compio::io::{AsyncRead, AsyncReadManaged, AsyncWrite, AsyncWriteExt};
 use compio::net::TcpStream;
 #[cfg(unix)]
 use compio::net::UnixStream;
+use compio::runtime::BufferPool;
 
 #[cfg(feature = "compio-tls")]
 use compio::tls::TlsStream;
+use zerocopy::{FromZeros, IntoBytes};
 
-const READ_BUF_CAPACITY: usize = 8192;
+use crate::opts::DEFAULT_READ_BUFFER_SIZE;
+use crate::protocol::compression::{CompressedPacketHeader, CompressionStats, PacketCompression};
+use crate::socket_stats::SocketStats;
+#[cfg(feature = "compio-tls")]
+use crate::tls::{Tls, TlsBackend};
 
 enum StreamInner {
     Tcp(TcpStream),
     #[cfg(feature = "compio-tls")]
-    Tls(TlsStream<TcpStream>),
+    /// Boxed, rustls streams are over 1 KiB
+    Tls(Box<TlsStream<TcpStream>>),
     #[cfg(unix)]
     Unix(UnixStream),
 }
@@ -35,39 +47,69 @@ pub struct Stream {
     inner: StreamInner,
     read_buf: Vec<u8>,
     read_pos: usize,
+    /// `0` if every read is sized to the bytes still needed
+    read_buffer_size: usize,
+    /// Buffers registered with the ring, see [`Stream::register_read_buffers`]
+    registered_buffers: Option<BufferPool>,
+    /// Set after `CLIENT_COMPRESS` is negotiated
+    compression: Option<Box<PacketCompression>>,
+    /// Writes held back until the next flush, see [`Stream::write_deferred`]
+    pending: Vec<u8>,
+    /// Set `TCP_QUICKACK` after every flush
+    quickack: bool,
+    /// See [`Stream::set_timeouts`]
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
 }
 
 impl Stream {
-    pub fn tcp(stream: TcpStream) -> Self {
+    fn new(inner: StreamInner, read_buffer_size: usize) -> Self {
         Self {
-            inner: StreamInner::Tcp(stream),
-            read_buf: Vec::with_capacity(READ_BUF_CAPACITY),
+            inner,
+            read_buf: Vec::with_capacity(read_buffer_size),
             read_pos: 0,
+            read_buffer_size,
+            registered_buffers: None,
+            compression: None,
+            pending: Vec::new(),
+            quickack: false,
+            read_timeout: None,
+            write_timeout: None,
         }
     }
 
+    pub fn tcp(stream: TcpStream) -> Self {
+        Self::tcp_with_capacity(stream, DEFAULT_READ_BUFFER_SIZE)
+    }
+
+    /// `read_buffer_size` of `0` reads exactly the requested bytes from the socket.
+    pub fn tcp_with_capacity(stream: TcpStream, read_buffer_size: usize) -> Self {
+        Self::new(StreamInner::Tcp(stream), read_buffer_size)
+    }
+
     #[cfg(unix)]
     pub fn unix(stream: UnixStream) -> Self {
-        Self {
-            inner: StreamInner::Unix(stream),
-            read_buf: Vec::with_capacity(READ_BUF_CAPACITY),
-            read_pos: 0,
-        }
+        Self::unix_with_capacity(stream, DEFAULT_READ_BUFFER_SIZE)
+    }
+
+    /// `read_buffer_size` of `0` reads exactly the requested bytes from the socket.
+    #[cfg(unix)]
+    pub fn unix_with_capacity(stream: UnixStream, read_buffer_size: usize) -> Self {
+        Self::new(StreamInner::Unix(stream), read_buffer_size)
     }
 
     #[cfg(feature = "compio-tls")]
     pub async fn upgrade_to_tls(self, host: &str) -> std::io::Result<Self> {
         match self.inner {
             StreamInner::Tcp(tcp_stream) => {
-                let native_connector =
-                    compio::native_tls::TlsConnector::new().map_err(std::io::Error::other)?;
-                let connector = compio::tls::TlsConnector::from(native_connector);
+                let connector = Tls::compio_connector()?;
                 let tls_stream = connector.connect(host, tcp_stream).await?;
-                Ok(Self {
-                    inner: StreamInner::Tls(tls_stream),
-                    read_buf: Vec::with_capacity(READ_BUF_CAPACITY),
-                    read_pos: 0,
-                })
+                let mut stream = Self::new(
+                    StreamInner::Tls(Box::new(tls_stream)),
+                    self.read_buffer_size,
+                );
+                stream.set_timeouts(self.read_timeout, self.write_timeout);
+                Ok(stream)
             }
             #[cfg(feature = "compio-tls")]
             StreamInner::Tls(_) => Err(std::io::Error::new(
@@ -82,6 +124,62 @@ impl Stream {
         }
     }
 
+    /// Read through a ring of `count` buffers of `read_buffer_size` bytes provided to the
+    /// runtime's driver (an io_uring provided-buffer ring), copying each filled buffer into the
+    /// read buffer. `count` is rounded up to a power of two.
+    ///
+    /// Returns `false` without registering anything if `count` or `read_buffer_size` is `0` or
+    /// the stream is TLS, which reads through its own buffer. Logs a warning and returns `false`
+    /// if the driver cannot register the buffers.
+    pub fn register_read_buffers(&mut self, count: usize) -> bool {
+        #[cfg(feature = "compio-tls")]
+        if matches!(self.inner, StreamInner::Tls(_)) {
+            return false;
+        }
+        if count == 0 || self.read_buffer_size == 0 {
+            return false;
+        }
+        let Some(ring_len) = u16::try_from(count.next_power_of_two())
+            .ok()
+            .filter(|len| *len <= 1 << 15)
+        else {
+            tracing::warn!(
+                count,
+                "too many registered read buffers, reading without them"
+            );
+            return false;
+        };
+        match BufferPool::new(ring_len, self.read_buffer_size) {
+            Ok(pool) => {
+                self.registered_buffers = Some(pool);
+                true
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "reading without registered buffers");
+                false
+            }
+        }
+    }
+
+    pub fn has_registered_read_buffers(&self) -> bool {
+        self.registered_buffers.is_some()
+    }
+
+    /// Wrap all subsequent packets in the compressed protocol.
+    pub fn enable_compression(&mut self, compression: PacketCompression) {
+        self.compression = Some(Box::new(compression));
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compression.is_some()
+    }
+
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.compression
+            .as_ref()
+            .map(|compression| compression.stats())
+    }
+
     // --- Buffered read internals ---
 
     fn available(&self) -> usize {
@@ -89,7 +187,9 @@ impl Stream {
     }
 
     /// Compact the buffer and read more data from the socket.
-    async fn fill_buf(&mut self) -> std::io::Result<()> {
+    ///
+    /// Without buffering, reads at most `wanted` bytes.
+    async fn fill_buf(&mut self, wanted: usize) -> std::io::Result<()> {
         if self.read_pos > 0 {
             let valid = self.available();
             self.read_buf
@@ -98,8 +198,27 @@ impl Stream {
             self.read_pos = 0;
         }
 
-        let buf = std::mem::take(&mut self.read_buf);
-        let BufResult(result, buf) = self.read_raw(buf).await;
+        if let Some(result) = self.read_registered().await {
+            return match result? {
+                0 => Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "connection closed",
+                )),
+                _ => Ok(()),
+            };
+        }
+
+        let mut buf = std::mem::take(&mut self.read_buf);
+        let BufResult(result, buf) = if self.read_buffer_size == 0 {
+            let start = buf.len();
+            buf.reserve(wanted);
+            let BufResult(result, slice) = self
+                .read_raw(compio::buf::IoBuf::slice(buf, start..start + wanted))
+                .await;
+            BufResult(result, slice.into_inner())
+        } else {
+            self.read_raw(buf).await
+        };
         self.read_buf = buf;
         let n = result?;
         if n == 0 {
@@ -111,10 +230,63 @@ impl Stream {
         Ok(())
     }
 
+    /// Read the next compressed packet into `compression`
+    async fn read_compressed(
+        &mut self,
+        compression: &mut PacketCompression,
+    ) -> std::io::Result<()> {
+        let mut header = CompressedPacketHeader::new_zeroed();
+        self.read_exact_buffered(header.as_mut_bytes()).await?;
+        self.read_exact_buffered(compression.body_mut(&header))
+            .await?;
+        compression
+            .decode(&header)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+
     // --- Public read API ---
 
     /// Read exactly `buf.len()` bytes into the provided buffer.
-    pub async fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+    pub async fn read_exact(&mut self, mut buf: &mut [u8]) -> std::io::Result<()> {
+        let Some(mut compression) = self.compression.take() else {
+            return self.read_exact_buffered(buf).await;
+        };
+        let mut result = Ok(());
+        while !buf.is_empty() {
+            if compression.is_empty() {
+                result = self.read_compressed(&mut compression).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+            let len = compression.read(buf);
+            buf = &mut buf[len..];
+        }
+        self.compression = Some(compression);
+        result
+    }
+
+    /// Read exactly `buf.len()` bytes into uninitialized memory.
+    pub async fn read_buf_exact(&mut self, mut buf: &mut [MaybeUninit<u8>]) -> std::io::Result<()> {
+        let Some(mut compression) = self.compression.take() else {
+            return self.read_buf_exact_buffered(buf).await;
+        };
+        let mut result = Ok(());
+        while !buf.is_empty() {
+            if compression.is_empty() {
+                result = self.read_compressed(&mut compression).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+            let len = compression.read_uninit(buf);
+            buf = &mut buf[len..];
+        }
+        self.compression = Some(compression);
+        result
+    }
+
+    async fn read_exact_buffered(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
         let mut filled = 0;
         while filled < buf.len() {
             let avail = self.available();
@@ -125,14 +297,16 @@ impl Stream {
                 self.read_pos += to_copy;
                 filled += to_copy;
             } else {
-                self.fill_buf().await?;
+                self.fill_buf(buf.len() - filled).await?;
             }
         }
         Ok(())
     }
 
-    /// Read exactly `buf.len()` bytes into uninitialized memory.
-    pub async fn read_buf_exact(&mut self, buf: &mut [MaybeUninit<u8>]) -> std::io::Result<()> {
+    async fn read_buf_exact_buffered(
+        &mut self,
+        buf: &mut [MaybeUninit<u8>],
+    ) -> std::io::Result<()> {
         let mut filled = 0;
         while filled < buf.len() {
             let avail = self.available();
@@ -151,7 +325,7 @@ impl Stream {
                 self.read_pos += to_copy;
                 filled += to_copy;
             } else {
-                self.fill_buf().await?;
+                self.fill_buf(buf.len() - filled).await?;
             }
         }
         Ok(())
@@ -159,7 +333,7 @@ impl Stream {
 
     // --- Raw (unbuffered) I/O ---
 
-    async fn read_raw(&mut self, buf: Vec<u8>) -> BufResult<usize, Vec<u8>> {
+    async fn read_raw<B: IoBufMut>(&mut self, buf: B) -> BufResult<usize, B> {
         match &mut self.inner {
             StreamInner::Tcp(r) => r.read(buf).await,
             #[cfg(feature = "compio-tls")]
@@ -169,28 +343,124 @@ impl Stream {
         }
     }
 
+    /// Read into a buffer of the ring and copy it to the end of the read buffer.
+    /// compio has no fixed-buffer reads into memory we own, so the copy cannot be avoided.
+    /// `None` if the stream has no registered buffers.
+    async fn read_registered(&mut self) -> Option<std::io::Result<usize>> {
+        let pool = self.registered_buffers.as_ref()?;
+        let filled = match &mut self.inner {
+            StreamInner::Tcp(r) => r.read_managed(pool, 0).await,
+            #[cfg(feature = "compio-tls")]
+            StreamInner::Tls(_) => return None,
+            #[cfg(unix)]
+            StreamInner::Unix(r) => r.read_managed(pool, 0).await,
+        };
+        Some(filled.map(|buffer| {
+            self.read_buf.extend_from_slice(&buffer);
+            buffer.len()
+        }))
+    }
+
     // --- Write API (pass-through, no buffering needed) ---
 
     pub async fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
-        let owned = buf.to_vec();
-        let BufResult(result, _) = match &mut self.inner {
+        if let Some(compression) = &mut self.compression {
+            compression.write(buf);
+            return Ok(());
+        }
+        // keep the order of the deferred writes
+        if !self.pending.is_empty() {
+            self.pending.extend_from_slice(buf);
+            return Ok(());
+        }
+        self.write_raw(buf).await
+    }
+
+    /// Hold `buf` back until the next [`Stream::flush`], which writes everything held back at once.
+    pub fn write_deferred(&mut self, buf: &[u8]) {
+        match &mut self.compression {
+            Some(compression) => compression.write(buf),
+            None => self.pending.extend_from_slice(buf),
+        }
+    }
+
+    async fn write_raw(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        let BufResult(result, _) = self.write_owned(buf.to_vec()).await;
+        result
+    }
+
+    async fn write_owned(&mut self, owned: Vec<u8>) -> BufResult<(), Vec<u8>> {
+        match &mut self.inner {
             StreamInner::Tcp(r) => r.write_all(owned).await,
             #[cfg(feature = "compio-tls")]
             StreamInner::Tls(r) => r.write_all(owned).await,
             #[cfg(unix)]
             StreamInner::Unix(r) => r.write_all(owned).await,
-        };
-        result
+        }
     }
 
     pub async fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(mut compression) = self.compression.take() {
+            let result = match compression.encode() {
+                Ok(wire) => self.write_raw(wire).await,
+                Err(e) => Err(std::io::Error::other(e.to_string())),
+            };
+            self.compression = Some(compression);
+            result?;
+        } else if !self.pending.is_empty() {
+            let pending = std::mem::take(&mut self.pending);
+            let BufResult(result, mut pending) = self.write_owned(pending).await;
+            pending.clear();
+            self.pending = pending;
+            result?;
+        }
         match &mut self.inner {
-            StreamInner::Tcp(r) => r.flush().await,
+            StreamInner::Tcp(r) => r.flush().await?,
             #[cfg(feature = "compio-tls")]
-            StreamInner::Tls(r) => r.flush().await,
+            StreamInner::Tls(r) => r.flush().await?,
             #[cfg(unix)]
-            StreamInner::Unix(r) => r.flush().await,
+            StreamInner::Unix(r) => r.flush().await?,
+        }
+        if self.quickack {
+            self.enable_quickack()?;
         }
+        Ok(())
+    }
+
+    /// Set `TCP_QUICKACK` after every flush.
+    /// Ignored on TLS and Unix sockets and on platforms other than Linux.
+    pub fn set_quickack(&mut self, quickack: bool) {
+        self.quickack = quickack;
+    }
+
+    /// Limit the time the connection waits for a payload to be read or written.
+    /// `None` waits forever.
+    pub fn set_timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) {
+        self.read_timeout = read;
+        self.write_timeout = write;
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    fn enable_quickack(&self) -> std::io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+            let fd = match &self.inner {
+                StreamInner::Tcp(r) => r.as_raw_fd(),
+                #[cfg(feature = "compio-tls")]
+                StreamInner::Tls(_) => return Ok(()),
+                StreamInner::Unix(_) => return Ok(()),
+            };
+            crate::tcp_quickack::enable(fd)?;
+        }
+        Ok(())
     }
 
     // --- Misc ---
@@ -207,4 +477,22 @@ impl Stream {
             StreamInner::Unix(_) => false,
         }
     }
+
+    /// TCP statistics of the socket.
+    /// `None` for Unix sockets and on platforms other than Linux.
+    pub fn socket_stats(&self) -> std::io::Result<Option<SocketStats>> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+            let fd = match &self.inner {
+                StreamInner::Tcp(r) => r.as_raw_fd(),
+                #[cfg(feature = "compio-tls")]
+                StreamInner::Tls(_) => return Ok(None),
+                StreamInner::Unix(_) => return Ok(None),
+            };
+            crate::socket_stats::tcp_socket_stats(fd).map(Some)
+        }
+        #[cfg(not(target_os = "linux"))]
+        Ok(None)
+    }
 }
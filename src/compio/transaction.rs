@@ -1,37 +1,172 @@
+use std::ops::{Deref, DerefMut};
+
 use super::Conn;
-use crate::error::{Error, Result};
+use crate::error::Result;
+use crate::quote::quote_identifier;
+use crate::statement_buffer::StatementBuffer;
 
-/// A MySQL transaction for the compio async connection.
-pub struct Transaction {
-    connection_id: u64,
+/// A MySQL transaction for the compio async connection
+///
+/// Passed to the closure of [`Conn::transaction`]. The transaction borrows the connection, so
+/// every statement inside the closure runs through it: `tx.query_drop(..)`, `tx.exec(..)` and
+/// the other `Conn` methods are available through `Deref`.
+/// `commit` and `rollback` consume the transaction. [`savepoint`](Self::savepoint) nests a scope
+/// that can be rolled back on its own.
+pub struct Transaction<'conn> {
+    conn: &'conn mut Conn,
 }
 
-impl Transaction {
-    pub(crate) fn new(connection_id: u64) -> Self {
-        Self { connection_id }
+impl<'conn> Transaction<'conn> {
+    pub(crate) fn new(conn: &'conn mut Conn) -> Self {
+        Self { conn }
+    }
+
+    /// Commit the transaction
+    pub async fn commit(self) -> Result<()> {
+        self.conn.query_drop("COMMIT").await
     }
 
-    pub async fn commit(self, conn: &mut Conn) -> Result<()> {
-        let actual = conn.connection_id();
-        if self.connection_id != actual {
-            return Err(Error::ConnectionMismatch {
-                expected: self.connection_id,
-                actual,
-            });
+    /// Send the statements of `buffer` in one pipelined batch, then commit
+    ///
+    /// See [`Conn::flush_statements`]. If a statement fails, the transaction is rolled back
+    /// and the error is returned.
+    pub async fn commit_buffered(self, buffer: &mut StatementBuffer) -> Result<Vec<u64>> {
+        match self.conn.flush_statements(buffer).await {
+            Ok(affected_rows) => {
+                self.conn.query_drop("COMMIT").await?;
+                Ok(affected_rows)
+            }
+            Err(err) => {
+                let _ = self.conn.query_drop("ROLLBACK").await;
+                Err(err)
+            }
         }
-        conn.set_in_transaction(false);
-        conn.query_drop("COMMIT").await
     }
 
-    pub async fn rollback(self, conn: &mut Conn) -> Result<()> {
-        let actual = conn.connection_id();
-        if self.connection_id != actual {
-            return Err(Error::ConnectionMismatch {
-                expected: self.connection_id,
-                actual,
-            });
+    /// Rollback the transaction
+    pub async fn rollback(self) -> Result<()> {
+        self.conn.query_drop("ROLLBACK").await
+    }
+
+    /// Execute a closure within a savepoint (`SAVEPOINT name`)
+    ///
+    /// If the closure returns `Ok`, the savepoint is released (`RELEASE SAVEPOINT`). If it returns
+    /// `Err`, the statements since the savepoint are rolled back (`ROLLBACK TO SAVEPOINT`) and the
+    /// transaction continues. Savepoints nest through [`Savepoint::savepoint`].
+    pub async fn savepoint<F, R>(&mut self, name: &str, f: F) -> Result<R>
+    where
+        F: AsyncFnOnce(Savepoint<'_>) -> Result<R>,
+    {
+        run_savepoint(self.conn, name, f).await
+    }
+}
+
+impl Deref for Transaction<'_> {
+    type Target = Conn;
+    fn deref(&self) -> &Self::Target {
+        self.conn
+    }
+}
+
+impl DerefMut for Transaction<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+    }
+}
+
+/// A savepoint within a [`Transaction`]
+///
+/// Passed to the closure of [`Transaction::savepoint`]. The `Conn` methods are available
+/// through `Deref`. `release` and `rollback` consume the savepoint.
+pub struct Savepoint<'conn> {
+    conn: &'conn mut Conn,
+    name: &'conn str,
+    ended: &'conn mut bool,
+}
+
+impl Savepoint<'_> {
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Keep the statements since the savepoint (`RELEASE SAVEPOINT`)
+    pub async fn release(self) -> Result<()> {
+        *self.ended = true;
+        self.conn
+            .query_drop(&format!(
+                "RELEASE SAVEPOINT {}",
+                quote_identifier(self.name)
+            ))
+            .await
+    }
+
+    /// Undo the statements since the savepoint (`ROLLBACK TO SAVEPOINT`)
+    ///
+    /// The enclosing transaction continues.
+    pub async fn rollback(self) -> Result<()> {
+        *self.ended = true;
+        self.conn
+            .query_drop(&format!(
+                "ROLLBACK TO SAVEPOINT {}",
+                quote_identifier(self.name)
+            ))
+            .await
+    }
+
+    /// Execute a closure within a nested savepoint
+    ///
+    /// See [`Transaction::savepoint`].
+    pub async fn savepoint<F, R>(&mut self, name: &str, f: F) -> Result<R>
+    where
+        F: AsyncFnOnce(Savepoint<'_>) -> Result<R>,
+    {
+        run_savepoint(self.conn, name, f).await
+    }
+}
+
+impl Deref for Savepoint<'_> {
+    type Target = Conn;
+    fn deref(&self) -> &Self::Target {
+        self.conn
+    }
+}
+
+impl DerefMut for Savepoint<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+    }
+}
+
+async fn run_savepoint<F, R>(conn: &mut Conn, name: &str, f: F) -> Result<R>
+where
+    F: AsyncFnOnce(Savepoint<'_>) -> Result<R>,
+{
+    let quoted = quote_identifier(name);
+    conn.query_drop(&format!("SAVEPOINT {quoted}")).await?;
+
+    let mut ended = false;
+    let result = f(Savepoint {
+        conn: &mut *conn,
+        name,
+        ended: &mut ended,
+    })
+    .await;
+
+    // If no explicit release/rollback was called, release on Ok, rollback on Err.
+    // A savepoint ends with its transaction.
+    if !ended && conn.in_transaction() {
+        match &result {
+            Ok(_) => {
+                conn.query_drop(&format!("RELEASE SAVEPOINT {quoted}"))
+                    .await?
+            }
+            Err(_) => {
+                let _ = conn
+                    .query_drop(&format!("ROLLBACK TO SAVEPOINT {quoted}"))
+                    .await;
+            }
         }
-        conn.set_in_transaction(false);
-        conn.query_drop("ROLLBACK").await
     }
+
+    result
 }
@@ -137,7 +137,9 @@ pub const CAPABILITIES_ALWAYS_ENABLED: CapabilityFlags = CapabilityFlags::CLIENT
     .union(CapabilityFlags::CLIENT_SECURE_CONNECTION) // On? Off?
     .union(CapabilityFlags::CLIENT_PLUGIN_AUTH)
     .union(CapabilityFlags::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA)
-    .union(CapabilityFlags::CLIENT_DEPRECATE_EOF);
+    .union(CapabilityFlags::CLIENT_DEPRECATE_EOF)
+    .union(CapabilityFlags::CLIENT_SESSION_TRACK)
+    .union(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES);
 
 // Capabilities that are configurable by user
 pub const CAPABILITIES_CONFIGURABLE: CapabilityFlags = CapabilityFlags::CLIENT_FOUND_ROWS
@@ -155,15 +157,13 @@ pub const CAPABILITIES_ALWAYS_DISABLED: CapabilityFlags =
         .union(CapabilityFlags::CLIENT_INTERACTIVE)
         .union(CapabilityFlags::CLIENT_IGNORE_SIGPIPE)
         .union(CapabilityFlags::CLIENT_RESERVED)
-        .union(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES)
-        .union(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM)
+        .union(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM) // set by opts.compression_algorithm
         .union(CapabilityFlags::CLIENT_MULTI_FACTOR_AUTHENTICATION)
         .union(CapabilityFlags::CLIENT_CAPABILITY_EXTENSION)
         .union(CapabilityFlags::CLIENT_SSL) // set by opts.tls
         .union(CapabilityFlags::CLIENT_SSL_VERIFY_SERVER_CERT)
         .union(CapabilityFlags::CLIENT_REMEMBER_OPTIONS)
-        .union(CapabilityFlags::CLIENT_CONNECT_ATTRS) // TODO
-        .union(CapabilityFlags::CLIENT_SESSION_TRACK); // To support this flag, we need to update the parsing logic
+        .union(CapabilityFlags::CLIENT_CONNECT_ATTRS); // set by opts.application_name
 
 bitflags::bitflags! {
     /// MariaDB Extension Capability Flags
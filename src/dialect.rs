@@ -0,0 +1,78 @@
+use std::fmt;
+
+use crate::constant::CapabilityFlags;
+use crate::pool_config::supports_reset_connection;
+
+/// The server or proxy on the other end of the MySQL protocol.
+///
+/// Detected from the initial handshake unless set with `Opts::dialect`. The dialect switches off
+/// protocol features that MySQL-compatible servers implement differently:
+///
+/// | | `COM_RESET_CONNECTION` | `CLIENT_SESSION_TRACK`, `CLIENT_QUERY_ATTRIBUTES` |
+/// |---|---|---|
+/// | `MySql` | 5.7.3+ | requested |
+/// | `MariaDb` | 10.2.4+ | requested |
+/// | `TiDb` | not used | not requested |
+/// | `Vitess` | not used | not requested |
+///
+/// Without `COM_RESET_CONNECTION`, `Conn::reset()` returns `Error::Unsupported` and the pools
+/// clean up returned connections with `ROLLBACK` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    MySql,
+    MariaDb,
+    /// TiDB, detected by `TiDB` in the server version
+    TiDb,
+    /// A Vitess `vtgate`, detected by `Vitess` in the server version
+    Vitess,
+}
+
+impl Dialect {
+    /// Detect the dialect from the server version and capabilities of the initial handshake.
+    pub fn detect(server_version: &[u8], capability_flags: CapabilityFlags) -> Self {
+        let contains = |needle: &[u8]| {
+            server_version
+                .windows(needle.len())
+                .any(|window| window.eq_ignore_ascii_case(needle))
+        };
+        if contains(b"TiDB") {
+            Self::TiDb
+        } else if contains(b"Vitess") {
+            Self::Vitess
+        } else if capability_flags.is_mariadb() {
+            Self::MariaDb
+        } else {
+            Self::MySql
+        }
+    }
+
+    /// Returns true if `COM_RESET_CONNECTION` can be sent to a server of this version.
+    pub fn supports_reset_connection(&self, server_version: &[u8]) -> bool {
+        match self {
+            Self::MySql => supports_reset_connection(server_version, false),
+            Self::MariaDb => supports_reset_connection(server_version, true),
+            Self::TiDb | Self::Vitess => false,
+        }
+    }
+
+    /// Capabilities that are never requested from this dialect.
+    pub fn disabled_capabilities(&self) -> CapabilityFlags {
+        match self {
+            Self::MySql | Self::MariaDb => CapabilityFlags::empty(),
+            Self::TiDb | Self::Vitess => {
+                CapabilityFlags::CLIENT_SESSION_TRACK | CapabilityFlags::CLIENT_QUERY_ATTRIBUTES
+            }
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MySql => "MySQL",
+            Self::MariaDb => "MariaDB",
+            Self::TiDb => "TiDB",
+            Self::Vitess => "Vitess",
+        })
+    }
+}
@@ -0,0 +1,33 @@
+use crate::constant::CapabilityFlags;
+use crate::dialect::Dialect;
+use crate::test_macros::{check, check_eq};
+
+#[test]
+fn detect_from_handshake() -> crate::error::Result<()> {
+    let mysql = CapabilityFlags::CLIENT_LONG_PASSWORD | CapabilityFlags::CLIENT_PROTOCOL_41;
+    let mariadb = CapabilityFlags::CLIENT_PROTOCOL_41;
+    check_eq!(Dialect::detect(b"8.0.36", mysql), Dialect::MySql);
+    check_eq!(
+        Dialect::detect(b"5.5.5-10.11.6-MariaDB", mariadb),
+        Dialect::MariaDb
+    );
+    check_eq!(Dialect::detect(b"8.0.11-TiDB-v7.5.1", mysql), Dialect::TiDb);
+    check_eq!(Dialect::detect(b"8.0.30-Vitess", mysql), Dialect::Vitess);
+    Ok(())
+}
+
+#[test]
+fn quirks() -> crate::error::Result<()> {
+    check!(Dialect::MySql.supports_reset_connection(b"8.0.36"));
+    check!(!Dialect::MySql.supports_reset_connection(b"5.6.51"));
+    check!(!Dialect::TiDb.supports_reset_connection(b"8.0.11-TiDB-v7.5.1"));
+    check!(!Dialect::Vitess.supports_reset_connection(b"8.0.30-Vitess"));
+    check!(Dialect::MySql.disabled_capabilities().is_empty());
+    check!(
+        Dialect::Vitess
+            .disabled_capabilities()
+            .contains(CapabilityFlags::CLIENT_SESSION_TRACK)
+    );
+    check_eq!(Dialect::TiDb.to_string(), "TiDB");
+    Ok(())
+}
@@ -0,0 +1,563 @@
+//! Client-side emulation of prepared statements for servers and proxies that only handle the
+//! text protocol well, e.g. some serverless gateways.
+//!
+//! With `Opts::emulate_prepared_statements`, `Conn::prepare()` does not contact the server.
+//! Each execution substitutes the parameters into the SQL text with [`interpolate`] and sends
+//! it with `COM_QUERY`. The text rows of the response are converted to binary rows, so
+//! handlers and [`FromRow`](crate::raw::FromRow) types work unchanged.
+//!
+//! Parameters are formatted from their [`Params`] encoding, so every parameter type works
+//! except [`LongData`](crate::protocol::r#trait::param::LongData).
+//! Row iterators, streams, `send_long_data()` and `flush_statements()` need server-side
+//! statements and return `Error::BadUsageError` for emulated ones.
+
+use std::fmt::{Display, LowerExp, Write};
+
+use crate::PreparedStatement;
+use crate::constant::{ColumnFlags, ColumnType};
+use crate::error::{Error, Result, eyre};
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::primitive::*;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::param::Params;
+use crate::protocol::r#trait::{BinaryResultSetHandler, TextResultSetHandler};
+use crate::protocol::{BinaryRowPayload, TextRowPayload};
+use crate::sql_lexer::{TokenKind, tokenize};
+use crate::value::NullBitmap;
+
+/// Substitute the `?` placeholders of `sql` with the values of `params` as SQL literals.
+///
+/// `no_backslash_escapes` selects the string escaping of the `NO_BACKSLASH_ESCAPES` SQL mode,
+/// which the server reports in its status flags.
+/// The result must not be sent on a connection whose character set
+/// [`escape_with_backslash_is_dangerous`](crate::charset::Charset::escape_with_backslash_is_dangerous).
+///
+/// ```
+/// use zero_mysql::emulate::interpolate;
+///
+/// let sql = interpolate("SELECT * FROM t WHERE a = ? AND b = ?", &(1, "it's"), false).unwrap();
+/// assert_eq!(sql, r"SELECT * FROM t WHERE a = 1 AND b = 'it\'s'");
+/// ```
+pub fn interpolate<P: Params + ?Sized>(
+    sql: &str,
+    params: &P,
+    no_backslash_escapes: bool,
+) -> Result<String> {
+    let num_params = params.len();
+    let mut null_bitmap = Vec::new();
+    params.encode_null_bitmap(&mut null_bitmap);
+    let mut types = Vec::with_capacity(num_params * 2);
+    params.encode_types(&mut types);
+    let mut values = Vec::with_capacity(params.encoded_len());
+    params.encode_values(&mut values)?;
+    let null_bitmap = NullBitmap::for_parameters(&null_bitmap);
+
+    let mut out = String::with_capacity(sql.len() + values.len() * 2);
+    let mut data = values.as_slice();
+    let mut index = 0;
+    for token in tokenize(sql) {
+        match token.kind {
+            TokenKind::Symbol if token.text == "?" => {
+                if index == num_params {
+                    return Err(placeholder_mismatch(num_params, sql));
+                }
+                if null_bitmap.is_null(index) {
+                    out.push_str("NULL");
+                } else {
+                    let Some(&[column_type, flag]) = types.get(index * 2..index * 2 + 2) else {
+                        return Err(Error::LibraryBug(eyre!(
+                            "missing type of parameter {}",
+                            index
+                        )));
+                    };
+                    data = write_literal(
+                        &mut out,
+                        column_type,
+                        flag & 0x80 != 0,
+                        data,
+                        no_backslash_escapes,
+                    )?;
+                }
+                index += 1;
+            }
+            TokenKind::Unterminated(reason) => {
+                return Err(Error::BadUsageError(format!(
+                    "Cannot emulate {}: {}",
+                    reason, sql
+                )));
+            }
+            _ => out.push_str(token.text),
+        }
+    }
+    if index != num_params {
+        return Err(placeholder_mismatch(num_params, sql));
+    }
+    Ok(out)
+}
+
+fn placeholder_mismatch(num_params: usize, sql: &str) -> Error {
+    Error::BadUsageError(format!(
+        "{} parameters do not match the placeholders of: {}",
+        num_params, sql
+    ))
+}
+
+/// Write the literal of one binary-encoded parameter, returning the remaining values.
+fn write_literal<'a>(
+    out: &mut String,
+    column_type: u8,
+    is_unsigned: bool,
+    data: &'a [u8],
+    no_backslash_escapes: bool,
+) -> Result<&'a [u8]> {
+    let column_type = ColumnType::from_u8(column_type).ok_or_else(|| {
+        Error::BadUsageError(format!("Unknown parameter type: {:#04x}", column_type))
+    })?;
+    let rest = match column_type {
+        ColumnType::MYSQL_TYPE_NULL => {
+            out.push_str("NULL");
+            data
+        }
+        ColumnType::MYSQL_TYPE_TINY => {
+            let (v, rest) = read_int_1(data)?;
+            write_int(out, is_unsigned, u64::from(v), i64::from(v as i8));
+            rest
+        }
+        ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => {
+            let (v, rest) = read_int_2(data)?;
+            write_int(out, is_unsigned, u64::from(v), i64::from(v as i16));
+            rest
+        }
+        ColumnType::MYSQL_TYPE_LONG | ColumnType::MYSQL_TYPE_INT24 => {
+            let (v, rest) = read_int_4(data)?;
+            write_int(out, is_unsigned, u64::from(v), i64::from(v as i32));
+            rest
+        }
+        ColumnType::MYSQL_TYPE_LONGLONG => {
+            let (v, rest) = read_int_8(data)?;
+            write_int(out, is_unsigned, v, v as i64);
+            rest
+        }
+        ColumnType::MYSQL_TYPE_FLOAT => {
+            let (v, rest) = read_int_4(data)?;
+            let v = f32::from_bits(v);
+            write_float(out, v, v.is_finite())?;
+            rest
+        }
+        ColumnType::MYSQL_TYPE_DOUBLE => {
+            let (v, rest) = read_int_8(data)?;
+            let v = f64::from_bits(v);
+            write_float(out, v, v.is_finite())?;
+            rest
+        }
+        ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+            let (v, rest) = read_string_lenenc(data)?;
+            let is_number = !v.is_empty()
+                && v.iter()
+                    .all(|b| matches!(b, b'0'..=b'9' | b'.' | b'-' | b'+' | b'e' | b'E'));
+            if !is_number {
+                return Err(Error::BadUsageError(format!(
+                    "Invalid DECIMAL parameter: {}",
+                    String::from_utf8_lossy(v)
+                )));
+            }
+            // Checked to be ASCII above
+            out.push_str(std::str::from_utf8(v).unwrap_or_default());
+            rest
+        }
+        ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => {
+            let (len, rest) = read_int_1(data)?;
+            let (v, rest) = read_string_fix(rest, len as usize)?;
+            write_datetime(out, "DATE", v)?;
+            rest
+        }
+        ColumnType::MYSQL_TYPE_DATETIME
+        | ColumnType::MYSQL_TYPE_TIMESTAMP
+        | ColumnType::MYSQL_TYPE_TIMESTAMP2
+        | ColumnType::MYSQL_TYPE_DATETIME2 => {
+            let (len, rest) = read_int_1(data)?;
+            let (v, rest) = read_string_fix(rest, len as usize)?;
+            write_datetime(out, "TIMESTAMP", v)?;
+            rest
+        }
+        ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2 => {
+            let (len, rest) = read_int_1(data)?;
+            let (v, rest) = read_string_fix(rest, len as usize)?;
+            write_time(out, v)?;
+            rest
+        }
+        ColumnType::MYSQL_TYPE_LONG_BLOB => {
+            return Err(Error::BadUsageError(
+                "LongData parameters cannot be used with emulated prepared statements".to_string(),
+            ));
+        }
+        ColumnType::MYSQL_TYPE_BLOB
+        | ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_GEOMETRY
+        | ColumnType::MYSQL_TYPE_BIT => {
+            let (v, rest) = read_string_lenenc(data)?;
+            write_hex(out, v);
+            rest
+        }
+        ColumnType::MYSQL_TYPE_VARCHAR
+        | ColumnType::MYSQL_TYPE_VAR_STRING
+        | ColumnType::MYSQL_TYPE_STRING
+        | ColumnType::MYSQL_TYPE_JSON
+        | ColumnType::MYSQL_TYPE_ENUM
+        | ColumnType::MYSQL_TYPE_SET
+        | ColumnType::MYSQL_TYPE_TYPED_ARRAY => {
+            let (v, rest) = read_string_lenenc(data)?;
+            match std::str::from_utf8(v) {
+                Ok(v) => write_string(out, v, no_backslash_escapes),
+                Err(_) => write_hex(out, v),
+            }
+            rest
+        }
+    };
+    Ok(rest)
+}
+
+fn write_int(out: &mut String, is_unsigned: bool, unsigned: u64, signed: i64) {
+    let _ = if is_unsigned {
+        write!(out, "{}", unsigned)
+    } else {
+        write!(out, "{}", signed)
+    };
+}
+
+/// Floats are written in exponent notation, which MySQL reads as a `DOUBLE` literal
+fn write_float<F: LowerExp + Display>(out: &mut String, v: F, is_finite: bool) -> Result<()> {
+    if !is_finite {
+        return Err(Error::BadUsageError(format!(
+            "{} cannot be sent as a SQL literal",
+            v
+        )));
+    }
+    let _ = write!(out, "{:e}", v);
+    Ok(())
+}
+
+/// A single-quoted string literal escaped for the current SQL mode
+fn write_string(out: &mut String, v: &str, no_backslash_escapes: bool) {
+    out.reserve(v.len() + 2);
+    out.push('\'');
+    for c in v.chars() {
+        match c {
+            '\'' if no_backslash_escapes => out.push_str("''"),
+            _ if no_backslash_escapes => out.push(c),
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\0' => out.push_str("\\0"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\u{1a}' => out.push_str("\\Z"),
+            _ => out.push(c),
+        }
+    }
+    out.push('\'');
+}
+
+/// A hexadecimal literal, which needs no escaping in any SQL mode
+fn write_hex(out: &mut String, v: &[u8]) {
+    out.reserve(v.len() * 2 + 3);
+    out.push_str("X'");
+    for b in v {
+        let _ = write!(out, "{:02X}", b);
+    }
+    out.push('\'');
+}
+
+/// `DATE'..'` or `TIMESTAMP'..'` from the binary format of length 0, 4, 7 or 11
+fn write_datetime(out: &mut String, keyword: &str, v: &[u8]) -> Result<()> {
+    let (year, month, day, hour, minute, second, micros) = match *v {
+        [] => {
+            // The zero date is rejected by typed literals in strict mode
+            out.push_str(if keyword == "DATE" {
+                "'0000-00-00'"
+            } else {
+                "'0000-00-00 00:00:00'"
+            });
+            return Ok(());
+        }
+        [y0, y1, mo, d] => (u16::from_le_bytes([y0, y1]), mo, d, 0, 0, 0, 0),
+        [y0, y1, mo, d, h, mi, s] => (u16::from_le_bytes([y0, y1]), mo, d, h, mi, s, 0),
+        [y0, y1, mo, d, h, mi, s, u0, u1, u2, u3] => (
+            u16::from_le_bytes([y0, y1]),
+            mo,
+            d,
+            h,
+            mi,
+            s,
+            u32::from_le_bytes([u0, u1, u2, u3]),
+        ),
+        _ => {
+            return Err(Error::LibraryBug(eyre!(
+                "invalid datetime length: {}",
+                v.len()
+            )));
+        }
+    };
+    let _ = write!(out, "{}'{:04}-{:02}-{:02}", keyword, year, month, day);
+    if keyword != "DATE" {
+        let _ = write!(out, " {:02}:{:02}:{:02}", hour, minute, second);
+        if micros > 0 {
+            let _ = write!(out, ".{:06}", micros);
+        }
+    }
+    out.push('\'');
+    Ok(())
+}
+
+/// `TIME'..'` from the binary format of length 0, 8 or 12
+fn write_time(out: &mut String, v: &[u8]) -> Result<()> {
+    let (is_negative, days, hour, minute, second, micros) = match *v {
+        [] => (0, 0, 0, 0, 0, 0),
+        [neg, d0, d1, d2, d3, h, mi, s] => (neg, u32::from_le_bytes([d0, d1, d2, d3]), h, mi, s, 0),
+        [neg, d0, d1, d2, d3, h, mi, s, u0, u1, u2, u3] => (
+            neg,
+            u32::from_le_bytes([d0, d1, d2, d3]),
+            h,
+            mi,
+            s,
+            u32::from_le_bytes([u0, u1, u2, u3]),
+        ),
+        _ => {
+            return Err(Error::LibraryBug(eyre!("invalid time length: {}", v.len())));
+        }
+    };
+    let sign = if is_negative != 0 { "-" } else { "" };
+    let hours = u64::from(days) * 24 + u64::from(hour);
+    let _ = write!(out, "TIME'{}{:02}:{:02}:{:02}", sign, hours, minute, second);
+    if micros > 0 {
+        let _ = write!(out, ".{:06}", micros);
+    }
+    out.push('\'');
+    Ok(())
+}
+
+/// Error for the methods that need a statement prepared on the server
+pub(crate) fn check_not_emulated(stmt: &PreparedStatement, method: &str) -> Result<()> {
+    if stmt.is_emulated() {
+        return Err(Error::BadUsageError(format!(
+            "{} is not supported with Opts::emulate_prepared_statements",
+            method
+        )));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Text rows to binary rows
+// ============================================================================
+
+/// Feeds the text result sets of an emulated execution to a binary handler
+pub(crate) struct TextToBinary<'h, H> {
+    inner: &'h mut H,
+    row: Vec<u8>,
+}
+
+impl<'h, H: BinaryResultSetHandler> TextToBinary<'h, H> {
+    pub(crate) fn new(inner: &'h mut H) -> Self {
+        Self {
+            inner,
+            row: Vec::new(),
+        }
+    }
+}
+
+impl<H: BinaryResultSetHandler> TextResultSetHandler for TextToBinary<'_, H> {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        self.inner.no_result_set(ok)
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.inner.resultset_start(cols)
+    }
+
+    fn row(&mut self, cols: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        text_row_to_binary(cols, row, &mut self.row)?;
+        let null_bitmap_len = (cols.len() + 7 + 2) >> 3;
+        let (null_bitmap, values) = self.row[1..].split_at(null_bitmap_len);
+        self.inner.row(
+            cols,
+            BinaryRowPayload::new(NullBitmap::for_result_set(null_bitmap), values, cols.len()),
+        )
+    }
+
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        self.inner.resultset_end(eof)
+    }
+}
+
+/// Re-encode a text protocol row as a binary protocol row packet in `out`.
+pub(crate) fn text_row_to_binary(
+    cols: &[ColumnDefinition<'_>],
+    row: TextRowPayload<'_>,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    out.clear();
+    out.push(0x00);
+    let null_bitmap_len = (cols.len() + 7 + 2) >> 3;
+    out.resize(1 + null_bitmap_len, 0);
+
+    let mut data = row.0;
+    for (index, col) in cols.iter().enumerate() {
+        if data.first() == Some(&0xFB) {
+            let bit = index + 2;
+            out[1 + (bit >> 3)] |= 1 << (bit & 7);
+            data = &data[1..];
+            continue;
+        }
+        let (text, rest) = read_string_lenenc(data)?;
+        data = rest;
+        let is_unsigned = col.tail.flags()?.contains(ColumnFlags::UNSIGNED_FLAG);
+        match col.tail.column_type()? {
+            ColumnType::MYSQL_TYPE_TINY => out.push(parse_int(text, is_unsigned)? as u8),
+            ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => {
+                write_int_2(out, parse_int(text, is_unsigned)? as u16)
+            }
+            ColumnType::MYSQL_TYPE_LONG | ColumnType::MYSQL_TYPE_INT24 => {
+                write_int_4(out, parse_int(text, is_unsigned)? as u32)
+            }
+            ColumnType::MYSQL_TYPE_LONGLONG => write_int_8(out, parse_int(text, is_unsigned)?),
+            ColumnType::MYSQL_TYPE_FLOAT => {
+                write_int_4(out, parse_text::<f32>(text, "FLOAT")?.to_bits())
+            }
+            ColumnType::MYSQL_TYPE_DOUBLE => {
+                write_int_8(out, parse_text::<f64>(text, "DOUBLE")?.to_bits())
+            }
+            ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => {
+                write_binary_datetime(out, text, false)?
+            }
+            ColumnType::MYSQL_TYPE_DATETIME
+            | ColumnType::MYSQL_TYPE_TIMESTAMP
+            | ColumnType::MYSQL_TYPE_TIMESTAMP2
+            | ColumnType::MYSQL_TYPE_DATETIME2 => write_binary_datetime(out, text, true)?,
+            ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2 => {
+                write_binary_time(out, text)?
+            }
+            _ => write_bytes_lenenc(out, text),
+        }
+    }
+    Ok(())
+}
+
+fn parse_text<T: std::str::FromStr>(text: &[u8], type_name: &str) -> Result<T> {
+    std::str::from_utf8(text)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .ok_or_else(|| {
+            Error::LibraryBug(eyre!(
+                "invalid {} text value: {}",
+                type_name,
+                String::from_utf8_lossy(text)
+            ))
+        })
+}
+
+/// An integer as the bits of its binary encoding
+fn parse_int(text: &[u8], is_unsigned: bool) -> Result<u64> {
+    if is_unsigned {
+        parse_text::<u64>(text, "integer")
+    } else {
+        parse_text::<i64>(text, "integer").map(|v| v as u64)
+    }
+}
+
+/// Split `text` at every non-digit, e.g. `2024-01-02 03:04:05.5` into its 7 fields.
+/// The fraction is scaled to microseconds.
+fn datetime_fields(text: &[u8]) -> Result<Vec<u32>> {
+    let invalid = || {
+        Error::LibraryBug(eyre!(
+            "invalid temporal text value: {}",
+            String::from_utf8_lossy(text)
+        ))
+    };
+    let (whole, fraction) = match text.iter().position(|&b| b == b'.') {
+        Some(dot) => (&text[..dot], Some(&text[dot + 1..])),
+        None => (text, None),
+    };
+    let mut fields = whole
+        .split(|b| !b.is_ascii_digit())
+        .filter(|field| !field.is_empty())
+        .map(|field| parse_text::<u32>(field, "temporal").map_err(|_err| invalid()))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(fraction) = fraction {
+        if fraction.is_empty() || fraction.len() > 6 || !fraction.iter().all(u8::is_ascii_digit) {
+            return Err(invalid());
+        }
+        let micros = parse_text::<u32>(fraction, "temporal")?;
+        fields.push(micros * 10_u32.pow(6 - fraction.len() as u32));
+    }
+    Ok(fields)
+}
+
+/// The binary DATE or DATETIME with the shortest length, as the server sends it
+fn write_binary_datetime(out: &mut Vec<u8>, text: &[u8], with_time: bool) -> Result<()> {
+    let mut fields = datetime_fields(text)?;
+    if fields.len() < 3 || fields.len() > 7 || (!with_time && fields.len() > 3) {
+        return Err(Error::LibraryBug(eyre!(
+            "invalid temporal text value: {}",
+            String::from_utf8_lossy(text)
+        )));
+    }
+    fields.resize(7, 0);
+    let len: u8 = if fields[6] != 0 {
+        11
+    } else if fields[3..6].iter().any(|&v| v != 0) {
+        7
+    } else if fields[..3].iter().any(|&v| v != 0) {
+        4
+    } else {
+        0
+    };
+    out.push(len);
+    if len >= 4 {
+        write_int_2(out, fields[0] as u16);
+        out.push(fields[1] as u8);
+        out.push(fields[2] as u8);
+    }
+    if len >= 7 {
+        out.extend(fields[3..6].iter().map(|&v| v as u8));
+    }
+    if len == 11 {
+        write_int_4(out, fields[6]);
+    }
+    Ok(())
+}
+
+/// The binary TIME of `[-]HHH:MM:SS[.ffffff]`
+fn write_binary_time(out: &mut Vec<u8>, text: &[u8]) -> Result<()> {
+    let is_negative = text.first() == Some(&b'-');
+    let fields = datetime_fields(text)?;
+    let (hours, minute, second, micros) = match *fields.as_slice() {
+        [hours, minute, second] => (hours, minute, second, 0),
+        [hours, minute, second, micros] => (hours, minute, second, micros),
+        _ => {
+            return Err(Error::LibraryBug(eyre!(
+                "invalid TIME text value: {}",
+                String::from_utf8_lossy(text)
+            )));
+        }
+    };
+    let len: u8 = if micros != 0 {
+        12
+    } else if hours != 0 || minute != 0 || second != 0 {
+        8
+    } else {
+        0
+    };
+    out.push(len);
+    if len >= 8 {
+        out.push(u8::from(is_negative));
+        write_int_4(out, hours / 24);
+        out.push((hours % 24) as u8);
+        out.push(minute as u8);
+        out.push(second as u8);
+    }
+    if len == 12 {
+        write_int_4(out, micros);
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use zerocopy::FromBytes;
+
+use crate::charset::Charset;
+use crate::emulate::{interpolate, text_row_to_binary};
+use crate::error::{Error, Result};
+use crate::opts::Opts;
+use crate::protocol::TextRowPayload;
+use crate::protocol::command::resultset::read_binary_row;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail};
+use crate::protocol::r#trait::param::LongData;
+use crate::raw::FromRow;
+use crate::test_macros::{check, check_eq};
+
+fn tail(column_type: u8, flags: u16) -> [u8; 12] {
+    let [flags0, flags1] = flags.to_le_bytes();
+    [0x2D, 0x00, 0, 0, 0, 0, column_type, flags0, flags1, 0, 0, 0]
+}
+
+fn column(tail: &ColumnDefinitionTail) -> ColumnDefinition<'_> {
+    ColumnDefinition {
+        schema: b"test",
+        table_alias: b"t",
+        table_original: b"t",
+        name_alias: b"c",
+        name_original: b"c",
+        tail,
+        name_id: None,
+    }
+}
+
+/// A text protocol row of length-encoded values, `None` for NULL
+fn text_row(values: &[Option<&str>]) -> Vec<u8> {
+    let mut row = Vec::new();
+    for value in values {
+        match value {
+            Some(value) => {
+                row.push(value.len() as u8);
+                row.extend_from_slice(value.as_bytes());
+            }
+            None => row.push(0xFB),
+        }
+    }
+    row
+}
+
+#[test]
+fn interpolate_numbers() -> Result<()> {
+    let sql = interpolate(
+        "SELECT ?, ?, ?, ?, ?, ?",
+        &(1_i8, -2_i64, u64::MAX, 1.5_f64, 0.1_f32, true),
+        false,
+    )?;
+    check_eq!(sql, "SELECT 1, -2, 18446744073709551615, 1.5e0, 1e-1, 1");
+    Ok(())
+}
+
+#[test]
+fn interpolate_strings_for_sql_mode() -> Result<()> {
+    let value = "a'b\\c\n\0";
+    check_eq!(
+        interpolate("SELECT ?", &(value,), false)?,
+        r"SELECT 'a\'b\\c\n\0'"
+    );
+    check_eq!(
+        interpolate("SELECT ?", &(value,), true)?,
+        "SELECT 'a''b\\c\n\0'"
+    );
+    Ok(())
+}
+
+#[test]
+fn interpolate_bytes_and_null() -> Result<()> {
+    let sql = interpolate(
+        "INSERT INTO t VALUES (?, ?)",
+        &(&b"\x00'\xFF"[..], None::<i32>),
+        false,
+    )?;
+    check_eq!(sql, "INSERT INTO t VALUES (X'0027FF', NULL)");
+    Ok(())
+}
+
+#[test]
+fn interpolate_skips_quoted_placeholders() -> Result<()> {
+    let sql = interpolate("SELECT '?', `?`, ? /* ? */ -- ?", &(1,), false)?;
+    check_eq!(sql, "SELECT '?', `?`, 1 /* ? */ -- ?");
+    Ok(())
+}
+
+#[test]
+fn interpolate_rejects_bad_params() -> Result<()> {
+    check!(matches!(
+        interpolate("SELECT ?, ?", &(1,), false),
+        Err(Error::BadUsageError(_))
+    ));
+    check!(matches!(
+        interpolate("SELECT ?", &(1, 2), false),
+        Err(Error::BadUsageError(_))
+    ));
+    check!(matches!(
+        interpolate("SELECT ?", &(f64::NAN,), false),
+        Err(Error::BadUsageError(_))
+    ));
+    check!(matches!(
+        interpolate("SELECT ?", &(LongData,), false),
+        Err(Error::BadUsageError(_))
+    ));
+    check!(matches!(
+        interpolate("SELECT '?", &(), false),
+        Err(Error::BadUsageError(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn text_row_decodes_as_binary_row() -> Result<()> {
+    let bigint = tail(0x08, 0x0001);
+    let int_unsigned = tail(0x03, 0x0020);
+    let double = tail(0x05, 0);
+    let varchar = tail(0xFD, 0);
+    let cols = [
+        column(ColumnDefinitionTail::ref_from_bytes(&bigint)?),
+        column(ColumnDefinitionTail::ref_from_bytes(&int_unsigned)?),
+        column(ColumnDefinitionTail::ref_from_bytes(&double)?),
+        column(ColumnDefinitionTail::ref_from_bytes(&varchar)?),
+        column(ColumnDefinitionTail::ref_from_bytes(&varchar)?),
+    ];
+    let text = text_row(&[Some("-5"), Some("4000000000"), Some("2.5"), Some("x"), None]);
+
+    let mut packet = Vec::new();
+    text_row_to_binary(&cols, TextRowPayload(&text), &mut packet)?;
+    let row = read_binary_row(&packet, cols.len())?;
+    let decoded = <(i64, u32, f64, String, Option<String>)>::from_row(&cols, row)?;
+    check_eq!(decoded, (-5, 4_000_000_000, 2.5, "x".to_string(), None));
+    Ok(())
+}
+
+#[test]
+fn text_temporal_values_use_the_shortest_binary_length() -> Result<()> {
+    let datetime = tail(0x0C, 0);
+    let time = tail(0x0B, 0);
+    let date = tail(0x0A, 0);
+    let cols = [
+        column(ColumnDefinitionTail::ref_from_bytes(&datetime)?),
+        column(ColumnDefinitionTail::ref_from_bytes(&time)?),
+        column(ColumnDefinitionTail::ref_from_bytes(&date)?),
+        column(ColumnDefinitionTail::ref_from_bytes(&datetime)?),
+    ];
+    let text = text_row(&[
+        Some("2024-01-02 03:04:05.5"),
+        Some("-27:00:01"),
+        Some("0000-00-00"),
+        Some("2024-01-02 00:00:00"),
+    ]);
+
+    let mut packet = Vec::new();
+    text_row_to_binary(&cols, TextRowPayload(&text), &mut packet)?;
+    // DATETIME with microseconds
+    let mut expected = vec![11, 0xE8, 0x07, 1, 2, 3, 4, 5, 0x20, 0xA1, 0x07, 0];
+    // -1 day 03:00:01
+    expected.extend([8, 1, 1, 0, 0, 0, 3, 0, 1]);
+    // zero DATE
+    expected.push(0);
+    // DATETIME at midnight
+    expected.extend([4, 0xE8, 0x07, 1, 2]);
+    check_eq!(&packet[2..], expected.as_slice());
+    Ok(())
+}
+
+// Under GBK, `E4 B8 AD 5C 27` reads as `(E4 B8)(AD 5C) 27`: the escaped quote ends the string,
+// so emulation must refuse such a connection character set
+#[test]
+fn gbk_trail_byte_swallows_backslash_escape() -> Result<()> {
+    let sql = interpolate("SELECT ?", &("\u{4E2D}'",), false)?;
+    check!(
+        sql.as_bytes()
+            .windows(5)
+            .any(|w| w == [0xE4, 0xB8, 0xAD, 0x5C, 0x27])
+    );
+    check!(Charset::Gbk.escape_with_backslash_is_dangerous());
+    check!(!Charset::Utf8mb4.escape_with_backslash_is_dangerous());
+
+    let opts = Opts {
+        emulate_prepared_statements: true,
+        charset: Some(Charset::Gbk),
+        ..Opts::default()
+    };
+    check!(matches!(
+        opts.connection_collation(),
+        Err(Error::BadUsageError(_))
+    ));
+    let opts = Opts {
+        emulate_prepared_statements: false,
+        ..opts
+    };
+    check!(opts.connection_collation().is_ok());
+    Ok(())
+}
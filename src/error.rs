@@ -20,7 +20,13 @@ pub enum Error {
     BadUsageError(String),
     // ─── Temporary Error ─────────────────────────────────────────────────
     #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error),
+    IoError(std::io::Error),
+    /// `Opts::connect_timeout`, `Opts::read_timeout` or `Opts::write_timeout` elapsed
+    #[error("Timed out")]
+    Timeout,
+    /// A `CancellationToken` aborted the command
+    #[error("Cancelled")]
+    Cancelled,
     // ─── Library Error ───────────────────────────────────────────────────
     #[error("A bug in zero-mysql: {0}")]
     LibraryBug(#[from] color_eyre::Report),
@@ -43,17 +49,62 @@ impl<'buf> From<ErrPayloadBytes<'buf>> for Error {
     }
 }
 
+/// Socket timeouts of the sync connection surface as `WouldBlock` or `TimedOut`
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::IoError(err),
+        }
+    }
+}
+
 impl From<core::convert::Infallible> for Error {
     fn from(err: core::convert::Infallible) -> Self {
         match err {}
     }
 }
 
+/// `ER_SERVER_SHUTDOWN`: the server is shutting down
+const ER_SERVER_SHUTDOWN: u16 = 1053;
+/// `ER_CONNECTION_KILLED` (MariaDB): `KILL CONNECTION` or a shutdown ended the session
+const ER_CONNECTION_KILLED: u16 = 1927;
+/// `ER_SESSION_WAS_KILLED` (MySQL): `KILL CONNECTION` ended the session
+const ER_SESSION_WAS_KILLED: u16 = 3169;
+/// `ER_CLIENT_INTERACTION_TIMEOUT` (MySQL): `wait_timeout` closed an idle connection
+const ER_CLIENT_INTERACTION_TIMEOUT: u16 = 4031;
+
 impl Error {
     pub fn from_debug(err: impl std::fmt::Debug) -> Self {
         Self::LibraryBug(color_eyre::eyre::eyre!(format!("{:#?}", err)))
     }
 
+    /// Returns true if the server reported that it is shutting down (`ER_SERVER_SHUTDOWN`).
+    ///
+    /// Every connection to the server is about to be closed. Pools drop their idle connections
+    /// when a connection returns with this error.
+    pub fn is_server_shutdown(&self) -> bool {
+        matches!(self, Error::ServerError(err) if err.error_code == ER_SERVER_SHUTDOWN)
+    }
+
+    /// Returns true if the server aborted the command and closed the session, because it is
+    /// shutting down, the session was killed, or the session was idle for too long.
+    ///
+    /// The aborted statement was rolled back, so the command can be retried on a new connection.
+    /// A transaction that was open on the connection is lost and must be retried as a whole.
+    pub fn is_retryable_on_new_connection(&self) -> bool {
+        matches!(
+            self,
+            Error::ServerError(err) if matches!(
+                err.error_code,
+                ER_SERVER_SHUTDOWN
+                    | ER_CONNECTION_KILLED
+                    | ER_SESSION_WAS_KILLED
+                    | ER_CLIENT_INTERACTION_TIMEOUT
+            )
+        )
+    }
+
     /// Returns true if the error indicates the connection is broken and cannot be reused.
     ///
     /// This is conservative - returns true (broken) when in doubt.
@@ -69,12 +120,16 @@ impl Error {
                     "42000" | "42S02" | "42S22" => false,
                     // Not supported - connection still usable
                     "0A000" => false,
+                    // Interrupted by KILL QUERY - connection still usable
+                    "70100" => false,
                     // Everything else - assume broken
                     _ => true,
                 }
             }
             // User errors - connection still usable
             Error::BadUsageError(_) | Error::MissingColumn(_) | Error::UnknownColumn(_) => false,
+            // Interrupted by KILL QUERY, or already marked broken if the command was dropped
+            Error::Cancelled => false,
             // All other errors - assume broken
             _ => true,
         }
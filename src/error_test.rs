@@ -0,0 +1,53 @@
+use std::io;
+
+use crate::error::{Error, Result};
+use crate::protocol::response::ErrPayload;
+use crate::test_macros::check;
+
+fn server_error(error_code: u16, sql_state: &str) -> Error {
+    Error::ServerError(ErrPayload {
+        error_code,
+        sql_state: sql_state.to_string(),
+        message: String::new(),
+    })
+}
+
+#[test]
+fn server_shutdown_is_retryable_on_a_new_connection() -> Result<()> {
+    let shutdown = server_error(1053, "08S01");
+    check!(shutdown.is_server_shutdown());
+    check!(shutdown.is_retryable_on_new_connection());
+    check!(shutdown.is_conn_broken());
+    Ok(())
+}
+
+#[test]
+fn killed_sessions_are_retryable_but_not_a_shutdown() -> Result<()> {
+    for error_code in [1927, 3169, 4031] {
+        let killed = server_error(error_code, "HY000");
+        check!(!killed.is_server_shutdown());
+        check!(killed.is_retryable_on_new_connection());
+        check!(killed.is_conn_broken());
+    }
+    Ok(())
+}
+
+#[test]
+fn other_errors_are_not_retryable_on_a_new_connection() -> Result<()> {
+    check!(!server_error(1213, "40001").is_retryable_on_new_connection());
+    check!(!server_error(1062, "23000").is_retryable_on_new_connection());
+    check!(!Error::BadUsageError(String::new()).is_retryable_on_new_connection());
+    Ok(())
+}
+
+#[test]
+fn socket_timeouts_become_timeout_errors() -> Result<()> {
+    for kind in [io::ErrorKind::WouldBlock, io::ErrorKind::TimedOut] {
+        let timeout = Error::from(io::Error::from(kind));
+        check!(matches!(timeout, Error::Timeout));
+        check!(timeout.is_conn_broken());
+    }
+    let reset = Error::from(io::Error::from(io::ErrorKind::ConnectionReset));
+    check!(matches!(reset, Error::IoError(_)));
+    Ok(())
+}
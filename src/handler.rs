@@ -1,10 +1,17 @@
-use crate::error::Result;
-use crate::protocol::command::ColumnDefinition;
-use crate::protocol::response::{OkPayload, OkPayloadBytes};
-use crate::protocol::r#trait::{BinaryResultSetHandler, TextResultSetHandler};
+use crate::constant::ServerStatusFlags;
+use crate::error::{Error, Result, eyre};
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitions};
+use crate::protocol::response::{OkPayload, OkPayloadBytes, SessionStateChange};
+use crate::protocol::r#trait::{
+    AsyncBinaryResultSetHandler, AsyncTextResultSetHandler, BinaryResultSetHandler,
+    TextResultSetHandler,
+};
 use crate::protocol::{BinaryRowPayload, TextRowPayload};
 use crate::raw::FromRow;
+use crate::value::NullBitmap;
 use smart_default::SmartDefault;
+use std::ops::Range;
+use std::sync::Arc;
 
 /// A handler that ignores all result set data but captures affected_rows and last_insert_id
 ///
@@ -121,7 +128,20 @@ pub struct CollectHandler<Row> {
     last_insert_id: u64,
 }
 
+/// Capacity reserved by [`CollectHandler::with_max_rows`] at most, so that a large `LIMIT`
+/// does not allocate for rows that may never come
+const MAX_RESERVED_ROWS: u64 = 4096;
+
 impl<Row> CollectHandler<Row> {
+    /// Reserve space for up to `max_rows` rows, e.g. from [`PreparedStatement::max_rows`](crate::PreparedStatement::max_rows).
+    pub fn with_max_rows(max_rows: Option<u64>) -> Self {
+        let capacity = max_rows.map_or(0, |max_rows| max_rows.min(MAX_RESERVED_ROWS) as usize);
+        Self {
+            rows: Vec::with_capacity(capacity),
+            affected_rows: 0,
+            last_insert_id: 0,
+        }
+    }
     pub fn take_rows(&mut self) -> Vec<Row> {
         std::mem::take(&mut self.rows)
     }
@@ -161,6 +181,64 @@ impl<Row: for<'buf> FromRow<'buf>> BinaryResultSetHandler for CollectHandler<Row
     }
 }
 
+/// A handler that collects all rows into a `Vec<`[`Row`](crate::row::Row)`>`, for result sets whose
+/// columns are not known at compile time.
+///
+/// The rows of a result set share one copy of the column definitions.
+#[derive(Default)]
+pub struct CollectRowsHandler {
+    columns: Option<Arc<ColumnDefinitions>>,
+    rows: Vec<crate::row::Row>,
+    affected_rows: u64,
+    last_insert_id: u64,
+}
+
+impl CollectRowsHandler {
+    pub fn take_rows(&mut self) -> Vec<crate::row::Row> {
+        std::mem::take(&mut self.rows)
+    }
+    pub fn into_rows(self) -> Vec<crate::row::Row> {
+        self.rows
+    }
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+    pub fn last_insert_id(&self) -> u64 {
+        self.last_insert_id
+    }
+}
+
+impl BinaryResultSetHandler for CollectRowsHandler {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        let payload = OkPayload::try_from(ok)?;
+        self.affected_rows = payload.affected_rows;
+        self.last_insert_id = payload.last_insert_id;
+        Ok(())
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.columns = Some(Arc::new(ColumnDefinitions::from_definitions(cols)?));
+        Ok(())
+    }
+
+    fn row(&mut self, cols: &[ColumnDefinition], row: BinaryRowPayload) -> Result<()> {
+        let columns = match &self.columns {
+            Some(columns) => Arc::clone(columns),
+            None => Arc::new(ColumnDefinitions::from_definitions(cols)?),
+        };
+        self.rows.push(crate::row::Row::new(columns, row)?);
+        Ok(())
+    }
+
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        self.columns = None;
+        let payload = OkPayload::try_from(eof)?;
+        self.affected_rows = payload.affected_rows;
+        self.last_insert_id = payload.last_insert_id;
+        Ok(())
+    }
+}
+
 /// A handler that calls a closure for each row.
 ///
 /// Useful for `exec_foreach()` methods that process rows without collecting.
@@ -248,3 +326,273 @@ where
         Ok(())
     }
 }
+
+/// Bytes of rows to skip after a handler error before giving up on the connection.
+const MAX_DRAIN_BYTES: usize = 1 << 20;
+
+/// Callback of `Conn::on_session_state_change()`
+pub(crate) type SessionStateHook = Box<dyn FnMut(&SessionStateChange) + Send + Sync>;
+
+/// Wraps a handler passed to the protocol state machines.
+///
+/// - Records the server status flags and session state changes of every OK/EOF packet.
+/// - After the handler returns an error, skips the rest of the response so that the connection
+///   stays in sync, and returns the error from [`HandlerGuard::finish`].
+///   If more than `MAX_DRAIN_BYTES` of rows remain, the error is returned right away
+///   and [`HandlerGuard::abandoned`] is set: the connection must not be reused.
+pub(crate) struct HandlerGuard<'h, H> {
+    inner: &'h mut H,
+    pub status: Option<ServerStatusFlags>,
+    pub session_state: Vec<SessionStateChange>,
+    /// Summed over all OK packets
+    pub affected_rows: u64,
+    error: Option<Error>,
+    drained_bytes: usize,
+    pub abandoned: bool,
+}
+
+impl<'h, H> HandlerGuard<'h, H> {
+    pub fn new(inner: &'h mut H) -> Self {
+        Self {
+            inner,
+            status: None,
+            session_state: Vec::new(),
+            affected_rows: 0,
+            error: None,
+            drained_bytes: 0,
+            abandoned: false,
+        }
+    }
+
+    /// Returns true if the handler failed and the rest of the response was skipped.
+    pub fn failed(&self) -> bool {
+        self.error.is_some()
+    }
+
+    pub fn inner_mut(&mut self) -> &mut H {
+        self.inner
+    }
+
+    /// Returns the handler error, if any.
+    pub fn finish(self) -> Result<()> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn record(&mut self, ok: OkPayloadBytes<'_>) {
+        if let Ok(payload) = OkPayload::try_from(ok) {
+            self.status = Some(payload.status_flags);
+            self.affected_rows += payload.affected_rows;
+            self.session_state
+                .extend_from_slice(payload.session_state());
+        }
+    }
+
+    /// Run a handler callback unless an earlier one failed.
+    fn call(&mut self, f: impl FnOnce(&mut H) -> Result<()>) -> Result<()> {
+        if self.error.is_none()
+            && let Err(err) = f(self.inner)
+        {
+            self.error = Some(err);
+        }
+        Ok(())
+    }
+
+    fn drain_row(&mut self, len: usize) -> Result<()> {
+        self.drained_bytes += len;
+        if self.drained_bytes > MAX_DRAIN_BYTES
+            && let Some(err) = self.error.take()
+        {
+            self.abandoned = true;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+impl<H: BinaryResultSetHandler> BinaryResultSetHandler for HandlerGuard<'_, H> {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        self.record(ok);
+        self.call(|inner| inner.no_result_set(ok))
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.call(|inner| inner.resultset_start(cols))
+    }
+
+    fn row(&mut self, cols: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<()> {
+        if self.failed() {
+            return self.drain_row(row.values().len());
+        }
+        self.call(|inner| inner.row(cols, row))
+    }
+
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        self.record(eof);
+        self.call(|inner| inner.resultset_end(eof))
+    }
+}
+
+impl<H: TextResultSetHandler> TextResultSetHandler for HandlerGuard<'_, H> {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        self.record(ok);
+        self.call(|inner| inner.no_result_set(ok))
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.call(|inner| inner.resultset_start(cols))
+    }
+
+    fn row(&mut self, cols: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        if self.failed() {
+            return self.drain_row(row.0.len());
+        }
+        self.call(|inner| inner.row(cols, row))
+    }
+
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        self.record(eof);
+        self.call(|inner| inner.resultset_end(eof))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Event {
+    NoResultSet,
+    ResultSetStart,
+    /// A binary row; the null bitmap is stored right before the values.
+    BinaryRow {
+        null_bitmap_len: usize,
+        num_columns: usize,
+    },
+    TextRow,
+    ResultSetEnd,
+}
+
+/// Records the callbacks of a state machine step so that an async handler can be awaited
+/// after the step, before the next packet is read.
+///
+/// An error of the async handler is stored in [`DeferredHandler::failed`]
+/// and returned from the next callback, so that [`HandlerGuard`] skips the rest of the response.
+#[derive(Default)]
+pub(crate) struct DeferredHandler {
+    events: Vec<(Event, Range<usize>)>,
+    bytes: Vec<u8>,
+    pub failed: Option<Error>,
+}
+
+impl DeferredHandler {
+    fn push(&mut self, event: Event, parts: &[&[u8]]) -> Result<()> {
+        if let Some(err) = self.failed.take() {
+            return Err(err);
+        }
+        let start = self.bytes.len();
+        for part in parts {
+            self.bytes.extend_from_slice(part);
+        }
+        self.events.push((event, start..self.bytes.len()));
+        Ok(())
+    }
+
+    /// Await `handler` for the callbacks recorded by the binary protocol state machine.
+    pub async fn dispatch_binary<H: AsyncBinaryResultSetHandler>(
+        &mut self,
+        handler: &mut H,
+        cols: &[ColumnDefinition<'_>],
+    ) {
+        for (event, range) in self.events.drain(..) {
+            if self.failed.is_some() {
+                continue;
+            }
+            let bytes = &self.bytes[range];
+            let result = match event {
+                Event::NoResultSet => handler.no_result_set(OkPayloadBytes(bytes)).await,
+                Event::ResultSetStart => handler.resultset_start(cols).await,
+                Event::BinaryRow {
+                    null_bitmap_len,
+                    num_columns,
+                } => {
+                    let (null_bitmap, values) = bytes.split_at(null_bitmap_len);
+                    let row = BinaryRowPayload::new(
+                        NullBitmap::for_result_set(null_bitmap),
+                        values,
+                        num_columns,
+                    );
+                    handler.row(cols, row).await
+                }
+                Event::TextRow => Err(Error::LibraryBug(eyre!("text row in a binary result set"))),
+                Event::ResultSetEnd => handler.resultset_end(OkPayloadBytes(bytes)).await,
+            };
+            self.failed = result.err();
+        }
+        self.bytes.clear();
+    }
+
+    /// Await `handler` for the callbacks recorded by the text protocol state machine.
+    pub async fn dispatch_text<H: AsyncTextResultSetHandler>(
+        &mut self,
+        handler: &mut H,
+        cols: &[ColumnDefinition<'_>],
+    ) {
+        for (event, range) in self.events.drain(..) {
+            if self.failed.is_some() {
+                continue;
+            }
+            let bytes = &self.bytes[range];
+            let result = match event {
+                Event::NoResultSet => handler.no_result_set(OkPayloadBytes(bytes)).await,
+                Event::ResultSetStart => handler.resultset_start(cols).await,
+                Event::TextRow => handler.row(cols, TextRowPayload(bytes)).await,
+                Event::BinaryRow { .. } => {
+                    Err(Error::LibraryBug(eyre!("binary row in a text result set")))
+                }
+                Event::ResultSetEnd => handler.resultset_end(OkPayloadBytes(bytes)).await,
+            };
+            self.failed = result.err();
+        }
+        self.bytes.clear();
+    }
+}
+
+impl BinaryResultSetHandler for DeferredHandler {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        self.push(Event::NoResultSet, &[ok.bytes()])
+    }
+
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.push(Event::ResultSetStart, &[])
+    }
+
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<()> {
+        let null_bitmap = row.null_bitmap().as_bytes();
+        let event = Event::BinaryRow {
+            null_bitmap_len: null_bitmap.len(),
+            num_columns: row.num_columns(),
+        };
+        self.push(event, &[null_bitmap, row.values()])
+    }
+
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        self.push(Event::ResultSetEnd, &[eof.bytes()])
+    }
+}
+
+impl TextResultSetHandler for DeferredHandler {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        self.push(Event::NoResultSet, &[ok.bytes()])
+    }
+
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.push(Event::ResultSetStart, &[])
+    }
+
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        self.push(Event::TextRow, &[row.0])
+    }
+
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        self.push(Event::ResultSetEnd, &[eof.bytes()])
+    }
+}
@@ -0,0 +1,189 @@
+use crate::constant::ServerStatusFlags;
+use crate::error::{Error, Result};
+use crate::handler::{CollectHandler, DeferredHandler, HandlerGuard};
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::{
+    AsyncBinaryResultSetHandler, AsyncTextResultSetHandler, BinaryResultSetHandler,
+    TextResultSetHandler,
+};
+use crate::protocol::{BinaryRowPayload, TextRowPayload};
+use crate::test_macros::{check, check_eq, check_err};
+use crate::value::NullBitmap;
+
+/// Fails on the first row and counts the rows it saw.
+#[derive(Default)]
+struct FailingHandler {
+    rows: usize,
+}
+
+impl TextResultSetHandler for FailingHandler {
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    fn row(&mut self, _: &[ColumnDefinition<'_>], _: TextRowPayload<'_>) -> Result<()> {
+        self.rows += 1;
+        Err(Error::BadUsageError("stop".to_string()))
+    }
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// EOF with SERVER_STATUS_AUTOCOMMIT
+const EOF: &[u8] = &[0xFE, 0, 0, 0x02, 0x00, 0, 0];
+
+#[test]
+fn handler_error_skips_the_rest_of_the_response() -> Result<()> {
+    let mut handler = FailingHandler::default();
+    let mut guard = HandlerGuard::new(&mut handler);
+    for _ in 0..3 {
+        guard.row(&[], TextRowPayload(b"\x01a"))?;
+    }
+    guard.resultset_end(OkPayloadBytes(EOF))?;
+    check!(guard.failed());
+    check!(!guard.abandoned);
+    check_eq!(
+        guard.status,
+        Some(ServerStatusFlags::SERVER_STATUS_AUTOCOMMIT)
+    );
+    check_err!(guard.finish());
+    check_eq!(handler.rows, 1);
+    Ok(())
+}
+
+#[test]
+fn large_remainder_abandons_the_connection() -> Result<()> {
+    let mut handler = FailingHandler::default();
+    let mut guard = HandlerGuard::new(&mut handler);
+    let row = vec![0_u8; 64 * 1024];
+    guard.row(&[], TextRowPayload(&row))?;
+    let mut drained = 0;
+    while guard.row(&[], TextRowPayload(&row)).is_ok() {
+        drained += 1;
+    }
+    check!(guard.abandoned);
+    check_eq!(drained, 16);
+    Ok(())
+}
+
+/// Records rows and fails on the row `fail_at`.
+#[derive(Default)]
+struct AsyncRecorder {
+    rows: Vec<Vec<u8>>,
+    null_first: Vec<bool>,
+    ends: usize,
+    fail_at: Option<usize>,
+}
+
+impl AsyncRecorder {
+    async fn push_row(&mut self, row: &[u8]) -> Result<()> {
+        tokio::task::yield_now().await;
+        if self.fail_at == Some(self.rows.len()) {
+            return Err(Error::BadUsageError("stop".to_string()));
+        }
+        self.rows.push(row.to_vec());
+        Ok(())
+    }
+}
+
+impl AsyncBinaryResultSetHandler for AsyncRecorder {
+    async fn no_result_set(&mut self, _: OkPayloadBytes<'_>) -> Result<()> {
+        Ok(())
+    }
+    async fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    async fn row(&mut self, _: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<()> {
+        self.null_first.push(row.null_bitmap().is_null(0));
+        self.push_row(row.values()).await
+    }
+    async fn resultset_end(&mut self, _: OkPayloadBytes<'_>) -> Result<()> {
+        self.ends += 1;
+        Ok(())
+    }
+}
+
+impl AsyncTextResultSetHandler for AsyncRecorder {
+    async fn no_result_set(&mut self, _: OkPayloadBytes<'_>) -> Result<()> {
+        Ok(())
+    }
+    async fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    async fn row(&mut self, _: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        self.push_row(row.0).await
+    }
+    async fn resultset_end(&mut self, _: OkPayloadBytes<'_>) -> Result<()> {
+        self.ends += 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn deferred_binary_rows_keep_null_bitmap() -> Result<()> {
+    let mut deferred = DeferredHandler::default();
+    let mut recorder = AsyncRecorder::default();
+
+    // bit 2 is the first column in result set null bitmaps
+    BinaryResultSetHandler::row(
+        &mut deferred,
+        &[],
+        BinaryRowPayload::new(NullBitmap::for_result_set(&[0b100]), &[], 1),
+    )?;
+    BinaryResultSetHandler::row(
+        &mut deferred,
+        &[],
+        BinaryRowPayload::new(NullBitmap::for_result_set(&[0]), &[7, 0, 0, 0], 1),
+    )?;
+    deferred.dispatch_binary(&mut recorder, &[]).await;
+    BinaryResultSetHandler::resultset_end(&mut deferred, OkPayloadBytes(EOF))?;
+    deferred.dispatch_binary(&mut recorder, &[]).await;
+
+    check_eq!(recorder.null_first, vec![true, false]);
+    check_eq!(recorder.rows, vec![vec![], vec![7, 0, 0, 0]]);
+    check_eq!(recorder.ends, 1);
+    check!(deferred.failed.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn deferred_error_is_returned_from_the_next_callback() -> Result<()> {
+    let mut deferred = DeferredHandler::default();
+    let mut recorder = AsyncRecorder {
+        fail_at: Some(1),
+        ..Default::default()
+    };
+    let mut guard = HandlerGuard::new(&mut deferred);
+
+    for row in [b"\x01a", b"\x01b", b"\x01c"] {
+        TextResultSetHandler::row(&mut guard, &[], TextRowPayload(row))?;
+        guard.inner_mut().dispatch_text(&mut recorder, &[]).await;
+    }
+    TextResultSetHandler::resultset_end(&mut guard, OkPayloadBytes(EOF))?;
+    guard.inner_mut().dispatch_text(&mut recorder, &[]).await;
+
+    check!(guard.failed());
+    check_eq!(
+        guard.status,
+        Some(ServerStatusFlags::SERVER_STATUS_AUTOCOMMIT)
+    );
+    check_err!(guard.finish());
+    check_eq!(recorder.rows, vec![b"\x01a".to_vec()]);
+    check_eq!(recorder.ends, 0);
+    Ok(())
+}
+
+#[test]
+fn collect_handler_reserves_up_to_max_rows() -> Result<()> {
+    let limited = CollectHandler::<(i64,)>::with_max_rows(Some(10));
+    check!(limited.into_rows().capacity() >= 10);
+    let huge = CollectHandler::<(i64,)>::with_max_rows(Some(u64::MAX));
+    check!(huge.into_rows().capacity() < 1 << 20);
+    let unbounded = CollectHandler::<(i64,)>::with_max_rows(None);
+    check_eq!(unbounded.into_rows().capacity(), 0);
+    Ok(())
+}
@@ -0,0 +1,205 @@
+//! SQL rewriting helpers for optimizer hints and server-side statement timeouts.
+//!
+//! The SQL text is sent to the server as written, so leading comments and `/*+ ... */` hint
+//! comments survive `prepare`. MySQL only reads a hint comment placed directly after the
+//! leading `SELECT`, `INSERT`, `REPLACE`, `UPDATE` or `DELETE` keyword; anywhere else it is an
+//! ordinary comment. [`with_optimizer_hints`] puts hints in that position.
+//!
+//! Hints are part of the statement text and cannot change between executions of one prepared
+//! statement. To vary them per execution, execute each variant with `Conn::exec_cached()` so
+//! that every variant is prepared once.
+
+use std::time::Duration;
+
+/// Skip leading whitespace and comments (`/* */`, `-- `, `#`).
+///
+/// Optimizer hint comments (`/*+ ... */`) are not skipped.
+pub fn skip_leading_comments(sql: &str) -> &str {
+    let mut rest = sql.trim_start();
+    loop {
+        if rest.starts_with("/*") && !rest.starts_with("/*+") {
+            match rest[2..].find("*/") {
+                Some(end) => rest = rest[2 + end + 2..].trim_start(),
+                None => return "",
+            }
+        } else if rest.starts_with("-- ")
+            || rest.starts_with("--\t")
+            || rest == "--"
+            || rest.starts_with('#')
+        {
+            rest = skip_line(rest);
+        } else {
+            return rest;
+        }
+    }
+}
+
+fn skip_line(sql: &str) -> &str {
+    match sql.find('\n') {
+        Some(end) => sql[end + 1..].trim_start(),
+        None => "",
+    }
+}
+
+/// Returns true if `sql` starts with `keyword` (case-insensitive) followed by a non-identifier character.
+pub(crate) fn starts_with_keyword(sql: &str, keyword: &str) -> bool {
+    let Some(head) = sql.get(..keyword.len()) else {
+        return false;
+    };
+    head.eq_ignore_ascii_case(keyword)
+        && !sql[keyword.len()..]
+            .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Statements that take an optimizer hint comment after their leading keyword
+const HINTABLE_KEYWORDS: &[&str] = &["SELECT", "INSERT", "REPLACE", "UPDATE", "DELETE"];
+
+/// Insert `/*+ hints */` after the leading statement keyword (MySQL 5.7.7+).
+///
+/// If the keyword is already followed by a hint comment, `hints` are appended to it, because
+/// MySQL ignores a second hint comment. Returns `None` if the statement does not take hints
+/// or `hints` contains `*/`.
+///
+/// ```
+/// use zero_mysql::hint::with_optimizer_hints;
+///
+/// let sql = with_optimizer_hints("UPDATE t SET a = 1", "SET_VAR(sort_buffer_size = 16M)");
+/// assert_eq!(sql.as_deref(), Some("UPDATE /*+ SET_VAR(sort_buffer_size = 16M) */ t SET a = 1"));
+///
+/// let sql = with_optimizer_hints("SELECT /*+ BKA(t1) */ * FROM t1", "NO_ICP(t1)");
+/// assert_eq!(sql.as_deref(), Some("SELECT /*+ BKA(t1) NO_ICP(t1) */ * FROM t1"));
+/// ```
+pub fn with_optimizer_hints(sql: &str, hints: &str) -> Option<String> {
+    if hints.contains("*/") {
+        return None;
+    }
+    let body = skip_leading_comments(sql);
+    let keyword = HINTABLE_KEYWORDS
+        .iter()
+        .find(|keyword| starts_with_keyword(body, keyword))?;
+    let (head, tail) = sql.split_at(sql.len() - body.len() + keyword.len());
+    let rest = tail.trim_start();
+    match rest.strip_prefix("/*+") {
+        Some(existing) => {
+            let end = existing.find("*/")?;
+            Some(format!(
+                "{head}{}/*+{} {hints} */{}",
+                &tail[..tail.len() - rest.len()],
+                existing[..end].trim_end(),
+                &existing[end + 2..]
+            ))
+        }
+        None => Some(format!("{head} /*+ {hints} */{tail}")),
+    }
+}
+
+/// Prefix the statement with a `/* comment */`, e.g. to tag it for the slow log or
+/// `performance_schema`.
+///
+/// Returns `None` if `comment` contains `*/`.
+///
+/// ```
+/// use zero_mysql::hint::with_comment;
+///
+/// let sql = with_comment("SELECT 1", "app=billing");
+/// assert_eq!(sql.as_deref(), Some("/* app=billing */ SELECT 1"));
+/// ```
+pub fn with_comment(sql: &str, comment: &str) -> Option<String> {
+    if comment.contains("*/") {
+        return None;
+    }
+    Some(format!("/* {comment} */ {sql}"))
+}
+
+/// Convert a timeout to a `MAX_EXECUTION_TIME` value in milliseconds.
+///
+/// Rounds up and never returns 0, because 0 disables the limit.
+pub fn max_execution_time_millis(timeout: Duration) -> u64 {
+    let millis = timeout.as_nanos().div_ceil(1_000_000);
+    u64::try_from(millis).unwrap_or(u64::MAX).max(1)
+}
+
+/// Convert a timeout to a `max_statement_time` value in seconds.
+///
+/// Never below one microsecond, which `{:.6}` would print as 0, because 0 disables the limit.
+fn max_statement_time_secs(timeout: Duration) -> f64 {
+    timeout.as_secs_f64().max(0.000_001)
+}
+
+/// Insert a `/*+ MAX_EXECUTION_TIME(n) */` hint after the leading `SELECT` keyword (MySQL 5.7.8+).
+///
+/// Returns `None` if the statement is not a `SELECT`, since MySQL ignores the hint elsewhere.
+///
+/// ```
+/// use std::time::Duration;
+/// use zero_mysql::hint::with_max_execution_time;
+///
+/// let sql = with_max_execution_time("SELECT * FROM t", Duration::from_millis(1500));
+/// assert_eq!(sql.as_deref(), Some("SELECT /*+ MAX_EXECUTION_TIME(1500) */ * FROM t"));
+/// assert_eq!(with_max_execution_time("DELETE FROM t", Duration::from_secs(1)), None);
+/// ```
+pub fn with_max_execution_time(sql: &str, timeout: Duration) -> Option<String> {
+    if !starts_with_keyword(skip_leading_comments(sql), "SELECT") {
+        return None;
+    }
+    with_optimizer_hints(
+        sql,
+        &format!("MAX_EXECUTION_TIME({})", max_execution_time_millis(timeout)),
+    )
+}
+
+/// Prefix the statement with `SET STATEMENT max_statement_time=<seconds> FOR` (MariaDB 10.1.2+).
+///
+/// ```
+/// use std::time::Duration;
+/// use zero_mysql::hint::with_max_statement_time;
+///
+/// let sql = with_max_statement_time("UPDATE t SET a = 1", Duration::from_millis(1500));
+/// assert_eq!(sql, "SET STATEMENT max_statement_time=1.500000 FOR UPDATE t SET a = 1");
+/// ```
+pub fn with_max_statement_time(sql: &str, timeout: Duration) -> String {
+    format!(
+        "SET STATEMENT max_statement_time={:.6} FOR {}",
+        max_statement_time_secs(timeout),
+        sql
+    )
+}
+
+/// Rewrite `sql` so that the server aborts it after `timeout`.
+///
+/// Returns `None` if the server has no per-statement limit for this statement.
+pub(crate) fn with_statement_timeout(
+    sql: &str,
+    timeout: Duration,
+    is_mariadb: bool,
+) -> Option<String> {
+    if is_mariadb {
+        Some(with_max_statement_time(sql, timeout))
+    } else {
+        with_max_execution_time(sql, timeout)
+    }
+}
+
+/// `SET SESSION` statement that limits the execution time of subsequent statements.
+pub(crate) fn set_session_timeout(timeout: Duration, is_mariadb: bool) -> String {
+    if is_mariadb {
+        format!(
+            "SET SESSION max_statement_time={:.6}",
+            max_statement_time_secs(timeout)
+        )
+    } else {
+        format!(
+            "SET SESSION max_execution_time={}",
+            max_execution_time_millis(timeout)
+        )
+    }
+}
+
+/// `SET SESSION` statement that restores the server default set by [`set_session_timeout`].
+pub(crate) fn reset_session_timeout(is_mariadb: bool) -> &'static str {
+    if is_mariadb {
+        "SET SESSION max_statement_time=DEFAULT"
+    } else {
+        "SET SESSION max_execution_time=DEFAULT"
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use crate::hint::{
+    max_execution_time_millis, set_session_timeout, skip_leading_comments, with_comment,
+    with_max_execution_time, with_max_statement_time, with_optimizer_hints,
+};
+use crate::test_macros::{check, check_eq};
+
+#[test]
+fn millis_round_up_and_never_zero() -> crate::error::Result<()> {
+    check_eq!(max_execution_time_millis(Duration::ZERO), 1);
+    check_eq!(max_execution_time_millis(Duration::from_micros(1)), 1);
+    check_eq!(max_execution_time_millis(Duration::from_micros(1500)), 2);
+    check_eq!(max_execution_time_millis(Duration::from_secs(3)), 3000);
+    Ok(())
+}
+
+#[test]
+fn hint_after_select() -> crate::error::Result<()> {
+    let timeout = Duration::from_millis(250);
+    check_eq!(
+        with_max_execution_time("select 1", timeout).as_deref(),
+        Some("select /*+ MAX_EXECUTION_TIME(250) */ 1")
+    );
+    check_eq!(
+        with_max_execution_time("  /* tag */ -- note\n# x\nSELECT\n1", timeout).as_deref(),
+        Some("  /* tag */ -- note\n# x\nSELECT /*+ MAX_EXECUTION_TIME(250) */\n1")
+    );
+    Ok(())
+}
+
+#[test]
+fn no_hint_for_other_statements() -> crate::error::Result<()> {
+    let timeout = Duration::from_millis(250);
+    check!(with_max_execution_time("UPDATE t SET a = 1", timeout).is_none());
+    check!(with_max_execution_time("SELECTED", timeout).is_none());
+    check!(with_max_execution_time("/* unterminated SELECT 1", timeout).is_none());
+    check!(with_max_execution_time("/*+ BKA(t) */ SELECT 1", timeout).is_none());
+    Ok(())
+}
+
+#[test]
+fn optimizer_hints_after_keyword() -> crate::error::Result<()> {
+    check_eq!(
+        with_optimizer_hints(
+            "/* tag */ insert into t values (1)",
+            "SET_VAR(foreign_key_checks=OFF)"
+        )
+        .as_deref(),
+        Some("/* tag */ insert /*+ SET_VAR(foreign_key_checks=OFF) */ into t values (1)")
+    );
+    check_eq!(
+        with_optimizer_hints("DELETE\n/*+ BKA(t) */ FROM t", "NO_BNL(t)").as_deref(),
+        Some("DELETE\n/*+ BKA(t) NO_BNL(t) */ FROM t")
+    );
+    check!(with_optimizer_hints("WITH x AS (SELECT 1) SELECT * FROM x", "BKA(x)").is_none());
+    check!(with_optimizer_hints("SELECT 1", "BKA(t) */ DROP TABLE t; /*").is_none());
+    check!(with_optimizer_hints("SELECT /*+ BKA(t) 1", "NO_BNL(t)").is_none());
+    Ok(())
+}
+
+#[test]
+fn max_execution_time_joins_existing_hints() -> crate::error::Result<()> {
+    check_eq!(
+        with_max_execution_time("SELECT /*+ BKA(t) */ * FROM t", Duration::from_millis(5))
+            .as_deref(),
+        Some("SELECT /*+ BKA(t) MAX_EXECUTION_TIME(5) */ * FROM t")
+    );
+    Ok(())
+}
+
+#[test]
+fn comment_prefix() -> crate::error::Result<()> {
+    check_eq!(
+        with_comment("SELECT 1", "trace=1").as_deref(),
+        Some("/* trace=1 */ SELECT 1")
+    );
+    check!(with_comment("SELECT 1", "*/ DROP TABLE t; /*").is_none());
+    Ok(())
+}
+
+#[test]
+fn skip_comments_keeps_optimizer_hints() -> crate::error::Result<()> {
+    check_eq!(
+        skip_leading_comments("/* a */ /*+ hint */ x"),
+        "/*+ hint */ x"
+    );
+    check_eq!(skip_leading_comments("--x"), "--x");
+    Ok(())
+}
+
+#[test]
+fn mariadb_set_statement() -> crate::error::Result<()> {
+    check_eq!(
+        with_max_statement_time("SELECT 1", Duration::from_micros(2_500_001)),
+        "SET STATEMENT max_statement_time=2.500001 FOR SELECT 1"
+    );
+    Ok(())
+}
+
+#[test]
+fn mariadb_statement_time_never_zero() -> crate::error::Result<()> {
+    for timeout in [
+        Duration::ZERO,
+        Duration::from_nanos(1),
+        Duration::from_nanos(499),
+    ] {
+        check_eq!(
+            with_max_statement_time("SELECT 1", timeout),
+            "SET STATEMENT max_statement_time=0.000001 FOR SELECT 1"
+        );
+        check_eq!(
+            set_session_timeout(timeout, true),
+            "SET SESSION max_statement_time=0.000001"
+        );
+    }
+    Ok(())
+}
@@ -5,23 +5,74 @@
     clippy::unwrap_used
 )]
 
+#[cfg(all(feature = "native-tls", feature = "rustls"))]
+compile_error!("the `native-tls` and `rustls` features are mutually exclusive");
+#[cfg(all(
+    any(feature = "sync-tls", feature = "tokio-tls", feature = "compio-tls"),
+    not(any(feature = "native-tls", feature = "rustls"))
+))]
+compile_error!(
+    "`sync-tls`, `tokio-tls` and `compio-tls` need the `native-tls` or `rustls` feature"
+);
+
+pub mod alloc_stats;
+pub mod arena;
+pub mod audit;
+pub mod binlog;
 mod buffer;
 mod buffer_pool;
+pub mod charset;
+pub mod classify;
+pub mod column_names;
+pub mod compat;
 pub mod constant;
+mod dialect;
+pub mod emulate;
 pub mod error;
 pub mod handler;
+pub mod hint;
+pub mod local_infile;
+pub mod multi_result;
+pub mod negotiation;
 mod nightly;
 mod opts;
+pub mod paranoid;
+mod pool_config;
+mod pool_sizing;
 mod prepared;
 pub mod protocol;
+pub mod quote;
 pub mod raw;
 pub mod ref_row;
+pub mod row;
+pub mod schema_check;
+pub mod schema_drift;
+pub mod server_info;
+pub mod session;
+pub mod snapshot;
+pub mod socket_stats;
+mod sql_lexer;
+pub mod statement_buffer;
+pub mod statement_cache;
+pub mod statement_log;
 pub mod sync;
+mod tcp_quickack;
+#[cfg(any(feature = "sync-tls", feature = "tokio-tls", feature = "compio-tls"))]
+mod tls;
+pub mod tls_info;
+pub mod topology;
 pub mod value;
+pub mod warm_up;
 
 pub use buffer::BufferSet;
 pub use buffer_pool::BufferPool;
-pub use opts::Opts;
+pub use dialect::Dialect;
+pub use opts::{
+    CompressionAlgorithm, DEFAULT_OFFLOAD_DECODE_BYTES, DEFAULT_READ_BUFFER_SIZE,
+    DEFAULT_YIELD_EVERY_BYTES, DangerZone, FlushPolicy, Opts, PASSWORD_FILE_ENV,
+};
+pub use pool_config::{PoolConfig, ResetOnReturn};
+pub use pool_sizing::AdaptivePoolSizing;
 pub use prepared::PreparedStatement;
 
 #[cfg(feature = "tokio")]
@@ -33,16 +84,89 @@ pub mod compio;
 #[cfg(all(feature = "diesel", feature = "sync"))]
 pub mod diesel;
 
+#[cfg(feature = "spill")]
+pub mod spill;
+
 #[cfg(feature = "derive")]
 pub use zero_mysql_derive as r#macro;
 
+#[cfg(all(test, feature = "alloc-stats"))]
+mod alloc_stats_test;
+#[cfg(test)]
+mod arena_test;
+#[cfg(test)]
+mod audit_test;
+#[cfg(test)]
+mod binlog_test;
 #[cfg(test)]
 mod buffer_test;
 #[cfg(test)]
+mod charset_test;
+#[cfg(test)]
+mod classify_test;
+#[cfg(test)]
+mod column_names_test;
+#[cfg(test)]
+mod compat_test;
+#[cfg(test)]
 mod constant_test;
 #[cfg(test)]
+mod dialect_test;
+#[cfg(test)]
+mod emulate_test;
+#[cfg(test)]
+mod error_test;
+#[cfg(test)]
+mod handler_test;
+#[cfg(test)]
+mod hint_test;
+#[cfg(test)]
+mod local_infile_test;
+#[cfg(test)]
+mod multi_result_test;
+#[cfg(test)]
+mod negotiation_test;
+#[cfg(test)]
 mod opts_test;
 #[cfg(test)]
+mod paranoid_test;
+#[cfg(test)]
+mod pool_config_test;
+#[cfg(test)]
+mod pool_sizing_test;
+#[cfg(test)]
+mod prepared_test;
+#[cfg(test)]
+mod quote_test;
+#[cfg(test)]
+mod row_test;
+#[cfg(test)]
+mod schema_check_test;
+#[cfg(test)]
+mod schema_drift_test;
+#[cfg(test)]
+mod server_info_test;
+#[cfg(test)]
+mod session_test;
+#[cfg(test)]
+mod snapshot_test;
+#[cfg(all(test, target_os = "linux"))]
+mod socket_stats_test;
+#[cfg(all(test, feature = "spill"))]
+mod spill_test;
+#[cfg(test)]
+mod statement_buffer_test;
+#[cfg(test)]
+mod statement_cache_test;
+#[cfg(test)]
+mod statement_log_test;
+#[cfg(test)]
 mod test_macros;
 #[cfg(test)]
+mod tls_info_test;
+#[cfg(test)]
+mod topology_test;
+#[cfg(test)]
 mod value_test;
+#[cfg(test)]
+mod warm_up_test;
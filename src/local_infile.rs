@@ -0,0 +1,160 @@
+//! `LOAD DATA LOCAL INFILE` support.
+//!
+//! When a query is `LOAD DATA LOCAL INFILE '<name>' INTO TABLE ..`, the server asks the client
+//! for the contents of `<name>`. Set [`Opts::local_infile`](crate::Opts::local_infile) to answer
+//! these requests; without it, `CLIENT_LOCAL_FILES` is not negotiated and the server rejects the query.
+//!
+//! The file name comes from the server, not from the query the client sent.
+//! A handler should only open files it expects, otherwise a malicious server can read any file
+//! the client can read.
+//!
+//! ```
+//! use std::fs::File;
+//! use std::io;
+//! use zero_mysql::Opts;
+//! use zero_mysql::local_infile::LocalInfile;
+//!
+//! let mut opts = Opts::default();
+//! opts.local_infile = Some(LocalInfile::new(|filename: &[u8]| match filename {
+//!     b"users.csv" => File::open("/srv/import/users.csv"),
+//!     _ => Err(io::Error::new(io::ErrorKind::PermissionDenied, "unexpected file")),
+//! }));
+//! ```
+
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::protocol::packet::PacketHeader;
+use zerocopy::FromBytes;
+
+/// Largest chunk of file data sent in a single packet
+pub const LOCAL_INFILE_CHUNK_SIZE: usize = 1 << 20;
+
+/// Opens the file requested by `LOAD DATA LOCAL INFILE`.
+///
+/// Implemented for closures taking the file name and returning a reader.
+/// The reader is read on the connection's task, including for the async backends.
+pub trait LocalInfileHandler: Send + Sync {
+    fn open(&self, filename: &[u8]) -> std::io::Result<Box<dyn Read + Send>>;
+}
+
+impl<F, R> LocalInfileHandler for F
+where
+    F: Fn(&[u8]) -> std::io::Result<R> + Send + Sync,
+    R: Read + Send + 'static,
+{
+    fn open(&self, filename: &[u8]) -> std::io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(self(filename)?))
+    }
+}
+
+/// A shared [`LocalInfileHandler`].
+#[derive(Clone)]
+pub struct LocalInfile {
+    handler: Arc<dyn LocalInfileHandler>,
+}
+
+impl LocalInfile {
+    pub fn new(handler: impl LocalInfileHandler + 'static) -> Self {
+        Self {
+            handler: Arc::new(handler),
+        }
+    }
+}
+
+impl fmt::Debug for LocalInfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalInfile").finish_non_exhaustive()
+    }
+}
+
+/// The packets sent in response to a LOCAL INFILE request:
+/// the file contents, then an empty packet.
+///
+/// An empty file is sent if the file cannot be opened or read.
+/// The error is kept and reported after the server's response, so the connection stays usable.
+pub(crate) struct LocalInfilePackets {
+    reader: Option<Box<dyn Read + Send>>,
+    sequence_id: u8,
+    error: Option<Error>,
+    finished: bool,
+}
+
+impl LocalInfilePackets {
+    /// `sequence_id` is the sequence id of the LOCAL INFILE request packet.
+    pub fn new(local_infile: Option<&LocalInfile>, filename: &[u8], sequence_id: u8) -> Self {
+        let (reader, error) = match local_infile {
+            Some(local_infile) => match local_infile.handler.open(filename) {
+                Ok(reader) => (Some(reader), None),
+                Err(err) => (None, Some(Error::IoError(err))),
+            },
+            None => (
+                None,
+                Some(Error::BadUsageError(format!(
+                    "LOCAL INFILE request for '{}' but Opts::local_infile is not set",
+                    String::from_utf8_lossy(filename)
+                ))),
+            ),
+        };
+        Self {
+            reader,
+            sequence_id,
+            error,
+            finished: false,
+        }
+    }
+
+    /// Encode the next packet (header and payload) into `buffer`.
+    ///
+    /// Returns `None` after the empty packet.
+    pub fn next_packet<'b>(&mut self, buffer: &'b mut Vec<u8>) -> Result<Option<&'b [u8]>> {
+        if self.finished {
+            return Ok(None);
+        }
+        buffer.clear();
+        buffer.resize(4 + LOCAL_INFILE_CHUNK_SIZE, 0);
+
+        let len = match self
+            .reader
+            .as_mut()
+            .map(|reader| read_chunk(reader, &mut buffer[4..]))
+        {
+            Some(Ok(len)) => len,
+            Some(Err(err)) => {
+                self.error = Some(Error::IoError(err));
+                0
+            }
+            None => 0,
+        };
+        if len == 0 {
+            self.reader = None;
+            self.finished = true;
+        }
+
+        self.sequence_id = self.sequence_id.wrapping_add(1);
+        buffer.truncate(4 + len);
+        PacketHeader::mut_from_bytes(&mut buffer[..4])?.encode_in_place(len, self.sequence_id);
+        Ok(Some(buffer))
+    }
+
+    /// The error of opening or reading the file
+    pub fn into_error(self) -> Option<Error> {
+        self.error
+    }
+}
+
+/// Read until `buf` is full or the reader is exhausted
+fn read_chunk(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
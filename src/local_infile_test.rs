@@ -0,0 +1,88 @@
+use std::io;
+
+use crate::buffer::BufferSet;
+use crate::error::Error;
+use crate::handler::DropHandler;
+use crate::local_infile::{LOCAL_INFILE_CHUNK_SIZE, LocalInfile, LocalInfilePackets};
+use crate::protocol::command::Action;
+use crate::protocol::command::query::Query;
+use crate::test_macros::{check, check_eq};
+
+fn csv() -> LocalInfile {
+    LocalInfile::new(|filename: &[u8]| match filename {
+        b"a.csv" => Ok(io::Cursor::new(b"1,a\n2,b\n".to_vec())),
+        b"big.csv" => Ok(io::Cursor::new(vec![b'x'; LOCAL_INFILE_CHUNK_SIZE + 1])),
+        _ => Err(io::Error::new(io::ErrorKind::NotFound, "no such file")),
+    })
+}
+
+/// (payload length, sequence id) of every packet
+type Headers = Vec<(usize, u8)>;
+
+fn packets(mut packets: LocalInfilePackets) -> crate::error::Result<(Headers, Option<Error>)> {
+    let mut buffer = Vec::new();
+    let mut headers = Vec::new();
+    while let Some(packet) = packets.next_packet(&mut buffer)? {
+        let [l0, l1, l2, sequence_id] = packet.first_chunk::<4>().copied().unwrap_or_default();
+        let len = u32::from_le_bytes([l0, l1, l2, 0]) as usize;
+        check_eq!(packet.len(), 4 + len);
+        headers.push((len, sequence_id));
+    }
+    Ok((headers, packets.into_error()))
+}
+
+#[test]
+fn sends_file_then_empty_packet() -> crate::error::Result<()> {
+    let local_infile = csv();
+    let (headers, error) = packets(LocalInfilePackets::new(Some(&local_infile), b"a.csv", 1))?;
+    check_eq!(headers, vec![(8, 2), (0, 3)]);
+    check!(error.is_none());
+
+    let (big, _) = packets(LocalInfilePackets::new(Some(&local_infile), b"big.csv", 1))?;
+    check_eq!(big, vec![(LOCAL_INFILE_CHUNK_SIZE, 2), (1, 3), (0, 4)]);
+    Ok(())
+}
+
+#[test]
+fn sends_empty_file_on_error() -> crate::error::Result<()> {
+    let local_infile = csv();
+    let (headers, error) = packets(LocalInfilePackets::new(
+        Some(&local_infile),
+        b"/etc/passwd",
+        1,
+    ))?;
+    check_eq!(headers, vec![(0, 2)]);
+    check!(matches!(error, Some(Error::IoError(_))));
+
+    let (no_handler, usage_error) = packets(LocalInfilePackets::new(None, b"a.csv", 1))?;
+    check_eq!(no_handler, vec![(0, 2)]);
+    check!(matches!(usage_error, Some(Error::BadUsageError(_))));
+    Ok(())
+}
+
+#[test]
+fn query_requests_local_infile() -> crate::error::Result<()> {
+    let mut handler = DropHandler::default();
+    let mut query = Query::new(&mut handler);
+    let mut buffer_set = BufferSet::new();
+
+    check!(matches!(
+        query.step(&mut buffer_set)?,
+        Action::NeedPacket(_)
+    ));
+    buffer_set.read_buffer = b"\xFBa.csv".to_vec();
+    check!(matches!(
+        query.step(&mut buffer_set)?,
+        Action::LocalInfile { filename: b"a.csv" }
+    ));
+
+    // OK with 2 affected rows after the file is sent
+    check!(matches!(
+        query.step(&mut buffer_set)?,
+        Action::NeedPacket(_)
+    ));
+    buffer_set.read_buffer = vec![0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00];
+    check!(matches!(query.step(&mut buffer_set)?, Action::Finished));
+    check_eq!(handler.affected_rows(), 2);
+    Ok(())
+}
@@ -0,0 +1,375 @@
+//! Result sets of a multi-statement query or a `CALL`.
+//!
+//! `CALL` of a stored procedure returns one result set per `SELECT` in the procedure,
+//! followed by an OK packet with the status of the `CALL` itself.
+//! Multi-statement queries (`SELECT 1; UPDATE t ..; SELECT 2`) return one result per statement.
+//! [`MultiResultHandler`] collects each of them into a [`ResultSet`],
+//! which `query_multi()` and `exec_multi()` return in order.
+//!
+//! ```no_run
+//! # fn run(conn: &mut zero_mysql::sync::Conn) -> zero_mysql::error::Result<()> {
+//! let mut stmt = conn.prepare("CALL get_order(?)")?;
+//! let results = conn.exec_multi(&mut stmt, (42,))?;
+//! let [order, items, status] = results.as_slice() else {
+//!     return Ok(());
+//! };
+//! let order: Vec<(i64, String)> = order.rows()?;
+//! let items: Vec<(i64, i32)> = items.rows()?;
+//! println!("{order:?} {items:?} warnings: {}", status.ok().warnings);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! When the shape of the procedure is known, `call()` decodes each result set into its own
+//! `Vec` of a tuple with [`CallHandler`], without buffering rows:
+//!
+//! ```no_run
+//! # fn run(conn: &mut zero_mysql::sync::Conn) -> zero_mysql::error::Result<()> {
+//! let (order, items): (Vec<(i64, String)>, Vec<(i64, i32)>) =
+//!     conn.call("CALL get_order(?)", (42,))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::Range;
+
+use crate::error::{Error, Result};
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitions};
+use crate::protocol::response::{OkPayload, OkPayloadBytes};
+use crate::protocol::r#trait::{BinaryResultSetHandler, TextResultSetHandler};
+use crate::protocol::{BinaryRowPayload, TextRowPayload};
+use crate::raw::FromRow;
+use crate::value::NullBitmap;
+
+/// A row of a [`ResultSet`]
+#[derive(Debug, Clone)]
+pub enum ResultSetRow<'a> {
+    /// A row of `query_multi()`
+    Text(TextRowPayload<'a>),
+    /// A row of `exec_multi()`
+    Binary(BinaryRowPayload<'a>),
+}
+
+/// Where a row is stored in `ResultSet::bytes`
+#[derive(Debug, Clone)]
+struct RowRange {
+    range: Range<usize>,
+    /// `None` for text rows, the null bitmap length for binary rows
+    null_bitmap_len: Option<usize>,
+}
+
+/// One result of a multi-statement query or a `CALL`: the columns and rows of a result set
+/// and the OK packet that ends it, or only an OK packet.
+#[derive(Debug)]
+pub struct ResultSet {
+    columns: Option<ColumnDefinitions>,
+    bytes: Vec<u8>,
+    rows: Vec<RowRange>,
+    ok: OkPayload,
+}
+
+impl ResultSet {
+    /// Columns of the result set. Empty if the statement returned only an OK packet.
+    pub fn columns(&self) -> &[ColumnDefinition<'_>] {
+        self.columns
+            .as_ref()
+            .map_or(&[], ColumnDefinitions::definitions)
+    }
+
+    /// Whether the statement returned rows, as opposed to only an OK packet
+    pub fn has_columns(&self) -> bool {
+        self.columns.is_some()
+    }
+
+    /// The OK packet after the rows, or the OK packet of a statement without a result set
+    pub fn ok(&self) -> &OkPayload {
+        &self.ok
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Iterate over the rows
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = ResultSetRow<'_>> {
+        let num_columns = self.columns().len();
+        self.rows.iter().map(move |row| {
+            let bytes = &self.bytes[row.range.clone()];
+            match row.null_bitmap_len {
+                None => ResultSetRow::Text(TextRowPayload(bytes)),
+                Some(null_bitmap_len) => {
+                    let (null_bitmap, values) = bytes.split_at(null_bitmap_len);
+                    ResultSetRow::Binary(BinaryRowPayload::new(
+                        NullBitmap::for_result_set(null_bitmap),
+                        values,
+                        num_columns,
+                    ))
+                }
+            }
+        })
+    }
+
+    /// Decode the rows of an `exec_multi()` result set.
+    ///
+    /// Text rows of `query_multi()` cannot be decoded with `FromRow`; use [`iter`](Self::iter).
+    pub fn rows<Row: for<'buf> FromRow<'buf>>(&self) -> Result<Vec<Row>> {
+        let cols = self.columns();
+        self.iter()
+            .map(|row| match row {
+                ResultSetRow::Binary(row) => Row::from_row(cols, row),
+                ResultSetRow::Text(_) => Err(Error::BadUsageError(
+                    "rows() requires a binary result set from exec_multi(); use iter() for query_multi()"
+                        .to_string(),
+                )),
+            })
+            .collect()
+    }
+}
+
+/// The rows of `exec_decode_or_raw()`: decoded into `Row`, or kept as a [`ResultSet`] if any
+/// row does not decode, e.g. after a column was renamed or retyped.
+#[derive(Debug)]
+pub enum DecodeOrRaw<Row> {
+    Decoded(Vec<Row>),
+    Raw {
+        result_set: ResultSet,
+        /// The first decode error, e.g. `Error::MissingColumn`
+        mismatch: Error,
+    },
+}
+
+impl<Row: for<'buf> FromRow<'buf>> DecodeOrRaw<Row> {
+    /// Decode the first result set with columns. `Decoded` is empty if there is none.
+    pub fn from_result_sets(result_sets: Vec<ResultSet>) -> Self {
+        let Some(result_set) = result_sets.into_iter().find(ResultSet::has_columns) else {
+            return Self::Decoded(Vec::new());
+        };
+        match result_set.rows() {
+            Ok(rows) => Self::Decoded(rows),
+            Err(mismatch) => {
+                tracing::warn!(error = %mismatch, "rows do not decode, returning the raw result set");
+                Self::Raw {
+                    result_set,
+                    mismatch,
+                }
+            }
+        }
+    }
+}
+
+impl<Row> DecodeOrRaw<Row> {
+    /// The decoded rows, or `None` if the rows did not decode
+    pub fn decoded(self) -> Option<Vec<Row>> {
+        match self {
+            Self::Decoded(rows) => Some(rows),
+            Self::Raw { .. } => None,
+        }
+    }
+
+    /// Why the rows did not decode
+    pub fn mismatch(&self) -> Option<&Error> {
+        match self {
+            Self::Decoded(_) => None,
+            Self::Raw { mismatch, .. } => Some(mismatch),
+        }
+    }
+}
+
+/// Collects every result of a response into a [`ResultSet`].
+#[derive(Debug, Default)]
+pub struct MultiResultHandler {
+    result_sets: Vec<ResultSet>,
+    columns: Option<ColumnDefinitions>,
+    bytes: Vec<u8>,
+    rows: Vec<RowRange>,
+}
+
+impl MultiResultHandler {
+    pub fn into_result_sets(self) -> Vec<ResultSet> {
+        self.result_sets
+    }
+
+    fn start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.columns = Some(ColumnDefinitions::from_definitions(cols)?);
+        Ok(())
+    }
+
+    fn push_row(&mut self, parts: &[&[u8]], null_bitmap_len: Option<usize>) {
+        let start = self.bytes.len();
+        for part in parts {
+            self.bytes.extend_from_slice(part);
+        }
+        self.rows.push(RowRange {
+            range: start..self.bytes.len(),
+            null_bitmap_len,
+        });
+    }
+
+    fn end(&mut self, ok: OkPayloadBytes<'_>) -> Result<()> {
+        let ok = OkPayload::try_from(ok)?;
+        self.result_sets.push(ResultSet {
+            columns: self.columns.take(),
+            bytes: std::mem::take(&mut self.bytes),
+            rows: std::mem::take(&mut self.rows),
+            ok,
+        });
+        Ok(())
+    }
+}
+
+impl TextResultSetHandler for MultiResultHandler {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        self.end(ok)
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.start(cols)
+    }
+
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        self.push_row(&[row.0], None);
+        Ok(())
+    }
+
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        self.end(eof)
+    }
+}
+
+impl BinaryResultSetHandler for MultiResultHandler {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        self.end(ok)
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.start(cols)
+    }
+
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<()> {
+        let null_bitmap = row.null_bitmap().as_bytes();
+        self.push_row(&[null_bitmap, row.values()], Some(null_bitmap.len()));
+        Ok(())
+    }
+
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        self.end(eof)
+    }
+}
+
+/// A tuple of `Vec<Row>`, one for each result set of a `CALL`, in order.
+pub trait CallResults: Default {
+    /// The number of result sets
+    const LEN: usize;
+
+    /// Decode `row` of the result set at `index` and push it to its `Vec`
+    fn push(
+        &mut self,
+        index: usize,
+        cols: &[ColumnDefinition<'_>],
+        row: BinaryRowPayload<'_>,
+    ) -> Result<()>;
+}
+
+macro_rules! impl_call_results {
+    ($($idx:tt: $T:ident),+) => {
+        impl<$($T),+> CallResults for ($(Vec<$T>,)+)
+        where
+            $($T: for<'buf> FromRow<'buf>,)+
+        {
+            const LEN: usize = [$($idx),+].len();
+
+            fn push(
+                &mut self,
+                index: usize,
+                cols: &[ColumnDefinition<'_>],
+                row: BinaryRowPayload<'_>,
+            ) -> Result<()> {
+                match index {
+                    $($idx => self.$idx.push($T::from_row(cols, row)?),)+
+                    _ => {
+                        return Err(Error::BadUsageError(format!(
+                            "no Vec for result set {index} of a CALL with {} result sets",
+                            Self::LEN
+                        )));
+                    }
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_call_results!(0: A);
+impl_call_results!(0: A, 1: B);
+impl_call_results!(0: A, 1: B, 2: C);
+impl_call_results!(0: A, 1: B, 2: C, 3: D);
+impl_call_results!(0: A, 1: B, 2: C, 3: D, 4: E);
+impl_call_results!(0: A, 1: B, 2: C, 3: D, 4: E, 5: F);
+impl_call_results!(0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G);
+impl_call_results!(0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G, 7: H);
+
+/// Decodes the result sets of a `CALL` into a [`CallResults`] tuple.
+///
+/// The procedure must return exactly `T::LEN` result sets followed by the OK packet of the
+/// `CALL` itself; [`finish`](Self::finish) fails otherwise.
+#[derive(Debug, Default)]
+pub struct CallHandler<T> {
+    results: T,
+    /// The number of result sets that started
+    started: usize,
+    /// The OK packet without a result set that ends the `CALL`
+    status: Option<OkPayload>,
+}
+
+impl<T: CallResults> CallHandler<T> {
+    /// The decoded result sets, after checking that all of them and the final OK were received
+    pub fn finish(self) -> Result<T> {
+        if self.started != T::LEN {
+            return Err(Error::BadUsageError(format!(
+                "expected {} result sets from the CALL, got {}",
+                T::LEN,
+                self.started
+            )));
+        }
+        if self.status.is_none() {
+            return Err(Error::BadUsageError(
+                "the CALL did not end with an OK packet".to_string(),
+            ));
+        }
+        Ok(self.results)
+    }
+
+    /// The OK packet of the `CALL` itself, once received
+    pub fn status(&self) -> Option<&OkPayload> {
+        self.status.as_ref()
+    }
+}
+
+impl<T: CallResults> BinaryResultSetHandler for CallHandler<T> {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> Result<()> {
+        self.status = Some(OkPayload::try_from(ok)?);
+        Ok(())
+    }
+
+    fn resultset_start(&mut self, _cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.started += 1;
+        Ok(())
+    }
+
+    /// Rows of extra result sets are read and skipped so that the connection stays usable;
+    /// `finish()` reports them.
+    fn row(&mut self, cols: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<()> {
+        if self.started > T::LEN {
+            return Ok(());
+        }
+        self.results.push(self.started - 1, cols, row)
+    }
+
+    fn resultset_end(&mut self, _eof: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use zerocopy::FromBytes;
+
+use crate::multi_result::{CallHandler, DecodeOrRaw, MultiResultHandler, ResultSetRow};
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail};
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::{BinaryResultSetHandler, TextResultSetHandler};
+use crate::protocol::{BinaryRowPayload, TextRowPayload};
+use crate::test_macros::{check, check_eq};
+use crate::value::NullBitmap;
+
+/// EOF with SERVER_MORE_RESULTS_EXISTS
+const MORE: &[u8] = &[0xFE, 0, 0, 0x0A, 0x00, 0, 0];
+/// OK with 3 affected rows and SERVER_STATUS_AUTOCOMMIT
+const DONE: &[u8] = &[0x00, 3, 0, 0x02, 0x00, 0, 0];
+
+/// BIGINT NOT NULL
+const BIGINT_TAIL: [u8; 12] = [
+    0x3F, 0x00, 0x14, 0x00, 0x00, 0x00, 0x08, 0x01, 0x00, 0x00, 0, 0,
+];
+
+fn bigint_column(tail: &ColumnDefinitionTail) -> ColumnDefinition<'_> {
+    ColumnDefinition {
+        schema: b"test",
+        table_alias: b"",
+        table_original: b"",
+        name_alias: b"id",
+        name_original: b"id",
+        tail,
+        name_id: None,
+    }
+}
+
+#[test]
+fn binary_result_sets_are_split_at_each_end() -> crate::error::Result<()> {
+    let tail = ColumnDefinitionTail::ref_from_bytes(&BIGINT_TAIL)?;
+    let cols = [bigint_column(tail)];
+    let mut handler = MultiResultHandler::default();
+
+    BinaryResultSetHandler::resultset_start(&mut handler, &cols)?;
+    for id in [1_i64, 2] {
+        let values = id.to_le_bytes();
+        let row = BinaryRowPayload::new(NullBitmap::for_result_set(&[0]), &values, 1);
+        BinaryResultSetHandler::row(&mut handler, &cols, row)?;
+    }
+    BinaryResultSetHandler::resultset_end(&mut handler, OkPayloadBytes(MORE))?;
+    BinaryResultSetHandler::resultset_start(&mut handler, &cols)?;
+    BinaryResultSetHandler::resultset_end(&mut handler, OkPayloadBytes(MORE))?;
+    BinaryResultSetHandler::no_result_set(&mut handler, OkPayloadBytes(DONE))?;
+
+    let result_sets = handler.into_result_sets();
+    let [first, second, status] = result_sets.as_slice() else {
+        return Err(crate::error::Error::LibraryBug(crate::error::eyre!(
+            "expected 3 result sets, got {}",
+            result_sets.len()
+        )));
+    };
+    check_eq!(first.rows::<(i64,)>()?, vec![(1,), (2,)]);
+    check_eq!(first.iter().len(), 2);
+    check_eq!(first.columns()[0].name_alias, b"id");
+    check!(second.has_columns());
+    check!(second.is_empty());
+    check!(!status.has_columns());
+    check_eq!(status.ok().affected_rows, 3);
+    Ok(())
+}
+
+#[test]
+fn text_rows_are_kept_as_payloads() -> crate::error::Result<()> {
+    let mut handler = MultiResultHandler::default();
+    TextResultSetHandler::resultset_start(&mut handler, &[])?;
+    TextResultSetHandler::row(&mut handler, &[], TextRowPayload(b"\x01a"))?;
+    TextResultSetHandler::resultset_end(&mut handler, OkPayloadBytes(DONE))?;
+
+    let result_sets = handler.into_result_sets();
+    check_eq!(result_sets.len(), 1);
+    let rows: Vec<_> = result_sets[0].iter().collect();
+    check!(matches!(
+        rows[..],
+        [ResultSetRow::Text(TextRowPayload(b"\x01a"))]
+    ));
+    // Text rows cannot be decoded with FromRow
+    check!(result_sets[0].rows::<(i64,)>().is_err());
+    Ok(())
+}
+
+#[test]
+fn decode_or_raw_keeps_rows_that_do_not_decode() -> crate::error::Result<()> {
+    let tail = ColumnDefinitionTail::ref_from_bytes(&BIGINT_TAIL)?;
+    let cols = [bigint_column(tail)];
+    let mut handler = MultiResultHandler::default();
+    BinaryResultSetHandler::resultset_start(&mut handler, &cols)?;
+    let values = 7_i64.to_le_bytes();
+    let row = BinaryRowPayload::new(NullBitmap::for_result_set(&[0]), &values, 1);
+    BinaryResultSetHandler::row(&mut handler, &cols, row)?;
+    BinaryResultSetHandler::resultset_end(&mut handler, OkPayloadBytes(DONE))?;
+    let result_sets = handler.into_result_sets();
+
+    let raw = DecodeOrRaw::<(String,)>::from_result_sets(result_sets);
+    check!(raw.mismatch().is_some());
+    let DecodeOrRaw::Raw { result_set, .. } = raw else {
+        return Err(crate::error::Error::LibraryBug(crate::error::eyre!(
+            "expected raw rows"
+        )));
+    };
+    check_eq!(result_set.rows::<(i64,)>()?, vec![(7,)]);
+
+    let decoded = DecodeOrRaw::<(i64,)>::from_result_sets(vec![result_set]);
+    check_eq!(decoded.decoded(), Some(vec![(7,)]));
+    check_eq!(
+        DecodeOrRaw::<(i64,)>::from_result_sets(Vec::new()).decoded(),
+        Some(vec![])
+    );
+    Ok(())
+}
+
+/// Feed result sets of BIGINT rows to `handler`, then the OK packet of the `CALL` if `status`
+fn feed_call<H: BinaryResultSetHandler>(
+    handler: &mut H,
+    result_sets: &[&[i64]],
+    status: bool,
+) -> crate::error::Result<()> {
+    let tail = ColumnDefinitionTail::ref_from_bytes(&BIGINT_TAIL)?;
+    let cols = [bigint_column(tail)];
+    for rows in result_sets {
+        handler.resultset_start(&cols)?;
+        for id in *rows {
+            let values = id.to_le_bytes();
+            let row = BinaryRowPayload::new(NullBitmap::for_result_set(&[0]), &values, 1);
+            handler.row(&cols, row)?;
+        }
+        handler.resultset_end(OkPayloadBytes(MORE))?;
+    }
+    if status {
+        handler.no_result_set(OkPayloadBytes(DONE))?;
+    }
+    Ok(())
+}
+
+#[test]
+fn call_decodes_each_result_set_in_order() -> crate::error::Result<()> {
+    let mut handler = CallHandler::<(Vec<(i64,)>, Vec<(i64,)>)>::default();
+    feed_call(&mut handler, &[&[1, 2], &[3]], true)?;
+    check_eq!(handler.status().map(|ok| ok.affected_rows), Some(3));
+    let (first, second) = handler.finish()?;
+    check_eq!(first, vec![(1,), (2,)]);
+    check_eq!(second, vec![(3,)]);
+    Ok(())
+}
+
+#[test]
+fn call_checks_the_number_of_result_sets() -> crate::error::Result<()> {
+    type Two = (Vec<(i64,)>, Vec<(i64,)>);
+
+    let mut fewer = CallHandler::<Two>::default();
+    feed_call(&mut fewer, &[&[1]], true)?;
+    check!(fewer.finish().is_err());
+
+    // Rows of extra result sets are skipped without failing the exec
+    let mut more = CallHandler::<Two>::default();
+    feed_call(&mut more, &[&[1], &[2], &[3]], true)?;
+    check!(more.finish().is_err());
+
+    let mut no_status = CallHandler::<Two>::default();
+    feed_call(&mut no_status, &[&[1], &[2]], false)?;
+    check!(no_status.finish().is_err());
+    Ok(())
+}
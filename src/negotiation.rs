@@ -0,0 +1,144 @@
+//! Capabilities requested in `Opts` that the handshake did not negotiate.
+//!
+//! A server that lacks a capability, or a dialect that switches it off, does not fail the
+//! connection: `tls` connects unencrypted, `compress` connects uncompressed, and so on.
+//! `Conn::negotiation_report()` lists these downgrades, each one is logged with
+//! `tracing::warn!`, and `Opts::strict_capabilities` turns them into a connection error.
+
+use std::fmt;
+
+use crate::Opts;
+use crate::constant::{CAPABILITIES_CONFIGURABLE, CapabilityFlags};
+use crate::dialect::Dialect;
+use crate::opts::CompressionAlgorithm;
+
+/// Why a requested capability was not negotiated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DowngradeReason {
+    /// The server did not announce the capability in its initial handshake
+    NotSupportedByServer,
+    /// The capability is never requested from this dialect. See [`Dialect`].
+    DisabledForDialect(Dialect),
+}
+
+/// A capability requested in `Opts` that the connection does not use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Downgrade {
+    /// The `Opts` field that requested the capability, e.g. `"tls"`
+    pub option: &'static str,
+    pub capability: CapabilityFlags,
+    pub reason: DowngradeReason,
+}
+
+impl fmt::Display for Downgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?}): ", self.option, self.capability)?;
+        match self.reason {
+            DowngradeReason::NotSupportedByServer => f.write_str("not supported by the server"),
+            DowngradeReason::DisabledForDialect(dialect) => write!(f, "disabled for {}", dialect),
+        }
+    }
+}
+
+/// The capabilities requested in `Opts` and the ones the handshake negotiated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiationReport {
+    requested: CapabilityFlags,
+    negotiated: CapabilityFlags,
+    downgrades: Vec<Downgrade>,
+}
+
+impl NegotiationReport {
+    /// Compare the capabilities `opts` asks for with the `negotiated` ones.
+    pub fn new(opts: &Opts, dialect: Dialect, negotiated: CapabilityFlags) -> Self {
+        let requested_by = requested_capabilities(opts);
+        let mut requested = CapabilityFlags::empty();
+        let mut downgrades = Vec::new();
+        for (option, capability) in requested_by {
+            requested |= capability;
+            if negotiated.contains(capability) {
+                continue;
+            }
+            let reason = if dialect.disabled_capabilities().contains(capability) {
+                DowngradeReason::DisabledForDialect(dialect)
+            } else {
+                DowngradeReason::NotSupportedByServer
+            };
+            downgrades.push(Downgrade {
+                option,
+                capability,
+                reason,
+            });
+        }
+        Self {
+            requested,
+            negotiated,
+            downgrades,
+        }
+    }
+
+    /// Capabilities requested by `Opts` fields, excluding the ones that are always requested
+    pub fn requested(&self) -> CapabilityFlags {
+        self.requested
+    }
+
+    /// All capabilities of the connection
+    pub fn negotiated(&self) -> CapabilityFlags {
+        self.negotiated
+    }
+
+    /// Requested capabilities that were not negotiated
+    pub fn downgrades(&self) -> &[Downgrade] {
+        &self.downgrades
+    }
+
+    pub fn is_downgraded(&self) -> bool {
+        !self.downgrades.is_empty()
+    }
+}
+
+impl Default for NegotiationReport {
+    fn default() -> Self {
+        Self {
+            requested: CapabilityFlags::empty(),
+            negotiated: CapabilityFlags::empty(),
+            downgrades: Vec::new(),
+        }
+    }
+}
+
+/// The capability each set `Opts` field asks for
+fn requested_capabilities(opts: &Opts) -> Vec<(&'static str, CapabilityFlags)> {
+    let mut requested = Vec::new();
+    if opts.tls && !opts.danger_zone.skip_tls {
+        requested.push(("tls", CapabilityFlags::CLIENT_SSL));
+    }
+    if opts.compress {
+        requested.push(match opts.compression_algorithm {
+            CompressionAlgorithm::Zlib => ("compress", CapabilityFlags::CLIENT_COMPRESS),
+            CompressionAlgorithm::Zstd => (
+                "compression_algorithm",
+                CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM,
+            ),
+        });
+    }
+    if opts.db.is_some() {
+        requested.push(("db", CapabilityFlags::CLIENT_CONNECT_WITH_DB));
+    }
+    if opts.local_infile.is_some() {
+        requested.push(("local_infile", CapabilityFlags::CLIENT_LOCAL_FILES));
+    }
+    if opts.application_name.is_some() {
+        requested.push(("application_name", CapabilityFlags::CLIENT_CONNECT_ATTRS));
+    }
+    if opts.session_track_system_variables.is_some() {
+        requested.push((
+            "session_track_system_variables",
+            CapabilityFlags::CLIENT_SESSION_TRACK,
+        ));
+    }
+    for capability in (opts.capabilities & CAPABILITIES_CONFIGURABLE).iter() {
+        requested.push(("capabilities", capability));
+    }
+    requested
+}
@@ -0,0 +1,71 @@
+use crate::Dialect;
+use crate::Opts;
+use crate::constant::CapabilityFlags;
+use crate::negotiation::{Downgrade, DowngradeReason, NegotiationReport};
+use crate::opts::CompressionAlgorithm;
+use crate::test_macros::{check, check_eq};
+
+#[test]
+fn nothing_requested() -> crate::error::Result<()> {
+    let report = NegotiationReport::new(&Opts::default(), Dialect::MySql, CapabilityFlags::empty());
+    check!(!report.is_downgraded());
+    check_eq!(report.requested(), CapabilityFlags::empty());
+    Ok(())
+}
+
+#[test]
+fn server_without_tls_and_zstd() -> crate::error::Result<()> {
+    let opts = Opts {
+        tls: true,
+        compress: true,
+        compression_algorithm: CompressionAlgorithm::Zstd,
+        ..Opts::default()
+    };
+    // zstd fell back to zlib
+    let negotiated = CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_COMPRESS;
+    let report = NegotiationReport::new(&opts, Dialect::MySql, negotiated);
+    check_eq!(
+        report.downgrades(),
+        [
+            Downgrade {
+                option: "tls",
+                capability: CapabilityFlags::CLIENT_SSL,
+                reason: DowngradeReason::NotSupportedByServer,
+            },
+            Downgrade {
+                option: "compression_algorithm",
+                capability: CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM,
+                reason: DowngradeReason::NotSupportedByServer,
+            },
+        ]
+    );
+    check_eq!(report.negotiated(), negotiated);
+    check_eq!(
+        report.downgrades()[0].to_string(),
+        "tls (CapabilityFlags(CLIENT_SSL)): not supported by the server"
+    );
+    Ok(())
+}
+
+#[test]
+fn dialect_disables_session_track() -> crate::error::Result<()> {
+    let opts = Opts {
+        session_track_system_variables: Some("autocommit".to_string()),
+        capabilities: CapabilityFlags::CLIENT_FOUND_ROWS,
+        ..Opts::default()
+    };
+    let report = NegotiationReport::new(&opts, Dialect::TiDb, CapabilityFlags::CLIENT_FOUND_ROWS);
+    check_eq!(
+        report.requested(),
+        CapabilityFlags::CLIENT_SESSION_TRACK | CapabilityFlags::CLIENT_FOUND_ROWS
+    );
+    check_eq!(
+        report.downgrades(),
+        [Downgrade {
+            option: "session_track_system_variables",
+            capability: CapabilityFlags::CLIENT_SESSION_TRACK,
+            reason: DowngradeReason::DisabledForDialect(Dialect::TiDb),
+        }]
+    );
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Heuristic detection of SQL injection in text protocol queries.
+//!
+//! With the `paranoid` feature enabled, every text protocol query is checked before it is sent.
+//! A suspicious query is logged with `tracing::warn!` and panics in debug builds.
+//! Use prepared statements (`prepare()` + `exec()`) to pass user input instead.
+
+use crate::sql_lexer::{Token, TokenKind, tokenize};
+
+/// Returns a short description of why `sql` looks like it contains interpolated user input.
+///
+/// This is a heuristic. It looks for the usual results of formatting untrusted strings into SQL:
+/// - an unterminated string literal or backtick identifier
+/// - a comment right after a string literal (`... WHERE name = 'x' -- '`)
+/// - a tautology after `OR` (`OR 1=1`, `OR 'a'='a'`)
+/// - another statement after a `;` that follows a string literal (`'x'; DROP TABLE t`)
+///
+/// ```
+/// use zero_mysql::paranoid::suspicious_sql;
+///
+/// assert_eq!(suspicious_sql("SELECT * FROM user WHERE name = 'alice'"), None);
+/// assert!(suspicious_sql("SELECT * FROM user WHERE name = '' OR '1'='1'").is_some());
+/// ```
+pub fn suspicious_sql(sql: &str) -> Option<&'static str> {
+    let tokens: Vec<_> = tokenize(sql)
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Whitespace)
+        .collect();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let prev_is_string = i > 0 && tokens[i - 1].kind == TokenKind::String;
+        match token.kind {
+            TokenKind::Unterminated(reason) => return Some(reason),
+            TokenKind::Comment if prev_is_string => {
+                return Some("comment after a string literal");
+            }
+            TokenKind::Symbol if token.text == ";" && prev_is_string && i + 1 < tokens.len() => {
+                return Some("statement after a string literal");
+            }
+            TokenKind::Word if token.text.eq_ignore_ascii_case("OR") => {
+                if let [lhs, eq, rhs, ..] = &tokens[i + 1..]
+                    && is_literal(lhs)
+                    && eq.text == "="
+                    && is_literal(rhs)
+                    && lhs.text == rhs.text
+                {
+                    return Some("tautology after OR");
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_literal(token: &Token<'_>) -> bool {
+    matches!(token.kind, TokenKind::String | TokenKind::Number)
+}
+
+/// Checks a text protocol query if the `paranoid` feature is enabled.
+#[inline]
+pub(crate) fn check_query(sql: &str) {
+    #[cfg(feature = "paranoid")]
+    if let Some(reason) = suspicious_sql(sql) {
+        tracing::warn!(
+            reason,
+            sql,
+            "possible SQL injection in a text protocol query, use prepared statements instead"
+        );
+        debug_assert!(false, "possible SQL injection ({reason}): {sql}");
+    }
+    #[cfg(not(feature = "paranoid"))]
+    let _ = sql;
+}
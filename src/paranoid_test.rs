@@ -0,0 +1,48 @@
+use crate::paranoid::suspicious_sql;
+use crate::test_macros::{check, check_eq};
+
+#[test]
+fn accepts_ordinary_queries() -> crate::error::Result<()> {
+    for sql in [
+        "SELECT 1",
+        "SELECT * FROM t WHERE name = 'it''s' AND id = 3",
+        "SELECT 'a;b', \"--\" FROM `we'ird`",
+        "SELECT /*+ MAX_EXECUTION_TIME(10) */ 1 -- trailing comment",
+        "/* tag */ SELECT 1; SELECT 2",
+        "UPDATE t SET a = 1 WHERE b = 2 OR c = 3",
+        "SELECT 'x';",
+    ] {
+        check_eq!(suspicious_sql(sql), None);
+    }
+    Ok(())
+}
+
+#[test]
+fn rejects_injection_patterns() -> crate::error::Result<()> {
+    check_eq!(
+        suspicious_sql("SELECT * FROM t WHERE name = 'O'Brien'"),
+        Some("unterminated string literal")
+    );
+    check_eq!(
+        suspicious_sql("SELECT * FROM t WHERE name = 'admin' -- ' AND pw = 'x'"),
+        Some("comment after a string literal")
+    );
+    check_eq!(
+        suspicious_sql("SELECT * FROM t WHERE name = 'x'#'"),
+        Some("comment after a string literal")
+    );
+    check_eq!(
+        suspicious_sql("SELECT * FROM t WHERE id = 1 or 1=1"),
+        Some("tautology after OR")
+    );
+    check_eq!(
+        suspicious_sql("SELECT * FROM t WHERE name = '' OR 'a'='a'"),
+        Some("tautology after OR")
+    );
+    check_eq!(
+        suspicious_sql("SELECT * FROM t WHERE name = 'x'; DROP TABLE t"),
+        Some("statement after a string literal")
+    );
+    check!(suspicious_sql("SELECT 1 /* open").is_some());
+    Ok(())
+}
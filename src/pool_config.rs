@@ -0,0 +1,162 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::Duration;
+
+/// Connection lifetime and health check settings of a pool.
+///
+/// The limits are checked when a connection is acquired or returned, and by the background
+/// reaper started with `tokio::Pool::spawn_reaper`, which also pings idle connections
+/// and opens connections up to `min_idle`.
+///
+/// ```
+/// use std::time::Duration;
+/// use zero_mysql::PoolConfig;
+///
+/// let config = PoolConfig::default()
+///     .with_max_lifetime(Duration::from_secs(30 * 60))
+///     .with_idle_timeout(Duration::from_secs(10 * 60))
+///     .with_min_idle(2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    reset_on_return: ResetOnReturn,
+    max_lifetime: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    health_check_interval: Duration,
+    min_idle: usize,
+    jitter: f64,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            reset_on_return: ResetOnReturn::default(),
+            max_lifetime: None,
+            idle_timeout: None,
+            health_check_interval: Duration::from_secs(30),
+            min_idle: 0,
+            jitter: 0.1,
+        }
+    }
+}
+
+/// How a connection is cleaned up when it is returned to the pool.
+///
+/// After the cleanup, connections left inside a transaction or with a different autocommit
+/// setting than when they were opened are dropped, as are connections whose cleanup failed.
+/// `Opts::pool_reset_conn = false` turns every mode into `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResetOnReturn {
+    /// `COM_RESET_CONNECTION`. On servers without it (MySQL before 5.7.3, MariaDB before 10.2.4,
+    /// TiDB and Vitess), `ROLLBACK` and restore autocommit instead, keeping other session state.
+    #[default]
+    Fast,
+    /// `Fast`, re-running `Opts::init_commands` before the session setup as connecting does,
+    /// then read `@@autocommit` back from the server.
+    Full,
+    /// Return connections as they are, only restoring roles changed with `set_role()`.
+    None,
+}
+
+impl PoolConfig {
+    /// How connections are cleaned up when returned to the pool.
+    ///
+    /// Default: `ResetOnReturn::Fast`
+    pub fn with_reset_on_return(mut self, reset_on_return: ResetOnReturn) -> Self {
+        self.reset_on_return = reset_on_return;
+        self
+    }
+
+    /// Close connections older than `max_lifetime`, e.g. to pick up DNS or credential changes.
+    ///
+    /// Default: `None`
+    pub fn with_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// Close connections that stayed idle in the pool longer than `idle_timeout`.
+    ///
+    /// Default: `None`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// How often the reaper runs.
+    ///
+    /// Default: `30s`
+    pub fn with_health_check_interval(mut self, interval: Duration) -> Self {
+        self.health_check_interval = interval;
+        self
+    }
+
+    /// The number of idle connections the reaper keeps open, capped by the idle capacity.
+    ///
+    /// Default: `0`
+    pub fn with_min_idle(mut self, min_idle: usize) -> Self {
+        self.min_idle = min_idle;
+        self
+    }
+
+    /// Shorten `max_lifetime` and `idle_timeout` by a random fraction up to `jitter` per connection,
+    /// so that connections opened together are not all closed together. Clamped to `0.0..=1.0`.
+    ///
+    /// Default: `0.1`
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn reset_on_return(&self) -> ResetOnReturn {
+        self.reset_on_return
+    }
+
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        self.max_lifetime
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    pub fn health_check_interval(&self) -> Duration {
+        self.health_check_interval
+    }
+
+    pub fn min_idle(&self) -> usize {
+        self.min_idle
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    /// `limit` shortened by a random fraction up to `jitter`
+    pub(crate) fn jittered(&self, limit: Duration) -> Duration {
+        // RandomState is seeded randomly per instance
+        let random = RandomState::new().hash_one(0_u8) as f64 / u64::MAX as f64;
+        limit.mul_f64(1.0 - self.jitter * random)
+    }
+}
+
+/// Returns true if the server understands `COM_RESET_CONNECTION`
+pub(crate) fn supports_reset_connection(server_version: &[u8], mariadb: bool) -> bool {
+    let text = String::from_utf8_lossy(server_version);
+    // MariaDB before 11 may prefix its version with a fake "5.5.5-" for old clients
+    let mut parts = text
+        .strip_prefix("5.5.5-")
+        .unwrap_or(&text)
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let version = (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+    );
+    if mariadb {
+        version >= (10, 2, 4)
+    } else {
+        version >= (5, 7, 3)
+    }
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use crate::pool_config::{PoolConfig, ResetOnReturn, supports_reset_connection};
+use crate::test_macros::{check, check_eq};
+
+#[test]
+fn defaults() -> crate::error::Result<()> {
+    let config = PoolConfig::default();
+    check!(config.max_lifetime().is_none());
+    check!(config.idle_timeout().is_none());
+    check_eq!(config.health_check_interval(), Duration::from_secs(30));
+    check_eq!(config.min_idle(), 0);
+    check_eq!(config.jitter(), 0.1);
+    Ok(())
+}
+
+#[test]
+fn jitter_shortens_limits() -> crate::error::Result<()> {
+    let limit = Duration::from_secs(100);
+    let config = PoolConfig::default().with_jitter(0.5);
+    for _ in 0..100 {
+        let jittered = config.jittered(limit);
+        check!(jittered <= limit);
+        check!(jittered >= Duration::from_secs(50));
+    }
+    check_eq!(
+        PoolConfig::default().with_jitter(0.0).jittered(limit),
+        limit
+    );
+    check_eq!(PoolConfig::default().with_jitter(7.0).jitter(), 1.0);
+    Ok(())
+}
+
+#[test]
+fn reset_on_return() -> crate::error::Result<()> {
+    check_eq!(PoolConfig::default().reset_on_return(), ResetOnReturn::Fast);
+    let config = PoolConfig::default().with_reset_on_return(ResetOnReturn::Full);
+    check_eq!(config.reset_on_return(), ResetOnReturn::Full);
+    Ok(())
+}
+
+#[test]
+fn detects_reset_connection_support() -> crate::error::Result<()> {
+    check!(supports_reset_connection(b"8.0.36", false));
+    check!(supports_reset_connection(b"5.7.3-log", false));
+    check!(!supports_reset_connection(b"5.7.2", false));
+    check!(!supports_reset_connection(b"5.6.51-log", false));
+    check!(supports_reset_connection(b"11.4.8-MariaDB", true));
+    check!(supports_reset_connection(b"5.5.5-10.2.4-MariaDB", true));
+    check!(!supports_reset_connection(b"5.5.5-10.1.48-MariaDB", true));
+    check!(!supports_reset_connection(b"garbage", false));
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Configuration for adaptive idle pool sizing.
+///
+/// The pool records how long each `get()` waited and how busy the pool was.
+/// Every `window` acquisitions the observed wait percentile is compared against `target_wait`:
+/// - above `target_wait * (1 + hysteresis)`: the idle capacity grows by `step`
+/// - below `target_wait * (1 - hysteresis)` and utilization below `low_utilization`: it shrinks by `step`
+/// - otherwise it is left unchanged
+///
+/// The idle capacity always stays within `min_idle..=max_idle`.
+#[derive(Debug, Clone)]
+pub struct AdaptivePoolSizing {
+    /// Lower bound of the idle capacity.
+    ///
+    /// Default: `1`
+    pub min_idle: usize,
+
+    /// Upper bound of the idle capacity.
+    ///
+    /// Default: `100`
+    pub max_idle: usize,
+
+    /// The acquire wait time the controller tries to stay under.
+    ///
+    /// Default: `5ms`
+    pub target_wait: Duration,
+
+    /// The wait-time percentile compared against `target_wait`, in `0.0..=1.0`.
+    ///
+    /// Default: `0.95`
+    pub percentile: f64,
+
+    /// Number of acquisitions per evaluation.
+    ///
+    /// Default: `64`
+    pub window: usize,
+
+    /// Relative dead band around `target_wait` in which the size is kept.
+    ///
+    /// Default: `0.2`
+    pub hysteresis: f64,
+
+    /// Shrinking is only allowed when the peak utilization (in use / (in use + idle)) of the window is below this.
+    ///
+    /// Default: `0.5`
+    pub low_utilization: f64,
+
+    /// Number of connections added or removed per adjustment.
+    ///
+    /// Default: `1`
+    pub step: usize,
+}
+
+impl Default for AdaptivePoolSizing {
+    fn default() -> Self {
+        Self {
+            min_idle: 1,
+            max_idle: 100,
+            target_wait: Duration::from_millis(5),
+            percentile: 0.95,
+            window: 64,
+            hysteresis: 0.2,
+            low_utilization: 0.5,
+            step: 1,
+        }
+    }
+}
+
+struct Window {
+    waits: Vec<Duration>,
+    peak_utilization: f64,
+}
+
+/// Tracks acquire statistics and computes the idle capacity of a pool.
+pub(crate) struct PoolSizer {
+    config: AdaptivePoolSizing,
+    target: AtomicUsize,
+    in_use: AtomicUsize,
+    window: Mutex<Window>,
+}
+
+impl PoolSizer {
+    pub fn new(config: AdaptivePoolSizing) -> Self {
+        let max_idle = config.max_idle.max(config.min_idle);
+        let config = AdaptivePoolSizing { max_idle, ..config };
+        Self {
+            target: AtomicUsize::new(config.min_idle),
+            in_use: AtomicUsize::new(0),
+            window: Mutex::new(Window {
+                waits: Vec::with_capacity(config.window),
+                peak_utilization: 0.0,
+            }),
+            config,
+        }
+    }
+
+    /// The current number of idle connections the pool should keep.
+    pub fn target(&self) -> usize {
+        self.target.load(Ordering::Relaxed)
+    }
+
+    /// The maximum idle capacity the pool can ever need.
+    pub fn max_idle(&self) -> usize {
+        self.config.max_idle
+    }
+
+    /// Record a successful acquisition that waited `wait` while `idle` connections remained idle.
+    pub fn record_acquire(&self, wait: Duration, idle: usize) {
+        let in_use = self.in_use.fetch_add(1, Ordering::Relaxed) + 1;
+        let utilization = in_use as f64 / (in_use + idle) as f64;
+
+        let Ok(mut window) = self.window.lock() else {
+            return;
+        };
+        window.waits.push(wait);
+        if utilization > window.peak_utilization {
+            window.peak_utilization = utilization;
+        }
+        if window.waits.len() < self.config.window.max(1) {
+            return;
+        }
+
+        let observed = percentile(&mut window.waits, self.config.percentile);
+        let peak_utilization = window.peak_utilization;
+        window.waits.clear();
+        window.peak_utilization = 0.0;
+        drop(window);
+
+        self.adjust(observed, peak_utilization);
+    }
+
+    /// Record that a connection acquired from the pool was released.
+    pub fn record_release(&self) {
+        let _ = self
+            .in_use
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    fn adjust(&self, observed: Duration, peak_utilization: f64) {
+        let target_wait = self.config.target_wait.as_secs_f64();
+        let observed = observed.as_secs_f64();
+        let current = self.target();
+
+        let next = if observed > target_wait * (1.0 + self.config.hysteresis) {
+            current.saturating_add(self.config.step)
+        } else if observed < target_wait * (1.0 - self.config.hysteresis)
+            && peak_utilization < self.config.low_utilization
+        {
+            current.saturating_sub(self.config.step)
+        } else {
+            current
+        };
+        let next = next.clamp(self.config.min_idle, self.config.max_idle);
+
+        if next != current {
+            tracing::debug!(from = current, to = next, "adaptive pool resize");
+            self.target.store(next, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Returns the `p`-th percentile (nearest-rank) of `samples`. Reorders `samples`.
+fn percentile(samples: &mut [Duration], p: f64) -> Duration {
+    if samples.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((samples.len() as f64) * p.clamp(0.0, 1.0)).ceil() as usize;
+    let index = rank.saturating_sub(1).min(samples.len() - 1);
+    let (_, value, _) = samples.select_nth_unstable(index);
+    *value
+}
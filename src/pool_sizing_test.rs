@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use crate::pool_sizing::{AdaptivePoolSizing, PoolSizer};
+use crate::test_macros::check_eq;
+
+fn config() -> AdaptivePoolSizing {
+    AdaptivePoolSizing {
+        min_idle: 2,
+        max_idle: 4,
+        target_wait: Duration::from_millis(10),
+        window: 4,
+        ..Default::default()
+    }
+}
+
+fn run_window(sizer: &PoolSizer, wait: Duration, idle: usize) {
+    for _ in 0..4 {
+        sizer.record_acquire(wait, idle);
+        sizer.record_release();
+    }
+}
+
+#[test]
+fn starts_at_min_idle() -> crate::error::Result<()> {
+    let sizer = PoolSizer::new(config());
+    check_eq!(sizer.target(), 2);
+    Ok(())
+}
+
+#[test]
+fn grows_on_slow_acquire_up_to_max() -> crate::error::Result<()> {
+    let sizer = PoolSizer::new(config());
+    run_window(&sizer, Duration::from_millis(50), 0);
+    check_eq!(sizer.target(), 3);
+    run_window(&sizer, Duration::from_millis(50), 0);
+    run_window(&sizer, Duration::from_millis(50), 0);
+    check_eq!(sizer.target(), 4);
+    Ok(())
+}
+
+#[test]
+fn holds_within_hysteresis_band() -> crate::error::Result<()> {
+    let sizer = PoolSizer::new(config());
+    run_window(&sizer, Duration::from_millis(50), 0);
+    run_window(&sizer, Duration::from_millis(10), 0);
+    check_eq!(sizer.target(), 3);
+    Ok(())
+}
+
+#[test]
+fn shrinks_only_when_underutilized() -> crate::error::Result<()> {
+    let sizer = PoolSizer::new(config());
+    run_window(&sizer, Duration::from_millis(50), 0);
+    run_window(&sizer, Duration::from_millis(50), 0);
+    check_eq!(sizer.target(), 4);
+
+    // fast but fully utilized: keep
+    run_window(&sizer, Duration::ZERO, 0);
+    check_eq!(sizer.target(), 4);
+
+    // fast and mostly idle: shrink down to min
+    run_window(&sizer, Duration::ZERO, 10);
+    run_window(&sizer, Duration::ZERO, 10);
+    run_window(&sizer, Duration::ZERO, 10);
+    check_eq!(sizer.target(), 2);
+    Ok(())
+}
+
+#[test]
+fn waits_for_full_window() -> crate::error::Result<()> {
+    let sizer = PoolSizer::new(config());
+    for _ in 0..3 {
+        sizer.record_acquire(Duration::from_secs(1), 0);
+    }
+    check_eq!(sizer.target(), 2);
+    Ok(())
+}
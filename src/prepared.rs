@@ -1,8 +1,18 @@
+use std::fmt;
+
+use crate::classify::StatementClass;
 use crate::protocol::command::{ColumnDefinition, ColumnDefinitions};
+use crate::schema_drift::ColumnSummary;
 
 pub struct PreparedStatement {
     id: u32,
     column_definitions: Option<ColumnDefinitions>,
+    sql: Option<Box<str>>,
+    class: StatementClass,
+    max_rows: Option<u64>,
+    /// The columns of the previous execution, tracked if `Opts::schema_drift` is set
+    columns_seen: Option<Vec<ColumnSummary>>,
+    emulated: bool,
 }
 
 impl PreparedStatement {
@@ -10,8 +20,28 @@ impl PreparedStatement {
         PreparedStatement {
             id,
             column_definitions: None,
+            sql: None,
+            class: StatementClass::Unknown,
+            max_rows: None,
+            columns_seen: None,
+            emulated: false,
         }
     }
+
+    /// A statement executed by client-side parameter substitution.
+    /// See [`crate::emulate`].
+    pub fn emulated(sql: &str) -> Self {
+        let mut stmt = Self::new(0);
+        stmt.set_sql(sql);
+        stmt.emulated = true;
+        stmt
+    }
+
+    /// The statement was not prepared on the server. See [`crate::emulate`].
+    pub fn is_emulated(&self) -> bool {
+        self.emulated
+    }
+
     pub fn id(&self) -> u32 {
         self.id
     }
@@ -23,4 +53,91 @@ impl PreparedStatement {
     pub fn set_column_definitions(&mut self, column_definitions: ColumnDefinitions) {
         self.column_definitions = Some(column_definitions);
     }
+
+    /// The number of columns in the result set, or 0 if the statement returns no rows.
+    pub fn num_columns(&self) -> usize {
+        self.column_definitions().map_or(0, <[_]>::len)
+    }
+
+    /// The SQL text this statement was prepared from.
+    ///
+    /// Only available if `Opts::retain_statement_sql` was set when the statement was prepared,
+    /// or if the statement is emulated.
+    pub fn sql(&self) -> Option<&str> {
+        self.sql.as_deref()
+    }
+
+    pub fn set_sql(&mut self, sql: &str) {
+        self.sql = Some(sql.into());
+    }
+
+    /// Heap bytes held by the SQL text and the column metadata
+    pub(crate) fn heap_size(&self) -> usize {
+        self.sql.as_ref().map_or(0, |sql| sql.len())
+            + self
+                .column_definitions
+                .as_ref()
+                .map_or(0, ColumnDefinitions::heap_size)
+    }
+
+    /// Only classified if `Opts::audit` was set when the statement was prepared.
+    pub(crate) fn class(&self) -> StatementClass {
+        self.class
+    }
+
+    pub(crate) fn set_class(&mut self, class: StatementClass) {
+        self.class = class;
+    }
+
+    /// An upper bound on the rows of one execution, from a `LIMIT` with number literals.
+    /// See [`max_rows`](crate::classify::max_rows).
+    pub fn max_rows(&self) -> Option<u64> {
+        self.max_rows
+    }
+
+    pub(crate) fn set_max_rows(&mut self, max_rows: Option<u64>) {
+        self.max_rows = max_rows;
+    }
+
+    /// Remember the columns of this execution, returning the previous execution's columns if they differ.
+    pub(crate) fn track_columns(&mut self) -> Option<Vec<ColumnSummary>> {
+        let cols = self.column_definitions().unwrap_or_default();
+        if let Some(seen) = &self.columns_seen
+            && seen.len() == cols.len()
+            && seen.iter().zip(cols).all(|(seen, col)| seen.matches(col))
+        {
+            return None;
+        }
+        let current = cols
+            .iter()
+            .map(ColumnSummary::new)
+            .collect::<Option<Vec<_>>>()?;
+        self.columns_seen.replace(current)
+    }
+
+    /// The columns remembered by `track_columns()`
+    pub(crate) fn columns_seen(&self) -> &[ColumnSummary] {
+        self.columns_seen.as_deref().unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for PreparedStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreparedStatement")
+            .field("id", &self.id)
+            .field("num_columns", &self.num_columns())
+            .field("sql", &self.sql())
+            .finish()
+    }
+}
+
+/// `stmt#3 (2 columns)` or `stmt#3 (2 columns): SELECT a, b FROM t WHERE id = ?`
+impl fmt::Display for PreparedStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stmt#{} ({} columns)", self.id, self.num_columns())?;
+        if let Some(sql) = self.sql() {
+            write!(f, ": {sql}")?;
+        }
+        Ok(())
+    }
 }
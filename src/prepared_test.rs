@@ -0,0 +1,25 @@
+use crate::PreparedStatement;
+use crate::test_macros::check_eq;
+
+#[test]
+fn display_without_sql() -> crate::error::Result<()> {
+    let stmt = PreparedStatement::new(3);
+    check_eq!(stmt.to_string(), "stmt#3 (0 columns)");
+    check_eq!(
+        format!("{stmt:?}"),
+        "PreparedStatement { id: 3, num_columns: 0, sql: None }"
+    );
+    Ok(())
+}
+
+#[test]
+fn display_with_sql() -> crate::error::Result<()> {
+    let mut stmt = PreparedStatement::new(7);
+    stmt.set_sql("DELETE FROM t WHERE id = ?");
+    check_eq!(stmt.sql(), Some("DELETE FROM t WHERE id = ?"));
+    check_eq!(
+        stmt.to_string(),
+        "stmt#7 (0 columns): DELETE FROM t WHERE id = ?"
+    );
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Query attributes (`CLIENT_QUERY_ATTRIBUTES`, MySQL 8.0.23+).
+//!
+//! Named values sent along with `COM_QUERY` and `COM_STMT_EXECUTE`,
+//! readable on the server with `mysql_query_attribute_string(name)`.
+//! Once the capability is negotiated, every `COM_QUERY` and `COM_STMT_EXECUTE`
+//! carries a parameter count and parameter names, even without attributes.
+
+use crate::error::{Error, Result};
+use crate::protocol::primitive::*;
+use crate::protocol::r#trait::param::TypedParam;
+
+/// No query attributes
+pub const NO_ATTRIBUTES: &[(&str, &str)] = &[];
+
+pub(crate) fn unsupported() -> Error {
+    Error::Unsupported(
+        "query attributes require MySQL 8.0.23 or later (CLIENT_QUERY_ATTRIBUTES)".to_string(),
+    )
+}
+
+/// Set the NULL bits of `attributes` in the bitmap at `bitmap`,
+/// where the attributes follow `offset` other parameters.
+pub(crate) fn set_null_bits<A: TypedParam>(
+    bitmap: &mut [u8],
+    offset: usize,
+    attributes: &[(&str, A)],
+) {
+    for (i, (_, value)) in attributes.iter().enumerate() {
+        if value.is_null() {
+            let bit = offset + i;
+            if let Some(byte) = bitmap.get_mut(bit / 8) {
+                *byte |= 1 << (bit % 8);
+            }
+        }
+    }
+}
+
+/// Write `[type: 2][name: lenenc]` of each attribute
+pub(crate) fn write_types<A: TypedParam>(out: &mut Vec<u8>, attributes: &[(&str, A)]) {
+    for (name, _) in attributes {
+        A::encode_type(out);
+        write_string_lenenc(out, name);
+    }
+}
+
+/// Write the values of the attributes that are not NULL
+pub(crate) fn write_values<A: TypedParam>(
+    out: &mut Vec<u8>,
+    attributes: &[(&str, A)],
+) -> Result<()> {
+    for (_, value) in attributes {
+        if !value.is_null() {
+            value.encode_value(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Insert an empty name after each 2-byte type in `out[types_start..]`
+pub(crate) fn name_types(out: &mut Vec<u8>, types_start: usize) {
+    let num_types = (out.len() - types_start) / 2;
+    out.resize(types_start + 3 * num_types, 0);
+    for i in (0..num_types).rev() {
+        let src = types_start + 2 * i;
+        let dst = types_start + 3 * i;
+        out.copy_within(src..src + 2, dst);
+        out[dst + 2] = 0;
+    }
+}
@@ -0,0 +1,89 @@
+use crate::constant::ColumnType;
+use crate::protocol::command::attributes::NO_ATTRIBUTES;
+use crate::protocol::command::prepared::{
+    write_execute, write_execute_as_attributes, write_execute_with_attributes,
+};
+use crate::protocol::command::query::{write_query, write_query_with_attributes};
+use crate::test_macros::check_eq;
+
+const VAR_STRING: u8 = ColumnType::MYSQL_TYPE_VAR_STRING as u8;
+const LONG: u8 = ColumnType::MYSQL_TYPE_LONG as u8;
+
+#[test]
+fn query_without_attributes() -> crate::error::Result<()> {
+    let mut out = Vec::new();
+    write_query_with_attributes(&mut out, "SELECT 1", NO_ATTRIBUTES)?;
+    // [0x03][parameter_count: 0][parameter_set_count: 1][sql]
+    check_eq!(out, b"\x03\x00\x01SELECT 1");
+    Ok(())
+}
+
+#[test]
+fn query_with_attributes() -> crate::error::Result<()> {
+    let mut out = Vec::new();
+    write_query_with_attributes(&mut out, "SELECT 1", &[("a", Some("x")), ("bc", None)])?;
+    let mut expected = vec![0x03, 2, 1];
+    // NULL bitmap: the second attribute is NULL
+    expected.push(0b10);
+    expected.push(0x01);
+    expected.extend_from_slice(&[VAR_STRING, 0x00, 1, b'a']);
+    expected.extend_from_slice(&[VAR_STRING, 0x00, 2, b'b', b'c']);
+    expected.extend_from_slice(&[1, b'x']);
+    expected.extend_from_slice(b"SELECT 1");
+    check_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn execute_with_params_and_attributes() -> crate::error::Result<()> {
+    let mut out = Vec::new();
+    write_execute_with_attributes(&mut out, 7, (5_i32, None::<i32>), &[("t", "x")])?;
+    let mut expected = vec![0x17, 7, 0, 0, 0, 0x08, 1, 0, 0, 0];
+    // parameter count, including attributes
+    expected.push(3);
+    // NULL bitmap: the second parameter is NULL
+    expected.push(0b010);
+    expected.push(0x01);
+    // parameters have empty names
+    expected.extend_from_slice(&[LONG, 0x00, 0, LONG, 0x00, 0]);
+    expected.extend_from_slice(&[VAR_STRING, 0x00, 1, b't']);
+    expected.extend_from_slice(&[5, 0, 0, 0]);
+    expected.extend_from_slice(&[1, b'x']);
+    check_eq!(out, expected);
+    Ok(())
+}
+
+#[test]
+fn execute_as_attributes_matches_direct_encoding() -> crate::error::Result<()> {
+    let mut direct = Vec::new();
+    write_execute_with_attributes(&mut direct, 3, (1_i32, "abc", None::<i32>), NO_ATTRIBUTES)?;
+
+    let mut payload = Vec::new();
+    write_execute(&mut payload, 3, (1_i32, "abc", None::<i32>))?;
+    let mut converted = Vec::new();
+    write_execute_as_attributes(&mut converted, &payload, 3);
+    check_eq!(converted, direct);
+    Ok(())
+}
+
+#[test]
+fn execute_as_attributes_without_params() -> crate::error::Result<()> {
+    let mut direct = Vec::new();
+    write_execute_with_attributes(&mut direct, 3, (), NO_ATTRIBUTES)?;
+
+    let mut payload = Vec::new();
+    write_execute(&mut payload, 3, ())?;
+    let mut converted = Vec::new();
+    write_execute_as_attributes(&mut converted, &payload, 0);
+    check_eq!(converted, direct);
+    check_eq!(converted.len(), 11);
+    Ok(())
+}
+
+#[test]
+fn query_without_capability_is_plain() -> crate::error::Result<()> {
+    let mut out = Vec::new();
+    write_query(&mut out, "SELECT 1");
+    check_eq!(out, b"\x03SELECT 1");
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use crate::constant::CommandByte;
+use crate::protocol::primitive::*;
+
+/// `flags` bit of COM_BINLOG_DUMP: reply with EOF at the end of the binlog instead of waiting for events
+pub const BINLOG_DUMP_NON_BLOCK: u16 = 0x01;
+
+/// `flags` bit of COM_BINLOG_DUMP_GTID: the GTID set is sent
+pub const BINLOG_THROUGH_GTID: u16 = 0x04;
+
+/// Write COM_REGISTER_SLAVE command
+///
+/// `hostname`, `user` and `password` are shown in `SHOW REPLICAS` and truncated to 255 bytes.
+pub fn write_register_replica(
+    out: &mut Vec<u8>,
+    server_id: u32,
+    hostname: &str,
+    user: &str,
+    password: &str,
+    port: u16,
+) {
+    write_int_1(out, CommandByte::RegisterSlave as u8);
+    write_int_4(out, server_id);
+    for field in [hostname, user, password] {
+        let bytes = &field.as_bytes()[..field.len().min(255)];
+        write_int_1(out, bytes.len() as u8);
+        out.extend_from_slice(bytes);
+    }
+    write_int_2(out, port);
+    // replication rank, ignored
+    write_int_4(out, 0);
+    // source id, filled in by the server
+    write_int_4(out, 0);
+}
+
+/// Write COM_BINLOG_DUMP command
+///
+/// Positions beyond 4 GiB require [`write_binlog_dump_gtid`].
+pub fn write_binlog_dump(out: &mut Vec<u8>, server_id: u32, flags: u16, file: &str, position: u32) {
+    write_int_1(out, CommandByte::BinlogDump as u8);
+    write_int_4(out, position);
+    write_int_2(out, flags);
+    write_int_4(out, server_id);
+    out.extend_from_slice(file.as_bytes());
+}
+
+/// Write COM_BINLOG_DUMP_GTID command
+///
+/// `gtid_set` is the encoded GTID set, sent if `flags` contains [`BINLOG_THROUGH_GTID`].
+pub fn write_binlog_dump_gtid(
+    out: &mut Vec<u8>,
+    server_id: u32,
+    flags: u16,
+    file: &str,
+    position: u64,
+    gtid_set: &[u8],
+) {
+    write_int_1(out, CommandByte::BinlogDumpGtid as u8);
+    write_int_2(out, flags);
+    write_int_4(out, server_id);
+    write_int_4(out, file.len() as u32);
+    out.extend_from_slice(file.as_bytes());
+    write_int_8(out, position);
+    if flags & BINLOG_THROUGH_GTID != 0 {
+        write_int_4(out, gtid_set.len() as u32);
+        out.extend_from_slice(gtid_set);
+    }
+}
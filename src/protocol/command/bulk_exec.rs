@@ -3,6 +3,7 @@ use crate::buffer::BufferSet;
 use crate::constant::CommandByte;
 use crate::error::{Error, Result, eyre};
 use crate::protocol::command::ColumnDefinitions;
+use crate::protocol::command::check_columns_eof;
 use crate::protocol::command::prepared::read_binary_row;
 use crate::protocol::primitive::*;
 use crate::protocol::response::{ErrPayloadBytes, OkPayloadBytes};
@@ -20,6 +21,11 @@ bitflags::bitflags! {
 pub trait BulkParamsSet {
     fn encode_types(&self, out: &mut Vec<u8>);
     fn encode_rows(self, out: &mut Vec<u8>) -> Result<()>;
+
+    /// Number of bytes written by `encode_types` and `encode_rows`, or a lower bound
+    fn encoded_len(&self) -> usize {
+        0
+    }
 }
 
 impl<P: TypedParams> BulkParamsSet for &[P] {
@@ -33,6 +39,15 @@ impl<P: TypedParams> BulkParamsSet for &[P] {
         }
         Ok(())
     }
+
+    fn encoded_len(&self) -> usize {
+        // [indicator: 1 per param][values] per row
+        let rows: usize = self
+            .iter()
+            .map(|params| params.len() + params.encoded_len())
+            .sum();
+        self.first().map_or(0, |params| 2 * params.len()) + rows
+    }
 }
 
 pub fn write_bulk_execute<P: BulkParamsSet>(
@@ -41,6 +56,8 @@ pub fn write_bulk_execute<P: BulkParamsSet>(
     params: P,
     flags: BulkFlags,
 ) -> Result<()> {
+    // [command: 1][statement id: 4][flags: 2]
+    out.reserve(7 + params.encoded_len());
     write_int_1(out, CommandByte::StmtBulkExecute as u8);
     write_int_4(out, statement_id);
     write_int_2(out, flags.bits());
@@ -103,6 +120,7 @@ enum BulkExecState {
     Start,
     ReadingFirstPacket,
     ReadingColumns { num_columns: usize },
+    ReadingColumnsEof { num_columns: usize },
     ReadingRows { num_columns: usize },
     Finished,
 }
@@ -162,6 +180,7 @@ impl<'h, 'stmt, H: BinaryResultSetHandler> BulkExec<'h, 'stmt, H> {
                         } else {
                             // No metadata from server, use cached definitions
                             if let Some(cache) = self.stmt.column_definitions() {
+                                buffer_set.reset_arena();
                                 self.handler.resultset_start(cache)?;
                                 self.state = BulkExecState::ReadingRows { num_columns };
                                 Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
@@ -179,16 +198,30 @@ impl<'h, 'stmt, H: BinaryResultSetHandler> BulkExec<'h, 'stmt, H> {
             BulkExecState::ReadingColumns { num_columns } => {
                 // Parse all column definitions from the buffer
                 // The buffer contains [len(u32)][payload][len(u32)][payload]...
-                let column_defs = ColumnDefinitions::new(
+                let mut column_defs = ColumnDefinitions::new(
                     *num_columns,
                     std::mem::take(&mut buffer_set.column_definition_buffer),
                 )?;
+                buffer_set.intern_columns(&mut column_defs);
+
+                buffer_set.reset_arena();
 
                 // Cache the column definitions in the prepared statement
                 self.handler.resultset_start(column_defs.definitions())?;
                 self.stmt.set_column_definitions(column_defs);
 
                 // Move to reading rows
+                let count = *num_columns;
+                self.state = if buffer_set.legacy_eof {
+                    BulkExecState::ReadingColumnsEof { num_columns: count }
+                } else {
+                    BulkExecState::ReadingRows { num_columns: count }
+                };
+                Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
+            }
+
+            BulkExecState::ReadingColumnsEof { num_columns } => {
+                check_columns_eof(&buffer_set.read_buffer)?;
                 self.state = BulkExecState::ReadingRows {
                     num_columns: *num_columns,
                 };
@@ -203,7 +236,11 @@ impl<'h, 'stmt, H: BinaryResultSetHandler> BulkExec<'h, 'stmt, H> {
                         let cols = self.stmt.column_definitions().ok_or_else(|| {
                             Error::LibraryBug(eyre!("no column definitions while reading rows"))
                         })?;
-                        self.handler.row(cols, row)?;
+                        crate::protocol::validate::check_binary_row(cols, payload)?;
+                        match &buffer_set.arena {
+                            Some(arena) => self.handler.row_with_arena(cols, row, arena)?,
+                            None => self.handler.row(cols, row)?,
+                        }
                         Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
                     }
                     0xFE => {
@@ -1,8 +1,9 @@
+use crate::column_names::{ColumnNameId, ColumnNames};
 use crate::constant::{ColumnFlags, ColumnType};
 use crate::error::{Error, Result, eyre};
 use crate::protocol::primitive::*;
 use zerocopy::byteorder::little_endian::{U16 as U16LE, U32 as U32LE};
-use zerocopy::{FromBytes, Immutable, KnownLayout};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
 
 /// Represents a payload part of a column definition packet
 #[derive(Debug, Clone, Copy)]
@@ -33,6 +34,8 @@ pub struct ColumnDefinition<'a> {
     pub name_alias: &'a [u8],
     pub name_original: &'a [u8],
     pub tail: &'a ColumnDefinitionTail,
+    /// The interned `name_alias`, set if `Opts::intern_column_names` is enabled.
+    pub name_id: Option<ColumnNameId>,
 }
 
 impl<'a> TryFrom<ColumnDefinitionBytes<'a>> for ColumnDefinition<'a> {
@@ -61,13 +64,14 @@ impl<'a> TryFrom<ColumnDefinitionBytes<'a>> for ColumnDefinition<'a> {
             name_alias,
             name_original,
             tail,
+            name_id: None,
         })
     }
 }
 
 /// Fixed-size tail of Column Definition packet (12 bytes)
 #[repr(C, packed)]
-#[derive(Debug, Clone, Copy, FromBytes, KnownLayout, Immutable)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable)]
 pub struct ColumnDefinitionTail {
     charset: U16LE,
     column_length: U32LE,
@@ -99,6 +103,7 @@ impl ColumnDefinitionTail {
     }
 }
 
+#[derive(Debug)]
 pub struct ColumnDefinitions {
     _packets: Vec<u8>, // concatenation of packets (length(usize, native endian) + payload)
     definitions: Vec<ColumnDefinition<'static>>,
@@ -135,6 +140,41 @@ impl ColumnDefinitions {
         })
     }
 
+    /// Copy borrowed column definitions into an owned `ColumnDefinitions`.
+    pub fn from_definitions(cols: &[ColumnDefinition<'_>]) -> Result<Self> {
+        let mut packets = Vec::new();
+        let mut payload = Vec::new();
+        for col in cols {
+            payload.clear();
+            write_bytes_lenenc(&mut payload, b"def");
+            write_bytes_lenenc(&mut payload, col.schema);
+            write_bytes_lenenc(&mut payload, col.table_alias);
+            write_bytes_lenenc(&mut payload, col.table_original);
+            write_bytes_lenenc(&mut payload, col.name_alias);
+            write_bytes_lenenc(&mut payload, col.name_original);
+            write_int_lenenc(&mut payload, 0x0c);
+            payload.extend_from_slice(col.tail.as_bytes());
+            packets.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+            packets.extend_from_slice(&payload);
+        }
+        let mut owned = Self::new(cols.len(), packets)?;
+        for (owned_col, col) in owned.definitions.iter_mut().zip(cols) {
+            owned_col.name_id = col.name_id;
+        }
+        Ok(owned)
+    }
+
+    /// Set [`ColumnDefinition::name_id`] of every column.
+    pub fn intern_names(&mut self, names: &mut ColumnNames) {
+        names.intern_columns(&mut self.definitions);
+    }
+
+    /// Heap bytes held: the packets and the parsed definitions
+    pub(crate) fn heap_size(&self) -> usize {
+        self._packets.capacity()
+            + self.definitions.capacity() * std::mem::size_of::<ColumnDefinition<'static>>()
+    }
+
     pub fn definitions<'a>(&'a self) -> &'a [ColumnDefinition<'a>] {
         self.definitions.as_slice()
     }
@@ -3,7 +3,9 @@ use std::mem::{align_of, size_of};
 use zerocopy::FromBytes;
 
 use crate::constant::{ColumnFlags, ColumnType};
-use crate::protocol::command::{ColumnDefinition, ColumnDefinitionBytes, ColumnDefinitionTail};
+use crate::protocol::command::{
+    ColumnDefinition, ColumnDefinitionBytes, ColumnDefinitionTail, ColumnDefinitions,
+};
 use crate::test_macros::{check, check_eq, check_err};
 
 #[test]
@@ -218,3 +220,30 @@ fn column_definition_try_from() -> crate::error::Result<()> {
     check_eq!(col_type, ColumnType::MYSQL_TYPE_LONG);
     Ok(())
 }
+
+#[test]
+fn column_definitions_from_definitions() -> crate::error::Result<()> {
+    let tail_bytes: [u8; 12] = [
+        0x3F, 0x00, 0x14, 0x00, 0x00, 0x00, 0x08, 0x01, 0x00, 0x00, 0, 0,
+    ];
+    let tail = ColumnDefinitionTail::ref_from_bytes(&tail_bytes)?;
+    let col = ColumnDefinition {
+        schema: b"test",
+        table_alias: b"u",
+        table_original: b"users",
+        name_alias: b"n",
+        name_original: b"count",
+        tail,
+        name_id: None,
+    };
+
+    let owned = ColumnDefinitions::from_definitions(&[col.clone(), col])?;
+    let defs = owned.definitions();
+    check_eq!(defs.len(), 2);
+    check_eq!(defs[1].table_original, b"users");
+    check_eq!(defs[1].name_alias, b"n");
+    check_eq!(defs[1].tail.column_length(), 20);
+    check_eq!(defs[1].tail.column_type()?, ColumnType::MYSQL_TYPE_LONGLONG);
+    check!(defs[1].tail.flags()?.contains(ColumnFlags::NOT_NULL_FLAG));
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use crate::PreparedStatement;
+use crate::buffer::BufferSet;
+use crate::error::{Error, Result};
+use crate::protocol::command::prepared::Exec;
+use crate::protocol::command::query::Query;
+use crate::protocol::command::{Action, ColumnDefinition};
+use crate::protocol::response::{OkPayload, OkPayloadBytes};
+use crate::protocol::r#trait::{BinaryResultSetHandler, TextResultSetHandler};
+use crate::protocol::{BinaryRowPayload, TextRowPayload};
+use crate::test_macros::{check, check_eq};
+
+/// EOF packet without `CLIENT_DEPRECATE_EOF`: [0xFE][warnings][status]
+const EOF: [u8; 5] = [0xFE, 0x00, 0x00, 0x02, 0x00];
+
+#[derive(Default)]
+struct Counter {
+    rows: usize,
+    ends: usize,
+}
+
+impl TextResultSetHandler for Counter {
+    fn no_result_set(&mut self, _ok: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_start(&mut self, _cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    fn row(&mut self, _cols: &[ColumnDefinition<'_>], _row: TextRowPayload<'_>) -> Result<()> {
+        self.rows += 1;
+        Ok(())
+    }
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        let _ = OkPayload::try_from(eof)?;
+        self.ends += 1;
+        Ok(())
+    }
+}
+
+impl BinaryResultSetHandler for Counter {
+    fn no_result_set(&mut self, _ok: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_start(&mut self, _cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    fn row(&mut self, _cols: &[ColumnDefinition<'_>], _row: BinaryRowPayload<'_>) -> Result<()> {
+        self.rows += 1;
+        Ok(())
+    }
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()> {
+        let _ = OkPayload::try_from(eof)?;
+        self.ends += 1;
+        Ok(())
+    }
+}
+
+/// A buffer set holding the definition of one column of `column_type`
+fn buffer_set(column_type: u8) -> BufferSet {
+    let mut packet = b"\x03def\x04test\x01t\x01t\x01c\x01c\x0c".to_vec();
+    packet.extend_from_slice(&[
+        0x21,
+        0x00,
+        0x0B,
+        0x00,
+        0x00,
+        0x00,
+        column_type,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ]);
+
+    let mut buffer_set = BufferSet::new();
+    buffer_set.legacy_eof = true;
+    buffer_set
+        .column_definition_buffer
+        .extend((packet.len() as u32).to_ne_bytes());
+    buffer_set.column_definition_buffer.extend(packet);
+    buffer_set
+}
+
+/// Feed `packet` and step once
+fn feed<'buf>(
+    step: impl FnOnce(&'buf mut BufferSet) -> Result<Action<'buf>>,
+    buffer_set: &'buf mut BufferSet,
+    packet: &[u8],
+) -> Result<Action<'buf>> {
+    buffer_set.read_buffer = packet.to_vec();
+    step(buffer_set)
+}
+
+#[test]
+fn query_skips_eof_after_columns() -> Result<()> {
+    let mut counter = Counter::default();
+    let mut query = Query::new(&mut counter);
+    let mut buffer_set = buffer_set(0xFD);
+
+    check!(matches!(
+        query.step(&mut buffer_set)?,
+        Action::NeedPacket(_)
+    ));
+    check!(matches!(
+        feed(|b| query.step(b), &mut buffer_set, &[0x01])?,
+        Action::ReadColumnMetadata { num_columns: 1 }
+    ));
+    check!(matches!(
+        query.step(&mut buffer_set)?,
+        Action::NeedPacket(_)
+    ));
+    // The EOF after the columns is not the end of the result set
+    check!(matches!(
+        feed(|b| query.step(b), &mut buffer_set, &EOF)?,
+        Action::NeedPacket(_)
+    ));
+    check!(matches!(
+        feed(|b| query.step(b), &mut buffer_set, b"\x01a")?,
+        Action::NeedPacket(_)
+    ));
+    check!(matches!(
+        feed(|b| query.step(b), &mut buffer_set, &EOF)?,
+        Action::Finished
+    ));
+    check_eq!((counter.rows, counter.ends), (1, 1));
+    Ok(())
+}
+
+#[test]
+fn exec_skips_eof_after_columns() -> Result<()> {
+    let mut counter = Counter::default();
+    let mut stmt = PreparedStatement::new(1);
+    let mut exec = Exec::new(&mut counter, &mut stmt, false);
+    let mut buffer_set = buffer_set(0x03);
+
+    check!(matches!(exec.step(&mut buffer_set)?, Action::NeedPacket(_)));
+    check!(matches!(
+        feed(|b| exec.step(b), &mut buffer_set, &[0x01])?,
+        Action::ReadColumnMetadata { num_columns: 1 }
+    ));
+    check!(matches!(exec.step(&mut buffer_set)?, Action::NeedPacket(_)));
+    check!(matches!(
+        feed(|b| exec.step(b), &mut buffer_set, &EOF)?,
+        Action::NeedPacket(_)
+    ));
+    check!(matches!(
+        feed(|b| exec.step(b), &mut buffer_set, &[0x00, 0x00, 7, 0, 0, 0])?,
+        Action::NeedPacket(_)
+    ));
+    check!(matches!(
+        feed(|b| exec.step(b), &mut buffer_set, &EOF)?,
+        Action::Finished
+    ));
+    check_eq!((counter.rows, counter.ends), (1, 1));
+    Ok(())
+}
+
+#[test]
+fn error_instead_of_columns_eof() -> Result<()> {
+    let mut counter = Counter::default();
+    let mut query = Query::new(&mut counter);
+    let mut buffer_set = buffer_set(0xFD);
+
+    let _ = query.step(&mut buffer_set)?;
+    let _ = feed(|b| query.step(b), &mut buffer_set, &[0x01])?;
+    let _ = query.step(&mut buffer_set)?;
+    let result = feed(
+        |b| query.step(b),
+        &mut buffer_set,
+        b"\xFF\x15\x04#28000denied",
+    );
+    check!(matches!(result, Err(Error::ServerError(_))));
+    Ok(())
+}
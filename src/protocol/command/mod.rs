@@ -1,3 +1,5 @@
+pub mod attributes;
+pub mod binlog;
 pub mod bulk_exec;
 mod column_definition;
 pub mod prepared;
@@ -11,15 +13,33 @@ pub use column_definition::ColumnDefinitionBytes;
 pub use column_definition::ColumnDefinitionTail;
 pub use column_definition::ColumnDefinitions;
 
+use crate::error::Result;
+use crate::protocol::response::{ErrPayloadBytes, read_eof_packet};
+
 /// Action returned by state machines indicating what I/O operation is needed next
 pub enum Action<'buf> {
     /// State machine needs more data - provides mutable reference to buffer to fill
     NeedPacket(&'buf mut Vec<u8>),
     /// State machine needs to read N column definition packets
     ReadColumnMetadata { num_columns: usize },
+    /// The server requested a file with `LOAD DATA LOCAL INFILE`.
+    /// The file contents must be sent before the next `step`.
+    LocalInfile { filename: &'buf [u8] },
     /// State machine has finished processing
     Finished,
 }
 
+/// Check the EOF packet that follows column definitions without `CLIENT_DEPRECATE_EOF`
+pub(crate) fn check_columns_eof(payload: &[u8]) -> Result<()> {
+    if payload.first() == Some(&0xFF) {
+        return Err(ErrPayloadBytes(payload).into());
+    }
+    read_eof_packet(payload).map(|_| ())
+}
+
+#[cfg(test)]
+mod attributes_test;
 #[cfg(test)]
 mod column_definition_test;
+#[cfg(test)]
+mod legacy_eof_test;
@@ -2,9 +2,11 @@ use crate::buffer::BufferSet;
 use crate::constant::CommandByte;
 use crate::error::{Error, Result, eyre};
 use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::attributes;
+use crate::protocol::command::check_columns_eof;
 use crate::protocol::primitive::*;
 use crate::protocol::response::{ErrPayloadBytes, OkPayloadBytes};
-use crate::protocol::r#trait::param::Params;
+use crate::protocol::r#trait::param::{Params, TypedParam};
 use zerocopy::byteorder::little_endian::{U16 as U16LE, U32 as U32LE};
 use zerocopy::{FromBytes, Immutable, KnownLayout};
 
@@ -55,18 +57,22 @@ pub fn read_prepare_ok(payload: &[u8]) -> Result<&PrepareOk> {
 }
 
 /// Write COM_STMT_EXECUTE command
+///
+/// The packet size is computed with [`Params::encoded_len`] and reserved once,
+/// so the write buffer does not grow while the parameters are encoded.
 pub fn write_execute<P: Params>(out: &mut Vec<u8>, statement_id: u32, params: P) -> Result<()> {
+    let num_params = params.len();
+    out.reserve(execute_len(num_params, params.encoded_len()));
+
     write_int_1(out, CommandByte::StmtExecute as u8);
     write_int_4(out, statement_id);
 
     // flags (1 byte) - CURSOR_TYPE_NO_CURSOR
     write_int_1(out, 0x00);
 
-    // iteration count (4 bytes) - always 1
+    // iteration count (4 bytes) - always 1; MySQL executes once for any count
     write_int_4(out, 1);
 
-    let num_params = params.len();
-
     if num_params > 0 {
         // NULL bitmap: (num_params + 7) / 8 bytes
         params.encode_null_bitmap(out);
@@ -85,6 +91,83 @@ pub fn write_execute<P: Params>(out: &mut Vec<u8>, statement_id: u32, params: P)
     Ok(())
 }
 
+/// `flags` bit of COM_STMT_EXECUTE: the parameter count is sent even without parameters
+const PARAMETER_COUNT_AVAILABLE: u8 = 0x08;
+
+/// Write COM_STMT_EXECUTE command with `CLIENT_QUERY_ATTRIBUTES`
+///
+/// The statement parameters are sent unnamed, followed by the named `attributes`.
+pub fn write_execute_with_attributes<P: Params, A: TypedParam>(
+    out: &mut Vec<u8>,
+    statement_id: u32,
+    params: P,
+    attributes: &[(&str, A)],
+) -> Result<()> {
+    let num_params = params.len();
+    let count = num_params + attributes.len();
+    // [parameter count: lenenc][a name per parameter], names of attributes are not counted
+    out.reserve(execute_len(num_params, params.encoded_len()) + 9 + num_params);
+
+    write_int_1(out, CommandByte::StmtExecute as u8);
+    write_int_4(out, statement_id);
+    write_int_1(out, PARAMETER_COUNT_AVAILABLE);
+    write_int_4(out, 1);
+    write_int_lenenc(out, count as u64);
+
+    if count > 0 {
+        let bitmap_start = out.len();
+        params.encode_null_bitmap(out);
+        out.resize(bitmap_start + count.div_ceil(8), 0);
+        attributes::set_null_bits(&mut out[bitmap_start..], num_params, attributes);
+
+        // new-params-bound-flag (1 byte)
+        write_int_1(out, 0x01);
+        let types_start = out.len();
+        params.encode_types(out);
+        attributes::name_types(out, types_start);
+        attributes::write_types(out, attributes);
+
+        params.encode_values(out)?;
+        attributes::write_values(out, attributes)?;
+    }
+    Ok(())
+}
+
+/// Convert a COM_STMT_EXECUTE payload of [`write_execute`] with `num_params` parameters
+/// to the `CLIENT_QUERY_ATTRIBUTES` format of [`write_execute_with_attributes`], appending it to `out`.
+pub fn write_execute_as_attributes(out: &mut Vec<u8>, payload: &[u8], num_params: usize) {
+    // [command: 1][statement id: 4][flags: 1][iteration count: 4]
+    let (header, rest) = payload.split_at(10.min(payload.len()));
+    let flags_pos = out.len() + 5;
+    out.extend_from_slice(header);
+    if let Some(flags) = out.get_mut(flags_pos) {
+        *flags |= PARAMETER_COUNT_AVAILABLE;
+    }
+    write_int_lenenc(out, num_params as u64);
+    if num_params == 0 {
+        return;
+    }
+    // [null bitmap][new-params-bound-flag: 1][types: 2 per param][values]
+    let (bitmap, rest) = rest.split_at((num_params.div_ceil(8) + 1).min(rest.len()));
+    let (types, values) = rest.split_at((2 * num_params).min(rest.len()));
+    out.extend_from_slice(bitmap);
+    let types_start = out.len();
+    out.extend_from_slice(types);
+    attributes::name_types(out, types_start);
+    out.extend_from_slice(values);
+}
+
+/// Size of a COM_STMT_EXECUTE payload with `num_params` parameters whose values take `values_len` bytes
+fn execute_len(num_params: usize, values_len: usize) -> usize {
+    // [command: 1][statement id: 4][flags: 1][iteration count: 4]
+    let header = 10;
+    if num_params == 0 {
+        return header;
+    }
+    // [null bitmap][new-params-bound-flag: 1][types: 2 per param][values]
+    header + num_params.div_ceil(8) + 1 + 2 * num_params + values_len
+}
+
 /// Read COM_STMT_EXECUTE response
 /// This can be either an OK packet or a result set
 pub fn read_execute_response(payload: &[u8], cache_metadata: bool) -> Result<ExecuteResponse<'_>> {
@@ -142,6 +225,20 @@ pub fn write_close_statement(out: &mut Vec<u8>, statement_id: u32) {
     write_int_4(out, statement_id);
 }
 
+/// Largest chunk of parameter data sent in a single COM_STMT_SEND_LONG_DATA packet
+pub const LONG_DATA_CHUNK_SIZE: usize = 1 << 20;
+
+/// Write COM_STMT_SEND_LONG_DATA command
+///
+/// The server does not respond. It appends `data` to the parameter `param_id`
+/// and uses it for the next COM_STMT_EXECUTE.
+pub fn write_send_long_data(out: &mut Vec<u8>, statement_id: u32, param_id: u16, data: &[u8]) {
+    write_int_1(out, CommandByte::StmtSendLongData as u8);
+    write_int_4(out, statement_id);
+    write_int_2(out, param_id);
+    out.extend_from_slice(data);
+}
+
 /// Write COM_STMT_RESET command
 pub fn write_reset_statement(out: &mut Vec<u8>, statement_id: u32) {
     write_int_1(out, CommandByte::StmtReset as u8);
@@ -164,12 +261,42 @@ enum ExecState {
     ReadingFirstPacket,
     /// Reading column definitions (processing the buffer after reading all packets)
     ReadingColumns { num_columns: usize },
+    /// Reading the EOF packet after column definitions (no `CLIENT_DEPRECATE_EOF`)
+    ReadingColumnsEof { num_columns: usize },
     /// Reading rows
     ReadingRows { num_columns: usize },
     /// Finished
     Finished,
 }
 
+/// A paused [`Exec`], for pull-based consumers that cannot keep the handler borrowed
+/// between rows.
+pub struct ExecCursor {
+    state: ExecState,
+    cache_metadata: bool,
+}
+
+impl ExecCursor {
+    /// Continue where [`Exec::pause`] stopped, with the same statement and a handler.
+    pub fn resume<'h, 'stmt, H>(
+        self,
+        handler: &'h mut H,
+        stmt: &'stmt mut PreparedStatement,
+    ) -> Exec<'h, 'stmt, H> {
+        Exec {
+            state: self.state,
+            handler,
+            stmt,
+            cache_metadata: self.cache_metadata,
+        }
+    }
+
+    /// Returns true if the whole response was read.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, ExecState::Finished)
+    }
+}
+
 /// State machine for executing prepared statements (binary protocol) with integrated handler
 ///
 /// The handler is provided at construction and called directly by the state machine.
@@ -196,6 +323,22 @@ impl<'h, 'stmt, H: BinaryResultSetHandler> Exec<'h, 'stmt, H> {
         }
     }
 
+    /// Pause the state machine, releasing the handler and the statement.
+    pub fn pause(self) -> ExecCursor {
+        ExecCursor {
+            state: self.state,
+            cache_metadata: self.cache_metadata,
+        }
+    }
+
+    /// The handler and the column definitions of the current result set
+    pub fn handler_and_columns(
+        &mut self,
+    ) -> (&mut H, &[crate::protocol::command::ColumnDefinition<'_>]) {
+        let cols = self.stmt.column_definitions().unwrap_or_default();
+        (self.handler, cols)
+    }
+
     /// Drive the state machine forward
     ///
     /// # Arguments
@@ -256,6 +399,7 @@ impl<'h, 'stmt, H: BinaryResultSetHandler> Exec<'h, 'stmt, H> {
                         } else {
                             // No metadata from server, use cached definitions
                             if let Some(cols) = self.stmt.column_definitions() {
+                                buffer_set.reset_arena();
                                 self.handler.resultset_start(cols)?;
                                 self.state = ExecState::ReadingRows { num_columns };
                                 Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
@@ -273,16 +417,30 @@ impl<'h, 'stmt, H: BinaryResultSetHandler> Exec<'h, 'stmt, H> {
             ExecState::ReadingColumns { num_columns } => {
                 // Parse all column definitions from the buffer
                 // The buffer contains [len(u32)][payload][len(u32)][payload]...
-                let column_defs = ColumnDefinitions::new(
+                let mut column_defs = ColumnDefinitions::new(
                     *num_columns,
                     std::mem::take(&mut buffer_set.column_definition_buffer),
                 )?;
+                buffer_set.intern_columns(&mut column_defs);
+
+                buffer_set.reset_arena();
 
                 // Cache the column definitions in the prepared statement
                 self.handler.resultset_start(column_defs.definitions())?;
                 self.stmt.set_column_definitions(column_defs);
 
                 // Move to reading rows
+                let count = *num_columns;
+                self.state = if buffer_set.legacy_eof {
+                    ExecState::ReadingColumnsEof { num_columns: count }
+                } else {
+                    ExecState::ReadingRows { num_columns: count }
+                };
+                Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
+            }
+
+            ExecState::ReadingColumnsEof { num_columns } => {
+                check_columns_eof(&buffer_set.read_buffer)?;
                 self.state = ExecState::ReadingRows {
                     num_columns: *num_columns,
                 };
@@ -297,7 +455,11 @@ impl<'h, 'stmt, H: BinaryResultSetHandler> Exec<'h, 'stmt, H> {
                         let cols = self.stmt.column_definitions().ok_or_else(|| {
                             Error::LibraryBug(eyre!("no column definitions while reading rows"))
                         })?;
-                        self.handler.row(cols, row)?;
+                        crate::protocol::validate::check_binary_row(cols, payload)?;
+                        match &buffer_set.arena {
+                            Some(arena) => self.handler.row_with_arena(cols, row, arena)?,
+                            None => self.handler.row(cols, row)?,
+                        }
                         Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
                     }
                     0xFE => {
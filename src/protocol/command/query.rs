@@ -3,8 +3,11 @@ use crate::constant::CommandByte;
 use crate::error::{Error, Result, eyre};
 use crate::protocol::TextRowPayload;
 use crate::protocol::command::ColumnDefinitions;
+use crate::protocol::command::attributes;
+use crate::protocol::command::check_columns_eof;
 use crate::protocol::primitive::*;
 use crate::protocol::response::{ErrPayloadBytes, OkPayloadBytes};
+use crate::protocol::r#trait::param::TypedParam;
 
 const MAX_PAYLOAD_LENGTH: usize = (1 << 24) - 4;
 
@@ -14,11 +17,34 @@ pub fn write_query(out: &mut Vec<u8>, sql: &str) {
     out.extend_from_slice(sql.as_bytes());
 }
 
+/// Write COM_QUERY command with `CLIENT_QUERY_ATTRIBUTES`
+pub fn write_query_with_attributes<A: TypedParam>(
+    out: &mut Vec<u8>,
+    sql: &str,
+    attributes: &[(&str, A)],
+) -> Result<()> {
+    write_int_1(out, CommandByte::Query as u8);
+    write_int_lenenc(out, attributes.len() as u64);
+    // parameter_set_count is always 1
+    write_int_lenenc(out, 1);
+    if !attributes.is_empty() {
+        let bitmap_start = out.len();
+        out.resize(bitmap_start + attributes.len().div_ceil(8), 0);
+        attributes::set_null_bits(&mut out[bitmap_start..], 0, attributes);
+        // new_params_bind_flag
+        write_int_1(out, 0x01);
+        attributes::write_types(out, attributes);
+        attributes::write_values(out, attributes)?;
+    }
+    out.extend_from_slice(sql.as_bytes());
+    Ok(())
+}
+
 /// Read COM_QUERY response
 /// This can be:
 /// - 0xFF: ERR packet (error occurred)
 /// - 0x00: OK packet (query succeeded without result set)
-/// - 0xFB: LOCAL INFILE packet (not yet supported)
+/// - 0xFB: LOCAL INFILE request (followed by the file name)
 /// - Otherwise: Result set (first byte is column count as length-encoded integer)
 pub fn read_query_response(payload: &[u8]) -> Result<QueryResponse<'_>> {
     if payload.is_empty() {
@@ -30,9 +56,9 @@ pub fn read_query_response(payload: &[u8]) -> Result<QueryResponse<'_>> {
     match payload[0] {
         0xFF => Err(ErrPayloadBytes(payload).into()),
         0x00 => Ok(QueryResponse::Ok(OkPayloadBytes(payload))),
-        0xFB => Err(Error::BadUsageError(
-            "LOCAL INFILE queries are not yet supported".to_string(),
-        )),
+        0xFB => Ok(QueryResponse::LocalInfile {
+            filename: &payload[1..],
+        }),
         _ => {
             let (column_count, _rest) = read_int_lenenc(payload)?;
             Ok(QueryResponse::ResultSet { column_count })
@@ -45,6 +71,7 @@ pub fn read_query_response(payload: &[u8]) -> Result<QueryResponse<'_>> {
 pub enum QueryResponse<'a> {
     Ok(OkPayloadBytes<'a>),
     ResultSet { column_count: u64 },
+    LocalInfile { filename: &'a [u8] },
 }
 
 // ============================================================================
@@ -59,8 +86,12 @@ enum QueryState {
     Start,
     /// Reading the first response packet
     ReadingFirstPacket,
+    /// Sending the file requested by LOCAL INFILE
+    SendingLocalInfile,
     /// Reading column definitions
     ReadingColumns { num_columns: usize },
+    /// Reading the EOF packet after column definitions (no `CLIENT_DEPRECATE_EOF`)
+    ReadingColumnsEof,
     /// Reading rows
     ReadingRows,
     /// Finished
@@ -87,6 +118,17 @@ impl<'h, H: TextResultSetHandler> Query<'h, H> {
         }
     }
 
+    /// The handler and the column definitions of the current result set
+    pub fn handler_and_columns(
+        &mut self,
+    ) -> (&mut H, &[crate::protocol::command::ColumnDefinition<'_>]) {
+        let cols = self
+            .column_defs
+            .as_ref()
+            .map_or(&[][..], |defs| defs.definitions());
+        (self.handler, cols)
+    }
+
     /// Drive the state machine forward
     ///
     /// # Arguments
@@ -139,19 +181,43 @@ impl<'h, H: TextResultSetHandler> Query<'h, H> {
                         self.state = QueryState::ReadingColumns { num_columns };
                         Ok(Action::ReadColumnMetadata { num_columns })
                     }
+                    QueryResponse::LocalInfile { .. } => {
+                        self.state = QueryState::SendingLocalInfile;
+                        // Borrow again for 'buf: [0xFB][filename]
+                        Ok(Action::LocalInfile {
+                            filename: &buffer_set.read_buffer[1..],
+                        })
+                    }
                 }
             }
 
+            QueryState::SendingLocalInfile => {
+                // The server responds with OK or ERR after the file
+                self.state = QueryState::ReadingFirstPacket;
+                Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
+            }
+
             QueryState::ReadingColumns { num_columns } => {
                 // Parse all column definitions from the buffer
                 // The buffer contains [len(u32)][payload][len(u32)][payload]...
-                let column_defs = ColumnDefinitions::new(
+                let mut column_defs = ColumnDefinitions::new(
                     *num_columns,
                     std::mem::take(&mut buffer_set.column_definition_buffer),
                 )?;
+                buffer_set.intern_columns(&mut column_defs);
 
                 self.handler.resultset_start(column_defs.definitions())?;
                 self.column_defs = Some(column_defs);
+                self.state = if buffer_set.legacy_eof {
+                    QueryState::ReadingColumnsEof
+                } else {
+                    QueryState::ReadingRows
+                };
+                Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
+            }
+
+            QueryState::ReadingColumnsEof => {
+                check_columns_eof(&buffer_set.read_buffer)?;
                 self.state = QueryState::ReadingRows;
                 Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
             }
@@ -193,6 +259,7 @@ impl<'h, H: TextResultSetHandler> Query<'h, H> {
                         let cols = self.column_defs.as_ref().ok_or_else(|| {
                             Error::LibraryBug(eyre!("no column definitions while reading rows"))
                         })?;
+                        crate::protocol::validate::check_text_row(cols.definitions(), payload)?;
                         let row = TextRowPayload(payload);
                         self.handler.row(cols.definitions(), row)?;
                         Ok(Action::NeedPacket(&mut buffer_set.read_buffer))
@@ -0,0 +1,330 @@
+//! Compressed protocol (`CLIENT_COMPRESS` or `CLIENT_ZSTD_COMPRESSION_ALGORITHM`).
+//!
+//! After the handshake, every packet stream is wrapped in compressed packets:
+//!
+//! ```text
+//! [compressed length: 3][compressed sequence id: 1][uncompressed length: 3][zlib or zstd data]
+//! ```
+//!
+//! The compressed sequence id is independent of the packet sequence id and restarts with every command.
+//! Payloads shorter than [`MIN_COMPRESS_LENGTH`] are sent as-is with an uncompressed length of 0.
+//!
+//! [`PacketCompression`] holds the framing state; each backend's `Stream` performs the I/O.
+//! It also keeps [`CompressionStats`], and with [`Opts::compression_auto_disable`] stops compressing
+//! outgoing payloads that do not shrink. The framing stays in place since it cannot be renegotiated,
+//! and the server keeps compressing its own packets.
+
+use std::io::{Read, Write};
+use std::mem::MaybeUninit;
+use std::time::{Duration, Instant};
+
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+use crate::constant::CapabilityFlags;
+use crate::error::{Error, Result};
+use crate::opts::Opts;
+
+/// Payloads shorter than this are not compressed.
+pub const MIN_COMPRESS_LENGTH: usize = 50;
+
+const MAX_PAYLOAD_LENGTH: usize = 0xFFFFFF;
+
+/// Bytes compressed before [`Opts::compression_auto_disable`] decides whether to keep compressing
+pub const AUTO_DISABLE_SAMPLE_BYTES: u64 = 1024 * 1024;
+
+/// Compression is turned off if the sampled payloads shrink by less than this factor
+pub const AUTO_DISABLE_MIN_RATIO: f64 = 1.1;
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, FromBytes, KnownLayout, Immutable, IntoBytes)]
+pub struct CompressedPacketHeader {
+    pub compressed_length: [u8; 3],
+    pub sequence_id: u8,
+    pub uncompressed_length: [u8; 3],
+}
+
+impl CompressedPacketHeader {
+    pub fn encode(compressed_length: usize, sequence_id: u8, uncompressed_length: usize) -> Self {
+        let compressed = u32::to_le_bytes(compressed_length as u32);
+        let uncompressed = u32::to_le_bytes(uncompressed_length as u32);
+        Self {
+            compressed_length: [compressed[0], compressed[1], compressed[2]],
+            sequence_id,
+            uncompressed_length: [uncompressed[0], uncompressed[1], uncompressed[2]],
+        }
+    }
+
+    pub fn compressed_length(&self) -> usize {
+        let [a, b, c] = self.compressed_length;
+        u32::from_le_bytes([a, b, c, 0]) as usize
+    }
+
+    /// 0 if the payload is not compressed
+    pub fn uncompressed_length(&self) -> usize {
+        let [a, b, c] = self.uncompressed_length;
+        u32::from_le_bytes([a, b, c, 0]) as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum Codec {
+    #[default]
+    Zlib,
+    #[cfg(feature = "zstd-compression")]
+    Zstd { level: i32 },
+}
+
+/// Compression counters of one direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionDirectionStats {
+    /// Payload bytes before compression
+    pub uncompressed_bytes: u64,
+    /// Bytes of compressed packet bodies, excluding the 7-byte headers
+    pub wire_bytes: u64,
+    /// Time spent in zlib or zstd
+    pub codec_time: Duration,
+}
+
+impl CompressionDirectionStats {
+    /// `uncompressed_bytes / wire_bytes`, or 1.0 if nothing was transferred.
+    /// Close to 1.0 means the payloads do not compress.
+    pub fn ratio(&self) -> f64 {
+        if self.wire_bytes == 0 {
+            1.0
+        } else {
+            self.uncompressed_bytes as f64 / self.wire_bytes as f64
+        }
+    }
+
+    fn record(&mut self, uncompressed_bytes: usize, wire_bytes: usize) {
+        self.uncompressed_bytes += uncompressed_bytes as u64;
+        self.wire_bytes += wire_bytes as u64;
+    }
+}
+
+/// Compression statistics of a connection, from `Conn::compression_stats()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// Packets sent to the server
+    pub sent: CompressionDirectionStats,
+    /// Packets received from the server
+    pub received: CompressionDirectionStats,
+    /// Outgoing payloads are sent uncompressed because they did not compress.
+    /// Set by [`Opts::compression_auto_disable`].
+    pub send_compression_disabled: bool,
+}
+
+/// Framing state of a compressed connection.
+#[derive(Debug, Default)]
+pub struct PacketCompression {
+    codec: Codec,
+    stats: CompressionStats,
+    auto_disable: bool,
+    /// Bytes given to the encoder and bytes it produced, until the auto-disable decision
+    sample_in: u64,
+    sample_out: u64,
+    sequence_id: u8,
+    /// Decompressed bytes; `inbox[inbox_pos..]` is not read yet
+    inbox: Vec<u8>,
+    inbox_pos: usize,
+    /// Body of the compressed packet being read
+    body: Vec<u8>,
+    /// Packets written since the last [`PacketCompression::encode`]
+    outbox: Vec<u8>,
+    /// Compressed packets to send
+    wire: Vec<u8>,
+}
+
+impl PacketCompression {
+    pub fn zlib() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "zstd-compression")]
+    pub fn zstd(level: u8) -> Self {
+        Self {
+            codec: Codec::Zstd {
+                level: i32::from(level),
+            },
+            ..Self::default()
+        }
+    }
+
+    /// Stop compressing outgoing payloads if the first [`AUTO_DISABLE_SAMPLE_BYTES`]
+    /// shrink by less than [`AUTO_DISABLE_MIN_RATIO`].
+    pub fn with_auto_disable(mut self, auto_disable: bool) -> Self {
+        self.auto_disable = auto_disable;
+        self
+    }
+
+    /// The compression negotiated in the handshake, if any.
+    pub fn negotiated(capability_flags: CapabilityFlags, opts: &Opts) -> Option<Self> {
+        #[cfg(feature = "zstd-compression")]
+        if capability_flags.contains(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM) {
+            return Some(
+                Self::zstd(opts.zstd_compression_level)
+                    .with_auto_disable(opts.compression_auto_disable),
+            );
+        }
+        capability_flags
+            .contains(CapabilityFlags::CLIENT_COMPRESS)
+            .then(|| Self::zlib().with_auto_disable(opts.compression_auto_disable))
+    }
+
+    pub fn stats(&self) -> CompressionStats {
+        self.stats
+    }
+
+    /// Returns true if all decompressed bytes were read and another compressed packet is needed.
+    pub fn is_empty(&self) -> bool {
+        self.inbox_pos == self.inbox.len()
+    }
+
+    /// Copy decompressed bytes into `buf`, returning the number of bytes copied.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let pending = &self.inbox[self.inbox_pos..];
+        let len = pending.len().min(buf.len());
+        buf[..len].copy_from_slice(&pending[..len]);
+        self.inbox_pos += len;
+        len
+    }
+
+    /// Copy decompressed bytes into `buf`, returning the number of bytes copied.
+    pub fn read_uninit(&mut self, buf: &mut [MaybeUninit<u8>]) -> usize {
+        let pending = &self.inbox[self.inbox_pos..];
+        let len = pending.len().min(buf.len());
+        buf[..len].write_copy_of_slice(&pending[..len]);
+        self.inbox_pos += len;
+        len
+    }
+
+    /// A buffer for the body of the compressed packet with `header`.
+    /// Fill it, then call [`PacketCompression::decode`].
+    pub fn body_mut(&mut self, header: &CompressedPacketHeader) -> &mut [u8] {
+        self.body.resize(header.compressed_length(), 0);
+        &mut self.body
+    }
+
+    /// Decompress the packet read into [`PacketCompression::body_mut`].
+    pub fn decode(&mut self, header: &CompressedPacketHeader) -> Result<()> {
+        self.sequence_id = header.sequence_id.wrapping_add(1);
+        if self.is_empty() {
+            self.inbox.clear();
+            self.inbox_pos = 0;
+        }
+
+        let uncompressed_length = header.uncompressed_length();
+        if uncompressed_length == 0 {
+            self.inbox.extend_from_slice(&self.body);
+            self.stats.received.record(self.body.len(), self.body.len());
+            return Ok(());
+        }
+        let start = self.inbox.len();
+        self.inbox.reserve(uncompressed_length);
+        let started = Instant::now();
+        // One byte more than the header declares is enough to detect a longer payload
+        let limit = uncompressed_length as u64 + 1;
+        match self.codec {
+            Codec::Zlib => {
+                flate2::read::ZlibDecoder::new(self.body.as_slice())
+                    .take(limit)
+                    .read_to_end(&mut self.inbox)?;
+            }
+            #[cfg(feature = "zstd-compression")]
+            Codec::Zstd { .. } => {
+                zstd::stream::read::Decoder::new(self.body.as_slice())?
+                    .take(limit)
+                    .read_to_end(&mut self.inbox)?;
+            }
+        }
+        let decompressed = self.inbox.len() - start;
+        if decompressed != uncompressed_length {
+            self.inbox.truncate(start);
+            return Err(Error::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "malformed compressed packet: header declares {} bytes, payload has {}{}",
+                    uncompressed_length,
+                    decompressed,
+                    if decompressed > uncompressed_length {
+                        " or more"
+                    } else {
+                        ""
+                    }
+                ),
+            )));
+        }
+        self.stats.received.codec_time += started.elapsed();
+        self.stats
+            .received
+            .record(uncompressed_length, self.body.len());
+        Ok(())
+    }
+
+    /// Buffer packets until [`PacketCompression::encode`].
+    pub fn write(&mut self, buf: &[u8]) {
+        self.outbox.extend_from_slice(buf);
+    }
+
+    /// Wrap the buffered packets in compressed packets and return the bytes to send.
+    pub fn encode(&mut self) -> Result<&[u8]> {
+        self.wire.clear();
+        // A packet with sequence id 0 starts a new command
+        if self.outbox.get(3) == Some(&0) {
+            self.sequence_id = 0;
+        }
+
+        for chunk in self.outbox.chunks(MAX_PAYLOAD_LENGTH) {
+            let header_pos = self.wire.len();
+            self.wire.extend_from_slice(&[0; 7]);
+
+            let mut uncompressed_length = 0;
+            if chunk.len() >= MIN_COMPRESS_LENGTH && !self.stats.send_compression_disabled {
+                let started = Instant::now();
+                match self.codec {
+                    Codec::Zlib => {
+                        let mut encoder = flate2::write::ZlibEncoder::new(
+                            &mut self.wire,
+                            flate2::Compression::default(),
+                        );
+                        encoder.write_all(chunk)?;
+                        encoder.finish()?;
+                    }
+                    #[cfg(feature = "zstd-compression")]
+                    Codec::Zstd { level } => {
+                        zstd::stream::copy_encode(chunk, &mut self.wire, level)?
+                    }
+                }
+                self.stats.sent.codec_time += started.elapsed();
+                let compressed_length = self.wire.len() - header_pos - 7;
+                if self.auto_disable {
+                    self.sample_in += chunk.len() as u64;
+                    self.sample_out += compressed_length as u64;
+                    if self.sample_in >= AUTO_DISABLE_SAMPLE_BYTES {
+                        self.auto_disable = false;
+                        self.stats.send_compression_disabled = (self.sample_in as f64)
+                            < self.sample_out as f64 * AUTO_DISABLE_MIN_RATIO;
+                    }
+                }
+                if compressed_length < chunk.len() {
+                    uncompressed_length = chunk.len();
+                } else {
+                    // Incompressible
+                    self.wire.truncate(header_pos + 7);
+                }
+            }
+            if uncompressed_length == 0 {
+                self.wire.extend_from_slice(chunk);
+            }
+
+            let wire_length = self.wire.len() - header_pos - 7;
+            self.stats.sent.record(chunk.len(), wire_length);
+            let header =
+                CompressedPacketHeader::encode(wire_length, self.sequence_id, uncompressed_length);
+            self.wire[header_pos..header_pos + 7].copy_from_slice(header.as_bytes());
+            self.sequence_id = self.sequence_id.wrapping_add(1);
+        }
+        self.outbox.clear();
+        Ok(&self.wire)
+    }
+}
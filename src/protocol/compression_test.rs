@@ -0,0 +1,275 @@
+use zerocopy::FromBytes;
+
+use crate::protocol::compression::{
+    AUTO_DISABLE_SAMPLE_BYTES, CompressedPacketHeader, MIN_COMPRESS_LENGTH, PacketCompression,
+};
+use crate::test_macros::{check, check_eq};
+
+/// Split `wire` into compressed packets
+fn packets(mut wire: &[u8]) -> crate::error::Result<Vec<(CompressedPacketHeader, Vec<u8>)>> {
+    let mut packets = Vec::new();
+    while !wire.is_empty() {
+        let (header, rest) = CompressedPacketHeader::read_from_prefix(wire)
+            .map_err(|e| crate::error::Error::LibraryBug(crate::error::eyre!("{e:?}")))?;
+        let (body, rest) = rest.split_at(header.compressed_length());
+        packets.push((header, body.to_vec()));
+        wire = rest;
+    }
+    Ok(packets)
+}
+
+/// Decode all compressed packets in `wire` and return the uncompressed bytes
+fn round_trip(wire: &[u8]) -> crate::error::Result<Vec<u8>> {
+    let mut reader = PacketCompression::default();
+    let mut out = Vec::new();
+    for (header, body) in packets(wire)? {
+        reader.body_mut(&header).copy_from_slice(&body);
+        reader.decode(&header)?;
+        let mut buf = vec![0; header.uncompressed_length().max(body.len())];
+        let len = reader.read(&mut buf);
+        out.extend_from_slice(&buf[..len]);
+        check!(reader.is_empty());
+    }
+    Ok(out)
+}
+
+/// A packet with `payload` and the given sequence id
+fn packet(sequence_id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = (payload.len() as u32).to_le_bytes();
+    packet[3] = sequence_id;
+    [&packet[..], payload].concat()
+}
+
+#[test]
+fn small_payload_is_not_compressed() -> crate::error::Result<()> {
+    let ping = packet(0, &[0x0e]);
+    check!(ping.len() < MIN_COMPRESS_LENGTH);
+
+    let mut compression = PacketCompression::default();
+    compression.write(&ping);
+    let wire = compression.encode()?.to_vec();
+
+    let packets = packets(&wire)?;
+    check_eq!(packets.len(), 1);
+    check_eq!(packets[0].0.uncompressed_length(), 0);
+    check_eq!(packets[0].1, ping);
+    check_eq!(round_trip(&wire)?, ping);
+    Ok(())
+}
+
+#[test]
+fn large_payload_is_compressed() -> crate::error::Result<()> {
+    let query = packet(
+        0,
+        &[&[0x03][..], &b"SELECT 1 UNION ALL ".repeat(100)].concat(),
+    );
+
+    let mut compression = PacketCompression::default();
+    compression.write(&query);
+    let wire = compression.encode()?.to_vec();
+
+    let packets = packets(&wire)?;
+    check_eq!(packets.len(), 1);
+    check_eq!(packets[0].0.uncompressed_length(), query.len());
+    check!(packets[0].1.len() < query.len());
+    check_eq!(round_trip(&wire)?, query);
+    Ok(())
+}
+
+#[test]
+fn incompressible_payload_is_sent_as_is() -> crate::error::Result<()> {
+    // A byte sequence without repetitions
+    let payload: Vec<u8> = (0..=255_u8).map(|b| b.wrapping_mul(167)).collect();
+    let data = packet(1, &payload);
+
+    let mut compression = PacketCompression::default();
+    compression.write(&data);
+    let wire = compression.encode()?.to_vec();
+
+    let packets = packets(&wire)?;
+    check_eq!(packets[0].0.uncompressed_length(), 0);
+    check_eq!(round_trip(&wire)?, data);
+    Ok(())
+}
+
+#[test]
+fn sequence_id_restarts_with_each_command() -> crate::error::Result<()> {
+    let mut compression = PacketCompression::default();
+    let mut sequence_ids = Vec::new();
+    // A command, a continuation packet of the same command, then a new command
+    for data in [packet(0, b"a"), packet(1, b"b"), packet(0, b"c")] {
+        compression.write(&data);
+        let wire = compression.encode()?.to_vec();
+        sequence_ids.extend(packets(&wire)?.iter().map(|(header, _)| header.sequence_id));
+    }
+    check_eq!(sequence_ids, [0, 1, 0]);
+
+    // The response continues the sequence of the request
+    let mut conversation = PacketCompression::default();
+    conversation.write(&packet(0, b"d"));
+    conversation.encode()?;
+    let header = CompressedPacketHeader::encode(1, 1, 0);
+    conversation.body_mut(&header).copy_from_slice(b"e");
+    conversation.decode(&header)?;
+    conversation.write(&packet(1, b"f"));
+    let wire = conversation.encode()?.to_vec();
+    check_eq!(packets(&wire)?[0].0.sequence_id, 2);
+    Ok(())
+}
+
+#[test]
+fn reads_span_compressed_packets() -> crate::error::Result<()> {
+    let mut compression = PacketCompression::default();
+    for (sequence_id, body) in [(0, &b"abc"[..]), (1, &b"def"[..])] {
+        let header = CompressedPacketHeader::encode(body.len(), sequence_id, 0);
+        compression.body_mut(&header).copy_from_slice(body);
+        compression.decode(&header)?;
+    }
+    let mut buf = [0; 4];
+    check_eq!(compression.read(&mut buf), 4);
+    check_eq!(&buf, b"abcd");
+    check_eq!(compression.read(&mut buf), 2);
+    check!(compression.is_empty());
+    Ok(())
+}
+
+/// `len` pseudo-random bytes that zlib cannot shrink
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_F491_u32;
+    std::iter::repeat_with(|| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state.to_le_bytes()[0]
+    })
+    .take(len)
+    .collect()
+}
+
+#[test]
+fn stats_count_both_directions() -> crate::error::Result<()> {
+    let query = packet(
+        0,
+        &[&[0x03][..], &b"SELECT 1 UNION ALL ".repeat(100)].concat(),
+    );
+
+    let mut compression = PacketCompression::default();
+    compression.write(&query);
+    let wire = compression.encode()?.to_vec();
+    let sent = compression.stats().sent;
+    check_eq!(sent.uncompressed_bytes, query.len() as u64);
+    check_eq!(sent.wire_bytes, (wire.len() - 7) as u64);
+    check!(sent.wire_bytes < sent.uncompressed_bytes);
+
+    let (header, body) = &packets(&wire)?[0];
+    compression.body_mut(header).copy_from_slice(body);
+    compression.decode(header)?;
+    let received = compression.stats().received;
+    check_eq!(received.uncompressed_bytes, query.len() as u64);
+    check_eq!(received.wire_bytes, body.len() as u64);
+    check!(!compression.stats().send_compression_disabled);
+    Ok(())
+}
+
+#[test]
+fn auto_disable_stops_compressing_incompressible_payloads() -> crate::error::Result<()> {
+    let mut compression = PacketCompression::zlib().with_auto_disable(true);
+    compression.write(&packet(0, &noise(AUTO_DISABLE_SAMPLE_BYTES as usize)));
+    compression.encode()?;
+    check!(compression.stats().send_compression_disabled);
+
+    // Compressible payloads are now sent as-is, but still framed
+    let query = packet(
+        0,
+        &[&[0x03][..], &b"SELECT 1 UNION ALL ".repeat(100)].concat(),
+    );
+    compression.write(&query);
+    let wire = compression.encode()?.to_vec();
+    let packets = packets(&wire)?;
+    check_eq!(packets[0].0.uncompressed_length(), 0);
+    check_eq!(round_trip(&wire)?, query);
+    Ok(())
+}
+
+#[test]
+fn auto_disable_keeps_compressing_compressible_payloads() -> crate::error::Result<()> {
+    let mut compression = PacketCompression::zlib().with_auto_disable(true);
+    let payload = b"SELECT 1 UNION ALL ".repeat(AUTO_DISABLE_SAMPLE_BYTES as usize / 16);
+    compression.write(&packet(0, &payload));
+    compression.encode()?;
+    check!(!compression.stats().send_compression_disabled);
+
+    // Without auto-disable, incompressible payloads never turn compression off
+    let mut always = PacketCompression::zlib();
+    always.write(&packet(0, &noise(AUTO_DISABLE_SAMPLE_BYTES as usize)));
+    always.encode()?;
+    check!(!always.stats().send_compression_disabled);
+    Ok(())
+}
+
+#[cfg(feature = "zstd-compression")]
+#[test]
+fn zstd_round_trip() -> crate::error::Result<()> {
+    let query = packet(
+        0,
+        &[&[0x03][..], &b"SELECT 1 UNION ALL ".repeat(100)].concat(),
+    );
+
+    let mut compression = PacketCompression::zstd(3);
+    compression.write(&query);
+    let wire = compression.encode()?.to_vec();
+
+    let packets = packets(&wire)?;
+    check_eq!(packets[0].0.uncompressed_length(), query.len());
+    check!(packets[0].1.starts_with(&[0x28, 0xB5, 0x2F, 0xFD])); // zstd frame magic
+
+    let mut reader = PacketCompression::zstd(3);
+    let (header, body) = &packets[0];
+    reader.body_mut(header).copy_from_slice(body);
+    reader.decode(header)?;
+    let mut buf = vec![0; query.len()];
+    check_eq!(reader.read(&mut buf), query.len());
+    check_eq!(buf, query);
+    Ok(())
+}
+
+/// A zlib packet whose header declares `declared` bytes for `payload`
+fn mislabeled(
+    payload: &[u8],
+    declared: usize,
+) -> crate::error::Result<(CompressedPacketHeader, Vec<u8>)> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, payload)?;
+    let body = encoder.finish()?;
+    Ok((
+        CompressedPacketHeader::encode(body.len(), 0, declared),
+        body,
+    ))
+}
+
+#[test]
+fn payload_longer_than_header_is_rejected() -> crate::error::Result<()> {
+    // A bomb: the header claims 16 bytes, the body inflates to 1 MiB
+    let (header, body) = mislabeled(&vec![0; 1 << 20], 16)?;
+    let mut reader = PacketCompression::default();
+    reader.body_mut(&header).copy_from_slice(&body);
+    check!(matches!(
+        reader.decode(&header),
+        Err(crate::error::Error::IoError(err)) if err.kind() == std::io::ErrorKind::InvalidData
+    ));
+    check!(reader.is_empty());
+    Ok(())
+}
+
+#[test]
+fn payload_shorter_than_header_is_rejected() -> crate::error::Result<()> {
+    let (header, body) = mislabeled(&[0; 100], 200)?;
+    let mut reader = PacketCompression::default();
+    reader.body_mut(&header).copy_from_slice(&body);
+    check!(matches!(
+        reader.decode(&header),
+        Err(crate::error::Error::IoError(err)) if err.kind() == std::io::ErrorKind::InvalidData
+    ));
+    check!(reader.is_empty());
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! Ed25519 signatures for MariaDB's `client_ed25519` authentication plugin.
+//!
+//! This is RFC 8032 Ed25519, except that the secret key is expanded from `SHA512(password)`
+//! instead of `SHA512(32-byte seed)`, so the password can have any length.
+//! Off-the-shelf key pairs only accept a 32-byte seed, so the signature is assembled here
+//! from the constant-time scalar and point arithmetic of `curve25519-dalek`.
+
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::scalar::{Scalar, clamp_integer};
+use sha2::{Digest, Sha512};
+
+/// Sign `message` with the key expanded from `secret`.
+///
+/// With a 32-byte `secret`, this is the standard Ed25519 signature with `secret` as the seed.
+pub fn sign(secret: &[u8], message: &[u8]) -> [u8; 64] {
+    let az: [u8; 64] = Sha512::digest(secret).into();
+    let (scalar_bytes, prefix) = az.split_at(32);
+    let mut clamped = [0_u8; 32];
+    clamped.copy_from_slice(scalar_bytes);
+    let a = Scalar::from_bytes_mod_order(clamp_integer(clamped));
+    let public_key = EdwardsPoint::mul_base(&a).compress();
+
+    // r = SHA512(prefix || M) mod l
+    let mut nonce_hasher = Sha512::new();
+    nonce_hasher.update(prefix);
+    nonce_hasher.update(message);
+    let r = Scalar::from_bytes_mod_order_wide(&nonce_hasher.finalize().into());
+    let big_r = EdwardsPoint::mul_base(&r).compress();
+
+    // k = SHA512(R || A || M) mod l
+    let mut hram_hasher = Sha512::new();
+    hram_hasher.update(big_r.as_bytes());
+    hram_hasher.update(public_key.as_bytes());
+    hram_hasher.update(message);
+    let k = Scalar::from_bytes_mod_order_wide(&hram_hasher.finalize().into());
+
+    // S = r + k * a mod l
+    let s = r + k * a;
+
+    let mut signature = [0_u8; 64];
+    let (r_out, s_out) = signature.split_at_mut(32);
+    r_out.copy_from_slice(big_r.as_bytes());
+    s_out.copy_from_slice(s.as_bytes());
+    signature
+}
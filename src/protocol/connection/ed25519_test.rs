@@ -0,0 +1,80 @@
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::{Scalar, clamp_integer};
+use sha2::{Digest, Sha512};
+
+use crate::protocol::connection::ed25519::sign;
+use crate::test_macros::{check, check_eq};
+
+fn unhex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .filter_map(|i| s.get(i..i + 2))
+        .filter_map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect()
+}
+
+// RFC 8032 7.1 TEST 1
+#[test]
+fn rfc8032_empty_message() -> crate::error::Result<()> {
+    let signature = sign(
+        &unhex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"),
+        b"",
+    );
+    check_eq!(
+        signature.to_vec(),
+        unhex(
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+        )
+    );
+    Ok(())
+}
+
+// RFC 8032 7.1 TEST 2
+#[test]
+fn rfc8032_one_byte_message() -> crate::error::Result<()> {
+    let signature = sign(
+        &unhex("4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb"),
+        &[0x72],
+    );
+    check_eq!(
+        signature.to_vec(),
+        unhex(
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"
+        )
+    );
+    Ok(())
+}
+
+// A password of any length signs with the key expanded from SHA512(password),
+// so the signature must verify against the matching public key
+#[test]
+fn password_signature_verifies() -> crate::error::Result<()> {
+    let password = b"a password that is not 32 bytes long";
+    let message = b"01234567890123456789012345678901";
+    let signature = sign(password, message);
+
+    let az: [u8; 64] = Sha512::digest(password).into();
+    let mut a = [0_u8; 32];
+    a.copy_from_slice(&az[..32]);
+    let public_key = EdwardsPoint::mul_base_clamped(clamp_integer(a)).compress();
+
+    let mut r = [0_u8; 32];
+    r.copy_from_slice(&signature[..32]);
+    let mut s = [0_u8; 32];
+    s.copy_from_slice(&signature[32..]);
+    let big_r = CompressedEdwardsY(r).decompress();
+    let public_point = public_key.decompress();
+    let s = Option::<Scalar>::from(Scalar::from_canonical_bytes(s));
+    check!(big_r.is_some() && public_point.is_some() && s.is_some());
+
+    let mut hram_hasher = Sha512::new();
+    hram_hasher.update(r);
+    hram_hasher.update(public_key.as_bytes());
+    hram_hasher.update(message);
+    let k = Scalar::from_bytes_mod_order_wide(&hram_hasher.finalize().into());
+
+    if let (Some(big_r), Some(public_point), Some(s)) = (big_r, public_point, s) {
+        check_eq!(EdwardsPoint::mul_base(&s), big_r + k * public_point);
+    }
+    Ok(())
+}
@@ -3,12 +3,15 @@ use zerocopy::byteorder::little_endian::{U16 as U16LE, U32 as U32LE};
 use zerocopy::{FromBytes, Immutable, KnownLayout};
 
 use crate::buffer::BufferSet;
+use crate::charset::Collation;
 use crate::constant::{
-    CAPABILITIES_ALWAYS_ENABLED, CAPABILITIES_CONFIGURABLE, CapabilityFlags,
+    CAPABILITIES_ALWAYS_ENABLED, CAPABILITIES_CONFIGURABLE, CapabilityFlags, CommandByte,
     MARIADB_CAPABILITIES_ENABLED, MAX_ALLOWED_PACKET, MariadbCapabilityFlags, UTF8MB4_GENERAL_CI,
 };
+use crate::dialect::Dialect;
 use crate::error::{Error, Result, eyre};
-use crate::opts::Opts;
+use crate::negotiation::NegotiationReport;
+use crate::opts::{CompressionAlgorithm, Opts};
 use crate::protocol::primitive::*;
 use crate::protocol::response::ErrPayloadBytes;
 
@@ -120,6 +123,14 @@ pub fn read_auth_switch_request(payload: &[u8]) -> Result<AuthSwitchRequest<'_>>
     let (plugin_name, rest) = read_string_null(data)?;
     data = rest;
 
+    // client_ed25519 sends a raw 32-byte nonce, other plugins a null-terminated scramble
+    if plugin_name == b"client_ed25519" {
+        return Ok(AuthSwitchRequest {
+            plugin_name,
+            plugin_data: data,
+        });
+    }
+
     if let Some(0) = data.last() {
         Ok(AuthSwitchRequest {
             plugin_name,
@@ -139,6 +150,20 @@ pub fn write_auth_switch_response(out: &mut Vec<u8>, auth_data: &[u8]) {
     out.extend_from_slice(auth_data);
 }
 
+/// Write the connection attributes of `opts` as a length-encoded list of key-value strings
+fn write_connect_attrs(out: &mut Vec<u8>, opts: &Opts) {
+    let mut attrs = Vec::new();
+    write_string_lenenc(&mut attrs, "_client_name");
+    write_string_lenenc(&mut attrs, "zero-mysql");
+    write_string_lenenc(&mut attrs, "_client_version");
+    write_string_lenenc(&mut attrs, env!("CARGO_PKG_VERSION"));
+    if let Some(name) = &opts.application_name {
+        write_string_lenenc(&mut attrs, "program_name");
+        write_string_lenenc(&mut attrs, name);
+    }
+    write_bytes_lenenc(out, &attrs);
+}
+
 // ============================================================================
 // Authentication Plugins
 // ============================================================================
@@ -222,6 +247,43 @@ pub fn auth_caching_sha2_password(password: &str, challenge: &[u8]) -> [u8; 32]
     result
 }
 
+/// sha256_password authentication - initial response
+///
+/// The password is sent in cleartext (null-terminated) if the connection is secure.
+/// Otherwise `0x01` requests the server's RSA public key, and the password is sent
+/// encrypted with [`rsa_encrypt_password`] in the next packet.
+/// An empty password is sent as a single `0x00`.
+///
+/// # Arguments
+/// * `password` - Plain text password
+/// * `secure` - Whether the connection is encrypted (or trusted)
+pub fn auth_sha256_password(password: &str, secure: bool) -> Vec<u8> {
+    if password.is_empty() {
+        vec![0]
+    } else if secure {
+        let mut response = Vec::with_capacity(password.len() + 1);
+        response.extend_from_slice(password.as_bytes());
+        response.push(0);
+        response
+    } else {
+        vec![0x01]
+    }
+}
+
+/// client_ed25519 authentication (MariaDB)
+///
+/// Ed25519 signature of the 32-byte challenge with the key derived from `SHA512(password)`.
+///
+/// # Arguments
+/// * `password` - Plain text password
+/// * `challenge` - 32-byte challenge from the server's auth switch request
+///
+/// # Returns
+/// 64-byte signature
+pub fn auth_client_ed25519(password: &str, challenge: &[u8]) -> [u8; 64] {
+    super::ed25519::sign(password.as_bytes(), challenge)
+}
+
 /// caching_sha2_password fast auth result
 ///
 /// After sending the initial auth response, server may respond with:
@@ -296,11 +358,27 @@ fn rsa_encrypt_password(password: &str, scramble: &[u8], pem_str: &str) -> Resul
 // State Machine API for Handshake
 // ============================================================================
 
+/// The collation byte of the handshake. A collation above 255 is sent as the default collation
+/// of its character set and set afterwards with `SET NAMES`.
+fn handshake_collation(opts: &Opts) -> Result<u8> {
+    let collation = opts.connection_collation()?;
+    let sent = if collation.id() > 255 {
+        collation
+            .charset()
+            .default_collation()
+            .unwrap_or(Collation::UTF8MB4_GENERAL_CI)
+    } else {
+        collation
+    };
+    Ok(u8::try_from(sent.id()).unwrap_or(UTF8MB4_GENERAL_CI))
+}
+
 /// Write SSL request packet (sent before HandshakeResponse when TLS is enabled)
 fn write_ssl_request(
     out: &mut Vec<u8>,
     capability_flags: CapabilityFlags,
     mariadb_capabilities: MariadbCapabilityFlags,
+    collation: u8,
 ) {
     // capability flags (4 bytes)
     write_int_4(out, capability_flags.bits());
@@ -309,7 +387,7 @@ fn write_ssl_request(
     write_int_4(out, MAX_ALLOWED_PACKET);
 
     // charset (1 byte)
-    write_int_1(out, UTF8MB4_GENERAL_CI);
+    write_int_1(out, collation);
 
     // reserved (23 bytes of 0x00)
     out.extend_from_slice(&[0_u8; 19]);
@@ -342,6 +420,8 @@ enum HandshakeState {
     Start,
     /// Waiting for initial handshake packet to be read
     WaitingInitialHandshake,
+    /// Re-authentication of an established connection - need to write COM_CHANGE_USER
+    ChangeUser,
     /// SSL request written, waiting for TLS upgrade to complete
     WaitingTlsUpgrade,
     /// Handshake response written, waiting for auth result
@@ -366,6 +446,11 @@ pub struct Handshake<'a> {
     next_sequence_id: u8,
     capability_flags: Option<CapabilityFlags>,
     mariadb_capabilities: Option<MariadbCapabilityFlags>,
+    /// Nonce of an AuthSwitchRequest, which replaces the initial handshake's scramble
+    switch_scramble: Option<Vec<u8>>,
+    /// Plugin named by an AuthSwitchRequest, which replaces the initial handshake's plugin
+    switch_plugin: Option<String>,
+    negotiation: Option<NegotiationReport>,
 }
 
 impl<'a> Handshake<'a> {
@@ -378,9 +463,52 @@ impl<'a> Handshake<'a> {
             next_sequence_id: 1,
             capability_flags: None,
             mariadb_capabilities: None,
+            switch_scramble: None,
+            switch_plugin: None,
+            negotiation: None,
         }
     }
 
+    /// Create a state machine that re-authenticates an established connection with COM_CHANGE_USER
+    ///
+    /// `opts` provides the new user, password and database.
+    /// The other arguments are the results of the connection's original handshake.
+    pub fn change_user(
+        opts: &'a Opts,
+        initial_handshake: InitialHandshake,
+        capability_flags: CapabilityFlags,
+        mariadb_capabilities: MariadbCapabilityFlags,
+    ) -> Self {
+        Self {
+            state: HandshakeState::ChangeUser,
+            opts,
+            initial_handshake: Some(initial_handshake),
+            next_sequence_id: 0,
+            capability_flags: Some(capability_flags),
+            mariadb_capabilities: Some(mariadb_capabilities),
+            switch_scramble: None,
+            switch_plugin: None,
+            negotiation: None,
+        }
+    }
+
+    /// Warn about every requested capability the server did not grant, and fail if
+    /// `strict_capabilities` is set and any was downgraded.
+    fn check_negotiated_capabilities(&self, report: &NegotiationReport) -> Result<()> {
+        for downgrade in report.downgrades() {
+            tracing::warn!(%downgrade, "requested capability was not negotiated");
+        }
+        if self.opts.strict_capabilities && report.is_downgraded() {
+            let downgrades: Vec<String> =
+                report.downgrades().iter().map(|d| d.to_string()).collect();
+            return Err(Error::Unsupported(format!(
+                "requested capabilities were not negotiated: {}",
+                downgrades.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
     /// Drive the state machine forward
     ///
     /// Returns an action indicating what I/O operation the caller should perform.
@@ -401,26 +529,74 @@ impl<'a> Handshake<'a> {
                 if self.opts.db.is_some() {
                     client_caps |= CapabilityFlags::CLIENT_CONNECT_WITH_DB;
                 }
-                if self.opts.tls {
+                if self.opts.local_infile.is_some() {
+                    client_caps |= CapabilityFlags::CLIENT_LOCAL_FILES;
+                }
+                client_caps.set(
+                    CapabilityFlags::CLIENT_CONNECT_ATTRS,
+                    self.opts.application_name.is_some(),
+                );
+                if self.opts.tls && !self.opts.danger_zone.skip_tls {
                     client_caps |= CapabilityFlags::CLIENT_SSL;
                 }
+                if self.opts.compress {
+                    client_caps |= match self.opts.compression_algorithm {
+                        CompressionAlgorithm::Zstd
+                            if handshake
+                                .capability_flags
+                                .contains(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM) =>
+                        {
+                            if !cfg!(feature = "zstd-compression") {
+                                return Err(Error::BadUsageError(
+                                    "zstd compression requires the zstd-compression feature"
+                                        .to_string(),
+                                ));
+                            }
+                            CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM
+                        }
+                        _ => CapabilityFlags::CLIENT_COMPRESS,
+                    };
+                }
+                if self.opts.danger_zone.is_enabled() {
+                    tracing::debug!(danger_zone = ?self.opts.danger_zone, "relaxed handshake");
+                }
+
+                let dialect = self.opts.dialect.unwrap_or_else(|| {
+                    Dialect::detect(
+                        &buffer_set.initial_handshake[handshake.server_version.clone()],
+                        handshake.capability_flags,
+                    )
+                });
+                client_caps -= dialect.disabled_capabilities();
 
                 let negotiated_caps = client_caps & handshake.capability_flags;
                 let mariadb_caps = if negotiated_caps.is_mariadb() {
-                    if !handshake
-                        .mariadb_capabilities
-                        .contains(MARIADB_CAPABILITIES_ENABLED)
-                    {
+                    let required = self.opts.mariadb_capabilities & MARIADB_CAPABILITIES_ENABLED;
+                    if !handshake.mariadb_capabilities.contains(required) {
                         return Err(Error::Unsupported(format!(
                             "MariaDB server does not support the required capabilities. Server: {:?} Required: {:?}",
-                            handshake.mariadb_capabilities, MARIADB_CAPABILITIES_ENABLED
+                            handshake.mariadb_capabilities, required
                         )));
                     }
-                    MARIADB_CAPABILITIES_ENABLED
+                    required
                 } else {
                     MariadbCapabilityFlags::empty()
                 };
 
+                if self.opts.tls
+                    && self.opts.require_tls
+                    && !self.opts.danger_zone.skip_tls
+                    && !negotiated_caps.contains(CapabilityFlags::CLIENT_SSL)
+                {
+                    return Err(Error::Unsupported(
+                        "the server does not support TLS, which require_tls requires".to_string(),
+                    ));
+                }
+
+                let report = NegotiationReport::new(self.opts, dialect, negotiated_caps);
+                self.check_negotiated_capabilities(&report)?;
+                self.negotiation = Some(report);
+
                 // Store capabilities and initial handshake
                 self.capability_flags = Some(negotiated_caps);
                 self.mariadb_capabilities = Some(mariadb_caps);
@@ -428,7 +604,12 @@ impl<'a> Handshake<'a> {
 
                 // TLS: SSLRequest + HandshakeResponse
                 if self.opts.tls && negotiated_caps.contains(CapabilityFlags::CLIENT_SSL) {
-                    write_ssl_request(buffer_set.new_write_buffer(), negotiated_caps, mariadb_caps);
+                    write_ssl_request(
+                        buffer_set.new_write_buffer(),
+                        negotiated_caps,
+                        mariadb_caps,
+                        handshake_collation(self.opts)?,
+                    );
 
                     let seq = self.next_sequence_id;
                     self.next_sequence_id = self.next_sequence_id.wrapping_add(1);
@@ -446,6 +627,15 @@ impl<'a> Handshake<'a> {
                 }
             }
 
+            HandshakeState::ChangeUser => {
+                self.write_change_user(buffer_set)?;
+                let seq = self.next_sequence_id;
+                self.next_sequence_id = self.next_sequence_id.wrapping_add(2);
+                self.state = HandshakeState::WaitingAuthResult;
+
+                Ok(HandshakeAction::WritePacket { sequence_id: seq })
+            }
+
             HandshakeState::WaitingTlsUpgrade => {
                 // TLS upgrade completed, now send handshake response
                 self.write_handshake_response(buffer_set)?;
@@ -484,8 +674,11 @@ impl<'a> Handshake<'a> {
                     }
                     0x01 => {
                         // AuthMoreData — caching_sha2_password fast auth result
+                        // or the RSA public key requested by sha256_password
                         if initial_plugin == b"caching_sha2_password" {
                             self.handle_auth_more_data(buffer_set)
+                        } else if initial_plugin == b"sha256_password" {
+                            self.write_rsa_encrypted_password(buffer_set)
                         } else {
                             Err(Error::LibraryBug(eyre!(
                                 "unexpected AuthMoreData (0x01) for plugin {:?}",
@@ -498,14 +691,16 @@ impl<'a> Handshake<'a> {
                         let auth_switch = read_auth_switch_request(payload)?;
 
                         // Compute auth response for new plugin
-                        let (auth_response, is_caching_sha2) = match auth_switch.plugin_name {
+                        let (auth_response, next_state) = match auth_switch.plugin_name {
                             b"mysql_native_password" => (
                                 auth_mysql_native_password(
                                     &self.opts.password,
                                     auth_switch.plugin_data,
                                 )
                                 .to_vec(),
-                                false,
+                                HandshakeState::WaitingFinalAuthResult {
+                                    caching_sha2: false,
+                                },
                             ),
                             b"caching_sha2_password" => (
                                 auth_caching_sha2_password(
@@ -513,7 +708,26 @@ impl<'a> Handshake<'a> {
                                     auth_switch.plugin_data,
                                 )
                                 .to_vec(),
-                                true,
+                                HandshakeState::WaitingFinalAuthResult { caching_sha2: true },
+                            ),
+                            b"sha256_password" => {
+                                let response =
+                                    auth_sha256_password(&self.opts.password, self.is_secure()?);
+                                let next_state = if response == [0x01] {
+                                    HandshakeState::WaitingRsaPublicKey
+                                } else {
+                                    HandshakeState::WaitingFinalAuthResult {
+                                        caching_sha2: false,
+                                    }
+                                };
+                                (response, next_state)
+                            }
+                            b"client_ed25519" => (
+                                auth_client_ed25519(&self.opts.password, auth_switch.plugin_data)
+                                    .to_vec(),
+                                HandshakeState::WaitingFinalAuthResult {
+                                    caching_sha2: false,
+                                },
                             ),
                             plugin => {
                                 return Err(Error::Unsupported(
@@ -522,13 +736,14 @@ impl<'a> Handshake<'a> {
                             }
                         };
 
+                        self.switch_scramble = Some(auth_switch.plugin_data.to_vec());
+                        self.switch_plugin =
+                            Some(String::from_utf8_lossy(auth_switch.plugin_name).into_owned());
                         write_auth_switch_response(buffer_set.new_write_buffer(), &auth_response);
 
                         let seq = self.next_sequence_id;
                         self.next_sequence_id = self.next_sequence_id.wrapping_add(2);
-                        self.state = HandshakeState::WaitingFinalAuthResult {
-                            caching_sha2: is_caching_sha2,
-                        };
+                        self.state = next_state;
 
                         Ok(HandshakeAction::WritePacket { sequence_id: seq })
                     }
@@ -586,48 +801,7 @@ impl<'a> Handshake<'a> {
                 }
             }
 
-            HandshakeState::WaitingRsaPublicKey => {
-                let payload = &buffer_set.read_buffer[..];
-                if payload.is_empty() {
-                    return Err(Error::LibraryBug(eyre!(
-                        "empty payload while waiting for RSA public key"
-                    )));
-                }
-
-                match payload[0] {
-                    0xFF => return Err(ErrPayloadBytes(payload).into()),
-                    0x01 if payload.len() >= 2 => {}
-                    header => {
-                        return Err(Error::LibraryBug(eyre!(
-                            "expected AuthMoreData (0x01) with RSA public key, got 0x{:02X}",
-                            header
-                        )));
-                    }
-                }
-
-                let pem = std::str::from_utf8(&payload[1..]).map_err(|e| {
-                    Error::LibraryBug(eyre!("RSA public key is not valid UTF-8: {}", e))
-                })?;
-
-                let handshake = self
-                    .initial_handshake
-                    .as_ref()
-                    .ok_or_else(|| Error::LibraryBug(eyre!("initial_handshake not set")))?;
-
-                let encrypted =
-                    rsa_encrypt_password(&self.opts.password, &handshake.auth_plugin_data, pem)?;
-
-                let out = buffer_set.new_write_buffer();
-                out.extend_from_slice(&encrypted);
-
-                let seq = self.next_sequence_id;
-                self.next_sequence_id = self.next_sequence_id.wrapping_add(2);
-                self.state = HandshakeState::WaitingFinalAuthResult {
-                    caching_sha2: false,
-                };
-
-                Ok(HandshakeAction::WritePacket { sequence_id: seq })
-            }
+            HandshakeState::WaitingRsaPublicKey => self.write_rsa_encrypted_password(buffer_set),
 
             HandshakeState::Connected => Err(Error::LibraryBug(eyre!(
                 "step() called after handshake completed"
@@ -635,6 +809,35 @@ impl<'a> Handshake<'a> {
         }
     }
 
+    /// The authentication plugin the server accepted
+    ///
+    /// This is the plugin of the last AuthSwitchRequest, if any, or else the plugin the client
+    /// answered the initial handshake with.
+    pub fn auth_plugin(&self, buffer_set: &BufferSet) -> String {
+        if let Some(plugin) = &self.switch_plugin {
+            return plugin.clone();
+        }
+        if self.opts.danger_zone.native_password_only {
+            return "mysql_native_password".to_string();
+        }
+        self.initial_handshake
+            .as_ref()
+            .and_then(|handshake| {
+                buffer_set
+                    .initial_handshake
+                    .get(handshake.auth_plugin_name.clone())
+            })
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .unwrap_or_default()
+    }
+
+    /// The capabilities requested in `Opts` and the ones the server negotiated.
+    ///
+    /// Empty for `change_user`, which keeps the negotiated capabilities of the connection.
+    pub fn negotiation_report(&self) -> NegotiationReport {
+        self.negotiation.clone().unwrap_or_default()
+    }
+
     /// Consume the state machine and return the connection info
     ///
     /// Returns an error if called before handshake is complete (before Finished action)
@@ -679,24 +882,13 @@ impl<'a> Handshake<'a> {
         })?;
 
         // Compute auth response based on plugin name
-        let auth_plugin_name = &buffer_set.initial_handshake[handshake.auth_plugin_name.clone()];
-        let auth_response = {
-            match auth_plugin_name {
-                b"mysql_native_password" => {
-                    auth_mysql_native_password(&self.opts.password, &handshake.auth_plugin_data)
-                        .to_vec()
-                }
-                b"caching_sha2_password" => {
-                    auth_caching_sha2_password(&self.opts.password, &handshake.auth_plugin_data)
-                        .to_vec()
-                }
-                plugin => {
-                    return Err(Error::Unsupported(
-                        String::from_utf8_lossy(plugin).to_string(),
-                    ));
-                }
-            }
+        let auth_plugin_name: &[u8] = if self.opts.danger_zone.native_password_only {
+            b"mysql_native_password"
+        } else {
+            &buffer_set.initial_handshake[handshake.auth_plugin_name.clone()]
         };
+        let auth_response = self.auth_response(auth_plugin_name, &handshake.auth_plugin_data)?;
+        let collation = handshake_collation(self.opts)?;
 
         let out = &mut buffer_set.write_buffer;
         // capability flags (4 bytes)
@@ -704,7 +896,7 @@ impl<'a> Handshake<'a> {
         // max packet size (4 bytes)
         write_int_4(out, MAX_ALLOWED_PACKET);
         // charset (1 byte)
-        write_int_1(out, UTF8MB4_GENERAL_CI);
+        write_int_1(out, collation);
         // reserved (19 bytes) + MariaDB capabilities (4 bytes) = 23 bytes
         out.extend_from_slice(&[0_u8; 19]);
         write_int_4(out, mariadb_capabilities.bits());
@@ -727,9 +919,138 @@ impl<'a> Handshake<'a> {
             write_string_null(out, auth_plugin_name);
         }
 
+        // connection attributes (length-encoded, if CLIENT_CONNECT_ATTRS)
+        if capability_flags.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS) {
+            write_connect_attrs(out, self.opts);
+        }
+
+        // zstd compression level (1 byte, if CLIENT_ZSTD_COMPRESSION_ALGORITHM)
+        if capability_flags.contains(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM) {
+            write_int_1(out, self.opts.zstd_compression_level);
+        }
+
         Ok(())
     }
 
+    /// Auth response for the plugin advertised in the initial handshake
+    fn auth_response(&self, auth_plugin_name: &[u8], scramble: &[u8]) -> Result<Vec<u8>> {
+        Ok(match auth_plugin_name {
+            b"mysql_native_password" => {
+                auth_mysql_native_password(&self.opts.password, scramble).to_vec()
+            }
+            b"caching_sha2_password" => {
+                auth_caching_sha2_password(&self.opts.password, scramble).to_vec()
+            }
+            b"sha256_password" => auth_sha256_password(&self.opts.password, self.is_secure()?),
+            b"client_ed25519" => auth_client_ed25519(&self.opts.password, scramble).to_vec(),
+            plugin => {
+                return Err(Error::Unsupported(
+                    String::from_utf8_lossy(plugin).to_string(),
+                ));
+            }
+        })
+    }
+
+    /// Write COM_CHANGE_USER with the auth response for the initial handshake's plugin
+    fn write_change_user(&self, buffer_set: &mut BufferSet) -> Result<()> {
+        buffer_set.new_write_buffer();
+
+        let handshake = self.initial_handshake.as_ref().ok_or_else(|| {
+            Error::LibraryBug(eyre!("initial_handshake not set in write_change_user"))
+        })?;
+        let auth_plugin_name: &[u8] = if self.opts.danger_zone.native_password_only {
+            b"mysql_native_password"
+        } else {
+            &buffer_set.initial_handshake[handshake.auth_plugin_name.clone()]
+        };
+        let auth_response = self.auth_response(auth_plugin_name, &handshake.auth_plugin_data)?;
+        let auth_response_len = u8::try_from(auth_response.len()).map_err(|_too_long| {
+            Error::LibraryBug(eyre!(
+                "auth response of {} bytes does not fit COM_CHANGE_USER",
+                auth_response.len()
+            ))
+        })?;
+
+        let collation = self.opts.connection_collation()?;
+
+        let out = &mut buffer_set.write_buffer;
+        write_int_1(out, CommandByte::ChangeUser as u8);
+        write_string_null(out, self.opts.user.as_bytes());
+        write_int_1(out, auth_response_len);
+        out.extend_from_slice(&auth_response);
+        write_string_null(out, self.opts.db.as_deref().unwrap_or_default().as_bytes());
+        write_int_2(out, collation.id());
+        write_string_null(out, auth_plugin_name);
+        if self
+            .capability_flags
+            .is_some_and(|caps| caps.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS))
+        {
+            write_connect_attrs(out, self.opts);
+        }
+        Ok(())
+    }
+
+    /// Encrypt the password with the RSA public key in an AuthMoreData packet.
+    ///
+    /// Used by caching_sha2_password full authentication and sha256_password.
+    fn write_rsa_encrypted_password<'buf>(
+        &mut self,
+        buffer_set: &'buf mut BufferSet,
+    ) -> Result<HandshakeAction<'buf>> {
+        let payload = &buffer_set.read_buffer[..];
+        if payload.is_empty() {
+            return Err(Error::LibraryBug(eyre!(
+                "empty payload while waiting for RSA public key"
+            )));
+        }
+
+        match payload[0] {
+            0xFF => return Err(ErrPayloadBytes(payload).into()),
+            0x01 if payload.len() >= 2 => {}
+            header => {
+                return Err(Error::LibraryBug(eyre!(
+                    "expected AuthMoreData (0x01) with RSA public key, got 0x{:02X}",
+                    header
+                )));
+            }
+        }
+
+        let pem = std::str::from_utf8(&payload[1..])
+            .map_err(|e| Error::LibraryBug(eyre!("RSA public key is not valid UTF-8: {}", e)))?;
+
+        let handshake = self
+            .initial_handshake
+            .as_ref()
+            .ok_or_else(|| Error::LibraryBug(eyre!("initial_handshake not set")))?;
+
+        let scramble = self
+            .switch_scramble
+            .as_deref()
+            .unwrap_or(&handshake.auth_plugin_data);
+        let encrypted = rsa_encrypt_password(&self.opts.password, scramble, pem)?;
+
+        let out = buffer_set.new_write_buffer();
+        out.extend_from_slice(&encrypted);
+
+        let seq = self.next_sequence_id;
+        self.next_sequence_id = self.next_sequence_id.wrapping_add(2);
+        self.state = HandshakeState::WaitingFinalAuthResult {
+            caching_sha2: false,
+        };
+
+        Ok(HandshakeAction::WritePacket { sequence_id: seq })
+    }
+
+    /// Returns true if the password may be sent in cleartext:
+    /// TLS is active or `cleartext_password_without_tls` is set.
+    fn is_secure(&self) -> Result<bool> {
+        let capability_flags = self
+            .capability_flags
+            .ok_or_else(|| Error::LibraryBug(eyre!("capability_flags not set")))?;
+        Ok(capability_flags.contains(CapabilityFlags::CLIENT_SSL)
+            || self.opts.danger_zone.cleartext_password_without_tls)
+    }
+
     /// Handle AuthMoreData (0x01) packet for caching_sha2_password.
     ///
     /// Called from both `WaitingAuthResult` and `WaitingFinalAuthResult { caching_sha2: true }`.
@@ -754,12 +1075,8 @@ impl<'a> Handshake<'a> {
                 Ok(HandshakeAction::ReadPacket(&mut buffer_set.read_buffer))
             }
             CachingSha2PasswordFastAuthResult::FullAuthRequired => {
-                let capability_flags = self
-                    .capability_flags
-                    .ok_or_else(|| Error::LibraryBug(eyre!("capability_flags not set")))?;
-
-                if capability_flags.contains(CapabilityFlags::CLIENT_SSL) {
-                    // TLS is active — send cleartext password (null-terminated)
+                if self.is_secure()? {
+                    // TLS is active (or trusted) — send cleartext password (null-terminated)
                     let out = buffer_set.new_write_buffer();
                     out.extend_from_slice(self.opts.password.as_bytes());
                     out.push(0);
@@ -0,0 +1,551 @@
+use crate::buffer::BufferSet;
+use crate::constant::CapabilityFlags;
+use crate::opts::{CompressionAlgorithm, DangerZone, Opts};
+use crate::protocol::connection::handshake::{
+    auth_caching_sha2_password, auth_client_ed25519, read_initial_handshake,
+};
+use crate::protocol::connection::{Handshake, HandshakeAction};
+use crate::test_macros::{check, check_eq};
+
+/// A MySQL 8 initial handshake with `plugin` as the default plugin.
+fn initial_handshake(plugin: &[u8]) -> Vec<u8> {
+    let caps = (CapabilityFlags::all() - CapabilityFlags::CLIENT_DEPRECATE_EOF).bits();
+    let mut packet = vec![10];
+    packet.extend_from_slice(b"8.0.36\0");
+    packet.extend_from_slice(&1_u32.to_le_bytes()); // connection id
+    packet.extend_from_slice(b"abcdefgh"); // auth data part 1
+    packet.push(0);
+    packet.extend_from_slice(&(caps as u16).to_le_bytes());
+    packet.push(255); // charset
+    packet.extend_from_slice(&2_u16.to_le_bytes()); // status
+    packet.extend_from_slice(&((caps >> 16) as u16).to_le_bytes());
+    packet.push(21); // auth data length
+    packet.extend_from_slice(&[0; 10]);
+    packet.extend_from_slice(b"ijklmnopqrst\0"); // auth data part 2
+    packet.extend_from_slice(plugin);
+    packet.push(0);
+    packet
+}
+
+fn start<'a>(opts: &'a Opts, buffer_set: &mut BufferSet) -> crate::error::Result<Handshake<'a>> {
+    start_with_plugin(opts, buffer_set, b"caching_sha2_password")
+}
+
+fn start_with_plugin<'a>(
+    opts: &'a Opts,
+    buffer_set: &mut BufferSet,
+    plugin: &[u8],
+) -> crate::error::Result<Handshake<'a>> {
+    let mut handshake = Handshake::new(opts);
+    let HandshakeAction::ReadPacket(buffer) = handshake.step(buffer_set)? else {
+        return Err(crate::error::Error::LibraryBug(crate::error::eyre!(
+            "expected ReadPacket"
+        )));
+    };
+    *buffer = initial_handshake(plugin);
+    Ok(handshake)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn trusted_local_proxy_skips_tls_and_uses_native_password() -> crate::error::Result<()> {
+    let opts = Opts {
+        tls: true,
+        danger_zone: DangerZone::trusted_local_proxy(),
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    let action = handshake.step(&mut buffer_set)?;
+    check!(matches!(action, HandshakeAction::WritePacket { .. }));
+    check!(contains(
+        buffer_set.write_buffer(),
+        b"mysql_native_password\0"
+    ));
+    check_eq!(handshake.auth_plugin(&buffer_set), "mysql_native_password");
+    Ok(())
+}
+
+#[test]
+fn require_tls_rejects_server_without_tls() -> crate::error::Result<()> {
+    let opts = Opts {
+        tls: true,
+        require_tls: true,
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    // Clear CLIENT_SSL (0x0800) in the lower capability flags
+    if let Some(flags) = buffer_set.initial_handshake.get_mut(22) {
+        *flags &= !0x08;
+    }
+    check!(matches!(
+        handshake.step(&mut buffer_set),
+        Err(crate::error::Error::Unsupported(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn strict_by_default() -> crate::error::Result<()> {
+    let opts = Opts {
+        tls: true,
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    let action = handshake.step(&mut buffer_set)?;
+    check!(matches!(action, HandshakeAction::UpgradeTls { .. }));
+    Ok(())
+}
+
+#[test]
+fn cleartext_full_auth_without_tls() -> crate::error::Result<()> {
+    let opts = Opts {
+        password: "secret".to_string(),
+        danger_zone: DangerZone {
+            cleartext_password_without_tls: true,
+            ..DangerZone::default()
+        },
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    handshake.step(&mut buffer_set)?;
+
+    // AuthMoreData: full authentication required
+    buffer_set.read_buffer = vec![0x01, 0x04];
+    let action = handshake.step(&mut buffer_set)?;
+    check!(matches!(action, HandshakeAction::WritePacket { .. }));
+    check_eq!(&buffer_set.write_buffer()[4..], b"secret\0");
+    Ok(())
+}
+
+#[test]
+fn compress_requests_client_compress() -> crate::error::Result<()> {
+    for compress in [false, true] {
+        let opts = Opts {
+            compress,
+            ..Opts::default()
+        };
+        let mut buffer_set = BufferSet::new();
+        let mut handshake = start(&opts, &mut buffer_set)?;
+        handshake.step(&mut buffer_set)?;
+        // [header: 4][client capabilities: 4]
+        let caps = buffer_set
+            .write_buffer()
+            .get(4..8)
+            .and_then(|caps| caps.try_into().ok())
+            .map(u32::from_le_bytes)
+            .map(CapabilityFlags::from_bits_truncate);
+        check_eq!(
+            caps.map(|caps| caps.contains(CapabilityFlags::CLIENT_COMPRESS)),
+            Some(compress)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn application_name_sends_connect_attrs() -> crate::error::Result<()> {
+    for application_name in [None, Some("billing-worker")] {
+        let opts = Opts {
+            application_name: application_name.map(str::to_string),
+            ..Opts::default()
+        };
+        let mut buffer_set = BufferSet::new();
+        let mut handshake = start(&opts, &mut buffer_set)?;
+        handshake.step(&mut buffer_set)?;
+        let caps = buffer_set
+            .write_buffer()
+            .get(4..8)
+            .and_then(|caps| caps.try_into().ok())
+            .map(u32::from_le_bytes)
+            .map(CapabilityFlags::from_bits_truncate);
+        check_eq!(
+            caps.map(|caps| caps.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS)),
+            Some(application_name.is_some())
+        );
+        check_eq!(
+            contains(
+                buffer_set.write_buffer(),
+                b"\x0cprogram_name\x0ebilling-worker"
+            ),
+            application_name.is_some()
+        );
+        check_eq!(
+            contains(buffer_set.write_buffer(), b"\x0c_client_name\x0azero-mysql"),
+            application_name.is_some()
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn zstd_sends_compression_level() -> crate::error::Result<()> {
+    let opts = Opts {
+        compress: true,
+        compression_algorithm: CompressionAlgorithm::Zstd,
+        zstd_compression_level: 7,
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    let result = handshake.step(&mut buffer_set);
+    if !cfg!(feature = "zstd-compression") {
+        check!(matches!(result, Err(crate::error::Error::BadUsageError(_))));
+        return Ok(());
+    }
+    result?;
+
+    let caps = buffer_set
+        .write_buffer()
+        .get(4..8)
+        .and_then(|caps| caps.try_into().ok())
+        .map(u32::from_le_bytes)
+        .map(CapabilityFlags::from_bits_truncate)
+        .unwrap_or(CapabilityFlags::empty());
+    check!(caps.contains(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM));
+    check!(!caps.contains(CapabilityFlags::CLIENT_COMPRESS));
+    check!(
+        buffer_set
+            .write_buffer()
+            .ends_with(b"caching_sha2_password\0\x07")
+    );
+    Ok(())
+}
+
+fn rsa_error(e: impl std::fmt::Display) -> crate::error::Error {
+    crate::error::Error::LibraryBug(crate::error::eyre!("{e}"))
+}
+
+/// An RSA key pair as (PKCS#8 private key, PEM public key)
+fn rsa_key_pair() -> crate::error::Result<(Vec<u8>, String)> {
+    use aws_lc_rs::encoding::AsDer;
+    use aws_lc_rs::rsa::{KeyPair, KeySize};
+    use aws_lc_rs::signature::KeyPair as _;
+
+    let key_pair = KeyPair::generate(KeySize::Rsa2048).map_err(rsa_error)?;
+    let private_key = key_pair.as_der().map_err(rsa_error)?.as_ref().to_vec();
+    let public_key = key_pair.public_key().as_der().map_err(rsa_error)?;
+    let pem = pem::encode(&pem::Pem::new("PUBLIC KEY", public_key.as_ref().to_vec()));
+    Ok((private_key, pem))
+}
+
+/// Decrypt an RSA-encrypted password and undo the XOR with `scramble`
+fn decrypt_password(
+    private_key: &[u8],
+    encrypted: &[u8],
+    scramble: &[u8],
+) -> crate::error::Result<Vec<u8>> {
+    use aws_lc_rs::rsa::{OAEP_SHA1_MGF1SHA1, OaepPrivateDecryptingKey, PrivateDecryptingKey};
+
+    let pkcs8 = PrivateDecryptingKey::from_pkcs8(private_key).map_err(rsa_error)?;
+    let key = OaepPrivateDecryptingKey::new(pkcs8).map_err(rsa_error)?;
+    let mut plaintext = vec![0; encrypted.len()];
+    let mut password = key
+        .decrypt(&OAEP_SHA1_MGF1SHA1, encrypted, &mut plaintext, None)
+        .map_err(rsa_error)?
+        .to_vec();
+    for (byte, mask) in password.iter_mut().zip(scramble.iter().cycle()) {
+        *byte ^= mask;
+    }
+    Ok(password)
+}
+
+#[test]
+fn rsa_full_auth_without_tls() -> crate::error::Result<()> {
+    let (private_key, pem) = rsa_key_pair()?;
+    let opts = Opts {
+        password: "secret".to_string(),
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    handshake.step(&mut buffer_set)?;
+
+    // AuthMoreData: full authentication required -> request the public key
+    buffer_set.read_buffer = vec![0x01, 0x04];
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::WritePacket { sequence_id: 3 }
+    ));
+    check_eq!(&buffer_set.write_buffer()[4..], &[0x02]);
+
+    // AuthMoreData: public key -> encrypted password
+    buffer_set.read_buffer = [&[0x01][..], pem.as_bytes()].concat();
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::WritePacket { sequence_id: 5 }
+    ));
+    let password = decrypt_password(
+        &private_key,
+        &buffer_set.write_buffer()[4..],
+        b"abcdefghijklmnopqrst",
+    )?;
+    check_eq!(password, b"secret\0");
+
+    buffer_set.read_buffer = vec![0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00];
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::Finished
+    ));
+    Ok(())
+}
+
+#[test]
+fn rsa_full_auth_after_auth_switch_uses_new_scramble() -> crate::error::Result<()> {
+    let (private_key, pem) = rsa_key_pair()?;
+    let opts = Opts {
+        password: "secret".to_string(),
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    handshake.step(&mut buffer_set)?;
+
+    buffer_set.read_buffer = [
+        &[0xFE][..],
+        b"caching_sha2_password\0",
+        b"ABCDEFGHIJKLMNOPQRST\0",
+    ]
+    .concat();
+    handshake.step(&mut buffer_set)?;
+    buffer_set.read_buffer = vec![0x01, 0x04];
+    handshake.step(&mut buffer_set)?;
+    buffer_set.read_buffer = [&[0x01][..], pem.as_bytes()].concat();
+    handshake.step(&mut buffer_set)?;
+
+    let password = decrypt_password(
+        &private_key,
+        &buffer_set.write_buffer()[4..],
+        b"ABCDEFGHIJKLMNOPQRST",
+    )?;
+    check_eq!(password, b"secret\0");
+    Ok(())
+}
+
+#[test]
+fn full_auth_over_tls_sends_cleartext() -> crate::error::Result<()> {
+    let opts = Opts {
+        password: "secret".to_string(),
+        tls: true,
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::UpgradeTls { .. }
+    ));
+    handshake.step(&mut buffer_set)?;
+
+    buffer_set.read_buffer = vec![0x01, 0x04];
+    handshake.step(&mut buffer_set)?;
+    check_eq!(&buffer_set.write_buffer()[4..], b"secret\0");
+    Ok(())
+}
+
+#[test]
+fn sha256_password_requests_public_key_without_tls() -> crate::error::Result<()> {
+    let (private_key, pem) = rsa_key_pair()?;
+    let opts = Opts {
+        password: "secret".to_string(),
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start_with_plugin(&opts, &mut buffer_set, b"sha256_password")?;
+    handshake.step(&mut buffer_set)?;
+    // [auth response length: 1][0x01] before the plugin name
+    check!(
+        buffer_set
+            .write_buffer()
+            .ends_with(b"\x01\x01sha256_password\0")
+    );
+
+    buffer_set.read_buffer = [&[0x01][..], pem.as_bytes()].concat();
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::WritePacket { sequence_id: 3 }
+    ));
+    let password = decrypt_password(
+        &private_key,
+        &buffer_set.write_buffer()[4..],
+        b"abcdefghijklmnopqrst",
+    )?;
+    check_eq!(password, b"secret\0");
+
+    buffer_set.read_buffer = vec![0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00];
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::Finished
+    ));
+    Ok(())
+}
+
+#[test]
+fn sha256_password_sends_cleartext_over_tls() -> crate::error::Result<()> {
+    let opts = Opts {
+        password: "secret".to_string(),
+        tls: true,
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start_with_plugin(&opts, &mut buffer_set, b"sha256_password")?;
+    handshake.step(&mut buffer_set)?;
+    handshake.step(&mut buffer_set)?;
+    check!(
+        buffer_set
+            .write_buffer()
+            .ends_with(b"\x07secret\0sha256_password\0")
+    );
+    Ok(())
+}
+
+#[test]
+fn sha256_password_after_auth_switch() -> crate::error::Result<()> {
+    let (private_key, pem) = rsa_key_pair()?;
+    let opts = Opts {
+        password: "secret".to_string(),
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start(&opts, &mut buffer_set)?;
+    handshake.step(&mut buffer_set)?;
+
+    buffer_set.read_buffer =
+        [&[0xFE][..], b"sha256_password\0", b"ABCDEFGHIJKLMNOPQRST\0"].concat();
+    handshake.step(&mut buffer_set)?;
+    check_eq!(&buffer_set.write_buffer()[4..], &[0x01]);
+
+    buffer_set.read_buffer = [&[0x01][..], pem.as_bytes()].concat();
+    handshake.step(&mut buffer_set)?;
+    let password = decrypt_password(
+        &private_key,
+        &buffer_set.write_buffer()[4..],
+        b"ABCDEFGHIJKLMNOPQRST",
+    )?;
+    check_eq!(password, b"secret\0");
+
+    // Empty password
+    let empty = Opts::default();
+    let mut empty_buffer_set = BufferSet::new();
+    let mut empty_handshake = start(&empty, &mut empty_buffer_set)?;
+    empty_handshake.step(&mut empty_buffer_set)?;
+    empty_buffer_set.read_buffer =
+        [&[0xFE][..], b"sha256_password\0", b"ABCDEFGHIJKLMNOPQRST\0"].concat();
+    empty_handshake.step(&mut empty_buffer_set)?;
+    check_eq!(&empty_buffer_set.write_buffer()[4..], &[0x00]);
+    Ok(())
+}
+
+#[test]
+fn client_ed25519_after_auth_switch() -> crate::error::Result<()> {
+    let opts = Opts {
+        password: "secret".to_string(),
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    let mut handshake = start_with_plugin(&opts, &mut buffer_set, b"mysql_native_password")?;
+    handshake.step(&mut buffer_set)?;
+    check_eq!(handshake.auth_plugin(&buffer_set), "mysql_native_password");
+
+    // The 32-byte nonce is not null-terminated, so a trailing zero belongs to the nonce
+    let nonce = *b"0123456789abcdef0123456789abcde\0";
+    buffer_set.read_buffer = [&[0xFE][..], b"client_ed25519\0", &nonce].concat();
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::WritePacket { .. }
+    ));
+    check_eq!(
+        &buffer_set.write_buffer()[4..],
+        &auth_client_ed25519("secret", &nonce)[..]
+    );
+
+    buffer_set.read_buffer = vec![0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00];
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::Finished
+    ));
+    check_eq!(handshake.auth_plugin(&buffer_set), "client_ed25519");
+    Ok(())
+}
+
+#[test]
+fn change_user_with_caching_sha2_fast_auth() -> crate::error::Result<()> {
+    let opts = Opts {
+        user: "tenant".to_string(),
+        password: "secret".to_string(),
+        db: Some("tenant_db".to_string()),
+        ..Opts::default()
+    };
+    let mut buffer_set = BufferSet::new();
+    buffer_set.initial_handshake = initial_handshake(b"caching_sha2_password");
+    let initial = read_initial_handshake(&buffer_set.initial_handshake)?;
+    let scramble = initial.auth_plugin_data.clone();
+    let caps = CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_PLUGIN_AUTH;
+    let mut handshake = Handshake::change_user(
+        &opts,
+        initial,
+        caps,
+        crate::constant::MariadbCapabilityFlags::empty(),
+    );
+
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::WritePacket { sequence_id: 0 }
+    ));
+    let auth_response = auth_caching_sha2_password("secret", &scramble);
+    let expected = [
+        &[0x11][..],
+        b"tenant\0",
+        &[32],
+        &auth_response,
+        b"tenant_db\0",
+        &[45, 0],
+        b"caching_sha2_password\0",
+    ]
+    .concat();
+    check_eq!(&buffer_set.write_buffer()[4..], &expected[..]);
+
+    // Fast auth success, then OK
+    buffer_set.read_buffer = vec![0x01, 0x03];
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::ReadPacket(_)
+    ));
+    buffer_set.read_buffer = vec![0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00];
+    check!(matches!(
+        handshake.step(&mut buffer_set)?,
+        HandshakeAction::Finished
+    ));
+    let (_, negotiated, _) = handshake.finish()?;
+    check_eq!(negotiated, caps);
+    Ok(())
+}
+
+#[test]
+fn strict_capabilities_rejects_downgrade() -> crate::error::Result<()> {
+    for strict_capabilities in [false, true] {
+        let opts = Opts {
+            dialect: Some(crate::Dialect::Vitess),
+            session_track_system_variables: Some("autocommit".to_string()),
+            strict_capabilities,
+            ..Opts::default()
+        };
+        let mut buffer_set = BufferSet::new();
+        let mut handshake = start(&opts, &mut buffer_set)?;
+        let result = handshake.step(&mut buffer_set);
+        check_eq!(
+            matches!(result, Err(crate::error::Error::Unsupported(_))),
+            strict_capabilities
+        );
+        if !strict_capabilities {
+            check_eq!(handshake.negotiation_report().downgrades().len(), 1);
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,12 @@
+mod ed25519;
 mod handshake;
 
 pub use handshake::AuthSwitchRequest;
 pub use handshake::Handshake;
 pub use handshake::HandshakeAction;
 pub use handshake::InitialHandshake;
+
+#[cfg(test)]
+mod ed25519_test;
+#[cfg(test)]
+mod handshake_test;
@@ -1,10 +1,17 @@
 pub mod command;
+pub mod compression;
 pub mod connection;
 pub mod packet;
 pub mod primitive;
 pub mod response;
 mod row;
 pub mod r#trait;
+pub mod validate;
 
 pub use row::{BinaryRowPayload, TextRowPayload};
-pub use r#trait::{BinaryResultSetHandler, RowDecoder};
+pub use r#trait::{BinaryResultSetHandler, Command, RowDecoder};
+
+#[cfg(test)]
+mod compression_test;
+#[cfg(test)]
+mod validate_test;
@@ -168,6 +168,20 @@ pub fn write_int_lenenc(out: &mut Vec<u8>, value: u64) {
     }
 }
 
+/// Number of bytes `write_int_lenenc` writes for `value`
+#[inline]
+pub fn lenenc_len(value: u64) -> usize {
+    if value < 251 {
+        1
+    } else if value < (1 << 16) {
+        3
+    } else if value < (1 << 24) {
+        4
+    } else {
+        9
+    }
+}
+
 /// Write fixed-length bytes
 #[inline]
 pub fn write_bytes_fix(out: &mut Vec<u8>, data: &[u8]) {
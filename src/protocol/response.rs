@@ -32,8 +32,78 @@ pub struct OkPayload {
     pub last_insert_id: u64,
     pub status_flags: ServerStatusFlags,
     pub warnings: u16,
-    // pub info: String, // SERVER_SESSION_STATE_CHANGED
-    // pub session_state_info: String, // SERVER_SESSION_STATE_CHANGED
+    session_state: Vec<SessionStateChange>,
+}
+
+impl OkPayload {
+    /// Session state changes reported with `SERVER_SESSION_STATE_CHANGED` (`CLIENT_SESSION_TRACK`).
+    ///
+    /// Which changes are reported depends on the server's `session_track_*` variables.
+    pub fn session_state(&self) -> &[SessionStateChange] {
+        &self.session_state
+    }
+}
+
+/// A session state change from an OK packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStateChange {
+    /// `SESSION_TRACK_SYSTEM_VARIABLES`: a tracked system variable was set
+    SystemVariable { name: String, value: String },
+    /// `SESSION_TRACK_SCHEMA`: the default database changed
+    Schema(String),
+    /// `SESSION_TRACK_STATE_CHANGE`: any session state changed (`session_track_state_change`)
+    StateChanged(bool),
+    /// `SESSION_TRACK_GTIDS`: GTIDs of the committed transaction
+    Gtids(String),
+    /// `SESSION_TRACK_TRANSACTION_CHARACTERISTICS`: SQL to restore the transaction characteristics
+    TransactionCharacteristics(String),
+    /// `SESSION_TRACK_TRANSACTION_STATE`: the 8-character transaction state, e.g. `T_______`
+    TransactionState(String),
+    /// A type this library does not know
+    Unknown { kind: u8, data: Vec<u8> },
+}
+
+impl SessionStateChange {
+    /// Parse the session-state-info of an OK packet.
+    pub fn parse_all(mut data: &[u8]) -> Result<Vec<Self>> {
+        let mut changes = Vec::new();
+        while !data.is_empty() {
+            let (kind, rest) = read_int_1(data)?;
+            let (entry, rest) = read_string_lenenc(rest)?;
+            changes.push(Self::parse(kind, entry)?);
+            data = rest;
+        }
+        Ok(changes)
+    }
+
+    fn parse(kind: u8, entry: &[u8]) -> Result<Self> {
+        let string = |data: &[u8]| -> Result<String> {
+            let (value, _rest) = read_string_lenenc(data)?;
+            Ok(String::from_utf8_lossy(value).into_owned())
+        };
+        Ok(match kind {
+            0 => {
+                let (name, rest) = read_string_lenenc(entry)?;
+                SessionStateChange::SystemVariable {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    value: string(rest)?,
+                }
+            }
+            1 => SessionStateChange::Schema(string(entry)?),
+            2 => SessionStateChange::StateChanged(string(entry)? == "1"),
+            3 => {
+                // [encoding specification: 1][gtids: lenenc]
+                let (_encoding, rest) = read_int_1(entry)?;
+                SessionStateChange::Gtids(string(rest)?)
+            }
+            4 => SessionStateChange::TransactionCharacteristics(string(entry)?),
+            5 => SessionStateChange::TransactionState(string(entry)?),
+            _ => SessionStateChange::Unknown {
+                kind,
+                data: entry.to_vec(),
+            },
+        })
+    }
 }
 
 impl TryFrom<OkPayloadBytes<'_>> for OkPayload {
@@ -48,18 +118,46 @@ impl TryFrom<OkPayloadBytes<'_>> for OkPayload {
             )));
         }
 
+        // Without CLIENT_DEPRECATE_EOF, result sets end with a 5-byte EOF packet.
+        // An OK packet with the 0xFE header is at least 7 bytes.
+        if header == 0xFE && data.len() == 4 {
+            let (warnings, data) = read_int_2(data)?;
+            let (status_flags, _data) = read_int_2(data)?;
+            return Ok(OkPayload {
+                affected_rows: 0,
+                last_insert_id: 0,
+                status_flags: ServerStatusFlags::from_bits_truncate(status_flags),
+                warnings,
+                session_state: Vec::new(),
+            });
+        }
+
         let (affected_rows, data) = read_int_lenenc(data)?;
         let (last_insert_id, data) = read_int_lenenc(data)?;
         let (status_flags, data) = read_int_2(data)?;
-        let (warnings, _data) = read_int_2(data)?;
+        let (warnings, data) = read_int_2(data)?;
+        let status_flags = ServerStatusFlags::from_bits_truncate(status_flags);
 
-        // TODO: Supports SERVER_SESSION_STATE_CHANGED
+        // The server only sets SERVER_SESSION_STATE_CHANGED with CLIENT_SESSION_TRACK,
+        // in which case the info is a lenenc string followed by the session state.
+        // Tolerate MySQL-compatible servers that set the flag but omit both fields.
+        let session_state = if status_flags
+            .contains(ServerStatusFlags::SERVER_SESSION_STATE_CHANGED)
+            && !data.is_empty()
+        {
+            let (_info, data) = read_string_lenenc(data)?;
+            let (session_state_info, _data) = read_string_lenenc(data)?;
+            SessionStateChange::parse_all(session_state_info)?
+        } else {
+            Vec::new()
+        };
 
         Ok(OkPayload {
             affected_rows,
             last_insert_id,
-            status_flags: ServerStatusFlags::from_bits_truncate(status_flags),
+            status_flags,
             warnings,
+            session_state,
         })
     }
 }
@@ -139,9 +237,103 @@ pub fn read_eof_packet(payload: &[u8]) -> Result<&EofPacket> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_macros::{check, check_eq};
 
     #[test]
     fn eof_packet_has_alignment_of_1() {
         assert_eq!(std::mem::align_of::<EofPacket>(), 1);
     }
+
+    /// An OK packet with `status_flags` followed by `rest`
+    fn ok_packet(status_flags: ServerStatusFlags, rest: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x00, 0x00, 0x00];
+        write_int_2(&mut packet, status_flags.bits());
+        write_int_2(&mut packet, 0);
+        packet.extend_from_slice(rest);
+        packet
+    }
+
+    #[test]
+    fn ok_packet_with_session_state() -> Result<()> {
+        let mut variable = Vec::new();
+        write_string_lenenc(&mut variable, "autocommit");
+        write_string_lenenc(&mut variable, "OFF");
+        let mut gtids = vec![0x00];
+        write_string_lenenc(&mut gtids, "3E11FA47-71CA-11E1-9E33-C80AA9429562:23");
+
+        let mut state = Vec::new();
+        for (kind, data) in [
+            (0, variable),
+            (1, b"\x04test".to_vec()),
+            (2, b"\x011".to_vec()),
+            (3, gtids),
+            (5, b"\x08T_______".to_vec()),
+            (9, b"?".to_vec()),
+        ] {
+            write_int_1(&mut state, kind);
+            write_bytes_lenenc(&mut state, &data);
+        }
+        let mut rest = vec![0x00]; // empty info
+        write_bytes_lenenc(&mut rest, &state);
+
+        let packet = ok_packet(
+            ServerStatusFlags::SERVER_STATUS_AUTOCOMMIT
+                | ServerStatusFlags::SERVER_SESSION_STATE_CHANGED,
+            &rest,
+        );
+        let ok = OkPayload::try_from(OkPayloadBytes(&packet))?;
+        check_eq!(
+            ok.session_state(),
+            [
+                SessionStateChange::SystemVariable {
+                    name: "autocommit".to_string(),
+                    value: "OFF".to_string(),
+                },
+                SessionStateChange::Schema("test".to_string()),
+                SessionStateChange::StateChanged(true),
+                SessionStateChange::Gtids("3E11FA47-71CA-11E1-9E33-C80AA9429562:23".to_string()),
+                SessionStateChange::TransactionState("T_______".to_string()),
+                SessionStateChange::Unknown {
+                    kind: 9,
+                    data: b"?".to_vec(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ok_packet_with_missing_session_state() -> Result<()> {
+        let packet = ok_packet(ServerStatusFlags::SERVER_SESSION_STATE_CHANGED, b"");
+        let ok = OkPayload::try_from(OkPayloadBytes(&packet))?;
+        check!(ok.session_state().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ok_packet_without_session_state() -> Result<()> {
+        // Without SERVER_SESSION_STATE_CHANGED, trailing info is not session state
+        let packet = ok_packet(
+            ServerStatusFlags::SERVER_STATUS_AUTOCOMMIT,
+            b"Rows matched: 1",
+        );
+        let ok = OkPayload::try_from(OkPayloadBytes(&packet))?;
+        check!(ok.session_state().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_eof_packet() -> Result<()> {
+        // [0xFE][warnings: 1][status: SERVER_MORE_RESULTS_EXISTS | SERVER_STATUS_AUTOCOMMIT]
+        let packet = [0xFE, 0x01, 0x00, 0x0A, 0x00];
+        let eof = OkPayload::try_from(OkPayloadBytes(&packet))?;
+        check_eq!(eof.warnings, 1);
+        check_eq!(eof.affected_rows, 0);
+        check_eq!(
+            eof.status_flags,
+            ServerStatusFlags::SERVER_MORE_RESULTS_EXISTS
+                | ServerStatusFlags::SERVER_STATUS_AUTOCOMMIT
+        );
+        Ok(())
+    }
 }
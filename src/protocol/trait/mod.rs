@@ -1,7 +1,9 @@
 pub mod param;
 
+use crate::arena::ScratchArena;
+use crate::buffer::BufferSet;
 use crate::error::Result;
-use crate::protocol::command::ColumnDefinition;
+use crate::protocol::command::{Action, ColumnDefinition};
 use crate::protocol::response::OkPayloadBytes;
 use crate::protocol::{BinaryRowPayload, TextRowPayload};
 
@@ -29,6 +31,18 @@ pub trait BinaryResultSetHandler {
     fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()>;
     fn row(&mut self, cols: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<()>;
     fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()>;
+
+    /// Called instead of `row` when `Opts::scratch_arena` is enabled.
+    ///
+    /// Values allocated in `arena` are freed when the next result set starts.
+    fn row_with_arena(
+        &mut self,
+        cols: &[ColumnDefinition<'_>],
+        row: BinaryRowPayload<'_>,
+        _arena: &ScratchArena,
+    ) -> Result<()> {
+        self.row(cols, row)
+    }
 }
 
 /// Trait that defines event callbacks for text protocol result sets
@@ -39,5 +53,64 @@ pub trait TextResultSetHandler {
     fn resultset_end(&mut self, eof: OkPayloadBytes) -> Result<()>;
 }
 
+/// Async variant of [`BinaryResultSetHandler`] for the tokio backend.
+///
+/// The connection awaits each callback before it reads the next packet from the socket,
+/// so a handler that waits on a bounded channel or a file write slows down the server (backpressure)
+/// instead of blocking the runtime or buffering rows without limit.
+///
+/// Used by [`tokio::Conn::exec_with_backpressure`](crate::tokio::Conn::exec_with_backpressure).
+pub trait AsyncBinaryResultSetHandler {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> impl Future<Output = Result<()>> + Send;
+    fn resultset_start(
+        &mut self,
+        cols: &[ColumnDefinition<'_>],
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn row(
+        &mut self,
+        cols: &[ColumnDefinition<'_>],
+        row: BinaryRowPayload<'_>,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Async variant of [`TextResultSetHandler`] for the tokio backend.
+///
+/// See [`AsyncBinaryResultSetHandler`].
+/// Used by [`tokio::Conn::query_with_backpressure`](crate::tokio::Conn::query_with_backpressure).
+pub trait AsyncTextResultSetHandler {
+    fn no_result_set(&mut self, ok: OkPayloadBytes) -> impl Future<Output = Result<()>> + Send;
+    fn resultset_start(
+        &mut self,
+        cols: &[ColumnDefinition<'_>],
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn row(
+        &mut self,
+        cols: &[ColumnDefinition<'_>],
+        row: TextRowPayload<'_>,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn resultset_end(&mut self, eof: OkPayloadBytes) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// A protocol command that `Conn` has no method for, such as a `COM_BINLOG_DUMP` variant
+/// or a vendor extension.
+///
+/// `Conn::run_command` writes the payload from `encode`, then calls `step` until it returns
+/// [`Action::Finished`], reading the next packet into the buffer of every [`Action::NeedPacket`]
+/// and the column definitions of every [`Action::ReadColumnMetadata`] into
+/// `BufferSet::column_definition_buffer`. [`Action::LocalInfile`] is not supported.
+///
+/// The command must read every packet of the response, including ERR packets, which the
+/// connection does not check. Results are kept in the command itself.
+/// When `BufferSet::legacy_eof` is set, the EOF packet after column definitions is also
+/// read by the command.
+pub trait Command {
+    /// Write the command payload, starting with the command byte.
+    fn encode(&mut self, out: &mut Vec<u8>) -> Result<()>;
+
+    /// Advance the state machine. The first call is made before any packet is read.
+    fn step<'buf>(&mut self, buffer_set: &'buf mut BufferSet) -> Result<Action<'buf>>;
+}
+
 #[cfg(test)]
 mod param_test;
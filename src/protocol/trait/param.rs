@@ -3,6 +3,7 @@ use auto_impl::auto_impl;
 use crate::constant::ColumnType;
 use crate::error::Result;
 use crate::protocol::primitive::*;
+use crate::value::BitValue;
 
 /// Parameter indicator for COM_STMT_BULK_EXECUTE
 ///
@@ -26,12 +27,32 @@ pub trait Param {
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()>;
 }
 
+impl<T: TypedParam> Param for T {
+    fn is_null(&self) -> bool {
+        TypedParam::is_null(self)
+    }
+    fn encode_type(&self, out: &mut Vec<u8>) {
+        T::encode_type(out);
+    }
+    fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
+        TypedParam::encode_value(self, out)
+    }
+}
+
 pub trait TypedParam {
     fn is_null(&self) -> bool {
         false
     }
     fn encode_type(out: &mut Vec<u8>);
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()>;
+
+    /// Number of bytes written by `encode_value`
+    ///
+    /// Used to reserve the write buffer once before encoding.
+    /// If the size is only known after encoding, return a lower bound.
+    fn encoded_len(&self) -> usize {
+        0
+    }
 }
 
 impl TypedParam for bool {
@@ -40,6 +61,10 @@ impl TypedParam for bool {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_1(out, u8::from(*self));
         Ok(())
@@ -52,6 +77,10 @@ impl TypedParam for i8 {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_1(out, *self as u8);
         Ok(())
@@ -64,6 +93,10 @@ impl TypedParam for i16 {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        2
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_2(out, *self as u16);
         Ok(())
@@ -76,6 +109,10 @@ impl TypedParam for i32 {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        4
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_4(out, *self as u32);
         Ok(())
@@ -88,6 +125,10 @@ impl TypedParam for i64 {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        8
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_8(out, *self as u64);
         Ok(())
@@ -100,6 +141,10 @@ impl TypedParam for u8 {
         out.push(0x80);
     }
 
+    fn encoded_len(&self) -> usize {
+        1
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_1(out, *self);
         Ok(())
@@ -112,6 +157,10 @@ impl TypedParam for u16 {
         out.push(0x80);
     }
 
+    fn encoded_len(&self) -> usize {
+        2
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_2(out, *self);
         Ok(())
@@ -124,6 +173,10 @@ impl TypedParam for u32 {
         out.push(0x80);
     }
 
+    fn encoded_len(&self) -> usize {
+        4
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_4(out, *self);
         Ok(())
@@ -136,18 +189,43 @@ impl TypedParam for u64 {
         out.push(0x80);
     }
 
+    fn encoded_len(&self) -> usize {
+        8
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_8(out, *self);
         Ok(())
     }
 }
 
+/// Sent as a BIGINT UNSIGNED, e.g. for a `BIT(n)` column
+impl TypedParam for BitValue {
+    fn encode_type(out: &mut Vec<u8>) {
+        out.push(ColumnType::MYSQL_TYPE_LONGLONG as u8);
+        out.push(0x80);
+    }
+
+    fn encoded_len(&self) -> usize {
+        8
+    }
+
+    fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
+        write_int_8(out, self.0);
+        Ok(())
+    }
+}
+
 impl TypedParam for f32 {
     fn encode_type(out: &mut Vec<u8>) {
         out.push(ColumnType::MYSQL_TYPE_FLOAT as u8);
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        4
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_4(out, self.to_bits());
         Ok(())
@@ -160,6 +238,10 @@ impl TypedParam for f64 {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        8
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_int_8(out, self.to_bits());
         Ok(())
@@ -172,6 +254,10 @@ impl TypedParam for &str {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        lenenc_len(self.len() as u64) + self.len()
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_string_lenenc(out, self);
         Ok(())
@@ -184,6 +270,10 @@ impl TypedParam for String {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        lenenc_len(self.len() as u64) + self.len()
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_string_lenenc(out, self);
         Ok(())
@@ -196,6 +286,10 @@ impl TypedParam for &String {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        lenenc_len(self.len() as u64) + self.len()
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_string_lenenc(out, self);
         Ok(())
@@ -208,6 +302,10 @@ impl TypedParam for &[u8] {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        lenenc_len(self.len() as u64) + self.len()
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_bytes_lenenc(out, self);
         Ok(())
@@ -220,6 +318,10 @@ impl TypedParam for Vec<u8> {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        lenenc_len(self.len() as u64) + self.len()
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_bytes_lenenc(out, self);
         Ok(())
@@ -232,12 +334,56 @@ impl TypedParam for &Vec<u8> {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        lenenc_len(self.len() as u64) + self.len()
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         write_bytes_lenenc(out, self);
         Ok(())
     }
 }
 
+/// Sent as a BLOB, e.g. `uuid.as_bytes()` for a `BINARY(16)` column
+impl<const N: usize> TypedParam for &[u8; N] {
+    fn encode_type(out: &mut Vec<u8>) {
+        out.push(ColumnType::MYSQL_TYPE_BLOB as u8);
+        out.push(0x00);
+    }
+
+    fn encoded_len(&self) -> usize {
+        lenenc_len(N as u64) + N
+    }
+
+    fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
+        write_bytes_lenenc(out, self.as_slice());
+        Ok(())
+    }
+}
+
+/// A BLOB parameter whose value was already sent with `Conn::send_long_data`.
+///
+/// No value is written to `COM_STMT_EXECUTE` for this parameter;
+/// the server uses the data it received with `COM_STMT_SEND_LONG_DATA`.
+/// Not supported by `exec_bulk_insert_or_update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LongData;
+
+impl TypedParam for LongData {
+    fn encode_type(out: &mut Vec<u8>) {
+        out.push(ColumnType::MYSQL_TYPE_LONG_BLOB as u8);
+        out.push(0x00);
+    }
+
+    fn encoded_len(&self) -> usize {
+        0
+    }
+
+    fn encode_value(&self, _out: &mut Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+}
+
 impl<T: TypedParam> TypedParam for Option<T> {
     fn is_null(&self) -> bool {
         self.is_none()
@@ -253,6 +399,10 @@ impl<T: TypedParam> TypedParam for Option<T> {
             None => Ok(()),
         }
     }
+
+    fn encoded_len(&self) -> usize {
+        self.as_ref().map_or(0, T::encoded_len)
+    }
 }
 
 // ============================================================================
@@ -297,6 +447,15 @@ pub trait Params {
     ///
     /// See: https://mariadb.com/docs/server/reference/clientserver-protocol/3-binary-protocol-prepared-statements/com_stmt_bulk_execute
     fn encode_values_for_bulk(&self, out: &mut Vec<u8>) -> Result<()>;
+
+    /// Number of bytes written by `encode_values`
+    ///
+    /// `write_execute` reserves the whole packet once with this size,
+    /// so encoding never reallocates the write buffer.
+    /// If the size is only known after encoding, return a lower bound.
+    fn encoded_len(&self) -> usize {
+        0
+    }
 }
 
 #[auto_impl(&)]
@@ -309,6 +468,9 @@ pub trait TypedParams {
     fn encode_types(out: &mut Vec<u8>);
     fn encode_values(&self, out: &mut Vec<u8>) -> Result<()>;
     fn encode_values_for_bulk(&self, out: &mut Vec<u8>) -> Result<()>;
+    fn encoded_len(&self) -> usize {
+        0
+    }
 }
 
 impl<T: TypedParams> Params for T {
@@ -327,6 +489,9 @@ impl<T: TypedParams> Params for T {
     fn encode_values_for_bulk(&self, out: &mut Vec<u8>) -> Result<()> {
         TypedParams::encode_values_for_bulk(self, out)
     }
+    fn encoded_len(&self) -> usize {
+        TypedParams::encoded_len(self)
+    }
 }
 
 impl TypedParams for () {
@@ -399,6 +564,10 @@ macro_rules! impl_params_for_tuple {
                 )+
                 Ok(())
             }
+
+            fn encoded_len(&self) -> usize {
+                0 $(+ self.$idx.encoded_len())+
+            }
         }
     };
 }
@@ -466,6 +635,10 @@ impl<T: TypedParam> Params for [T] {
         }
         Ok(())
     }
+
+    fn encoded_len(&self) -> usize {
+        self.iter().map(T::encoded_len).sum()
+    }
 }
 
 impl<T: TypedParam> Params for &[T] {
@@ -488,6 +661,10 @@ impl<T: TypedParam> Params for &[T] {
     fn encode_values_for_bulk(&self, out: &mut Vec<u8>) -> Result<()> {
         <[T] as Params>::encode_values_for_bulk(self, out)
     }
+
+    fn encoded_len(&self) -> usize {
+        <[T] as Params>::encoded_len(self)
+    }
 }
 
 impl<T: TypedParam> Params for Vec<T> {
@@ -510,6 +687,10 @@ impl<T: TypedParam> Params for Vec<T> {
     fn encode_values_for_bulk(&self, out: &mut Vec<u8>) -> Result<()> {
         self.as_slice().encode_values_for_bulk(out)
     }
+
+    fn encoded_len(&self) -> usize {
+        self.as_slice().encoded_len()
+    }
 }
 
 impl<T: TypedParam> Params for &Vec<T> {
@@ -532,6 +713,10 @@ impl<T: TypedParam> Params for &Vec<T> {
     fn encode_values_for_bulk(&self, out: &mut Vec<u8>) -> Result<()> {
         self.as_slice().encode_values_for_bulk(out)
     }
+
+    fn encoded_len(&self) -> usize {
+        self.as_slice().encoded_len()
+    }
 }
 
 // ============================================================================
@@ -545,9 +730,13 @@ impl TypedParam for uuid::Uuid {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        1 + uuid::fmt::Hyphenated::LENGTH
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
-        let s = self.as_hyphenated().to_string();
-        write_string_lenenc(out, &s);
+        let mut buf = [0; uuid::fmt::Hyphenated::LENGTH];
+        write_string_lenenc(out, self.as_hyphenated().encode_lower(&mut buf));
         Ok(())
     }
 }
@@ -559,9 +748,13 @@ impl TypedParam for &uuid::Uuid {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        1 + uuid::fmt::Hyphenated::LENGTH
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
-        let s = self.as_hyphenated().to_string();
-        write_string_lenenc(out, &s);
+        let mut buf = [0; uuid::fmt::Hyphenated::LENGTH];
+        write_string_lenenc(out, self.as_hyphenated().encode_lower(&mut buf));
         Ok(())
     }
 }
@@ -580,6 +773,10 @@ impl TypedParam for chrono::NaiveDate {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        5
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         out.push(4); // length
         write_int_2(out, u16::try_from(self.year()).unwrap_or(0));
@@ -596,6 +793,10 @@ impl TypedParam for chrono::NaiveTime {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        if self.nanosecond() >= 1000 { 13 } else { 9 }
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         let micros = self.nanosecond() / 1000;
         if micros > 0 {
@@ -625,6 +826,14 @@ impl TypedParam for chrono::NaiveDateTime {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        if self.and_utc().timestamp_subsec_micros() > 0 {
+            12
+        } else {
+            8
+        }
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         let micros = self.and_utc().timestamp_subsec_micros();
         if micros > 0 {
@@ -649,6 +858,22 @@ impl TypedParam for chrono::NaiveDateTime {
     }
 }
 
+/// Sent as a DATETIME in UTC
+#[cfg(feature = "with-chrono")]
+impl TypedParam for chrono::DateTime<chrono::Utc> {
+    fn encode_type(out: &mut Vec<u8>) {
+        <chrono::NaiveDateTime as TypedParam>::encode_type(out);
+    }
+
+    fn encoded_len(&self) -> usize {
+        TypedParam::encoded_len(&self.naive_utc())
+    }
+
+    fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
+        TypedParam::encode_value(&self.naive_utc(), out)
+    }
+}
+
 // ============================================================================
 // time crate support
 // ============================================================================
@@ -660,6 +885,10 @@ impl TypedParam for time::Date {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        5
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         out.push(4); // length
         write_int_2(out, u16::try_from(self.year()).unwrap_or(0));
@@ -676,6 +905,10 @@ impl TypedParam for time::Time {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        if self.microsecond() > 0 { 13 } else { 9 }
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         let micros = self.microsecond();
         if micros > 0 {
@@ -705,6 +938,10 @@ impl TypedParam for time::PrimitiveDateTime {
         out.push(0x00);
     }
 
+    fn encoded_len(&self) -> usize {
+        if self.microsecond() > 0 { 12 } else { 8 }
+    }
+
     fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
         let micros = self.microsecond();
         if micros > 0 {
@@ -729,6 +966,28 @@ impl TypedParam for time::PrimitiveDateTime {
     }
 }
 
+/// Sent as a DATETIME in UTC
+#[cfg(feature = "with-time")]
+impl TypedParam for time::OffsetDateTime {
+    fn encode_type(out: &mut Vec<u8>) {
+        <time::PrimitiveDateTime as TypedParam>::encode_type(out);
+    }
+
+    fn encoded_len(&self) -> usize {
+        TypedParam::encoded_len(&utc_datetime(self))
+    }
+
+    fn encode_value(&self, out: &mut Vec<u8>) -> Result<()> {
+        TypedParam::encode_value(&utc_datetime(self), out)
+    }
+}
+
+#[cfg(feature = "with-time")]
+fn utc_datetime(datetime: &time::OffsetDateTime) -> time::PrimitiveDateTime {
+    let utc = datetime.to_offset(time::UtcOffset::UTC);
+    time::PrimitiveDateTime::new(utc.date(), utc.time())
+}
+
 // ============================================================================
 // rust_decimal support
 // ============================================================================
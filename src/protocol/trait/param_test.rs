@@ -1,5 +1,6 @@
 use crate::constant::ColumnType;
-use crate::protocol::r#trait::param::{Params, TypedParam};
+use crate::protocol::command::prepared::{write_execute, write_send_long_data};
+use crate::protocol::r#trait::param::{LongData, Params, TypedParam};
 use crate::test_macros::{check, check_eq};
 
 #[test]
@@ -328,3 +329,309 @@ fn params_slice_strings() -> crate::error::Result<()> {
     check_eq!(out.len(), 12);
     Ok(())
 }
+
+#[test]
+fn long_data_param_has_no_value() -> crate::error::Result<()> {
+    let mut out = Vec::new();
+    write_execute(&mut out, 7, (5_i8, LongData))?;
+
+    // command, statement id, flags, iteration count
+    check_eq!(out[..10], [0x17, 7, 0, 0, 0, 0x00, 1, 0, 0, 0]);
+    // null bitmap, new-params-bound flag, types, then only the TINY value
+    check_eq!(
+        out[10..],
+        [
+            0x00,
+            0x01,
+            ColumnType::MYSQL_TYPE_TINY as u8,
+            0x00,
+            ColumnType::MYSQL_TYPE_LONG_BLOB as u8,
+            0x00,
+            5,
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn send_long_data_packet() -> crate::error::Result<()> {
+    let mut out = Vec::new();
+    write_send_long_data(&mut out, 7, 1, b"blob");
+    check_eq!(out, [0x18, 7, 0, 0, 0, 1, 0, b'b', b'l', b'o', b'b']);
+    Ok(())
+}
+
+/// `encoded_len` must match the bytes written by `encode_value`
+fn check_encoded_len<T: TypedParam>(param: T) -> crate::error::Result<()> {
+    let mut values = Vec::new();
+    param.encode_value(&mut values)?;
+    check_eq!(param.encoded_len(), values.len());
+    Ok(())
+}
+
+#[test]
+fn encoded_len_matches_encode_value() -> crate::error::Result<()> {
+    check_encoded_len(true)?;
+    check_encoded_len(-1_i8)?;
+    check_encoded_len(1_u16)?;
+    check_encoded_len(1_i32)?;
+    check_encoded_len(1.5_f32)?;
+    check_encoded_len(1_u64)?;
+    check_encoded_len(1.5_f64)?;
+    check_encoded_len("")?;
+    check_encoded_len("a".repeat(250))?;
+    check_encoded_len("a".repeat(251))?;
+    check_encoded_len(vec![0_u8; 1 << 16])?;
+    check_encoded_len(&[0_u8; 3][..])?;
+    check_encoded_len(&[0_u8; 16])?;
+    check_encoded_len(Some(7_i64))?;
+    check_encoded_len(None::<String>)?;
+    check_encoded_len(LongData)?;
+    Ok(())
+}
+
+#[test]
+fn write_execute_reserves_exact_size() -> crate::error::Result<()> {
+    let mut out = Vec::new();
+    write_execute(
+        &mut out,
+        1,
+        (1_i32, "hello".repeat(100), None::<i64>, 2.5_f64),
+    )?;
+    check_eq!(out.capacity(), out.len());
+
+    let mut slice_out = Vec::new();
+    write_execute(&mut slice_out, 1, vec!["x".repeat(300); 20])?;
+    check_eq!(slice_out.capacity(), slice_out.len());
+    Ok(())
+}
+
+#[cfg(all(feature = "with-chrono", feature = "with-time", feature = "with-uuid"))]
+#[test]
+fn encoded_len_matches_encode_value_for_external_types() -> crate::error::Result<()> {
+    let date = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap_or_default();
+    check_encoded_len(date)?;
+    check_encoded_len(date.and_hms_opt(1, 2, 3).unwrap_or_default())?;
+    check_encoded_len(date.and_hms_micro_opt(1, 2, 3, 4).unwrap_or_default())?;
+    check_encoded_len(chrono::NaiveTime::from_hms_micro_opt(1, 2, 3, 4).unwrap_or_default())?;
+    check_encoded_len(chrono::NaiveTime::from_hms_nano_opt(1, 2, 3, 999).unwrap_or_default())?;
+    check_encoded_len(
+        date.and_hms_micro_opt(1, 2, 3, 4)
+            .unwrap_or_default()
+            .and_utc(),
+    )?;
+    check_encoded_len(time::Time::MIDNIGHT)?;
+    check_encoded_len(time::PrimitiveDateTime::MIN)?;
+    check_encoded_len(time::OffsetDateTime::UNIX_EPOCH)?;
+    check_encoded_len(uuid::Uuid::nil())?;
+    Ok(())
+}
+
+/// Encode `param` as an execute parameter and decode it as a result set value of the same type
+#[cfg(any(feature = "with-chrono", feature = "with-time", feature = "with-uuid"))]
+fn round_trip<T>(param: &T) -> crate::error::Result<T>
+where
+    T: TypedParam + for<'a> crate::raw::FromRawValue<'a>,
+{
+    use zerocopy::FromBytes;
+
+    let mut types = Vec::new();
+    T::encode_type(&mut types);
+    let mut values = Vec::new();
+    param.encode_value(&mut values)?;
+
+    let mut tail = [0_u8; 12];
+    tail[6] = types[0];
+    let col = crate::protocol::command::ColumnDefinitionTail::ref_from_bytes(&tail)?;
+    let (decoded, rest) = crate::raw::parse_value::<T>(col, false, &values)?;
+    check!(rest.is_empty());
+    Ok(decoded)
+}
+
+#[cfg(feature = "with-chrono")]
+#[test]
+fn chrono_round_trip() -> crate::error::Result<()> {
+    use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+    let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap_or_default();
+    check_eq!(round_trip(&date)?, date);
+
+    // DATETIME(6) sends 11 bytes with microseconds and 7 bytes without
+    let micros = date
+        .and_hms_micro_opt(23, 59, 58, 123_456)
+        .unwrap_or_default();
+    let seconds = date.and_hms_opt(23, 59, 58).unwrap_or_default();
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    check_eq!(round_trip(&micros)?, micros);
+    check_eq!(round_trip(&seconds)?, seconds);
+    check_eq!(round_trip(&midnight)?, midnight);
+
+    let utc: DateTime<Utc> = micros.and_utc();
+    check_eq!(round_trip(&utc)?, utc);
+    check_eq!(round_trip(&seconds.and_utc())?, seconds.and_utc());
+
+    // TIME(6) sends 12 bytes with microseconds and 8 bytes without
+    let time_micros = NaiveTime::from_hms_micro_opt(12, 30, 45, 1).unwrap_or_default();
+    let time_seconds = NaiveTime::from_hms_opt(12, 30, 45).unwrap_or_default();
+    check_eq!(round_trip(&time_micros)?, time_micros);
+    check_eq!(round_trip(&time_seconds)?, time_seconds);
+    Ok(())
+}
+
+#[cfg(feature = "with-chrono")]
+#[test]
+fn chrono_utc_from_datetime_lengths() -> crate::error::Result<()> {
+    use chrono::{DateTime, NaiveDate, Utc};
+    use zerocopy::FromBytes;
+
+    let mut tail = [0_u8; 12];
+    tail[6] = ColumnType::MYSQL_TYPE_DATETIME as u8;
+    let col = crate::protocol::command::ColumnDefinitionTail::ref_from_bytes(&tail)?;
+    let date = NaiveDate::from_ymd_opt(2024, 12, 25).unwrap_or_default();
+
+    let (date_only, _) =
+        crate::raw::parse_value::<DateTime<Utc>>(col, false, &[4, 0xE8, 0x07, 12, 25])?;
+    check_eq!(
+        date_only,
+        date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+    );
+
+    check!(crate::raw::parse_value::<DateTime<Utc>>(col, false, &[0]).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "with-chrono")]
+#[test]
+fn chrono_time_rejects_days_and_negative() -> crate::error::Result<()> {
+    use zerocopy::FromBytes;
+
+    let mut tail = [0_u8; 12];
+    tail[6] = ColumnType::MYSQL_TYPE_TIME as u8;
+    let col = crate::protocol::command::ColumnDefinitionTail::ref_from_bytes(&tail)?;
+
+    let negative = [8, 1, 0, 0, 0, 0, 1, 2, 3];
+    check!(crate::raw::parse_value::<chrono::NaiveTime>(col, false, &negative).is_err());
+    let with_days = [12, 0, 1, 0, 0, 0, 1, 2, 3, 4, 0, 0, 0];
+    check!(crate::raw::parse_value::<chrono::NaiveTime>(col, false, &with_days).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "with-time")]
+#[test]
+fn time_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    use time::{Date, Month, PrimitiveDateTime, Time, UtcOffset};
+
+    let date = Date::from_calendar_date(2024, Month::February, 29)?;
+    check_eq!(round_trip(&date)?, date);
+
+    // DATETIME(6) sends 11 bytes with microseconds and 7 bytes without
+    let micros = PrimitiveDateTime::new(date, Time::from_hms_micro(23, 59, 58, 123_456)?);
+    let seconds = PrimitiveDateTime::new(date, Time::from_hms(23, 59, 58)?);
+    let midnight = PrimitiveDateTime::new(date, Time::MIDNIGHT);
+    check_eq!(round_trip(&micros)?, micros);
+    check_eq!(round_trip(&seconds)?, seconds);
+    check_eq!(round_trip(&midnight)?, midnight);
+
+    // Sent in UTC, decoded with a UTC offset
+    let utc = micros.assume_utc();
+    check_eq!(round_trip(&utc)?, utc);
+    let seoul = utc.to_offset(UtcOffset::from_hms(9, 0, 0)?);
+    let decoded = round_trip(&seoul)?;
+    check_eq!(decoded, seoul);
+    check_eq!(decoded.offset(), UtcOffset::UTC);
+
+    // TIME(6) sends 12 bytes with microseconds and 8 bytes without
+    let time_micros = Time::from_hms_micro(12, 30, 45, 1)?;
+    let time_seconds = Time::from_hms(12, 30, 45)?;
+    check_eq!(round_trip(&time_micros)?, time_micros);
+    check_eq!(round_trip(&time_seconds)?, time_seconds);
+    Ok(())
+}
+
+#[cfg(feature = "with-time")]
+#[test]
+fn time_offset_from_datetime_lengths() -> Result<(), Box<dyn std::error::Error>> {
+    use time::{Date, Month, OffsetDateTime};
+    use zerocopy::FromBytes;
+
+    let mut tail = [0_u8; 12];
+    tail[6] = ColumnType::MYSQL_TYPE_DATETIME as u8;
+    let col = crate::protocol::command::ColumnDefinitionTail::ref_from_bytes(&tail)
+        .map_err(crate::error::Error::from)?;
+    let date = Date::from_calendar_date(2024, Month::December, 25)?;
+
+    let (date_only, _) =
+        crate::raw::parse_value::<OffsetDateTime>(col, false, &[4, 0xE8, 0x07, 12, 25])?;
+    check_eq!(date_only, date.midnight().assume_utc());
+
+    check!(crate::raw::parse_value::<OffsetDateTime>(col, false, &[0]).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "with-uuid")]
+#[test]
+fn uuid_from_text_and_binary() -> crate::error::Result<()> {
+    use zerocopy::FromBytes;
+
+    let uuid = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+    check_eq!(round_trip(&uuid)?, uuid);
+
+    let mut tail = [0_u8; 12];
+    tail[..2].copy_from_slice(&63_u16.to_le_bytes());
+    tail[6] = ColumnType::MYSQL_TYPE_STRING as u8;
+    let binary = crate::protocol::command::ColumnDefinitionTail::ref_from_bytes(&tail)?;
+
+    // BINARY(16)
+    let mut raw = vec![16];
+    raw.extend_from_slice(uuid.as_bytes());
+    let (from_bytes, _) = crate::raw::parse_value::<uuid::Uuid>(binary, false, &raw)?;
+    check_eq!(from_bytes, uuid);
+
+    // BINARY(36) holding the text form
+    let mut text = vec![36];
+    text.extend_from_slice(uuid.hyphenated().to_string().as_bytes());
+    let (from_text, _) = crate::raw::parse_value::<uuid::Uuid>(binary, false, &text)?;
+    check_eq!(from_text, uuid);
+
+    let short = [4, 1, 2, 3, 4];
+    check!(crate::raw::parse_value::<uuid::Uuid>(binary, false, &short).is_err());
+    Ok(())
+}
+
+#[test]
+fn bit_columns() -> crate::error::Result<()> {
+    use crate::value::BitValue;
+    use zerocopy::FromBytes;
+
+    let mut tail = [0_u8; 12];
+    tail[..2].copy_from_slice(&63_u16.to_le_bytes());
+    tail[6] = ColumnType::MYSQL_TYPE_BIT as u8;
+    let bit = crate::protocol::command::ColumnDefinitionTail::ref_from_bytes(&tail)?;
+
+    // BIT(10) b'1000000101' is sent as 2 big-endian bytes
+    let raw = [2, 0b10, 0b0000_0101];
+    let (value, _) = crate::raw::parse_value::<BitValue>(bit, false, &raw)?;
+    check_eq!(value, BitValue(0b10_0000_0101));
+    check!(value.bit(0) && !value.bit(1) && value.bit(9) && !value.bit(64));
+    let (number, _) = crate::raw::parse_value::<u64>(bit, false, &raw)?;
+    check_eq!(number, 0b10_0000_0101);
+    let (bytes, _) = crate::raw::parse_value::<&[u8]>(bit, false, &raw)?;
+    check_eq!(bytes, &raw[1..]);
+
+    // BIT(1)
+    let (flag, _) = crate::raw::parse_value::<bool>(bit, false, &[1, 1])?;
+    check!(flag);
+    let (none, _) = crate::raw::parse_value::<Option<bool>>(bit, false, &[1, 0])?;
+    check_eq!(none, Some(false));
+
+    let wide = [9, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+    check!(crate::raw::parse_value::<u64>(bit, false, &wide).is_err());
+
+    let mut types = Vec::new();
+    BitValue::encode_type(&mut types);
+    check_eq!(types, [ColumnType::MYSQL_TYPE_LONGLONG as u8, 0x80]);
+    let mut values = Vec::new();
+    BitValue(0b101).encode_value(&mut values)?;
+    check_eq!(values, 5_u64.to_le_bytes());
+    Ok(())
+}
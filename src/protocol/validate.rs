@@ -0,0 +1,222 @@
+//! Eager validation of result set rows.
+//!
+//! Row values are normally decoded lazily, so a malformed packet from a buggy server or proxy
+//! surfaces as a confusing decode error (or not at all, if the column is never read).
+//! With the `debug-protocol` feature enabled, every row is validated before it reaches the
+//! handler: the column count, the null bitmap, the length of every value, and the encoding of
+//! every length-encoded integer. The error names the column and the byte offset.
+//!
+//! Without the feature, the checks compile to nothing.
+
+use crate::constant::ColumnType;
+use crate::error::{Error, Result, eyre};
+use crate::protocol::command::ColumnDefinition;
+
+/// Validate a binary protocol row packet (starting with the `0x00` header).
+pub fn validate_binary_row(cols: &[ColumnDefinition<'_>], payload: &[u8]) -> Result<()> {
+    let Some((&0x00, data)) = payload.split_first() else {
+        return Err(malformed(payload, 0, "binary row must start with 0x00"));
+    };
+    let null_bitmap_len = (cols.len() + 7 + 2) >> 3;
+    let (null_bitmap, mut values) = data.split_at_checked(null_bitmap_len).ok_or_else(|| {
+        malformed(
+            payload,
+            1,
+            format!(
+                "null bitmap of {} columns needs {null_bitmap_len} bytes",
+                cols.len()
+            ),
+        )
+    })?;
+    // The first 2 bits are reserved, and bits after the last column are unused
+    let used = |bit: usize| (2..cols.len() + 2).contains(&bit);
+    for bit in 0..null_bitmap_len * 8 {
+        if !used(bit) && null_bitmap[bit >> 3] & (1 << (bit & 7)) != 0 {
+            return Err(malformed(
+                payload,
+                1 + (bit >> 3),
+                format!("reserved null bitmap bit {bit} is set"),
+            ));
+        }
+    }
+
+    for (i, col) in cols.iter().enumerate() {
+        let bit = i + 2;
+        if null_bitmap[bit >> 3] & (1 << (bit & 7)) != 0 {
+            continue;
+        }
+        let offset = payload.len() - values.len();
+        let column_type = col.tail.column_type()?;
+        let len = match column_type {
+            ColumnType::MYSQL_TYPE_NULL => 0,
+            ColumnType::MYSQL_TYPE_TINY => 1,
+            ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => 2,
+            ColumnType::MYSQL_TYPE_INT24
+            | ColumnType::MYSQL_TYPE_LONG
+            | ColumnType::MYSQL_TYPE_FLOAT => 4,
+            ColumnType::MYSQL_TYPE_LONGLONG | ColumnType::MYSQL_TYPE_DOUBLE => 8,
+            ColumnType::MYSQL_TYPE_DATE
+            | ColumnType::MYSQL_TYPE_NEWDATE
+            | ColumnType::MYSQL_TYPE_DATETIME
+            | ColumnType::MYSQL_TYPE_TIMESTAMP
+            | ColumnType::MYSQL_TYPE_TIMESTAMP2
+            | ColumnType::MYSQL_TYPE_DATETIME2
+            | ColumnType::MYSQL_TYPE_TIME
+            | ColumnType::MYSQL_TYPE_TIME2 => {
+                let Some((&len, _)) = values.split_first() else {
+                    return Err(column_error(payload, offset, i, col, "missing length"));
+                };
+                let valid: &[u8] = match column_type {
+                    ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2 => &[0, 8, 12],
+                    ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => &[0, 4],
+                    _ => &[0, 4, 7, 11],
+                };
+                if !valid.contains(&len) {
+                    return Err(column_error(
+                        payload,
+                        offset,
+                        i,
+                        col,
+                        format!("invalid temporal length {len}"),
+                    ));
+                }
+                1 + len as usize
+            }
+            _ => {
+                let (len, header) = lenenc(values)
+                    .map_err(|reason| column_error(payload, offset, i, col, reason))?;
+                header + len
+            }
+        };
+        values = values.get(len..).ok_or_else(|| {
+            column_error(
+                payload,
+                offset,
+                i,
+                col,
+                format!(
+                    "{column_type:?} value needs {len} bytes, {} remain",
+                    values.len()
+                ),
+            )
+        })?;
+    }
+
+    if !values.is_empty() {
+        return Err(malformed(
+            payload,
+            payload.len() - values.len(),
+            format!(
+                "{} trailing bytes after {} columns",
+                values.len(),
+                cols.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Validate a text protocol row packet.
+pub fn validate_text_row(cols: &[ColumnDefinition<'_>], payload: &[u8]) -> Result<()> {
+    let mut values = payload;
+    for (i, col) in cols.iter().enumerate() {
+        let offset = payload.len() - values.len();
+        // 0xFB indicates NULL value
+        if let Some((&0xFB, rest)) = values.split_first() {
+            values = rest;
+            continue;
+        }
+        let (len, header) =
+            lenenc(values).map_err(|reason| column_error(payload, offset, i, col, reason))?;
+        values = values.get(header + len..).ok_or_else(|| {
+            column_error(
+                payload,
+                offset,
+                i,
+                col,
+                format!("value needs {len} bytes, {} remain", values.len() - header),
+            )
+        })?;
+    }
+    if !values.is_empty() {
+        return Err(malformed(
+            payload,
+            payload.len() - values.len(),
+            format!(
+                "{} trailing bytes after {} columns",
+                values.len(),
+                cols.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Validate a binary row if the `debug-protocol` feature is enabled.
+#[inline]
+pub(crate) fn check_binary_row(cols: &[ColumnDefinition<'_>], payload: &[u8]) -> Result<()> {
+    #[cfg(feature = "debug-protocol")]
+    validate_binary_row(cols, payload)?;
+    #[cfg(not(feature = "debug-protocol"))]
+    let _ = (cols, payload);
+    Ok(())
+}
+
+/// Validate a text row if the `debug-protocol` feature is enabled.
+#[inline]
+pub(crate) fn check_text_row(cols: &[ColumnDefinition<'_>], payload: &[u8]) -> Result<()> {
+    #[cfg(feature = "debug-protocol")]
+    validate_text_row(cols, payload)?;
+    #[cfg(not(feature = "debug-protocol"))]
+    let _ = (cols, payload);
+    Ok(())
+}
+
+/// Returns `(value, header length)` of a length-encoded integer, rejecting non-canonical encodings.
+fn lenenc(data: &[u8]) -> core::result::Result<(usize, usize), String> {
+    let (width, min) = match data.first() {
+        None => return Err("missing length".to_string()),
+        Some(&byte @ ..=0xFA) => return Ok((byte as usize, 1)),
+        Some(0xFC) => (2, 0xFB),
+        Some(0xFD) => (3, 1 << 16),
+        Some(0xFE) => (8, 1 << 24),
+        Some(&byte) => return Err(format!("invalid length prefix 0x{byte:02X}")),
+    };
+    let bytes = data
+        .get(1..1 + width)
+        .ok_or_else(|| format!("truncated {width}-byte length"))?;
+    let mut value = [0; 8];
+    value[..width].copy_from_slice(bytes);
+    let value = u64::from_le_bytes(value);
+    if value < min {
+        return Err(format!(
+            "length {value} is not minimally encoded in {width} bytes"
+        ));
+    }
+    let value = usize::try_from(value).map_err(|e| format!("length {value}: {e}"))?;
+    Ok((value, 1 + width))
+}
+
+fn column_error(
+    payload: &[u8],
+    offset: usize,
+    index: usize,
+    col: &ColumnDefinition<'_>,
+    reason: impl std::fmt::Display,
+) -> Error {
+    malformed(
+        payload,
+        offset,
+        format!(
+            "column {index} (`{}`): {reason}",
+            String::from_utf8_lossy(col.name_alias)
+        ),
+    )
+}
+
+fn malformed(payload: &[u8], offset: usize, reason: impl std::fmt::Display) -> Error {
+    Error::LibraryBug(eyre!(
+        "malformed row packet at byte {offset} of {}: {reason}",
+        payload.len()
+    ))
+}
@@ -0,0 +1,137 @@
+use zerocopy::FromBytes;
+
+use crate::constant::ColumnType;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail};
+use crate::protocol::validate::{validate_binary_row, validate_text_row};
+use crate::test_macros::{check, check_err};
+
+fn tail(column_type: ColumnType) -> crate::error::Result<ColumnDefinitionTail> {
+    let mut bytes = [0u8; 12];
+    bytes[0..2].copy_from_slice(&33u16.to_le_bytes()); // charset (utf8)
+    bytes[6] = column_type as u8;
+    Ok(*ColumnDefinitionTail::ref_from_bytes(&bytes)?)
+}
+
+fn column<'a>(name: &'a str, tail: &'a ColumnDefinitionTail) -> ColumnDefinition<'a> {
+    ColumnDefinition {
+        schema: b"",
+        table_alias: b"",
+        table_original: b"",
+        name_alias: name.as_bytes(),
+        name_original: name.as_bytes(),
+        tail,
+        name_id: None,
+    }
+}
+
+fn error_message(result: crate::error::Result<()>) -> String {
+    result.err().map(|e| e.to_string()).unwrap_or_default()
+}
+
+#[test]
+fn binary_row_valid() -> crate::error::Result<()> {
+    let long = tail(ColumnType::MYSQL_TYPE_LONG)?;
+    let varchar = tail(ColumnType::MYSQL_TYPE_VAR_STRING)?;
+    let datetime = tail(ColumnType::MYSQL_TYPE_DATETIME)?;
+    let cols = [
+        column("id", &long),
+        column("name", &varchar),
+        column("created", &datetime),
+    ];
+
+    // id = 7, name = 'ab', created = NULL (bit 4)
+    let row = [0x00, 0b0001_0000, 7, 0, 0, 0, 2, b'a', b'b'];
+    validate_binary_row(&cols, &row)?;
+
+    // created = 2024-01-02
+    let with_date = [0x00, 0, 7, 0, 0, 0, 0, 4, 0xE8, 0x07, 1, 2];
+    validate_binary_row(&cols, &with_date)?;
+    Ok(())
+}
+
+#[test]
+fn binary_row_truncated_value() -> crate::error::Result<()> {
+    let long = tail(ColumnType::MYSQL_TYPE_LONG)?;
+    let longlong = tail(ColumnType::MYSQL_TYPE_LONGLONG)?;
+    let cols = [column("id", &long), column("total", &longlong)];
+
+    let row = [0x00, 0, 7, 0, 0, 0, 1, 2, 3];
+    let message = error_message(validate_binary_row(&cols, &row));
+    check!(message.contains("column 1 (`total`)"), "{message}");
+    check!(message.contains("needs 8 bytes, 3 remain"), "{message}");
+    check!(message.contains("at byte 6 of 9"), "{message}");
+    Ok(())
+}
+
+#[test]
+fn binary_row_structure() -> crate::error::Result<()> {
+    let long = tail(ColumnType::MYSQL_TYPE_LONG)?;
+    let date = tail(ColumnType::MYSQL_TYPE_DATE)?;
+    let cols = [column("id", &long)];
+
+    // trailing bytes, e.g. a column count mismatch
+    let message = error_message(validate_binary_row(&cols, &[0x00, 0, 7, 0, 0, 0, 8]));
+    check!(
+        message.contains("1 trailing bytes after 1 columns"),
+        "{message}"
+    );
+    // reserved null bitmap bit
+    let reserved = error_message(validate_binary_row(&cols, &[0x00, 0b1, 7, 0, 0, 0]));
+    check!(
+        reserved.contains("reserved null bitmap bit 0"),
+        "{reserved}"
+    );
+    // null bitmap bit beyond the last column
+    check_err!(validate_binary_row(&cols, &[0x00, 0b1000, 7, 0, 0, 0]));
+    // missing header
+    check_err!(validate_binary_row(&cols, &[0xFE, 0, 0]));
+    // DATE with a DATETIME length
+    let temporal = error_message(validate_binary_row(
+        &[column("d", &date)],
+        &[0x00, 0, 7, 0xE8, 0x07, 1, 2, 0, 0, 0],
+    ));
+    check!(temporal.contains("invalid temporal length 7"), "{temporal}");
+    Ok(())
+}
+
+#[test]
+fn text_row() -> crate::error::Result<()> {
+    let varchar = tail(ColumnType::MYSQL_TYPE_VAR_STRING)?;
+    let cols = [column("a", &varchar), column("b", &varchar)];
+
+    validate_text_row(&cols, &[1, b'x', 0xFB])?;
+    validate_text_row(&cols, &[0, 0])?;
+
+    // missing column
+    let message = error_message(validate_text_row(&cols, &[1, b'x']));
+    check!(
+        message.contains("column 1 (`b`): missing length"),
+        "{message}"
+    );
+    // extra column
+    check_err!(validate_text_row(&cols, &[0, 0, 0]));
+    // value longer than the packet
+    let truncated = error_message(validate_text_row(&cols, &[5, b'x', 0xFB]));
+    check!(
+        truncated.contains("value needs 5 bytes, 2 remain"),
+        "{truncated}"
+    );
+    Ok(())
+}
+
+#[test]
+fn lenenc_must_be_minimal() -> crate::error::Result<()> {
+    let varchar = tail(ColumnType::MYSQL_TYPE_VAR_STRING)?;
+    let cols = [column("a", &varchar)];
+
+    let message = error_message(validate_text_row(&cols, &[0xFC, 1, 0, b'x']));
+    check!(message.contains("not minimally encoded"), "{message}");
+    let invalid = error_message(validate_text_row(&cols, &[0xFF]));
+    check!(invalid.contains("invalid length prefix 0xFF"), "{invalid}");
+    check_err!(validate_text_row(&cols, &[0xFD, 0]));
+
+    let mut long_value = vec![0xFC, 0xFB, 0x00];
+    long_value.extend_from_slice(&[b'y'; 0xFB]);
+    validate_text_row(&cols, &long_value)?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! Quoting of identifiers and string literals for SQL that cannot use placeholders.
+
+/// Quote `name` as an identifier with backticks, doubling embedded backticks.
+///
+/// ```
+/// use zero_mysql::quote::quote_identifier;
+///
+/// assert_eq!(quote_identifier("order"), "`order`");
+/// assert_eq!(quote_identifier("a`b"), "`a``b`");
+/// ```
+pub fn quote_identifier(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 2);
+    out.push('`');
+    for c in name.chars() {
+        if c == '`' {
+            out.push('`');
+        }
+        out.push(c);
+    }
+    out.push('`');
+    out
+}
+
+/// Quote `value` as a single-quoted string literal.
+///
+/// Quotes and backslashes are escaped, so the result is valid with or without
+/// the `NO_BACKSLASH_ESCAPES` SQL mode as long as `value` contains no backslash.
+///
+/// ```
+/// use zero_mysql::quote::quote_string;
+///
+/// assert_eq!(quote_string("it's"), "'it''s'");
+/// ```
+pub fn quote_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('\'');
+    for c in value.chars() {
+        match c {
+            '\'' => out.push_str("''"),
+            '\\' => out.push_str("\\\\"),
+            '\0' => out.push_str("\\0"),
+            _ => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// An argument of [`format_ddl!`](crate::format_ddl).
+///
+/// Strings are quoted as identifiers, `(schema, name)` pairs as qualified identifiers,
+/// and [`Literal`] as a string literal.
+pub trait DdlArg {
+    fn write_ddl(&self, out: &mut String);
+}
+
+impl DdlArg for str {
+    fn write_ddl(&self, out: &mut String) {
+        out.push_str(&quote_identifier(self));
+    }
+}
+
+impl DdlArg for String {
+    fn write_ddl(&self, out: &mut String) {
+        self.as_str().write_ddl(out);
+    }
+}
+
+impl<T: DdlArg + ?Sized> DdlArg for &T {
+    fn write_ddl(&self, out: &mut String) {
+        (**self).write_ddl(out);
+    }
+}
+
+impl<A: DdlArg, B: DdlArg> DdlArg for (A, B) {
+    fn write_ddl(&self, out: &mut String) {
+        self.0.write_ddl(out);
+        out.push('.');
+        self.1.write_ddl(out);
+    }
+}
+
+/// A [`format_ddl!`](crate::format_ddl) argument quoted as a string literal, e.g. for `COMMENT`.
+#[derive(Debug, Clone, Copy)]
+pub struct Literal<'a>(pub &'a str);
+
+impl DdlArg for Literal<'_> {
+    fn write_ddl(&self, out: &mut String) {
+        out.push_str(&quote_string(self.0));
+    }
+}
+
+/// Formats a [`DdlArg`] for [`format_ddl!`](crate::format_ddl).
+#[doc(hidden)]
+pub struct Ddl<'a, T: ?Sized>(pub &'a T);
+
+impl<T: DdlArg + ?Sized> std::fmt::Display for Ddl<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        self.0.write_ddl(&mut out);
+        f.write_str(&out)
+    }
+}
+
+/// `format!` for DDL, quoting every argument as an identifier.
+///
+/// Arguments are [`DdlArg`]s: strings become identifiers, `(schema, name)` pairs become
+/// qualified identifiers, and [`Literal`] becomes a string literal. Only positional `{}`
+/// arguments are quoted; inline captures like `{name}` are inserted as they are.
+///
+/// ```
+/// use zero_mysql::format_ddl;
+/// use zero_mysql::quote::Literal;
+///
+/// let table = "order";
+/// let column = "created`at";
+/// let ddl = format_ddl!(
+///     "CREATE TABLE {} ({} DATETIME NOT NULL) COMMENT {}",
+///     ("shop", table),
+///     column,
+///     Literal("it's generated"),
+/// );
+/// assert_eq!(
+///     ddl,
+///     "CREATE TABLE `shop`.`order` (`created``at` DATETIME NOT NULL) COMMENT 'it''s generated'"
+/// );
+/// ```
+#[macro_export]
+macro_rules! format_ddl {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        format!($fmt $(, $crate::quote::Ddl(&$arg))*)
+    };
+}
@@ -0,0 +1,40 @@
+use crate::quote::{Literal, quote_identifier, quote_string};
+use crate::test_macros::check_eq;
+
+#[test]
+fn identifiers() -> crate::error::Result<()> {
+    check_eq!(quote_identifier(""), "``");
+    check_eq!(quote_identifier("user"), "`user`");
+    check_eq!(quote_identifier("``"), "``````");
+    check_eq!(quote_identifier("r\u{f6}le"), "`r\u{f6}le`");
+    Ok(())
+}
+
+#[test]
+fn strings() -> crate::error::Result<()> {
+    check_eq!(quote_string(""), "''");
+    check_eq!(quote_string("a'b"), "'a''b'");
+    check_eq!(quote_string("a\\'b"), "'a\\\\''b'");
+    check_eq!(quote_string("\0"), "'\\0'");
+    Ok(())
+}
+
+#[test]
+fn ddl() -> crate::error::Result<()> {
+    let table = String::from("t`1");
+    check_eq!(
+        crate::format_ddl!("DROP TABLE IF EXISTS {}", table),
+        "DROP TABLE IF EXISTS `t``1`"
+    );
+    check_eq!(
+        crate::format_ddl!(
+            "ALTER TABLE {} ADD INDEX {} ({}) COMMENT {}",
+            ("db", "users"),
+            "by_email",
+            "email",
+            Literal("a\\b"),
+        ),
+        "ALTER TABLE `db`.`users` ADD INDEX `by_email` (`email`) COMMENT 'a\\\\b'"
+    );
+    Ok(())
+}
@@ -3,12 +3,15 @@
 //! This module provides traits for decoding MySQL values directly into target types
 //! without intermediate `Value` allocation.
 
+use std::borrow::Cow;
+
+use crate::charset::Charset;
 use crate::constant::{ColumnFlags, ColumnType};
 use crate::error::{Error, Result, eyre};
 use crate::protocol::BinaryRowPayload;
 use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail};
 use crate::protocol::primitive::*;
-use crate::value::{Time8, Time12, Timestamp4, Timestamp7, Timestamp11, Value};
+use crate::value::{BitValue, Time8, Time12, Timestamp4, Timestamp7, Timestamp11, Value};
 use simdutf8::basic::from_utf8;
 use zerocopy::FromBytes;
 
@@ -111,6 +114,13 @@ pub trait FromRawValue<'buf>: Sized {
         )))
     }
 
+    /// A STRING in a `charset` that is not UTF-8. Decoded as STRING by default.
+    ///
+    /// Owned string types override this to transcode with [`Charset::decode`].
+    fn from_str_with_charset(v: &'buf [u8], _charset: Charset) -> Result<Self> {
+        Self::from_str(v)
+    }
+
     fn from_decimal(_v: &'buf [u8]) -> Result<Self> {
         Err(Error::BadUsageError(format!(
             "Cannot decode MySQL type DECIMAL to {}",
@@ -118,6 +128,11 @@ pub trait FromRawValue<'buf>: Sized {
         )))
     }
 
+    /// BIT(n) as a big-endian bit string of `(n + 7) / 8` bytes. Decoded as BYTES by default.
+    fn from_bit(v: &'buf [u8]) -> Result<Self> {
+        Self::from_bytes(v)
+    }
+
     fn from_date0() -> Result<Self> {
         Err(Error::BadUsageError(format!(
             "Cannot decode MySQL type DATE to {}",
@@ -327,6 +342,11 @@ pub fn parse_value<'buf, T: FromRawValue<'buf>>(
             Ok((T::from_decimal(bytes)?, rest))
         }
 
+        ColumnType::MYSQL_TYPE_BIT => {
+            let (bytes, rest) = read_string_lenenc(data)?;
+            Ok((T::from_bit(bytes)?, rest))
+        }
+
         // String and BLOB types
         ColumnType::MYSQL_TYPE_VARCHAR
         | ColumnType::MYSQL_TYPE_VAR_STRING
@@ -339,13 +359,17 @@ pub fn parse_value<'buf, T: FromRawValue<'buf>>(
         | ColumnType::MYSQL_TYPE_JSON
         | ColumnType::MYSQL_TYPE_ENUM
         | ColumnType::MYSQL_TYPE_SET
-        | ColumnType::MYSQL_TYPE_BIT
         | ColumnType::MYSQL_TYPE_TYPED_ARRAY => {
             let (bytes, rest) = read_string_lenenc(data)?;
             let out = if is_binary_charset {
                 T::from_bytes(bytes)?
             } else {
-                T::from_str(bytes)?
+                let charset = Charset::from_collation(col.charset());
+                if charset.is_utf8() {
+                    T::from_str(bytes)?
+                } else {
+                    T::from_str_with_charset(bytes, charset)?
+                }
             };
             Ok((out, rest))
         }
@@ -576,6 +600,10 @@ impl FromRawValue<'_> for bool {
     fn from_u8(v: u8) -> Result<Self> {
         Ok(v != 0)
     }
+
+    fn from_bit(v: &[u8]) -> Result<Self> {
+        Ok(bits_to_u64(v)? != 0)
+    }
 }
 
 impl FromRawValue<'_> for u8 {
@@ -624,6 +652,32 @@ impl FromRawValue<'_> for u64 {
     fn from_u64(v: u64) -> Result<Self> {
         Ok(v)
     }
+
+    fn from_bit(v: &[u8]) -> Result<Self> {
+        bits_to_u64(v)
+    }
+}
+
+impl FromRawValue<'_> for BitValue {
+    fn from_bit(v: &[u8]) -> Result<Self> {
+        bits_to_u64(v).map(BitValue)
+    }
+
+    /// `b'101'` literals and `BINARY` columns
+    fn from_bytes(v: &[u8]) -> Result<Self> {
+        bits_to_u64(v).map(BitValue)
+    }
+}
+
+/// Interpret a big-endian bit string of up to 64 bits
+fn bits_to_u64(v: &[u8]) -> Result<u64> {
+    if v.len() > 8 {
+        return Err(Error::BadUsageError(format!(
+            "Cannot decode {} bytes of BIT to a 64-bit value",
+            v.len()
+        )));
+    }
+    Ok(v.iter().fold(0, |acc, &byte| (acc << 8) | u64::from(byte)))
 }
 
 impl FromRawValue<'_> for f32 {
@@ -668,6 +722,21 @@ impl FromRawValue<'_> for String {
             Error::BadUsageError(format!("Cannot decode MySQL type STRING to String: {}", e))
         })
     }
+
+    fn from_str_with_charset(v: &[u8], charset: Charset) -> Result<Self> {
+        charset.decode(v).map(Cow::into_owned)
+    }
+}
+
+/// Borrowed for UTF-8 columns, owned for columns transcoded from another charset
+impl<'a> FromRawValue<'a> for Cow<'a, str> {
+    fn from_str(v: &'a [u8]) -> Result<Self> {
+        Charset::Utf8mb4.decode(v)
+    }
+
+    fn from_str_with_charset(v: &'a [u8], charset: Charset) -> Result<Self> {
+        charset.decode(v)
+    }
 }
 
 impl<'a, T: FromRawValue<'a>> FromRawValue<'a> for Option<T> {
@@ -723,10 +792,18 @@ impl<'a, T: FromRawValue<'a>> FromRawValue<'a> for Option<T> {
         T::from_str(v).map(Some)
     }
 
+    fn from_str_with_charset(v: &'a [u8], charset: Charset) -> Result<Self> {
+        T::from_str_with_charset(v, charset).map(Some)
+    }
+
     fn from_decimal(v: &'a [u8]) -> Result<Self> {
         T::from_decimal(v).map(Some)
     }
 
+    fn from_bit(v: &'a [u8]) -> Result<Self> {
+        T::from_bit(v).map(Some)
+    }
+
     fn from_date0() -> Result<Self> {
         T::from_date0().map(Some)
     }
@@ -808,22 +885,33 @@ impl_from_row_tuple!(0: A, 1: B, 2: C, 3: D, 4: E, 5: F, 6: G, 7: H, 8: I, 9: J,
 // UUID support
 // ============================================================================
 
+/// `BINARY(16)` values are read as the 16 raw bytes. Any other string or binary value,
+/// including `CHAR(36)` and MariaDB's `UUID` type, is parsed from its text form.
 #[cfg(feature = "with-uuid")]
 impl FromRawValue<'_> for uuid::Uuid {
     fn from_str(v: &[u8]) -> Result<Self> {
-        let s = from_utf8(v).map_err(|e| {
-            Error::BadUsageError(format!("Cannot decode MySQL STRING to UUID: {}", e))
-        })?;
-        uuid::Uuid::parse_str(s)
-            .map_err(|e| Error::BadUsageError(format!("Cannot parse UUID from '{}': {}", s, e)))
+        parse_uuid_text(v)
     }
 
     fn from_bytes(v: &[u8]) -> Result<Self> {
-        uuid::Uuid::from_slice(v)
-            .map_err(|e| Error::BadUsageError(format!("Cannot decode MySQL BINARY to UUID: {}", e)))
+        match <[u8; 16]>::try_from(v) {
+            Ok(bytes) => Ok(uuid::Uuid::from_bytes(bytes)),
+            Err(_) => parse_uuid_text(v),
+        }
     }
 }
 
+#[cfg(feature = "with-uuid")]
+fn parse_uuid_text(v: &[u8]) -> Result<uuid::Uuid> {
+    uuid::Uuid::try_parse_ascii(v).map_err(|e| {
+        Error::BadUsageError(format!(
+            "Cannot parse UUID from '{}': {}",
+            String::from_utf8_lossy(v),
+            e
+        ))
+    })
+}
+
 // ============================================================================
 // chrono support
 // ============================================================================
@@ -948,6 +1036,29 @@ impl FromRawValue<'_> for chrono::NaiveDateTime {
     }
 }
 
+/// The stored DATETIME is taken as UTC. TIMESTAMP columns are sent in the session `time_zone`,
+/// so set it to `'+00:00'` before decoding them.
+#[cfg(feature = "with-chrono")]
+impl FromRawValue<'_> for chrono::DateTime<chrono::Utc> {
+    fn from_datetime0() -> Result<Self> {
+        Err(Error::BadUsageError(
+            "Cannot decode zero DATETIME to chrono::DateTime<Utc>".to_string(),
+        ))
+    }
+
+    fn from_datetime4(v: &Timestamp4) -> Result<Self> {
+        chrono::NaiveDateTime::from_datetime4(v).map(|datetime| datetime.and_utc())
+    }
+
+    fn from_datetime7(v: &Timestamp7) -> Result<Self> {
+        chrono::NaiveDateTime::from_datetime7(v).map(|datetime| datetime.and_utc())
+    }
+
+    fn from_datetime11(v: &Timestamp11) -> Result<Self> {
+        chrono::NaiveDateTime::from_datetime11(v).map(|datetime| datetime.and_utc())
+    }
+}
+
 // ============================================================================
 // time crate support
 // ============================================================================
@@ -1085,6 +1196,29 @@ impl FromRawValue<'_> for time::PrimitiveDateTime {
     }
 }
 
+/// The stored DATETIME is taken as UTC. TIMESTAMP columns are sent in the session `time_zone`,
+/// so set it to `'+00:00'` before decoding them.
+#[cfg(feature = "with-time")]
+impl FromRawValue<'_> for time::OffsetDateTime {
+    fn from_datetime0() -> Result<Self> {
+        Err(Error::BadUsageError(
+            "Cannot decode zero DATETIME to time::OffsetDateTime".to_string(),
+        ))
+    }
+
+    fn from_datetime4(v: &Timestamp4) -> Result<Self> {
+        time::PrimitiveDateTime::from_datetime4(v).map(time::PrimitiveDateTime::assume_utc)
+    }
+
+    fn from_datetime7(v: &Timestamp7) -> Result<Self> {
+        time::PrimitiveDateTime::from_datetime7(v).map(time::PrimitiveDateTime::assume_utc)
+    }
+
+    fn from_datetime11(v: &Timestamp11) -> Result<Self> {
+        time::PrimitiveDateTime::from_datetime11(v).map(time::PrimitiveDateTime::assume_utc)
+    }
+}
+
 // ============================================================================
 // rust_decimal support
 // ============================================================================
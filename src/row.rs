@@ -0,0 +1,216 @@
+//! Rows with dynamic access by column name or index.
+//!
+//! `FromRow` decodes into a type known at compile time. Generic tooling such as a REPL or an
+//! admin dashboard does not know the schema in advance, so [`Row`] keeps the column definitions
+//! and the binary row bytes, and decodes a value only when it is asked for:
+//!
+//! ```no_run
+//! # fn run(conn: &mut zero_mysql::sync::Conn) -> zero_mysql::error::Result<()> {
+//! use zero_mysql::handler::CollectRowsHandler;
+//! use zero_mysql::value::Value;
+//!
+//! let mut stmt = conn.prepare("SELECT id, name FROM users")?;
+//! let mut handler = CollectRowsHandler::default();
+//! conn.exec(&mut stmt, (), &mut handler)?;
+//! for row in handler.into_rows() {
+//!     let id: i64 = row.get("id")?;
+//!     let name: Option<String> = row.get(1)?;
+//!     let first: Value<'_> = row.get(0)?;
+//!     println!("{id} {name:?} {first:?}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The rows of one result set share their column definitions.
+
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitions};
+use crate::raw::{FromRawValue, FromRow, parse_value, skip_value};
+use crate::value::NullBitmap;
+
+/// An owned binary row: the column definitions, the null bitmap and the undecoded values.
+#[derive(Debug, Clone)]
+pub struct Row {
+    columns: Arc<ColumnDefinitions>,
+    null_bitmap: Vec<u8>,
+    values: Vec<u8>,
+    /// `offsets[i]..offsets[i + 1]` is the value of column `i` in `values`
+    offsets: Vec<usize>,
+}
+
+impl Row {
+    /// Copy `row` out of the read buffer.
+    pub fn new(columns: Arc<ColumnDefinitions>, row: BinaryRowPayload<'_>) -> Result<Self> {
+        let null_bitmap = row.null_bitmap();
+        let values = row.values();
+        let mut offsets = Vec::with_capacity(columns.definitions().len() + 1);
+        offsets.push(0);
+        let mut rest = values;
+        for (idx, col) in columns.definitions().iter().enumerate() {
+            let ((), next) = skip_value(col.tail, null_bitmap.is_null(idx), rest)?;
+            rest = next;
+            offsets.push(values.len() - rest.len());
+        }
+        Ok(Self {
+            null_bitmap: null_bitmap.as_bytes().to_vec(),
+            values: values.to_vec(),
+            columns,
+            offsets,
+        })
+    }
+
+    /// The number of columns.
+    pub fn len(&self) -> usize {
+        self.columns.definitions().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The column definitions.
+    pub fn columns(&self) -> &[ColumnDefinition<'_>] {
+        self.columns.definitions()
+    }
+
+    /// The index of the column named `name`, or `None` if there is no such column.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns()
+            .iter()
+            .position(|col| col.name_alias == name.as_bytes())
+    }
+
+    /// Returns true if the value of column `index` is NULL.
+    pub fn is_null(&self, index: impl RowIndex) -> Result<bool> {
+        let idx = index.index(self.columns())?;
+        Ok(NullBitmap::for_result_set(&self.null_bitmap).is_null(idx))
+    }
+
+    /// Decode the value of a column, by name (`"id"`) or by index (`0`).
+    ///
+    /// Use `Option<T>` for nullable columns and [`Value`](crate::value::Value) if the type is not
+    /// known in advance.
+    pub fn get<'a, T: FromRawValue<'a>>(&'a self, index: impl RowIndex) -> Result<T> {
+        let idx = index.index(self.columns())?;
+        let (Some(col), Some(&start), Some(&end)) = (
+            self.columns().get(idx),
+            self.offsets.get(idx),
+            self.offsets.get(idx + 1),
+        ) else {
+            return Err(Error::LibraryBug(crate::error::eyre!(
+                "Row: missing offsets of column {idx}"
+            )));
+        };
+        let is_null = NullBitmap::for_result_set(&self.null_bitmap).is_null(idx);
+        let (value, _) = parse_value::<T>(col.tail, is_null, &self.values[start..end])?;
+        Ok(value)
+    }
+}
+
+/// Copies the column definitions into every row. Use
+/// [`CollectRowsHandler`](crate::handler::CollectRowsHandler) to share them between the rows of a
+/// result set.
+impl FromRow<'_> for Row {
+    fn from_row(cols: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<Self> {
+        Self::new(Arc::new(ColumnDefinitions::from_definitions(cols)?), row)
+    }
+}
+
+/// A binary row borrowed from the connection's read buffer, e.g. from `sync::Conn::exec_iter()`.
+///
+/// Values are decoded on access, like [`Row`], without copying the row first.
+#[derive(Debug, Clone)]
+pub struct BinaryRowRef<'a> {
+    cols: &'a [ColumnDefinition<'a>],
+    row: BinaryRowPayload<'a>,
+}
+
+impl<'a> BinaryRowRef<'a> {
+    pub fn new(cols: &'a [ColumnDefinition<'a>], row: BinaryRowPayload<'a>) -> Self {
+        Self { cols, row }
+    }
+
+    /// The number of columns.
+    pub fn len(&self) -> usize {
+        self.cols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cols.is_empty()
+    }
+
+    /// The column definitions.
+    pub fn columns(&self) -> &'a [ColumnDefinition<'a>] {
+        self.cols
+    }
+
+    /// The raw row.
+    pub fn payload(&self) -> &BinaryRowPayload<'a> {
+        &self.row
+    }
+
+    /// Returns true if the value of column `index` is NULL.
+    pub fn is_null(&self, index: impl RowIndex) -> Result<bool> {
+        let idx = index.index(self.cols)?;
+        Ok(self.row.null_bitmap().is_null(idx))
+    }
+
+    /// Decode the value of a column, by name (`"id"`) or by index (`0`).
+    pub fn get<T: FromRawValue<'a>>(&self, index: impl RowIndex) -> Result<T> {
+        let idx = index.index(self.cols)?;
+        let null_bitmap = self.row.null_bitmap();
+        let mut data = self.row.values();
+        for (skipped, col) in self.cols.iter().enumerate().take(idx) {
+            let ((), rest) = skip_value(col.tail, null_bitmap.is_null(skipped), data)?;
+            data = rest;
+        }
+        let col = self.cols.get(idx).ok_or_else(|| {
+            Error::LibraryBug(crate::error::eyre!("BinaryRowRef: no column {idx}"))
+        })?;
+        let (value, _) = parse_value::<T>(col.tail, null_bitmap.is_null(idx), data)?;
+        Ok(value)
+    }
+
+    /// Decode the whole row.
+    pub fn decode<T: FromRow<'a>>(&self) -> Result<T> {
+        T::from_row(self.cols, self.row.clone())
+    }
+
+    /// Copy the row into an owned [`Row`].
+    pub fn to_row(&self) -> Result<Row> {
+        Row::from_row(self.cols, self.row.clone())
+    }
+}
+
+/// A column of a [`Row`]: an index or a column name.
+pub trait RowIndex {
+    /// The index of the column in `columns`.
+    fn index(&self, columns: &[ColumnDefinition<'_>]) -> Result<usize>;
+}
+
+impl RowIndex for usize {
+    fn index(&self, columns: &[ColumnDefinition<'_>]) -> Result<usize> {
+        if *self < columns.len() {
+            Ok(*self)
+        } else {
+            Err(Error::BadUsageError(format!(
+                "Column index {} out of range for a row of {} columns",
+                self,
+                columns.len()
+            )))
+        }
+    }
+}
+
+impl RowIndex for &str {
+    fn index(&self, columns: &[ColumnDefinition<'_>]) -> Result<usize> {
+        columns
+            .iter()
+            .position(|col| col.name_alias == self.as_bytes())
+            .ok_or_else(|| Error::UnknownColumn((*self).to_string()))
+    }
+}
@@ -0,0 +1,148 @@
+use zerocopy::FromBytes;
+
+use crate::error::{Error, Result};
+use crate::handler::CollectRowsHandler;
+use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail};
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::BinaryResultSetHandler;
+use crate::raw::FromRow;
+use crate::row::{BinaryRowRef, Row};
+use crate::test_macros::{check, check_eq};
+use crate::value::{NullBitmap, Value};
+
+/// OK with SERVER_STATUS_AUTOCOMMIT
+const EOF: &[u8] = &[0xFE, 0, 0, 0x02, 0x00, 0, 0];
+
+/// BIGINT NOT NULL
+const BIGINT_TAIL: [u8; 12] = [
+    0x3F, 0x00, 0x14, 0x00, 0x00, 0x00, 0x08, 0x01, 0x00, 0x00, 0, 0,
+];
+/// VARCHAR(255) utf8mb4
+const VARCHAR_TAIL: [u8; 12] = [
+    0x2D, 0x00, 0xFC, 0x03, 0x00, 0x00, 0xFD, 0x00, 0x00, 0x00, 0, 0,
+];
+
+fn column<'a>(name: &'a [u8], tail: &'a ColumnDefinitionTail) -> ColumnDefinition<'a> {
+    ColumnDefinition {
+        schema: b"test",
+        table_alias: b"users",
+        table_original: b"users",
+        name_alias: name,
+        name_original: name,
+        tail,
+        name_id: None,
+    }
+}
+
+/// `(id, name, nickname)` with `nickname` NULL
+fn values(id: i64, name: &str) -> Vec<u8> {
+    let mut values = id.to_le_bytes().to_vec();
+    values.push(name.len() as u8);
+    values.extend_from_slice(name.as_bytes());
+    values
+}
+
+/// NULL bitmap (offset 2) with column 2 NULL
+const NICKNAME_NULL: &[u8] = &[0b0001_0000];
+
+#[test]
+fn get_by_name_and_index() -> Result<()> {
+    let bigint = ColumnDefinitionTail::ref_from_bytes(&BIGINT_TAIL)?;
+    let varchar = ColumnDefinitionTail::ref_from_bytes(&VARCHAR_TAIL)?;
+    let cols = [
+        column(b"id", bigint),
+        column(b"name", varchar),
+        column(b"nickname", varchar),
+    ];
+    let bytes = values(7, "alice");
+    let row = Row::from_row(
+        &cols,
+        BinaryRowPayload::new(NullBitmap::for_result_set(NICKNAME_NULL), &bytes, 3),
+    )?;
+
+    check_eq!(row.len(), 3);
+    check_eq!(row.get::<i64>("id")?, 7);
+    check_eq!(row.get::<i64>(0)?, 7);
+    check_eq!(row.get::<&str>("name")?, "alice");
+    check_eq!(row.get::<String>(1)?, "alice");
+    check_eq!(row.get::<Option<String>>("nickname")?, None);
+    check!(row.is_null("nickname")?);
+    check!(!row.is_null(0)?);
+    check!(matches!(row.get::<Value<'_>>(0)?, Value::SignedInt(7)));
+    check_eq!(row.column_index("name"), Some(1));
+    check_eq!(row.column_index("email"), None);
+    Ok(())
+}
+
+#[test]
+fn unknown_column_and_index_out_of_range() -> Result<()> {
+    let bigint = ColumnDefinitionTail::ref_from_bytes(&BIGINT_TAIL)?;
+    let cols = [column(b"id", bigint)];
+    let bytes = 1_i64.to_le_bytes();
+    let row = Row::from_row(
+        &cols,
+        BinaryRowPayload::new(NullBitmap::for_result_set(&[0]), &bytes, 1),
+    )?;
+
+    check!(matches!(row.get::<i64>("email"), Err(Error::UnknownColumn(name)) if name == "email"));
+    check!(matches!(row.get::<i64>(1), Err(Error::BadUsageError(_))));
+    Ok(())
+}
+
+#[test]
+fn collect_rows_share_columns() -> Result<()> {
+    let bigint = ColumnDefinitionTail::ref_from_bytes(&BIGINT_TAIL)?;
+    let varchar = ColumnDefinitionTail::ref_from_bytes(&VARCHAR_TAIL)?;
+    let cols = [
+        column(b"id", bigint),
+        column(b"name", varchar),
+        column(b"nickname", varchar),
+    ];
+    let mut handler = CollectRowsHandler::default();
+    handler.resultset_start(&cols)?;
+    for (id, name) in [(1, "alice"), (2, "bob")] {
+        let bytes = values(id, name);
+        let row = BinaryRowPayload::new(NullBitmap::for_result_set(NICKNAME_NULL), &bytes, 3);
+        handler.row(&cols, row)?;
+    }
+    handler.resultset_end(OkPayloadBytes(EOF))?;
+
+    let rows = handler.into_rows();
+    let names = rows
+        .iter()
+        .map(|row| row.get::<String>("name"))
+        .collect::<Result<Vec<_>>>()?;
+    check_eq!(names, vec!["alice", "bob"]);
+    let [first, second] = rows.as_slice() else {
+        return Err(Error::LibraryBug(crate::error::eyre!("expected 2 rows")));
+    };
+    check!(std::ptr::eq(first.columns(), second.columns()));
+    Ok(())
+}
+
+#[test]
+fn row_ref_decodes_in_place() -> Result<()> {
+    let bigint = ColumnDefinitionTail::ref_from_bytes(&BIGINT_TAIL)?;
+    let varchar = ColumnDefinitionTail::ref_from_bytes(&VARCHAR_TAIL)?;
+    let cols = [
+        column(b"id", bigint),
+        column(b"name", varchar),
+        column(b"nickname", varchar),
+    ];
+    let bytes = values(3, "carol");
+    let row = BinaryRowRef::new(
+        &cols,
+        BinaryRowPayload::new(NullBitmap::for_result_set(NICKNAME_NULL), &bytes, 3),
+    );
+
+    let name: &str = row.get("name")?;
+    check!(std::ptr::eq(name.as_ptr(), bytes[9..].as_ptr()));
+    check_eq!(row.get::<Option<&str>>(2)?, None);
+    check_eq!(
+        row.decode::<(i64, String, Option<String>)>()?,
+        (3, "carol".to_string(), None)
+    );
+    check_eq!(row.to_row()?.get::<i64>("id")?, 3);
+    Ok(())
+}
@@ -0,0 +1,842 @@
+//! Offline checking of SQL statements against a committed schema snapshot.
+//!
+//! A [`SchemaSnapshot`] is a JSON dump of the tables and columns of a database, written with
+//! `zero-mysql-cli dump-schema` and committed next to the code. [`SchemaSnapshot::check`] then
+//! finds statements that reference missing tables or columns without a database connection,
+//! from a unit test or with `zero-mysql-cli verify` in CI.
+//!
+//! ```
+//! use zero_mysql::schema_check::{SchemaIssue, SchemaSnapshot};
+//!
+//! let snapshot = SchemaSnapshot::from_json(
+//!     r#"{"tables": [{"name": "users", "columns": [
+//!         {"name": "id", "type": "bigint", "nullable": false},
+//!         {"name": "name", "type": "varchar(50)", "nullable": true}
+//!     ]}]}"#,
+//! )?;
+//! assert_eq!(
+//!     snapshot.check("SELECT u.id, u.email FROM users u"),
+//!     vec![SchemaIssue::UnknownColumn {
+//!         table: "users".to_string(),
+//!         column: "email".to_string(),
+//!     }]
+//! );
+//! # Ok::<(), zero_mysql::error::Error>(())
+//! ```
+//!
+//! The check works on the SQL tokens, without a full parser. It reports:
+//!
+//! - tables after `FROM`, `JOIN`, `UPDATE` and `INTO` that are not in the snapshot, except
+//!   database-qualified tables and common table expressions
+//! - qualified columns (`table.column` or `alias.column`) missing from their table
+//! - columns of `INSERT INTO t (...)` and of `SET` assignments missing from the target table
+//!
+//! Unqualified columns in other clauses are not checked, since they cannot be told apart from
+//! aliases and functions without resolving the whole statement. Only `SELECT`, `INSERT`,
+//! `REPLACE`, `UPDATE`, `DELETE` and `WITH` statements are checked.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::sql_lexer::{Token, TokenKind, tokenize};
+
+/// Lists the columns of the current database, in the order expected by
+/// [`SchemaSnapshot::push_column`].
+pub const SCHEMA_DUMP_SQL: &str = "SELECT TABLE_NAME, COLUMN_NAME, COLUMN_TYPE, IS_NULLABLE \
+     FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() \
+     ORDER BY TABLE_NAME, ORDINAL_POSITION";
+
+/// The tables and columns of a database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaSnapshot {
+    pub tables: Vec<TableSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSchema {
+    pub name: String,
+    /// Example: `"varchar(50)"`
+    pub column_type: String,
+    pub nullable: bool,
+}
+
+/// A reference that does not match the snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaIssue {
+    UnknownTable(String),
+    UnknownColumn { table: String, column: String },
+}
+
+impl fmt::Display for SchemaIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTable(table) => write!(f, "unknown table `{table}`"),
+            Self::UnknownColumn { table, column } => {
+                write!(f, "unknown column `{column}` in table `{table}`")
+            }
+        }
+    }
+}
+
+impl TableSchema {
+    /// Column names are case-insensitive.
+    pub fn column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl SchemaSnapshot {
+    /// Table names are compared case-insensitively, since the server setting
+    /// `lower_case_table_names` differs between platforms.
+    pub fn table(&self, name: &str) -> Option<&TableSchema> {
+        self.tables
+            .iter()
+            .find(|table| table.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Append a column, starting a new table if `table` differs from the last table.
+    pub fn push_column(&mut self, table: &str, column: ColumnSchema) {
+        match self.tables.last_mut() {
+            Some(last) if last.name == table => last.columns.push(column),
+            _ => self.tables.push(TableSchema {
+                name: table.to_string(),
+                columns: vec![column],
+            }),
+        }
+    }
+
+    /// Parse a snapshot written by [`SchemaSnapshot::to_json`].
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut parser = JsonParser {
+            bytes: json.as_bytes(),
+            pos: 0,
+        };
+        let root = parser.parse_document()?;
+        let mut snapshot = Self::default();
+        for table in root.field("tables").and_then(Json::as_array).unwrap_or(&[]) {
+            let name = table
+                .field("name")
+                .and_then(Json::as_str)
+                .ok_or_else(|| invalid_snapshot("a table has no name"))?;
+            let mut columns = Vec::new();
+            for column in table
+                .field("columns")
+                .and_then(Json::as_array)
+                .unwrap_or(&[])
+            {
+                columns.push(ColumnSchema {
+                    name: column
+                        .field("name")
+                        .and_then(Json::as_str)
+                        .ok_or_else(|| invalid_snapshot("a column has no name"))?
+                        .to_string(),
+                    column_type: column
+                        .field("type")
+                        .and_then(Json::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    nullable: matches!(column.field("nullable"), Some(Json::Bool(true))),
+                });
+            }
+            snapshot.tables.push(TableSchema {
+                name: name.to_string(),
+                columns,
+            });
+        }
+        Ok(snapshot)
+    }
+
+    /// Write the snapshot as JSON, one column per line so that changes diff well.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n  \"tables\": [");
+        for (i, table) in self.tables.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            out.push_str("    {\n      \"name\": ");
+            push_json_string(&mut out, &table.name);
+            out.push_str(",\n      \"columns\": [");
+            for (j, column) in table.columns.iter().enumerate() {
+                out.push_str(if j == 0 { "\n" } else { ",\n" });
+                out.push_str("        { \"name\": ");
+                push_json_string(&mut out, &column.name);
+                out.push_str(", \"type\": ");
+                push_json_string(&mut out, &column.column_type);
+                out.push_str(if column.nullable {
+                    ", \"nullable\": true }"
+                } else {
+                    ", \"nullable\": false }"
+                });
+            }
+            out.push_str("\n      ]\n    }");
+        }
+        out.push_str("\n  ]\n}\n");
+        out
+    }
+
+    /// Find the references in `sql` that do not match the snapshot.
+    pub fn check(&self, sql: &str) -> Vec<SchemaIssue> {
+        let tokens: Vec<Token<'_>> = tokenize(sql)
+            .into_iter()
+            .filter(|token| {
+                !matches!(
+                    token.kind,
+                    TokenKind::Whitespace | TokenKind::Comment | TokenKind::Hint
+                )
+            })
+            .collect();
+        let checked = tokens.first().is_some_and(|first| {
+            is_any_word(
+                first,
+                &["SELECT", "INSERT", "REPLACE", "UPDATE", "DELETE", "WITH"],
+            ) || first.text == "("
+        });
+        if !checked {
+            return Vec::new();
+        }
+        let mut checker = Checker {
+            snapshot: self,
+            ctes: cte_names(&tokens),
+            tokens,
+            scope: Vec::new(),
+            issues: Vec::new(),
+        };
+        checker.check_tables();
+        checker.check_qualified_columns();
+        checker.issues
+    }
+}
+
+/// Split a script into statements at `;` outside of literals and comments.
+/// Returns the 1-based line number of each statement with its trimmed text.
+pub fn split_statements(script: &str) -> Vec<(usize, &str)> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    for token in tokenize(script) {
+        offset += token.text.len();
+        if token.kind == TokenKind::Symbol && token.text == ";" {
+            push_statement(&mut statements, script, start, offset - 1);
+            start = offset;
+        }
+    }
+    push_statement(&mut statements, script, start, script.len());
+    statements
+}
+
+fn push_statement<'a>(
+    statements: &mut Vec<(usize, &'a str)>,
+    script: &'a str,
+    start: usize,
+    end: usize,
+) {
+    let Some(text) = script.get(start..end) else {
+        return;
+    };
+    let trimmed = text.trim_start();
+    if trimmed.trim_end().is_empty() {
+        return;
+    }
+    let leading = text.len() - trimmed.len();
+    let line = script
+        .get(..start + leading)
+        .map_or(0, |before| before.matches('\n').count())
+        + 1;
+    statements.push((line, trimmed.trim_end()));
+}
+
+/// Words after which `(` opens a subquery or expression rather than a function call
+const NON_FUNCTION_WORDS: &[&str] = &[
+    "ALL",
+    "AND",
+    "ANY",
+    "AS",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "DISTINCT",
+    "ELSE",
+    "EXISTS",
+    "FROM",
+    "HAVING",
+    "IN",
+    "IS",
+    "JOIN",
+    "LATERAL",
+    "LIKE",
+    "NOT",
+    "ON",
+    "OR",
+    "RECURSIVE",
+    "RETURN",
+    "SELECT",
+    "SET",
+    "SOME",
+    "THEN",
+    "UNION",
+    "USING",
+    "VALUE",
+    "VALUES",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+/// Words that end a table list instead of naming a table
+const NON_TABLE_WORDS: &[&str] = &["DUAL", "DUMPFILE", "LATERAL", "OUTFILE", "SELECT"];
+
+/// Words that follow a table in a table list instead of aliasing it
+const NON_ALIAS_WORDS: &[&str] = &[
+    "CROSS",
+    "EXCEPT",
+    "FOR",
+    "FORCE",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "IGNORE",
+    "INNER",
+    "INTERSECT",
+    "INTO",
+    "JOIN",
+    "LEFT",
+    "LIMIT",
+    "LOCK",
+    "NATURAL",
+    "ON",
+    "ORDER",
+    "OUTER",
+    "PARTITION",
+    "RETURNING",
+    "RIGHT",
+    "SELECT",
+    "SET",
+    "STRAIGHT_JOIN",
+    "UNION",
+    "USE",
+    "USING",
+    "VALUE",
+    "VALUES",
+    "WHERE",
+    "WINDOW",
+];
+
+/// Words that end the assignments of a `SET` clause
+const SET_END_WORDS: &[&str] = &["LIMIT", "ON", "ORDER", "RETURNING", "WHERE"];
+
+struct Checker<'s, 'a> {
+    snapshot: &'s SchemaSnapshot,
+    tokens: Vec<Token<'a>>,
+    ctes: Vec<Cow<'a, str>>,
+    /// Table names and aliases in the statement
+    scope: Vec<(Cow<'a, str>, &'s TableSchema)>,
+    issues: Vec<SchemaIssue>,
+}
+
+impl<'s, 'a> Checker<'s, 'a> {
+    fn report(&mut self, issue: SchemaIssue) {
+        if !self.issues.contains(&issue) {
+            self.issues.push(issue);
+        }
+    }
+
+    fn token(&self, i: usize) -> Option<&Token<'a>> {
+        self.tokens.get(i)
+    }
+
+    fn symbol_at(&self, i: usize, symbol: &str) -> bool {
+        self.token(i)
+            .is_some_and(|token| token.kind == TokenKind::Symbol && token.text == symbol)
+    }
+
+    /// Walk the statement and check the tables of `FROM`, `JOIN`, `UPDATE` and `INTO`,
+    /// skipping the arguments of function calls such as `EXTRACT(YEAR FROM d)`.
+    fn check_tables(&mut self) {
+        // Whether each open parenthesis is a function call
+        let mut parens: Vec<bool> = Vec::new();
+        // The table of the latest `UPDATE` or `INTO`, for `SET` assignments
+        let mut target = None;
+        let mut i = 0;
+        while let Some(&token) = self.token(i) {
+            let previous = i.checked_sub(1).and_then(|p| self.token(p)).copied();
+            i += 1;
+            if token.kind == TokenKind::Symbol {
+                match token.text {
+                    "(" => parens.push(previous.is_some_and(|prev| is_function_name(&prev))),
+                    ")" => {
+                        parens.pop();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if parens.last() == Some(&true) {
+                continue;
+            }
+            if is_any_word(&token, &["FROM", "JOIN"]) {
+                i = self.read_tables(i, true).0;
+            } else if is_word(&token, "UPDATE")
+                && !previous.is_some_and(|prev| is_any_word(&prev, &["FOR", "KEY"]))
+            {
+                let (next, tables) = self.read_tables(i, true);
+                target = single(&tables);
+                i = next;
+            } else if is_word(&token, "INTO") {
+                let (next, tables) = self.read_tables(i, false);
+                target = single(&tables);
+                i = next;
+                if let Some(table) = target {
+                    i = self.check_insert_columns(i, table);
+                }
+            } else if is_word(&token, "SET")
+                && let Some(table) = target
+            {
+                self.check_assignments(i, table);
+            }
+        }
+    }
+
+    /// Read a table list starting at `i`, returning the index after it and the known tables.
+    fn read_tables(&mut self, mut i: usize, allow_list: bool) -> (usize, Vec<&'s TableSchema>) {
+        let mut tables = Vec::new();
+        while let Some(&token) = self.token(i) {
+            let Some(table) = name(&token).filter(|_| !is_any_word(&token, NON_TABLE_WORDS)) else {
+                break;
+            };
+            i += 1;
+            // Database-qualified tables are not in the snapshot
+            let qualified = self.symbol_at(i, ".");
+            if qualified {
+                i += 2;
+            }
+            if is_word_at(self.token(i), "AS") {
+                i += 1;
+            }
+            let alias = self
+                .token(i)
+                .filter(|next| !is_any_word(next, NON_ALIAS_WORDS))
+                .and_then(name);
+            if alias.is_some() {
+                i += 1;
+            }
+            let is_cte = self.ctes.iter().any(|cte| cte.eq_ignore_ascii_case(&table));
+            if !qualified && !is_cte {
+                match self.snapshot.table(&table) {
+                    Some(schema) => {
+                        tables.push(schema);
+                        self.scope.push((table, schema));
+                        if let Some(alias) = alias {
+                            self.scope.push((alias, schema));
+                        }
+                    }
+                    None => self.report(SchemaIssue::UnknownTable(table.into_owned())),
+                }
+            }
+            if !allow_list || !self.symbol_at(i, ",") {
+                break;
+            }
+            i += 1;
+        }
+        (i, tables)
+    }
+
+    /// Check the column list of `INSERT INTO table (...)` at `i`.
+    fn check_insert_columns(&mut self, mut i: usize, table: &'s TableSchema) -> usize {
+        let is_subquery = self
+            .token(i + 1)
+            .is_some_and(|token| is_any_word(token, &["SELECT", "WITH"]));
+        if !self.symbol_at(i, "(") || is_subquery {
+            return i;
+        }
+        i += 1;
+        while let Some(&token) = self.token(i) {
+            i += 1;
+            if token.text == ")" {
+                break;
+            }
+            if let Some(column) = name(&token) {
+                self.check_column(table, &column);
+            }
+        }
+        i
+    }
+
+    /// Check the targets of `SET a = ..., b = ...` starting at `i`.
+    fn check_assignments(&mut self, mut i: usize, table: &'s TableSchema) {
+        let mut depth = 0_usize;
+        let mut expect_target = true;
+        while let Some(&token) = self.token(i) {
+            i += 1;
+            match token.text {
+                "(" => depth += 1,
+                ")" if depth == 0 => return,
+                ")" => depth -= 1,
+                ";" => return,
+                "," if depth == 0 => expect_target = true,
+                _ if depth == 0 && is_any_word(&token, SET_END_WORDS) => return,
+                _ if expect_target => {
+                    expect_target = false;
+                    if self.symbol_at(i, "=")
+                        && let Some(column) = name(&token)
+                    {
+                        self.check_column(table, &column);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Check every `qualifier.column` whose qualifier is a table or alias in the statement.
+    fn check_qualified_columns(&mut self) {
+        for i in 0..self.tokens.len() {
+            if !self.symbol_at(i + 1, ".")
+                || self.symbol_at(i + 3, ".")
+                || self.symbol_at(i + 3, "(")
+            {
+                continue;
+            }
+            let (Some(qualifier), Some(column)) = (
+                self.token(i).and_then(name),
+                self.token(i + 2).and_then(name),
+            ) else {
+                continue;
+            };
+            let tables: Vec<&TableSchema> = self
+                .scope
+                .iter()
+                .filter(|(scope_name, _)| scope_name.eq_ignore_ascii_case(&qualifier))
+                .map(|(_, table)| *table)
+                .collect();
+            if let Some(first) = tables.first()
+                && tables.iter().all(|table| table.column(&column).is_none())
+            {
+                self.report(SchemaIssue::UnknownColumn {
+                    table: first.name.clone(),
+                    column: column.into_owned(),
+                });
+            }
+        }
+    }
+
+    fn check_column(&mut self, table: &TableSchema, column: &str) {
+        if table.column(column).is_none() {
+            self.report(SchemaIssue::UnknownColumn {
+                table: table.name.clone(),
+                column: column.to_string(),
+            });
+        }
+    }
+}
+
+fn single<'s>(tables: &[&'s TableSchema]) -> Option<&'s TableSchema> {
+    match tables {
+        [table] => Some(table),
+        _ => None,
+    }
+}
+
+/// Names declared with `name AS (` or `name (columns) AS (`
+fn cte_names<'a>(tokens: &[Token<'a>]) -> Vec<Cow<'a, str>> {
+    let mut names = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let declares = match tokens.get(i + 1) {
+            Some(next) if is_word(next, "AS") => tokens.get(i + 2).is_some_and(|t| t.text == "("),
+            Some(next) if next.text == "(" => {
+                let after = tokens
+                    .iter()
+                    .skip(i + 2)
+                    .position(|t| t.text == ")")
+                    .map_or(tokens.len(), |close| i + 2 + close + 1);
+                is_word_at(tokens.get(after), "AS")
+                    && tokens.get(after + 1).is_some_and(|t| t.text == "(")
+            }
+            _ => false,
+        };
+        if declares && let Some(cte) = name(token) {
+            names.push(cte);
+        }
+    }
+    names
+}
+
+/// An unquoted identifier, or `None` for keywords-like tokens that cannot name an object
+fn name<'a>(token: &Token<'a>) -> Option<Cow<'a, str>> {
+    match token.kind {
+        TokenKind::Word if !token.text.starts_with('@') => Some(Cow::Borrowed(token.text)),
+        TokenKind::Identifier => {
+            let inner = token.text.get(1..token.text.len().saturating_sub(1))?;
+            Some(if inner.contains("``") {
+                Cow::Owned(inner.replace("``", "`"))
+            } else {
+                Cow::Borrowed(inner)
+            })
+        }
+        _ => None,
+    }
+}
+
+fn is_function_name(token: &Token<'_>) -> bool {
+    match token.kind {
+        TokenKind::Word => !is_any_word(token, NON_FUNCTION_WORDS),
+        TokenKind::Identifier => true,
+        _ => false,
+    }
+}
+
+fn is_word(token: &Token<'_>, word: &str) -> bool {
+    token.kind == TokenKind::Word && token.text.eq_ignore_ascii_case(word)
+}
+
+fn is_word_at(token: Option<&Token<'_>>, word: &str) -> bool {
+    token.is_some_and(|token| is_word(token, word))
+}
+
+fn is_any_word(token: &Token<'_>, words: &[&str]) -> bool {
+    words.iter().any(|word| is_word(token, word))
+}
+
+fn invalid_snapshot(message: &str) -> Error {
+    Error::BadUsageError(format!("Invalid schema snapshot: {message}"))
+}
+
+fn push_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            control if u32::from(control) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", u32::from(control)));
+            }
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// The subset of JSON values needed for snapshots; numbers are skipped
+enum Json {
+    Null,
+    Bool(bool),
+    Number,
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn field(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, message: &str) -> Error {
+        invalid_snapshot(&format!("{message} at byte {}", self.pos))
+    }
+
+    fn parse_document(&mut self) -> Result<Json> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.pos < self.bytes.len() {
+            return Err(self.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", byte as char)))
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Json) -> Result<Json> {
+        if self.bytes.get(self.pos..self.pos + literal.len()) == Some(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Json> {
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => Ok(Json::String(self.parse_string()?)),
+            Some(b't') => self.parse_literal("true", Json::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Json::Bool(false)),
+            Some(b'n') => self.parse_literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => {
+                while self.bytes.get(self.pos).is_some_and(|&b| {
+                    b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E')
+                }) {
+                    self.pos += 1;
+                }
+                Ok(Json::Number)
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            fields.push((key, self.parse_value()?));
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut out = Vec::new();
+        loop {
+            let Some(&byte) = self.bytes.get(self.pos) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => self.parse_escape(&mut out)?,
+                _ => out.push(byte),
+            }
+        }
+        String::from_utf8(out).map_err(|e| self.error(&e.to_string()))
+    }
+
+    fn parse_escape(&mut self, out: &mut Vec<u8>) -> Result<()> {
+        let Some(&byte) = self.bytes.get(self.pos) else {
+            return Err(self.error("unterminated escape"));
+        };
+        self.pos += 1;
+        let c = match byte {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.parse_hex4()?;
+                let code = if (0xD800..0xDC00).contains(&high)
+                    && self.bytes.get(self.pos..self.pos + 2) == Some(b"\\u")
+                {
+                    self.pos += 2;
+                    let low = self.parse_hex4()?;
+                    0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                } else {
+                    high
+                };
+                char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        let mut buf = [0; 4];
+        out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        Ok(())
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32> {
+        let hex = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.pos += 4;
+        Ok(hex)
+    }
+}
@@ -0,0 +1,123 @@
+use crate::schema_check::{
+    ColumnSchema, SchemaIssue, SchemaSnapshot, TableSchema, split_statements,
+};
+use crate::test_macros::{check, check_eq};
+
+fn column(name: &str) -> ColumnSchema {
+    ColumnSchema {
+        name: name.to_string(),
+        column_type: "bigint".to_string(),
+        nullable: false,
+    }
+}
+
+fn snapshot() -> SchemaSnapshot {
+    SchemaSnapshot {
+        tables: vec![
+            TableSchema {
+                name: "users".to_string(),
+                columns: vec![column("id"), column("name"), column("created_at")],
+            },
+            TableSchema {
+                name: "orders".to_string(),
+                columns: vec![column("id"), column("user_id"), column("amount")],
+            },
+        ],
+    }
+}
+
+fn unknown_column(table: &str, column: &str) -> SchemaIssue {
+    SchemaIssue::UnknownColumn {
+        table: table.to_string(),
+        column: column.to_string(),
+    }
+}
+
+#[test]
+fn json_round_trip() -> crate::error::Result<()> {
+    let mut snapshot = snapshot();
+    snapshot.push_column(
+        "orders",
+        ColumnSchema {
+            name: "note \"quoted\"\n".to_string(),
+            column_type: "varchar(50)".to_string(),
+            nullable: true,
+        },
+    );
+    snapshot.push_column("tags", column("id"));
+    check_eq!(snapshot.tables.len(), 3);
+    check_eq!(SchemaSnapshot::from_json(&snapshot.to_json())?, snapshot);
+
+    let parsed = SchemaSnapshot::from_json(
+        r#"{"version": 1.5e3, "tables": [{"name": "t\u00e9", "columns": [{"name": "a"}]}]}"#,
+    )?;
+    check_eq!(parsed.tables.len(), 1);
+    check!(parsed.table("T\u{e9}").is_some());
+    check!(SchemaSnapshot::from_json(r#"{"tables": [}"#).is_err());
+    check!(SchemaSnapshot::from_json(r#"{"tables": [{"columns": []}]}"#).is_err());
+    Ok(())
+}
+
+#[test]
+fn reports_unknown_tables_and_columns() -> crate::error::Result<()> {
+    let snapshot = snapshot();
+    check_eq!(
+        snapshot.check("SELECT u.name, o.total FROM users u JOIN orders AS o ON o.user_id = u.id"),
+        vec![unknown_column("orders", "total")]
+    );
+    check_eq!(
+        snapshot.check("SELECT * FROM users, payments WHERE users.email = ?"),
+        vec![
+            SchemaIssue::UnknownTable("payments".to_string()),
+            unknown_column("users", "email"),
+        ]
+    );
+    check_eq!(
+        snapshot.check("INSERT INTO `users` (id, `nick`) VALUES (?, ?)"),
+        vec![unknown_column("users", "nick")]
+    );
+    check_eq!(
+        snapshot.check("UPDATE users SET name = CONCAT(name, ?), age = ? WHERE id = ?"),
+        vec![unknown_column("users", "age")]
+    );
+    check_eq!(
+        snapshot.check("DELETE FROM sessions WHERE id IN (SELECT id FROM users)"),
+        vec![SchemaIssue::UnknownTable("sessions".to_string())]
+    );
+    Ok(())
+}
+
+#[test]
+fn accepts_valid_statements() -> crate::error::Result<()> {
+    let snapshot = snapshot();
+    for sql in [
+        "SELECT id, EXTRACT(YEAR FROM created_at) FROM users WHERE id = ? FOR UPDATE",
+        "SELECT TRIM(LEADING 'x' FROM name) FROM users",
+        "WITH recent AS (SELECT id FROM orders) SELECT r.anything FROM recent r",
+        "WITH totals (user_id, n) AS (SELECT user_id, COUNT(*) FROM orders GROUP BY user_id) \
+         SELECT t.n FROM totals t",
+        "INSERT INTO orders (user_id, amount) VALUES (?, ?) \
+         ON DUPLICATE KEY UPDATE amount = VALUES(amount)",
+        "INSERT INTO users SET name = ?",
+        "SELECT 1 FROM DUAL",
+        "SELECT * FROM information_schema.COLUMNS",
+        "SELECT d.x FROM (SELECT 1 AS x) AS d",
+        "SELECT id INTO @id FROM users LIMIT 1",
+        "SELECT ':users.email', `users.email` FROM users -- users.email",
+        "SHOW TABLES FROM other_db",
+        "CREATE TABLE audit (id BIGINT)",
+    ] {
+        check_eq!((sql, snapshot.check(sql)), (sql, vec![]));
+    }
+    Ok(())
+}
+
+#[test]
+fn split_scripts() -> crate::error::Result<()> {
+    check_eq!(
+        split_statements("SELECT 1;\n\n  SELECT ';'\n  FROM t;\n-- done\n;"),
+        vec![(1, "SELECT 1"), (3, "SELECT ';'\n  FROM t"), (5, "-- done")]
+    );
+    check_eq!(split_statements(" ; \n"), vec![]);
+    Ok(())
+}
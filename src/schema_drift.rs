@@ -0,0 +1,121 @@
+//! Result set schema drift detection.
+//!
+//! An online schema change (`ALTER TABLE`, a recreated view, ...) can change the columns a
+//! prepared statement returns while the statement stays cached. Set
+//! [`Opts::schema_drift`](crate::Opts::schema_drift) to receive a [`SchemaDrift`] when the
+//! columns of an execution differ from the previous execution of the same statement, before the
+//! change surfaces as a decode error somewhere else.
+//!
+//! The columns are compared by count, name, type and signedness. Only successful executions are
+//! compared; for statements returning several result sets, the last result set is compared.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::constant::{ColumnFlags, ColumnType};
+use crate::protocol::command::ColumnDefinition;
+
+/// A result set column, as compared between executions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSummary {
+    /// The column name or alias.
+    pub name: String,
+    pub column_type: ColumnType,
+    pub unsigned: bool,
+}
+
+impl ColumnSummary {
+    /// `None` if the column type is unknown.
+    pub(crate) fn new(col: &ColumnDefinition<'_>) -> Option<Self> {
+        Some(Self {
+            name: String::from_utf8_lossy(col.name_alias).into_owned(),
+            column_type: col.tail.column_type().ok()?,
+            unsigned: col
+                .tail
+                .flags()
+                .is_ok_and(|flags| flags.contains(ColumnFlags::UNSIGNED_FLAG)),
+        })
+    }
+
+    /// Compare without allocating.
+    pub(crate) fn matches(&self, col: &ColumnDefinition<'_>) -> bool {
+        self.name.as_bytes() == col.name_alias
+            && col.tail.column_type().ok() == Some(self.column_type)
+            && col
+                .tail
+                .flags()
+                .is_ok_and(|flags| flags.contains(ColumnFlags::UNSIGNED_FLAG))
+                == self.unsigned
+    }
+}
+
+/// The columns of a prepared statement changed between two executions.
+#[derive(Debug, Clone)]
+pub struct SchemaDrift {
+    pub connection_id: u64,
+    pub statement_id: u32,
+    /// Only available if `Opts::retain_statement_sql` was set when the statement was prepared.
+    pub sql: Option<String>,
+    /// The columns of the previous execution.
+    pub before: Vec<ColumnSummary>,
+    /// The columns of this execution.
+    pub after: Vec<ColumnSummary>,
+}
+
+impl SchemaDrift {
+    /// The positions of the columns that were added, removed, renamed or retyped.
+    pub fn changed_columns(&self) -> Vec<usize> {
+        (0..self.before.len().max(self.after.len()))
+            .filter(|&i| self.before.get(i) != self.after.get(i))
+            .collect()
+    }
+}
+
+/// `stmt#3 on conn#42: 2 -> 3 columns, changed [1, 2]`
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stmt#{} on conn#{}: {} -> {} columns, changed {:?}",
+            self.statement_id,
+            self.connection_id,
+            self.before.len(),
+            self.after.len(),
+            self.changed_columns()
+        )
+    }
+}
+
+/// A shared callback for [`SchemaDrift`] events.
+///
+/// ```
+/// use zero_mysql::Opts;
+/// use zero_mysql::schema_drift::{SchemaDrift, SchemaDriftHook};
+///
+/// let mut opts = Opts::default();
+/// opts.schema_drift = Some(SchemaDriftHook::new(|drift: &SchemaDrift| {
+///     eprintln!("schema drift: {drift}");
+/// }));
+/// ```
+#[derive(Clone)]
+pub struct SchemaDriftHook {
+    hook: Arc<dyn Fn(&SchemaDrift) + Send + Sync>,
+}
+
+impl SchemaDriftHook {
+    pub fn new(hook: impl Fn(&SchemaDrift) + Send + Sync + 'static) -> Self {
+        Self {
+            hook: Arc::new(hook),
+        }
+    }
+
+    pub(crate) fn report(&self, drift: &SchemaDrift) {
+        (self.hook)(drift)
+    }
+}
+
+impl fmt::Debug for SchemaDriftHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaDriftHook").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,89 @@
+use zerocopy::FromBytes;
+
+use crate::PreparedStatement;
+use crate::constant::ColumnType;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail, ColumnDefinitions};
+use crate::schema_drift::{ColumnSummary, SchemaDrift};
+use crate::test_macros::{check, check_eq};
+
+/// LONGLONG, NOT NULL
+const BIGINT: [u8; 12] = [0x3F, 0, 20, 0, 0, 0, 0x08, 0x01, 0, 0, 0, 0];
+/// LONGLONG, NOT NULL | UNSIGNED
+const BIGINT_UNSIGNED: [u8; 12] = [0x3F, 0, 20, 0, 0, 0, 0x08, 0x21, 0, 0, 0, 0];
+/// VAR_STRING
+const VARCHAR: [u8; 12] = [0x21, 0, 0xFF, 0, 0, 0, 0xFD, 0, 0, 0, 0, 0];
+
+fn columns(cols: &[(&[u8], &[u8; 12])]) -> crate::error::Result<ColumnDefinitions> {
+    let defs = cols
+        .iter()
+        .map(|(name, tail)| {
+            Ok(ColumnDefinition {
+                schema: b"test",
+                table_alias: b"t",
+                table_original: b"t",
+                name_alias: name,
+                name_original: name,
+                tail: ColumnDefinitionTail::ref_from_bytes(*tail)?,
+                name_id: None,
+            })
+        })
+        .collect::<crate::error::Result<Vec<_>>>()?;
+    ColumnDefinitions::from_definitions(&defs)
+}
+
+#[test]
+fn track_columns_reports_changes() -> crate::error::Result<()> {
+    let mut stmt = PreparedStatement::new(1);
+    stmt.set_column_definitions(columns(&[(b"id", &BIGINT), (b"name", &VARCHAR)])?);
+    check_eq!(stmt.track_columns(), None);
+    check_eq!(stmt.columns_seen().len(), 2);
+
+    // Same columns in a new packet buffer
+    stmt.set_column_definitions(columns(&[(b"id", &BIGINT), (b"name", &VARCHAR)])?);
+    check_eq!(stmt.track_columns(), None);
+
+    stmt.set_column_definitions(columns(&[(b"id", &BIGINT_UNSIGNED), (b"name", &VARCHAR)])?);
+    let previous = stmt.track_columns();
+    check_eq!(
+        previous.as_ref().and_then(|previous| previous.first()),
+        Some(&ColumnSummary {
+            name: "id".to_string(),
+            column_type: ColumnType::MYSQL_TYPE_LONGLONG,
+            unsigned: false,
+        })
+    );
+    check!(stmt.columns_seen().first().is_some_and(|id| id.unsigned));
+
+    stmt.set_column_definitions(columns(&[(b"id", &BIGINT_UNSIGNED)])?);
+    check_eq!(stmt.track_columns().map(|seen| seen.len()), Some(2));
+    Ok(())
+}
+
+#[test]
+fn changed_columns() -> crate::error::Result<()> {
+    let column = |name: &str, column_type| ColumnSummary {
+        name: name.to_string(),
+        column_type,
+        unsigned: false,
+    };
+    let drift = SchemaDrift {
+        connection_id: 42,
+        statement_id: 3,
+        sql: None,
+        before: vec![
+            column("id", ColumnType::MYSQL_TYPE_LONGLONG),
+            column("name", ColumnType::MYSQL_TYPE_VAR_STRING),
+        ],
+        after: vec![
+            column("id", ColumnType::MYSQL_TYPE_LONGLONG),
+            column("name", ColumnType::MYSQL_TYPE_BLOB),
+            column("email", ColumnType::MYSQL_TYPE_VAR_STRING),
+        ],
+    };
+    check_eq!(drift.changed_columns(), vec![1, 2]);
+    check_eq!(
+        drift.to_string(),
+        "stmt#3 on conn#42: 2 -> 3 columns, changed [1, 2]"
+    );
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! The backend instance a connection landed on.
+//!
+//! Behind a load balancer or a proxy, the host in `Opts` does not say which server a connection
+//! reached. With `Opts::server_info`, the connection reads `@@version_comment`, `@@hostname`,
+//! `@@server_id` and `@@read_only` in one query right after connecting, and `Conn::server_info()`
+//! returns them, so that pools and applications can log the exact instance.
+
+use std::fmt;
+
+use crate::error::{Error, Result, eyre};
+use crate::protocol::TextRowPayload;
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::primitive::read_string_lenenc;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::TextResultSetHandler;
+
+pub(crate) const SERVER_INFO_SQL: &str =
+    "SELECT @@version_comment, @@hostname, @@server_id, @@read_only";
+
+/// The server a connection is connected to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInfo {
+    /// Example: `"MySQL Community Server - GPL"`
+    pub version_comment: String,
+    /// The host name of the server machine, not the host in `Opts`
+    pub hostname: String,
+    /// Unique per server in a replication topology
+    pub server_id: u32,
+    /// `true` on replicas that reject writes
+    pub read_only: bool,
+}
+
+/// `hostname#server_id`, with `(read-only)` for read-only servers
+impl fmt::Display for ServerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.hostname, self.server_id)?;
+        if self.read_only {
+            f.write_str(" (read-only)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the row of [`SERVER_INFO_SQL`]
+#[derive(Default)]
+pub(crate) struct ServerInfoHandler {
+    info: Option<ServerInfo>,
+}
+
+impl ServerInfoHandler {
+    pub(crate) fn into_info(self) -> Result<ServerInfo> {
+        self.info
+            .ok_or_else(|| Error::LibraryBug(eyre!("server info query returned no row")))
+    }
+}
+
+/// A text value, empty for NULL
+fn read_value(data: &[u8]) -> Result<(String, &[u8])> {
+    if let Some((&0xFB, rest)) = data.split_first() {
+        return Ok((String::new(), rest));
+    }
+    let (value, rest) = read_string_lenenc(data)?;
+    Ok((String::from_utf8_lossy(value).into_owned(), rest))
+}
+
+impl TextResultSetHandler for ServerInfoHandler {
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        let (version_comment, rest) = read_value(row.0)?;
+        let (hostname, rest) = read_value(rest)?;
+        let (server_id, rest) = read_value(rest)?;
+        let (read_only, _) = read_value(rest)?;
+        let server_id = server_id
+            .parse()
+            .map_err(|_err| Error::LibraryBug(eyre!("invalid @@server_id '{}'", server_id)))?;
+        self.info = Some(ServerInfo {
+            version_comment,
+            hostname,
+            server_id,
+            read_only: read_only != "0" && !read_only.eq_ignore_ascii_case("OFF"),
+        });
+        Ok(())
+    }
+}
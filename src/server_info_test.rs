@@ -0,0 +1,62 @@
+use crate::error::Result;
+use crate::protocol::TextRowPayload;
+use crate::protocol::r#trait::TextResultSetHandler;
+use crate::server_info::{ServerInfo, ServerInfoHandler};
+use crate::test_macros::{check, check_eq};
+
+/// A text protocol row of length-encoded values, `None` for NULL
+fn text_row(values: &[Option<&str>]) -> Vec<u8> {
+    let mut row = Vec::new();
+    for value in values {
+        match value {
+            Some(value) => {
+                row.push(value.len() as u8);
+                row.extend_from_slice(value.as_bytes());
+            }
+            None => row.push(0xFB),
+        }
+    }
+    row
+}
+
+#[test]
+fn collect_server_info() -> Result<()> {
+    let mut handler = ServerInfoHandler::default();
+    let row = text_row(&[
+        Some("MySQL Community Server - GPL"),
+        Some("db-3"),
+        Some("3"),
+        Some("1"),
+    ]);
+    handler.row(&[], TextRowPayload(&row))?;
+    let info = handler.into_info()?;
+    check_eq!(
+        info,
+        ServerInfo {
+            version_comment: "MySQL Community Server - GPL".to_string(),
+            hostname: "db-3".to_string(),
+            server_id: 3,
+            read_only: true,
+        }
+    );
+    check_eq!(info.to_string(), "db-3#3 (read-only)");
+    Ok(())
+}
+
+#[test]
+fn null_version_comment_and_writable_server() -> Result<()> {
+    let mut handler = ServerInfoHandler::default();
+    let row = text_row(&[None, Some("primary"), Some("1"), Some("0")]);
+    handler.row(&[], TextRowPayload(&row))?;
+    let info = handler.into_info()?;
+    check_eq!(info.version_comment, "");
+    check!(!info.read_only);
+    check_eq!(info.to_string(), "primary#1");
+    Ok(())
+}
+
+#[test]
+fn missing_row_is_an_error() -> Result<()> {
+    check!(ServerInfoHandler::default().into_info().is_err());
+    Ok(())
+}
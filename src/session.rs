@@ -0,0 +1,147 @@
+//! Moving a logical session to another physical connection.
+//!
+//! After a failover, a new connection starts with a fresh session: the default database,
+//! the session system variables and the user variables of the old one are gone.
+//! `Conn::snapshot_session` captures them into a [`SessionSnapshot`] while the old connection
+//! is healthy, and `Conn::restore_session` replays it on the new connection.
+//!
+//! - The default database is read with `SELECT DATABASE()`.
+//! - Session system variables are the ones the server reported through session tracking
+//!   (`session_track_system_variables`) since the connection was opened, reset or re-authenticated.
+//!   Variables that are not tracked are not captured.
+//! - User variables are read from `performance_schema.user_variables_by_thread` on MySQL and
+//!   `information_schema.USER_VARIABLES` on MariaDB, and restored as strings.
+//!
+//! Temporary tables, prepared statements, locks and open transactions are not captured.
+
+use crate::protocol::response::SessionStateChange;
+use crate::quote::{quote_identifier, quote_string};
+
+pub(crate) const DATABASE_SQL: &str = "SELECT DATABASE()";
+
+/// Works on MySQL 5.7, unlike `PS_CURRENT_THREAD_ID()`
+pub(crate) const MYSQL_USER_VARIABLES_SQL: &str = "SELECT VARIABLE_NAME, VARIABLE_VALUE \
+     FROM performance_schema.user_variables_by_thread \
+     WHERE THREAD_ID = (SELECT THREAD_ID FROM performance_schema.threads \
+     WHERE PROCESSLIST_ID = CONNECTION_ID())";
+
+pub(crate) const MARIADB_USER_VARIABLES_SQL: &str =
+    "SELECT VARIABLE_NAME, VARIABLE_VALUE FROM information_schema.USER_VARIABLES";
+
+/// The state of a session, captured by `Conn::snapshot_session`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionSnapshot {
+    /// The default database, `None` if none is selected
+    pub database: Option<String>,
+    /// Tracked session system variables, in the order they were first set
+    pub system_variables: Vec<(String, String)>,
+    /// User variables without the `@`, `None` for `NULL`
+    pub user_variables: Vec<(String, Option<String>)>,
+}
+
+impl SessionSnapshot {
+    /// The statements `Conn::restore_session` runs: a `USE` and a single `SET` of all variables.
+    pub fn restore_statements(&self) -> Vec<String> {
+        let mut statements = Vec::new();
+        if let Some(database) = &self.database {
+            statements.push(format!("USE {}", quote_identifier(database)));
+        }
+        let assignments: Vec<String> = self
+            .system_variables
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "SESSION {} = {}",
+                    quote_identifier(name),
+                    system_variable_literal(value)
+                )
+            })
+            .chain(self.user_variables.iter().map(|(name, value)| {
+                let literal = match value {
+                    Some(value) => user_variable_literal(value),
+                    None => "NULL".to_string(),
+                };
+                format!("@{} = {literal}", quote_identifier(name))
+            }))
+            .collect();
+        if !assignments.is_empty() {
+            statements.push(format!("SET {}", assignments.join(", ")));
+        }
+        statements
+    }
+}
+
+/// Numeric system variables reject string literals
+fn system_variable_literal(value: &str) -> String {
+    let numeric = !value.is_empty()
+        && value
+            .strip_prefix('-')
+            .unwrap_or(value)
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b == b'.');
+    if numeric {
+        value.to_string()
+    } else {
+        quote_string(value)
+    }
+}
+
+/// A hex literal survives any `sql_mode` and character set
+fn user_variable_literal(value: &str) -> String {
+    if value.is_empty() {
+        return "''".to_string();
+    }
+    let mut out = String::with_capacity(value.len() * 2 + 26);
+    out.push_str("CONVERT(X'");
+    for byte in value.bytes() {
+        out.push_str(&format!("{byte:02X}"));
+    }
+    out.push_str("' USING utf8mb4)");
+    out
+}
+
+/// Parse the rows of [`MYSQL_USER_VARIABLES_SQL`] or [`MARIADB_USER_VARIABLES_SQL`].
+pub(crate) fn parse_user_variables(
+    rows: Vec<Vec<Option<String>>>,
+) -> Vec<(String, Option<String>)> {
+    rows.into_iter()
+        .filter_map(|row| {
+            let mut values = row.into_iter();
+            let name = values.next().flatten()?;
+            Some((name, values.next().flatten()))
+        })
+        .collect()
+}
+
+/// The session system variables reported by session tracking.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrackedVariables {
+    variables: Vec<(String, String)>,
+}
+
+impl TrackedVariables {
+    pub(crate) fn apply(&mut self, changes: &[SessionStateChange]) {
+        for change in changes {
+            let SessionStateChange::SystemVariable { name, value } = change else {
+                continue;
+            };
+            match self
+                .variables
+                .iter_mut()
+                .find(|(tracked, _)| tracked.eq_ignore_ascii_case(name))
+            {
+                Some((_, tracked_value)) => tracked_value.clone_from(value),
+                None => self.variables.push((name.clone(), value.clone())),
+            }
+        }
+    }
+
+    /// The session was reset
+    pub(crate) fn clear(&mut self) {
+        self.variables.clear();
+    }
+
+    pub(crate) fn to_vec(&self) -> Vec<(String, String)> {
+        self.variables.clone()
+    }
+}
@@ -0,0 +1,83 @@
+use crate::protocol::response::SessionStateChange;
+use crate::session::{SessionSnapshot, TrackedVariables, parse_user_variables};
+use crate::test_macros::{check, check_eq};
+
+fn variable(name: &str, value: &str) -> SessionStateChange {
+    SessionStateChange::SystemVariable {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[test]
+fn tracked_variables_keep_the_last_value() -> crate::error::Result<()> {
+    let mut tracked = TrackedVariables::default();
+    tracked.apply(&[
+        variable("time_zone", "+09:00"),
+        SessionStateChange::Schema("app".to_string()),
+        variable("autocommit", "OFF"),
+    ]);
+    tracked.apply(&[variable("TIME_ZONE", "+00:00")]);
+    check_eq!(
+        tracked.to_vec(),
+        vec![
+            ("time_zone".to_string(), "+00:00".to_string()),
+            ("autocommit".to_string(), "OFF".to_string()),
+        ]
+    );
+
+    tracked.clear();
+    check!(tracked.to_vec().is_empty());
+    Ok(())
+}
+
+#[test]
+fn restore_statements() -> crate::error::Result<()> {
+    let snapshot = SessionSnapshot {
+        database: Some("app`db".to_string()),
+        system_variables: vec![
+            ("time_zone".to_string(), "+00:00".to_string()),
+            ("sql_select_limit".to_string(), "100".to_string()),
+            ("sql_mode".to_string(), "ANSI_QUOTES".to_string()),
+        ],
+        user_variables: vec![
+            ("tenant".to_string(), Some("it's".to_string())),
+            ("cursor".to_string(), None),
+            ("empty".to_string(), Some(String::new())),
+        ],
+    };
+    check_eq!(
+        snapshot.restore_statements(),
+        vec![
+            "USE `app``db`".to_string(),
+            "SET SESSION `time_zone` = '+00:00', SESSION `sql_select_limit` = 100, \
+             SESSION `sql_mode` = 'ANSI_QUOTES', @`tenant` = CONVERT(X'69742773' USING utf8mb4), \
+             @`cursor` = NULL, @`empty` = ''"
+                .to_string(),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn restore_nothing() -> crate::error::Result<()> {
+    check!(SessionSnapshot::default().restore_statements().is_empty());
+    Ok(())
+}
+
+#[test]
+fn user_variable_rows() -> crate::error::Result<()> {
+    let rows = vec![
+        vec![Some("a".to_string()), Some("1".to_string())],
+        vec![Some("b".to_string()), None],
+        vec![None, Some("skipped".to_string())],
+    ];
+    check_eq!(
+        parse_user_variables(rows),
+        vec![
+            ("a".to_string(), Some("1".to_string())),
+            ("b".to_string(), None)
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! Consistent snapshots for change data capture bootstrapping.
+//!
+//! A CDC pipeline first copies the existing rows and then follows the binary log.
+//! The copy and the binlog position must describe the same point in time,
+//! otherwise changes are lost or applied twice.
+//! `Conn::start_snapshot` opens a `WITH CONSISTENT SNAPSHOT` transaction and records the
+//! binlog position of that snapshot; every table read through the returned `Snapshot`
+//! sees the data as of that position.
+//!
+//! On MySQL, the position is read under a short `FLUSH TABLES WITH READ LOCK`, which requires
+//! the `RELOAD` privilege. On MariaDB, the position of the snapshot is exposed by the
+//! `binlog_snapshot_file` and `binlog_snapshot_position` status variables and no lock is taken.
+
+use crate::error::{Error, Result};
+use crate::quote::quote_identifier;
+
+/// A position in the binary log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinlogPosition {
+    /// Binary log file name, e.g. `binlog.000042`
+    pub file: String,
+    /// Byte offset in `file`
+    pub position: u64,
+    /// Executed GTID set (MySQL) or GTID position (MariaDB), if GTIDs are in use
+    pub gtid_set: Option<String>,
+}
+
+pub(crate) const LOCK_SQL: &str = "FLUSH TABLES WITH READ LOCK";
+pub(crate) const UNLOCK_SQL: &str = "UNLOCK TABLES";
+pub(crate) const START_SQL: &str = "START TRANSACTION WITH CONSISTENT SNAPSHOT, READ ONLY";
+
+/// Removed in MySQL 8.4
+pub(crate) const MASTER_STATUS_SQL: &str = "SHOW MASTER STATUS";
+/// Added in MySQL 8.2
+pub(crate) const BINARY_LOG_STATUS_SQL: &str = "SHOW BINARY LOG STATUS";
+
+pub(crate) const MARIADB_SNAPSHOT_STATUS_SQL: &str = "SHOW STATUS LIKE 'binlog_snapshot_%'";
+
+/// Parse the rows of [`MASTER_STATUS_SQL`] or [`BINARY_LOG_STATUS_SQL`].
+///
+/// The columns are `File, Position, Binlog_Do_DB, Binlog_Ignore_DB[, Executed_Gtid_Set]`.
+pub(crate) fn parse_master_status(rows: &[Vec<Option<String>>]) -> Result<BinlogPosition> {
+    let Some([Some(file), Some(position), rest @ ..]) = rows.first().map(Vec::as_slice) else {
+        return Err(binlog_disabled());
+    };
+    Ok(BinlogPosition {
+        file: file.clone(),
+        position: parse_position(position)?,
+        gtid_set: rest.get(2).cloned().flatten().and_then(non_empty),
+    })
+}
+
+/// Parse the rows of [`MARIADB_SNAPSHOT_STATUS_SQL`] (`Variable_name, Value`).
+pub(crate) fn parse_mariadb_snapshot_status(rows: &[Vec<Option<String>>]) -> Result<(String, u64)> {
+    let value = |name: &str| {
+        rows.iter().find_map(|row| match row.as_slice() {
+            [Some(key), Some(value)] if key.eq_ignore_ascii_case(name) => Some(value.as_str()),
+            _ => None,
+        })
+    };
+    match (
+        value("binlog_snapshot_file"),
+        value("binlog_snapshot_position"),
+    ) {
+        (Some(file), Some(position)) if !file.is_empty() => {
+            Ok((file.to_string(), parse_position(position)?))
+        }
+        _ => Err(binlog_disabled()),
+    }
+}
+
+/// `SELECT BINLOG_GTID_POS(file, position)` for MariaDB
+pub(crate) fn mariadb_gtid_pos_sql(file: &str, position: u64) -> String {
+    format!(
+        "SELECT BINLOG_GTID_POS({}, {position})",
+        crate::quote::quote_string(file)
+    )
+}
+
+/// `SELECT * FROM table`, where `table` is `name` or `schema.name`.
+///
+/// ```
+/// use zero_mysql::snapshot::select_all_sql;
+///
+/// assert_eq!(select_all_sql("orders"), "SELECT * FROM `orders`");
+/// assert_eq!(select_all_sql("shop.orders"), "SELECT * FROM `shop`.`orders`");
+/// ```
+pub fn select_all_sql(table: &str) -> String {
+    match table.split_once('.') {
+        Some((schema, name)) => format!(
+            "SELECT * FROM {}.{}",
+            quote_identifier(schema),
+            quote_identifier(name)
+        ),
+        None => format!("SELECT * FROM {}", quote_identifier(table)),
+    }
+}
+
+/// Returns the first column of the first row, if it is not empty
+pub(crate) fn first_value(rows: &[Vec<Option<String>>]) -> Option<String> {
+    rows.first()
+        .and_then(|row| row.first().cloned().flatten())
+        .and_then(non_empty)
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+fn parse_position(position: &str) -> Result<u64> {
+    position
+        .parse()
+        .map_err(|e| Error::LibraryBug(crate::error::eyre!("invalid binlog position: {e}")))
+}
+
+fn binlog_disabled() -> Error {
+    Error::Unsupported("binary logging is disabled on the server".to_string())
+}
@@ -0,0 +1,97 @@
+use crate::error::Error;
+use crate::snapshot::{
+    BinlogPosition, first_value, mariadb_gtid_pos_sql, parse_mariadb_snapshot_status,
+    parse_master_status, select_all_sql,
+};
+use crate::test_macros::{check, check_eq};
+
+fn row(values: &[Option<&str>]) -> Vec<Option<String>> {
+    values.iter().map(|v| v.map(str::to_string)).collect()
+}
+
+#[test]
+fn master_status_with_gtid() -> crate::error::Result<()> {
+    let rows = [row(&[
+        Some("binlog.000042"),
+        Some("157"),
+        Some(""),
+        Some(""),
+        Some("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-23"),
+    ])];
+    check_eq!(
+        parse_master_status(&rows)?,
+        BinlogPosition {
+            file: "binlog.000042".to_string(),
+            position: 157,
+            gtid_set: Some("3e11fa47-71ca-11e1-9e33-c80aa9429562:1-23".to_string()),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn master_status_without_gtid() -> crate::error::Result<()> {
+    // GTID mode off, and servers without the Executed_Gtid_Set column
+    for rows in [
+        [row(&[
+            Some("binlog.000001"),
+            Some("4"),
+            Some(""),
+            Some(""),
+            Some(""),
+        ])],
+        [row(&[Some("binlog.000001"), Some("4"), Some(""), Some("")])],
+    ] {
+        let position = parse_master_status(&rows)?;
+        check_eq!(position.position, 4);
+        check_eq!(position.gtid_set, None);
+    }
+    Ok(())
+}
+
+#[test]
+fn binlog_disabled() -> crate::error::Result<()> {
+    check!(matches!(
+        parse_master_status(&[]),
+        Err(Error::Unsupported(_))
+    ));
+    let rows = [
+        row(&[Some("binlog_snapshot_file"), Some("")]),
+        row(&[Some("binlog_snapshot_position"), Some("0")]),
+    ];
+    check!(matches!(
+        parse_mariadb_snapshot_status(&rows),
+        Err(Error::Unsupported(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn mariadb_snapshot_status() -> crate::error::Result<()> {
+    let rows = [
+        row(&[Some("Binlog_snapshot_file"), Some("mariadb-bin.000003")]),
+        row(&[Some("Binlog_snapshot_position"), Some("1024")]),
+        row(&[Some("Binlog_snapshot_gtid"), Some("0-1-7")]),
+    ];
+    check_eq!(
+        parse_mariadb_snapshot_status(&rows)?,
+        ("mariadb-bin.000003".to_string(), 1024)
+    );
+    check_eq!(
+        mariadb_gtid_pos_sql("mariadb-bin.000003", 1024),
+        "SELECT BINLOG_GTID_POS('mariadb-bin.000003', 1024)"
+    );
+    check_eq!(
+        first_value(&[row(&[Some("0-1-7")])]),
+        Some("0-1-7".to_string())
+    );
+    check_eq!(first_value(&[row(&[None])]), None);
+    Ok(())
+}
+
+#[test]
+fn select_all_quotes_identifiers() -> crate::error::Result<()> {
+    check_eq!(select_all_sql("a`b"), "SELECT * FROM `a``b`");
+    check_eq!(select_all_sql("db.t"), "SELECT * FROM `db`.`t`");
+    Ok(())
+}
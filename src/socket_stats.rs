@@ -0,0 +1,56 @@
+//! TCP statistics of a connection's socket.
+//!
+//! A growing round-trip time or retransmit count points at the network,
+//! while slow queries with a steady RTT point at the server.
+
+use std::time::Duration;
+
+/// Statistics from `TCP_INFO` and `SIOCOUTQ`. Only available on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketStats {
+    /// Smoothed round-trip time
+    pub rtt: Duration,
+    /// Mean deviation of the round-trip time
+    pub rtt_var: Duration,
+    /// Segments retransmitted since the connection was opened
+    pub total_retransmits: u32,
+    /// Segments sent but not acknowledged yet
+    pub unacked: u32,
+    /// Bytes in the send queue that the server has not acknowledged
+    pub send_queue_bytes: u32,
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn tcp_socket_stats(fd: std::os::fd::RawFd) -> std::io::Result<SocketStats> {
+    // SAFETY: tcp_info is plain old data
+    let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+    // SAFETY: `info` is valid for writes of `len` bytes
+    let getsockopt_ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            (&raw mut info).cast(),
+            &raw mut len,
+        )
+    };
+    if getsockopt_ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut send_queue: libc::c_int = 0;
+    // SAFETY: SIOCOUTQ (TIOCOUTQ) writes a single int
+    let ioctl_ret = unsafe { libc::ioctl(fd, libc::TIOCOUTQ, &raw mut send_queue) };
+    if ioctl_ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(SocketStats {
+        rtt: Duration::from_micros(info.tcpi_rtt.into()),
+        rtt_var: Duration::from_micros(info.tcpi_rttvar.into()),
+        total_retransmits: info.tcpi_total_retrans,
+        unacked: info.tcpi_unacked,
+        send_queue_bytes: u32::try_from(send_queue).unwrap_or_default(),
+    })
+}
@@ -0,0 +1,30 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::socket_stats::tcp_socket_stats;
+use crate::test_macros::{check, check_eq};
+
+#[test]
+fn loopback_socket_stats() -> crate::error::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let mut client = TcpStream::connect(listener.local_addr()?)?;
+    let (mut server, _) = listener.accept()?;
+    client.write_all(b"ping")?;
+    let mut buf = [0_u8; 4];
+    server.read_exact(&mut buf)?;
+
+    let stats = tcp_socket_stats(client.as_raw_fd())?;
+    check_eq!(stats.total_retransmits, 0);
+    check!(stats.rtt < std::time::Duration::from_secs(1));
+    Ok(())
+}
+
+#[test]
+fn unix_socket_has_no_tcp_stats() -> crate::error::Result<()> {
+    let (client, _server) = std::os::unix::net::UnixStream::pair()?;
+    let stream = crate::sync::Stream::unix(client);
+    check_eq!(stream.socket_stats()?, None);
+    Ok(())
+}
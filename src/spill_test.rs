@@ -0,0 +1,84 @@
+use crate::constant::ColumnType;
+use crate::error::Result;
+use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail};
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::BinaryResultSetHandler;
+use crate::spill::SpillHandler;
+use crate::test_macros::{check, check_eq};
+use crate::value::NullBitmap;
+use zerocopy::FromBytes;
+
+/// A nullable BIGINT column
+const LONGLONG_TAIL: [u8; 12] = [
+    0x3F,
+    0x00,
+    0x14,
+    0x00,
+    0x00,
+    0x00,
+    ColumnType::MYSQL_TYPE_LONGLONG as u8,
+    0x00,
+    0x00,
+    0x00,
+    0x00,
+    0x00,
+];
+
+fn feed(handler: &mut SpillHandler, count: i64) -> Result<()> {
+    let tail = ColumnDefinitionTail::ref_from_bytes(&LONGLONG_TAIL)?;
+    let cols = [ColumnDefinition {
+        schema: b"test",
+        table_alias: b"t",
+        table_original: b"t",
+        name_alias: b"n",
+        name_original: b"n",
+        tail,
+        name_id: None,
+    }];
+    handler.resultset_start(&cols)?;
+    for n in 0..count {
+        let values = n.to_le_bytes();
+        let row = if n % 10 == 0 {
+            // bit 2 is the first column in result set null bitmaps
+            BinaryRowPayload::new(NullBitmap::for_result_set(&[0b100]), &[], 1)
+        } else {
+            BinaryRowPayload::new(NullBitmap::for_result_set(&[0]), &values, 1)
+        };
+        handler.row(&cols, row)?;
+    }
+    handler.resultset_end(OkPayloadBytes(&[0xFE, 0, 0, 0x02, 0, 0, 0]))
+}
+
+fn expected(count: i64) -> Vec<(Option<i64>,)> {
+    (0..count)
+        .map(|n| (if n % 10 == 0 { None } else { Some(n) },))
+        .collect()
+}
+
+#[test]
+fn small_result_stays_in_memory() -> Result<()> {
+    let mut handler = SpillHandler::new(1 << 20);
+    feed(&mut handler, 100)?;
+    let mut rows = handler.finish()?;
+
+    check!(!rows.is_spilled());
+    check_eq!(rows.len(), 100);
+    check_eq!(rows.collect::<(Option<i64>,)>()?, expected(100));
+    Ok(())
+}
+
+#[test]
+fn rows_beyond_the_budget_are_spilled() -> Result<()> {
+    let mut handler = SpillHandler::new(1024).with_compression_level(1);
+    feed(&mut handler, 10_000)?;
+    let mut rows = handler.finish()?;
+
+    check!(rows.is_spilled());
+    check_eq!(rows.columns()[0].name_alias, b"n");
+    check_eq!(rows.rows::<(Option<i64>,)>()?.len(), 10_000);
+    check_eq!(rows.collect::<(Option<i64>,)>()?, expected(10_000));
+    // The rows can be read again
+    check_eq!(rows.collect::<(Option<i64>,)>()?, expected(10_000));
+    Ok(())
+}
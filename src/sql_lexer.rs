@@ -0,0 +1,140 @@
+//! A minimal SQL lexer for the heuristics that inspect or rewrite SQL text.
+//!
+//! It only understands what is needed to tell literals, identifiers and comments apart.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Whitespace,
+    /// Keyword, unquoted identifier, or variable (`@x`)
+    Word,
+    /// `'...'` or `"..."`, including the quotes
+    String,
+    Number,
+    /// `` `...` ``, including the backticks
+    Identifier,
+    /// `/* */`, `-- `, or `#` comment
+    Comment,
+    /// Optimizer hint `/*+ */` or versioned comment `/*! */`
+    Hint,
+    Symbol,
+    /// A string, identifier, or comment that runs to the end of the input
+    Unterminated(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+}
+
+/// Split `sql` into tokens. Concatenating the token texts yields `sql`.
+pub(crate) fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                TokenKind::Whitespace
+            }
+            quote @ (b'\'' | b'"' | b'`') => match skip_quoted(bytes, i, quote) {
+                Some(end) => {
+                    i = end;
+                    if quote == b'`' {
+                        TokenKind::Identifier
+                    } else {
+                        TokenKind::String
+                    }
+                }
+                None => {
+                    i = bytes.len();
+                    TokenKind::Unterminated(if quote == b'`' {
+                        "unterminated identifier"
+                    } else {
+                        "unterminated string literal"
+                    })
+                }
+            },
+            b'#' => {
+                i = find_from(bytes, i, b"\n").unwrap_or(bytes.len());
+                TokenKind::Comment
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-')
+                && bytes.get(i + 2).is_none_or(u8::is_ascii_whitespace) =>
+            {
+                i = find_from(bytes, i, b"\n").unwrap_or(bytes.len());
+                TokenKind::Comment
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => match find_from(bytes, i + 2, b"*/") {
+                Some(end) => {
+                    i = end + 2;
+                    if matches!(bytes.get(start + 2), Some(b'+' | b'!')) {
+                        TokenKind::Hint
+                    } else {
+                        TokenKind::Comment
+                    }
+                }
+                None => {
+                    i = bytes.len();
+                    TokenKind::Unterminated("unterminated comment")
+                }
+            },
+            b'0'..=b'9' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                TokenKind::Number
+            }
+            c if is_word_byte(c) => {
+                while i < bytes.len() && is_word_byte(bytes[i]) {
+                    i += 1;
+                }
+                TokenKind::Word
+            }
+            _ => {
+                i += 1;
+                TokenKind::Symbol
+            }
+        };
+        tokens.push(Token {
+            kind,
+            text: &sql[start..i],
+        });
+    }
+    tokens
+}
+
+fn is_word_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'_' | b'$' | b'@') || c >= 0x80
+}
+
+/// Returns the index after the closing quote, handling doubled quotes and backslash escapes.
+fn skip_quoted(bytes: &[u8], start: usize, quote: u8) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if quote != b'`' => i += 2,
+            c if c == quote => {
+                if bytes.get(i + 1) == Some(&quote) {
+                    i += 2;
+                } else {
+                    return Some(i + 1);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn find_from(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|pos| from + pos)
+}
@@ -0,0 +1,123 @@
+//! Client-side buffering of write statements for unit-of-work patterns.
+//!
+//! A [`StatementBuffer`] collects parameterized statements without sending them.
+//! `Conn::flush_statements()` then prepares each distinct SQL once and sends all executions
+//! back-to-back, reading the responses afterwards, so that N statements cost one round trip
+//! instead of N. Without an open transaction, the batch is wrapped in `BEGIN` and `COMMIT`
+//! and rolled back if any statement fails.
+//!
+//! ```no_run
+//! # fn run(conn: &mut zero_mysql::sync::Conn) -> zero_mysql::error::Result<()> {
+//! use zero_mysql::statement_buffer::StatementBuffer;
+//!
+//! let mut buffer = StatementBuffer::new();
+//! buffer.push("UPDATE account SET balance = balance - ? WHERE id = ?", (100, 1))?;
+//! buffer.push("UPDATE account SET balance = balance + ? WHERE id = ?", (100, 2))?;
+//! buffer.push("INSERT INTO transfer (src, dst, amount) VALUES (?, ?, ?)", (1, 2, 100))?;
+//! let affected_rows = conn.flush_statements(&mut buffer)?;
+//! assert!(buffer.is_empty());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::Range;
+
+use crate::error::Result;
+use crate::protocol::command::prepared::{write_execute, write_execute_as_attributes};
+use crate::protocol::r#trait::param::Params;
+
+/// Number of executions sent before their responses are read.
+///
+/// Bounds the responses queued in the socket buffers, so that the server never blocks on a
+/// full send buffer while the client is still writing.
+pub const PIPELINE_WINDOW: usize = 1024;
+
+/// Offset of the statement id in a `COM_STMT_EXECUTE` payload
+const STATEMENT_ID_OFFSET: usize = 1;
+
+/// Write statements waiting to be sent by `Conn::flush_statements()`.
+#[derive(Debug, Default)]
+pub struct StatementBuffer {
+    /// Distinct SQL texts, prepared once per flush
+    sqls: Vec<String>,
+    /// Index into `sqls`, number of parameters and range of the `COM_STMT_EXECUTE` payload in `payloads`
+    statements: Vec<(usize, usize, Range<usize>)>,
+    payloads: Vec<u8>,
+}
+
+impl StatementBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Buffer an execution of `sql` with `params`.
+    ///
+    /// The parameters are encoded right away; the statement is prepared at flush time.
+    pub fn push<P: Params>(&mut self, sql: &str, params: P) -> Result<()> {
+        let sql_index = match self.sqls.iter().position(|buffered| buffered == sql) {
+            Some(index) => index,
+            None => {
+                self.sqls.push(sql.to_string());
+                self.sqls.len() - 1
+            }
+        };
+        let num_params = params.len();
+        let start = self.payloads.len();
+        if let Err(err) = write_execute(&mut self.payloads, 0, params) {
+            self.payloads.truncate(start);
+            return Err(err);
+        }
+        self.statements
+            .push((sql_index, num_params, start..self.payloads.len()));
+        Ok(())
+    }
+
+    /// Number of buffered statements
+    pub fn len(&self) -> usize {
+        self.statements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    /// Discard the buffered statements.
+    pub fn clear(&mut self) {
+        self.sqls.clear();
+        self.statements.clear();
+        self.payloads.clear();
+    }
+
+    /// Distinct SQL texts in the order they were first pushed
+    pub(crate) fn sqls(&self) -> &[String] {
+        &self.sqls
+    }
+
+    /// The index into [`StatementBuffer::sqls`], the number of parameters
+    /// and the `COM_STMT_EXECUTE` payload of each statement
+    pub(crate) fn statements(&self) -> impl Iterator<Item = (usize, usize, &[u8])> {
+        self.statements
+            .iter()
+            .map(|(sql_index, num_params, range)| {
+                (*sql_index, *num_params, &self.payloads[range.clone()])
+            })
+    }
+}
+
+/// Append `payload` to `out` with the statement id replaced by `statement_id`,
+/// in the `CLIENT_QUERY_ATTRIBUTES` format if `query_attributes`.
+pub(crate) fn write_buffered_execute(
+    out: &mut Vec<u8>,
+    payload: &[u8],
+    num_params: usize,
+    statement_id: u32,
+    query_attributes: bool,
+) {
+    let start = out.len() + STATEMENT_ID_OFFSET;
+    if query_attributes {
+        write_execute_as_attributes(out, payload, num_params);
+    } else {
+        out.extend_from_slice(payload);
+    }
+    out[start..start + 4].copy_from_slice(&statement_id.to_le_bytes());
+}
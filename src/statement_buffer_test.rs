@@ -0,0 +1,45 @@
+use crate::statement_buffer::{StatementBuffer, write_buffered_execute};
+use crate::test_macros::{check, check_eq};
+
+#[test]
+fn distinct_sql_is_stored_once() -> crate::error::Result<()> {
+    let mut buffer = StatementBuffer::new();
+    check!(buffer.is_empty());
+    buffer.push("UPDATE t SET a = ? WHERE id = ?", (1, 10))?;
+    buffer.push("DELETE FROM t WHERE id = ?", (11,))?;
+    buffer.push("UPDATE t SET a = ? WHERE id = ?", (2, 12))?;
+
+    check_eq!(buffer.len(), 3);
+    check_eq!(
+        buffer.sqls(),
+        [
+            "UPDATE t SET a = ? WHERE id = ?",
+            "DELETE FROM t WHERE id = ?"
+        ]
+    );
+    let sql_indices: Vec<usize> = buffer.statements().map(|(index, _, _)| index).collect();
+    check_eq!(sql_indices, [0, 1, 0]);
+
+    buffer.clear();
+    check!(buffer.is_empty());
+    check!(buffer.sqls().is_empty());
+    Ok(())
+}
+
+#[test]
+fn statement_id_is_patched_at_flush() -> crate::error::Result<()> {
+    let mut buffer = StatementBuffer::new();
+    buffer.push("DELETE FROM t WHERE id = ?", (7_i32,))?;
+    let (_, num_params, payload) = buffer
+        .statements()
+        .next()
+        .ok_or_else(|| crate::error::Error::LibraryBug(crate::error::eyre!("missing statement")))?;
+
+    let mut expected = Vec::new();
+    crate::protocol::command::prepared::write_execute(&mut expected, 0x0102_0304, (7_i32,))?;
+
+    let mut out = vec![0; 4];
+    write_buffered_execute(&mut out, payload, num_params, 0x0102_0304, false);
+    check_eq!(out[4..], expected[..]);
+    Ok(())
+}
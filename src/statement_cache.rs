@@ -0,0 +1,162 @@
+//! Per-connection cache of prepared statements, bounded in bytes and optionally in count.
+//!
+//! `Conn::exec_cached` prepares a statement on first use and keeps it for the next call with the
+//! same SQL. Each statement is charged for its SQL text and its result set metadata, which
+//! dominates for wide result sets. When the total exceeds `Opts::statement_cache_bytes`, or the
+//! number of statements exceeds `Opts::statement_cache_size`, the least recently used statements
+//! are closed.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::PreparedStatement;
+
+/// Counters of a connection's statement cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatementCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Statements closed to stay within the budget, including statements larger than the
+    /// whole byte budget that were never cached.
+    pub evictions: u64,
+    /// The number of cached statements.
+    pub len: usize,
+    /// The bytes charged for the cached statements.
+    pub bytes: usize,
+}
+
+impl StatementCacheStats {
+    /// `hits / (hits + misses)`, or 0 before the first lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+struct Entry {
+    stmt: PreparedStatement,
+    bytes: usize,
+    last_used: u64,
+}
+
+/// LRU cache of prepared statements keyed by SQL text
+pub(crate) struct StatementCache {
+    /// `0` for no byte bound
+    max_bytes: usize,
+    /// `0` for no count bound
+    max_len: usize,
+    entries: HashMap<Box<str>, Entry>,
+    /// `last_used` -> SQL, oldest first
+    lru: BTreeMap<u64, Box<str>>,
+    clock: u64,
+    stats: StatementCacheStats,
+}
+
+impl StatementCache {
+    /// Disabled if both bounds are `0`.
+    pub(crate) fn new(max_bytes: usize, max_len: usize) -> Self {
+        Self {
+            max_bytes,
+            max_len,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            stats: StatementCacheStats::default(),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> StatementCacheStats {
+        self.stats
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_bytes > 0 || self.max_len > 0
+    }
+
+    /// Whether a statement of `bytes` needs an eviction first
+    fn is_full(&self, bytes: usize) -> bool {
+        (self.max_bytes > 0 && self.stats.bytes + bytes > self.max_bytes)
+            || (self.max_len > 0 && self.stats.len >= self.max_len)
+    }
+
+    /// Whether `sql` is cached, without counting a hit or a miss.
+    pub(crate) fn contains(&self, sql: &str) -> bool {
+        self.entries.contains_key(sql)
+    }
+
+    /// Remove the statement for `sql` to execute it, counting a hit or a miss.
+    ///
+    /// Give it back with [`insert`](Self::insert) afterwards.
+    pub(crate) fn take(&mut self, sql: &str) -> Option<PreparedStatement> {
+        match self.entries.remove(sql) {
+            Some(entry) => {
+                self.lru.remove(&entry.last_used);
+                self.stats.hits += 1;
+                self.stats.bytes -= entry.bytes;
+                self.stats.len -= 1;
+                Some(entry.stmt)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Cache `stmt` as the most recently used statement.
+    ///
+    /// Returns the statements that no longer fit, which the caller must close.
+    pub(crate) fn insert(&mut self, sql: &str, stmt: PreparedStatement) -> Vec<PreparedStatement> {
+        let bytes = sql.len() + stmt.heap_size();
+        if !self.is_enabled() || (self.max_bytes > 0 && bytes > self.max_bytes) {
+            self.stats.evictions += 1;
+            return vec![stmt];
+        }
+
+        let mut evicted = Vec::new();
+        while self.is_full(bytes) {
+            let Some((_, oldest)) = self.lru.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.stats.bytes -= entry.bytes;
+                self.stats.len -= 1;
+                self.stats.evictions += 1;
+                evicted.push(entry.stmt);
+            }
+        }
+
+        self.clock += 1;
+        let sql: Box<str> = sql.into();
+        self.lru.insert(self.clock, sql.clone());
+        self.stats.bytes += bytes;
+        self.stats.len += 1;
+        if let Some(replaced) = self.entries.insert(
+            sql,
+            Entry {
+                stmt,
+                bytes,
+                last_used: self.clock,
+            },
+        ) {
+            // Only if the same SQL was inserted twice without `take`
+            self.lru.remove(&replaced.last_used);
+            self.stats.bytes -= replaced.bytes;
+            self.stats.len -= 1;
+            evicted.push(replaced.stmt);
+        }
+        evicted
+    }
+
+    /// Forget every statement without closing it, after the server closed them
+    /// (`COM_RESET_CONNECTION`, `COM_CHANGE_USER`).
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.stats.len = 0;
+        self.stats.bytes = 0;
+    }
+}
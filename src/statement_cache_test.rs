@@ -0,0 +1,80 @@
+use crate::PreparedStatement;
+use crate::statement_cache::{StatementCache, StatementCacheStats};
+use crate::test_macros::{check, check_eq};
+
+fn stmt(id: u32, sql: &str) -> PreparedStatement {
+    let mut stmt = PreparedStatement::new(id);
+    stmt.set_sql(sql);
+    stmt
+}
+
+fn ids(stmts: &[PreparedStatement]) -> Vec<u32> {
+    stmts.iter().map(PreparedStatement::id).collect()
+}
+
+#[test]
+fn evicts_least_recently_used() -> crate::error::Result<()> {
+    // SQL text is charged twice: as the key and as the retained text
+    let mut cache = StatementCache::new(20, 0);
+    check!(cache.insert("SELECT 1", stmt(1, "SELECT 1")).is_empty());
+    check!(cache.take("SELECT 1").is_some());
+    check!(cache.take("SELECT 2").is_none());
+    check!(cache.insert("SELECT 1", stmt(1, "SELECT 1")).is_empty());
+    check_eq!(
+        cache.stats(),
+        StatementCacheStats {
+            hits: 1,
+            misses: 1,
+            evictions: 0,
+            len: 1,
+            bytes: 16,
+        }
+    );
+
+    let evicted = cache.insert("SELECT 2", stmt(2, "SELECT 2"));
+    check_eq!(ids(&evicted), vec![1]);
+    check_eq!(cache.stats().evictions, 1);
+    check_eq!(cache.stats().len, 1);
+    check_eq!(cache.stats().hit_rate(), 0.5);
+    Ok(())
+}
+
+#[test]
+fn rejects_statements_over_budget() -> crate::error::Result<()> {
+    let mut cache = StatementCache::new(10, 0);
+    let rejected = cache.insert("SELECT 100", stmt(7, "SELECT 100"));
+    check_eq!(ids(&rejected), vec![7]);
+    check_eq!(cache.stats().len, 0);
+    check_eq!(cache.stats().evictions, 1);
+
+    let mut disabled = StatementCache::new(0, 0);
+    check_eq!(
+        ids(&disabled.insert("SELECT 1", PreparedStatement::new(1))),
+        vec![1]
+    );
+    Ok(())
+}
+
+#[test]
+fn clear_forgets_statements() -> crate::error::Result<()> {
+    let mut cache = StatementCache::new(1024, 0);
+    check!(cache.insert("SELECT 1", stmt(1, "SELECT 1")).is_empty());
+    cache.clear();
+    check!(cache.take("SELECT 1").is_none());
+    check_eq!(cache.stats().bytes, 0);
+    check_eq!(cache.stats().len, 0);
+    Ok(())
+}
+
+#[test]
+fn evicts_over_count_limit() -> crate::error::Result<()> {
+    // No byte bound, at most two statements
+    let mut cache = StatementCache::new(0, 2);
+    check!(cache.insert("SELECT 1", stmt(1, "SELECT 1")).is_empty());
+    check!(cache.insert("SELECT 2", stmt(2, "SELECT 2")).is_empty());
+    let evicted = cache.insert("SELECT 3", stmt(3, "SELECT 3"));
+    check_eq!(ids(&evicted), vec![1]);
+    check_eq!(cache.stats().len, 2);
+    check!(cache.take("SELECT 2").is_some());
+    Ok(())
+}
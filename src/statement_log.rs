@@ -0,0 +1,220 @@
+//! Statement logging with redaction.
+//!
+//! Set [`Opts::statement_log`](crate::Opts::statement_log) to receive a [`StatementEvent`]
+//! after every `query`, `prepare` and `exec` call on a connection.
+//! Parameter values of prepared statements are never logged.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+use crate::sql_lexer::{TokenKind, tokenize};
+
+/// How much of the SQL text is passed to the [`StatementLogger`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Redaction {
+    /// Log the SQL text as-is.
+    Off,
+    /// Replace string and numeric literals with `?`.
+    #[default]
+    DropLiterals,
+    /// Replace each string and numeric literal with a truncated SHA-256 hash (`#0123456789abcdef`)
+    /// so that equal values can be correlated without revealing them.
+    HashLiterals,
+    /// Do not log the SQL text, only its fingerprint.
+    FingerprintOnly,
+}
+
+/// The kind of command that was logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// Text protocol `COM_QUERY`
+    Query,
+    /// `COM_STMT_PREPARE`
+    Prepare,
+    /// `COM_STMT_EXECUTE`
+    Exec,
+    /// `COM_STMT_BULK_EXECUTE` (MariaDB)
+    BulkExec,
+}
+
+/// A statement that was sent to the server.
+#[derive(Debug)]
+pub struct StatementEvent<'a> {
+    pub kind: StatementKind,
+    /// The SQL text after redaction.
+    /// `None` for [`Redaction::FingerprintOnly`] or if the SQL text is unknown.
+    pub sql: Option<&'a str>,
+    /// See [`fingerprint`]. `None` if the SQL text is unknown.
+    pub fingerprint: Option<u64>,
+    /// The server-side id of the prepared statement.
+    pub statement_id: Option<u32>,
+    pub elapsed: Duration,
+    pub error: Option<&'a Error>,
+}
+
+/// Receives an event for every logged statement.
+///
+/// Implemented for closures taking `&StatementEvent`.
+pub trait StatementLogger: Send + Sync {
+    fn log(&self, event: &StatementEvent<'_>);
+}
+
+impl<F> StatementLogger for F
+where
+    F: Fn(&StatementEvent<'_>) + Send + Sync,
+{
+    fn log(&self, event: &StatementEvent<'_>) {
+        self(event)
+    }
+}
+
+/// A [`StatementLogger`] with its [`Redaction`] rule.
+///
+/// ```
+/// use zero_mysql::Opts;
+/// use zero_mysql::statement_log::{Redaction, StatementLog};
+///
+/// let mut opts = Opts::default();
+/// opts.statement_log = Some(
+///     StatementLog::new(|event: &zero_mysql::statement_log::StatementEvent<'_>| {
+///         eprintln!("{:?} {:?} took {:?}", event.kind, event.sql, event.elapsed);
+///     })
+///     .with_redaction(Redaction::HashLiterals),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct StatementLog {
+    logger: Arc<dyn StatementLogger>,
+    redaction: Redaction,
+}
+
+impl StatementLog {
+    /// Create a statement log with [`Redaction::DropLiterals`].
+    pub fn new(logger: impl StatementLogger + 'static) -> Self {
+        Self {
+            logger: Arc::new(logger),
+            redaction: Redaction::default(),
+        }
+    }
+
+    pub fn with_redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    pub fn redaction(&self) -> Redaction {
+        self.redaction
+    }
+
+    pub(crate) fn log(
+        &self,
+        kind: StatementKind,
+        sql: Option<&str>,
+        statement_id: Option<u32>,
+        started: Instant,
+        error: Option<&Error>,
+    ) {
+        let redacted = match (sql, self.redaction) {
+            (Some(_), Redaction::FingerprintOnly) | (None, _) => None,
+            (Some(sql), redaction) => Some(redact(sql, redaction)),
+        };
+        self.logger.log(&StatementEvent {
+            kind,
+            sql: redacted.as_deref(),
+            fingerprint: sql.map(fingerprint),
+            statement_id,
+            elapsed: started.elapsed(),
+            error,
+        });
+    }
+}
+
+impl fmt::Debug for StatementLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatementLog")
+            .field("redaction", &self.redaction)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Apply `redaction` to the string and numeric literals of `sql`.
+///
+/// ```
+/// use zero_mysql::statement_log::{Redaction, redact};
+///
+/// assert_eq!(
+///     redact("SELECT * FROM user WHERE email = 'a@b.c' AND age > 30", Redaction::DropLiterals),
+///     "SELECT * FROM user WHERE email = ? AND age > ?"
+/// );
+/// ```
+pub fn redact(sql: &str, redaction: Redaction) -> Cow<'_, str> {
+    match redaction {
+        Redaction::Off => Cow::Borrowed(sql),
+        Redaction::FingerprintOnly => Cow::Borrowed(""),
+        Redaction::DropLiterals | Redaction::HashLiterals => {
+            let mut out = String::with_capacity(sql.len());
+            for token in tokenize(sql) {
+                match token.kind {
+                    TokenKind::String | TokenKind::Number | TokenKind::Unterminated(_) => {
+                        if redaction == Redaction::HashLiterals {
+                            out.push('#');
+                            for byte in &Sha256::digest(token.text.as_bytes())[..8] {
+                                out.push_str(&format!("{byte:02x}"));
+                            }
+                        } else {
+                            out.push('?');
+                        }
+                    }
+                    _ => out.push_str(token.text),
+                }
+            }
+            Cow::Owned(out)
+        }
+    }
+}
+
+/// A 64-bit FNV-1a hash of the normalized statement.
+///
+/// Normalization replaces literals with `?`, removes comments, collapses whitespace,
+/// and lowercases keywords, so statements that differ only in values share a fingerprint.
+pub fn fingerprint(sql: &str) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+
+    let mut started = false;
+    let mut pending_space = false;
+    for token in tokenize(sql) {
+        if matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment) {
+            pending_space = started;
+            continue;
+        }
+        if pending_space {
+            feed(b" ");
+            pending_space = false;
+        }
+        started = true;
+        match token.kind {
+            TokenKind::String | TokenKind::Number | TokenKind::Unterminated(_) => feed(b"?"),
+            TokenKind::Word => {
+                for byte in token.text.bytes() {
+                    feed(&[byte.to_ascii_lowercase()]);
+                }
+            }
+            _ => feed(token.text.as_bytes()),
+        }
+    }
+    hash
+}
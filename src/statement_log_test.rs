@@ -0,0 +1,83 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::statement_log::{
+    Redaction, StatementEvent, StatementKind, StatementLog, fingerprint, redact,
+};
+use crate::test_macros::{check, check_eq};
+
+const SQL: &str = "SELECT * FROM t WHERE name = 'alice' AND `id` = 42 -- c";
+
+#[test]
+fn redact_literals() -> crate::error::Result<()> {
+    check_eq!(redact(SQL, Redaction::Off), SQL);
+    check_eq!(
+        redact(SQL, Redaction::DropLiterals),
+        "SELECT * FROM t WHERE name = ? AND `id` = ? -- c"
+    );
+    check_eq!(
+        redact("SELECT 'unterminated", Redaction::DropLiterals),
+        "SELECT ?"
+    );
+    Ok(())
+}
+
+#[test]
+fn hash_literals_is_stable() -> crate::error::Result<()> {
+    let a = redact("SELECT 'alice', 'alice', 'bob'", Redaction::HashLiterals);
+    let hashes: Vec<_> = a.split(", ").collect();
+    check_eq!(hashes.len(), 3);
+    check!(!a.contains("alice"));
+    check_eq!(hashes[0].trim_start_matches("SELECT "), hashes[1]);
+    check!(hashes[1] != hashes[2]);
+    check_eq!(hashes[2].len(), 17);
+    Ok(())
+}
+
+#[test]
+fn fingerprint_ignores_values_and_formatting() -> crate::error::Result<()> {
+    check_eq!(
+        fingerprint(SQL),
+        fingerprint("  select *  FROM t\nWHERE name = 'bob' /* x */ AND `id` = 7")
+    );
+    check!(fingerprint(SQL) != fingerprint("SELECT * FROM u WHERE name = 'alice'"));
+    Ok(())
+}
+
+#[test]
+fn log_applies_redaction() -> crate::error::Result<()> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let log = StatementLog::new(move |event: &StatementEvent<'_>| {
+        if let Ok(mut sink) = sink.lock() {
+            sink.push((event.kind, event.sql.map(str::to_string), event.fingerprint));
+        }
+    });
+
+    log.log(StatementKind::Query, Some(SQL), None, Instant::now(), None);
+    log.clone().with_redaction(Redaction::FingerprintOnly).log(
+        StatementKind::Prepare,
+        Some(SQL),
+        None,
+        Instant::now(),
+        None,
+    );
+    log.log(StatementKind::Exec, None, Some(1), Instant::now(), None);
+
+    let logged = events.lock().map_err(|_poisoned| {
+        crate::error::Error::LibraryBug(color_eyre::eyre::eyre!("poisoned"))
+    })?;
+    check_eq!(
+        *logged,
+        vec![
+            (
+                StatementKind::Query,
+                Some("SELECT * FROM t WHERE name = ? AND `id` = ? -- c".to_string()),
+                Some(fingerprint(SQL)),
+            ),
+            (StatementKind::Prepare, None, Some(fingerprint(SQL))),
+            (StatementKind::Exec, None, None),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use super::Conn;
+use crate::binlog::{BinlogParser, BinlogPosition, Event, PositionTracker, TableMap};
+use crate::error::Result;
+
+/// A stream of binlog events.
+///
+/// Created by [`Conn::binlog_dump`]. See [`crate::binlog`].
+pub struct BinlogStream<'conn> {
+    conn: &'conn mut Conn,
+    parser: BinlogParser,
+    tracker: PositionTracker,
+    finished: bool,
+}
+
+impl<'conn> BinlogStream<'conn> {
+    pub(crate) fn new(
+        conn: &'conn mut Conn,
+        parser: BinlogParser,
+        tracker: PositionTracker,
+    ) -> Self {
+        Self {
+            conn,
+            parser,
+            tracker,
+            finished: false,
+        }
+    }
+
+    /// Read the next event.
+    ///
+    /// Returns `None` at the end of the binlog of a non-blocking dump;
+    /// otherwise blocks until the server writes the next event.
+    pub fn next_event(&mut self) -> Result<Option<Event<'_>>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let Some(event) = self.conn.read_binlog_event()? else {
+            self.finished = true;
+            return Ok(None);
+        };
+        let event = self.parser.parse(event)?;
+        self.tracker.update(&event);
+        Ok(Some(event))
+    }
+
+    /// The position after the last complete transaction, to resume from with
+    /// [`BinlogRequest::from_position`](crate::binlog::BinlogRequest::from_position)
+    pub fn position(&self) -> &BinlogPosition {
+        self.tracker.position()
+    }
+
+    /// The table map of `table_id`, if seen
+    pub fn table(&self, table_id: u64) -> Option<&TableMap> {
+        self.parser.table(table_id)
+    }
+}
+
+impl Drop for BinlogStream<'_> {
+    fn drop(&mut self) {
+        // The server keeps sending events until the connection is closed
+        if !self.finished {
+            self.conn.mark_broken();
+        }
+    }
+}
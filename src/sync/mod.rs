@@ -1,9 +1,18 @@
+mod binlog;
 mod conn;
 mod pool;
+mod row_iter;
+mod snapshot;
 mod stream;
 mod transaction;
 
+pub use binlog::BinlogStream;
 pub use conn::Conn;
 pub use pool::{Pool, PooledConn};
+pub use row_iter::RowIter;
+pub use snapshot::Snapshot;
 pub use stream::Stream;
-pub use transaction::Transaction;
+pub use transaction::{Savepoint, Transaction};
+
+#[cfg(test)]
+mod stream_test;
@@ -1,62 +1,330 @@
+use std::fmt;
 use std::mem::ManuallyDrop;
 use std::ops::{Deref, DerefMut};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Instant;
 
 use crossbeam_queue::ArrayQueue;
 use std_semaphore::Semaphore;
 
 use crate::error::Result;
 use crate::opts::Opts;
+use crate::pool_sizing::PoolSizer;
+use crate::protocol::r#trait::param::Params;
+use crate::raw::FromRow;
 
 use super::Conn;
+use super::transaction::Transaction;
 
 pub struct Pool {
-    opts: Opts,
-    conns: ArrayQueue<Conn>,
-    semaphore: Option<Semaphore>,
+    opts: RwLock<Arc<Opts>>,
+    /// Incremented by `update_opts()`
+    generation: AtomicU64,
+    /// Idle connections with the generation they were opened in
+    conns: ArrayQueue<(Conn, u64)>,
+    semaphore: RwLock<Option<Arc<Semaphore>>>,
+    sizer: Option<PoolSizer>,
+    in_use: AtomicUsize,
 }
 
 impl Pool {
     pub fn new(opts: Opts) -> Self {
         let semaphore = opts
             .pool_max_concurrency
-            .map(|n| Semaphore::new(n as isize));
+            .map(|n| Arc::new(Semaphore::new(n as isize)));
+        let sizer = opts.pool_adaptive_sizing.clone().map(PoolSizer::new);
+        let capacity = sizer
+            .as_ref()
+            .map_or(opts.pool_max_idle_conn, PoolSizer::max_idle);
         Self {
-            conns: ArrayQueue::new(opts.pool_max_idle_conn),
-            opts,
-            semaphore,
+            conns: ArrayQueue::new(capacity.max(1)),
+            opts: RwLock::new(Arc::new(opts)),
+            generation: AtomicU64::new(0),
+            semaphore: RwLock::new(semaphore),
+            sizer,
+            in_use: AtomicUsize::new(0),
         }
     }
 
+    /// The options new connections are opened with.
+    pub fn opts(&self) -> Arc<Opts> {
+        Arc::clone(&self.opts.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Replace the options without recreating the pool.
+    ///
+    /// New connections are opened with `opts`, so a changed host, user, password or
+    /// `password_file` takes effect on the next connect. `pool_max_idle_conn`,
+    /// `pool_max_concurrency` and `pool_reset_conn` apply immediately; connections checked out
+    /// before the update do not count toward the new `pool_max_concurrency`.
+    /// `pool_adaptive_sizing` keeps the settings the pool was created with, and the idle queue
+    /// cannot grow beyond its initial capacity.
+    ///
+    /// Existing connections are phased out: idle ones are closed when they would be checked out,
+    /// checked-out ones when they are returned.
+    pub fn update_opts(&self, opts: Opts) {
+        let mut current = self.opts.write().unwrap_or_else(PoisonError::into_inner);
+        if opts.pool_max_concurrency != current.pool_max_concurrency {
+            *self
+                .semaphore
+                .write()
+                .unwrap_or_else(PoisonError::into_inner) = opts
+                .pool_max_concurrency
+                .map(|n| Arc::new(Semaphore::new(n as isize)));
+        }
+        tracing::info!(host = %opts.host, "pool options updated");
+        *current = Arc::new(opts);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Close the idle connections and phase out the checked-out ones, which are closed when they
+    /// are returned. New connections are opened on demand.
+    ///
+    /// Called when a connection is returned after the server reported `ER_SERVER_SHUTDOWN`, so
+    /// that a restarted server does not see a storm of commands on dead connections.
+    pub fn drain_idle(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        while self.conns.pop().is_some() {}
+    }
+
+    /// The number of idle connections the pool currently keeps.
+    ///
+    /// This changes over time when `Opts::pool_adaptive_sizing` is set.
+    pub fn idle_capacity(&self) -> usize {
+        self.sizer
+            .as_ref()
+            .map_or_else(|| self.opts().pool_max_idle_conn, PoolSizer::target)
+    }
+
+    /// The number of idle connections in the pool.
+    pub fn idle_count(&self) -> usize {
+        self.conns.len()
+    }
+
+    /// The number of connections currently checked out of the pool.
+    pub fn in_use_count(&self) -> usize {
+        self.in_use.load(Ordering::Relaxed)
+    }
+
     pub fn get(self: &Arc<Self>) -> Result<PooledConn> {
-        if let Some(sem) = &self.semaphore {
+        let started = Instant::now();
+        let semaphore = self
+            .semaphore
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if let Some(sem) = &semaphore {
             sem.acquire();
         }
-        let mut conn = match self.conns.pop() {
-            Some(c) => c,
-            None => Conn::new(self.opts.clone())?,
+        let permit = Permit(semaphore);
+        // An idle connection that fails the ping is dropped, e.g. after a server restart
+        let (conn, generation) = loop {
+            match self.pop_idle() {
+                Some((mut conn, generation)) => {
+                    if conn.ping().is_ok() {
+                        break (conn, generation);
+                    }
+                }
+                None => {
+                    let generation = self.generation.load(Ordering::SeqCst);
+                    let conn = Conn::new((*self.opts()).clone())?;
+                    tracing::debug!(conn = %conn, "opened a pooled connection");
+                    break (conn, generation);
+                }
+            }
         };
-        conn.ping()?;
+        if let Some(sizer) = &self.sizer {
+            sizer.record_acquire(started.elapsed(), self.conns.len());
+        }
+        self.in_use.fetch_add(1, Ordering::Relaxed);
         Ok(PooledConn {
             conn: ManuallyDrop::new(conn),
+            generation,
             pool: Arc::clone(self),
+            _permit: permit,
         })
     }
 
-    fn check_in(&self, mut conn: Conn) {
-        if conn.is_broken() {
+    /// Pop an idle connection, dropping ones opened before `update_opts()`
+    fn pop_idle(&self) -> Option<(Conn, u64)> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        while let Some(idle) = self.conns.pop() {
+            if idle.1 == generation {
+                return Some(idle);
+            }
+        }
+        None
+    }
+
+    /// Acquire a connection with `role` activated (`SET ROLE`).
+    ///
+    /// The role is reset when the connection is returned to the pool.
+    pub fn get_with_role(self: &Arc<Self>, role: &str) -> Result<PooledConn> {
+        let mut conn = self.get()?;
+        conn.set_role(role)?;
+        Ok(conn)
+    }
+
+    /// Acquire a connection, execute a text protocol query, and discard the result.
+    pub fn query_drop(self: &Arc<Self>, sql: &str) -> Result<()> {
+        self.get()?.query_drop(sql)
+    }
+
+    /// Acquire a connection, execute `sql` without parameters, and collect all rows.
+    ///
+    /// The statement is prepared and closed for this call only.
+    /// For repeated executions, prepare it on a connection from `get()` instead.
+    pub fn query_rows<Row>(self: &Arc<Self>, sql: &str) -> Result<Vec<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+    {
+        self.exec_collect(sql, ())
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and discard the result.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub fn exec_drop<P: Params>(self: &Arc<Self>, sql: &str, params: P) -> Result<()> {
+        let mut conn = self.get()?;
+        let mut stmt = conn.prepare(sql)?;
+        let result = conn.exec_drop(&mut stmt, params);
+        let closed = conn.close_statement(stmt);
+        result?;
+        closed
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and return the first row.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub fn exec_first<Row, P>(self: &Arc<Self>, sql: &str, params: P) -> Result<Option<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+        P: Params,
+    {
+        let mut conn = self.get()?;
+        let mut stmt = conn.prepare(sql)?;
+        let result = conn.exec_first(&mut stmt, params);
+        let closed = conn.close_statement(stmt);
+        let value = result?;
+        closed?;
+        Ok(value)
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and collect all rows.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub fn exec_collect<Row, P>(self: &Arc<Self>, sql: &str, params: P) -> Result<Vec<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+        P: Params,
+    {
+        let mut conn = self.get()?;
+        let mut stmt = conn.prepare(sql)?;
+        let result = conn.exec_collect(&mut stmt, params);
+        let closed = conn.close_statement(stmt);
+        let value = result?;
+        closed?;
+        Ok(value)
+    }
+
+    /// Acquire a connection and run `f` in a transaction on it.
+    ///
+    /// See `Conn::transaction`.
+    pub fn run_transaction<F, R>(self: &Arc<Self>, f: F) -> Result<R>
+    where
+        F: FnOnce(Transaction<'_>) -> Result<R>,
+    {
+        self.get()?.transaction(f)
+    }
+
+    fn check_in(&self, mut conn: Conn, generation: u64) {
+        self.in_use.fetch_sub(1, Ordering::Relaxed);
+        if let Some(sizer) = &self.sizer {
+            sizer.record_release();
+        }
+        if conn.is_server_shutdown() {
+            tracing::warn!(conn = %conn, "server is shutting down, draining the pool");
+            self.drain_idle();
+            return;
+        }
+        let opts = self.opts();
+        // Without a reset, a connection left inside a transaction cannot be reused
+        if conn.is_broken() || (!opts.pool_reset_conn && conn.in_transaction()) {
             return;
         }
-        if self.opts.pool_reset_conn && conn.reset().is_err() {
+        if generation != self.generation.load(Ordering::SeqCst) {
             return;
         }
-        let _ = self.conns.push(conn);
+        if self.conns.len() >= self.idle_capacity() {
+            return;
+        }
+        if opts.pool_reset_conn {
+            let reset = if conn
+                .dialect()
+                .supports_reset_connection(conn.server_version())
+            {
+                conn.reset()
+            } else {
+                // Without COM_RESET_CONNECTION, only end the transaction and restore roles
+                conn.query_drop("ROLLBACK").and_then(|()| {
+                    if conn.role_changed() {
+                        conn.reset_role()
+                    } else {
+                        Ok(())
+                    }
+                })
+            };
+            if reset.is_err() {
+                return;
+            }
+        } else if conn.role_changed() && conn.reset_role().is_err() {
+            return;
+        }
+        let _ = self.conns.push((conn, generation));
+    }
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("idle", &self.idle_count())
+            .field("in_use", &self.in_use_count())
+            .field("idle_capacity", &self.idle_capacity())
+            .field("max_concurrency", &self.opts().pool_max_concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+/// `pool (2 in use, 3 idle, idle capacity 100)`
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pool ({} in use, {} idle, idle capacity {})",
+            self.in_use_count(),
+            self.idle_count(),
+            self.idle_capacity()
+        )
     }
 }
 
 pub struct PooledConn {
     pool: Arc<Pool>,
     conn: ManuallyDrop<Conn>,
+    generation: u64,
+    _permit: Permit,
+}
+
+/// Releases the semaphore the connection was acquired from, even after `update_opts()`
+struct Permit(Option<Arc<Semaphore>>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(sem) = &self.0 {
+            sem.release();
+        }
+    }
 }
 
 impl Deref for PooledConn {
@@ -76,9 +344,6 @@ impl Drop for PooledConn {
     fn drop(&mut self) {
         // SAFETY: conn is never accessed after this
         let conn = unsafe { ManuallyDrop::take(&mut self.conn) };
-        self.pool.check_in(conn);
-        if let Some(sem) = &self.pool.semaphore {
-            sem.release();
-        }
+        self.pool.check_in(conn, self.generation);
     }
 }
@@ -0,0 +1,130 @@
+//! Lending iterator over the rows of a prepared statement.
+//!
+//! `Conn::exec_iter()` returns a [`RowIter`] whose rows borrow the connection's read buffer,
+//! for callers that cannot express their logic as a result set handler but still want to avoid
+//! copying rows. Each row is valid until the next call to [`RowIter::next`].
+
+use std::time::Instant;
+
+use crate::PreparedStatement;
+use crate::error::Result;
+use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::command::prepared::ExecCursor;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::BinaryResultSetHandler;
+use crate::row::BinaryRowRef;
+
+use super::Conn;
+
+/// Records that the last step of an iterated `Exec` passed a row
+#[derive(Default)]
+pub(super) struct RowSeen {
+    pub(super) seen: bool,
+}
+
+impl BinaryResultSetHandler for RowSeen {
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    fn row(&mut self, _: &[ColumnDefinition<'_>], _: BinaryRowPayload<'_>) -> Result<()> {
+        self.seen = true;
+        Ok(())
+    }
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The rows of `Conn::exec_iter()`, borrowed from the read buffer one at a time.
+///
+/// ```no_run
+/// # fn run(conn: &mut zero_mysql::sync::Conn) -> zero_mysql::error::Result<()> {
+/// let mut stmt = conn.prepare("SELECT id, name FROM users")?;
+/// let mut rows = conn.exec_iter(&mut stmt, ())?;
+/// while let Some(row) = rows.next() {
+///     let row = row?;
+///     let name: &str = row.get("name")?;
+///     println!("{} {name}", row.get::<i64>(0)?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The connection is marked broken until the iterator is read to the end. Call
+/// [`finish`](Self::finish) to skip the remaining rows and keep the connection usable.
+pub struct RowIter<'c> {
+    conn: &'c mut Conn,
+    stmt: &'c mut PreparedStatement,
+    /// `None` once the response is finished or failed
+    cursor: Option<ExecCursor>,
+    /// The current row is still in the read buffer
+    read_pending: bool,
+    started: Option<Instant>,
+    /// Rows left until `PreparedStatement::max_rows`
+    remaining: Option<u64>,
+}
+
+impl<'c> RowIter<'c> {
+    pub(super) fn new(
+        conn: &'c mut Conn,
+        stmt: &'c mut PreparedStatement,
+        cursor: ExecCursor,
+        started: Option<Instant>,
+    ) -> Self {
+        Self {
+            remaining: stmt.max_rows(),
+            conn,
+            stmt,
+            cursor: Some(cursor),
+            read_pending: false,
+            started,
+        }
+    }
+
+    /// The next row, or `None` at the end of the result set.
+    ///
+    /// An error ends the iteration.
+    #[expect(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<BinaryRowRef<'_>>> {
+        let cursor = self.cursor.take()?;
+        let read_pending = std::mem::take(&mut self.read_pending);
+        match self
+            .conn
+            .advance_row_iter(self.stmt, cursor, read_pending, self.started)
+        {
+            Ok(Some(cursor)) => {
+                self.cursor = Some(cursor);
+                self.read_pending = true;
+                self.remaining = self.remaining.map(|remaining| remaining.saturating_sub(1));
+                Some(self.conn.current_row(self.stmt))
+            }
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// Bounds on the remaining rows. The upper bound is known if the statement has a `LIMIT`
+    /// (see [`PreparedStatement::max_rows`]).
+    pub fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.cursor {
+            None => (0, Some(0)),
+            Some(_) => (
+                0,
+                self.remaining
+                    .map(|remaining| usize::try_from(remaining).unwrap_or(usize::MAX)),
+            ),
+        }
+    }
+
+    /// Read the remaining rows without decoding them.
+    pub fn finish(mut self) -> Result<()> {
+        while let Some(row) = self.next() {
+            row?;
+        }
+        Ok(())
+    }
+}
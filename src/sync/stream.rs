@@ -1,45 +1,164 @@
-use std::io::{BufReader, Read, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::mem::MaybeUninit;
 use std::net::TcpStream;
 #[cfg(unix)]
 use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use zerocopy::{FromZeros, IntoBytes};
 
 use crate::nightly::read_uninit_exact;
+use crate::opts::DEFAULT_READ_BUFFER_SIZE;
+use crate::protocol::compression::{CompressedPacketHeader, CompressionStats, PacketCompression};
+use crate::socket_stats::SocketStats;
 
 #[cfg(feature = "sync-tls")]
-use native_tls::TlsStream;
+use crate::tls::{Tls, TlsBackend};
 
-pub enum Stream {
+enum StreamInner {
     Tcp(BufReader<TcpStream>),
     #[cfg(feature = "sync-tls")]
-    Tls(BufReader<TlsStream<TcpStream>>),
+    /// Boxed, rustls streams are over 1 KiB
+    Tls(BufReader<Box<<Tls as TlsBackend>::Stream>>),
     #[cfg(unix)]
     Unix(BufReader<UnixStream>),
 }
 
+impl StreamInner {
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(r) => r.read_exact(buf),
+            #[cfg(feature = "sync-tls")]
+            Self::Tls(r) => r.read_exact(buf),
+            #[cfg(unix)]
+            Self::Unix(r) => r.read_exact(buf),
+        }
+    }
+
+    fn read_buf_exact(&mut self, buf: &mut [MaybeUninit<u8>]) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(r) => read_uninit_exact(r, buf),
+            #[cfg(feature = "sync-tls")]
+            Self::Tls(r) => read_uninit_exact(r, buf),
+            #[cfg(unix)]
+            Self::Unix(r) => read_uninit_exact(r, buf),
+        }
+    }
+
+    /// Capacity of the read buffer, `0` if unbuffered
+    fn capacity(&self) -> usize {
+        match self {
+            Self::Tcp(r) => r.capacity(),
+            #[cfg(feature = "sync-tls")]
+            Self::Tls(r) => r.capacity(),
+            #[cfg(unix)]
+            Self::Unix(r) => r.capacity(),
+        }
+    }
+
+    fn buf_reader(&mut self) -> &mut dyn BufRead {
+        match self {
+            Self::Tcp(r) => r,
+            #[cfg(feature = "sync-tls")]
+            Self::Tls(r) => r,
+            #[cfg(unix)]
+            Self::Unix(r) => r,
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(r) => r.get_mut().write_all(buf),
+            #[cfg(feature = "sync-tls")]
+            Self::Tls(r) => r.get_mut().write_all(buf),
+            #[cfg(unix)]
+            Self::Unix(r) => r.get_mut().write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(r) => r.get_mut().flush(),
+            #[cfg(feature = "sync-tls")]
+            Self::Tls(r) => r.get_mut().flush(),
+            #[cfg(unix)]
+            Self::Unix(r) => r.get_mut().flush(),
+        }
+    }
+
+    /// Read the next compressed packet into `compression`
+    fn read_compressed(&mut self, compression: &mut PacketCompression) -> std::io::Result<()> {
+        let mut header = CompressedPacketHeader::new_zeroed();
+        self.read_exact(header.as_mut_bytes())?;
+        self.read_exact(compression.body_mut(&header))?;
+        compression
+            .decode(&header)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+pub struct Stream {
+    inner: StreamInner,
+    /// Set after `CLIENT_COMPRESS` is negotiated
+    compression: Option<Box<PacketCompression>>,
+    /// Writes held back until the next flush, see [`Stream::write_deferred`]
+    pending: Vec<u8>,
+    /// Set `TCP_QUICKACK` after every flush
+    quickack: bool,
+}
+
 impl Stream {
+    fn new(inner: StreamInner) -> Self {
+        Self {
+            inner,
+            compression: None,
+            pending: Vec::new(),
+            quickack: false,
+        }
+    }
+
     pub fn tcp(stream: TcpStream) -> Self {
-        Self::Tcp(BufReader::new(stream))
+        Self::tcp_with_capacity(stream, DEFAULT_READ_BUFFER_SIZE)
+    }
+
+    /// `read_buffer_size` of `0` reads directly from the socket.
+    pub fn tcp_with_capacity(stream: TcpStream, read_buffer_size: usize) -> Self {
+        Self::new(StreamInner::Tcp(BufReader::with_capacity(
+            read_buffer_size,
+            stream,
+        )))
     }
 
     #[cfg(unix)]
     pub fn unix(stream: UnixStream) -> Self {
-        Self::Unix(BufReader::new(stream))
+        Self::unix_with_capacity(stream, DEFAULT_READ_BUFFER_SIZE)
+    }
+
+    /// `read_buffer_size` of `0` reads directly from the socket.
+    #[cfg(unix)]
+    pub fn unix_with_capacity(stream: UnixStream, read_buffer_size: usize) -> Self {
+        Self::new(StreamInner::Unix(BufReader::with_capacity(
+            read_buffer_size,
+            stream,
+        )))
     }
 
     #[cfg(feature = "sync-tls")]
     pub fn upgrade_to_tls(self, host: &str) -> std::io::Result<Self> {
-        let tcp = match self {
-            Self::Tcp(buf_reader) => buf_reader.into_inner(),
+        let (tcp, read_buffer_size) = match self.inner {
+            StreamInner::Tcp(buf_reader) => {
+                let read_buffer_size = buf_reader.capacity();
+                (buf_reader.into_inner(), read_buffer_size)
+            }
             #[cfg(feature = "sync-tls")]
-            Self::Tls(_) => {
+            StreamInner::Tls(_) => {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::InvalidInput,
                     "Already using TLS",
                 ));
             }
             #[cfg(unix)]
-            Self::Unix(_) => {
+            StreamInner::Unix(_) => {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::InvalidInput,
                     "TLS not supported for Unix sockets",
@@ -47,71 +166,210 @@ impl Stream {
             }
         };
 
-        let connector = native_tls::TlsConnector::new().map_err(std::io::Error::other)?;
-        let tls_stream = connector
-            .connect(host, tcp)
-            .map_err(std::io::Error::other)?;
+        let tls_stream = Box::new(Tls::connect(host, tcp)?);
 
-        Ok(Self::Tls(BufReader::new(tls_stream)))
+        Ok(Self::new(StreamInner::Tls(BufReader::with_capacity(
+            read_buffer_size,
+            tls_stream,
+        ))))
     }
 
-    pub fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
-        match self {
-            Self::Tcp(r) => r.read_exact(buf),
-            #[cfg(feature = "sync-tls")]
-            Self::Tls(r) => r.read_exact(buf),
-            #[cfg(unix)]
-            Self::Unix(r) => r.read_exact(buf),
+    /// Wrap all subsequent packets in the compressed protocol.
+    pub fn enable_compression(&mut self, compression: PacketCompression) {
+        self.compression = Some(Box::new(compression));
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compression.is_some()
+    }
+
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.compression
+            .as_ref()
+            .map(|compression| compression.stats())
+    }
+
+    pub fn read_exact(&mut self, mut buf: &mut [u8]) -> std::io::Result<()> {
+        let Some(compression) = &mut self.compression else {
+            return self.inner.read_exact(buf);
+        };
+        while !buf.is_empty() {
+            if compression.is_empty() {
+                self.inner.read_compressed(compression)?;
+            }
+            let len = compression.read(buf);
+            buf = &mut buf[len..];
         }
+        Ok(())
     }
 
-    pub fn read_buf_exact(&mut self, buf: &mut [MaybeUninit<u8>]) -> std::io::Result<()> {
-        match self {
-            Self::Tcp(r) => read_uninit_exact(r, buf),
-            #[cfg(feature = "sync-tls")]
-            Self::Tls(r) => read_uninit_exact(r, buf),
-            #[cfg(unix)]
-            Self::Unix(r) => read_uninit_exact(r, buf),
+    pub fn read_buf_exact(&mut self, mut buf: &mut [MaybeUninit<u8>]) -> std::io::Result<()> {
+        let Some(compression) = &mut self.compression else {
+            return self.inner.read_buf_exact(buf);
+        };
+        while !buf.is_empty() {
+            if compression.is_empty() {
+                self.inner.read_compressed(compression)?;
+            }
+            let len = compression.read_uninit(buf);
+            buf = &mut buf[len..];
+        }
+        Ok(())
+    }
+
+    /// Read a whole packet from the read buffer, filling it first if it is empty.
+    ///
+    /// A small packet's header and payload arrive in the same read, so the header is parsed
+    /// in place and the payload is copied once.
+    /// Returns `None` without consuming anything if the connection is compressed or unbuffered,
+    /// or the packet is incomplete or split into 16MB chunks.
+    pub fn read_buffered_payload(&mut self, buffer: &mut Vec<u8>) -> std::io::Result<Option<u8>> {
+        if self.compression.is_some() || self.inner.capacity() == 0 {
+            return Ok(None);
         }
+        let reader = self.inner.buf_reader();
+        let data = reader.fill_buf()?;
+        let Some((header, rest)) = data.split_first_chunk::<4>() else {
+            return Ok(None);
+        };
+        let [l0, l1, l2, sequence_id] = *header;
+        let length = u32::from_le_bytes([l0, l1, l2, 0]) as usize;
+        let Some(payload) = rest.get(..length).filter(|_| length < 0xFFFFFF) else {
+            return Ok(None);
+        };
+        buffer.extend_from_slice(payload);
+        reader.consume(4 + length);
+        Ok(Some(sequence_id))
     }
 
     pub fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
-        match self {
-            Self::Tcp(r) => r.get_mut().write_all(buf),
-            #[cfg(feature = "sync-tls")]
-            Self::Tls(r) => r.get_mut().write_all(buf),
-            #[cfg(unix)]
-            Self::Unix(r) => r.get_mut().write_all(buf),
+        match &mut self.compression {
+            Some(compression) => {
+                compression.write(buf);
+                Ok(())
+            }
+            // keep the order of the deferred writes
+            None if !self.pending.is_empty() => {
+                self.pending.extend_from_slice(buf);
+                Ok(())
+            }
+            None => self.inner.write_all(buf),
+        }
+    }
+
+    /// Hold `buf` back until the next [`Stream::flush`], which writes everything held back at once.
+    pub fn write_deferred(&mut self, buf: &[u8]) {
+        match &mut self.compression {
+            Some(compression) => compression.write(buf),
+            None => self.pending.extend_from_slice(buf),
         }
     }
 
     pub fn flush(&mut self) -> std::io::Result<()> {
-        match self {
-            Self::Tcp(r) => r.get_mut().flush(),
+        if let Some(compression) = &mut self.compression {
+            let wire = compression
+                .encode()
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            self.inner.write_all(wire)?;
+        } else if !self.pending.is_empty() {
+            let result = self.inner.write_all(&self.pending);
+            self.pending.clear();
+            result?;
+        }
+        self.inner.flush()?;
+        if self.quickack {
+            self.enable_quickack()?;
+        }
+        Ok(())
+    }
+
+    /// Set `TCP_QUICKACK` after every flush. Ignored on Unix sockets and platforms other than Linux.
+    pub fn set_quickack(&mut self, quickack: bool) {
+        self.quickack = quickack;
+    }
+
+    /// Set the read and write timeouts of the socket, `None` blocks forever.
+    /// A read or write that times out fails with `WouldBlock` or `TimedOut`.
+    pub fn set_timeouts(
+        &self,
+        read: Option<Duration>,
+        write: Option<Duration>,
+    ) -> std::io::Result<()> {
+        match &self.inner {
+            StreamInner::Tcp(r) => {
+                r.get_ref().set_read_timeout(read)?;
+                r.get_ref().set_write_timeout(write)
+            }
             #[cfg(feature = "sync-tls")]
-            Self::Tls(r) => r.get_mut().flush(),
+            StreamInner::Tls(r) => {
+                Tls::tcp(r.get_ref()).set_read_timeout(read)?;
+                Tls::tcp(r.get_ref()).set_write_timeout(write)
+            }
             #[cfg(unix)]
-            Self::Unix(r) => r.get_mut().flush(),
+            StreamInner::Unix(r) => {
+                r.get_ref().set_read_timeout(read)?;
+                r.get_ref().set_write_timeout(write)
+            }
+        }
+    }
+
+    fn enable_quickack(&self) -> std::io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+            let fd = match &self.inner {
+                StreamInner::Tcp(r) => r.get_ref().as_raw_fd(),
+                #[cfg(feature = "sync-tls")]
+                StreamInner::Tls(r) => Tls::tcp(r.get_ref()).as_raw_fd(),
+                StreamInner::Unix(_) => return Ok(()),
+            };
+            crate::tcp_quickack::enable(fd)?;
         }
+        Ok(())
     }
 
     /// Returns true if this is a TCP connection to a loopback address
     pub fn is_tcp_loopback(&self) -> bool {
-        match self {
-            Self::Tcp(r) => r
+        match &self.inner {
+            StreamInner::Tcp(r) => r
                 .get_ref()
                 .peer_addr()
                 .map(|addr| addr.ip().is_loopback())
                 .unwrap_or(false),
             #[cfg(feature = "sync-tls")]
-            Self::Tls(r) => r
-                .get_ref()
-                .get_ref()
+            StreamInner::Tls(r) => Tls::tcp(r.get_ref())
                 .peer_addr()
                 .map(|addr| addr.ip().is_loopback())
                 .unwrap_or(false),
             #[cfg(unix)]
-            Self::Unix(_) => false,
+            StreamInner::Unix(_) => false,
+        }
+    }
+
+    /// The DER-encoded certificate of the server. `None` without TLS.
+    pub fn peer_certificate(&self) -> Option<Vec<u8>> {
+        #[cfg(feature = "sync-tls")]
+        if let StreamInner::Tls(r) = &self.inner {
+            return Tls::peer_certificate(r.get_ref());
+        }
+        None
+    }
+
+    /// TCP statistics of the socket.
+    /// `None` for Unix sockets and on platforms other than Linux.
+    pub fn socket_stats(&self) -> std::io::Result<Option<SocketStats>> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+            let fd = match &self.inner {
+                StreamInner::Tcp(r) => r.get_ref().as_raw_fd(),
+                #[cfg(feature = "sync-tls")]
+                StreamInner::Tls(r) => Tls::tcp(r.get_ref()).as_raw_fd(),
+                StreamInner::Unix(_) => return Ok(None),
+            };
+            crate::socket_stats::tcp_socket_stats(fd).map(Some)
         }
+        #[cfg(not(target_os = "linux"))]
+        Ok(None)
     }
 }
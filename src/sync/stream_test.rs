@@ -0,0 +1,102 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::sync::Stream;
+use crate::test_macros::{check, check_eq};
+
+/// A stream whose peer has already written `bytes`
+fn stream_with(bytes: &[u8]) -> crate::error::Result<Stream> {
+    stream_with_capacity(bytes, crate::DEFAULT_READ_BUFFER_SIZE)
+}
+
+fn stream_with_capacity(bytes: &[u8], read_buffer_size: usize) -> crate::error::Result<Stream> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let mut peer = TcpStream::connect(listener.local_addr()?)?;
+    let (client, _) = listener.accept()?;
+    peer.write_all(bytes)?;
+    drop(peer);
+    Ok(Stream::tcp_with_capacity(client, read_buffer_size))
+}
+
+#[test]
+fn buffered_payload_reads_whole_packets() -> crate::error::Result<()> {
+    let mut stream = stream_with(b"\x03\x00\x00\x01abc\x01\x00\x00\x02d")?;
+    let mut buffer = Vec::new();
+    check_eq!(stream.read_buffered_payload(&mut buffer)?, Some(1));
+    check_eq!(buffer, b"abc");
+    buffer.clear();
+    check_eq!(stream.read_buffered_payload(&mut buffer)?, Some(2));
+    check_eq!(buffer, b"d");
+    Ok(())
+}
+
+#[test]
+fn buffered_payload_leaves_incomplete_packets() -> crate::error::Result<()> {
+    // 5-byte payload but only 3 bytes are sent
+    let mut stream = stream_with(b"\x05\x00\x00\x00abc")?;
+    let mut buffer = Vec::new();
+    check_eq!(stream.read_buffered_payload(&mut buffer)?, None);
+    check!(buffer.is_empty());
+
+    // Nothing was consumed
+    let mut header = [0_u8; 4];
+    stream.read_exact(&mut header)?;
+    check_eq!(header, [5, 0, 0, 0]);
+    Ok(())
+}
+
+#[test]
+fn unbuffered_stream_reads_directly() -> crate::error::Result<()> {
+    let mut stream = stream_with_capacity(b"\x03\x00\x00\x01abc", 0)?;
+    let mut buffer = Vec::new();
+    check_eq!(stream.read_buffered_payload(&mut buffer)?, None);
+
+    let mut packet = [0_u8; 7];
+    stream.read_exact(&mut packet)?;
+    check_eq!(&packet, b"\x03\x00\x00\x01abc");
+    Ok(())
+}
+
+/// A stream and its peer
+fn stream_pair() -> crate::error::Result<(Stream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let peer = TcpStream::connect(listener.local_addr()?)?;
+    let (client, _) = listener.accept()?;
+    Ok((Stream::tcp(client), peer))
+}
+
+#[test]
+fn deferred_writes_wait_for_flush() -> crate::error::Result<()> {
+    let (mut stream, mut peer) = stream_pair()?;
+    peer.set_nonblocking(true)?;
+
+    stream.write_deferred(b"ab");
+    // written after the deferred bytes, not before
+    stream.write_all(b"cd")?;
+    let mut received = [0_u8; 4];
+    check!(peer.read(&mut received).is_err());
+
+    stream.flush()?;
+    peer.set_nonblocking(false)?;
+    peer.read_exact(&mut received)?;
+    check_eq!(&received, b"abcd");
+
+    // nothing is held back after a flush
+    stream.write_all(b"ef")?;
+    let mut direct = [0_u8; 2];
+    peer.read_exact(&mut direct)?;
+    check_eq!(&direct, b"ef");
+    Ok(())
+}
+
+#[test]
+fn quickack_flush() -> crate::error::Result<()> {
+    let (mut stream, mut peer) = stream_pair()?;
+    stream.set_quickack(true);
+    stream.write_all(b"x")?;
+    stream.flush()?;
+    let mut received = [0_u8; 1];
+    peer.read_exact(&mut received)?;
+    check_eq!(&received, b"x");
+    Ok(())
+}
@@ -1,59 +1,162 @@
+use std::ops::{Deref, DerefMut};
+
 use super::Conn;
-use crate::error::{Error, Result};
+use crate::error::Result;
+use crate::quote::quote_identifier;
+use crate::statement_buffer::StatementBuffer;
 
 /// A MySQL transaction for the synchronous connection
 ///
-/// This struct provides transaction control. The connection is passed
-/// to `commit` and `rollback` methods to execute the transaction commands.
-pub struct Transaction {
-    connection_id: u64,
+/// Passed to the closure of [`Conn::transaction`]. The transaction borrows the connection, so
+/// every statement inside the closure runs through it: `tx.query_drop(..)`, `tx.exec(..)` and
+/// the other `Conn` methods are available through `Deref`.
+/// `commit` and `rollback` consume the transaction. [`savepoint`](Self::savepoint) nests a scope
+/// that can be rolled back on its own.
+pub struct Transaction<'conn> {
+    conn: &'conn mut Conn,
 }
 
-impl Transaction {
-    /// Create a new transaction (internal use only)
-    pub(crate) fn new(connection_id: u64) -> Self {
-        Self { connection_id }
+impl<'conn> Transaction<'conn> {
+    pub(crate) fn new(conn: &'conn mut Conn) -> Self {
+        Self { conn }
     }
 
     /// Commit the transaction
+    pub fn commit(self) -> Result<()> {
+        self.conn.query_drop("COMMIT")
+    }
+
+    /// Send the statements of `buffer` in one pipelined batch, then commit
     ///
-    /// This consumes the transaction and sends a COMMIT statement to the server.
-    /// The connection must be passed as an argument to execute the commit.
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::ConnectionMismatch` if the connection is not the same
-    /// as the one that started the transaction.
-    pub fn commit(self, conn: &mut Conn) -> Result<()> {
-        let actual = conn.connection_id();
-        if self.connection_id != actual {
-            return Err(Error::ConnectionMismatch {
-                expected: self.connection_id,
-                actual,
-            });
+    /// See [`Conn::flush_statements`]. If a statement fails, the transaction is rolled back
+    /// and the error is returned.
+    pub fn commit_buffered(self, buffer: &mut StatementBuffer) -> Result<Vec<u64>> {
+        match self.conn.flush_statements(buffer) {
+            Ok(affected_rows) => {
+                self.conn.query_drop("COMMIT")?;
+                Ok(affected_rows)
+            }
+            Err(err) => {
+                let _ = self.conn.query_drop("ROLLBACK");
+                Err(err)
+            }
         }
-        conn.set_in_transaction(false);
-        conn.query_drop("COMMIT")
     }
 
     /// Rollback the transaction
+    pub fn rollback(self) -> Result<()> {
+        self.conn.query_drop("ROLLBACK")
+    }
+
+    /// Execute a closure within a savepoint (`SAVEPOINT name`)
     ///
-    /// This consumes the transaction and sends a ROLLBACK statement to the server.
-    /// The connection must be passed as an argument to execute the rollback.
+    /// If the closure returns `Ok`, the savepoint is released (`RELEASE SAVEPOINT`). If it returns
+    /// `Err`, the statements since the savepoint are rolled back (`ROLLBACK TO SAVEPOINT`) and the
+    /// transaction continues. Savepoints nest through [`Savepoint::savepoint`].
+    pub fn savepoint<F, R>(&mut self, name: &str, f: F) -> Result<R>
+    where
+        F: FnOnce(Savepoint<'_>) -> Result<R>,
+    {
+        run_savepoint(self.conn, name, f)
+    }
+}
+
+impl Deref for Transaction<'_> {
+    type Target = Conn;
+    fn deref(&self) -> &Self::Target {
+        self.conn
+    }
+}
+
+impl DerefMut for Transaction<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+    }
+}
+
+/// A savepoint within a [`Transaction`]
+///
+/// Passed to the closure of [`Transaction::savepoint`]. The `Conn` methods are available
+/// through `Deref`. `release` and `rollback` consume the savepoint.
+pub struct Savepoint<'conn> {
+    conn: &'conn mut Conn,
+    name: &'conn str,
+    ended: &'conn mut bool,
+}
+
+impl Savepoint<'_> {
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Keep the statements since the savepoint (`RELEASE SAVEPOINT`)
+    pub fn release(self) -> Result<()> {
+        *self.ended = true;
+        self.conn.query_drop(&format!(
+            "RELEASE SAVEPOINT {}",
+            quote_identifier(self.name)
+        ))
+    }
+
+    /// Undo the statements since the savepoint (`ROLLBACK TO SAVEPOINT`)
     ///
-    /// # Errors
+    /// The enclosing transaction continues.
+    pub fn rollback(self) -> Result<()> {
+        *self.ended = true;
+        self.conn.query_drop(&format!(
+            "ROLLBACK TO SAVEPOINT {}",
+            quote_identifier(self.name)
+        ))
+    }
+
+    /// Execute a closure within a nested savepoint
     ///
-    /// Returns `Error::ConnectionMismatch` if the connection is not the same
-    /// as the one that started the transaction.
-    pub fn rollback(self, conn: &mut Conn) -> Result<()> {
-        let actual = conn.connection_id();
-        if self.connection_id != actual {
-            return Err(Error::ConnectionMismatch {
-                expected: self.connection_id,
-                actual,
-            });
+    /// See [`Transaction::savepoint`].
+    pub fn savepoint<F, R>(&mut self, name: &str, f: F) -> Result<R>
+    where
+        F: FnOnce(Savepoint<'_>) -> Result<R>,
+    {
+        run_savepoint(self.conn, name, f)
+    }
+}
+
+impl Deref for Savepoint<'_> {
+    type Target = Conn;
+    fn deref(&self) -> &Self::Target {
+        self.conn
+    }
+}
+
+impl DerefMut for Savepoint<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn
+    }
+}
+
+fn run_savepoint<F, R>(conn: &mut Conn, name: &str, f: F) -> Result<R>
+where
+    F: FnOnce(Savepoint<'_>) -> Result<R>,
+{
+    let quoted = quote_identifier(name);
+    conn.query_drop(&format!("SAVEPOINT {quoted}"))?;
+
+    let mut ended = false;
+    let result = f(Savepoint {
+        conn: &mut *conn,
+        name,
+        ended: &mut ended,
+    });
+
+    // If no explicit release/rollback was called, release on Ok, rollback on Err.
+    // A savepoint ends with its transaction.
+    if !ended && conn.in_transaction() {
+        match &result {
+            Ok(_) => conn.query_drop(&format!("RELEASE SAVEPOINT {quoted}"))?,
+            Err(_) => {
+                let _ = conn.query_drop(&format!("ROLLBACK TO SAVEPOINT {quoted}"));
+            }
         }
-        conn.set_in_transaction(false);
-        conn.query_drop("ROLLBACK")
     }
+
+    result
 }
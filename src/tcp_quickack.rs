@@ -0,0 +1,24 @@
+//! `TCP_QUICKACK` for [`Opts::tcp_quickack`](crate::Opts::tcp_quickack).
+//!
+//! Linux leaves quick-ACK mode on its own once the connection looks interactive again,
+//! so the option is set after every request instead of once per socket.
+
+/// Acknowledge the next incoming segments of `fd` immediately.
+#[cfg(target_os = "linux")]
+pub(crate) fn enable(fd: std::os::fd::RawFd) -> std::io::Result<()> {
+    let on: libc::c_int = 1;
+    // SAFETY: `on` is valid for reads of `size_of::<c_int>()` bytes
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::IPPROTO_TCP,
+            libc::TCP_QUICKACK,
+            (&raw const on).cast(),
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! TLS implementations.
+//!
+//! `sync-tls`, `tokio-tls` and `compio-tls` enable TLS for a runtime. The TLS library is chosen
+//! with exactly one of two features:
+//!
+//! - `native-tls`: OpenSSL, Secure Transport or SChannel, trusting the system's certificates
+//! - `rustls`: rustls with the `aws-lc-rs` provider, trusting the Mozilla root certificates
+//!   of `webpki-roots`
+//!
+//! The streams only see [`TlsBackend`], implemented by [`NativeTls`] and [`Rustls`].
+
+use std::io;
+#[cfg(feature = "sync-tls")]
+use std::io::{Read, Write};
+#[cfg(feature = "sync-tls")]
+use std::net::TcpStream;
+
+/// Upgrades a TCP stream to TLS and inspects the result
+pub(crate) trait TlsBackend {
+    #[cfg(feature = "sync-tls")]
+    type Stream: Read + Write;
+    #[cfg(feature = "tokio-tls")]
+    type TokioStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send;
+
+    /// Perform the TLS handshake with `host` on a blocking stream
+    #[cfg(feature = "sync-tls")]
+    fn connect(host: &str, tcp: TcpStream) -> io::Result<Self::Stream>;
+
+    #[cfg(feature = "sync-tls")]
+    fn tcp(stream: &Self::Stream) -> &TcpStream;
+
+    /// The DER-encoded certificate of the server
+    #[cfg(feature = "sync-tls")]
+    fn peer_certificate(stream: &Self::Stream) -> Option<Vec<u8>>;
+
+    /// Perform the TLS handshake with `host` on a tokio stream
+    #[cfg(feature = "tokio-tls")]
+    fn connect_tokio(
+        host: &str,
+        tcp: tokio::net::TcpStream,
+    ) -> impl Future<Output = io::Result<Self::TokioStream>> + Send;
+
+    #[cfg(feature = "tokio-tls")]
+    fn tokio_tcp(stream: &Self::TokioStream) -> &tokio::net::TcpStream;
+
+    /// The DER-encoded certificate of the server
+    #[cfg(feature = "tokio-tls")]
+    fn tokio_peer_certificate(stream: &Self::TokioStream) -> Option<Vec<u8>>;
+
+    /// compio implements both libraries behind one connector
+    #[cfg(feature = "compio-tls")]
+    fn compio_connector() -> io::Result<compio::tls::TlsConnector>;
+}
+
+/// The implementation selected by the `native-tls` or `rustls` feature
+#[cfg(feature = "native-tls")]
+pub(crate) type Tls = NativeTls;
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+pub(crate) type Tls = Rustls;
+
+#[cfg(feature = "native-tls")]
+pub(crate) struct NativeTls;
+
+#[cfg(feature = "native-tls")]
+impl TlsBackend for NativeTls {
+    #[cfg(feature = "sync-tls")]
+    type Stream = native_tls::TlsStream<TcpStream>;
+    #[cfg(feature = "tokio-tls")]
+    type TokioStream = tokio_native_tls::TlsStream<tokio::net::TcpStream>;
+
+    #[cfg(feature = "sync-tls")]
+    fn connect(host: &str, tcp: TcpStream) -> io::Result<Self::Stream> {
+        let connector = native_tls::TlsConnector::new().map_err(io::Error::other)?;
+        connector.connect(host, tcp).map_err(io::Error::other)
+    }
+
+    #[cfg(feature = "sync-tls")]
+    fn tcp(stream: &Self::Stream) -> &TcpStream {
+        stream.get_ref()
+    }
+
+    #[cfg(feature = "sync-tls")]
+    fn peer_certificate(stream: &Self::Stream) -> Option<Vec<u8>> {
+        stream.peer_certificate().ok().flatten()?.to_der().ok()
+    }
+
+    #[cfg(feature = "tokio-tls")]
+    async fn connect_tokio(
+        host: &str,
+        tcp: tokio::net::TcpStream,
+    ) -> io::Result<Self::TokioStream> {
+        let connector = native_tls::TlsConnector::new().map_err(io::Error::other)?;
+        tokio_native_tls::TlsConnector::from(connector)
+            .connect(host, tcp)
+            .await
+            .map_err(io::Error::other)
+    }
+
+    #[cfg(feature = "tokio-tls")]
+    fn tokio_tcp(stream: &Self::TokioStream) -> &tokio::net::TcpStream {
+        stream.get_ref().get_ref().get_ref()
+    }
+
+    #[cfg(feature = "tokio-tls")]
+    fn tokio_peer_certificate(stream: &Self::TokioStream) -> Option<Vec<u8>> {
+        stream
+            .get_ref()
+            .peer_certificate()
+            .ok()
+            .flatten()?
+            .to_der()
+            .ok()
+    }
+
+    #[cfg(feature = "compio-tls")]
+    fn compio_connector() -> io::Result<compio::tls::TlsConnector> {
+        let connector = compio::native_tls::TlsConnector::new().map_err(io::Error::other)?;
+        Ok(compio::tls::TlsConnector::from(connector))
+    }
+}
+
+#[cfg(feature = "rustls")]
+pub(crate) struct Rustls;
+
+#[cfg(feature = "rustls")]
+impl Rustls {
+    /// Built once and shared by every connection
+    fn client_config() -> io::Result<std::sync::Arc<rustls::ClientConfig>> {
+        use std::sync::{Arc, OnceLock};
+
+        static CONFIG: OnceLock<Arc<rustls::ClientConfig>> = OnceLock::new();
+        if let Some(config) = CONFIG.get() {
+            return Ok(Arc::clone(config));
+        }
+        let roots = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(Arc::clone(CONFIG.get_or_init(|| Arc::new(config))))
+    }
+
+    #[cfg(any(feature = "sync-tls", feature = "tokio-tls"))]
+    fn server_name(host: &str) -> io::Result<rustls::pki_types::ServerName<'static>> {
+        rustls::pki_types::ServerName::try_from(host.to_owned())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl TlsBackend for Rustls {
+    #[cfg(feature = "sync-tls")]
+    type Stream = rustls::StreamOwned<rustls::ClientConnection, TcpStream>;
+    #[cfg(feature = "tokio-tls")]
+    type TokioStream = tokio_rustls::client::TlsStream<tokio::net::TcpStream>;
+
+    #[cfg(feature = "sync-tls")]
+    fn connect(host: &str, tcp: TcpStream) -> io::Result<Self::Stream> {
+        let conn = rustls::ClientConnection::new(Self::client_config()?, Self::server_name(host)?)
+            .map_err(io::Error::other)?;
+        let mut stream = rustls::StreamOwned::new(conn, tcp);
+        // Fail here instead of on the first read if the handshake fails
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock)?;
+        }
+        Ok(stream)
+    }
+
+    #[cfg(feature = "sync-tls")]
+    fn tcp(stream: &Self::Stream) -> &TcpStream {
+        stream.get_ref()
+    }
+
+    #[cfg(feature = "sync-tls")]
+    fn peer_certificate(stream: &Self::Stream) -> Option<Vec<u8>> {
+        let certificate = stream.conn.peer_certificates()?.first()?;
+        Some(certificate.as_ref().to_vec())
+    }
+
+    #[cfg(feature = "tokio-tls")]
+    async fn connect_tokio(
+        host: &str,
+        tcp: tokio::net::TcpStream,
+    ) -> io::Result<Self::TokioStream> {
+        tokio_rustls::TlsConnector::from(Self::client_config()?)
+            .connect(Self::server_name(host)?, tcp)
+            .await
+    }
+
+    #[cfg(feature = "tokio-tls")]
+    fn tokio_tcp(stream: &Self::TokioStream) -> &tokio::net::TcpStream {
+        stream.get_ref().0
+    }
+
+    #[cfg(feature = "tokio-tls")]
+    fn tokio_peer_certificate(stream: &Self::TokioStream) -> Option<Vec<u8>> {
+        let certificate = stream.get_ref().1.peer_certificates()?.first()?;
+        Some(certificate.as_ref().to_vec())
+    }
+
+    #[cfg(feature = "compio-tls")]
+    fn compio_connector() -> io::Result<compio::tls::TlsConnector> {
+        Ok(compio::tls::TlsConnector::from(Self::client_config()?))
+    }
+}
@@ -0,0 +1,249 @@
+//! TLS session details of a connection.
+//!
+//! `Conn::tls_info()` reports whether a connection is encrypted, with the protocol version and
+//! cipher the server negotiated and the certificate the server presented, so that health checks
+//! and security scanners can verify the connection instead of trusting the configuration.
+//!
+//! The protocol version and cipher are read from the server's `Ssl_version` and `Ssl_cipher`
+//! session status variables. The certificate is read from the TLS stream; it is not available on
+//! the compio backend.
+
+use crate::error::Result;
+use crate::protocol::TextRowPayload;
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::primitive::read_string_lenenc;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::TextResultSetHandler;
+
+pub(crate) const TLS_STATUS_SQL: &str =
+    "SHOW SESSION STATUS WHERE Variable_name IN ('Ssl_version', 'Ssl_cipher')";
+
+/// The TLS session of an encrypted connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Example: `"TLSv1.3"`
+    pub version: String,
+    /// Example: `"TLS_AES_256_GCM_SHA384"`
+    pub cipher: String,
+    /// `None` on the compio backend, or if the server sent no certificate.
+    pub peer_certificate: Option<PeerCertificate>,
+}
+
+/// The X.509 certificate presented by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCertificate {
+    /// The subject distinguished name, e.g. `"CN=db.example.com, O=Example"`
+    pub subject: String,
+    /// The issuer distinguished name
+    pub issuer: String,
+    /// The start of the validity period in RFC 3339, e.g. `"2026-01-01T00:00:00Z"`
+    pub not_before: String,
+    /// The end of the validity period in RFC 3339, e.g. `"2027-01-01T00:00:00Z"`
+    pub not_after: String,
+    /// The DER encoding, for checks not covered by the fields above.
+    pub der: Vec<u8>,
+}
+
+impl PeerCertificate {
+    /// Parse a DER-encoded certificate. Returns `None` if `der` is not a certificate.
+    pub fn from_der(der: Vec<u8>) -> Option<Self> {
+        let (certificate, _) = read_tlv(&der, TAG_SEQUENCE)?;
+        let (tbs, _) = read_tlv(certificate, TAG_SEQUENCE)?;
+        // version is an optional [0] EXPLICIT field
+        let tbs = match read_any_tlv(tbs)? {
+            (TAG_VERSION, _, rest) => rest,
+            _ => tbs,
+        };
+        let (_, _serial, rest) = read_any_tlv(tbs)?;
+        let (_signature, rest) = read_tlv(rest, TAG_SEQUENCE)?;
+        let (issuer, rest) = read_tlv(rest, TAG_SEQUENCE)?;
+        let (validity, rest) = read_tlv(rest, TAG_SEQUENCE)?;
+        let (subject, _) = read_tlv(rest, TAG_SEQUENCE)?;
+        let (not_before, validity) = read_time(validity)?;
+        let (not_after, _) = read_time(validity)?;
+        Some(Self {
+            subject: format_name(subject)?,
+            issuer: format_name(issuer)?,
+            not_before,
+            not_after,
+            der,
+        })
+    }
+}
+
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_BMP_STRING: u8 = 0x1E;
+const TAG_VERSION: u8 = 0xA0;
+
+/// Read one DER element: `(tag, content, rest)`
+fn read_any_tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, data) = data.split_first()?;
+    let (&first, data) = data.split_first()?;
+    let (len, data) = if first < 0x80 {
+        (first as usize, data)
+    } else {
+        let num_bytes = (first & 0x7F) as usize;
+        if num_bytes == 0 || num_bytes > size_of::<usize>() || data.len() < num_bytes {
+            return None;
+        }
+        let (len_bytes, data) = data.split_at(num_bytes);
+        let len = len_bytes
+            .iter()
+            .fold(0_usize, |len, &byte| (len << 8) | byte as usize);
+        (len, data)
+    };
+    if data.len() < len {
+        return None;
+    }
+    let (content, rest) = data.split_at(len);
+    Some((tag, content, rest))
+}
+
+fn read_tlv(data: &[u8], expected_tag: u8) -> Option<(&[u8], &[u8])> {
+    match read_any_tlv(data)? {
+        (tag, content, rest) if tag == expected_tag => Some((content, rest)),
+        _ => None,
+    }
+}
+
+/// Read a UTCTime or GeneralizedTime as RFC 3339
+fn read_time(data: &[u8]) -> Option<(String, &[u8])> {
+    let (tag, content, rest) = read_any_tlv(data)?;
+    let text = std::str::from_utf8(content).ok()?;
+    let digits = text.strip_suffix('Z')?;
+    let full = match tag {
+        // RFC 5280: YY >= 50 is 19YY, otherwise 20YY
+        TAG_UTC_TIME if digits.len() == 12 => {
+            let century = if &digits[..2] >= "50" { "19" } else { "20" };
+            format!("{century}{digits}")
+        }
+        TAG_GENERALIZED_TIME if digits.len() == 14 => digits.to_string(),
+        _ => return None,
+    };
+    if !full.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    Some((
+        format!(
+            "{}-{}-{}T{}:{}:{}Z",
+            &full[0..4],
+            &full[4..6],
+            &full[6..8],
+            &full[8..10],
+            &full[10..12],
+            &full[12..14]
+        ),
+        rest,
+    ))
+}
+
+/// Format a Name as `"CN=db.example.com, O=Example"`, in certificate order
+fn format_name(mut name: &[u8]) -> Option<String> {
+    let mut parts = Vec::new();
+    while !name.is_empty() {
+        let (mut set, next_set) = read_tlv(name, TAG_SET)?;
+        name = next_set;
+        while !set.is_empty() {
+            let (attribute, next_attribute) = read_tlv(set, TAG_SEQUENCE)?;
+            set = next_attribute;
+            let (oid, value) = read_tlv(attribute, TAG_OID)?;
+            let (value_tag, value, _) = read_any_tlv(value)?;
+            let value = if value_tag == TAG_BMP_STRING {
+                let units: Vec<u16> = value
+                    .as_chunks::<2>()
+                    .0
+                    .iter()
+                    .map(|&pair| u16::from_be_bytes(pair))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            } else {
+                String::from_utf8_lossy(value).into_owned()
+            };
+            parts.push(format!("{}={value}", attribute_name(oid)));
+        }
+    }
+    Some(parts.join(", "))
+}
+
+fn attribute_name(oid: &[u8]) -> String {
+    match oid {
+        [0x55, 0x04, 0x03] => "CN".to_string(),
+        [0x55, 0x04, 0x06] => "C".to_string(),
+        [0x55, 0x04, 0x07] => "L".to_string(),
+        [0x55, 0x04, 0x08] => "ST".to_string(),
+        [0x55, 0x04, 0x0A] => "O".to_string(),
+        [0x55, 0x04, 0x0B] => "OU".to_string(),
+        _ => oid_to_string(oid),
+    }
+}
+
+/// Dotted decimal notation of an OID
+fn oid_to_string(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value = 0_u64;
+    for &byte in oid {
+        value = (value << 7) | u64::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Collects the rows of [`TLS_STATUS_SQL`]
+#[derive(Default)]
+pub(crate) struct TlsStatusHandler {
+    version: String,
+    cipher: String,
+}
+
+impl TlsStatusHandler {
+    /// `None` if the server reports no cipher, i.e. the connection is not encrypted
+    pub(crate) fn into_info(self, peer_certificate: Option<Vec<u8>>) -> Option<TlsInfo> {
+        if self.cipher.is_empty() {
+            return None;
+        }
+        Some(TlsInfo {
+            version: self.version,
+            cipher: self.cipher,
+            peer_certificate: peer_certificate.and_then(PeerCertificate::from_der),
+        })
+    }
+}
+
+impl TextResultSetHandler for TlsStatusHandler {
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        let (name, rest) = read_string_lenenc(row.0)?;
+        let (value, _) = read_string_lenenc(rest)?;
+        let value = String::from_utf8_lossy(value).into_owned();
+        if name.eq_ignore_ascii_case(b"Ssl_version") {
+            self.version = value;
+        } else if name.eq_ignore_ascii_case(b"Ssl_cipher") {
+            self.cipher = value;
+        }
+        Ok(())
+    }
+}
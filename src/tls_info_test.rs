@@ -0,0 +1,80 @@
+use crate::error::{Error, Result};
+use crate::test_macros::{check, check_eq};
+use crate::tls_info::{PeerCertificate, TlsStatusHandler};
+
+/// `openssl req -x509 -subj "/C=US/O=Example/CN=db.example.com"`, valid in 2026
+const CERT: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBxDCCAWugAwIBAgIUBuvT3wVKpmsh9Cj6Dq4KdxjE4+8wCgYIKoZIzj0EAwIw
+ODELMAkGA1UEBhMCVVMxEDAOBgNVBAoMB0V4YW1wbGUxFzAVBgNVBAMMDmRiLmV4
+YW1wbGUuY29tMB4XDTI2MDEwMTAwMDAwMFoXDTI3MDEwMTAwMDAwMFowODELMAkG
+A1UEBhMCVVMxEDAOBgNVBAoMB0V4YW1wbGUxFzAVBgNVBAMMDmRiLmV4YW1wbGUu
+Y29tMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAETMmUkvdnUOgjR9pUmuJp3Ai3
+xHBbrt4HPaCOmDGiJphu7jc/IU1+J3lsW6Ky8lvVe6vP+1Qg0V7uhwuOxaLdWaNT
+MFEwHQYDVR0OBBYEFDiBdlylPhJZRoqJXyVsVQ0LfbBcMB8GA1UdIwQYMBaAFDiB
+dlylPhJZRoqJXyVsVQ0LfbBcMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwID
+RwAwRAIgC5x7hFR+rDo3KvNLS8xlJKHQsmtRJvpx+R8+jyALpy0CIGa+foGpO5IP
+yZO+VyI0UjrhfCQPenltuLrM8q/JPcZi
+-----END CERTIFICATE-----";
+
+/// Generalized time after 2049, and an attribute without a short name
+const CERT_2051: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBtjCCAV2gAwIBAgIUNsUZ2Fz+kE0/alMhCVLk0u3xar0wCgYIKoZIzj0EAwIw
+MDEOMAwGA1UEAwwFbXlzcWwxHjAcBgkqhkiG9w0BCQEWD2RiYUBleGFtcGxlLmNv
+bTAgFw05OTEyMzEyMzU5NTlaGA8yMDUxMDEwMTAwMDAwMFowMDEOMAwGA1UEAwwF
+bXlzcWwxHjAcBgkqhkiG9w0BCQEWD2RiYUBleGFtcGxlLmNvbTBZMBMGByqGSM49
+AgEGCCqGSM49AwEHA0IABO4Vq4jlXvQfylCAWwIo1k721cwxxFEYSHaLcD74WjNk
+czJM+UK9STSNUCqt9DiIjxTtT+xx3XKKf/n6zsN/xFOjUzBRMB0GA1UdDgQWBBSC
+08NLKGDTJPFoNb0t+145GZfmtTAfBgNVHSMEGDAWgBSC08NLKGDTJPFoNb0t+145
+GZfmtTAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0cAMEQCIG5Kmykra1oe
+9acLNwS6JIqgpR+J13OufT44hwCHJ43/AiA4xJ4opY6ZWEG43yMdH6rNIddbKqcu
+Sm54CgeuP9Hdlg==
+-----END CERTIFICATE-----";
+
+fn der(pem: &str) -> Result<Vec<u8>> {
+    pem::parse(pem)
+        .map(pem::Pem::into_contents)
+        .map_err(|err| Error::BadUsageError(err.to_string()))
+}
+
+#[test]
+fn parse_certificate() -> Result<()> {
+    let der = der(CERT)?;
+    let cert = PeerCertificate::from_der(der.clone())
+        .ok_or_else(|| Error::BadUsageError("not a certificate".to_string()))?;
+    check_eq!(cert.subject, "C=US, O=Example, CN=db.example.com");
+    check_eq!(cert.issuer, "C=US, O=Example, CN=db.example.com");
+    check_eq!(cert.not_before, "2026-01-01T00:00:00Z");
+    check_eq!(cert.not_after, "2027-01-01T00:00:00Z");
+    check_eq!(cert.der, der);
+    Ok(())
+}
+
+#[test]
+fn parse_generalized_time_and_unknown_attribute() -> Result<()> {
+    let cert = PeerCertificate::from_der(der(CERT_2051)?)
+        .ok_or_else(|| Error::BadUsageError("not a certificate".to_string()))?;
+    check_eq!(
+        cert.subject,
+        "CN=mysql, 1.2.840.113549.1.9.1=dba@example.com"
+    );
+    check_eq!(cert.not_before, "1999-12-31T23:59:59Z");
+    check_eq!(cert.not_after, "2051-01-01T00:00:00Z");
+    Ok(())
+}
+
+#[test]
+fn reject_truncated_certificate() -> Result<()> {
+    let mut der = der(CERT)?;
+    der.truncate(100);
+    check!(PeerCertificate::from_der(der).is_none());
+    check!(PeerCertificate::from_der(Vec::new()).is_none());
+    Ok(())
+}
+
+#[test]
+fn no_cipher_means_unencrypted() -> Result<()> {
+    check!(TlsStatusHandler::default().into_info(None).is_none());
+    Ok(())
+}
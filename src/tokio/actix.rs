@@ -0,0 +1,46 @@
+//! actix-web extractor for [`PooledConn`].
+//!
+//! The pool is taken from `web::Data<Pool>` registered with `App::app_data`:
+//!
+//! ```ignore
+//! use actix_web::{App, HttpServer, web};
+//! use zero_mysql::tokio::{Pool, PooledConn};
+//!
+//! async fn handler(mut conn: PooledConn) -> actix_web::Result<&'static str> {
+//!     conn.query_drop("SELECT 1").await.map_err(actix_web::error::ErrorInternalServerError)?;
+//!     Ok("ok")
+//! }
+//!
+//! let pool = web::Data::new(Pool::new(opts));
+//! HttpServer::new(move || App::new().app_data(pool.clone()).route("/", web::get().to(handler)))
+//! ```
+//!
+//! There is no extractor for `Transaction` because transactions are scoped by `Conn::transaction`.
+
+use actix_web::dev::Payload;
+use actix_web::error::{ErrorInternalServerError, ErrorServiceUnavailable};
+use actix_web::{FromRequest, HttpRequest, web};
+use std::future::Future;
+use std::pin::Pin;
+
+use super::{Pool, PooledConn};
+
+impl FromRequest for PooledConn {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let pool = req.app_data::<web::Data<Pool>>().cloned();
+        Box::pin(async move {
+            let Some(pool) = pool else {
+                return Err(ErrorInternalServerError(
+                    "zero_mysql::tokio::Pool is not registered with App::app_data",
+                ));
+            };
+            pool.into_inner().get().await.map_err(|err| {
+                tracing::warn!(error = %err, "failed to acquire a connection from the pool");
+                ErrorServiceUnavailable("database connection unavailable")
+            })
+        })
+    }
+}
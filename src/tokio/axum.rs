@@ -0,0 +1,63 @@
+//! axum extractor for [`PooledConn`].
+//!
+//! The pool is taken from the router state through `FromRef`:
+//!
+//! ```ignore
+//! use std::sync::Arc;
+//! use axum::{Router, extract::FromRef, routing::get};
+//! use zero_mysql::tokio::{Pool, PooledConn};
+//!
+//! #[derive(Clone, FromRef)]
+//! struct AppState {
+//!     pool: Arc<Pool>,
+//! }
+//!
+//! async fn handler(mut conn: PooledConn) -> String {
+//!     conn.query_drop("SELECT 1").await.map(|()| "ok".into()).unwrap_or_default()
+//! }
+//!
+//! let app = Router::new().route("/", get(handler)).with_state(AppState { pool });
+//! ```
+//!
+//! There is no extractor for `Transaction` because transactions are scoped by `Conn::transaction`.
+
+use std::sync::Arc;
+
+use axum_core::extract::{FromRef, FromRequestParts};
+use axum_core::response::{IntoResponse, Response};
+use http::StatusCode;
+use http::request::Parts;
+
+use crate::error::Error;
+
+use super::{Pool, PooledConn};
+
+/// Rejection used when a connection cannot be acquired from the pool.
+///
+/// Responds with `503 Service Unavailable` without exposing the error message.
+#[derive(Debug)]
+pub struct PoolRejection(pub Error);
+
+impl IntoResponse for PoolRejection {
+    fn into_response(self) -> Response {
+        tracing::warn!(error = %self.0, "failed to acquire a connection from the pool");
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "database connection unavailable",
+        )
+            .into_response()
+    }
+}
+
+impl<S> FromRequestParts<S> for PooledConn
+where
+    Arc<Pool>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = PoolRejection;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let pool = Arc::<Pool>::from_ref(state);
+        pool.get().await.map_err(PoolRejection)
+    }
+}
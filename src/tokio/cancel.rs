@@ -0,0 +1,107 @@
+//! Aborting a running command from another task.
+//!
+//! A dropped query future leaves its response on the socket, so the connection cannot be used
+//! again. Instead, [`run_cancellable`] keeps reading while it asks the server to abort the
+//! statement with `KILL QUERY` on a short-lived second connection. The server then ends the
+//! response with `ER_QUERY_INTERRUPTED` and the connection stays usable. If `KILL QUERY` is
+//! disabled or fails, the command is dropped and the connection must be marked broken.
+//!
+//! `KILL QUERY` can reach the server after the command already finished. It then stays pending
+//! and aborts the next statement on the connection, so the caller must absorb it before reuse.
+
+use crate::error::{Error, Result};
+use crate::opts::Opts;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// `ER_QUERY_INTERRUPTED`: `KILL QUERY` aborted the statement
+pub(crate) const ER_QUERY_INTERRUPTED: u16 = 1317;
+
+/// Who to send `KILL QUERY` to
+pub(crate) struct KillQuery<'a> {
+    pub(crate) opts: &'a Opts,
+    pub(crate) connection_id: u64,
+}
+
+impl KillQuery<'_> {
+    /// Send `KILL QUERY` on a new connection and wait for the server to accept it
+    pub(crate) async fn run(self) -> Result<()> {
+        let mut conn = super::Conn::new(self.opts.clone()).await?;
+        conn.query_drop(&format!("KILL QUERY {}", self.connection_id))
+            .await?;
+        conn.close().await
+    }
+}
+
+/// How [`run_cancellable`] ended
+#[derive(Debug)]
+pub(crate) enum Cancellable<T> {
+    /// The command finished and no `KILL QUERY` is left over
+    Finished(Result<T>),
+    /// The command finished, but `KILL QUERY` may have arrived after it and is still pending
+    KillPending(Result<T>),
+    /// The command was dropped before it finished, because there was no kill or it failed
+    Dropped,
+}
+
+/// Which of the command and `KILL QUERY` finished first
+enum Race<T> {
+    Command(Result<T>),
+    Killed(Result<()>),
+}
+
+/// Drive `command` until it finishes or `cancel` fires.
+///
+/// On cancellation, `kill` is awaited to completion while `command` keeps running, and the result
+/// is `Error::Cancelled` if the server aborted the command. `kill` is never dropped half-way,
+/// so a `KILL QUERY` that was already sent is always reported as [`Cancellable::KillPending`].
+pub(crate) async fn run_cancellable<T>(
+    command: impl Future<Output = Result<T>>,
+    cancel: &CancellationToken,
+    kill: Option<impl Future<Output = Result<()>>>,
+) -> Cancellable<T> {
+    let mut command = std::pin::pin!(command);
+    tokio::select! {
+        biased;
+        result = &mut command => return Cancellable::Finished(result),
+        () = cancel.cancelled() => {}
+    }
+    let Some(kill) = kill else {
+        return Cancellable::Dropped;
+    };
+    let mut kill = std::pin::pin!(kill);
+    let race = tokio::select! {
+        biased;
+        result = &mut command => Race::Command(result),
+        killed = &mut kill => Race::Killed(killed),
+    };
+    match race {
+        Race::Command(result) => {
+            finish_kill(kill).await;
+            Cancellable::KillPending(result)
+        }
+        Race::Killed(Err(err)) => {
+            tracing::warn!(error = %err, "KILL QUERY failed, dropping the command");
+            Cancellable::Dropped
+        }
+        Race::Killed(Ok(())) => killed_result(command.await),
+    }
+}
+
+/// Wait for a `KILL QUERY` that was started before the command finished
+async fn finish_kill(kill: impl Future<Output = Result<()>>) {
+    if let Err(err) = kill.await {
+        tracing::debug!(error = %err, "KILL QUERY failed after the command finished");
+    }
+}
+
+/// The result of a command after the server accepted `KILL QUERY` for it
+fn killed_result<T>(result: Result<T>) -> Cancellable<T> {
+    match result {
+        Err(Error::ServerError(payload)) if payload.error_code == ER_QUERY_INTERRUPTED => {
+            Cancellable::Finished(Err(Error::Cancelled))
+        }
+        // The command finished before the kill reached it
+        other => Cancellable::KillPending(other),
+    }
+}
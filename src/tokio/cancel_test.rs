@@ -0,0 +1,147 @@
+use std::future::Ready;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::oneshot;
+
+use crate::error::{Error, Result};
+use crate::protocol::response::ErrPayload;
+use crate::test_macros::{check, check_eq};
+use crate::tokio::cancel::{Cancellable, CancellationToken, ER_QUERY_INTERRUPTED, run_cancellable};
+
+const NO_KILL: Option<Ready<Result<()>>> = None;
+
+fn cancelled() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    cancel
+}
+
+#[tokio::test]
+async fn finished_command_is_returned() -> Result<()> {
+    let cancel = CancellationToken::new();
+    let result = run_cancellable(async { Ok(7) }, &cancel, NO_KILL).await;
+    check!(matches!(result, Cancellable::Finished(Ok(7))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn finished_command_wins_over_cancellation() -> Result<()> {
+    let result = run_cancellable(async { Ok(7) }, &cancelled(), NO_KILL).await;
+    check!(matches!(result, Cancellable::Finished(Ok(7))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn pending_command_is_dropped_without_kill_query() -> Result<()> {
+    let cancel = CancellationToken::new();
+    let canceller = cancel.clone();
+    tokio::spawn(async move { canceller.cancel() });
+    let result = run_cancellable(std::future::pending::<Result<()>>(), &cancel, NO_KILL).await;
+    check!(matches!(result, Cancellable::Dropped));
+    Ok(())
+}
+
+#[tokio::test]
+async fn command_errors_are_kept() -> Result<()> {
+    let cancel = CancellationToken::new();
+    let result = run_cancellable(
+        async { Err::<(), _>(Error::BadUsageError("bad".to_string())) },
+        &cancel,
+        NO_KILL,
+    )
+    .await;
+    check!(matches!(
+        result,
+        Cancellable::Finished(Err(Error::BadUsageError(_)))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn pending_command_is_dropped_when_kill_query_fails() -> Result<()> {
+    let kill = async { Err(Error::BadUsageError("no server".to_string())) };
+    let result = run_cancellable(
+        std::future::pending::<Result<()>>(),
+        &cancelled(),
+        Some(kill),
+    )
+    .await;
+    check!(matches!(result, Cancellable::Dropped));
+    Ok(())
+}
+
+#[tokio::test]
+async fn interrupted_command_is_cancelled() -> Result<()> {
+    let (killed_tx, killed_rx) = oneshot::channel();
+    let command = async move {
+        let _ = killed_rx.await;
+        Err::<(), _>(Error::ServerError(ErrPayload {
+            error_code: ER_QUERY_INTERRUPTED,
+            sql_state: "70100".to_string(),
+            message: "Query execution was interrupted".to_string(),
+        }))
+    };
+    let kill = async move {
+        let _ = killed_tx.send(());
+        Ok(())
+    };
+    let result = run_cancellable(command, &cancelled(), Some(kill)).await;
+    check!(matches!(
+        result,
+        Cancellable::Finished(Err(Error::Cancelled))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn command_finishing_after_kill_leaves_kill_pending() -> Result<()> {
+    let (killed_tx, killed_rx) = oneshot::channel();
+    let command = async move {
+        let _ = killed_rx.await;
+        Ok(7)
+    };
+    let kill = async move {
+        let _ = killed_tx.send(());
+        Ok(())
+    };
+    let result = run_cancellable(command, &cancelled(), Some(kill)).await;
+    check!(matches!(result, Cancellable::KillPending(Ok(7))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn command_winning_the_race_waits_for_kill() -> Result<()> {
+    let (kill_sent_tx, kill_sent_rx) = oneshot::channel();
+    let (command_done_tx, command_done_rx) = oneshot::channel();
+    let (kill_done_tx, kill_done_rx) = oneshot::channel::<()>();
+    let kill_done = Arc::new(AtomicBool::new(false));
+    let command = async move {
+        let _ = kill_sent_rx.await;
+        let _ = command_done_tx.send(());
+        Ok(7)
+    };
+    let kill = {
+        let kill_done = Arc::clone(&kill_done);
+        async move {
+            // KILL QUERY is on its way when the command finishes
+            let _ = kill_sent_tx.send(());
+            let _ = kill_done_rx.await;
+            kill_done.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    };
+    let run = tokio::spawn(async move { run_cancellable(command, &cancelled(), Some(kill)).await });
+    command_done_rx
+        .await
+        .map_err(|err| Error::BadUsageError(err.to_string()))?;
+    tokio::task::yield_now().await;
+    check!(!run.is_finished());
+    check_eq!(kill_done_tx.send(()), Ok(()));
+    let result = run
+        .await
+        .map_err(|err| Error::BadUsageError(err.to_string()))?;
+    check!(kill_done.load(Ordering::SeqCst));
+    check!(matches!(result, Cancellable::KillPending(Ok(7))));
+    Ok(())
+}
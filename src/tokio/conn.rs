@@ -1,4 +1,6 @@
 use std::ops::AsyncFnOnce;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use tokio::net::TcpStream;
 #[cfg(unix)]
@@ -7,25 +9,74 @@ use tracing::instrument;
 use zerocopy::{FromBytes, FromZeros, IntoBytes};
 
 use crate::PreparedStatement;
+use crate::alloc_stats::{self, AllocStats, ConnAllocStats};
+use crate::audit::AuditLog;
+use crate::binlog::{
+    BinlogParser, BinlogRequest, CHECKSUM_SQL, MARIADB_CHECKSUM_SQL, MYSQL_CHECKSUM_SQL,
+    PositionTracker, parse_checksum,
+};
 use crate::buffer::BufferSet;
 use crate::buffer_pool::PooledBufferSet;
+use crate::charset::Collation;
+use crate::classify::{READ_ONLY_SQL, StatementClass, check_read_only, classify, max_rows};
 use crate::constant::CapabilityFlags;
+use crate::dialect::Dialect;
+use crate::emulate::{self, TextToBinary};
 use crate::error::{Error, Result};
+use crate::handler::{DeferredHandler, HandlerGuard, SessionStateHook};
+use crate::hint;
+use crate::local_infile::{LocalInfile, LocalInfilePackets};
+use crate::negotiation::NegotiationReport;
+use crate::opts::{FlushPolicy, Opts};
 use crate::protocol::TextRowPayload;
 use crate::protocol::command::Action;
 use crate::protocol::command::ColumnDefinition;
+use crate::protocol::command::attributes::{self, NO_ATTRIBUTES};
+use crate::protocol::command::binlog::{
+    BINLOG_DUMP_NON_BLOCK, BINLOG_THROUGH_GTID, write_binlog_dump, write_binlog_dump_gtid,
+    write_register_replica,
+};
 use crate::protocol::command::bulk_exec::{BulkExec, BulkFlags, BulkParamsSet, write_bulk_execute};
-use crate::protocol::command::prepared::{Exec, read_prepare_ok, write_execute, write_prepare};
-use crate::protocol::command::query::{Query, write_query};
+use crate::protocol::command::check_columns_eof;
+use crate::protocol::command::prepared::{
+    Exec, ExecCursor, LONG_DATA_CHUNK_SIZE, read_prepare_ok, write_close_statement, write_execute,
+    write_execute_with_attributes, write_prepare, write_send_long_data,
+};
+use crate::protocol::command::query::{Query, write_query, write_query_with_attributes};
 use crate::protocol::command::utility::{
-    DropHandler, FirstHandler, write_ping, write_reset_connection,
+    DropHandler, FirstHandler, write_ping, write_quit, write_reset_connection,
 };
+use crate::protocol::compression::{CompressionStats, PacketCompression};
 use crate::protocol::connection::{Handshake, HandshakeAction, InitialHandshake};
 use crate::protocol::packet::PacketHeader;
 use crate::protocol::primitive::read_string_lenenc;
-use crate::protocol::response::{ErrPayloadBytes, OkPayloadBytes};
-use crate::protocol::r#trait::{BinaryResultSetHandler, TextResultSetHandler, param::Params};
-
+use crate::protocol::response::{ErrPayloadBytes, OkPayload, OkPayloadBytes, SessionStateChange};
+use crate::protocol::r#trait::{
+    AsyncBinaryResultSetHandler, AsyncTextResultSetHandler, BinaryResultSetHandler, Command,
+    TextResultSetHandler, param::Params, param::TypedParam,
+};
+use crate::quote::quote_identifier;
+use crate::schema_drift::{SchemaDrift, SchemaDriftHook};
+use crate::server_info::{SERVER_INFO_SQL, ServerInfo, ServerInfoHandler};
+use crate::session::{
+    DATABASE_SQL, MARIADB_USER_VARIABLES_SQL, MYSQL_USER_VARIABLES_SQL, SessionSnapshot,
+    TrackedVariables, parse_user_variables,
+};
+use crate::snapshot::first_value;
+use crate::socket_stats::SocketStats;
+use crate::statement_buffer::{PIPELINE_WINDOW, StatementBuffer, write_buffered_execute};
+use crate::statement_cache::{StatementCache, StatementCacheStats};
+use crate::statement_log::{StatementKind, StatementLog};
+use crate::tls_info::{TLS_STATUS_SQL, TlsInfo, TlsStatusHandler};
+use crate::topology::TextRowsHandler;
+use crate::warm_up::StatementRecorder;
+
+use super::cancel::{
+    Cancellable, CancellationToken, ER_QUERY_INTERRUPTED, KillQuery, run_cancellable,
+};
+use super::coop::YieldBudget;
+use super::offload::OffloadCollectHandler;
+use super::row_stream::{RowSlot, RowStream};
 use super::stream::Stream;
 
 pub struct Conn {
@@ -34,8 +85,70 @@ pub struct Conn {
     initial_handshake: InitialHandshake,
     capability_flags: CapabilityFlags,
     mariadb_capabilities: crate::constant::MariadbCapabilityFlags,
-    in_transaction: bool,
+    server_status: crate::constant::ServerStatusFlags,
+    dialect: Dialect,
     is_broken: bool,
+    statement_log: Option<StatementLog>,
+    audit: Option<AuditLog>,
+    schema_drift: Option<SchemaDriftHook>,
+    user: String,
+    auth_plugin: String,
+    negotiation_report: NegotiationReport,
+    /// Sent again as a connection attribute by `change_user()`
+    application_name: Option<String>,
+    /// `Opts::session_setup_sql()`, run after connecting and after every reset
+    session_setup: Option<String>,
+    affected_rows: u64,
+    retain_statement_sql: bool,
+    emulate_prepared_statements: bool,
+    /// `Opts::connection_collation()`, kept across `change_user()`
+    collation: Collation,
+    server_info: Option<ServerInfo>,
+    /// Set when a command failed with `ER_SERVER_SHUTDOWN`
+    server_shutdown: bool,
+    /// `Opts::kill_query_opts()`, `None` if `Opts::kill_query_on_cancel` is off
+    kill_query_opts: Option<Arc<Opts>>,
+    statement_cache: StatementCache,
+    statement_recorder: Option<StatementRecorder>,
+    read_only: bool,
+    role_changed: bool,
+    alloc_stats: ConnAllocStats,
+    local_infile: Option<LocalInfile>,
+    session_state_hook: Option<SessionStateHook>,
+    flush_policy: FlushPolicy,
+    tracked_variables: TrackedVariables,
+    yield_budget: YieldBudget,
+    offload_decode_bytes: usize,
+}
+
+impl std::fmt::Debug for Conn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Conn")
+            .field("connection_id", &self.connection_id())
+            .field(
+                "server_version",
+                &String::from_utf8_lossy(self.server_version()),
+            )
+            .field("server_status", &self.server_status)
+            .field("is_broken", &self.is_broken)
+            .finish_non_exhaustive()
+    }
+}
+
+/// `conn#42 (11.4.8-MariaDB)`
+impl std::fmt::Display for Conn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "conn#{} ({})",
+            self.connection_id(),
+            String::from_utf8_lossy(self.server_version())
+        )?;
+        if let Some(info) = &self.server_info {
+            write!(f, " on {}", info)?;
+        }
+        Ok(())
+    }
 }
 
 impl Conn {
@@ -49,7 +162,7 @@ impl Conn {
         #[cfg(unix)]
         let stream = if let Some(socket_path) = &opts.socket {
             let stream = UnixStream::connect(socket_path).await?;
-            Stream::unix(stream)
+            Stream::unix_with_capacity(stream, opts.read_buffer_size)
         } else {
             if opts.host.is_empty() {
                 return Err(Error::BadUsageError(
@@ -57,9 +170,9 @@ impl Conn {
                 ));
             }
             let addr = format!("{}:{}", opts.host, opts.port);
-            let stream = TcpStream::connect(&addr).await?;
+            let stream = timed(opts.connect_timeout, TcpStream::connect(&addr)).await?;
             stream.set_nodelay(opts.tcp_nodelay)?;
-            Stream::tcp(stream)
+            Stream::tcp_with_capacity(stream, opts.read_buffer_size)
         };
 
         #[cfg(not(unix))]
@@ -75,9 +188,9 @@ impl Conn {
                 ));
             }
             let addr = format!("{}:{}", opts.host, opts.port);
-            let stream = TcpStream::connect(&addr).await?;
+            let stream = timed(opts.connect_timeout, TcpStream::connect(&addr)).await?;
             stream.set_nodelay(opts.tcp_nodelay)?;
-            Stream::tcp(stream)
+            Stream::tcp_with_capacity(stream, opts.read_buffer_size)
         };
 
         Self::new_with_stream(stream, &opts).await
@@ -85,8 +198,13 @@ impl Conn {
 
     /// Create a new MySQL connection with an existing stream (async)
     pub async fn new_with_stream(stream: Stream, opts: &crate::opts::Opts) -> Result<Self> {
+        let opts = &opts.resolve_password()?;
+        let collation = opts.connection_collation()?;
         let mut conn_stream = stream;
+        conn_stream.set_timeouts(opts.read_timeout, opts.write_timeout);
         let mut buffer_set = opts.buffer_pool.get_buffer_set();
+        buffer_set.set_scratch_arena(opts.scratch_arena);
+        buffer_set.set_intern_column_names(opts.intern_column_names);
 
         #[cfg(feature = "tokio-tls")]
         let host = opts.host.clone();
@@ -107,7 +225,8 @@ impl Conn {
                 #[cfg(feature = "tokio-tls")]
                 HandshakeAction::UpgradeTls { sequence_id } => {
                     write_handshake_payload(&mut conn_stream, &mut buffer_set, sequence_id).await?;
-                    conn_stream = conn_stream.upgrade_to_tls(&host).await?;
+                    let timeout = conn_stream.read_timeout();
+                    conn_stream = timed(timeout, conn_stream.upgrade_to_tls(&host)).await?;
                 }
                 #[cfg(not(feature = "tokio-tls"))]
                 HandshakeAction::UpgradeTls { .. } => {
@@ -119,7 +238,21 @@ impl Conn {
             }
         }
 
+        let auth_plugin = handshake.auth_plugin(&buffer_set);
+        let negotiation_report = handshake.negotiation_report();
         let (initial_handshake, capability_flags, mariadb_capabilities) = handshake.finish()?;
+        buffer_set.legacy_eof = !capability_flags.contains(CapabilityFlags::CLIENT_DEPRECATE_EOF);
+        if let Some(compression) = PacketCompression::negotiated(capability_flags, opts) {
+            conn_stream.enable_compression(compression);
+        }
+        conn_stream.set_quickack(opts.tcp_quickack);
+        let server_status = initial_handshake.status_flags;
+        let dialect = opts.dialect.unwrap_or_else(|| {
+            Dialect::detect(
+                &buffer_set.initial_handshake[initial_handshake.server_version.clone()],
+                capability_flags,
+            )
+        });
 
         let conn = Self {
             stream: conn_stream,
@@ -127,8 +260,40 @@ impl Conn {
             initial_handshake,
             capability_flags,
             mariadb_capabilities,
-            in_transaction: false,
+            server_status,
+            dialect,
             is_broken: false,
+            statement_log: opts.statement_log.clone(),
+            audit: opts.audit.clone(),
+            schema_drift: opts.schema_drift.clone(),
+            user: opts.user.clone(),
+            auth_plugin,
+            negotiation_report,
+            application_name: opts.application_name.clone(),
+            session_setup: opts.session_setup_sql(),
+            affected_rows: 0,
+            retain_statement_sql: opts.retain_statement_sql,
+            emulate_prepared_statements: opts.emulate_prepared_statements,
+            collation,
+            server_info: None,
+            server_shutdown: false,
+            kill_query_opts: opts
+                .kill_query_on_cancel
+                .then(|| Arc::new(opts.kill_query_opts())),
+            statement_cache: StatementCache::new(
+                opts.statement_cache_bytes,
+                opts.statement_cache_size,
+            ),
+            statement_recorder: opts.statement_recorder.clone(),
+            read_only: false,
+            role_changed: false,
+            alloc_stats: ConnAllocStats::default(),
+            local_infile: opts.local_infile.clone(),
+            session_state_hook: None,
+            flush_policy: opts.flush_policy,
+            tracked_variables: TrackedVariables::default(),
+            yield_budget: YieldBudget::from_opts(opts),
+            offload_decode_bytes: opts.offload_decode_bytes,
         };
 
         // Upgrade to Unix socket if connected via TCP to loopback
@@ -141,10 +306,25 @@ impl Conn {
         #[cfg(not(unix))]
         let mut conn = conn;
 
-        // Execute init command if specified
-        if let Some(init_command) = &opts.init_command {
+        for init_command in &opts.init_commands {
             conn.query_drop(init_command).await?;
         }
+        if let Some(session_setup) = conn.session_setup.clone() {
+            conn.query_drop(&session_setup).await?;
+        }
+        if opts.server_info {
+            let mut handler = ServerInfoHandler::default();
+            conn.query(SERVER_INFO_SQL, &mut handler).await?;
+            conn.server_info = Some(handler.into_info()?);
+        }
+        if opts.enforce_read_only {
+            conn.query_drop(READ_ONLY_SQL).await?;
+            conn.read_only = true;
+        }
+        if !opts.warm_up_statements.is_empty() {
+            conn.warm_up_statement_cache(opts.warm_up_statements.iter().map(String::as_str))
+                .await?;
+        }
 
         Ok(conn)
     }
@@ -158,6 +338,16 @@ impl Conn {
         self.capability_flags
     }
 
+    /// Get the negotiated MariaDB extended capability flags (empty on MySQL)
+    pub fn mariadb_capabilities(&self) -> crate::constant::MariadbCapabilityFlags {
+        self.mariadb_capabilities
+    }
+
+    fn bulk_operations(&self) -> bool {
+        self.mariadb_capabilities
+            .contains(crate::constant::MariadbCapabilityFlags::MARIADB_CLIENT_STMT_BULK_OPERATIONS)
+    }
+
     /// Check if the server is MySQL (as opposed to MariaDB)
     pub fn is_mysql(&self) -> bool {
         self.capability_flags.is_mysql()
@@ -168,11 +358,56 @@ impl Conn {
         self.capability_flags.is_mariadb()
     }
 
+    /// The server dialect, from `Opts::dialect` or detected from the initial handshake
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
     /// Get the connection ID assigned by the server
     pub fn connection_id(&self) -> u64 {
         self.initial_handshake.connection_id as u64
     }
 
+    /// The user the connection is authenticated as, updated by `change_user()`
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// The connection collation sent in the handshake, or set with `SET NAMES` when its id does
+    /// not fit the handshake byte or `Opts::set_names` is on
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    /// The server instance the connection landed on, if `Opts::server_info` is set
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
+
+    /// The authentication plugin the server accepted, e.g. `"caching_sha2_password"`,
+    /// updated by `change_user()`
+    pub fn auth_plugin(&self) -> &str {
+        &self.auth_plugin
+    }
+
+    /// Capabilities requested in `Opts` that the server or the dialect did not negotiate
+    pub fn negotiation_report(&self) -> &NegotiationReport {
+        &self.negotiation_report
+    }
+
+    /// TLS version, cipher and server certificate, or `None` if the connection is not encrypted.
+    ///
+    /// For encrypted connections, this reads the `Ssl_version` and `Ssl_cipher` session status
+    /// from the server, which takes a round trip.
+    pub async fn tls_info(&mut self) -> Result<Option<TlsInfo>> {
+        if !self.capability_flags.contains(CapabilityFlags::CLIENT_SSL) {
+            return Ok(None);
+        }
+        let mut handler = TlsStatusHandler::default();
+        self.query(TLS_STATUS_SQL, &mut handler).await?;
+        Ok(handler.into_info(self.stream.peer_certificate()))
+    }
+
     /// Get the server status flags from the initial handshake
     pub fn status_flags(&self) -> crate::constant::ServerStatusFlags {
         self.initial_handshake.status_flags
@@ -183,23 +418,219 @@ impl Conn {
         self.is_broken
     }
 
-    #[inline]
-    fn check_error<T>(&mut self, result: Result<T>) -> Result<T> {
-        if let Err(e) = &result
-            && e.is_conn_broken()
+    /// Returns true if a command failed because the server is shutting down.
+    ///
+    /// The connection is also broken. Pools drop their idle connections to the same server when
+    /// such a connection is returned.
+    pub fn is_server_shutdown(&self) -> bool {
+        self.server_shutdown
+    }
+
+    /// Hits, misses and size of the statement cache used by [`exec_cached`](Self::exec_cached).
+    pub fn statement_cache_stats(&self) -> StatementCacheStats {
+        self.statement_cache.stats()
+    }
+
+    /// Heap allocations made by the last command.
+    ///
+    /// Always zero unless the `alloc-stats` feature is enabled and
+    /// [`CountingAllocator`](crate::alloc_stats) is the global allocator.
+    pub fn last_alloc_stats(&self) -> AllocStats {
+        self.alloc_stats.last
+    }
+
+    /// Heap allocations made by all commands since the connection was opened.
+    pub fn alloc_stats(&self) -> AllocStats {
+        self.alloc_stats.total
+    }
+
+    /// RTT, retransmits and send-queue depth from `TCP_INFO`.
+    ///
+    /// `None` for Unix sockets and on platforms other than Linux.
+    pub fn socket_stats(&self) -> Result<Option<SocketStats>> {
+        Ok(self.stream.socket_stats()?)
+    }
+
+    /// Compressed bytes and codec time per direction, or `None` if the connection is not compressed.
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.stream.compression_stats()
+    }
+
+    /// Replace the handler for `LOAD DATA LOCAL INFILE` requests.
+    ///
+    /// The server only sends requests if `CLIENT_LOCAL_FILES` was negotiated,
+    /// i.e. `Opts::local_infile` was set or `Opts::capabilities` contains it.
+    pub fn set_local_infile(&mut self, local_infile: Option<LocalInfile>) {
+        self.local_infile = local_infile;
+    }
+
+    /// Call `hook` for every session state change the server reports
+    /// (`SERVER_SESSION_STATE_CHANGED`), e.g. a `SET SESSION` of a tracked variable or `USE db`.
+    ///
+    /// Which changes are reported depends on the server's `session_track_*` variables.
+    /// Replaces the previous hook.
+    pub fn on_session_state_change(
+        &mut self,
+        hook: impl FnMut(&SessionStateChange) + Send + Sync + 'static,
+    ) {
+        self.session_state_hook = Some(Box::new(hook));
+    }
+
+    fn notify_session_state(&mut self, changes: &[SessionStateChange]) {
+        self.tracked_variables.apply(changes);
+        if let Some(hook) = &mut self.session_state_hook {
+            changes.iter().for_each(hook);
+        }
+    }
+
+    /// Capture the default database, the tracked session system variables and the user variables,
+    /// to replay them on another connection with [`restore_session`](Self::restore_session),
+    /// e.g. after a failover. See [`crate::session`].
+    ///
+    /// # Errors
+    /// Returns the server error if the user variables cannot be read,
+    /// e.g. when `performance_schema` is disabled.
+    pub async fn snapshot_session(&mut self) -> Result<SessionSnapshot> {
+        let mut database = TextRowsHandler::default();
+        self.query(DATABASE_SQL, &mut database).await?;
+        let user_variables_sql = if self.is_mariadb() {
+            MARIADB_USER_VARIABLES_SQL
+        } else {
+            MYSQL_USER_VARIABLES_SQL
+        };
+        let mut user_variables = TextRowsHandler::default();
+        self.query(user_variables_sql, &mut user_variables).await?;
+        Ok(SessionSnapshot {
+            database: first_value(&database.rows),
+            system_variables: self.tracked_variables.to_vec(),
+            user_variables: parse_user_variables(user_variables.rows),
+        })
+    }
+
+    /// Replay a [`SessionSnapshot`] taken on another connection.
+    pub async fn restore_session(&mut self, snapshot: &SessionSnapshot) -> Result<()> {
+        for sql in snapshot.restore_statements() {
+            self.query_drop(&sql).await?;
+        }
+        Ok(())
+    }
+
+    fn log_start(&self) -> Option<Instant> {
+        self.statement_log.as_ref().map(|_| Instant::now())
+    }
+
+    fn log_statement<T>(
+        &self,
+        kind: StatementKind,
+        sql: Option<&str>,
+        statement_id: Option<u32>,
+        started: Option<Instant>,
+        result: &Result<T>,
+    ) {
+        if let (Some(log), Some(started)) = (&self.statement_log, started) {
+            log.log(kind, sql, statement_id, started, result.as_ref().err());
+        }
+        self.audit(
+            kind,
+            sql.map_or(StatementClass::Unknown, classify),
+            sql,
+            statement_id,
+            result,
+        );
+    }
+
+    fn log_exec<T>(
+        &self,
+        kind: StatementKind,
+        stmt: &mut PreparedStatement,
+        started: Option<Instant>,
+        result: &Result<T>,
+    ) {
+        if let (Some(log), Some(started)) = (&self.statement_log, started) {
+            log.log(
+                kind,
+                stmt.sql(),
+                Some(stmt.id()),
+                started,
+                result.as_ref().err(),
+            );
+        }
+        self.audit(kind, stmt.class(), stmt.sql(), Some(stmt.id()), result);
+        if let Some(hook) = &self.schema_drift
+            && result.is_ok()
+            && let Some(before) = stmt.track_columns()
         {
-            self.is_broken = true;
+            hook.report(&SchemaDrift {
+                connection_id: self.connection_id(),
+                statement_id: stmt.id(),
+                sql: stmt.sql().map(str::to_string),
+                before,
+                after: stmt.columns_seen().to_vec(),
+            });
+        }
+    }
+
+    fn audit<T>(
+        &self,
+        kind: StatementKind,
+        class: StatementClass,
+        sql: Option<&str>,
+        statement_id: Option<u32>,
+        result: &Result<T>,
+    ) {
+        if let Some(audit) = &self.audit {
+            audit.record(
+                &self.user,
+                self.connection_id(),
+                kind,
+                class,
+                sql,
+                statement_id,
+                self.affected_rows,
+                result.as_ref().err(),
+            );
+        }
+    }
+
+    /// Reject writes if `Opts::enforce_read_only` is set
+    fn reject_writes(&self, sql: &str) -> Result<()> {
+        if self.read_only {
+            check_read_only(sql)
+        } else {
+            Ok(())
         }
-        result
     }
 
-    pub(crate) fn set_in_transaction(&mut self, value: bool) {
-        self.in_transaction = value;
+    #[inline]
+    fn check_error<T>(&mut self, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result {
+            if e.is_conn_broken() {
+                self.is_broken = true;
+            }
+            if e.is_server_shutdown() {
+                self.server_shutdown = true;
+            }
+        }
+        result
     }
 
     /// Returns true if the connection is currently in a transaction
     pub fn in_transaction(&self) -> bool {
-        self.in_transaction
+        self.server_status
+            .contains(crate::constant::ServerStatusFlags::SERVER_STATUS_IN_TRANS)
+    }
+
+    /// Returns true if autocommit is enabled, per the server status of the last OK/EOF packet
+    pub fn autocommit(&self) -> bool {
+        self.server_status
+            .contains(crate::constant::ServerStatusFlags::SERVER_STATUS_AUTOCOMMIT)
+    }
+
+    /// Get the server status flags of the last OK/EOF packet
+    ///
+    /// Tracks transaction state, autocommit, pending result sets and open cursors.
+    pub fn server_status(&self) -> crate::constant::ServerStatusFlags {
+        self.server_status
     }
 
     /// Try to upgrade to Unix socket connection.
@@ -222,7 +653,7 @@ impl Conn {
             Ok(s) => s,
             Err(_) => return self,
         };
-        let stream = Stream::unix(unix_stream);
+        let stream = Stream::unix_with_capacity(unix_stream, opts.read_buffer_size);
 
         // Create new connection over Unix socket (re-handshakes)
         // Disable upgrade_to_unix_socket to prevent infinite recursion
@@ -237,7 +668,80 @@ impl Conn {
 
     /// Write a MySQL packet from write_buffer asynchronously, splitting it into 16MB chunks if necessary
     #[instrument(skip_all)]
+
+    fn query_attributes(&self) -> bool {
+        self.capability_flags
+            .contains(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES)
+    }
+
+    /// Write COM_QUERY in the format negotiated in the handshake
+    fn write_query_command<A: TypedParam>(
+        &mut self,
+        sql: &str,
+        attributes: &[(&str, A)],
+    ) -> Result<()> {
+        if self.query_attributes() {
+            write_query_with_attributes(self.buffer_set.new_write_buffer(), sql, attributes)
+        } else if attributes.is_empty() {
+            write_query(self.buffer_set.new_write_buffer(), sql);
+            Ok(())
+        } else {
+            Err(attributes::unsupported())
+        }
+    }
+
+    /// Write COM_STMT_EXECUTE in the format negotiated in the handshake
+    fn write_execute_command<P: Params, A: TypedParam>(
+        &mut self,
+        statement_id: u32,
+        params: P,
+        attributes: &[(&str, A)],
+    ) -> Result<()> {
+        if self.query_attributes() {
+            write_execute_with_attributes(
+                self.buffer_set.new_write_buffer(),
+                statement_id,
+                params,
+                attributes,
+            )
+        } else if attributes.is_empty() {
+            write_execute(self.buffer_set.new_write_buffer(), statement_id, params)
+        } else {
+            Err(attributes::unsupported())
+        }
+    }
+
     async fn write_payload(&mut self) -> Result<()> {
+        let timeout = self.stream.write_timeout();
+        timed(timeout, async {
+            self.write_packets(false).await?;
+            self.stream.flush().await?;
+            Ok::<_, Error>(())
+        })
+        .await
+    }
+
+    /// Write a command of the pipelined batch: at once with `FlushPolicy::PerCommand`,
+    /// held back until [`Self::flush_pipelined`] with `FlushPolicy::Coalesce`.
+    async fn write_pipelined(&mut self) -> Result<()> {
+        match self.flush_policy {
+            FlushPolicy::PerCommand => self.write_payload().await,
+            FlushPolicy::Coalesce => self.write_packets(true).await,
+        }
+    }
+
+    /// Send the commands held back by [`Self::write_pipelined`].
+    async fn flush_pipelined(&mut self) -> Result<()> {
+        if self.flush_policy == FlushPolicy::Coalesce {
+            let timeout = self.stream.write_timeout();
+            timed(timeout, self.stream.flush()).await?;
+        }
+        Ok(())
+    }
+
+    /// Split the write buffer into packets and write them without flushing.
+    /// `deferred` keeps them in the stream until the next flush.
+    async fn write_packets(&mut self, deferred: bool) -> Result<()> {
         let mut sequence_id = 0_u8;
         let mut buffer = self.buffer_set.write_buffer_mut().as_mut_slice();
 
@@ -245,7 +749,11 @@ impl Conn {
             let chunk_size = buffer[4..].len().min(0xFFFFFF);
             PacketHeader::mut_from_bytes(&mut buffer[0..4])?
                 .encode_in_place(chunk_size, sequence_id);
-            self.stream.write_all(&buffer[..4 + chunk_size]).await?;
+            if deferred {
+                self.stream.write_deferred(&buffer[..4 + chunk_size]);
+            } else {
+                self.stream.write_all(&buffer[..4 + chunk_size]).await?;
+            }
 
             if chunk_size < 0xFFFFFF {
                 break;
@@ -254,7 +762,6 @@ impl Conn {
             sequence_id = sequence_id.wrapping_add(1);
             buffer = &mut buffer[0xFFFFFF..];
         }
-        self.stream.flush().await?;
         Ok(())
     }
 
@@ -262,13 +769,131 @@ impl Conn {
     ///
     /// Returns `Ok(PreparedStatement)` on success.
     pub async fn prepare(&mut self, sql: &str) -> Result<PreparedStatement> {
-        let result = self.prepare_inner(sql).await;
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(self.prepare_inner(sql)).await;
+        self.log_statement(
+            StatementKind::Prepare,
+            Some(sql),
+            result.as_ref().ok().map(PreparedStatement::id),
+            started,
+            &result,
+        );
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    /// Deallocate a prepared statement on the server (`COM_STMT_CLOSE`).
+    pub async fn close_statement(&mut self, stmt: PreparedStatement) -> Result<()> {
+        if stmt.is_emulated() {
+            return Ok(());
+        }
+        write_close_statement(self.buffer_set.new_write_buffer(), stmt.id());
+        // the server does not respond to COM_STMT_CLOSE
+        let (result, allocs) = alloc_stats::count_async(self.write_payload()).await;
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    /// Send a parameter value in chunks (`COM_STMT_SEND_LONG_DATA`) without buffering it.
+    ///
+    /// Call this any number of times before `exec` and pass
+    /// [`LongData`](crate::protocol::r#trait::param::LongData) for the parameter.
+    /// The chunks are appended on the server; the total size is limited by `max_allowed_packet`.
+    pub async fn send_long_data(
+        &mut self,
+        stmt: &PreparedStatement,
+        param_index: u16,
+        data: &[u8],
+    ) -> Result<()> {
+        emulate::check_not_emulated(stmt, "send_long_data()")?;
+        let (result, allocs) =
+            alloc_stats::count_async(self.send_long_data_inner(stmt.id(), param_index, data)).await;
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
+    async fn send_long_data_inner(
+        &mut self,
+        statement_id: u32,
+        param_index: u16,
+        data: &[u8],
+    ) -> Result<()> {
+        let mut chunks = data.chunks(LONG_DATA_CHUNK_SIZE);
+        // An empty value is still sent once
+        let first = chunks.next().unwrap_or_default();
+        for chunk in std::iter::once(first).chain(chunks) {
+            write_send_long_data(
+                self.buffer_set.new_write_buffer(),
+                statement_id,
+                param_index,
+                chunk,
+            );
+            // the server does not respond to COM_STMT_SEND_LONG_DATA
+            self.write_payload().await?;
+        }
+        Ok(())
+    }
+
+    /// Stream a parameter value from `reader` with `COM_STMT_SEND_LONG_DATA`.
+    ///
+    /// Returns the number of bytes sent. See [`Conn::send_long_data`].
+    pub async fn send_long_data_from<R: tokio::io::AsyncRead + Unpin>(
+        &mut self,
+        stmt: &PreparedStatement,
+        param_index: u16,
+        reader: &mut R,
+    ) -> Result<u64> {
+        emulate::check_not_emulated(stmt, "send_long_data_from()")?;
+        let (result, allocs) = alloc_stats::count_async(self.send_long_data_from_inner(
+            stmt.id(),
+            param_index,
+            reader,
+        ))
+        .await;
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn send_long_data_from_inner<R: tokio::io::AsyncRead + Unpin>(
+        &mut self,
+        statement_id: u32,
+        param_index: u16,
+        reader: &mut R,
+    ) -> Result<u64> {
+        use tokio::io::AsyncReadExt;
+
+        let mut total = 0_u64;
+        loop {
+            let out = self.buffer_set.new_write_buffer();
+            write_send_long_data(out, statement_id, param_index, &[]);
+            let start = out.len();
+            out.resize(start + LONG_DATA_CHUNK_SIZE, 0);
+            let read = reader.read(&mut out[start..]).await?;
+            out.truncate(start + read);
+            if read == 0 && total > 0 {
+                return Ok(total);
+            }
+            // the server does not respond to COM_STMT_SEND_LONG_DATA
+            self.write_payload().await?;
+            total += read as u64;
+            if read == 0 {
+                return Ok(total);
+            }
+        }
+    }
+
     async fn prepare_inner(&mut self, sql: &str) -> Result<PreparedStatement> {
         use crate::protocol::command::ColumnDefinitions;
 
+        self.reject_writes(sql)?;
+        if self.emulate_prepared_statements {
+            let mut stmt = PreparedStatement::emulated(sql);
+            if self.audit.is_some() {
+                stmt.set_class(classify(sql));
+            }
+            stmt.set_max_rows(max_rows(sql));
+            return Ok(stmt);
+        }
         self.buffer_set.read_buffer.clear();
 
         write_prepare(self.buffer_set.new_write_buffer(), sql);
@@ -290,15 +915,25 @@ impl Conn {
         for _ in 0..num_params {
             let _ = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
         }
+        if num_params > 0 && self.buffer_set.legacy_eof {
+            let _ = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
+            check_columns_eof(&self.buffer_set.read_buffer)?;
+        }
 
         // Read and cache column definitions for MARIADB_CLIENT_CACHE_METADATA support
         let column_definitions = if num_columns > 0 {
             self.read_column_definition_packets(num_columns as usize)
                 .await?;
-            Some(ColumnDefinitions::new(
+            if self.buffer_set.legacy_eof {
+                let _ = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
+                check_columns_eof(&self.buffer_set.read_buffer)?;
+            }
+            let mut col_defs = ColumnDefinitions::new(
                 num_columns as usize,
                 std::mem::take(&mut self.buffer_set.column_definition_buffer),
-            )?)
+            )?;
+            self.buffer_set.intern_columns(&mut col_defs);
+            Some(col_defs)
         } else {
             None
         };
@@ -307,6 +942,13 @@ impl Conn {
         if let Some(col_defs) = column_definitions {
             stmt.set_column_definitions(col_defs);
         }
+        if self.retain_statement_sql {
+            stmt.set_sql(sql);
+        }
+        if self.audit.is_some() {
+            stmt.set_class(classify(sql));
+        }
+        stmt.set_max_rows(max_rows(sql));
         Ok(stmt)
     }
 
@@ -316,20 +958,25 @@ impl Conn {
         let out = &mut self.buffer_set.column_definition_buffer;
         out.clear();
 
-        // For each column, write [4 bytes len][payload]
-        for _ in 0..num_columns {
-            self.stream.read_exact(header.as_mut_bytes()).await?;
-            let length = header.length();
-            out.extend((length as u32).to_ne_bytes());
-
-            out.reserve(length);
-            let spare = out.spare_capacity_mut();
-            self.stream.read_buf_exact(&mut spare[..length]).await?;
-            // SAFETY: read_buf_exact filled exactly `length` bytes
-            unsafe {
-                out.set_len(out.len() + length);
+        let timeout = self.stream.read_timeout();
+        timed(timeout, async {
+            // For each column, write [4 bytes len][payload]
+            for _ in 0..num_columns {
+                self.stream.read_exact(header.as_mut_bytes()).await?;
+                let length = header.length();
+                out.extend((length as u32).to_ne_bytes());
+
+                out.reserve(length);
+                let spare = out.spare_capacity_mut();
+                self.stream.read_buf_exact(&mut spare[..length]).await?;
+                // SAFETY: read_buf_exact filled exactly `length` bytes
+                unsafe {
+                    out.set_len(out.len() + length);
+                }
             }
-        }
+            Ok::<_, Error>(())
+        })
+        .await?;
 
         Ok(header.sequence_id)
     }
@@ -342,70 +989,135 @@ impl Conn {
         let cache_metadata = self
             .mariadb_capabilities
             .contains(crate::constant::MariadbCapabilityFlags::MARIADB_CLIENT_CACHE_METADATA);
-        let mut exec = Exec::new(handler, stmt, cache_metadata);
+        let mut guard = HandlerGuard::new(handler);
+        let mut exec = Exec::new(&mut guard, stmt, cache_metadata);
 
         loop {
-            match exec.step(&mut self.buffer_set)? {
+            let action = match exec.step(&mut self.buffer_set) {
+                Ok(action) => action,
+                Err(err) => {
+                    if guard.abandoned {
+                        self.is_broken = true;
+                    }
+                    return Err(err);
+                }
+            };
+            match action {
                 Action::NeedPacket(buffer) => {
                     buffer.clear();
                     let _ = read_payload(&mut self.stream, buffer).await?;
+                    if self.yield_budget.consume(buffer.len()) {
+                        tokio::task::yield_now().await;
+                    }
                 }
                 Action::ReadColumnMetadata { num_columns } => {
                     self.read_column_definition_packets(num_columns).await?;
                 }
-                Action::Finished => return Ok(()),
+                Action::LocalInfile { .. } => {
+                    return Err(Error::LibraryBug(crate::error::eyre!(
+                        "unexpected LOCAL INFILE request"
+                    )));
+                }
+                Action::Finished => break,
+            }
+        }
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows = guard.affected_rows;
+        guard.finish()
+    }
+
+    /// Step `exec` once and do the IO it asks for. Returns true when the response is finished.
+    pub(super) async fn step_exec<H: BinaryResultSetHandler>(
+        &mut self,
+        exec: &mut Exec<'_, '_, H>,
+    ) -> Result<bool> {
+        match exec.step(&mut self.buffer_set)? {
+            Action::NeedPacket(buffer) => {
+                buffer.clear();
+                let _ = read_payload(&mut self.stream, buffer).await?;
+                if self.yield_budget.consume(buffer.len()) {
+                    tokio::task::yield_now().await;
+                }
+                Ok(false)
+            }
+            Action::ReadColumnMetadata { num_columns } => {
+                self.read_column_definition_packets(num_columns).await?;
+                Ok(false)
             }
+            Action::LocalInfile { .. } => Err(Error::LibraryBug(crate::error::eyre!(
+                "unexpected LOCAL INFILE request"
+            ))),
+            Action::Finished => Ok(true),
+        }
+    }
+
+    /// Send the file requested by `LOAD DATA LOCAL INFILE`.
+    ///
+    /// Returns the error of opening or reading the file.
+    async fn send_local_infile(
+        &mut self,
+        mut packets: LocalInfilePackets,
+    ) -> Result<Option<Error>> {
+        let timeout = self.stream.write_timeout();
+        while let Some(packet) = packets.next_packet(self.buffer_set.write_buffer_mut())? {
+            timed(timeout, self.stream.write_all(packet)).await?;
         }
+        timed(timeout, self.stream.flush()).await?;
+        Ok(packets.into_error())
     }
 
     async fn drive_query<H: TextResultSetHandler>(&mut self, handler: &mut H) -> Result<()> {
-        let mut query = Query::new(handler);
+        let mut guard = HandlerGuard::new(handler);
+        let mut query = Query::new(&mut guard);
+        let mut sequence_id = 0_u8;
+        let mut local_infile_error = None;
 
         loop {
-            match query.step(&mut self.buffer_set)? {
+            let action = match query.step(&mut self.buffer_set) {
+                Ok(action) => action,
+                Err(err) => {
+                    if guard.abandoned {
+                        self.is_broken = true;
+                    }
+                    return Err(local_infile_error.unwrap_or(err));
+                }
+            };
+            match action {
                 Action::NeedPacket(buffer) => {
                     buffer.clear();
-                    let _ = read_payload(&mut self.stream, buffer).await?;
+                    sequence_id = read_payload(&mut self.stream, buffer).await?;
+                    if self.yield_budget.consume(buffer.len()) {
+                        tokio::task::yield_now().await;
+                    }
                 }
                 Action::ReadColumnMetadata { num_columns } => {
                     self.read_column_definition_packets(num_columns).await?;
                 }
-                Action::Finished => return Ok(()),
+                Action::LocalInfile { filename } => {
+                    let packets =
+                        LocalInfilePackets::new(self.local_infile.as_ref(), filename, sequence_id);
+                    if let Some(err) = self.send_local_infile(packets).await? {
+                        local_infile_error.get_or_insert(err);
+                    }
+                }
+                Action::Finished => break,
             }
         }
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows = guard.affected_rows;
+        match local_infile_error {
+            Some(err) => Err(err),
+            None => guard.finish(),
+        }
     }
 
-    /// Execute a prepared statement with a result set handler (async)
-    pub async fn exec<P, H>(
-        &mut self,
-        stmt: &mut PreparedStatement,
-        params: P,
-        handler: &mut H,
-    ) -> Result<()>
-    where
-        P: Params,
-        H: BinaryResultSetHandler,
-    {
-        let result = self.exec_inner(stmt, params, handler).await;
-        self.check_error(result)
-    }
-
-    async fn exec_inner<P, H>(
-        &mut self,
-        stmt: &mut PreparedStatement,
-        params: P,
-        handler: &mut H,
-    ) -> Result<()>
-    where
-        P: Params,
-        H: BinaryResultSetHandler,
-    {
-        write_execute(self.buffer_set.new_write_buffer(), stmt.id(), params)?;
-        self.write_payload().await?;
-        self.drive_exec(stmt, handler).await
-    }
-
-    async fn drive_bulk_exec<H: BinaryResultSetHandler>(
+    async fn drive_exec_deferred<H: AsyncBinaryResultSetHandler>(
         &mut self,
         stmt: &mut crate::PreparedStatement,
         handler: &mut H,
@@ -413,55 +1125,486 @@ impl Conn {
         let cache_metadata = self
             .mariadb_capabilities
             .contains(crate::constant::MariadbCapabilityFlags::MARIADB_CLIENT_CACHE_METADATA);
-        let mut bulk_exec = BulkExec::new(handler, stmt, cache_metadata);
+        let mut deferred = DeferredHandler::default();
+        let mut guard = HandlerGuard::new(&mut deferred);
+        let mut exec = Exec::new(&mut guard, stmt, cache_metadata);
 
         loop {
-            match bulk_exec.step(&mut self.buffer_set)? {
+            let action = match exec.step(&mut self.buffer_set) {
+                Ok(action) => action,
+                Err(err) => {
+                    if guard.abandoned {
+                        self.is_broken = true;
+                    }
+                    return Err(err);
+                }
+            };
+            // Await the handler before reading the next packet
+            let (pending, cols) = exec.handler_and_columns();
+            pending.inner_mut().dispatch_binary(handler, cols).await;
+            match action {
                 Action::NeedPacket(buffer) => {
                     buffer.clear();
                     let _ = read_payload(&mut self.stream, buffer).await?;
+                    if self.yield_budget.consume(buffer.len()) {
+                        tokio::task::yield_now().await;
+                    }
                 }
                 Action::ReadColumnMetadata { num_columns } => {
                     self.read_column_definition_packets(num_columns).await?;
                 }
-                Action::Finished => return Ok(()),
+                Action::LocalInfile { .. } => {
+                    return Err(Error::LibraryBug(crate::error::eyre!(
+                        "unexpected LOCAL INFILE request"
+                    )));
+                }
+                Action::Finished => break,
             }
         }
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows = guard.affected_rows;
+        guard.finish()?;
+        match deferred.failed {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
     }
 
-    /// Execute a bulk prepared statement with a result set handler (async)
-    pub async fn exec_bulk_insert_or_update<P, I, H>(
+    async fn drive_query_deferred<H: AsyncTextResultSetHandler>(
         &mut self,
-        stmt: &mut PreparedStatement,
-        params: P,
-        flags: BulkFlags,
         handler: &mut H,
-    ) -> Result<()>
-    where
-        P: BulkParamsSet + IntoIterator<Item = I>,
-        I: Params,
-        H: BinaryResultSetHandler,
-    {
-        let result = self
-            .exec_bulk_insert_or_update_inner(stmt, params, flags, handler)
-            .await;
-        self.check_error(result)
-    }
+    ) -> Result<()> {
+        let mut deferred = DeferredHandler::default();
+        let mut guard = HandlerGuard::new(&mut deferred);
+        let mut query = Query::new(&mut guard);
+        let mut sequence_id = 0_u8;
+        let mut local_infile_error = None;
 
-    async fn exec_bulk_insert_or_update_inner<P, I, H>(
-        &mut self,
-        stmt: &mut PreparedStatement,
-        params: P,
-        flags: BulkFlags,
-        handler: &mut H,
-    ) -> Result<()>
+        loop {
+            let action = match query.step(&mut self.buffer_set) {
+                Ok(action) => action,
+                Err(err) => {
+                    if guard.abandoned {
+                        self.is_broken = true;
+                    }
+                    return Err(local_infile_error.unwrap_or(err));
+                }
+            };
+            // Await the handler before reading the next packet
+            let (pending, cols) = query.handler_and_columns();
+            pending.inner_mut().dispatch_text(handler, cols).await;
+            match action {
+                Action::NeedPacket(buffer) => {
+                    buffer.clear();
+                    sequence_id = read_payload(&mut self.stream, buffer).await?;
+                    if self.yield_budget.consume(buffer.len()) {
+                        tokio::task::yield_now().await;
+                    }
+                }
+                Action::ReadColumnMetadata { num_columns } => {
+                    self.read_column_definition_packets(num_columns).await?;
+                }
+                Action::LocalInfile { filename } => {
+                    let packets =
+                        LocalInfilePackets::new(self.local_infile.as_ref(), filename, sequence_id);
+                    if let Some(err) = self.send_local_infile(packets).await? {
+                        local_infile_error.get_or_insert(err);
+                    }
+                }
+                Action::Finished => break,
+            }
+        }
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows = guard.affected_rows;
+        if let Some(err) = local_infile_error {
+            return Err(err);
+        }
+        guard.finish()?;
+        match deferred.failed {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Execute a prepared statement with a result set handler (async)
+    pub async fn exec<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.exec_inner(stmt, params, handler)).await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    /// Executes `sql` with a statement from the connection's statement cache,
+    /// preparing it on a miss.
+    ///
+    /// The cache is bounded by `Opts::statement_cache_bytes` and `Opts::statement_cache_size`.
+    /// Statements evicted to stay within the budget are closed on the server.
+    pub async fn exec_cached<P, H>(&mut self, sql: &str, params: P, handler: &mut H) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        let mut stmt = match self.statement_cache.take(sql) {
+            Some(stmt) => stmt,
+            None => {
+                if let Some(recorder) = &self.statement_recorder {
+                    recorder.record(sql);
+                }
+                self.prepare(sql).await?
+            }
+        };
+        let result = self.exec(&mut stmt, params, handler).await;
+        if self.is_broken {
+            return result;
+        }
+        for evicted in self.statement_cache.insert(sql, stmt) {
+            self.close_statement(evicted).await?;
+        }
+        result
+    }
+
+    /// Prepare `statements` into the statement cache of `exec_cached()`, e.g. from a warm-up file.
+    /// Returns the number of statements prepared.
+    ///
+    /// Statements that are already cached are skipped, as are statements the server rejects,
+    /// e.g. after a table was dropped. Does nothing if the cache is disabled.
+    pub async fn warm_up_statement_cache<'a>(
+        &mut self,
+        statements: impl IntoIterator<Item = &'a str>,
+    ) -> Result<usize> {
+        if !self.statement_cache.is_enabled() {
+            return Ok(0);
+        }
+        let mut prepared = 0;
+        for sql in statements {
+            if self.statement_cache.contains(sql) {
+                continue;
+            }
+            let stmt = match self.prepare(sql).await {
+                Ok(stmt) => stmt,
+                Err(err @ Error::ServerError(_)) => {
+                    tracing::warn!(error = %err, sql, "skipped a warm-up statement");
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            prepared += 1;
+            for evicted in self.statement_cache.insert(sql, stmt) {
+                self.close_statement(evicted).await?;
+            }
+        }
+        Ok(prepared)
+    }
+
+    /// Execute a prepared statement with an async result set handler.
+    ///
+    /// Each callback is awaited before the next packet is read from the socket,
+    /// so a slow handler pauses the read instead of buffering the result set.
+    pub async fn exec_with_backpressure<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: AsyncBinaryResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.exec_with_backpressure_inner(stmt, params, handler))
+                .await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn exec_with_backpressure_inner<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: AsyncBinaryResultSetHandler,
+    {
+        emulate::check_not_emulated(stmt, "exec_with_backpressure()")?;
+        self.write_execute_command(stmt.id(), params, NO_ATTRIBUTES)?;
+        self.write_payload().await?;
+        self.drive_exec_deferred(stmt, handler).await
+    }
+
+    /// Executes a prepared statement with a server-side execution time limit.
+    ///
+    /// The limit is applied with `SET SESSION max_execution_time` (MySQL) or
+    /// `SET SESSION max_statement_time` (MariaDB) and restored to `DEFAULT` afterwards.
+    /// On MySQL, the limit only applies to read-only `SELECT` statements.
+    pub async fn exec_with_timeout<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        timeout: Duration,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.exec_with_timeout_inner(stmt, params, timeout, handler))
+                .await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn exec_with_timeout_inner<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        timeout: Duration,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        let is_mariadb = self.is_mariadb();
+        self.query_drop_inner(&hint::set_session_timeout(timeout, is_mariadb))
+            .await?;
+        let result = self.exec_inner(stmt, params, handler).await;
+        let reset = self
+            .query_drop_inner(hint::reset_session_timeout(is_mariadb))
+            .await;
+        result.and(reset)
+    }
+
+    /// Execute a prepared statement that `cancel` can abort from another task.
+    ///
+    /// See [`Conn::query_cancellable`].
+    pub async fn exec_cancellable<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        handler: &mut H,
+        cancel: &CancellationToken,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        let started = self.log_start();
+        let kill_query_opts = self.kill_query_opts.clone();
+        let kill = self.kill_query(kill_query_opts.as_deref());
+        let (outcome, allocs) = alloc_stats::count_async(run_cancellable(
+            self.exec_inner(stmt, params, handler),
+            cancel,
+            kill.map(KillQuery::run),
+        ))
+        .await;
+        let result = self.finish_cancellable(outcome).await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn exec_inner<P, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        H: BinaryResultSetHandler,
+    {
+        self.exec_with_attrs_inner(stmt, params, NO_ATTRIBUTES, handler)
+            .await
+    }
+
+    /// Execute a prepared statement with query attributes (MySQL 8.0.23+).
+    ///
+    /// See [`Conn::query_with_attrs`].
+    pub async fn exec_with_attrs<P, A, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        A: TypedParam,
+        H: BinaryResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.exec_with_attrs_inner(stmt, params, attrs, handler))
+                .await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn exec_with_attrs_inner<P, A, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        A: TypedParam,
+        H: BinaryResultSetHandler,
+    {
+        if stmt.is_emulated() {
+            return self.exec_emulated(stmt, params, attrs, handler).await;
+        }
+        self.write_execute_command(stmt.id(), params, attrs)?;
+        self.write_payload().await?;
+        self.drive_exec(stmt, handler).await
+    }
+
+    /// Execute an emulated statement as a text protocol query. See [`crate::emulate`].
+    async fn exec_emulated<P, A, H>(
+        &mut self,
+        stmt: &PreparedStatement,
+        params: P,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: Params,
+        A: TypedParam,
+        H: BinaryResultSetHandler,
+    {
+        let no_backslash_escapes = self
+            .server_status
+            .contains(crate::constant::ServerStatusFlags::SERVER_STATUS_NO_BACKSLASH_ESCAPES);
+        let sql = emulate::interpolate(
+            stmt.sql().unwrap_or_default(),
+            &params,
+            no_backslash_escapes,
+        )?;
+        self.write_query_command(&sql, attrs)?;
+        self.write_payload().await?;
+        self.drive_query(&mut TextToBinary::new(handler)).await
+    }
+
+    async fn drive_bulk_exec<H: BinaryResultSetHandler>(
+        &mut self,
+        stmt: &mut crate::PreparedStatement,
+        handler: &mut H,
+    ) -> Result<()> {
+        let cache_metadata = self
+            .mariadb_capabilities
+            .contains(crate::constant::MariadbCapabilityFlags::MARIADB_CLIENT_CACHE_METADATA);
+        let mut guard = HandlerGuard::new(handler);
+        let mut bulk_exec = BulkExec::new(&mut guard, stmt, cache_metadata);
+
+        loop {
+            let action = match bulk_exec.step(&mut self.buffer_set) {
+                Ok(action) => action,
+                Err(err) => {
+                    if guard.abandoned {
+                        self.is_broken = true;
+                    }
+                    return Err(err);
+                }
+            };
+            match action {
+                Action::NeedPacket(buffer) => {
+                    buffer.clear();
+                    let _ = read_payload(&mut self.stream, buffer).await?;
+                    if self.yield_budget.consume(buffer.len()) {
+                        tokio::task::yield_now().await;
+                    }
+                }
+                Action::ReadColumnMetadata { num_columns } => {
+                    self.read_column_definition_packets(num_columns).await?;
+                }
+                Action::LocalInfile { .. } => {
+                    return Err(Error::LibraryBug(crate::error::eyre!(
+                        "unexpected LOCAL INFILE request"
+                    )));
+                }
+                Action::Finished => break,
+            }
+        }
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows = guard.affected_rows;
+        guard.finish()
+    }
+
+    /// Execute a bulk prepared statement with a result set handler (async)
+    ///
+    /// MariaDB servers with `MARIADB_CLIENT_STMT_BULK_OPERATIONS` receive all parameter sets in one
+    /// `COM_STMT_BULK_EXECUTE`. MySQL has no bulk command: it executes `COM_STMT_EXECUTE` once
+    /// whatever its iteration count, so the statement is executed once per parameter set.
+    pub async fn exec_bulk_insert_or_update<P, I, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        flags: BulkFlags,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        P: BulkParamsSet + IntoIterator<Item = I>,
+        I: Params,
+        H: BinaryResultSetHandler,
+    {
+        let kind = if self.bulk_operations() {
+            StatementKind::BulkExec
+        } else {
+            StatementKind::Exec
+        };
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(
+            self.exec_bulk_insert_or_update_inner(stmt, params, flags, handler),
+        )
+        .await;
+        self.log_exec(kind, stmt, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn exec_bulk_insert_or_update_inner<P, I, H>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+        flags: BulkFlags,
+        handler: &mut H,
+    ) -> Result<()>
     where
         P: BulkParamsSet + IntoIterator<Item = I>,
         I: Params,
         H: BinaryResultSetHandler,
     {
-        if !self.is_mariadb() {
-            // Fallback to multiple exec_drop for non-MariaDB servers
+        if !self.bulk_operations() || stmt.is_emulated() {
+            // Fallback to multiple exec_drop for MySQL or without bulk operations
             for param in params {
                 self.exec_inner(stmt, param, &mut DropHandler::default())
                     .await?;
@@ -485,7 +1628,10 @@ impl Conn {
         Row: for<'buf> crate::raw::FromRow<'buf>,
         P: Params,
     {
-        let result = self.exec_first_inner(stmt, params).await;
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(self.exec_first_inner(stmt, params)).await;
+        self.log_exec(StatementKind::Exec, stmt, started, &result);
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
@@ -498,10 +1644,9 @@ impl Conn {
         Row: for<'buf> crate::raw::FromRow<'buf>,
         P: Params,
     {
-        write_execute(self.buffer_set.new_write_buffer(), stmt.id(), params)?;
-        self.write_payload().await?;
         let mut handler = FirstHandler::<Row>::default();
-        self.drive_exec(stmt, &mut handler).await?;
+        self.exec_with_attrs_inner(stmt, params, NO_ATTRIBUTES, &mut handler)
+            .await?;
         Ok(handler.take())
     }
 
@@ -524,11 +1669,169 @@ impl Conn {
         Row: for<'buf> crate::raw::FromRow<'buf>,
         P: Params,
     {
-        let mut handler = crate::handler::CollectHandler::<Row>::default();
+        let mut handler = crate::handler::CollectHandler::<Row>::with_max_rows(stmt.max_rows());
         self.exec(stmt, params, &mut handler).await?;
         Ok(handler.into_rows())
     }
 
+    /// Like [`exec_collect`](Self::exec_collect), but decode the rows on the blocking pool
+    /// in batches of [`Opts::offload_decode_bytes`](crate::Opts::offload_decode_bytes)
+    /// while the connection keeps reading.
+    ///
+    /// Use it for huge rows or result sets whose decoding would otherwise block the worker thread.
+    pub async fn exec_collect_offloaded<Row, P>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+    ) -> Result<Vec<Row>>
+    where
+        Row: for<'buf> crate::raw::FromRow<'buf> + Send + 'static,
+        P: Params,
+    {
+        let mut handler = OffloadCollectHandler::<Row>::new(self.offload_decode_bytes);
+        self.exec(stmt, params, &mut handler).await?;
+        handler.into_rows().await
+    }
+
+    /// Execute a prepared statement and return a [`RowStream`] that reads and decodes the rows
+    /// as it is polled.
+    ///
+    /// ```no_run
+    /// # async fn run(conn: &mut zero_mysql::tokio::Conn) -> zero_mysql::error::Result<()> {
+    /// let mut stmt = conn.prepare("SELECT id, name FROM users").await?;
+    /// let mut rows = conn.exec_stream::<(i64, String), _>(&mut stmt, ()).await?;
+    /// while let Some(row) = rows.next().await {
+    ///     let (id, name) = row?;
+    ///     println!("{id} {name}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The connection is marked broken until the stream is read to the end or
+    /// [`RowStream::finish`] is called.
+    pub async fn exec_stream<'c, Row, P>(
+        &'c mut self,
+        stmt: &'c mut PreparedStatement,
+        params: P,
+    ) -> Result<RowStream<'c, Row>>
+    where
+        Row: for<'buf> crate::raw::FromRow<'buf> + Send + 'c,
+        P: Params,
+    {
+        emulate::check_not_emulated(stmt, "exec_stream()")?;
+        let started = self.log_start();
+        let result = self.write_execute(stmt.id(), params).await;
+        if result.is_err() {
+            self.log_exec(StatementKind::Exec, stmt, started, &result);
+        }
+        self.check_error(result)?;
+        self.affected_rows = 0;
+        // Until the whole response is read
+        self.is_broken = true;
+        let cache_metadata = self
+            .mariadb_capabilities
+            .contains(crate::constant::MariadbCapabilityFlags::MARIADB_CLIENT_CACHE_METADATA);
+        let mut slot = RowSlot::<Row> { row: None };
+        let cursor = Exec::new(&mut slot, stmt, cache_metadata).pause();
+        Ok(RowStream::new(self, stmt, cursor, started))
+    }
+
+    async fn write_execute<P: Params>(&mut self, statement_id: u32, params: P) -> Result<()> {
+        self.write_execute_command(statement_id, params, NO_ATTRIBUTES)?;
+        self.write_payload().await
+    }
+
+    /// Read up to the next row of an `exec_stream()`.
+    /// Returns `None` for the cursor once the response is finished.
+    pub(super) async fn next_streamed_row<Row: for<'buf> crate::raw::FromRow<'buf>>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        cursor: ExecCursor,
+        started: Option<Instant>,
+    ) -> (Option<ExecCursor>, Option<Result<Row>>) {
+        let mut slot = RowSlot::<Row> { row: None };
+        let mut guard = HandlerGuard::new(&mut slot);
+        let mut exec = cursor.resume(&mut guard, stmt);
+        let step = loop {
+            match self.step_exec(&mut exec).await {
+                Ok(false) if exec.handler_and_columns().0.inner_mut().row.is_none() => {}
+                step => break step,
+            }
+        };
+        let paused = exec.pause();
+        if let Some(status) = guard.status {
+            self.server_status = status;
+        }
+        self.notify_session_state(&guard.session_state);
+        self.affected_rows += guard.affected_rows;
+        let row = slot.row.take();
+        match step {
+            Ok(false) => (Some(paused), row),
+            Ok(true) => {
+                self.is_broken = false;
+                self.log_exec(StatementKind::Exec, stmt, started, &Ok(()));
+                (None, row)
+            }
+            Err(err) => {
+                self.is_broken = err.is_conn_broken();
+                self.server_shutdown |= err.is_server_shutdown();
+                let failed: Result<Row> = Err(err);
+                self.log_exec(StatementKind::Exec, stmt, started, &failed);
+                (None, Some(failed))
+            }
+        }
+    }
+
+    /// Execute a prepared statement and collect every result set, e.g. of a `CALL`.
+    ///
+    /// A `CALL` returns one [`ResultSet`](crate::multi_result::ResultSet) per `SELECT` in the procedure,
+    /// followed by one without columns for the status of the `CALL`.
+    pub async fn exec_multi<P: Params>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+    ) -> Result<Vec<crate::multi_result::ResultSet>> {
+        let mut handler = crate::multi_result::MultiResultHandler::default();
+        self.exec(stmt, params, &mut handler).await?;
+        Ok(handler.into_result_sets())
+    }
+
+    /// Execute a prepared statement and decode the rows into `Row`, or return the raw result set
+    /// with the decode error if any row does not decode, so that a consumer can keep working
+    /// across schema changes.
+    ///
+    /// The rows are buffered before decoding. For a `CALL`, the first result set is decoded.
+    pub async fn exec_decode_or_raw<Row, P>(
+        &mut self,
+        stmt: &mut PreparedStatement,
+        params: P,
+    ) -> Result<crate::multi_result::DecodeOrRaw<Row>>
+    where
+        Row: for<'buf> crate::raw::FromRow<'buf>,
+        P: Params,
+    {
+        let result_sets = self.exec_multi(stmt, params).await?;
+        Ok(crate::multi_result::DecodeOrRaw::from_result_sets(
+            result_sets,
+        ))
+    }
+
+    /// Execute a `CALL` with a statement from the statement cache and decode each result set
+    /// into its own `Vec`, e.g. `conn.call::<(Vec<A>, Vec<B>), _>("CALL proc(?)", (1,))`.
+    ///
+    /// Fails if the procedure returns a different number of result sets than `T` has `Vec`s,
+    /// or if the response does not end with the OK packet of the `CALL`.
+    pub async fn call<T, P>(&mut self, sql: &str, params: P) -> Result<T>
+    where
+        T: crate::multi_result::CallResults,
+        P: Params,
+    {
+        let mut handler = crate::multi_result::CallHandler::<T>::default();
+        self.exec_cached(sql, params, &mut handler).await?;
+        handler.finish()
+    }
+
     /// Execute a prepared statement and call a closure for each row (async).
     ///
     /// The closure can return an error to stop iteration early.
@@ -552,7 +1855,10 @@ impl Conn {
     where
         H: TextResultSetHandler,
     {
-        let result = self.query_inner(sql, handler).await;
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(self.query_inner(sql, handler)).await;
+        self.log_statement(StatementKind::Query, Some(sql), None, started, &result);
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
@@ -560,29 +1866,267 @@ impl Conn {
     where
         H: TextResultSetHandler,
     {
-        write_query(self.buffer_set.new_write_buffer(), sql);
+        self.query_with_attrs_inner(sql, NO_ATTRIBUTES, handler)
+            .await
+    }
+
+    /// Execute a text protocol SQL query with query attributes (MySQL 8.0.23+).
+    ///
+    /// The server reads them with `mysql_query_attribute_string(name)`, e.g. for tracing or auditing.
+    /// Returns `Error::Unsupported` if the server did not negotiate `CLIENT_QUERY_ATTRIBUTES`.
+    pub async fn query_with_attrs<A, H>(
+        &mut self,
+        sql: &str,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        A: TypedParam,
+        H: TextResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.query_with_attrs_inner(sql, attrs, handler)).await;
+        self.log_statement(StatementKind::Query, Some(sql), None, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn query_with_attrs_inner<A, H>(
+        &mut self,
+        sql: &str,
+        attrs: &[(&str, A)],
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        A: TypedParam,
+        H: TextResultSetHandler,
+    {
+        self.reject_writes(sql)?;
+        crate::paranoid::check_query(sql);
+        self.write_query_command(sql, attrs)?;
         self.write_payload().await?;
         self.drive_query(handler).await
     }
 
+    /// Execute a text protocol SQL query with an async result set handler.
+    ///
+    /// See [`Conn::exec_with_backpressure`].
+    pub async fn query_with_backpressure<H>(&mut self, sql: &str, handler: &mut H) -> Result<()>
+    where
+        H: AsyncTextResultSetHandler,
+    {
+        let started = self.log_start();
+        let (result, allocs) =
+            alloc_stats::count_async(self.query_with_backpressure_inner(sql, handler)).await;
+        self.log_statement(StatementKind::Query, Some(sql), None, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn query_with_backpressure_inner<H>(&mut self, sql: &str, handler: &mut H) -> Result<()>
+    where
+        H: AsyncTextResultSetHandler,
+    {
+        self.reject_writes(sql)?;
+        crate::paranoid::check_query(sql);
+        self.write_query_command(sql, NO_ATTRIBUTES)?;
+        self.write_payload().await?;
+        self.drive_query_deferred(handler).await
+    }
+
+    /// Execute a text protocol SQL query with a server-side execution time limit.
+    ///
+    /// On MySQL, a `/*+ MAX_EXECUTION_TIME(n) */` hint is added to `SELECT` statements
+    /// and other statements are sent unchanged.
+    /// On MariaDB, the query is wrapped in `SET STATEMENT max_statement_time=.. FOR`.
+    pub async fn query_with_timeout<H>(
+        &mut self,
+        sql: &str,
+        timeout: Duration,
+        handler: &mut H,
+    ) -> Result<()>
+    where
+        H: TextResultSetHandler,
+    {
+        let rewritten = hint::with_statement_timeout(sql, timeout, self.is_mariadb());
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(
+            self.query_inner(rewritten.as_deref().unwrap_or(sql), handler),
+        )
+        .await;
+        self.log_statement(
+            StatementKind::Query,
+            Some(rewritten.as_deref().unwrap_or(sql)),
+            None,
+            started,
+            &result,
+        );
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    /// Execute a text protocol SQL query that `cancel` can abort from another task,
+    /// e.g. a long analytic query the user gave up on.
+    ///
+    /// When `cancel` fires, the statement is aborted with `KILL QUERY` on a second connection
+    /// and `Error::Cancelled` is returned once the server confirms it. The connection stays usable,
+    /// but the rows already passed to `handler` are a prefix of the result.
+    /// If `Opts::kill_query_on_cancel` is off or `KILL QUERY` fails, the command is dropped
+    /// instead and the connection is broken.
+    pub async fn query_cancellable<H>(
+        &mut self,
+        sql: &str,
+        handler: &mut H,
+        cancel: &CancellationToken,
+    ) -> Result<()>
+    where
+        H: TextResultSetHandler,
+    {
+        let started = self.log_start();
+        let kill_query_opts = self.kill_query_opts.clone();
+        let kill = self.kill_query(kill_query_opts.as_deref());
+        let (outcome, allocs) = alloc_stats::count_async(run_cancellable(
+            self.query_inner(sql, handler),
+            cancel,
+            kill.map(KillQuery::run),
+        ))
+        .await;
+        let result = self.finish_cancellable(outcome).await;
+        self.log_statement(StatementKind::Query, Some(sql), None, started, &result);
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    fn kill_query<'a>(&self, opts: Option<&'a Opts>) -> Option<KillQuery<'a>> {
+        opts.map(|opts| KillQuery {
+            opts,
+            connection_id: self.connection_id(),
+        })
+    }
+
+    /// The result of [`run_cancellable`], breaking the connection if the command was dropped
+    async fn finish_cancellable(&mut self, outcome: Cancellable<()>) -> Result<()> {
+        match outcome {
+            Cancellable::Finished(result) => result,
+            Cancellable::KillPending(result) => {
+                self.absorb_kill_query().await;
+                result
+            }
+            Cancellable::Dropped => {
+                self.is_broken = true;
+                Err(Error::Cancelled)
+            }
+        }
+    }
+
+    /// Run a no-op statement for a `KILL QUERY` that arrived after its command finished,
+    /// so it cannot abort the next statement. Breaks the connection if that fails otherwise.
+    async fn absorb_kill_query(&mut self) {
+        match self.query_drop("DO SLEEP(0)").await {
+            Ok(()) => {}
+            Err(Error::ServerError(payload)) if payload.error_code == ER_QUERY_INTERRUPTED => {}
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to absorb a late KILL QUERY");
+                self.is_broken = true;
+            }
+        }
+    }
+
+    /// Execute a text protocol SQL query and collect every result set,
+    /// e.g. of a multi-statement query or a `CALL`.
+    pub async fn query_multi(&mut self, sql: &str) -> Result<Vec<crate::multi_result::ResultSet>> {
+        let mut handler = crate::multi_result::MultiResultHandler::default();
+        self.query(sql, &mut handler).await?;
+        Ok(handler.into_result_sets())
+    }
+
     /// Execute a text protocol SQL query and discard all results (async)
     #[instrument(skip_all)]
     pub async fn query_drop(&mut self, sql: &str) -> Result<()> {
-        let result = self.query_drop_inner(sql).await;
+        let started = self.log_start();
+        let (result, allocs) = alloc_stats::count_async(self.query_drop_inner(sql)).await;
+        self.log_statement(StatementKind::Query, Some(sql), None, started, &result);
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
     async fn query_drop_inner(&mut self, sql: &str) -> Result<()> {
-        write_query(self.buffer_set.new_write_buffer(), sql);
+        self.reject_writes(sql)?;
+        crate::paranoid::check_query(sql);
+        self.write_query_command(sql, NO_ATTRIBUTES)?;
         self.write_payload().await?;
         self.drive_query(&mut DropHandler::default()).await
     }
 
+    /// Activate `role` for the session (`SET ROLE`), replacing the currently active roles.
+    ///
+    /// `role` is quoted as an identifier, so the host part defaults to `%`.
+    pub async fn set_role(&mut self, role: &str) -> Result<()> {
+        self.query_drop(&format!("SET ROLE {}", quote_identifier(role)))
+            .await?;
+        self.role_changed = true;
+        Ok(())
+    }
+
+    /// Restore the roles the session started with.
+    ///
+    /// Runs `SET ROLE DEFAULT` on MySQL and `SET ROLE NONE` on MariaDB.
+    pub async fn reset_role(&mut self) -> Result<()> {
+        let sql = if self.is_mariadb() {
+            "SET ROLE NONE"
+        } else {
+            "SET ROLE DEFAULT"
+        };
+        self.query_drop(sql).await?;
+        self.role_changed = false;
+        Ok(())
+    }
+
+    /// Returns true if `set_role()` was called since the connection was opened or reset.
+    pub fn role_changed(&self) -> bool {
+        self.role_changed
+    }
+
+    /// Run a protocol command that has no method on `Conn`.
+    ///
+    /// See [`Command`](crate::protocol::Command).
+    pub async fn run_command<C: Command>(&mut self, command: &mut C) -> Result<()> {
+        let (result, allocs) = alloc_stats::count_async(self.run_command_inner(command)).await;
+        self.alloc_stats.record(allocs);
+        self.check_error(result)
+    }
+
+    async fn run_command_inner<C: Command>(&mut self, command: &mut C) -> Result<()> {
+        command.encode(self.buffer_set.new_write_buffer())?;
+        self.write_payload().await?;
+        self.buffer_set.read_buffer.clear();
+        loop {
+            match command.step(&mut self.buffer_set)? {
+                Action::NeedPacket(buffer) => {
+                    buffer.clear();
+                    let _ = read_payload(&mut self.stream, buffer).await?;
+                }
+                Action::ReadColumnMetadata { num_columns } => {
+                    self.read_column_definition_packets(num_columns).await?;
+                }
+                Action::LocalInfile { .. } => {
+                    self.is_broken = true;
+                    return Err(Error::Unsupported(
+                        "LOCAL INFILE in a custom command".to_string(),
+                    ));
+                }
+                Action::Finished => return Ok(()),
+            }
+        }
+    }
+
     /// Send a ping to the server to check if the connection is alive (async)
     ///
     /// This sends a COM_PING command to the MySQL server and waits for an OK response.
     pub async fn ping(&mut self) -> Result<()> {
-        let result = self.ping_inner().await;
+        let (result, allocs) = alloc_stats::count_async(self.ping_inner()).await;
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
@@ -596,44 +2140,302 @@ impl Conn {
 
     /// Reset the connection to its initial state (async)
     pub async fn reset(&mut self) -> Result<()> {
-        let result = self.reset_inner().await;
+        self.reset_with_init_commands(&[]).await
+    }
+
+    /// [`reset`](Self::reset), running `init_commands` before the session setup is replayed
+    pub(crate) async fn reset_with_init_commands(
+        &mut self,
+        init_commands: &[String],
+    ) -> Result<()> {
+        let (result, allocs) = alloc_stats::count_async(self.reset_inner(init_commands)).await;
+        self.alloc_stats.record(allocs);
         self.check_error(result)
     }
 
-    async fn reset_inner(&mut self) -> Result<()> {
+    async fn reset_inner(&mut self, init_commands: &[String]) -> Result<()> {
+        if !self
+            .dialect
+            .supports_reset_connection(self.server_version())
+        {
+            return Err(Error::Unsupported(format!(
+                "COM_RESET_CONNECTION is not used with {}",
+                self.dialect
+            )));
+        }
+        // The server closes every prepared statement
+        self.statement_cache.clear();
+        self.tracked_variables.clear();
         write_reset_connection(self.buffer_set.new_write_buffer());
         self.write_payload().await?;
         self.buffer_set.read_buffer.clear();
         let _ = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
-        self.in_transaction = false;
+        if self.buffer_set.read_buffer.first() == Some(&0xFF) {
+            Err(ErrPayloadBytes(&self.buffer_set.read_buffer))?
+        }
+        self.server_status = OkPayload::try_from(OkPayloadBytes(&self.buffer_set.read_buffer))
+            .map_or(
+                self.server_status - crate::constant::ServerStatusFlags::SERVER_STATUS_IN_TRANS,
+                |ok| ok.status_flags,
+            );
+        self.role_changed = false;
+        // The session was reset
+        self.setup_session(init_commands).await
+    }
+
+    /// Run `init_commands`, then `Opts::session_setup_sql()` and the read-only setting,
+    /// in the same order as connecting
+    pub(crate) async fn setup_session(&mut self, init_commands: &[String]) -> Result<()> {
+        for init_command in init_commands {
+            self.query_drop(init_command).await?;
+        }
+        if let Some(session_setup) = self.session_setup.clone() {
+            self.query_drop(&session_setup).await?;
+        }
+        if self.read_only {
+            self.query_drop(READ_ONLY_SQL).await?;
+        }
         Ok(())
     }
 
+    /// Re-authenticate as another user with `COM_CHANGE_USER`, without reconnecting.
+    ///
+    /// The server resets the session as with [`reset`](Self::reset): prepared statements are closed,
+    /// user variables and temporary tables are dropped, and an open transaction is rolled back.
+    /// `Opts::danger_zone` does not apply to the new authentication.
+    pub async fn change_user(
+        &mut self,
+        user: &str,
+        password: &str,
+        db: Option<&str>,
+    ) -> Result<()> {
+        let (result, allocs) =
+            alloc_stats::count_async(self.change_user_inner(user, password, db)).await;
+        self.alloc_stats.record(allocs);
+        if result.is_ok() {
+            self.user = user.to_string();
+            if let Some(kill_query_opts) = &mut self.kill_query_opts {
+                let kill_query_opts = Arc::make_mut(kill_query_opts);
+                kill_query_opts.user = user.to_string();
+                kill_query_opts.password = password.to_string();
+            }
+        }
+        self.check_error(result)
+    }
+
+    async fn change_user_inner(
+        &mut self,
+        user: &str,
+        password: &str,
+        db: Option<&str>,
+    ) -> Result<()> {
+        self.statement_cache.clear();
+        self.tracked_variables.clear();
+        let opts = crate::opts::Opts {
+            user: user.to_string(),
+            password: password.to_string(),
+            db: db.map(str::to_string),
+            application_name: self.application_name.clone(),
+            collation: Some(self.collation),
+            ..Default::default()
+        };
+        let mut handshake = Handshake::change_user(
+            &opts,
+            self.initial_handshake.clone(),
+            self.capability_flags,
+            self.mariadb_capabilities,
+        );
+
+        loop {
+            match handshake.step(&mut self.buffer_set)? {
+                HandshakeAction::ReadPacket(buffer) => {
+                    buffer.clear();
+                    read_payload(&mut self.stream, buffer).await?;
+                }
+                HandshakeAction::WritePacket { sequence_id } => {
+                    write_handshake_payload(&mut self.stream, &mut self.buffer_set, sequence_id)
+                        .await?;
+                    self.buffer_set.read_buffer.clear();
+                    read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
+                }
+                HandshakeAction::UpgradeTls { .. } => {
+                    return Err(Error::LibraryBug(crate::error::eyre!(
+                        "TLS upgrade requested during COM_CHANGE_USER"
+                    )));
+                }
+                HandshakeAction::Finished => break,
+            }
+        }
+        self.auth_plugin = handshake.auth_plugin(&self.buffer_set);
+        handshake.finish()?;
+
+        self.server_status = OkPayload::try_from(OkPayloadBytes(&self.buffer_set.read_buffer))
+            .map_or(
+                self.server_status - crate::constant::ServerStatusFlags::SERVER_STATUS_IN_TRANS,
+                |ok| ok.status_flags,
+            );
+        self.role_changed = false;
+        // The session was reset
+        if let Some(session_setup) = self.session_setup.clone() {
+            self.query_drop(&session_setup).await?;
+        }
+        if self.read_only {
+            self.query_drop(READ_ONLY_SQL).await?;
+        }
+        Ok(())
+    }
+
+    /// Send `COM_QUIT` and close the connection.
+    pub async fn close(mut self) -> Result<()> {
+        write_quit(self.buffer_set.new_write_buffer());
+        self.write_payload().await
+    }
+
+    /// Send the statements of `buffer` in one pipelined batch, then clear it.
+    ///
+    /// Each distinct SQL is prepared once and all executions are written before their responses
+    /// are read, [`PIPELINE_WINDOW`] at a time. Without an open transaction, the batch runs in its
+    /// own transaction that is committed on success and rolled back on error.
+    /// Inside a transaction, the statements after a failing one still run:
+    /// roll the transaction back on error.
+    ///
+    /// Returns the affected rows of each statement. On error, `buffer` is left as it was.
+    pub async fn flush_statements(&mut self, buffer: &mut StatementBuffer) -> Result<Vec<u64>> {
+        let (result, allocs) = alloc_stats::count_async(self.flush_statements_inner(buffer)).await;
+        self.alloc_stats.record(allocs);
+        let affected_rows = self.check_error(result)?;
+        buffer.clear();
+        Ok(affected_rows)
+    }
+
+    async fn flush_statements_inner(&mut self, buffer: &StatementBuffer) -> Result<Vec<u64>> {
+        if buffer.is_empty() {
+            return Ok(Vec::new());
+        }
+        if self.emulate_prepared_statements {
+            return Err(Error::BadUsageError(
+                "flush_statements() is not supported with Opts::emulate_prepared_statements"
+                    .to_string(),
+            ));
+        }
+        let mut stmts = Vec::with_capacity(buffer.sqls().len());
+        let mut result = Ok(Vec::new());
+        for sql in buffer.sqls() {
+            match self.prepare_inner(sql).await {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        let begin = !self.in_transaction();
+        if result.is_ok() {
+            result = self.execute_pipelined(buffer, &mut stmts, begin).await;
+            if begin {
+                if result.is_ok() {
+                    if let Err(err) = self.query_drop_inner("COMMIT").await {
+                        result = Err(err);
+                    }
+                } else {
+                    let _ = self.query_drop_inner("ROLLBACK").await;
+                }
+            }
+        }
+
+        // the server does not respond to COM_STMT_CLOSE
+        for stmt in stmts {
+            write_close_statement(self.buffer_set.new_write_buffer(), stmt.id());
+            if let Err(err) = self.write_pipelined().await {
+                return result.and(Err(err));
+            }
+        }
+        if let Err(err) = self.flush_pipelined().await {
+            return result.and(Err(err));
+        }
+        result
+    }
+
+    /// Write `BEGIN` if `begin`, then the executions of `buffer` in windows,
+    /// reading each window's responses after writing it.
+    async fn execute_pipelined(
+        &mut self,
+        buffer: &StatementBuffer,
+        stmts: &mut [PreparedStatement],
+        begin: bool,
+    ) -> Result<Vec<u64>> {
+        let statements: Vec<(usize, usize, &[u8])> = buffer.statements().collect();
+        let mut affected_rows = Vec::with_capacity(statements.len());
+        // A server error does not stop the pipeline; the remaining responses are read first
+        let mut first_error = None;
+
+        if begin {
+            self.write_query_command("BEGIN", NO_ATTRIBUTES)?;
+            self.write_pipelined().await?;
+        }
+        for (window_index, window) in statements.chunks(PIPELINE_WINDOW).enumerate() {
+            for (sql_index, num_params, payload) in window {
+                let statement_id = stmts[*sql_index].id();
+                let query_attributes = self.query_attributes();
+                write_buffered_execute(
+                    self.buffer_set.new_write_buffer(),
+                    payload,
+                    *num_params,
+                    statement_id,
+                    query_attributes,
+                );
+                self.write_pipelined().await?;
+            }
+            self.flush_pipelined().await?;
+            if begin && window_index == 0 {
+                match self.drive_query(&mut DropHandler::default()).await {
+                    Ok(()) => {}
+                    Err(err @ Error::ServerError(_)) => {
+                        first_error.get_or_insert(err);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            for (sql_index, _, _) in window {
+                let mut handler = DropHandler::default();
+                match self.drive_exec(&mut stmts[*sql_index], &mut handler).await {
+                    Ok(()) => affected_rows.push(handler.affected_rows()),
+                    Err(err @ Error::ServerError(_)) => {
+                        first_error.get_or_insert(err);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            if first_error.is_some() {
+                break;
+            }
+        }
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(affected_rows),
+        }
+    }
+
     /// Execute a closure within a transaction (async)
     ///
     /// # Errors
-    /// Returns `Error::NestedTransaction` if called while already in a transaction
+    /// Returns `Error::NestedTransaction` if called while already in a transaction.
+    /// Use [`Transaction::savepoint`](super::Transaction::savepoint) to nest.
     pub async fn transaction<F, R>(&mut self, f: F) -> Result<R>
     where
-        F: AsyncFnOnce(&mut Conn, super::transaction::Transaction) -> Result<R>,
+        F: AsyncFnOnce(super::transaction::Transaction<'_>) -> Result<R>,
     {
-        if self.in_transaction {
+        if self.in_transaction() {
             return Err(Error::NestedTransaction);
         }
 
-        self.in_transaction = true;
-
-        if let Err(err) = self.query_drop("BEGIN").await {
-            self.in_transaction = false;
-            return Err(err);
-        }
+        self.query_drop("BEGIN").await?;
 
-        let tx = super::transaction::Transaction::new(self.connection_id());
-        let result = f(self, tx).await;
+        let result = f(super::transaction::Transaction::new(self)).await;
 
         // If no explicit commit/rollback was called, commit on Ok, rollback on Err
-        if self.in_transaction {
-            self.in_transaction = false;
+        if self.in_transaction() {
             match &result {
                 Ok(_) => self.query_drop("COMMIT").await?,
                 Err(_) => {
@@ -644,15 +2446,140 @@ impl Conn {
 
         result
     }
+
+    /// Start a read-only transaction with a consistent snapshot and record its binlog position.
+    ///
+    /// Use the returned [`Snapshot`](super::Snapshot) to read tables as of the position,
+    /// then start following the binlog from [`Snapshot::finish`](super::Snapshot::finish).
+    ///
+    /// # Errors
+    /// Returns `Error::NestedTransaction` if called while already in a transaction,
+    /// or `Error::Unsupported` if binary logging is disabled
+    pub async fn start_snapshot(&mut self) -> Result<super::Snapshot<'_>> {
+        super::Snapshot::start(self).await
+    }
+
+    /// Register as a replica and stream the binary log from `request.file` and `request.position`,
+    /// or the transactions missing from `request.gtid_set`.
+    ///
+    /// See [`crate::binlog`]. The connection cannot run other commands while the dump is running;
+    /// it is marked broken if the stream is dropped before the end of a non-blocking dump.
+    pub async fn binlog_dump(
+        &mut self,
+        request: &BinlogRequest,
+    ) -> Result<super::BinlogStream<'_>> {
+        let result = self.binlog_dump_inner(request).await;
+        let (checksum, tracker) = self.check_error(result)?;
+        Ok(super::BinlogStream::new(
+            self,
+            BinlogParser::with_checksum(checksum),
+            tracker,
+        ))
+    }
+
+    async fn binlog_dump_inner(
+        &mut self,
+        request: &BinlogRequest,
+    ) -> Result<(bool, PositionTracker)> {
+        let tracker = PositionTracker::new(request)?;
+        if tracker.gtid_set().is_some() && self.is_mariadb() {
+            return Err(Error::Unsupported(
+                "GTID binlog dump is not supported on MariaDB".to_string(),
+            ));
+        }
+        self.query_drop(if self.is_mariadb() {
+            MARIADB_CHECKSUM_SQL
+        } else {
+            MYSQL_CHECKSUM_SQL
+        })
+        .await?;
+        let mut checksum = TextRowsHandler::default();
+        self.query(CHECKSUM_SQL, &mut checksum).await?;
+
+        write_register_replica(
+            self.buffer_set.new_write_buffer(),
+            request.server_id,
+            &request.hostname,
+            "",
+            "",
+            request.port,
+        );
+        self.write_payload().await?;
+        read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await?;
+        if self.buffer_set.read_buffer.first() == Some(&0xFF) {
+            Err(ErrPayloadBytes(&self.buffer_set.read_buffer))?
+        }
+
+        let mut flags = if request.non_blocking {
+            BINLOG_DUMP_NON_BLOCK
+        } else {
+            0
+        };
+        match (tracker.gtid_set(), u32::try_from(request.position)) {
+            (None, Ok(position)) => write_binlog_dump(
+                self.buffer_set.new_write_buffer(),
+                request.server_id,
+                flags,
+                &request.file,
+                position,
+            ),
+            (gtid_set, _) => {
+                let mut encoded = Vec::new();
+                if let Some(gtid_set) = gtid_set {
+                    gtid_set.write_encoded(&mut encoded);
+                    flags |= BINLOG_THROUGH_GTID;
+                }
+                write_binlog_dump_gtid(
+                    self.buffer_set.new_write_buffer(),
+                    request.server_id,
+                    flags,
+                    &request.file,
+                    request.position,
+                    &encoded,
+                );
+            }
+        }
+        self.write_payload().await?;
+        Ok((parse_checksum(&checksum.rows), tracker))
+    }
+
+    /// Read the next packet of a binlog dump, returning the event or `None` at the end of the binlog
+    pub(super) async fn read_binlog_event(&mut self) -> Result<Option<&[u8]>> {
+        let result = read_payload(&mut self.stream, &mut self.buffer_set.read_buffer).await;
+        self.check_error(result)?;
+        let payload = self.buffer_set.read_buffer.as_slice();
+        match payload.split_first() {
+            Some((0x00, event)) => Ok(Some(event)),
+            Some((0xFE, _)) if payload.len() < 9 => Ok(None),
+            Some((0xFF, _)) => Err(ErrPayloadBytes(payload).into()),
+            _ => Err(Error::LibraryBug(crate::error::eyre!(
+                "binlog: unexpected packet header {:?}",
+                payload.first()
+            ))),
+        }
+    }
+
+    pub(super) fn mark_broken(&mut self) {
+        self.is_broken = true;
+    }
 }
 
 /// Read a complete MySQL payload asynchronously, concatenating packets if they span multiple 16MB chunks
 /// Returns the sequence_id of the last packet read.
 #[instrument(skip_all)]
 async fn read_payload(reader: &mut Stream, buffer: &mut Vec<u8>) -> Result<u8> {
+    let timeout = reader.read_timeout();
+    timed(timeout, read_packets(reader, buffer)).await
+}
+
+/// [`read_payload`] without the read timeout
+async fn read_packets(reader: &mut Stream, buffer: &mut Vec<u8>) -> Result<u8> {
     let mut packet_header = PacketHeader::new_zeroed();
 
     buffer.clear();
+    if let Some(sequence_id) = reader.read_buffered_payload(buffer).await? {
+        return Ok(sequence_id);
+    }
     reader.read_exact(packet_header.as_mut_bytes()).await?;
 
     let length = packet_header.length();
@@ -696,21 +2623,42 @@ async fn write_handshake_payload(
 ) -> Result<()> {
     let mut buffer = buffer_set.write_buffer_mut().as_mut_slice();
     let mut seq_id = sequence_id;
+    let timeout = stream.write_timeout();
 
-    loop {
-        let chunk_size = buffer[4..].len().min(0xFFFFFF);
-        PacketHeader::mut_from_bytes(&mut buffer[0..4])?.encode_in_place(chunk_size, seq_id);
-        stream.write_all(&buffer[..4 + chunk_size]).await?;
+    timed(timeout, async {
+        loop {
+            let chunk_size = buffer[4..].len().min(0xFFFFFF);
+            PacketHeader::mut_from_bytes(&mut buffer[0..4])?.encode_in_place(chunk_size, seq_id);
+            stream.write_all(&buffer[..4 + chunk_size]).await?;
 
-        if chunk_size < 0xFFFFFF {
-            break;
+            if chunk_size < 0xFFFFFF {
+                break;
+            }
+
+            seq_id = seq_id.wrapping_add(1);
+            buffer = &mut buffer[0xFFFFFF..];
         }
+        stream.flush().await?;
+        Ok::<_, Error>(())
+    })
+    .await
+}
 
-        seq_id = seq_id.wrapping_add(1);
-        buffer = &mut buffer[0xFFFFFF..];
-    }
-    stream.flush().await?;
-    Ok(())
+/// Fail with `Error::Timeout` if `io` does not finish within `timeout`
+async fn timed<T, E>(
+    timeout: Option<Duration>,
+    io: impl Future<Output = core::result::Result<T, E>>,
+) -> Result<T>
+where
+    E: Into<Error>,
+{
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, io)
+            .await
+            .map_err(|_elapsed| Error::Timeout)?,
+        None => io.await,
+    };
+    result.map_err(Into::into)
 }
 
 /// Handler to capture socket path from SELECT @@socket query
@@ -0,0 +1,46 @@
+//! Cooperative yielding while reading large responses.
+//!
+//! Reads served from the connection's read buffer never reach the socket, so Tokio's own
+//! cooperative budget does not apply to them. A multi-hundred-MB result set can then be decoded
+//! without ever returning to the scheduler. [`YieldBudget`] counts the packets and bytes read
+//! and tells the connection when to call `tokio::task::yield_now()`.
+
+use crate::opts::Opts;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct YieldBudget {
+    /// `0` disables the byte budget
+    every_bytes: usize,
+    /// `0` disables the packet budget
+    every_packets: usize,
+    bytes: usize,
+    packets: usize,
+}
+
+impl YieldBudget {
+    pub(crate) fn new(every_bytes: usize, every_packets: usize) -> Self {
+        Self {
+            every_bytes,
+            every_packets,
+            bytes: 0,
+            packets: 0,
+        }
+    }
+
+    pub(crate) fn from_opts(opts: &Opts) -> Self {
+        Self::new(opts.yield_every_bytes, opts.yield_every_packets)
+    }
+
+    /// Count a packet of `len` bytes. Returns true if the task should yield now.
+    pub(crate) fn consume(&mut self, len: usize) -> bool {
+        self.bytes = self.bytes.saturating_add(len);
+        self.packets += 1;
+        let exhausted = (self.every_bytes != 0 && self.bytes >= self.every_bytes)
+            || (self.every_packets != 0 && self.packets >= self.every_packets);
+        if exhausted {
+            self.bytes = 0;
+            self.packets = 0;
+        }
+        exhausted
+    }
+}
@@ -0,0 +1,46 @@
+use crate::error::Result;
+use crate::opts::Opts;
+use crate::test_macros::{check, check_eq};
+use crate::tokio::coop::YieldBudget;
+
+#[test]
+fn yields_after_byte_budget() -> Result<()> {
+    let mut budget = YieldBudget::new(100, 0);
+    check!(!budget.consume(40));
+    check!(!budget.consume(40));
+    check!(budget.consume(40));
+    // The budget starts over after yielding
+    check!(!budget.consume(99));
+    check!(budget.consume(1));
+    Ok(())
+}
+
+#[test]
+fn yields_after_packet_budget() -> Result<()> {
+    let mut budget = YieldBudget::new(0, 3);
+    let yields: Vec<bool> = std::iter::repeat_n(0, 7)
+        .map(|len| budget.consume(len))
+        .collect();
+    check_eq!(yields, vec![false, false, true, false, false, true, false]);
+    Ok(())
+}
+
+#[test]
+fn whichever_budget_runs_out_first() -> Result<()> {
+    let mut budget = YieldBudget::new(1000, 2);
+    check!(!budget.consume(10));
+    check!(budget.consume(10));
+    check!(budget.consume(2000));
+    Ok(())
+}
+
+#[test]
+fn disabled_budget_never_yields() -> Result<()> {
+    let opts = Opts {
+        yield_every_bytes: 0,
+        ..Opts::default()
+    };
+    let mut budget = YieldBudget::from_opts(&opts);
+    check!(std::iter::repeat_n(usize::MAX, 10_000).all(|len| !budget.consume(len)));
+    Ok(())
+}
@@ -1,9 +1,45 @@
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+mod binlog;
+mod cancel;
 mod conn;
+mod coop;
+pub mod global;
+mod offload;
 mod pool;
+pub mod routed;
+mod row_stream;
+pub mod scan;
+pub mod serial;
+pub mod sharded;
+mod snapshot;
 mod stream;
 mod transaction;
 
+pub use binlog::BinlogStream;
+pub use cancel::CancellationToken;
 pub use conn::Conn;
 pub use pool::{Pool, PooledConn};
+pub use row_stream::RowStream;
+pub use snapshot::Snapshot;
 pub use stream::Stream;
-pub use transaction::Transaction;
+pub use transaction::{Savepoint, Transaction};
+
+#[cfg(test)]
+mod cancel_test;
+#[cfg(test)]
+mod coop_test;
+#[cfg(test)]
+mod offload_test;
+#[cfg(test)]
+mod pool_test;
+#[cfg(test)]
+mod routed_test;
+#[cfg(test)]
+mod scan_test;
+#[cfg(test)]
+mod serial_test;
+#[cfg(test)]
+mod sharded_test;
@@ -0,0 +1,168 @@
+//! Decoding rows on the blocking pool.
+//!
+//! Decoding a huge result set into `FromRow` structs can take longer than reading it.
+//! `Conn::exec_collect_offloaded()` copies the raw rows into batches of
+//! [`Opts::offload_decode_bytes`](crate::Opts::offload_decode_bytes) and hands each full batch
+//! to `tokio::task::spawn_blocking`, so decoding runs on the blocking pool while the connection
+//! keeps reading. A result set smaller than one batch is decoded inline.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use tokio::task::JoinHandle;
+
+use crate::error::{Error, Result, eyre};
+use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitions};
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::BinaryResultSetHandler;
+use crate::raw::FromRow;
+use crate::value::NullBitmap;
+
+/// Raw rows of one result set, owned so that they can move to another thread
+pub(crate) struct RowBatch {
+    columns: Arc<ColumnDefinitions>,
+    bytes: Vec<u8>,
+    /// Range of each row in `bytes` and the length of its null bitmap
+    rows: Vec<(Range<usize>, usize)>,
+}
+
+impl RowBatch {
+    pub(crate) fn new(columns: Arc<ColumnDefinitions>) -> Self {
+        Self {
+            columns,
+            bytes: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, row: &BinaryRowPayload<'_>) {
+        let start = self.bytes.len();
+        let null_bitmap = row.null_bitmap().as_bytes();
+        self.bytes.extend_from_slice(null_bitmap);
+        self.bytes.extend_from_slice(row.values());
+        self.rows.push((start..self.bytes.len(), null_bitmap.len()));
+    }
+
+    pub(crate) fn byte_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub(crate) fn decode<Row: for<'buf> FromRow<'buf>>(&self) -> Result<Vec<Row>> {
+        let cols = self.columns.definitions();
+        self.rows
+            .iter()
+            .map(|(range, null_bitmap_len)| {
+                let (null_bitmap, values) = self.bytes[range.clone()].split_at(*null_bitmap_len);
+                Row::from_row(
+                    cols,
+                    BinaryRowPayload::new(
+                        NullBitmap::for_result_set(null_bitmap),
+                        values,
+                        cols.len(),
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Collects rows like `CollectHandler`, decoding full batches on the blocking pool.
+pub(crate) struct OffloadCollectHandler<Row> {
+    batch_bytes: usize,
+    batch: Option<RowBatch>,
+    /// Decoded batches in result order: spawned, or decoded inline at the end of a result set
+    decoded: Vec<Decoded<Row>>,
+}
+
+enum Decoded<Row> {
+    Spawned(JoinHandle<Result<Vec<Row>>>),
+    Inline(Vec<Row>),
+}
+
+impl<Row> OffloadCollectHandler<Row>
+where
+    Row: for<'buf> FromRow<'buf> + Send + 'static,
+{
+    pub(crate) fn new(batch_bytes: usize) -> Self {
+        Self {
+            batch_bytes,
+            batch: None,
+            decoded: Vec::new(),
+        }
+    }
+
+    fn spawn(&mut self, batch: RowBatch) {
+        self.decoded
+            .push(Decoded::Spawned(tokio::task::spawn_blocking(move || {
+                batch.decode()
+            })));
+    }
+
+    /// Decode the rest of the current result set: inline if nothing was spawned for it
+    fn flush(&mut self) -> Result<()> {
+        if let Some(batch) = self.batch.take()
+            && !batch.is_empty()
+        {
+            if matches!(self.decoded.last(), Some(Decoded::Spawned(_))) {
+                self.spawn(batch);
+            } else {
+                self.decoded.push(Decoded::Inline(batch.decode()?));
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for the spawned batches and concatenate the rows.
+    pub(crate) async fn into_rows(mut self) -> Result<Vec<Row>> {
+        self.flush()?;
+        let mut rows = Vec::new();
+        for decoded in self.decoded {
+            match decoded {
+                Decoded::Spawned(handle) => rows.extend(handle.await.map_err(|err| {
+                    Error::LibraryBug(eyre!("row decoding task failed: {err}"))
+                })??),
+                Decoded::Inline(batch) => rows.extend(batch),
+            }
+        }
+        Ok(rows)
+    }
+}
+
+impl<Row> BinaryResultSetHandler for OffloadCollectHandler<Row>
+where
+    Row: for<'buf> FromRow<'buf> + Send + 'static,
+{
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.batch = Some(RowBatch::new(Arc::new(
+            ColumnDefinitions::from_definitions(cols)?,
+        )));
+        Ok(())
+    }
+
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<()> {
+        let Some(batch) = &mut self.batch else {
+            return Err(Error::LibraryBug(eyre!("row before resultset_start")));
+        };
+        batch.push(&row);
+        if self.batch_bytes != 0 && batch.byte_len() >= self.batch_bytes {
+            let columns = Arc::clone(&batch.columns);
+            if let Some(full) = self.batch.replace(RowBatch::new(columns)) {
+                self.spawn(full);
+            }
+        }
+        Ok(())
+    }
+
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        self.flush()
+    }
+}
@@ -0,0 +1,65 @@
+use zerocopy::FromBytes;
+
+use crate::error::Result;
+use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail};
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::BinaryResultSetHandler;
+use crate::test_macros::check_eq;
+use crate::tokio::offload::OffloadCollectHandler;
+use crate::value::NullBitmap;
+
+/// EOF with SERVER_STATUS_AUTOCOMMIT
+const EOF: &[u8] = &[0xFE, 0, 0, 0x02, 0x00, 0, 0];
+
+/// BIGINT NOT NULL
+const BIGINT_TAIL: [u8; 12] = [
+    0x3F, 0x00, 0x14, 0x00, 0x00, 0x00, 0x08, 0x01, 0x00, 0x00, 0, 0,
+];
+
+fn collect(batch_bytes: usize, result_sets: &[&[i64]]) -> Result<OffloadCollectHandler<(i64,)>> {
+    let tail = ColumnDefinitionTail::ref_from_bytes(&BIGINT_TAIL)?;
+    let cols = [ColumnDefinition {
+        schema: b"test",
+        table_alias: b"",
+        table_original: b"",
+        name_alias: b"id",
+        name_original: b"id",
+        tail,
+        name_id: None,
+    }];
+    let mut handler = OffloadCollectHandler::new(batch_bytes);
+    for ids in result_sets {
+        handler.resultset_start(&cols)?;
+        for id in *ids {
+            let values = id.to_le_bytes();
+            handler.row(
+                &cols,
+                BinaryRowPayload::new(NullBitmap::for_result_set(&[0]), &values, 1),
+            )?;
+        }
+        handler.resultset_end(OkPayloadBytes(EOF))?;
+    }
+    Ok(handler)
+}
+
+#[tokio::test]
+async fn batches_keep_row_order() -> Result<()> {
+    // 9 bytes per row: 3 rows per batch
+    let handler = collect(27, &[&[1, 2, 3, 4, 5, 6, 7], &[8, 9]])?;
+    let ids: Vec<i64> = handler
+        .into_rows()
+        .await?
+        .into_iter()
+        .map(|(id,)| id)
+        .collect();
+    check_eq!(ids, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn zero_batch_size_decodes_inline() -> Result<()> {
+    let handler = collect(0, &[&[1, 2], &[], &[3]])?;
+    check_eq!(handler.into_rows().await?, vec![(1,), (2,), (3,)]);
+    Ok(())
+}
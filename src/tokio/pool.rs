@@ -1,78 +1,592 @@
+use std::fmt;
 use std::mem::ManuallyDrop;
+use std::ops::AsyncFnOnce;
 use std::ops::{Deref, DerefMut};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Instant;
+
+use tokio::task::JoinHandle;
 
 use crossbeam_queue::ArrayQueue;
-use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
 
 use crate::error::Result;
 use crate::opts::Opts;
+use crate::pool_config::{PoolConfig, ResetOnReturn};
+use crate::pool_sizing::PoolSizer;
+use crate::protocol::r#trait::param::Params;
+use crate::raw::FromRow;
 
 use super::Conn;
+use super::scan::ParallelScan;
+use super::transaction::Transaction;
 
 pub struct Pool {
-    opts: Opts,
-    conns: ArrayQueue<Conn>,
-    semaphore: Option<Arc<Semaphore>>,
+    opts: RwLock<Arc<Opts>>,
+    /// Incremented by `update_opts()`
+    generation: AtomicU64,
+    config: PoolConfig,
+    conns: ArrayQueue<IdleConn>,
+    semaphore: RwLock<Option<Arc<Semaphore>>>,
+    sizer: Option<PoolSizer>,
+    in_use: AtomicUsize,
+    closed: AtomicBool,
+    drained: Notify,
+    /// Notified when a connection is returned after `ER_SERVER_SHUTDOWN`
+    server_shutdown: Notify,
+}
+
+/// What the pool remembers about a connection from when it was opened
+#[derive(Debug, Clone, Copy)]
+struct Opened {
+    /// `max_lifetime` after the connection was opened
+    expires: Option<Instant>,
+    /// The autocommit setting after `Opts::init_commands`
+    autocommit: bool,
+    /// `Pool::generation` when the connection was opened
+    generation: u64,
+}
+
+impl Opened {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+/// An idle connection with its deadlines
+struct IdleConn {
+    conn: Conn,
+    opened: Opened,
+    /// `idle_timeout` after the connection was returned
+    idle_expires: Option<Instant>,
+}
+
+impl IdleConn {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.opened.is_expired(now) || self.idle_expires.is_some_and(|expires| expires <= now)
+    }
 }
 
 impl Pool {
     pub fn new(opts: Opts) -> Self {
+        Self::with_config(opts, PoolConfig::default())
+    }
+
+    /// Create a pool with connection lifetime and health check settings.
+    ///
+    /// Call [`spawn_reaper`](Self::spawn_reaper) to run the health checks in the background.
+    pub fn with_config(opts: Opts, config: PoolConfig) -> Self {
         let semaphore = opts
             .pool_max_concurrency
             .map(|n| Arc::new(Semaphore::new(n)));
+        let sizer = opts.pool_adaptive_sizing.clone().map(PoolSizer::new);
+        let capacity = sizer
+            .as_ref()
+            .map_or(opts.pool_max_idle_conn, PoolSizer::max_idle);
         Self {
-            conns: ArrayQueue::new(opts.pool_max_idle_conn),
-            opts,
-            semaphore,
+            conns: ArrayQueue::new(capacity.max(1)),
+            opts: RwLock::new(Arc::new(opts)),
+            generation: AtomicU64::new(0),
+            config,
+            semaphore: RwLock::new(semaphore),
+            sizer,
+            in_use: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            drained: Notify::new(),
+            server_shutdown: Notify::new(),
+        }
+    }
+
+    /// The options new connections are opened with.
+    pub fn opts(&self) -> Arc<Opts> {
+        Arc::clone(&self.opts.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Replace the options without recreating the pool.
+    ///
+    /// New connections are opened with `opts`, so a changed host, user, password or
+    /// `password_file` takes effect on the next connect. `pool_max_idle_conn`,
+    /// `pool_max_concurrency` and `pool_reset_conn` apply immediately; connections checked out
+    /// before the update do not count toward the new `pool_max_concurrency`.
+    /// `pool_adaptive_sizing` keeps the settings the pool was created with, and the idle queue
+    /// cannot grow beyond its initial capacity.
+    ///
+    /// Existing connections are phased out: they are closed when they reach
+    /// `PoolConfig::max_lifetime`, or when they are next returned or checked out if there is no
+    /// `max_lifetime`.
+    pub fn update_opts(&self, opts: Opts) {
+        let mut current = self.opts.write().unwrap_or_else(PoisonError::into_inner);
+        if opts.pool_max_concurrency != current.pool_max_concurrency {
+            *self
+                .semaphore
+                .write()
+                .unwrap_or_else(PoisonError::into_inner) = opts
+                .pool_max_concurrency
+                .map(|n| Arc::new(Semaphore::new(n)));
+        }
+        tracing::info!(host = %opts.host, "pool options updated");
+        *current = Arc::new(opts);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn config(&self) -> &PoolConfig {
+        &self.config
+    }
+
+    /// The number of idle connections the pool currently keeps.
+    ///
+    /// This changes over time when `Opts::pool_adaptive_sizing` is set.
+    pub fn idle_capacity(&self) -> usize {
+        self.sizer
+            .as_ref()
+            .map_or_else(|| self.opts().pool_max_idle_conn, PoolSizer::target)
+    }
+
+    /// The number of idle connections in the pool.
+    pub fn idle_count(&self) -> usize {
+        self.conns.len()
+    }
+
+    /// The number of connections currently checked out of the pool.
+    pub fn in_use_count(&self) -> usize {
+        self.in_use.load(Ordering::Relaxed)
+    }
+
+    /// Returns true after `close()` was called.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Close the pool for graceful shutdown.
+    ///
+    /// New `get()` calls fail, idle connections are closed with `COM_QUIT`,
+    /// and connections returned later are dropped instead of pooled.
+    /// Resolves once every checked-out connection has been returned.
+    /// Wrap it in `tokio::time::timeout` to bound the wait.
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        while let Some(idle) = self.conns.pop() {
+            let _ = idle.conn.close().await;
+        }
+        loop {
+            let drained = self.drained.notified();
+            if self.in_use_count() == 0 {
+                return;
+            }
+            drained.await;
         }
     }
 
     pub async fn get(self: &Arc<Self>) -> Result<PooledConn> {
-        let permit =
-            match &self.semaphore {
-                Some(sem) => Some(Arc::clone(sem).acquire_owned().await.map_err(
-                    |_acquire_err| {
-                        crate::error::Error::LibraryBug(color_eyre::eyre::eyre!("semaphore closed"))
-                    },
-                )?),
-                None => None,
+        if self.is_closed() {
+            return Err(crate::error::Error::BadUsageError(
+                "the pool is closed".to_string(),
+            ));
+        }
+        let started = Instant::now();
+        let semaphore = self
+            .semaphore
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let permit = match semaphore {
+            Some(sem) => Some(sem.acquire_owned().await.map_err(|_acquire_err| {
+                crate::error::Error::LibraryBug(color_eyre::eyre::eyre!("semaphore closed"))
+            })?),
+            None => None,
+        };
+        // An idle connection that fails the ping is dropped, e.g. after a server restart
+        let (conn, opened) = loop {
+            let Some(mut idle) = self.pop_idle() else {
+                break self.connect().await?;
             };
-        let mut conn = match self.conns.pop() {
-            Some(c) => c,
-            None => Conn::new(self.opts.clone()).await?,
+            if idle.conn.ping().await.is_ok() {
+                break (idle.conn, idle.opened);
+            }
         };
-        conn.ping().await?;
+        if let Some(sizer) = &self.sizer {
+            sizer.record_acquire(started.elapsed(), self.conns.len());
+        }
+        self.in_use.fetch_add(1, Ordering::Relaxed);
         Ok(PooledConn {
             conn: ManuallyDrop::new(conn),
+            opened,
             pool: Arc::clone(self),
             _permit: permit,
         })
     }
 
-    fn check_in(self: &Arc<Self>, mut conn: Conn) {
-        if conn.is_broken() {
+    /// Close the idle connections and phase out the checked-out ones, which are closed when they
+    /// are returned (unless `PoolConfig::max_lifetime` phases them out). New connections are
+    /// opened on demand.
+    ///
+    /// Called when a connection is returned after the server reported `ER_SERVER_SHUTDOWN`, so
+    /// that a restarted server does not see a storm of commands on dead connections.
+    pub fn drain_idle(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        while self.conns.pop().is_some() {}
+    }
+
+    /// Resolves the next time a connection is returned after `ER_SERVER_SHUTDOWN`
+    pub(crate) async fn server_shutdown(&self) {
+        self.server_shutdown.notified().await;
+    }
+
+    /// Pop an idle connection, dropping expired ones
+    fn pop_idle(&self) -> Option<IdleConn> {
+        let now = Instant::now();
+        while let Some(idle) = self.conns.pop() {
+            if !idle.is_expired(now) && !self.is_outdated(&idle.opened) {
+                return Some(idle);
+            }
+        }
+        None
+    }
+
+    /// The connection was opened before `update_opts()` and has no `max_lifetime` to phase it out
+    fn is_outdated(&self, opened: &Opened) -> bool {
+        opened.expires.is_none() && opened.generation != self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Open a connection and compute its `max_lifetime` deadline
+    async fn connect(&self) -> Result<(Conn, Opened)> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        let conn = Conn::new((*self.opts()).clone()).await?;
+        tracing::debug!(conn = %conn, "opened a pooled connection");
+        let expires = self
+            .config
+            .max_lifetime()
+            .map(|lifetime| Instant::now() + self.config.jittered(lifetime));
+        let autocommit = conn.autocommit();
+        Ok((
+            conn,
+            Opened {
+                expires,
+                autocommit,
+                generation,
+            },
+        ))
+    }
+
+    /// Return `conn` to the idle queue, or drop it if the queue is full or the pool is closed
+    fn push_idle(&self, conn: Conn, opened: Opened) {
+        let idle_expires = self
+            .config
+            .idle_timeout()
+            .map(|timeout| Instant::now() + self.config.jittered(timeout));
+        let _ = self.conns.push(IdleConn {
+            conn,
+            opened,
+            idle_expires,
+        });
+        self.drop_idle_if_closed();
+    }
+
+    /// `close()` may have emptied the idle queue between a caller's `is_closed()` check and its
+    /// push, so check again after pushing
+    fn drop_idle_if_closed(&self) {
+        if self.is_closed() {
+            while self.conns.pop().is_some() {}
+        }
+    }
+
+    /// Run one health check pass over the idle connections.
+    ///
+    /// Connections past `max_lifetime` or `idle_timeout` are closed, the others are pinged and
+    /// dropped if the ping fails. Then connections are opened until `min_idle` are idle.
+    pub async fn reap(&self) {
+        for _ in 0..self.conns.len() {
+            let Some(mut idle) = self.conns.pop() else {
+                break;
+            };
+            if idle.is_expired(Instant::now()) || self.is_outdated(&idle.opened) {
+                let _ = idle.conn.close().await;
+                continue;
+            }
+            if idle.conn.ping().await.is_ok() && !self.is_closed() {
+                let _ = self.conns.push(idle);
+                self.drop_idle_if_closed();
+            }
+        }
+
+        let min_idle = self.config.min_idle().min(self.idle_capacity());
+        while !self.is_closed() && self.conns.len() < min_idle {
+            match self.connect().await {
+                Ok((conn, opened)) => self.push_idle(conn, opened),
+                Err(err) => {
+                    tracing::warn!(host = %self.opts().host, error = %err, "failed to pre-warm the pool");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Call [`reap`](Self::reap) every `PoolConfig::health_check_interval`
+    /// until the task is aborted or the pool is dropped or closed.
+    pub fn spawn_reaper(self: &Arc<Self>) -> JoinHandle<()> {
+        let pool = Arc::downgrade(self);
+        let interval = self.config.health_check_interval();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(pool) = pool.upgrade() else {
+                    return;
+                };
+                if pool.is_closed() {
+                    return;
+                }
+                pool.reap().await;
+            }
+        })
+    }
+
+    /// Acquire a connection with `role` activated (`SET ROLE`).
+    ///
+    /// The role is reset when the connection is returned to the pool.
+    pub async fn get_with_role(self: &Arc<Self>, role: &str) -> Result<PooledConn> {
+        let mut conn = self.get().await?;
+        conn.set_role(role).await?;
+        Ok(conn)
+    }
+
+    /// Acquire a connection, execute a text protocol query, and discard the result.
+    pub async fn query_drop(self: &Arc<Self>, sql: &str) -> Result<()> {
+        self.get().await?.query_drop(sql).await
+    }
+
+    /// Acquire a connection, execute `sql` without parameters, and collect all rows.
+    ///
+    /// The statement is prepared and closed for this call only.
+    /// For repeated executions, prepare it on a connection from `get()` instead.
+    pub async fn query_rows<Row>(self: &Arc<Self>, sql: &str) -> Result<Vec<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+    {
+        self.exec_collect(sql, ()).await
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and discard the result.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub async fn exec_drop<P: Params>(self: &Arc<Self>, sql: &str, params: P) -> Result<()> {
+        let mut conn = self.get().await?;
+        let mut stmt = conn.prepare(sql).await?;
+        let result = conn.exec_drop(&mut stmt, params).await;
+        let closed = conn.close_statement(stmt).await;
+        result?;
+        closed
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and return the first row.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub async fn exec_first<Row, P>(self: &Arc<Self>, sql: &str, params: P) -> Result<Option<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+        P: Params,
+    {
+        let mut conn = self.get().await?;
+        let mut stmt = conn.prepare(sql).await?;
+        let result = conn.exec_first(&mut stmt, params).await;
+        let closed = conn.close_statement(stmt).await;
+        let value = result?;
+        closed?;
+        Ok(value)
+    }
+
+    /// Acquire a connection, execute `sql` with `params`, and collect all rows.
+    ///
+    /// The statement is prepared and closed for this call only.
+    pub async fn exec_collect<Row, P>(self: &Arc<Self>, sql: &str, params: P) -> Result<Vec<Row>>
+    where
+        Row: for<'buf> FromRow<'buf>,
+        P: Params,
+    {
+        let mut conn = self.get().await?;
+        let mut stmt = conn.prepare(sql).await?;
+        let result = conn.exec_collect(&mut stmt, params).await;
+        let closed = conn.close_statement(stmt).await;
+        let value = result?;
+        closed?;
+        Ok(value)
+    }
+
+    /// Acquire a connection and run `f` in a transaction on it.
+    ///
+    /// See `Conn::transaction`.
+    pub async fn run_transaction<F, R>(self: &Arc<Self>, f: F) -> Result<R>
+    where
+        F: AsyncFnOnce(Transaction<'_>) -> Result<R>,
+    {
+        self.get().await?.transaction(f).await
+    }
+
+    /// Scan `table` in `splits` primary key ranges on up to `concurrency` connections at once.
+    ///
+    /// `table` may be qualified with a schema (`db.table`). See [`super::scan`].
+    pub async fn parallel_scan<Row>(
+        self: &Arc<Self>,
+        table: &str,
+        splits: usize,
+        concurrency: usize,
+    ) -> Result<ParallelScan<Row>>
+    where
+        Row: for<'buf> FromRow<'buf> + Send + 'static,
+    {
+        super::scan::parallel_scan(self, table, splits, concurrency).await
+    }
+
+    fn check_in(self: &Arc<Self>, mut conn: Conn, opened: Opened) {
+        let in_use = self
+            .in_use
+            .fetch_sub(1, Ordering::Relaxed)
+            .saturating_sub(1);
+        if let Some(sizer) = &self.sizer {
+            sizer.record_release();
+        }
+        if self.is_closed() {
+            if in_use == 0 {
+                self.drained.notify_waiters();
+            }
+            return;
+        }
+        if conn.is_server_shutdown() {
+            tracing::warn!(conn = %conn, "server is shutting down, draining the pool");
+            self.drain_idle();
+            self.server_shutdown.notify_waiters();
             return;
         }
-        if self.opts.pool_reset_conn {
+        let reset = self.reset_on_return();
+        // Without a reset, a connection left inside a transaction cannot be reused
+        if conn.is_broken() || (reset == ResetOnReturn::None && conn.in_transaction()) {
+            return;
+        }
+        if opened.is_expired(Instant::now()) || self.is_outdated(&opened) {
+            return;
+        }
+        if self.conns.len() >= self.idle_capacity() {
+            return;
+        }
+        if reset != ResetOnReturn::None || conn.role_changed() {
             let Ok(handle) = tokio::runtime::Handle::try_current() else {
                 return;
             };
             let pool = Arc::clone(self);
             handle.spawn(async move {
-                if conn.reset().await.is_ok() {
-                    let _ = pool.conns.push(conn);
+                match pool.reset(&mut conn, reset, opened).await {
+                    Ok(()) if !pool.is_closed() => pool.push_idle(conn, opened),
+                    Ok(()) => {}
+                    Err(err) => {
+                        tracing::debug!(conn = %conn, error = %err, "dropping a connection that failed to reset");
+                    }
                 }
             });
+        } else if conn.autocommit() == opened.autocommit {
+            self.push_idle(conn, opened);
+        }
+    }
+
+    /// `PoolConfig::reset_on_return`, or `None` if `Opts::pool_reset_conn` is false
+    fn reset_on_return(&self) -> ResetOnReturn {
+        if self.opts().pool_reset_conn {
+            self.config.reset_on_return()
         } else {
-            let _ = self.conns.push(conn);
+            ResetOnReturn::None
         }
     }
+
+    /// Clean up a returned connection, failing if it cannot be reused
+    async fn reset(&self, conn: &mut Conn, reset: ResetOnReturn, opened: Opened) -> Result<()> {
+        if reset == ResetOnReturn::None {
+            conn.reset_role().await?;
+            return if conn.in_transaction() || conn.autocommit() != opened.autocommit {
+                Err(session_not_restored(conn))
+            } else {
+                Ok(())
+            };
+        }
+        // `Full` runs the init commands before the session setup, as connecting does
+        let opts = self.opts();
+        let init_commands = match reset {
+            ResetOnReturn::Full => opts.init_commands.as_slice(),
+            _ => &[],
+        };
+        if conn
+            .dialect()
+            .supports_reset_connection(conn.server_version())
+        {
+            conn.reset_with_init_commands(init_commands).await?;
+        } else {
+            conn.query_drop("ROLLBACK").await?;
+            if conn.role_changed() {
+                conn.reset_role().await?;
+            }
+            if reset == ResetOnReturn::Full {
+                conn.setup_session(init_commands).await?;
+            }
+        }
+        if conn.autocommit() != opened.autocommit {
+            conn.query_drop(if opened.autocommit {
+                "SET autocommit = 1"
+            } else {
+                "SET autocommit = 0"
+            })
+            .await?;
+        }
+        if reset == ResetOnReturn::Full {
+            let mut stmt = conn.prepare("SELECT @@autocommit").await?;
+            let selected = conn.exec_first::<(i64,), _>(&mut stmt, ()).await;
+            conn.close_statement(stmt).await?;
+            if selected? != Some((i64::from(opened.autocommit),)) {
+                return Err(session_not_restored(conn));
+            }
+        }
+        if conn.in_transaction() || conn.autocommit() != opened.autocommit {
+            return Err(session_not_restored(conn));
+        }
+        Ok(())
+    }
+}
+
+fn session_not_restored(conn: &Conn) -> crate::error::Error {
+    crate::error::Error::BadUsageError(format!(
+        "{conn}: the session was not restored (in_transaction={}, autocommit={})",
+        conn.in_transaction(),
+        conn.autocommit()
+    ))
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("idle", &self.idle_count())
+            .field("in_use", &self.in_use_count())
+            .field("idle_capacity", &self.idle_capacity())
+            .field("max_concurrency", &self.opts().pool_max_concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+/// `pool (2 in use, 3 idle, idle capacity 100)`
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pool ({} in use, {} idle, idle capacity {})",
+            self.in_use_count(),
+            self.idle_count(),
+            self.idle_capacity()
+        )
+    }
 }
 
 pub struct PooledConn {
     pool: Arc<Pool>,
     conn: ManuallyDrop<Conn>,
+    opened: Opened,
     _permit: Option<OwnedSemaphorePermit>,
 }
 
@@ -93,6 +607,6 @@ impl Drop for PooledConn {
     fn drop(&mut self) {
         // SAFETY: conn is never accessed after this
         let conn = unsafe { ManuallyDrop::take(&mut self.conn) };
-        self.pool.check_in(conn);
+        self.pool.check_in(conn, self.opened);
     }
 }
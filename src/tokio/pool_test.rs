@@ -0,0 +1,29 @@
+use crate::opts::Opts;
+use crate::test_macros::check_eq;
+use crate::tokio::Pool;
+
+#[test]
+fn update_opts_applies_to_the_pool() -> crate::error::Result<()> {
+    let pool = Pool::new(Opts {
+        host: "db-1".to_string(),
+        pool_max_idle_conn: 10,
+        pool_max_concurrency: Some(4),
+        ..Opts::default()
+    });
+    let before = pool.opts();
+
+    pool.update_opts(Opts {
+        host: "db-2".to_string(),
+        pool_max_idle_conn: 2,
+        pool_max_concurrency: None,
+        ..Opts::default()
+    });
+    check_eq!(before.host, "db-1");
+    check_eq!(pool.opts().host, "db-2");
+    check_eq!(pool.idle_capacity(), 2);
+    check_eq!(
+        format!("{pool:?}"),
+        "Pool { idle: 0, in_use: 0, idle_capacity: 2, max_concurrency: None, .. }"
+    );
+    Ok(())
+}
@@ -0,0 +1,685 @@
+//! Read/write split over a primary pool and replica pools.
+//!
+//! ```no_run
+//! # async fn run() -> zero_mysql::error::Result<()> {
+//! use std::time::Duration;
+//! use zero_mysql::Opts;
+//! use zero_mysql::tokio::routed::{Route, RoutedPool};
+//!
+//! let pool = RoutedPool::new(
+//!     Opts::try_from("mysql://primary.db")?,
+//!     vec![Opts::try_from("mysql://replica1.db")?, Opts::try_from("mysql://replica2.db")?],
+//! )
+//! .with_stale_read_tolerance(Duration::from_secs(1));
+//!
+//! pool.write().await?.query_drop("UPDATE t SET a = 1").await?;
+//! pool.read().await?.query_drop("SELECT * FROM t").await?;
+//!
+//! // classified: SELECT without locking clauses or session state goes to a replica
+//! let sql = "SELECT * FROM t";
+//! pool.route(sql).await?.query_drop(sql).await?;
+//!
+//! // per-query override
+//! pool.get(Route::Primary).await?.query_drop(sql).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Add `enforce_read_only=true` to the replica URLs to reject writes sent to a replica by
+//! mistake, e.g. with `get(Route::Replica)`, before they reach the server.
+//!
+//! # Replica lag
+//!
+//! With [`RoutedPool::with_max_replica_lag`], replicas lagging behind the primary by more than
+//! the threshold are taken out of rotation until they catch up.
+//! Lag is measured by [`RoutedPool::check_replica_lag`], which [`RoutedPool::spawn_lag_monitor`]
+//! calls periodically.
+//!
+//! ```no_run
+//! # async fn run() -> zero_mysql::error::Result<()> {
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use zero_mysql::Opts;
+//! use zero_mysql::tokio::routed::{LagSource, RoutedPool};
+//!
+//! let pool = Arc::new(
+//!     RoutedPool::new(Opts::try_from("mysql://primary.db")?, vec![Opts::try_from("mysql://replica1.db")?])
+//!         .with_max_replica_lag(Duration::from_secs(5))
+//!         // pt-heartbeat instead of SHOW REPLICA STATUS
+//!         .with_lag_source(LagSource::Query(
+//!             "SELECT TIMESTAMPDIFF(MICROSECOND, MAX(ts), UTC_TIMESTAMP(6)) / 1e6 FROM heartbeat.heartbeat".into(),
+//!         )),
+//! );
+//! let monitor = pool.spawn_lag_monitor(Duration::from_secs(1));
+//! for stats in pool.replica_stats() {
+//!     println!("{:?} in rotation: {}", stats.lag, stats.in_rotation);
+//! }
+//! monitor.abort();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Cluster topology
+//!
+//! For Group Replication and Galera clusters, [`RoutedPool::refresh_topology`] replaces the
+//! primary and replicas with the members the cluster reports, so new nodes and failovers are
+//! picked up without a restart. [`RoutedPool::spawn_topology_monitor`] calls it periodically,
+//! and right away when the primary reports that it is shutting down.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::classify::{StatementClass, classify_tokens};
+use crate::error::{Error, Result};
+use crate::opts::Opts;
+use crate::protocol::TextRowPayload;
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::primitive::read_string_lenenc;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::TextResultSetHandler;
+use crate::socket_stats::SocketStats;
+use crate::sql_lexer::{Token, TokenKind, tokenize};
+use crate::topology::{
+    GALERA_ADDRESSES_SQL, GROUP_MEMBERS_SQL, Member, MemberRole, TextRowsHandler, group_members,
+    parse_galera_addresses,
+};
+
+use super::{Pool, PooledConn};
+
+/// Where a connection is acquired from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    /// The primary. Use for writes and reads that must see the latest writes.
+    Primary,
+    /// A replica, or the primary if there are no replicas or the last write is too recent.
+    Replica,
+}
+
+/// How [`RoutedPool::check_replica_lag`] measures replica lag.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LagSource {
+    /// `Seconds_Behind_Source` of `SHOW REPLICA STATUS`, or `Seconds_Behind_Master` on MariaDB,
+    /// which keeps the old column name, and on older servers that only have `SHOW SLAVE STATUS`.
+    #[default]
+    ReplicaStatus,
+    /// A query returning the lag in seconds in the first column of the first row,
+    /// e.g. against a heartbeat table.
+    Query(String),
+}
+
+/// Lag and usage of a replica pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaStats {
+    pub host: String,
+    pub port: u16,
+    /// The last measured lag. `None` if not measured yet, replication is stopped, or the replica
+    /// could not be reached.
+    pub lag: Option<Duration>,
+    /// Whether [`RoutedPool::read`] routes to this replica.
+    pub in_rotation: bool,
+    pub idle: usize,
+    pub in_use: usize,
+    /// TCP statistics of the connection used for the last lag check.
+    /// Rising RTT or retransmits with steady lag point at the network rather than the replica.
+    pub socket: Option<SocketStats>,
+}
+
+struct Replica {
+    pool: Arc<Pool>,
+    /// The last measured lag in milliseconds, `u64::MAX` if unknown
+    lag_millis: AtomicU64,
+    in_rotation: AtomicBool,
+    socket_stats: Mutex<Option<SocketStats>>,
+}
+
+impl Replica {
+    fn new(opts: Opts) -> Self {
+        Self {
+            pool: Arc::new(Pool::new(opts)),
+            lag_millis: AtomicU64::new(u64::MAX),
+            in_rotation: AtomicBool::new(true),
+            socket_stats: Mutex::new(None),
+        }
+    }
+
+    fn is(&self, member: &Member) -> bool {
+        let opts = self.pool.opts();
+        opts.host == member.host && opts.port == member.port
+    }
+
+    fn lag(&self) -> Option<Duration> {
+        match self.lag_millis.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            millis => Some(Duration::from_millis(millis)),
+        }
+    }
+
+    fn record_lag(&self, lag: Option<Duration>, max_lag: Option<Duration>) {
+        let millis = lag.map_or(u64::MAX, |lag| {
+            u64::try_from(lag.as_millis()).unwrap_or(u64::MAX - 1)
+        });
+        self.lag_millis.store(millis, Ordering::Relaxed);
+        let in_rotation = within_max_lag(lag, max_lag);
+        let was_in_rotation = self.in_rotation.swap(in_rotation, Ordering::Relaxed);
+        if was_in_rotation && !in_rotation {
+            tracing::warn!(host = %self.pool.opts().host, ?lag, "replica taken out of rotation");
+        } else if !was_in_rotation && in_rotation {
+            tracing::info!(host = %self.pool.opts().host, ?lag, "replica back in rotation");
+        }
+    }
+}
+
+pub struct RoutedPool {
+    /// Options for pools of discovered members, with the host and port replaced
+    template: Opts,
+    primary: RwLock<Arc<Pool>>,
+    replicas: RwLock<Arc<[Arc<Replica>]>>,
+    next_replica: AtomicUsize,
+    stale_read_tolerance: Option<Duration>,
+    last_write: Mutex<Option<Instant>>,
+    max_replica_lag: Option<Duration>,
+    lag_source: LagSource,
+}
+
+impl RoutedPool {
+    pub fn new(primary: Opts, replicas: Vec<Opts>) -> Self {
+        Self {
+            template: primary.clone(),
+            primary: RwLock::new(Arc::new(Pool::new(primary))),
+            replicas: RwLock::new(
+                replicas
+                    .into_iter()
+                    .map(|opts| Arc::new(Replica::new(opts)))
+                    .collect(),
+            ),
+            next_replica: AtomicUsize::new(0),
+            stale_read_tolerance: None,
+            last_write: Mutex::new(None),
+            max_replica_lag: None,
+            lag_source: LagSource::default(),
+        }
+    }
+
+    /// Read from the primary for `tolerance` after each `write()` (read-your-writes).
+    ///
+    /// Without this, replica reads may not observe a write made just before.
+    pub fn with_stale_read_tolerance(mut self, tolerance: Duration) -> Self {
+        self.stale_read_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Take replicas out of rotation while their lag exceeds `max_lag` or is unknown.
+    ///
+    /// Lag is only updated by [`check_replica_lag`](Self::check_replica_lag).
+    /// All replicas are in rotation until the first check.
+    pub fn with_max_replica_lag(mut self, max_lag: Duration) -> Self {
+        self.max_replica_lag = Some(max_lag);
+        self
+    }
+
+    pub fn with_lag_source(mut self, source: LagSource) -> Self {
+        self.lag_source = source;
+        self
+    }
+
+    pub fn primary(&self) -> Arc<Pool> {
+        self.primary
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn replicas(&self) -> Vec<Arc<Pool>> {
+        self.replica_list()
+            .iter()
+            .map(|replica| Arc::clone(&replica.pool))
+            .collect()
+    }
+
+    fn replica_list(&self) -> Arc<[Arc<Replica>]> {
+        self.replicas
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Lag and usage of each replica, in the order given to [`new`](Self::new)
+    /// or discovered by [`refresh_topology`](Self::refresh_topology).
+    pub fn replica_stats(&self) -> Vec<ReplicaStats> {
+        self.replica_list()
+            .iter()
+            .map(|replica| ReplicaStats {
+                host: replica.pool.opts().host.clone(),
+                port: replica.pool.opts().port,
+                lag: replica.lag(),
+                in_rotation: replica.in_rotation.load(Ordering::Relaxed),
+                idle: replica.pool.idle_count(),
+                in_use: replica.pool.in_use_count(),
+                socket: *replica
+                    .socket_stats
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner),
+            })
+            .collect()
+    }
+
+    /// Acquire a connection to the primary and mark the start of a write.
+    pub async fn write(&self) -> Result<PooledConn> {
+        if let Ok(mut last_write) = self.last_write.lock() {
+            *last_write = Some(Instant::now());
+        }
+        self.primary().get().await
+    }
+
+    /// Acquire a connection to a replica in rotation, in round-robin order.
+    ///
+    /// Falls back to the primary if there are no replicas in rotation, if a write happened within
+    /// the stale read tolerance, or if no replica can be reached.
+    pub async fn read(&self) -> Result<PooledConn> {
+        let replicas = self.replica_list();
+        if replicas.is_empty() || self.within_stale_window() {
+            return self.primary().get().await;
+        }
+        let start = self.next_replica.fetch_add(1, Ordering::Relaxed);
+        for offset in 0..replicas.len() {
+            let replica = &replicas[(start + offset) % replicas.len()];
+            if !replica.in_rotation.load(Ordering::Relaxed) {
+                continue;
+            }
+            match replica.pool.get().await {
+                Ok(conn) => return Ok(conn),
+                Err(err) => tracing::warn!(error = %err, "replica unavailable"),
+            }
+        }
+        self.primary().get().await
+    }
+
+    pub async fn get(&self, route: Route) -> Result<PooledConn> {
+        match route {
+            Route::Primary => self.write().await,
+            Route::Replica => self.read().await,
+        }
+    }
+
+    /// Acquire a connection for `sql`, routed by [`route_for`].
+    pub async fn route(&self, sql: &str) -> Result<PooledConn> {
+        self.get(route_for(sql)).await
+    }
+
+    /// Measure the lag of every replica and update which replicas are in rotation.
+    ///
+    /// A replica that cannot be reached or reports no lag (replication stopped) has unknown lag.
+    pub async fn check_replica_lag(&self) {
+        for replica in self.replica_list().iter() {
+            let lag = match self.measure_lag(replica).await {
+                Ok(lag) => lag,
+                Err(err) => {
+                    let host = &replica.pool.opts().host;
+                    tracing::warn!(%host, error = %err, "failed to measure replica lag");
+                    None
+                }
+            };
+            replica.record_lag(lag, self.max_replica_lag);
+        }
+    }
+
+    /// Call [`check_replica_lag`](Self::check_replica_lag) every `interval`
+    /// until the task is aborted or the pool is dropped.
+    pub fn spawn_lag_monitor(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let pool = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(pool) = pool.upgrade() else {
+                    return;
+                };
+                pool.check_replica_lag().await;
+            }
+        })
+    }
+
+    /// Learn the cluster members from the primary and replace the primary and replicas with them.
+    ///
+    /// If the primary cannot be reached, the members are learned from a replica instead.
+    /// Reads `performance_schema.replication_group_members` (Group Replication),
+    /// falling back to `wsrep_incoming_addresses` (Galera).
+    /// Pools of members that are still present are kept. New members use the options of the
+    /// initial primary with the host and port replaced.
+    /// Nothing changes if no member or no writable member is found.
+    ///
+    /// Returns the discovered members.
+    pub async fn refresh_topology(&self) -> Result<Vec<Member>> {
+        let members = self.discover_members().await?;
+        let current = self.primary();
+        let current_member = Member {
+            host: current.opts().host.clone(),
+            port: current.opts().port,
+            role: MemberRole::Primary,
+        };
+        let Some((primary, replicas)) = plan_topology(&current_member, &members) else {
+            tracing::warn!(
+                members = members.len(),
+                "no writable cluster member found, keeping the current topology"
+            );
+            return Ok(members);
+        };
+
+        if primary != current_member {
+            tracing::warn!(host = %primary.host, port = primary.port, "cluster primary changed");
+            *self.primary.write().unwrap_or_else(PoisonError::into_inner) =
+                Arc::new(Pool::new(self.member_opts(&primary)));
+        }
+        let previous = self.replica_list();
+        let next: Arc<[Arc<Replica>]> = replicas
+            .iter()
+            .map(
+                |member| match previous.iter().find(|replica| replica.is(member)) {
+                    Some(replica) => Arc::clone(replica),
+                    None => Arc::new(Replica::new(self.member_opts(member))),
+                },
+            )
+            .collect();
+        *self
+            .replicas
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = next;
+        Ok(members)
+    }
+
+    /// Call [`refresh_topology`](Self::refresh_topology) every `interval`
+    /// until the task is aborted or the pool is dropped.
+    ///
+    /// The topology is also refreshed as soon as a connection to the primary fails with
+    /// `ER_SERVER_SHUTDOWN`, so a planned switchover is picked up without waiting for `interval`.
+    pub fn spawn_topology_monitor(
+        self: &Arc<Self>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let pool = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let Some(routed) = pool.upgrade() else {
+                    return;
+                };
+                if let Err(err) = routed.refresh_topology().await {
+                    tracing::warn!(error = %err, "failed to refresh cluster topology");
+                }
+                let primary = routed.primary();
+                drop(routed);
+                if tokio::time::timeout(interval, primary.server_shutdown())
+                    .await
+                    .is_ok()
+                {
+                    tracing::warn!(
+                        host = %primary.opts().host,
+                        "primary is shutting down, refreshing cluster topology"
+                    );
+                }
+            }
+        })
+    }
+
+    async fn discover_members(&self) -> Result<Vec<Member>> {
+        let mut conn = match self.primary().get().await {
+            Ok(conn) => conn,
+            // The primary may be down for a failover, the replicas know the new one
+            Err(err) => self.any_replica().await.ok_or(err)?,
+        };
+        let mut group = TextRowsHandler::default();
+        match conn.query(GROUP_MEMBERS_SQL, &mut group).await {
+            Ok(()) if !group.rows.is_empty() => return Ok(group_members(&group.rows)),
+            // not a Group Replication member, or the table does not exist (MariaDB)
+            Ok(()) | Err(Error::ServerError(_)) => {}
+            Err(err) => return Err(err),
+        }
+        let mut galera = TextRowsHandler::default();
+        conn.query(GALERA_ADDRESSES_SQL, &mut galera).await?;
+        Ok(match galera.rows.first().map(Vec::as_slice) {
+            Some([_, Some(addresses)]) => parse_galera_addresses(addresses, self.template.port),
+            _ => Vec::new(),
+        })
+    }
+
+    /// A connection to the first replica that can be reached
+    async fn any_replica(&self) -> Option<PooledConn> {
+        for replica in self.replica_list().iter() {
+            if let Ok(conn) = replica.pool.get().await {
+                return Some(conn);
+            }
+        }
+        None
+    }
+
+    fn member_opts(&self, member: &Member) -> Opts {
+        let mut opts = self.template.clone();
+        opts.host = member.host.clone();
+        opts.port = member.port;
+        opts.socket = None;
+        opts
+    }
+
+    async fn measure_lag(&self, replica: &Replica) -> Result<Option<Duration>> {
+        let mut conn = replica.pool.get().await?;
+        *replica
+            .socket_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = conn.socket_stats().ok().flatten();
+        match &self.lag_source {
+            LagSource::Query(sql) => {
+                let mut handler = LagHandler::new(&[]);
+                conn.query(sql, &mut handler).await?;
+                Ok(handler.lag)
+            }
+            LagSource::ReplicaStatus => {
+                let mut handler = LagHandler::new(SECONDS_BEHIND_COLUMNS);
+                match conn.query("SHOW REPLICA STATUS", &mut handler).await {
+                    Ok(()) => Ok(handler.lag),
+                    // MySQL before 8.0.22 and MariaDB before 10.5.1 have no SHOW REPLICA STATUS
+                    Err(Error::ServerError(_)) => {
+                        let mut fallback = LagHandler::new(SECONDS_BEHIND_COLUMNS);
+                        conn.query("SHOW SLAVE STATUS", &mut fallback).await?;
+                        Ok(fallback.lag)
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        }
+    }
+
+    fn within_stale_window(&self) -> bool {
+        let Some(tolerance) = self.stale_read_tolerance else {
+            return false;
+        };
+        match self.last_write.lock() {
+            Ok(last_write) => last_write.is_some_and(|at| at.elapsed() < tolerance),
+            Err(_poisoned) => true,
+        }
+    }
+}
+
+impl fmt::Debug for RoutedPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoutedPool")
+            .field("primary", &self.primary())
+            .field("replicas", &self.replica_stats())
+            .field("max_replica_lag", &self.max_replica_lag)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Choose the primary and replicas among `members`.
+///
+/// The current primary is kept while it is a writable member (multi-primary clusters).
+/// All other members become replicas. Returns `None` if there is no writable member.
+pub(crate) fn plan_topology(current: &Member, members: &[Member]) -> Option<(Member, Vec<Member>)> {
+    let primary = members
+        .iter()
+        .filter(|member| member.role == MemberRole::Primary)
+        .find(|member| member.host == current.host && member.port == current.port)
+        .or_else(|| {
+            members
+                .iter()
+                .find(|member| member.role == MemberRole::Primary)
+        })?
+        .clone();
+    let replicas = members
+        .iter()
+        .filter(|member| member.host != primary.host || member.port != primary.port)
+        .cloned()
+        .collect();
+    Some((primary, replicas))
+}
+
+/// Whether a replica with `lag` stays in rotation. Unknown lag is only tolerated without a limit.
+pub(crate) fn within_max_lag(lag: Option<Duration>, max_lag: Option<Duration>) -> bool {
+    match max_lag {
+        None => true,
+        Some(max_lag) => lag.is_some_and(|lag| lag <= max_lag),
+    }
+}
+
+/// Parse a lag in seconds, e.g. `3` or `0.250000`.
+pub(crate) fn parse_lag_seconds(text: &[u8]) -> Option<Duration> {
+    let seconds: f64 = std::str::from_utf8(text).ok()?.trim().parse().ok()?;
+    Duration::try_from_secs_f64(seconds.max(0.0)).ok()
+}
+
+/// The lag column: `Seconds_Behind_Source` in `SHOW REPLICA STATUS` of MySQL 8.0.22+,
+/// `Seconds_Behind_Master` in that of MariaDB and in `SHOW SLAVE STATUS`
+pub(crate) const SECONDS_BEHIND_COLUMNS: &[&str] =
+    &["Seconds_Behind_Source", "Seconds_Behind_Master"];
+
+/// Reads the lag from the first row, in the first column named one of `columns`,
+/// or the first column if `columns` is empty.
+pub(crate) struct LagHandler {
+    columns: &'static [&'static str],
+    index: Option<usize>,
+    pub(crate) lag: Option<Duration>,
+}
+
+impl LagHandler {
+    pub(crate) fn new(columns: &'static [&'static str]) -> Self {
+        Self {
+            columns,
+            index: None,
+            lag: None,
+        }
+    }
+}
+
+impl TextResultSetHandler for LagHandler {
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+
+    fn resultset_start(&mut self, cols: &[ColumnDefinition<'_>]) -> Result<()> {
+        self.index = if self.columns.is_empty() {
+            Some(0)
+        } else {
+            cols.iter().position(|col| {
+                self.columns
+                    .iter()
+                    .any(|name| col.name_alias.eq_ignore_ascii_case(name.as_bytes()))
+            })
+        };
+        Ok(())
+    }
+
+    fn row(&mut self, _: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        let Some(index) = self.index.take() else {
+            return Ok(());
+        };
+        let mut rest = row.0;
+        for i in 0..=index {
+            // 0xFB indicates NULL value
+            if rest.first() == Some(&0xFB) {
+                if i == index {
+                    return Ok(());
+                }
+                rest = &rest[1..];
+                continue;
+            }
+            let (value, tail) = read_string_lenenc(rest)?;
+            if i == index {
+                self.lag = parse_lag_seconds(value);
+            }
+            rest = tail;
+        }
+        Ok(())
+    }
+
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Classify `sql` for routing.
+///
+/// Statements that [`classify`](crate::classify::classify) as [`StatementClass::Select`], e.g. `SELECT`, `SHOW`, `EXPLAIN`,
+/// `(SELECT ...)` and `WITH ... SELECT`, go to a replica unless they contain `FOR UPDATE`,
+/// `FOR SHARE`, `LOCK IN SHARE MODE`, or `INTO`, or depend on the session: user variables (`@x`),
+/// `LAST_INSERT_ID()`, `FOUND_ROWS()`, `ROW_COUNT()` and the named lock functions such as
+/// `GET_LOCK()`. A multi-statement query goes to a replica only if every statement does.
+/// Everything else goes to the primary.
+pub fn route_for(sql: &str) -> Route {
+    let tokens = tokenize(sql);
+    let mut statements = tokens
+        .split(|token| token.kind == TokenKind::Symbol && token.text == ";")
+        .filter(|statement| {
+            statement
+                .iter()
+                .any(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+        })
+        .peekable();
+    if statements.peek().is_none() {
+        return Route::Primary;
+    }
+    if statements.all(is_replica_read) {
+        Route::Replica
+    } else {
+        Route::Primary
+    }
+}
+
+/// Functions whose result depends on the state of the session that calls them
+const SESSION_FUNCTIONS: &[&str] = &[
+    "LAST_INSERT_ID",
+    "FOUND_ROWS",
+    "ROW_COUNT",
+    "GET_LOCK",
+    "RELEASE_LOCK",
+    "RELEASE_ALL_LOCKS",
+    "IS_FREE_LOCK",
+    "IS_USED_LOCK",
+];
+
+/// Whether one statement of a query reads without locking or depending on the session
+fn is_replica_read(statement: &[Token<'_>]) -> bool {
+    if classify_tokens(statement) != StatementClass::Select {
+        return false;
+    }
+    let words = statement
+        .iter()
+        .filter(|token| token.kind == TokenKind::Word)
+        .map(|token| token.text);
+    let mut prev = "";
+    for word in words {
+        let locking = (prev.eq_ignore_ascii_case("FOR")
+            && (word.eq_ignore_ascii_case("UPDATE") || word.eq_ignore_ascii_case("SHARE")))
+            || (prev.eq_ignore_ascii_case("SHARE") && word.eq_ignore_ascii_case("MODE"))
+            || word.eq_ignore_ascii_case("INTO");
+        let session = (word.starts_with('@') && !word.starts_with("@@"))
+            || SESSION_FUNCTIONS
+                .iter()
+                .any(|function| word.eq_ignore_ascii_case(function));
+        if locking || session {
+            return false;
+        }
+        prev = word;
+    }
+    true
+}
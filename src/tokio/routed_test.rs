@@ -0,0 +1,178 @@
+use zerocopy::FromBytes;
+
+use crate::protocol::TextRowPayload;
+use crate::protocol::command::{ColumnDefinition, ColumnDefinitionTail};
+use crate::protocol::r#trait::TextResultSetHandler;
+use crate::test_macros::check;
+use crate::test_macros::check_eq;
+use crate::tokio::routed::{
+    LagHandler, Route, SECONDS_BEHIND_COLUMNS, parse_lag_seconds, plan_topology, route_for,
+    within_max_lag,
+};
+use crate::topology::{Member, MemberRole};
+use std::time::Duration;
+
+#[test]
+fn reads_go_to_replicas() -> crate::error::Result<()> {
+    for sql in [
+        "SELECT 1",
+        "  /* tag */ select * from t where a = 'for update'",
+        "(SELECT a FROM t) UNION (SELECT a FROM u)",
+        "SHOW TABLES",
+        "EXPLAIN SELECT * FROM t",
+        "WITH a AS (SELECT 1) SELECT * FROM a",
+        "SELECT 1; SELECT 2;",
+        "SELECT @@version",
+        "SELECT 'LAST_INSERT_ID()', '@x'",
+    ] {
+        check_eq!(route_for(sql), Route::Replica, "{}", sql);
+    }
+    Ok(())
+}
+
+#[test]
+fn writes_and_locking_reads_go_to_primary() -> crate::error::Result<()> {
+    for sql in [
+        "",
+        "INSERT INTO t VALUES (1)",
+        "UPDATE t SET a = 1",
+        "SELECT * FROM t FOR UPDATE",
+        "SELECT * FROM t FOR SHARE",
+        "SELECT * FROM t LOCK IN SHARE MODE",
+        "SELECT a INTO @x FROM t",
+        "SET @x = 1",
+        "WITH a AS (SELECT 1) DELETE FROM t",
+        ";",
+    ] {
+        check_eq!(route_for(sql), Route::Primary, "{}", sql);
+    }
+    Ok(())
+}
+
+#[test]
+fn multi_statement_queries_go_to_primary_unless_all_read() -> crate::error::Result<()> {
+    for sql in [
+        "SELECT 1; DELETE FROM t",
+        "SELECT 1; SELECT * FROM t FOR UPDATE",
+        "/* a */ SELECT 1 ; UPDATE t SET a = 1;",
+    ] {
+        check_eq!(route_for(sql), Route::Primary, "{}", sql);
+    }
+    Ok(())
+}
+
+#[test]
+fn session_dependent_reads_go_to_primary() -> crate::error::Result<()> {
+    for sql in [
+        "SELECT LAST_INSERT_ID()",
+        "SELECT FOUND_ROWS()",
+        "select row_count()",
+        "SELECT GET_LOCK('job', 10)",
+        "SELECT RELEASE_LOCK('job')",
+        "SELECT * FROM t WHERE id = @last_id",
+        "SELECT @x",
+        "SELECT 1; SELECT LAST_INSERT_ID()",
+    ] {
+        check_eq!(route_for(sql), Route::Primary, "{}", sql);
+    }
+    Ok(())
+}
+
+#[test]
+fn parses_lag_seconds() -> crate::error::Result<()> {
+    check_eq!(parse_lag_seconds(b"3"), Some(Duration::from_secs(3)));
+    check_eq!(
+        parse_lag_seconds(b"0.250000"),
+        Some(Duration::from_millis(250))
+    );
+    check_eq!(parse_lag_seconds(b"-0.001"), Some(Duration::ZERO));
+    check_eq!(parse_lag_seconds(b""), None);
+    check_eq!(parse_lag_seconds(b"NULL"), None);
+    Ok(())
+}
+
+fn varchar_column<'a>(name: &'a [u8], tail: &'a ColumnDefinitionTail) -> ColumnDefinition<'a> {
+    ColumnDefinition {
+        schema: b"",
+        table_alias: b"",
+        table_original: b"",
+        name_alias: name,
+        name_original: name,
+        tail,
+        name_id: None,
+    }
+}
+
+/// `SHOW REPLICA STATUS` of MariaDB 10.5.1+ keeps the `Seconds_Behind_Master` column
+#[test]
+fn lag_is_read_from_seconds_behind_master() -> crate::error::Result<()> {
+    let tail = [0x2D, 0x00, 0, 0, 0, 0, 0xFD, 0, 0, 0, 0, 0];
+    let tail = ColumnDefinitionTail::ref_from_bytes(&tail)?;
+    for name in [&b"Seconds_Behind_Master"[..], b"Seconds_Behind_Source"] {
+        let cols = [
+            varchar_column(b"Slave_IO_State", tail),
+            varchar_column(name, tail),
+        ];
+        let mut handler = LagHandler::new(SECONDS_BEHIND_COLUMNS);
+        handler.resultset_start(&cols)?;
+        handler.row(&cols, TextRowPayload(b"\x07Waiting\x013"))?;
+        check_eq!(handler.lag, Some(Duration::from_secs(3)));
+    }
+    Ok(())
+}
+
+#[test]
+fn lagging_replicas_leave_rotation() -> crate::error::Result<()> {
+    let max = Some(Duration::from_secs(5));
+    check_eq!(within_max_lag(Some(Duration::from_secs(5)), max), true);
+    check_eq!(within_max_lag(Some(Duration::from_secs(6)), max), false);
+    check_eq!(within_max_lag(None, max), false);
+    check_eq!(within_max_lag(None, None), true);
+    check_eq!(within_max_lag(Some(Duration::from_secs(600)), None), true);
+    Ok(())
+}
+
+fn member(host: &str, role: MemberRole) -> Member {
+    Member {
+        host: host.to_string(),
+        port: 3306,
+        role,
+    }
+}
+
+#[test]
+fn topology_follows_the_writable_member() -> crate::error::Result<()> {
+    let current = member("db1", MemberRole::Primary);
+    let failed_over = [
+        member("db1", MemberRole::Secondary),
+        member("db2", MemberRole::Primary),
+        member("db3", MemberRole::Secondary),
+    ];
+    check_eq!(
+        plan_topology(&current, &failed_over),
+        Some((
+            member("db2", MemberRole::Primary),
+            vec![
+                member("db1", MemberRole::Secondary),
+                member("db3", MemberRole::Secondary),
+            ]
+        ))
+    );
+
+    // multi-primary: keep the current primary
+    let galera = [
+        member("db3", MemberRole::Primary),
+        member("db1", MemberRole::Primary),
+    ];
+    check_eq!(
+        plan_topology(&current, &galera),
+        Some((
+            member("db1", MemberRole::Primary),
+            vec![member("db3", MemberRole::Primary)]
+        ))
+    );
+
+    check!(plan_topology(&current, &[member("db2", MemberRole::Secondary)]).is_none());
+    check!(plan_topology(&current, &[]).is_none());
+    Ok(())
+}
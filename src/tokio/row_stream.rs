@@ -0,0 +1,183 @@
+//! Pull-based rows of a prepared statement.
+//!
+//! `Conn::exec_stream()` returns a [`RowStream`] that reads and decodes one row per
+//! `poll_next()`, for callers that prefer `while let Some(row) = stream.next().await`
+//! over a push-based result set handler.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures_core::Stream;
+
+use crate::PreparedStatement;
+use crate::error::Result;
+use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::command::prepared::ExecCursor;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::BinaryResultSetHandler;
+use crate::raw::FromRow;
+
+use super::Conn;
+
+/// Holds the row decoded by the last step of a streamed `Exec`
+pub(super) struct RowSlot<Row> {
+    pub(super) row: Option<Result<Row>>,
+}
+
+impl<Row: for<'buf> FromRow<'buf>> BinaryResultSetHandler for RowSlot<Row> {
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+    // Never fails, so that the state machine moves past a row that does not decode
+    fn row(&mut self, cols: &[ColumnDefinition<'_>], row: BinaryRowPayload<'_>) -> Result<()> {
+        self.row = Some(Row::from_row(cols, row));
+        Ok(())
+    }
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The connection and statement of an unfinished stream
+struct Parts<'c> {
+    conn: &'c mut Conn,
+    stmt: &'c mut PreparedStatement,
+    cursor: ExecCursor,
+    started: Option<Instant>,
+}
+
+impl<'c> Parts<'c> {
+    /// Read the next row. Returns `None` parts once the response is finished.
+    async fn next<Row: for<'buf> FromRow<'buf>>(self) -> (Option<Self>, Option<Result<Row>>) {
+        let Self {
+            conn,
+            stmt,
+            cursor,
+            started,
+        } = self;
+        let (cursor, item) = conn.next_streamed_row(stmt, cursor, started).await;
+        let parts = cursor.map(|cursor| Self {
+            conn,
+            stmt,
+            cursor,
+            started,
+        });
+        (parts, item)
+    }
+}
+
+type NextRow<'c, Row> =
+    Pin<Box<dyn Future<Output = (Option<Parts<'c>>, Option<Result<Row>>)> + Send + 'c>>;
+
+enum State<'c, Row> {
+    Idle(Parts<'c>),
+    Reading(NextRow<'c, Row>),
+    Done,
+}
+
+/// The rows of `Conn::exec_stream()`, decoded lazily as they are polled.
+///
+/// A row that does not decode is returned as an error and the stream continues with the next row.
+/// An error reading the response ends the stream.
+///
+/// The connection is marked broken until the stream is read to the end. Call
+/// [`finish`](Self::finish) to skip the remaining rows and keep the connection usable.
+///
+/// `size_hint()` has an upper bound if the statement has a `LIMIT`
+/// (see [`PreparedStatement::max_rows`]).
+pub struct RowStream<'c, Row> {
+    state: State<'c, Row>,
+    /// Rows left until `PreparedStatement::max_rows`
+    remaining: Option<u64>,
+}
+
+impl<'c, Row> RowStream<'c, Row>
+where
+    Row: for<'buf> FromRow<'buf> + Send + 'c,
+{
+    pub(super) fn new(
+        conn: &'c mut Conn,
+        stmt: &'c mut PreparedStatement,
+        cursor: ExecCursor,
+        started: Option<Instant>,
+    ) -> Self {
+        Self {
+            remaining: stmt.max_rows(),
+            state: State::Idle(Parts {
+                conn,
+                stmt,
+                cursor,
+                started,
+            }),
+        }
+    }
+
+    /// The next row, or `None` at the end of the result set.
+    pub async fn next(&mut self) -> Option<Result<Row>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Read the remaining rows without decoding them into the caller, and return the first error.
+    pub async fn finish(mut self) -> Result<()> {
+        let mut first_error = None;
+        while let Some(item) = self.next().await {
+            if let Err(err) = item {
+                first_error.get_or_insert(err);
+            }
+        }
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'c, Row> Stream for RowStream<'c, Row>
+where
+    Row: for<'buf> FromRow<'buf> + Send + 'c,
+{
+    type Item = Result<Row>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match std::mem::replace(&mut this.state, State::Done) {
+                State::Idle(parts) => this.state = State::Reading(Box::pin(parts.next())),
+                State::Reading(mut next_row) => {
+                    let Poll::Ready((parts, item)) = next_row.as_mut().poll(cx) else {
+                        this.state = State::Reading(next_row);
+                        return Poll::Pending;
+                    };
+                    if let Some(parts) = parts {
+                        this.state = State::Idle(parts);
+                    }
+                    if item.is_some() {
+                        this.remaining =
+                            this.remaining.map(|remaining| remaining.saturating_sub(1));
+                    }
+                    if item.is_some() || matches!(this.state, State::Done) {
+                        return Poll::Ready(item);
+                    }
+                }
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.state {
+            State::Done => (0, Some(0)),
+            _ => (
+                0,
+                self.remaining
+                    .map(|remaining| usize::try_from(remaining).unwrap_or(usize::MAX)),
+            ),
+        }
+    }
+}
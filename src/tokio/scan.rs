@@ -0,0 +1,225 @@
+//! Parallel full-table scans partitioned by primary key range.
+//!
+//! [`Pool::parallel_scan`](super::Pool::parallel_scan) looks up the leading primary key column of the table, reads its
+//! `MIN` and `MAX`, and splits that range into partitions of equal width. Up to `concurrency`
+//! tasks scan the partitions on their own pooled connections, and the rows of all partitions are
+//! merged into one [`ParallelScan`] in arrival order.
+//!
+//! ```no_run
+//! # async fn run(pool: std::sync::Arc<zero_mysql::tokio::Pool>) -> zero_mysql::error::Result<()> {
+//! let mut scan = pool.parallel_scan::<(i64, String)>("users", 16, 4).await?;
+//! while let Some(row) = scan.next().await {
+//!     let (id, name) = row?;
+//!     println!("{id} {name}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The leading primary key column must be an integer. Partitions are planned from `MIN`/`MAX`
+//! only, so a skewed key distribution gives partitions of unequal size. The partitions are not
+//! read from one snapshot; rows written during the scan may or may not be seen.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tokio::sync::mpsc;
+
+use crate::error::{Error, Result};
+use crate::handler::FirstHandler;
+use crate::protocol::BinaryRowPayload;
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::AsyncBinaryResultSetHandler;
+use crate::quote::quote_identifier;
+use crate::raw::FromRow;
+
+use super::Pool;
+
+/// The leading primary key column of a table and whether it is an integer
+const PRIMARY_KEY_SQL: &str = "SELECT k.COLUMN_NAME, c.DATA_TYPE \
+     FROM information_schema.KEY_COLUMN_USAGE k \
+     JOIN information_schema.COLUMNS c ON c.TABLE_SCHEMA = k.TABLE_SCHEMA \
+     AND c.TABLE_NAME = k.TABLE_NAME AND c.COLUMN_NAME = k.COLUMN_NAME \
+     WHERE k.TABLE_SCHEMA = COALESCE(?, DATABASE()) AND k.TABLE_NAME = ? \
+     AND k.CONSTRAINT_NAME = 'PRIMARY' AND k.ORDINAL_POSITION = 1";
+
+const INTEGER_TYPES: &[&str] = &["tinyint", "smallint", "mediumint", "int", "bigint"];
+
+/// Rows per partition buffered ahead of the consumer
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Split `min..=max` into at most `splits` inclusive ranges of equal width.
+pub(crate) fn split_range(min: i64, max: i64, splits: usize) -> Vec<(i64, i64)> {
+    if min > max {
+        return Vec::new();
+    }
+    let width = i128::from(max) - i128::from(min) + 1;
+    let splits = (splits.max(1) as i128).min(width);
+    (0..splits)
+        .map(|i| {
+            let start = i128::from(min) + width * i / splits;
+            let end = i128::from(min) + width * (i + 1) / splits - 1;
+            // Both bounds lie within min..=max
+            (start as i64, end as i64)
+        })
+        .collect()
+}
+
+/// The rows of a [`Pool::parallel_scan`](super::Pool::parallel_scan), in arrival order.
+pub struct ParallelScan<Row> {
+    rows: mpsc::Receiver<Result<Row>>,
+    partitions: Vec<(i64, i64)>,
+}
+
+impl<Row> ParallelScan<Row> {
+    /// The next row, or `None` once every partition was scanned.
+    ///
+    /// After an error, the failed partition is abandoned; the other partitions continue.
+    pub async fn next(&mut self) -> Option<Result<Row>> {
+        self.rows.recv().await
+    }
+
+    /// The inclusive primary key ranges of the partitions
+    pub fn partitions(&self) -> &[(i64, i64)] {
+        &self.partitions
+    }
+}
+
+/// Forwards each decoded row to the scan's channel
+struct ChannelHandler<Row> {
+    rows: mpsc::Sender<Result<Row>>,
+}
+
+impl<Row> AsyncBinaryResultSetHandler for ChannelHandler<Row>
+where
+    Row: for<'buf> FromRow<'buf> + Send,
+{
+    async fn no_result_set(&mut self, _: OkPayloadBytes<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn row(
+        &mut self,
+        cols: &[ColumnDefinition<'_>],
+        row: BinaryRowPayload<'_>,
+    ) -> Result<()> {
+        let row = Row::from_row(cols, row)?;
+        self.rows
+            .send(Ok(row))
+            .await
+            .map_err(|_closed| Error::BadUsageError("the parallel scan was dropped".to_string()))
+    }
+
+    async fn resultset_end(&mut self, _: OkPayloadBytes<'_>) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub(crate) async fn parallel_scan<Row>(
+    pool: &Arc<Pool>,
+    table: &str,
+    splits: usize,
+    concurrency: usize,
+) -> Result<ParallelScan<Row>>
+where
+    Row: for<'buf> FromRow<'buf> + Send + 'static,
+{
+    let (schema, name) = match table.split_once('.') {
+        Some((schema, name)) => (Some(schema), name),
+        None => (None, table),
+    };
+    let quoted = match schema {
+        Some(schema) => format!("{}.{}", quote_identifier(schema), quote_identifier(name)),
+        None => quote_identifier(name),
+    };
+
+    let mut conn = pool.get().await?;
+    let mut stmt = conn.prepare(PRIMARY_KEY_SQL).await?;
+    let mut key = FirstHandler::<(String, String)>::default();
+    let found = conn.exec(&mut stmt, (schema, name), &mut key).await;
+    conn.close_statement(stmt).await?;
+    found?;
+    let Some((column, data_type)) = key.take() else {
+        return Err(Error::BadUsageError(format!(
+            "parallel_scan: {table} has no primary key"
+        )));
+    };
+    if !INTEGER_TYPES
+        .iter()
+        .any(|integer| data_type.eq_ignore_ascii_case(integer))
+    {
+        return Err(Error::BadUsageError(format!(
+            "parallel_scan: the primary key {column} of {table} is not an integer"
+        )));
+    }
+    let column = quote_identifier(&column);
+
+    let mut bounds = conn
+        .prepare(&format!(
+            "SELECT MIN({column}), MAX({column}) FROM {quoted}"
+        ))
+        .await?;
+    let mut range = FirstHandler::<(Option<i64>, Option<i64>)>::default();
+    let scanned = conn.exec(&mut bounds, (), &mut range).await;
+    conn.close_statement(bounds).await?;
+    scanned?;
+    drop(conn);
+    let partitions = match range.take() {
+        Some((Some(min), Some(max))) => split_range(min, max, splits),
+        _ => Vec::new(),
+    };
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let queue = Arc::new(Mutex::new(
+        partitions.iter().copied().collect::<VecDeque<_>>(),
+    ));
+    let sql = Arc::<str>::from(format!(
+        "SELECT * FROM {quoted} WHERE {column} BETWEEN ? AND ?"
+    ));
+    for _ in 0..concurrency.max(1).min(partitions.len()) {
+        let pool = Arc::clone(pool);
+        let queue = Arc::clone(&queue);
+        let sql = Arc::clone(&sql);
+        let mut handler = ChannelHandler::<Row> { rows: tx.clone() };
+        tokio::spawn(async move {
+            if let Err(err) = scan_partitions(&pool, &sql, &queue, &mut handler).await {
+                let _ = handler.rows.send(Err(err)).await;
+            }
+        });
+    }
+    Ok(ParallelScan {
+        rows: rx,
+        partitions,
+    })
+}
+
+/// Scan partitions from `queue` on one connection until the queue is empty.
+async fn scan_partitions<Row>(
+    pool: &Arc<Pool>,
+    sql: &str,
+    queue: &Mutex<VecDeque<(i64, i64)>>,
+    handler: &mut ChannelHandler<Row>,
+) -> Result<()>
+where
+    Row: for<'buf> FromRow<'buf> + Send,
+{
+    let mut conn = pool.get().await?;
+    let mut stmt = conn.prepare(sql).await?;
+    loop {
+        let next = queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front();
+        let Some((start, end)) = next else {
+            break;
+        };
+        conn.exec_with_backpressure(&mut stmt, (start, end), handler)
+            .await?;
+    }
+    conn.close_statement(stmt).await
+}
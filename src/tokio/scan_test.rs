@@ -0,0 +1,22 @@
+use crate::test_macros::check_eq;
+use crate::tokio::scan::split_range;
+
+#[test]
+fn splits_cover_the_range() -> crate::error::Result<()> {
+    check_eq!(
+        split_range(1, 100, 4),
+        vec![(1, 25), (26, 50), (51, 75), (76, 100)]
+    );
+    check_eq!(split_range(1, 10, 3), vec![(1, 3), (4, 6), (7, 10)]);
+    check_eq!(split_range(5, 5, 8), vec![(5, 5)]);
+    check_eq!(split_range(1, 3, 0), vec![(1, 3)]);
+    check_eq!(split_range(3, 1, 4), vec![]);
+    Ok(())
+}
+
+#[test]
+fn splits_the_full_i64_range() -> crate::error::Result<()> {
+    let splits = split_range(i64::MIN, i64::MAX, 2);
+    check_eq!(splits, vec![(i64::MIN, -1), (0, i64::MAX)]);
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use crate::error::Result;
+use crate::test_macros::check_eq;
+use crate::tokio::serial::{Priority, Queue};
+
+#[test]
+fn higher_priority_runs_first() -> Result<()> {
+    let queue = Queue::default();
+    queue.push(Priority::Low, "low 1");
+    queue.push(Priority::Normal, "normal 1");
+    queue.push(Priority::Low, "low 2");
+    queue.push(Priority::High, "high 1");
+    queue.push(Priority::Normal, "normal 2");
+    check_eq!(queue.len(), 5);
+
+    let order: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+    check_eq!(
+        order,
+        vec!["high 1", "normal 1", "normal 2", "low 1", "low 2"]
+    );
+    check_eq!(queue.len(), 0);
+    Ok(())
+}
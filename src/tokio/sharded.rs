@@ -0,0 +1,176 @@
+//! Scatter-gather queries over a set of shards.
+//!
+//! [`ShardedPool::scatter`] runs one query on every shard concurrently and gathers the typed rows.
+//! [`group_by`] and [`hash_join`] then merge them on the client, covering aggregates and joins
+//! that span shards.
+//!
+//! ```no_run
+//! # async fn run() -> zero_mysql::error::Result<()> {
+//! use zero_mysql::Opts;
+//! use zero_mysql::tokio::sharded::{ShardedPool, hash_join};
+//!
+//! let pool = ShardedPool::new(vec![
+//!     Opts::try_from("mysql://shard0.db")?,
+//!     Opts::try_from("mysql://shard1.db")?,
+//! ]);
+//!
+//! // SELECT country, SUM(amount) ... GROUP BY country, across shards
+//! let totals = pool
+//!     .scatter_group_by(
+//!         "SELECT country, SUM(amount) FROM orders GROUP BY country",
+//!         (),
+//!         |(country, _): &(String, i64)| country.clone(),
+//!         |total: &mut i64, (_, amount)| *total += amount,
+//!     )
+//!     .await?;
+//!
+//! // users JOIN orders ON users.id = orders.user_id, across shards
+//! let users: Vec<(i64, String)> = pool.scatter("SELECT id, name FROM users", ()).await?;
+//! let orders: Vec<(i64, i64)> = pool.scatter("SELECT user_id, amount FROM orders", ()).await?;
+//! for ((_, name), (_, amount)) in hash_join(users, orders, |user| user.0, |order| order.0) {
+//!     println!("{name}: {amount}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use tokio::task::JoinSet;
+
+use crate::error::{Error, Result};
+use crate::opts::Opts;
+use crate::protocol::r#trait::param::Params;
+use crate::raw::FromRow;
+
+use super::Pool;
+
+/// One pool per shard.
+pub struct ShardedPool {
+    shards: Vec<Arc<Pool>>,
+}
+
+impl ShardedPool {
+    pub fn new(shards: Vec<Opts>) -> Self {
+        Self::from_pools(
+            shards
+                .into_iter()
+                .map(|opts| Arc::new(Pool::new(opts)))
+                .collect(),
+        )
+    }
+
+    pub fn from_pools(shards: Vec<Arc<Pool>>) -> Self {
+        Self { shards }
+    }
+
+    pub fn shards(&self) -> &[Arc<Pool>] {
+        &self.shards
+    }
+
+    /// The pool of shard `index`, for queries that target a single shard.
+    pub fn shard(&self, index: usize) -> Option<&Arc<Pool>> {
+        self.shards.get(index)
+    }
+
+    /// Execute `sql` with `params` on every shard concurrently and collect the rows.
+    ///
+    /// The rows are concatenated in shard order. Every shard is awaited before returning, so
+    /// no query is left running; if any shard fails, the first error in shard order is returned.
+    pub async fn scatter<Row, P>(&self, sql: &str, params: P) -> Result<Vec<Row>>
+    where
+        Row: for<'buf> FromRow<'buf> + Send + 'static,
+        P: Params + Clone + Send + 'static,
+    {
+        let sql = Arc::<str>::from(sql);
+        let mut tasks = JoinSet::new();
+        for (index, shard) in self.shards.iter().enumerate() {
+            let shard = Arc::clone(shard);
+            let sql = Arc::clone(&sql);
+            let params = params.clone();
+            tasks.spawn(async move { (index, shard.exec_collect::<Row, P>(&sql, params).await) });
+        }
+
+        let mut results = Vec::with_capacity(self.shards.len());
+        while let Some(joined) = tasks.join_next().await {
+            results.push(joined.map_err(|err| {
+                Error::LibraryBug(crate::error::eyre!("shard query task failed: {err}"))
+            })?);
+        }
+        results.sort_unstable_by_key(|(index, _)| *index);
+
+        let mut rows = Vec::new();
+        for (_, shard_rows) in results {
+            rows.extend(shard_rows?);
+        }
+        Ok(rows)
+    }
+
+    /// [`scatter`](Self::scatter) followed by [`group_by`].
+    pub async fn scatter_group_by<Row, P, K, V>(
+        &self,
+        sql: &str,
+        params: P,
+        key: impl Fn(&Row) -> K,
+        fold: impl FnMut(&mut V, Row),
+    ) -> Result<HashMap<K, V>>
+    where
+        Row: for<'buf> FromRow<'buf> + Send + 'static,
+        P: Params + Clone + Send + 'static,
+        K: Eq + Hash,
+        V: Default,
+    {
+        let rows = self.scatter(sql, params).await?;
+        Ok(group_by(rows, key, fold))
+    }
+}
+
+/// Group `rows` by `key`, folding each row into its group's accumulator, which starts at
+/// `V::default()`.
+///
+/// To merge per-shard aggregates, fold the partial aggregates again: sum `COUNT` and `SUM`,
+/// take the min of `MIN`, and so on. `AVG` must be computed from a per-shard `SUM` and `COUNT`.
+pub fn group_by<Row, K, V>(
+    rows: impl IntoIterator<Item = Row>,
+    key: impl Fn(&Row) -> K,
+    mut fold: impl FnMut(&mut V, Row),
+) -> HashMap<K, V>
+where
+    K: Eq + Hash,
+    V: Default,
+{
+    let mut groups = HashMap::new();
+    for row in rows {
+        fold(groups.entry(key(&row)).or_default(), row);
+    }
+    groups
+}
+
+/// Inner join `left` and `right` on equal keys with a hash table built from `left`.
+///
+/// The pairs are in `right` order; build the table from the smaller side.
+pub fn hash_join<L, R, K>(
+    left: impl IntoIterator<Item = L>,
+    right: impl IntoIterator<Item = R>,
+    left_key: impl Fn(&L) -> K,
+    right_key: impl Fn(&R) -> K,
+) -> Vec<(L, R)>
+where
+    L: Clone,
+    R: Clone,
+    K: Eq + Hash,
+{
+    let mut table: HashMap<K, Vec<L>> = HashMap::new();
+    for row in left {
+        table.entry(left_key(&row)).or_default().push(row);
+    }
+    let mut joined = Vec::new();
+    for row in right {
+        if let Some(matches) = table.get(&right_key(&row)) {
+            joined.extend(matches.iter().map(|matched| (matched.clone(), row.clone())));
+        }
+    }
+    joined
+}
@@ -0,0 +1,33 @@
+use crate::test_macros::check_eq;
+use crate::tokio::sharded::{group_by, hash_join};
+
+#[test]
+fn group_by_merges_partial_aggregates() -> crate::error::Result<()> {
+    // (country, count) from two shards
+    let rows = vec![("kr", 2), ("us", 5), ("kr", 3), ("jp", 1)];
+    let groups = group_by(
+        rows,
+        |(country, _)| *country,
+        |total: &mut i64, (_, count)| *total += count,
+    );
+    let mut totals = groups.into_iter().collect::<Vec<_>>();
+    totals.sort_unstable();
+    check_eq!(totals, vec![("jp", 1), ("kr", 5), ("us", 5)]);
+    Ok(())
+}
+
+#[test]
+fn hash_join_pairs_matching_keys() -> crate::error::Result<()> {
+    let users = vec![(1, "alice"), (2, "bob"), (3, "carol")];
+    let orders = vec![(2, 10), (1, 20), (2, 30), (4, 40)];
+    let joined = hash_join(users, orders, |user| user.0, |order| order.0);
+    check_eq!(
+        joined,
+        vec![
+            ((2, "bob"), (2, 10)),
+            ((1, "alice"), (1, 20)),
+            ((2, "bob"), (2, 30)),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use super::Conn;
+use crate::error::{Error, Result};
+use crate::protocol::r#trait::BinaryResultSetHandler;
+use crate::snapshot::{
+    BINARY_LOG_STATUS_SQL, BinlogPosition, LOCK_SQL, MARIADB_SNAPSHOT_STATUS_SQL,
+    MASTER_STATUS_SQL, START_SQL, UNLOCK_SQL, first_value, mariadb_gtid_pos_sql,
+    parse_mariadb_snapshot_status, parse_master_status, select_all_sql,
+};
+use crate::topology::TextRowsHandler;
+
+/// A read-only transaction with a consistent snapshot and its binlog position.
+///
+/// Created by [`Conn::start_snapshot`]. See [`crate::snapshot`].
+pub struct Snapshot<'conn> {
+    conn: &'conn mut Conn,
+    position: BinlogPosition,
+}
+
+impl<'conn> Snapshot<'conn> {
+    pub(crate) async fn start(conn: &'conn mut Conn) -> Result<Self> {
+        if conn.in_transaction() {
+            return Err(Error::NestedTransaction);
+        }
+        let position = if conn.is_mariadb() {
+            conn.query_drop(START_SQL).await?;
+            let mut status = TextRowsHandler::default();
+            conn.query(MARIADB_SNAPSHOT_STATUS_SQL, &mut status).await?;
+            let (file, position) = parse_mariadb_snapshot_status(&status.rows)?;
+            let mut gtid = TextRowsHandler::default();
+            conn.query(&mariadb_gtid_pos_sql(&file, position), &mut gtid)
+                .await?;
+            BinlogPosition {
+                file,
+                position,
+                gtid_set: first_value(&gtid.rows),
+            }
+        } else {
+            // Block commits while the snapshot and the position are taken
+            conn.query_drop(LOCK_SQL).await?;
+            let locked = match conn.query_drop(START_SQL).await {
+                Ok(()) => binlog_position(conn).await,
+                Err(e) => Err(e),
+            };
+            let unlocked = conn.query_drop(UNLOCK_SQL).await;
+            let position = locked?;
+            unlocked?;
+            position
+        };
+        Ok(Self { conn, position })
+    }
+
+    /// Binlog position of the snapshot
+    pub fn position(&self) -> &BinlogPosition {
+        &self.position
+    }
+
+    /// The connection, for custom queries within the snapshot
+    pub fn conn(&mut self) -> &mut Conn {
+        self.conn
+    }
+
+    /// Stream all rows of `table` (`name` or `schema.name`) to `handler`.
+    pub async fn read_table<H>(&mut self, table: &str, handler: &mut H) -> Result<()>
+    where
+        H: BinaryResultSetHandler,
+    {
+        let mut stmt = self.conn.prepare(&select_all_sql(table)).await?;
+        self.conn.exec(&mut stmt, (), handler).await?;
+        self.conn.close_statement(stmt).await
+    }
+
+    /// Call a closure for each row of `table` (`name` or `schema.name`).
+    ///
+    /// The closure can return an error to stop iteration early.
+    pub async fn read_table_foreach<Row, F>(&mut self, table: &str, f: F) -> Result<()>
+    where
+        Row: for<'buf> crate::raw::FromRow<'buf>,
+        F: FnMut(Row) -> Result<()>,
+    {
+        let mut handler = crate::handler::ForEachHandler::<Row, F>::new(f);
+        self.read_table(table, &mut handler).await
+    }
+
+    /// End the snapshot transaction and return its binlog position.
+    pub async fn finish(self) -> Result<BinlogPosition> {
+        self.conn.query_drop("COMMIT").await?;
+        Ok(self.position)
+    }
+}
+
+async fn binlog_position(conn: &mut Conn) -> Result<BinlogPosition> {
+    let mut status = TextRowsHandler::default();
+    match conn.query(BINARY_LOG_STATUS_SQL, &mut status).await {
+        // MySQL before 8.2
+        Err(Error::ServerError(_)) => {
+            status.rows.clear();
+            conn.query(MASTER_STATUS_SQL, &mut status).await?;
+        }
+        result => result?,
+    }
+    parse_master_status(&status.rows)
+}
@@ -1,43 +1,161 @@
 use core::mem::MaybeUninit;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 #[cfg(unix)]
 use tokio::net::UnixStream;
 
+use zerocopy::{FromZeros, IntoBytes};
+
+use crate::opts::DEFAULT_READ_BUFFER_SIZE;
+use crate::protocol::compression::{CompressedPacketHeader, CompressionStats, PacketCompression};
+use crate::socket_stats::SocketStats;
+
 #[cfg(feature = "tokio-tls")]
-use tokio_native_tls::TlsStream;
+use crate::tls::{Tls, TlsBackend};
 
-pub enum Stream {
+enum StreamInner {
     Tcp(BufReader<TcpStream>),
     #[cfg(feature = "tokio-tls")]
-    Tls(BufReader<TlsStream<TcpStream>>),
+    /// Boxed, rustls streams are over 1 KiB
+    Tls(BufReader<Box<<Tls as TlsBackend>::TokioStream>>),
     #[cfg(unix)]
     Unix(BufReader<UnixStream>),
 }
 
+impl StreamInner {
+    async fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(reader) => reader.read_exact(buf).await.map(|_| ()),
+            #[cfg(feature = "tokio-tls")]
+            Self::Tls(reader) => reader.read_exact(buf).await.map(|_| ()),
+            #[cfg(unix)]
+            Self::Unix(reader) => reader.read_exact(buf).await.map(|_| ()),
+        }
+    }
+
+    async fn read_buf_exact(&mut self, buf: &mut [MaybeUninit<u8>]) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(reader) => read_buf_exact_impl(reader, buf).await,
+            #[cfg(feature = "tokio-tls")]
+            Self::Tls(reader) => read_buf_exact_impl(reader, buf).await,
+            #[cfg(unix)]
+            Self::Unix(reader) => read_buf_exact_impl(reader, buf).await,
+        }
+    }
+
+    async fn read_buffered_payload(&mut self, buffer: &mut Vec<u8>) -> std::io::Result<Option<u8>> {
+        match self {
+            Self::Tcp(reader) => read_buffered_payload_impl(reader, buffer).await,
+            #[cfg(feature = "tokio-tls")]
+            Self::Tls(reader) => read_buffered_payload_impl(reader, buffer).await,
+            #[cfg(unix)]
+            Self::Unix(reader) => read_buffered_payload_impl(reader, buffer).await,
+        }
+    }
+
+    async fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(reader) => reader.get_mut().write_all(buf).await,
+            #[cfg(feature = "tokio-tls")]
+            Self::Tls(reader) => reader.get_mut().write_all(buf).await,
+            #[cfg(unix)]
+            Self::Unix(reader) => reader.get_mut().write_all(buf).await,
+        }
+    }
+
+    async fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(reader) => reader.get_mut().flush().await,
+            #[cfg(feature = "tokio-tls")]
+            Self::Tls(reader) => reader.get_mut().flush().await,
+            #[cfg(unix)]
+            Self::Unix(reader) => reader.get_mut().flush().await,
+        }
+    }
+
+    /// Read the next compressed packet into `compression`
+    async fn read_compressed(
+        &mut self,
+        compression: &mut PacketCompression,
+    ) -> std::io::Result<()> {
+        let mut header = CompressedPacketHeader::new_zeroed();
+        self.read_exact(header.as_mut_bytes()).await?;
+        self.read_exact(compression.body_mut(&header)).await?;
+        compression
+            .decode(&header)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+pub struct Stream {
+    inner: StreamInner,
+    /// `0` if reads go directly to the socket
+    read_buffer_size: usize,
+    /// Set after `CLIENT_COMPRESS` is negotiated
+    compression: Option<Box<PacketCompression>>,
+    /// Writes held back until the next flush, see [`Stream::write_deferred`]
+    pending: Vec<u8>,
+    /// Set `TCP_QUICKACK` after every flush
+    quickack: bool,
+    /// See [`Stream::set_timeouts`]
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
 impl Stream {
+    fn new(inner: StreamInner, read_buffer_size: usize) -> Self {
+        Self {
+            inner,
+            read_buffer_size,
+            compression: None,
+            pending: Vec::new(),
+            quickack: false,
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
     pub fn tcp(stream: TcpStream) -> Self {
-        Self::Tcp(BufReader::new(stream))
+        Self::tcp_with_capacity(stream, DEFAULT_READ_BUFFER_SIZE)
+    }
+
+    /// `read_buffer_size` of `0` reads directly from the socket.
+    pub fn tcp_with_capacity(stream: TcpStream, read_buffer_size: usize) -> Self {
+        Self::new(
+            StreamInner::Tcp(BufReader::with_capacity(read_buffer_size, stream)),
+            read_buffer_size,
+        )
     }
 
     #[cfg(unix)]
     pub fn unix(stream: UnixStream) -> Self {
-        Self::Unix(BufReader::new(stream))
+        Self::unix_with_capacity(stream, DEFAULT_READ_BUFFER_SIZE)
+    }
+
+    /// `read_buffer_size` of `0` reads directly from the socket.
+    #[cfg(unix)]
+    pub fn unix_with_capacity(stream: UnixStream, read_buffer_size: usize) -> Self {
+        Self::new(
+            StreamInner::Unix(BufReader::with_capacity(read_buffer_size, stream)),
+            read_buffer_size,
+        )
     }
 
     #[cfg(feature = "tokio-tls")]
     pub async fn upgrade_to_tls(self, host: &str) -> std::io::Result<Self> {
-        let tcp = match self {
-            Self::Tcp(buf_reader) => buf_reader.into_inner(),
+        let tcp = match self.inner {
+            StreamInner::Tcp(buf_reader) => buf_reader.into_inner(),
             #[cfg(feature = "tokio-tls")]
-            Self::Tls(_) => {
+            StreamInner::Tls(_) => {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::InvalidInput,
                     "Already using TLS",
                 ));
             }
             #[cfg(unix)]
-            Self::Unix(_) => {
+            StreamInner::Unix(_) => {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::InvalidInput,
                     "TLS not supported for Unix sockets",
@@ -45,77 +163,213 @@ impl Stream {
             }
         };
 
-        let connector = native_tls::TlsConnector::new().map_err(std::io::Error::other)?;
-        let connector = tokio_native_tls::TlsConnector::from(connector);
-        let tls_stream = connector
-            .connect(host, tcp)
-            .await
-            .map_err(std::io::Error::other)?;
+        let tls_stream = Box::new(Tls::connect_tokio(host, tcp).await?);
 
-        Ok(Self::Tls(BufReader::new(tls_stream)))
+        let mut stream = Self::new(
+            StreamInner::Tls(BufReader::with_capacity(self.read_buffer_size, tls_stream)),
+            self.read_buffer_size,
+        );
+        stream.set_timeouts(self.read_timeout, self.write_timeout);
+        Ok(stream)
     }
 
-    pub async fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
-        match self {
-            Self::Tcp(reader) => reader.read_exact(buf).await.map(|_| ()),
-            #[cfg(feature = "tokio-tls")]
-            Self::Tls(reader) => reader.read_exact(buf).await.map(|_| ()),
-            #[cfg(unix)]
-            Self::Unix(reader) => reader.read_exact(buf).await.map(|_| ()),
+    /// Wrap all subsequent packets in the compressed protocol.
+    pub fn enable_compression(&mut self, compression: PacketCompression) {
+        self.compression = Some(Box::new(compression));
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compression.is_some()
+    }
+
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.compression
+            .as_ref()
+            .map(|compression| compression.stats())
+    }
+
+    pub async fn read_exact(&mut self, mut buf: &mut [u8]) -> std::io::Result<()> {
+        let Some(compression) = &mut self.compression else {
+            return self.inner.read_exact(buf).await;
+        };
+        while !buf.is_empty() {
+            if compression.is_empty() {
+                self.inner.read_compressed(compression).await?;
+            }
+            let len = compression.read(buf);
+            buf = &mut buf[len..];
         }
+        Ok(())
     }
 
-    pub async fn read_buf_exact(&mut self, buf: &mut [MaybeUninit<u8>]) -> std::io::Result<()> {
-        match self {
-            Self::Tcp(reader) => read_buf_exact_impl(reader, buf).await,
-            #[cfg(feature = "tokio-tls")]
-            Self::Tls(reader) => read_buf_exact_impl(reader, buf).await,
-            #[cfg(unix)]
-            Self::Unix(reader) => read_buf_exact_impl(reader, buf).await,
+    pub async fn read_buf_exact(&mut self, mut buf: &mut [MaybeUninit<u8>]) -> std::io::Result<()> {
+        let Some(compression) = &mut self.compression else {
+            return self.inner.read_buf_exact(buf).await;
+        };
+        while !buf.is_empty() {
+            if compression.is_empty() {
+                self.inner.read_compressed(compression).await?;
+            }
+            let len = compression.read_uninit(buf);
+            buf = &mut buf[len..];
+        }
+        Ok(())
+    }
+
+    /// Read a whole packet from the read buffer, filling it first if it is empty.
+    ///
+    /// A small packet's header and payload arrive in the same read, so the header is parsed
+    /// in place and the payload is copied once.
+    /// Returns `None` without consuming anything if the connection is compressed or unbuffered,
+    /// or the packet is incomplete or split into 16MB chunks.
+    pub async fn read_buffered_payload(
+        &mut self,
+        buffer: &mut Vec<u8>,
+    ) -> std::io::Result<Option<u8>> {
+        if self.compression.is_some() || self.read_buffer_size == 0 {
+            return Ok(None);
         }
+        self.inner.read_buffered_payload(buffer).await
     }
 
     pub async fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
-        match self {
-            Self::Tcp(reader) => reader.get_mut().write_all(buf).await,
-            #[cfg(feature = "tokio-tls")]
-            Self::Tls(reader) => reader.get_mut().write_all(buf).await,
-            #[cfg(unix)]
-            Self::Unix(reader) => reader.get_mut().write_all(buf).await,
+        match &mut self.compression {
+            Some(compression) => {
+                compression.write(buf);
+                Ok(())
+            }
+            // keep the order of the deferred writes
+            None if !self.pending.is_empty() => {
+                self.pending.extend_from_slice(buf);
+                Ok(())
+            }
+            None => self.inner.write_all(buf).await,
+        }
+    }
+
+    /// Hold `buf` back until the next [`Stream::flush`], which writes everything held back at once.
+    pub fn write_deferred(&mut self, buf: &[u8]) {
+        match &mut self.compression {
+            Some(compression) => compression.write(buf),
+            None => self.pending.extend_from_slice(buf),
         }
     }
 
     pub async fn flush(&mut self) -> std::io::Result<()> {
-        match self {
-            Self::Tcp(reader) => reader.get_mut().flush().await,
-            #[cfg(feature = "tokio-tls")]
-            Self::Tls(reader) => reader.get_mut().flush().await,
-            #[cfg(unix)]
-            Self::Unix(reader) => reader.get_mut().flush().await,
+        if let Some(compression) = &mut self.compression {
+            let wire = compression
+                .encode()
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            self.inner.write_all(wire).await?;
+        } else if !self.pending.is_empty() {
+            let result = self.inner.write_all(&self.pending).await;
+            self.pending.clear();
+            result?;
+        }
+        self.inner.flush().await?;
+        if self.quickack {
+            self.enable_quickack()?;
         }
+        Ok(())
+    }
+
+    /// Set `TCP_QUICKACK` after every flush. Ignored on Unix sockets and platforms other than Linux.
+    pub fn set_quickack(&mut self, quickack: bool) {
+        self.quickack = quickack;
+    }
+
+    /// Limit the time the connection waits for a payload to be read or written.
+    /// `None` waits forever.
+    pub fn set_timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) {
+        self.read_timeout = read;
+        self.write_timeout = write;
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    fn enable_quickack(&self) -> std::io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+            let fd = match &self.inner {
+                StreamInner::Tcp(r) => r.get_ref().as_raw_fd(),
+                #[cfg(feature = "tokio-tls")]
+                StreamInner::Tls(r) => Tls::tokio_tcp(r.get_ref()).as_raw_fd(),
+                StreamInner::Unix(_) => return Ok(()),
+            };
+            crate::tcp_quickack::enable(fd)?;
+        }
+        Ok(())
     }
 
     /// Returns true if this is a TCP connection to a loopback address
     pub fn is_tcp_loopback(&self) -> bool {
-        match self {
-            Self::Tcp(r) => r
+        match &self.inner {
+            StreamInner::Tcp(r) => r
                 .get_ref()
                 .peer_addr()
                 .map(|addr| addr.ip().is_loopback())
                 .unwrap_or(false),
             #[cfg(feature = "tokio-tls")]
-            Self::Tls(r) => r
-                .get_ref()
-                .get_ref()
-                .get_ref()
-                .get_ref()
+            StreamInner::Tls(r) => Tls::tokio_tcp(r.get_ref())
                 .peer_addr()
                 .map(|addr| addr.ip().is_loopback())
                 .unwrap_or(false),
             #[cfg(unix)]
-            Self::Unix(_) => false,
+            StreamInner::Unix(_) => false,
         }
     }
+
+    /// The DER-encoded certificate of the server. `None` without TLS.
+    pub fn peer_certificate(&self) -> Option<Vec<u8>> {
+        #[cfg(feature = "tokio-tls")]
+        if let StreamInner::Tls(r) = &self.inner {
+            return Tls::tokio_peer_certificate(r.get_ref());
+        }
+        None
+    }
+
+    /// TCP statistics of the socket.
+    /// `None` for Unix sockets and on platforms other than Linux.
+    pub fn socket_stats(&self) -> std::io::Result<Option<SocketStats>> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::AsRawFd;
+            let fd = match &self.inner {
+                StreamInner::Tcp(r) => r.get_ref().as_raw_fd(),
+                #[cfg(feature = "tokio-tls")]
+                StreamInner::Tls(r) => Tls::tokio_tcp(r.get_ref()).as_raw_fd(),
+                StreamInner::Unix(_) => return Ok(None),
+            };
+            crate::socket_stats::tcp_socket_stats(fd).map(Some)
+        }
+        #[cfg(not(target_os = "linux"))]
+        Ok(None)
+    }
+}
+
+async fn read_buffered_payload_impl<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    buffer: &mut Vec<u8>,
+) -> std::io::Result<Option<u8>> {
+    let data = reader.fill_buf().await?;
+    let Some((header, rest)) = data.split_first_chunk::<4>() else {
+        return Ok(None);
+    };
+    let [l0, l1, l2, sequence_id] = *header;
+    let length = u32::from_le_bytes([l0, l1, l2, 0]) as usize;
+    let Some(payload) = rest.get(..length).filter(|_| length < 0xFFFFFF) else {
+        return Ok(None);
+    };
+    buffer.extend_from_slice(payload);
+    reader.consume(4 + length);
+    Ok(Some(sequence_id))
 }
 
 async fn read_buf_exact_impl<R: AsyncReadExt + Unpin>(
@@ -0,0 +1,122 @@
+//! Cluster membership discovery for MySQL Group Replication and Galera.
+//!
+//! Group Replication members are read from `performance_schema.replication_group_members`.
+//! Galera members are read from the `wsrep_incoming_addresses` status variable.
+//! Every Galera node accepts writes, so they are all reported as [`MemberRole::Primary`].
+
+use crate::error::Result;
+use crate::protocol::TextRowPayload;
+use crate::protocol::command::ColumnDefinition;
+use crate::protocol::primitive::read_string_lenenc;
+use crate::protocol::response::OkPayloadBytes;
+use crate::protocol::r#trait::TextResultSetHandler;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberRole {
+    /// Accepts writes
+    Primary,
+    /// Read-only
+    Secondary,
+}
+
+/// An online cluster member.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub host: String,
+    pub port: u16,
+    pub role: MemberRole,
+}
+
+pub(crate) const GROUP_MEMBERS_SQL: &str = "SELECT MEMBER_HOST, MEMBER_PORT, MEMBER_ROLE \
+     FROM performance_schema.replication_group_members WHERE MEMBER_STATE = 'ONLINE'";
+
+pub(crate) const GALERA_ADDRESSES_SQL: &str = "SHOW GLOBAL STATUS LIKE 'wsrep_incoming_addresses'";
+
+/// Parse the rows of [`GROUP_MEMBERS_SQL`].
+pub(crate) fn group_members(rows: &[Vec<Option<String>>]) -> Vec<Member> {
+    rows.iter()
+        .filter_map(|row| {
+            let [Some(host), Some(port), role] = row.as_slice() else {
+                return None;
+            };
+            Some(Member {
+                host: host.clone(),
+                port: port.parse().ok()?,
+                role: match role.as_deref() {
+                    Some("SECONDARY") => MemberRole::Secondary,
+                    _ => MemberRole::Primary,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Parse `wsrep_incoming_addresses`, e.g. `10.0.0.1:3306,[fe80::1]:3306`.
+///
+/// Addresses without a port use `default_port`. Unknown addresses (`AUTO`) are skipped.
+///
+/// ```
+/// use zero_mysql::topology::{MemberRole, parse_galera_addresses};
+///
+/// let members = parse_galera_addresses("10.0.0.1:3306,10.0.0.2:3307", 3306);
+/// assert_eq!(members[1].host, "10.0.0.2");
+/// assert_eq!(members[1].port, 3307);
+/// assert_eq!(members[1].role, MemberRole::Primary);
+/// ```
+pub fn parse_galera_addresses(addresses: &str, default_port: u16) -> Vec<Member> {
+    addresses
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty() && !address.eq_ignore_ascii_case("AUTO"))
+        .filter_map(|address| {
+            let (host, port) = match address.rsplit_once(':') {
+                Some((host, port)) if !host.ends_with(':') => (host, port.parse().ok()?),
+                _ => (address, default_port),
+            };
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            (!host.is_empty()).then(|| Member {
+                host: host.to_string(),
+                port,
+                role: MemberRole::Primary,
+            })
+        })
+        .collect()
+}
+
+/// Collects all rows of a text result set as strings.
+#[derive(Default)]
+pub(crate) struct TextRowsHandler {
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+impl TextResultSetHandler for TextRowsHandler {
+    fn no_result_set(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+
+    fn resultset_start(&mut self, _: &[ColumnDefinition<'_>]) -> Result<()> {
+        Ok(())
+    }
+
+    fn row(&mut self, cols: &[ColumnDefinition<'_>], row: TextRowPayload<'_>) -> Result<()> {
+        let mut rest = row.0;
+        let mut values = Vec::with_capacity(cols.len());
+        for _ in cols {
+            // 0xFB indicates NULL value
+            if let Some((&0xFB, tail)) = rest.split_first() {
+                values.push(None);
+                rest = tail;
+                continue;
+            }
+            let (value, tail) = read_string_lenenc(rest)?;
+            values.push(Some(String::from_utf8_lossy(value).into_owned()));
+            rest = tail;
+        }
+        self.rows.push(values);
+        Ok(())
+    }
+
+    fn resultset_end(&mut self, _: OkPayloadBytes) -> Result<()> {
+        Ok(())
+    }
+}
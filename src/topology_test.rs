@@ -0,0 +1,49 @@
+use crate::test_macros::check_eq;
+use crate::topology::{Member, MemberRole, group_members, parse_galera_addresses};
+
+fn member(host: &str, port: u16, role: MemberRole) -> Member {
+    Member {
+        host: host.to_string(),
+        port,
+        role,
+    }
+}
+
+#[test]
+fn group_replication_members() -> crate::error::Result<()> {
+    let rows = vec![
+        vec![
+            Some("db1".into()),
+            Some("3306".into()),
+            Some("PRIMARY".into()),
+        ],
+        vec![
+            Some("db2".into()),
+            Some("3306".into()),
+            Some("SECONDARY".into()),
+        ],
+        // MEMBER_HOST is NULL while a member is joining
+        vec![None, Some("3306".into()), Some("SECONDARY".into())],
+    ];
+    check_eq!(
+        group_members(&rows),
+        vec![
+            member("db1", 3306, MemberRole::Primary),
+            member("db2", 3306, MemberRole::Secondary),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn galera_addresses() -> crate::error::Result<()> {
+    check_eq!(
+        parse_galera_addresses("10.0.0.1:3306, [fe80::1]:3307,AUTO,,db3", 3306),
+        vec![
+            member("10.0.0.1", 3306, MemberRole::Primary),
+            member("fe80::1", 3307, MemberRole::Primary),
+            member("db3", 3306, MemberRole::Primary),
+        ]
+    );
+    Ok(())
+}
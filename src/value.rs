@@ -36,6 +36,33 @@ pub enum Value<'a> {
     Byte(&'a [u8]),
 }
 
+/// A BIT(n) value with bit 0 as the least significant bit.
+///
+/// The server sends BIT(n) as a big-endian bit string of `(n + 7) / 8` bytes, which
+/// `Value` keeps as `Byte`. Decoding to `BitValue`, `u64` or `bool` interprets those bytes.
+/// As a parameter, it is sent as a BIGINT UNSIGNED, which the server stores bit by bit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BitValue(pub u64);
+
+impl BitValue {
+    /// Whether bit `index` is set. Bits beyond 63 are never set.
+    pub fn bit(self, index: u32) -> bool {
+        self.0.checked_shr(index).is_some_and(|v| v & 1 == 1)
+    }
+}
+
+impl From<u64> for BitValue {
+    fn from(v: u64) -> Self {
+        Self(v)
+    }
+}
+
+impl From<BitValue> for u64 {
+    fn from(v: BitValue) -> Self {
+        v.0
+    }
+}
+
 // ============================================================================
 // Temporal Types
 // ============================================================================
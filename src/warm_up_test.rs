@@ -0,0 +1,31 @@
+use crate::test_macros::check_eq;
+use crate::warm_up::{StatementRecorder, format, parse};
+
+#[test]
+fn recorder_orders_by_prepares() -> crate::error::Result<()> {
+    let recorder = StatementRecorder::new();
+    recorder.record("SELECT 2");
+    recorder.record("SELECT 1");
+    recorder.clone().record("SELECT 2");
+    recorder.record("SELECT 3");
+    check_eq!(
+        recorder.statements(),
+        vec!["SELECT 2", "SELECT 1", "SELECT 3"]
+    );
+    Ok(())
+}
+
+#[test]
+fn file_round_trip() -> crate::error::Result<()> {
+    let statements = vec![
+        "SELECT 1".to_string(),
+        "SELECT *\nFROM t\r\nWHERE a = '\\n' AND b = ?".to_string(),
+        "# not a comment".to_string(),
+        " SELECT 2 ".to_string(),
+    ];
+    let text = format(&statements);
+    check_eq!(text.lines().count(), 5);
+    check_eq!(parse(&text), statements);
+    check_eq!(parse("# comment\n\nSELECT 1\\t\\\n"), vec!["SELECT 1\\t\\"]);
+    Ok(())
+}
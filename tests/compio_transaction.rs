@@ -56,11 +56,10 @@ async fn transaction_explicit_commit() -> Result<(), Error> {
     let mut conn = get_conn().await?;
     let table = TestTable::new(&mut conn).await?;
 
-    conn.transaction(async |conn1, tx| {
-        conn1
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
             .await?;
-        tx.commit(conn1).await
+        tx.commit().await
     })
     .await?;
 
@@ -74,11 +73,10 @@ async fn transaction_explicit_rollback() -> Result<(), Error> {
     let mut conn = get_conn().await?;
     let table = TestTable::new(&mut conn).await?;
 
-    conn.transaction(async |conn1, tx| {
-        conn1
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
             .await?;
-        tx.rollback(conn1).await
+        tx.rollback().await
     })
     .await?;
 
@@ -92,9 +90,8 @@ async fn transaction_implicit_commit_on_ok() -> Result<(), Error> {
     let mut conn = get_conn().await?;
     let table = TestTable::new(&mut conn).await?;
 
-    conn.transaction(async |conn1, _tx| {
-        conn1
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
             .await?;
         Ok(())
     })
@@ -111,9 +108,8 @@ async fn transaction_implicit_rollback_on_err() -> Result<(), Error> {
     let table = TestTable::new(&mut conn).await?;
 
     let result: Result<(), Error> = conn
-        .transaction(async |conn1, _tx| {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
+        .transaction(async |mut tx| {
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
                 .await?;
             Err(Error::BadUsageError("intentional error".into()))
         })
@@ -131,9 +127,8 @@ async fn transaction_implicit_commit_with_return_value() -> Result<(), Error> {
     let table = TestTable::new(&mut conn).await?;
 
     let result: i32 = conn
-        .transaction(async |conn1, _tx| {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
+        .transaction(async |mut tx| {
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))
                 .await?;
             Ok(123)
         })
@@ -150,14 +145,13 @@ async fn transaction_implicit_commit_multiple_inserts() -> Result<(), Error> {
     let mut conn = get_conn().await?;
     let table = TestTable::new(&mut conn).await?;
 
-    conn.transaction(async |conn1, _tx| {
+    conn.transaction(async |mut tx| {
         for i in 1..=5 {
-            conn1
-                .query_drop(&format!(
-                    "INSERT INTO {} (value) VALUES ({})",
-                    table.name, i
-                ))
-                .await?;
+            tx.query_drop(&format!(
+                "INSERT INTO {} (value) VALUES ({})",
+                table.name, i
+            ))
+            .await?;
         }
         Ok(())
     })
@@ -174,12 +168,10 @@ async fn transaction_implicit_rollback_partial_work() -> Result<(), Error> {
     let table = TestTable::new(&mut conn).await?;
 
     let result: Result<(), Error> = conn
-        .transaction(async |conn1, _tx| {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))
+        .transaction(async |mut tx| {
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))
                 .await?;
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))
                 .await?;
             Err(Error::BadUsageError("intentional error".into()))
         })
@@ -196,17 +188,15 @@ async fn transaction_connection_usable_after_implicit_commit() -> Result<(), Err
     let mut conn = get_conn().await?;
     let table = TestTable::new(&mut conn).await?;
 
-    conn.transaction(async |conn1, _tx| {
-        conn1
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))
             .await?;
         Ok(())
     })
     .await?;
 
-    conn.transaction(async |conn2, _tx| {
-        conn2
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))
             .await?;
         Ok(())
     })
@@ -223,17 +213,15 @@ async fn transaction_connection_usable_after_implicit_rollback() -> Result<(), E
     let table = TestTable::new(&mut conn).await?;
 
     let _: Result<(), Error> = conn
-        .transaction(async |conn1, _tx| {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))
+        .transaction(async |mut tx| {
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))
                 .await?;
             Err(Error::BadUsageError("intentional error".into()))
         })
         .await;
 
-    conn.transaction(async |conn2, _tx| {
-        conn2
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))
             .await?;
         Ok(())
     })
@@ -248,8 +236,8 @@ async fn transaction_connection_usable_after_implicit_rollback() -> Result<(), E
 async fn transaction_not_in_transaction_after_implicit_commit() -> Result<(), Error> {
     let mut conn = get_conn().await?;
 
-    conn.transaction(async |conn1, _tx| {
-        check!(conn1.in_transaction());
+    conn.transaction(async |tx| {
+        check!(tx.in_transaction());
         Ok(())
     })
     .await?;
@@ -263,7 +251,7 @@ async fn transaction_not_in_transaction_after_implicit_rollback() -> Result<(),
     let mut conn = get_conn().await?;
 
     let _: Result<(), Error> = conn
-        .transaction(async |_conn1, _tx| Err(Error::BadUsageError("intentional error".into())))
+        .transaction(async |_tx| Err(Error::BadUsageError("intentional error".into())))
         .await;
 
     check!(!conn.in_transaction());
@@ -5,8 +5,11 @@
 #![allow(dead_code)]
 
 use zero_mysql::Opts;
-use zero_mysql::r#macro::FromRow;
+use zero_mysql::r#macro::{FromRawValueEnum, FromRow, ToParams};
+use zero_mysql::protocol::r#trait::param::Params;
+use zero_mysql::raw::FromRawValue;
 use zero_mysql::sync::Conn;
+use zero_mysql::value::Value;
 
 include!("common/check.rs");
 include!("common/check_eq.rs");
@@ -63,6 +66,85 @@ struct PartialUser {
     name: String,
 }
 
+#[derive(Debug, PartialEq, FromRow)]
+struct UserWithDefaults {
+    id: i64,
+    #[from_row(default)]
+    age: u8,
+    #[from_row(with = "comma_separated")]
+    tags: Vec<String>,
+}
+
+fn comma_separated(value: Value<'_>) -> Result<Vec<String>, zero_mysql::error::Error> {
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::Byte(bytes) => Ok(String::from_utf8_lossy(bytes)
+            .split(',')
+            .map(str::to_string)
+            .collect()),
+        other => Err(zero_mysql::error::Error::BadUsageError(format!(
+            "expected a string, got {other:?}"
+        ))),
+    }
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Order {
+    order_id: i64,
+    amount: i64,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct OrderWithUser {
+    #[from_row(flatten)]
+    order: Order,
+    #[from_row(flatten)]
+    user: PartialUser,
+    id: i64,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+#[from_row(positional)]
+struct PositionalUser {
+    id: i64,
+    name: String,
+    #[from_row(default)]
+    age: u8,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+#[from_row(positional, strict)]
+struct StrictPositionalUser {
+    id: i64,
+    name: String,
+}
+
+#[derive(Debug, PartialEq, FromRawValueEnum)]
+enum Status {
+    #[mysql(rename = "active")]
+    Active = 1,
+    #[mysql(rename = "banned")]
+    Banned = 2,
+    Pending = 3,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct UserWithStatus {
+    id: i64,
+    status: Status,
+    previous: Option<Status>,
+}
+
+#[derive(ToParams)]
+struct NewUser<'a> {
+    id: i64,
+    #[param(rename = "user_name")]
+    name: &'a str,
+    #[param(skip)]
+    cached: bool,
+    email: Option<String>,
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -316,6 +398,179 @@ fn column_order_independence() -> Result<(), zero_mysql::error::Error> {
     Ok(())
 }
 
+#[test]
+fn interned_column_names() -> Result<(), zero_mysql::error::Error> {
+    let mut opts = Opts::try_from(TEST_URL)?;
+    opts.intern_column_names = true;
+    let mut conn = Conn::new(opts)?;
+
+    let mut stmt = conn.prepare("SELECT 'Alice' AS name, 25 AS age, 1 AS id, 0 AS extra")?;
+    check!(
+        stmt.column_definitions()
+            .is_some_and(|cols| cols.iter().all(|col| col.name_id.is_some()))
+    );
+    let users: Vec<User> = conn.exec_collect(&mut stmt, ())?;
+    check_eq!(
+        users,
+        vec![User {
+            id: 1,
+            name: "Alice".to_string(),
+            age: 25
+        }]
+    );
+
+    let mut strict = conn.prepare("SELECT 1 AS id, 'Bob' AS name, 0 AS extra")?;
+    check_err!(conn.exec_collect::<StrictUser, _>(&mut strict, ()));
+    Ok(())
+}
+
+#[test]
+fn default_and_with_attributes() -> Result<(), zero_mysql::error::Error> {
+    let mut conn = get_conn()?;
+
+    let mut stmt = conn.prepare("SELECT 1 AS id, 'a,b' AS tags")?;
+    let rows: Vec<UserWithDefaults> = conn.exec_collect(&mut stmt, ())?;
+    check_eq!(
+        rows,
+        vec![UserWithDefaults {
+            id: 1,
+            age: 0,
+            tags: vec!["a".to_string(), "b".to_string()],
+        }]
+    );
+
+    let mut null_tags = conn.prepare("SELECT 2 AS id, 30 AS age, NULL AS tags")?;
+    let with_null: Vec<UserWithDefaults> = conn.exec_collect(&mut null_tags, ())?;
+    check_eq!(
+        with_null,
+        vec![UserWithDefaults {
+            id: 2,
+            age: 30,
+            tags: Vec::new(),
+        }]
+    );
+
+    // `with` does not imply `default`
+    let mut missing_tags = conn.prepare("SELECT 3 AS id")?;
+    let err = check_err!(conn.exec_collect::<UserWithDefaults, _>(&mut missing_tags, ()));
+    check!(err.to_string().contains("Missing column"));
+    Ok(())
+}
+
+#[test]
+fn flatten_nested_structs() -> Result<(), zero_mysql::error::Error> {
+    let mut conn = get_conn()?;
+
+    let mut stmt =
+        conn.prepare("SELECT 7 AS id, 10 AS order_id, 'Alice' AS name, 250 AS amount")?;
+    let rows: Vec<OrderWithUser> = conn.exec_collect(&mut stmt, ())?;
+    check_eq!(
+        rows,
+        vec![OrderWithUser {
+            order: Order {
+                order_id: 10,
+                amount: 250,
+            },
+            user: PartialUser {
+                name: "Alice".to_string(),
+            },
+            id: 7,
+        }]
+    );
+
+    // A column missing from a flattened struct is reported by its FromRow impl
+    let mut missing = conn.prepare("SELECT 7 AS id, 10 AS order_id, 'Alice' AS name")?;
+    let err = check_err!(conn.exec_collect::<OrderWithUser, _>(&mut missing, ()));
+    check!(err.to_string().contains("Missing column"));
+    Ok(())
+}
+
+#[test]
+fn positional_mode() -> Result<(), zero_mysql::error::Error> {
+    let mut conn = get_conn()?;
+
+    // Column names are ignored
+    let mut stmt = conn.prepare("SELECT 1 AS a, 'Alice' AS b, 30 AS c, 'extra' AS d")?;
+    let rows: Vec<PositionalUser> = conn.exec_collect(&mut stmt, ())?;
+    check_eq!(
+        rows,
+        vec![PositionalUser {
+            id: 1,
+            name: "Alice".to_string(),
+            age: 30,
+        }]
+    );
+
+    let mut short = conn.prepare("SELECT 2, 'Bob'")?;
+    let defaulted: Vec<PositionalUser> = conn.exec_collect(&mut short, ())?;
+    check_eq!(
+        defaulted,
+        vec![PositionalUser {
+            id: 2,
+            name: "Bob".to_string(),
+            age: 0,
+        }]
+    );
+
+    let mut extra = conn.prepare("SELECT 3, 'Carol', 'extra'")?;
+    let unknown = check_err!(conn.exec_collect::<StrictPositionalUser, _>(&mut extra, ()));
+    check!(unknown.to_string().contains("Unknown column"));
+
+    let mut missing = conn.prepare("SELECT 4")?;
+    let missing_name = check_err!(conn.exec_collect::<StrictPositionalUser, _>(&mut missing, ()));
+    check!(missing_name.to_string().contains("Missing column"));
+    Ok(())
+}
+
+#[test]
+fn enum_from_raw_value() -> Result<(), zero_mysql::error::Error> {
+    check_eq!(Status::from_str(b"active")?, Status::Active);
+    check_eq!(Status::from_str(b"Pending")?, Status::Pending);
+    check_eq!(Status::from_i8(2)?, Status::Banned);
+    check_eq!(Status::from_u64(3)?, Status::Pending);
+    check_err!(Status::from_str(b"Active"));
+    check_err!(Status::from_i32(4));
+    check_err!(Status::from_double(1.0));
+    Ok(())
+}
+
+#[test]
+fn enum_columns() -> Result<(), zero_mysql::error::Error> {
+    let mut conn = get_conn()?;
+
+    conn.query_drop("DROP TABLE IF EXISTS test_derive_enums")?;
+    conn.query_drop(
+        "CREATE TABLE test_derive_enums (
+            id BIGINT,
+            status ENUM('active', 'banned', 'Pending'),
+            previous TINYINT
+        )",
+    )?;
+    conn.query_drop("INSERT INTO test_derive_enums VALUES (1, 'active', NULL), (2, 'banned', 1)")?;
+
+    let mut stmt =
+        conn.prepare("SELECT id, status, previous FROM test_derive_enums ORDER BY id")?;
+    let rows: Vec<UserWithStatus> = conn.exec_collect(&mut stmt, ())?;
+    check_eq!(
+        rows,
+        vec![
+            UserWithStatus {
+                id: 1,
+                status: Status::Active,
+                previous: None,
+            },
+            UserWithStatus {
+                id: 2,
+                status: Status::Banned,
+                previous: Some(Status::Active),
+            },
+        ]
+    );
+
+    conn.query_drop("DROP TABLE test_derive_enums")?;
+    Ok(())
+}
+
 #[test]
 fn int_types() -> Result<(), zero_mysql::error::Error> {
     let mut conn = get_conn()?;
@@ -376,3 +631,60 @@ fn float_types() -> Result<(), zero_mysql::error::Error> {
 
     Ok(())
 }
+
+fn encode(params: impl Params) -> Result<Vec<u8>, zero_mysql::error::Error> {
+    let mut out = Vec::new();
+    params.encode_null_bitmap(&mut out);
+    params.encode_types(&mut out);
+    params.encode_values(&mut out)?;
+    params.encode_values_for_bulk(&mut out)?;
+    Ok(out)
+}
+
+#[test]
+fn to_params_matches_tuple() -> Result<(), zero_mysql::error::Error> {
+    let user = NewUser {
+        id: 7,
+        name: "Alice",
+        cached: true,
+        email: None,
+    };
+    check_eq!(NewUser::PARAM_NAMES, &["id", "user_name", "email"]);
+    check_eq!(Params::len(&&user), 3);
+    check_eq!(encode(&user)?, encode((7_i64, "Alice", None::<String>))?);
+    Ok(())
+}
+
+#[test]
+fn to_params_exec() -> Result<(), zero_mysql::error::Error> {
+    let mut conn = get_conn()?;
+    conn.query_drop(
+        "CREATE TEMPORARY TABLE test_derive_params (
+            id BIGINT PRIMARY KEY,
+            user_name VARCHAR(255) NOT NULL,
+            email VARCHAR(255)
+        )",
+    )?;
+    let sql = format!(
+        "INSERT INTO test_derive_params ({}) VALUES (?, ?, ?)",
+        NewUser::PARAM_NAMES.join(", ")
+    );
+    let mut insert = conn.prepare(&sql)?;
+    conn.exec_drop(
+        &mut insert,
+        &NewUser {
+            id: 1,
+            name: "Alice",
+            cached: false,
+            email: Some("alice@example.com".to_string()),
+        },
+    )?;
+
+    let mut select = conn.prepare("SELECT user_name, email FROM test_derive_params")?;
+    let row: Option<(String, Option<String>)> = conn.exec_first(&mut select, ())?;
+    check_eq!(
+        row,
+        Some(("Alice".to_string(), Some("alice@example.com".to_string())))
+    );
+    Ok(())
+}
@@ -232,3 +232,52 @@ fn pool_multi_threaded() -> Result<(), Box<dyn std::error::Error>> {
     }
     Ok(())
 }
+
+#[test]
+fn pool_passthrough() -> Result<(), Box<dyn std::error::Error>> {
+    let opts = Opts::try_from(TEST_URL)?;
+    let pool = Arc::new(Pool::new(opts));
+
+    pool.query_drop("DROP TABLE IF EXISTS test_pool_passthrough")?;
+    pool.query_drop("CREATE TABLE test_pool_passthrough (id INT PRIMARY KEY, name TEXT)")?;
+    pool.exec_drop(
+        "INSERT INTO test_pool_passthrough VALUES (?, ?), (?, ?)",
+        (1, "a", 2, "b"),
+    )?;
+
+    let rows: Vec<(i32, String)> =
+        pool.query_rows("SELECT id, name FROM test_pool_passthrough ORDER BY id")?;
+    check_eq!(rows, vec![(1, "a".to_string()), (2, "b".to_string())]);
+
+    let first: Option<(String,)> =
+        pool.exec_first("SELECT name FROM test_pool_passthrough WHERE id = ?", (2,))?;
+    check_eq!(first, Some(("b".to_string(),)));
+
+    pool.run_transaction(|mut tx| tx.query_drop("DELETE FROM test_pool_passthrough WHERE id = 1"))?;
+    let remaining: Vec<(i32,)> = pool.query_rows("SELECT id FROM test_pool_passthrough")?;
+    check_eq!(remaining, vec![(2,)]);
+    check_eq!(pool.in_use_count(), 0);
+    Ok(())
+}
+
+#[test]
+fn pool_get_with_role() -> Result<(), Box<dyn std::error::Error>> {
+    let mut opts = Opts::try_from(TEST_URL)?;
+    opts.pool_max_idle_conn = 1;
+    opts.pool_reset_conn = false;
+    let pool = Arc::new(Pool::new(opts));
+
+    {
+        let mut admin = pool.get()?;
+        admin.query_drop("CREATE ROLE IF NOT EXISTS test_pool_role")?;
+        admin.query_drop("GRANT test_pool_role TO CURRENT_USER()")?;
+    }
+
+    let with_role = pool.get_with_role("test_pool_role")?;
+    check!(with_role.role_changed());
+    drop(with_role);
+
+    let reused = pool.get()?;
+    check!(!reused.role_changed());
+    Ok(())
+}
@@ -48,9 +48,9 @@ fn transaction_explicit_commit() -> Result<(), Error> {
     let mut conn = get_conn()?;
     let table = TestTable::new(&mut conn)?;
 
-    conn.transaction(|conn, tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
-        tx.commit(conn)
+    conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
+        tx.commit()
     })?;
 
     check_eq!(table.count(&mut conn)?, 1);
@@ -63,9 +63,9 @@ fn transaction_explicit_rollback() -> Result<(), Error> {
     let mut conn = get_conn()?;
     let table = TestTable::new(&mut conn)?;
 
-    conn.transaction(|conn, tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
-        tx.rollback(conn)
+    conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
+        tx.rollback()
     })?;
 
     check_eq!(table.count(&mut conn)?, 0);
@@ -79,8 +79,8 @@ fn transaction_implicit_commit_on_ok() -> Result<(), Error> {
     let table = TestTable::new(&mut conn)?;
 
     // Return Ok without explicit commit - should auto-commit
-    conn.transaction(|conn, _tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
+    conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
         Ok(())
     })?;
 
@@ -96,8 +96,8 @@ fn transaction_implicit_rollback_on_err() -> Result<(), Error> {
     let table = TestTable::new(&mut conn)?;
 
     // Return Err without explicit rollback - should auto-rollback
-    let result: Result<(), Error> = conn.transaction(|conn, _tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
+    let result: Result<(), Error> = conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
         Err(Error::BadUsageError("intentional error".into()))
     });
 
@@ -114,8 +114,8 @@ fn transaction_implicit_commit_with_return_value() -> Result<(), Error> {
     let table = TestTable::new(&mut conn)?;
 
     // Return Ok with a value without explicit commit
-    let result: i32 = conn.transaction(|conn, _tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
+    let result: i32 = conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", table.name))?;
         Ok(123)
     })?;
 
@@ -130,9 +130,9 @@ fn transaction_implicit_commit_multiple_inserts() -> Result<(), Error> {
     let mut conn = get_conn()?;
     let table = TestTable::new(&mut conn)?;
 
-    conn.transaction(|conn, _tx| {
+    conn.transaction(|mut tx| {
         for i in 1..=5 {
-            conn.query_drop(&format!(
+            tx.query_drop(&format!(
                 "INSERT INTO {} (value) VALUES ({})",
                 table.name, i
             ))?;
@@ -150,10 +150,10 @@ fn transaction_implicit_rollback_partial_work() -> Result<(), Error> {
     let mut conn = get_conn()?;
     let table = TestTable::new(&mut conn)?;
 
-    let result: Result<(), Error> = conn.transaction(|conn, _tx| {
+    let result: Result<(), Error> = conn.transaction(|mut tx| {
         // Do some work
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))?;
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))?;
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))?;
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))?;
         // Then fail
         Err(Error::BadUsageError("intentional error".into()))
     });
@@ -171,14 +171,14 @@ fn transaction_connection_usable_after_implicit_commit() -> Result<(), Error> {
     let table = TestTable::new(&mut conn)?;
 
     // First transaction with implicit commit
-    conn.transaction(|conn, _tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))?;
+    conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))?;
         Ok(())
     })?;
 
     // Connection should be usable for another transaction
-    conn.transaction(|conn, _tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))?;
+    conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))?;
         Ok(())
     })?;
 
@@ -193,14 +193,14 @@ fn transaction_connection_usable_after_implicit_rollback() -> Result<(), Error>
     let table = TestTable::new(&mut conn)?;
 
     // First transaction with implicit rollback
-    let _: Result<(), Error> = conn.transaction(|conn, _tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))?;
+    let _: Result<(), Error> = conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))?;
         Err(Error::BadUsageError("intentional error".into()))
     });
 
     // Connection should be usable for another transaction
-    conn.transaction(|conn, _tx| {
-        conn.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))?;
+    conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))?;
         Ok(())
     })?;
 
@@ -213,8 +213,8 @@ fn transaction_connection_usable_after_implicit_rollback() -> Result<(), Error>
 fn transaction_not_in_transaction_after_implicit_commit() -> Result<(), Error> {
     let mut conn = get_conn()?;
 
-    conn.transaction(|conn, _tx| {
-        check!(conn.in_transaction());
+    conn.transaction(|tx| {
+        check!(tx.in_transaction());
         Ok(())
     })?;
 
@@ -227,8 +227,59 @@ fn transaction_not_in_transaction_after_implicit_rollback() -> Result<(), Error>
     let mut conn = get_conn()?;
 
     let _: Result<(), Error> =
-        conn.transaction(|_conn, _tx| Err(Error::BadUsageError("intentional error".into())));
+        conn.transaction(|_tx| Err(Error::BadUsageError("intentional error".into())));
 
     check!(!conn.in_transaction());
     Ok(())
 }
+
+#[test]
+fn server_status_tracks_manual_transactions() -> Result<(), Error> {
+    use zero_mysql::constant::ServerStatusFlags;
+
+    let mut conn = get_conn()?;
+    check!(!conn.in_transaction());
+
+    conn.query_drop("START TRANSACTION")?;
+    check!(conn.in_transaction());
+    check!(
+        conn.server_status()
+            .contains(ServerStatusFlags::SERVER_STATUS_IN_TRANS)
+    );
+    check!(matches!(
+        conn.transaction(|_tx| Ok(())),
+        Err(Error::NestedTransaction)
+    ));
+
+    conn.query_drop("ROLLBACK")?;
+    check!(!conn.in_transaction());
+    check!(
+        conn.server_status()
+            .contains(ServerStatusFlags::SERVER_STATUS_AUTOCOMMIT)
+    );
+    Ok(())
+}
+
+#[test]
+fn savepoint_rolls_back_on_error() -> Result<(), Error> {
+    let mut conn = get_conn()?;
+    let table = TestTable::new(&mut conn)?;
+
+    conn.transaction(|mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", table.name))?;
+        let failed = tx.savepoint("inner", |mut sp| {
+            sp.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", table.name))?;
+            Err::<(), _>(Error::BadUsageError("intentional error".into()))
+        });
+        check!(failed.is_err());
+        check!(tx.in_transaction());
+        tx.savepoint("kept", |mut sp| {
+            sp.query_drop(&format!("INSERT INTO {} (value) VALUES (3)", table.name))?;
+            sp.savepoint("nested", |nested| nested.rollback())
+        })
+    })?;
+
+    check_eq!(table.count(&mut conn)?, 2);
+    table.cleanup(&mut conn);
+    Ok(())
+}
@@ -1,9 +1,10 @@
 //! Integration tests for async connection pool
 
 use std::sync::Arc;
+use std::time::Duration;
 
-use zero_mysql::Opts;
 use zero_mysql::tokio::Pool;
+use zero_mysql::{Opts, PoolConfig, ResetOnReturn};
 
 include!("common/check.rs");
 include!("common/check_eq.rs");
@@ -236,3 +237,176 @@ async fn pool_concurrent_tasks() -> Result<(), Box<dyn std::error::Error>> {
     }
     Ok(())
 }
+
+#[tokio::test]
+async fn pool_passthrough() -> Result<(), Box<dyn std::error::Error>> {
+    let opts = Opts::try_from(TEST_URL)?;
+    let pool = Arc::new(Pool::new(opts));
+
+    pool.query_drop("DROP TABLE IF EXISTS test_tokio_pool_passthrough")
+        .await?;
+    pool.query_drop("CREATE TABLE test_tokio_pool_passthrough (id INT PRIMARY KEY)")
+        .await?;
+    pool.exec_drop(
+        "INSERT INTO test_tokio_pool_passthrough VALUES (?), (?)",
+        (1, 2),
+    )
+    .await?;
+
+    let rows: Vec<(i32,)> = pool
+        .query_rows("SELECT id FROM test_tokio_pool_passthrough ORDER BY id")
+        .await?;
+    check_eq!(rows, vec![(1,), (2,)]);
+
+    pool.run_transaction(async |mut tx| {
+        tx.query_drop("DELETE FROM test_tokio_pool_passthrough WHERE id = 1")
+            .await
+    })
+    .await?;
+    let remaining: Vec<(i32,)> = pool
+        .query_rows("SELECT id FROM test_tokio_pool_passthrough")
+        .await?;
+    check_eq!(remaining, vec![(2,)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pool_close_waits_for_checked_out_conns() -> Result<(), Box<dyn std::error::Error>> {
+    let opts = Opts::try_from(TEST_URL)?;
+    let pool = Arc::new(Pool::new(opts));
+
+    let idle = pool.get().await?;
+    drop(idle);
+    let conn = pool.get().await?;
+
+    let closing = {
+        let pool = Arc::clone(&pool);
+        tokio::spawn(async move { pool.close().await })
+    };
+    tokio::task::yield_now().await;
+    check!(pool.is_closed());
+    check!(pool.get().await.is_err());
+    check!(!closing.is_finished());
+
+    drop(conn);
+    closing.await?;
+    check_eq!(pool.idle_count(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pool_max_lifetime() -> Result<(), Box<dyn std::error::Error>> {
+    let mut opts = Opts::try_from(TEST_URL)?;
+    opts.pool_reset_conn = false;
+    let config = PoolConfig::default()
+        .with_max_lifetime(Duration::from_millis(50))
+        .with_jitter(0.0);
+    let pool = Arc::new(Pool::with_config(opts, config));
+
+    let conn = pool.get().await?;
+    let id = conn.connection_id();
+    drop(conn);
+    check_eq!(pool.idle_count(), 1);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let replaced = pool.get().await?;
+    check!(replaced.connection_id() != id);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pool_reaper_closes_idle_and_prewarms() -> Result<(), Box<dyn std::error::Error>> {
+    let mut opts = Opts::try_from(TEST_URL)?;
+    opts.pool_reset_conn = false;
+    let config = PoolConfig::default()
+        .with_idle_timeout(Duration::from_millis(50))
+        .with_min_idle(2)
+        .with_jitter(0.0);
+    let pool = Arc::new(Pool::with_config(opts, config));
+
+    pool.reap().await;
+    check_eq!(pool.idle_count(), 2);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let conn = pool.get().await?;
+    drop(conn);
+    // The expired connections were dropped; the reaper tops the pool up again
+    pool.reap().await;
+    check_eq!(pool.idle_count(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pool_parallel_scan() -> Result<(), Box<dyn std::error::Error>> {
+    let opts = Opts::try_from(TEST_URL)?;
+    let pool = Arc::new(Pool::new(opts));
+
+    let mut conn = pool.get().await?;
+    conn.query_drop("DROP TABLE IF EXISTS test_parallel_scan")
+        .await?;
+    conn.query_drop("CREATE TABLE test_parallel_scan (id BIGINT PRIMARY KEY, v INT)")
+        .await?;
+    conn.query_drop(
+        "INSERT INTO test_parallel_scan VALUES (1, 10), (2, 20), (5, 50), (40, 400), (41, 410)",
+    )
+    .await?;
+    drop(conn);
+
+    let mut scan = pool
+        .parallel_scan::<(i64, i32)>("test_parallel_scan", 4, 2)
+        .await?;
+    check_eq!(scan.partitions().len(), 4);
+    let mut rows = Vec::new();
+    while let Some(row) = scan.next().await {
+        rows.push(row?);
+    }
+    rows.sort_unstable();
+    check_eq!(rows, vec![(1, 10), (2, 20), (5, 50), (40, 400), (41, 410)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pool_reset_on_return_restores_autocommit() -> Result<(), Box<dyn std::error::Error>> {
+    let mut opts = Opts::try_from(TEST_URL)?;
+    opts.pool_max_idle_conn = 1;
+    opts.init_commands = vec!["SET @init = 1".to_string()];
+    let config = PoolConfig::default().with_reset_on_return(ResetOnReturn::Full);
+    let pool = Arc::new(Pool::with_config(opts, config));
+
+    let mut conn = pool.get().await?;
+    let id = conn.connection_id();
+    conn.query_drop("SET autocommit = 0").await?;
+    conn.query_drop("BEGIN").await?;
+    check!(conn.in_transaction());
+    drop(conn);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    check_eq!(pool.idle_count(), 1);
+
+    let mut reused = pool.get().await?;
+    check_eq!(reused.connection_id(), id);
+    check!(reused.autocommit());
+    check!(!reused.in_transaction());
+    let mut stmt = reused.prepare("SELECT @init").await?;
+    let init: Option<(i64,)> = reused.exec_first(&mut stmt, ()).await?;
+    check_eq!(init, Some((1,)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn pool_reset_on_return_none_drops_open_transactions()
+-> Result<(), Box<dyn std::error::Error>> {
+    let opts = Opts::try_from(TEST_URL)?;
+    let config = PoolConfig::default().with_reset_on_return(ResetOnReturn::None);
+    let pool = Arc::new(Pool::with_config(opts, config));
+
+    let mut conn = pool.get().await?;
+    conn.query_drop("BEGIN").await?;
+    drop(conn);
+    check_eq!(pool.idle_count(), 0);
+
+    let mut autocommit_off = pool.get().await?;
+    autocommit_off.query_drop("SET autocommit = 0").await?;
+    drop(autocommit_off);
+    check_eq!(pool.idle_count(), 0);
+    Ok(())
+}
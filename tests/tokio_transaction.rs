@@ -53,11 +53,10 @@ async fn transaction_explicit_commit() -> Result<(), Error> {
     create_table(&mut conn, &table).await?;
 
     let t = table.clone();
-    conn.transaction(async |conn1, tx| {
-        conn1
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
             .await?;
-        tx.commit(conn1).await
+        tx.commit().await
     })
     .await?;
 
@@ -73,11 +72,10 @@ async fn transaction_explicit_rollback() -> Result<(), Error> {
     create_table(&mut conn, &table).await?;
 
     let t = table.clone();
-    conn.transaction(async |conn1, tx| {
-        conn1
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
             .await?;
-        tx.rollback(conn1).await
+        tx.rollback().await
     })
     .await?;
 
@@ -93,9 +91,8 @@ async fn transaction_implicit_commit_on_ok() -> Result<(), Error> {
     create_table(&mut conn, &table).await?;
 
     let t = table.clone();
-    conn.transaction(async |conn1, _tx| {
-        conn1
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
             .await?;
         Ok(())
     })
@@ -114,9 +111,8 @@ async fn transaction_implicit_rollback_on_err() -> Result<(), Error> {
 
     let t = table.clone();
     let result: Result<(), Error> = conn
-        .transaction(async |conn1, _tx| {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
+        .transaction(async |mut tx| {
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
                 .await?;
             Err(Error::BadUsageError("intentional error".into()))
         })
@@ -136,9 +132,8 @@ async fn transaction_implicit_commit_with_return_value() -> Result<(), Error> {
 
     let t = table.clone();
     let result: i32 = conn
-        .transaction(async |conn1, _tx| {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
+        .transaction(async |mut tx| {
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (42)", t))
                 .await?;
             Ok(123)
         })
@@ -157,10 +152,9 @@ async fn transaction_implicit_commit_multiple_inserts() -> Result<(), Error> {
     create_table(&mut conn, &table).await?;
 
     let t = table.clone();
-    conn.transaction(async |conn1, _tx| {
+    conn.transaction(async |mut tx| {
         for i in 1..=5 {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES ({})", t, i))
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES ({})", t, i))
                 .await?;
         }
         Ok(())
@@ -180,12 +174,10 @@ async fn transaction_implicit_rollback_partial_work() -> Result<(), Error> {
 
     let t = table.clone();
     let result: Result<(), Error> = conn
-        .transaction(async |conn1, _tx| {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (1)", t))
+        .transaction(async |mut tx| {
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", t))
                 .await?;
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (2)", t))
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", t))
                 .await?;
             Err(Error::BadUsageError("intentional error".into()))
         })
@@ -204,18 +196,16 @@ async fn transaction_connection_usable_after_implicit_commit() -> Result<(), Err
     create_table(&mut conn, &table).await?;
 
     let t1 = table.clone();
-    conn.transaction(async |conn1, _tx| {
-        conn1
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (1)", t1))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", t1))
             .await?;
         Ok(())
     })
     .await?;
 
     let t2 = table.clone();
-    conn.transaction(async |conn2, _tx| {
-        conn2
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (2)", t2))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", t2))
             .await?;
         Ok(())
     })
@@ -234,18 +224,16 @@ async fn transaction_connection_usable_after_implicit_rollback() -> Result<(), E
 
     let t1 = table.clone();
     let _: Result<(), Error> = conn
-        .transaction(async |conn1, _tx| {
-            conn1
-                .query_drop(&format!("INSERT INTO {} (value) VALUES (1)", t1))
+        .transaction(async |mut tx| {
+            tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", t1))
                 .await?;
             Err(Error::BadUsageError("intentional error".into()))
         })
         .await;
 
     let t2 = table.clone();
-    conn.transaction(async |conn2, _tx| {
-        conn2
-            .query_drop(&format!("INSERT INTO {} (value) VALUES (2)", t2))
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", t2))
             .await?;
         Ok(())
     })
@@ -260,7 +248,7 @@ async fn transaction_connection_usable_after_implicit_rollback() -> Result<(), E
 async fn transaction_not_in_transaction_after_implicit_commit() -> Result<(), Error> {
     let mut conn = get_conn().await?;
 
-    conn.transaction(async |_conn1, _tx| Ok(())).await?;
+    conn.transaction(async |_tx| Ok(())).await?;
 
     check!(!conn.in_transaction());
     Ok(())
@@ -271,9 +259,43 @@ async fn transaction_not_in_transaction_after_implicit_rollback() -> Result<(),
     let mut conn = get_conn().await?;
 
     let _: Result<(), Error> = conn
-        .transaction(async |_conn1, _tx| Err(Error::BadUsageError("intentional error".into())))
+        .transaction(async |_tx| Err(Error::BadUsageError("intentional error".into())))
         .await;
 
     check!(!conn.in_transaction());
     Ok(())
 }
+
+#[tokio::test]
+async fn savepoint_rolls_back_on_error() -> Result<(), Error> {
+    let mut conn = get_conn().await?;
+    let table = unique_table_name();
+    create_table(&mut conn, &table).await?;
+
+    let t = table.clone();
+    conn.transaction(async |mut tx| {
+        tx.query_drop(&format!("INSERT INTO {} (value) VALUES (1)", t))
+            .await?;
+        let failed = tx
+            .savepoint("inner", async |mut sp| {
+                sp.query_drop(&format!("INSERT INTO {} (value) VALUES (2)", t))
+                    .await?;
+                Err::<(), _>(Error::BadUsageError("intentional error".into()))
+            })
+            .await;
+        check!(failed.is_err());
+        check!(tx.in_transaction());
+        tx.savepoint("kept", async |mut sp| {
+            sp.query_drop(&format!("INSERT INTO {} (value) VALUES (3)", t))
+                .await?;
+            sp.savepoint("nested", async |nested| nested.rollback().await)
+                .await
+        })
+        .await
+    })
+    .await?;
+
+    check_eq!(count_rows(&mut conn, &table).await?, 2);
+    cleanup_table(&mut conn, &table).await;
+    Ok(())
+}
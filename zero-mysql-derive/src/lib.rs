@@ -5,6 +5,7 @@ use syn::{Data, DeriveInput, Fields, Meta, parse_macro_input, spanned::Spanned};
 /// Derive macro for `FromRow` trait.
 ///
 /// Generates an implementation that matches column names to struct fields.
+/// Columns interned with `Opts::intern_column_names` are matched by id instead of by name.
 ///
 /// # Example
 ///
@@ -29,6 +30,49 @@ use syn::{Data, DeriveInput, Fields, Meta, parse_macro_input, spanned::Spanned};
 ///     age: u8,
 /// }
 /// ```
+///
+/// # Positional Mode
+///
+/// `#[from_row(positional)]` decodes the fields from the columns in declaration order and
+/// ignores the column names, which skips the name lookup per column on wide result sets.
+/// Extra trailing columns are skipped, or rejected with `strict`. Cannot be combined with
+/// `flatten`.
+///
+/// ```ignore
+/// #[derive(FromRow)]
+/// #[from_row(positional)]
+/// struct User {
+///     name: String, // column 0
+///     age: u8,      // column 1
+/// }
+/// ```
+///
+/// # Field Attributes
+///
+/// - `#[from_row(default)]`: use `Default::default()` if the column is missing, instead of
+///   returning `Error::MissingColumn`
+/// - `#[from_row(with = "path::to::func")]`: decode the column with
+///   `fn(zero_mysql::value::Value<'_>) -> zero_mysql::error::Result<FieldType>`
+/// - `#[from_row(flatten)]`: decode the field, itself a `FromRow` struct, from the same
+///   columns, e.g. to map a JOIN to composed types. Cannot be combined with `strict`.
+///
+/// ```ignore
+/// fn parse_tags(value: Value<'_>) -> Result<Vec<String>> {
+///     match value {
+///         Value::Byte(bytes) => Ok(String::from_utf8_lossy(bytes).split(',').map(Into::into).collect()),
+///         _ => Ok(Vec::new()),
+///     }
+/// }
+///
+/// #[derive(FromRow)]
+/// struct Post {
+///     title: String,
+///     #[from_row(default)]
+///     views: u64,
+///     #[from_row(with = "parse_tags")]
+///     tags: Vec<String>,
+/// }
+/// ```
 #[proc_macro_derive(FromRow, attributes(from_row))]
 pub fn derive_from_row(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -37,16 +81,20 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
     let generics = &input.generics;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    // Check for #[from_row(strict)]
-    let strict = input.attrs.iter().any(|attr| {
-        if !attr.path().is_ident("from_row") {
-            return false;
-        }
-        match &attr.meta {
-            Meta::List(list) => list.tokens.to_string().contains("strict"),
-            _ => false,
-        }
-    });
+    // Check for #[from_row(strict)] and #[from_row(positional)]
+    let has_struct_attr = |flag: &str| {
+        input.attrs.iter().any(|attr| {
+            if !attr.path().is_ident("from_row") {
+                return false;
+            }
+            match &attr.meta {
+                Meta::List(list) => list.tokens.to_string().contains(flag),
+                _ => false,
+            }
+        })
+    };
+    let strict = has_struct_attr("strict");
+    let positional = has_struct_attr("positional");
 
     let fields = match &input.data {
         Data::Struct(data) => match &data.fields {
@@ -56,9 +104,69 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
         _ => panic!("FromRow only supports structs"),
     };
 
-    let field_names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
-    let field_types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let mut field_attrs = Vec::with_capacity(fields.len());
+    for field in fields {
+        match FieldAttrs::parse(field) {
+            Ok(attrs) => field_attrs.push(attrs),
+            Err(err) => return err.to_compile_error().into(),
+        }
+    }
+
+    // Flattened fields are decoded from the whole row by their own FromRow impl
+    let flattened: Vec<_> = fields
+        .iter()
+        .zip(field_attrs.iter())
+        .filter(|(_, attrs)| attrs.flatten)
+        .map(|(field, _)| field)
+        .collect();
+    if strict && let Some(field) = flattened.first() {
+        return syn::Error::new(
+            field.span(),
+            "#[from_row(strict)] cannot be combined with #[from_row(flatten)]",
+        )
+        .to_compile_error()
+        .into();
+    }
+    if positional && let Some(field) = flattened.first() {
+        return syn::Error::new(
+            field.span(),
+            "#[from_row(positional)] cannot be combined with #[from_row(flatten)]",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let flatten_decls = flattened.iter().map(|field| {
+        let name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        quote! {
+            let #name = <#ty as ::zero_mysql::raw::FromRow<'_>>::from_row(__cols, __row.clone())?;
+        }
+    });
+    let field_inits: Vec<_> = fields
+        .iter()
+        .zip(field_attrs.iter())
+        .map(|(field, attrs)| {
+            let name = field.ident.as_ref().unwrap();
+            if attrs.flatten {
+                quote! { #name }
+            } else {
+                quote! { #name: unsafe { #name.assume_init() } }
+            }
+        })
+        .collect();
+
+    let (column_fields, field_attrs): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .zip(field_attrs)
+        .filter(|(_, attrs)| !attrs.flatten)
+        .unzip();
+    let field_names: Vec<_> = column_fields
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect();
+    let field_types: Vec<_> = column_fields.iter().map(|f| &f.ty).collect();
     let field_name_strs: Vec<_> = field_names.iter().map(|n| n.to_string()).collect();
+    let num_fields = field_names.len();
 
     // Generate MaybeUninit declarations
     let uninit_decls = field_names
@@ -80,11 +188,20 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
         quote! { let mut #flag = false; }
     });
 
-    // Generate match arms
-    let match_arms = field_names.iter().zip(field_types.iter()).zip(set_flag_names.iter()).zip(field_name_strs.iter()).map(|(((name, ty), flag), name_str)| {
-        quote! {
-            #name_str => {
+    // Generate match arms, by field position
+    let match_arms = field_names.iter().zip(field_types.iter()).zip(set_flag_names.iter()).zip(field_attrs.iter()).enumerate().map(|(index, (((name, ty), flag), attrs))| {
+        let parse = match &attrs.with {
+            Some(with) => quote! {
+                let (__raw, __rest) = ::zero_mysql::raw::parse_value::<::zero_mysql::value::Value<'_>>(&__col.tail, __null_bitmap.is_null(__i), __data)?;
+                let __val: #ty = #with(__raw)?;
+            },
+            None => quote! {
                 let (__val, __rest) = ::zero_mysql::raw::parse_value::<#ty>(&__col.tail, __null_bitmap.is_null(__i), __data)?;
+            },
+        };
+        quote! {
+            ::core::option::Option::Some(#index) => {
+                #parse
                 #name.write(__val);
                 #flag = true;
                 __data = __rest;
@@ -95,7 +212,8 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
     // Generate fallback arm based on strict mode
     let fallback_arm = if strict {
         quote! {
-            __unknown => {
+            _ => {
+                let __unknown = ::core::str::from_utf8(__col.name_alias).unwrap_or("");
                 return Err(::zero_mysql::error::Error::UnknownColumn(__unknown.to_string()));
             }
         }
@@ -112,22 +230,58 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
     // Generate initialization checks
     let init_checks = field_names
         .iter()
+        .zip(field_types.iter())
         .zip(set_flag_names.iter())
         .zip(field_name_strs.iter())
-        .map(|((_name, flag), name_str)| {
-            quote! {
-                if !#flag {
-                    return Err(::zero_mysql::error::Error::MissingColumn(#name_str));
+        .zip(field_attrs.iter())
+        .map(|((((name, ty), flag), name_str), attrs)| {
+            if attrs.default {
+                quote! {
+                    if !#flag {
+                        #name.write(<#ty as ::core::default::Default>::default());
+                    }
+                }
+            } else {
+                quote! {
+                    if !#flag {
+                        return Err(::zero_mysql::error::Error::MissingColumn(#name_str));
+                    }
                 }
             }
         });
 
-    // Generate struct construction
-    let field_inits = field_names.iter().map(|name| {
-        quote! {
-            #name: unsafe { #name.assume_init() }
-        }
-    });
+    // The field decoded from column `__i`
+    let (field_tables, field_lookup) = if positional {
+        let tables = quote! {};
+        let lookup = quote! {
+            let __field = if __i < #num_fields {
+                ::core::option::Option::Some(__i)
+            } else {
+                ::core::option::Option::None
+            };
+        };
+        (tables, lookup)
+    } else {
+        let tables = quote! {
+            static __FIELD_IDS: ::std::sync::OnceLock<[::zero_mysql::column_names::ColumnNameId; #num_fields]> =
+                ::std::sync::OnceLock::new();
+            let __field_ids = __FIELD_IDS
+                .get_or_init(|| [#(::zero_mysql::column_names::intern(#field_name_strs)),*]);
+            const __FIELD_NAMES: [&str; #num_fields] = [#(#field_name_strs),*];
+        };
+        // Interned names are matched by id, others by text
+        let lookup = quote! {
+            let __field = match __col.name_id {
+                ::core::option::Option::Some(__id) => {
+                    __field_ids.iter().position(|__field_id| *__field_id == __id)
+                }
+                ::core::option::Option::None => __FIELD_NAMES
+                    .iter()
+                    .position(|__name| __name.as_bytes() == __col.name_alias),
+            };
+        };
+        (tables, lookup)
+    };
 
     let expanded = quote! {
         impl #impl_generics ::zero_mysql::raw::FromRow<'_> for #name #ty_generics #where_clause {
@@ -141,15 +295,18 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
                 let mut __data = __row.values();
                 let __null_bitmap = __row.null_bitmap();
 
+                #field_tables
+
                 for (__i, __col) in __cols.iter().enumerate() {
-                    let __col_name = ::core::str::from_utf8(__col.name_alias).unwrap_or("");
-                    match __col_name {
+                    #field_lookup
+                    match __field {
                         #(#match_arms)*
                         #fallback_arm
                     }
                 }
 
                 #(#init_checks)*
+                #(#flatten_decls)*
 
                 Ok(Self {
                     #(#field_inits),*
@@ -161,6 +318,335 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+/// Field-level `#[from_row(...)]` attributes
+#[derive(Default)]
+struct FieldAttrs {
+    default: bool,
+    with: Option<syn::Path>,
+    flatten: bool,
+}
+
+impl FieldAttrs {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("from_row"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    attrs.default = true;
+                    Ok(())
+                } else if meta.path.is_ident("with") {
+                    attrs.with = Some(meta.value()?.parse::<syn::LitStr>()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("flatten") {
+                    attrs.flatten = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `default`, `flatten` or `with = \"...\"`"))
+                }
+            })?;
+        }
+        if attrs.flatten && (attrs.default || attrs.with.is_some()) {
+            return Err(syn::Error::new(
+                field.span(),
+                "#[from_row(flatten)] cannot be combined with `default` or `with`",
+            ));
+        }
+        Ok(attrs)
+    }
+}
+
+/// Derive macro for `TypedParams`, which makes a struct (and a reference to it) usable as `Params`.
+///
+/// The fields are bound to the `?` placeholders in declaration order. The generated
+/// `PARAM_NAMES` constant lists the bound fields in the same order, for building the column list.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(ToParams)]
+/// struct NewUser {
+///     id: i64,
+///     #[param(rename = "user_name")]
+///     name: String,
+///     #[param(skip)]
+///     cached: bool,
+/// }
+///
+/// assert_eq!(NewUser::PARAM_NAMES, &["id", "user_name"]);
+/// conn.exec_drop(&mut stmt, &NewUser { id: 1, name: "Alice".into(), cached: false })?;
+/// ```
+///
+/// # Attributes
+///
+/// - `#[param(skip)]`: do not bind the field
+/// - `#[param(rename = "name")]`: the name of the field in `PARAM_NAMES`
+#[proc_macro_derive(ToParams, attributes(param))]
+pub fn derive_to_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return syn::Error::new(
+                    input.ident.span(),
+                    "ToParams only supports structs with named fields",
+                )
+                .to_compile_error()
+                .into();
+            }
+        },
+        _ => {
+            return syn::Error::new(input.ident.span(), "ToParams only supports structs")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    // Bound fields: (ident, type, param name)
+    let mut bound = Vec::new();
+    for field in fields {
+        let mut skip = false;
+        let mut rename = None;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("param"))
+        {
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    rename = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip` or `rename = \"...\"`"))
+                }
+            });
+            if let Err(err) = parsed {
+                return err.to_compile_error().into();
+            }
+        }
+        if !skip {
+            let ident = field.ident.as_ref().unwrap();
+            let param_name = rename.unwrap_or_else(|| ident.to_string());
+            bound.push((ident, &field.ty, param_name));
+        }
+    }
+
+    let num_params = bound.len();
+    let param_names = bound.iter().map(|(_, _, param_name)| param_name);
+    let idents: Vec<_> = bound.iter().map(|(ident, _, _)| *ident).collect();
+    let types: Vec<_> = bound.iter().map(|(_, ty, _)| *ty).collect();
+    let indices: Vec<_> = (0..num_params).collect();
+    let param = quote! { ::zero_mysql::protocol::r#trait::param::TypedParam };
+
+    let expanded = quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The names of the bound fields, in placeholder order
+            pub const PARAM_NAMES: &'static [&'static str] = &[#(#param_names),*];
+        }
+
+        impl #impl_generics ::zero_mysql::protocol::r#trait::param::TypedParams for #name #ty_generics #where_clause {
+            fn len(&self) -> usize {
+                #num_params
+            }
+
+            fn encode_null_bitmap(&self, out: &mut ::std::vec::Vec<u8>) {
+                let start = out.len();
+                out.resize(start + #num_params.div_ceil(8), 0);
+                #(
+                    if #param::is_null(&self.#idents) {
+                        if let ::core::option::Option::Some(byte) = out.get_mut(start + (#indices >> 3)) {
+                            *byte |= 1 << (#indices & 7);
+                        }
+                    }
+                )*
+            }
+
+            fn encode_types(out: &mut ::std::vec::Vec<u8>) {
+                #(
+                    <#types as #param>::encode_type(out);
+                )*
+            }
+
+            fn encode_values(&self, out: &mut ::std::vec::Vec<u8>) -> ::zero_mysql::error::Result<()> {
+                #(
+                    if !#param::is_null(&self.#idents) {
+                        #param::encode_value(&self.#idents, out)?;
+                    }
+                )*
+                Ok(())
+            }
+
+            fn encode_values_for_bulk(&self, out: &mut ::std::vec::Vec<u8>) -> ::zero_mysql::error::Result<()> {
+                #(
+                    if #param::is_null(&self.#idents) {
+                        out.push(::zero_mysql::protocol::r#trait::param::ParamIndicator::Null as u8);
+                    } else {
+                        out.push(::zero_mysql::protocol::r#trait::param::ParamIndicator::None as u8);
+                        #param::encode_value(&self.#idents, out)?;
+                    }
+                )*
+                Ok(())
+            }
+
+            fn encoded_len(&self) -> usize {
+                0 #(+ #param::encoded_len(&self.#idents))*
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Derive macro for `FromRawValue` on fieldless enums.
+///
+/// String columns (`ENUM`, `VARCHAR`, ...) are matched against the variant names,
+/// and integer columns (`TINYINT`, ...) against the discriminants.
+/// Any other value returns `Error::BadUsageError`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(FromRawValueEnum)]
+/// enum Status {
+///     #[mysql(rename = "active")]
+///     Active = 1,
+///     #[mysql(rename = "banned")]
+///     Banned = 2,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct User {
+///     name: String,
+///     status: Status,
+/// }
+/// ```
+///
+/// # Attributes
+///
+/// - `#[mysql(rename = "name")]`: the string value of the variant
+#[proc_macro_derive(FromRawValueEnum, attributes(mysql))]
+pub fn derive_from_raw_value_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let name = &input.ident;
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => {
+            return syn::Error::new(input.ident.span(), "FromRawValueEnum only supports enums")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    // (ident, string value)
+    let mut mapped = Vec::new();
+    for variant in variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return syn::Error::new(
+                variant.span(),
+                "FromRawValueEnum only supports variants without fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+        let mut rename = None;
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("mysql"))
+        {
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    rename = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"...\"`"))
+                }
+            });
+            if let Err(err) = parsed {
+                return err.to_compile_error().into();
+            }
+        }
+        let value = rename.unwrap_or_else(|| variant.ident.to_string());
+        mapped.push((&variant.ident, value));
+    }
+
+    let idents: Vec<_> = mapped.iter().map(|(ident, _)| *ident).collect();
+    let values: Vec<_> = mapped
+        .iter()
+        .map(|(_, value)| syn::LitByteStr::new(value.as_bytes(), name.span()))
+        .collect();
+    let name_str = name.to_string();
+
+    let expanded = quote! {
+        const _: () = {
+            fn from_int(v: i128) -> ::zero_mysql::error::Result<#name> {
+                #(
+                    if v == #name::#idents as i128 {
+                        return Ok(#name::#idents);
+                    }
+                )*
+                Err(::zero_mysql::error::Error::BadUsageError(format!(
+                    "Cannot decode {} to {}", v, #name_str
+                )))
+            }
+
+            impl<'buf> ::zero_mysql::raw::FromRawValue<'buf> for #name {
+                fn from_i8(v: i8) -> ::zero_mysql::error::Result<Self> {
+                    from_int(v.into())
+                }
+                fn from_i16(v: i16) -> ::zero_mysql::error::Result<Self> {
+                    from_int(v.into())
+                }
+                fn from_i32(v: i32) -> ::zero_mysql::error::Result<Self> {
+                    from_int(v.into())
+                }
+                fn from_i64(v: i64) -> ::zero_mysql::error::Result<Self> {
+                    from_int(v.into())
+                }
+                fn from_u8(v: u8) -> ::zero_mysql::error::Result<Self> {
+                    from_int(v.into())
+                }
+                fn from_u16(v: u16) -> ::zero_mysql::error::Result<Self> {
+                    from_int(v.into())
+                }
+                fn from_u32(v: u32) -> ::zero_mysql::error::Result<Self> {
+                    from_int(v.into())
+                }
+                fn from_u64(v: u64) -> ::zero_mysql::error::Result<Self> {
+                    from_int(v.into())
+                }
+                fn from_str(v: &'buf [u8]) -> ::zero_mysql::error::Result<Self> {
+                    match v {
+                        #(#values => Ok(Self::#idents),)*
+                        _ => Err(::zero_mysql::error::Error::BadUsageError(format!(
+                            "Cannot decode {:?} to {}",
+                            String::from_utf8_lossy(v),
+                            #name_str
+                        ))),
+                    }
+                }
+                fn from_bytes(v: &'buf [u8]) -> ::zero_mysql::error::Result<Self> {
+                    <Self as ::zero_mysql::raw::FromRawValue<'buf>>::from_str(v)
+                }
+            }
+        };
+    };
+
+    TokenStream::from(expanded)
+}
+
 /// Derive macro for `RefFromRow` trait - zero-copy row decoding.
 ///
 /// This macro generates a zero-copy implementation that returns a reference